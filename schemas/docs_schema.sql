@@ -15,17 +15,25 @@ DEFINE FIELD description ON space TYPE option<string>;
 DEFINE FIELD avatar_url ON space TYPE option<string>;
 DEFINE FIELD is_public ON space TYPE bool DEFAULT false;
 DEFINE FIELD is_deleted ON space TYPE bool DEFAULT false;
+DEFINE FIELD is_archived ON space TYPE bool DEFAULT false; -- 归档后只读
 DEFINE FIELD owner_id ON space TYPE string ASSERT $value != NONE; -- Rainbow-Auth用户ID
-DEFINE FIELD settings ON space TYPE object DEFAULT {};
+-- settings 中嵌套的 navigation、upload、allowed_html_tags 等键需要 FLEXIBLE，否则 SCHEMAFULL 会丢弃
+-- 迁移：已有数据库执行该 DEFINE FIELD 覆盖旧定义，之前保存的空间设置需要重新保存
+DEFINE FIELD settings ON space FLEXIBLE TYPE object DEFAULT {};
 DEFINE FIELD theme_config ON space TYPE object DEFAULT {};
 DEFINE FIELD member_count ON space TYPE number DEFAULT 0;
 DEFINE FIELD document_count ON space TYPE number DEFAULT 0;
+DEFINE FIELD storage_used ON space TYPE number DEFAULT 0; -- 未删除文件的总字节数，上传和删除时增减
 DEFINE FIELD created_at ON space TYPE datetime DEFAULT time::now();
 DEFINE FIELD updated_at ON space TYPE datetime DEFAULT time::now();
 DEFINE FIELD created_by ON space TYPE option<string>;
 DEFINE FIELD updated_by ON space TYPE option<string>;
 
 -- 空间索引 - slug全局唯一（类似GitHub仓库名）
+-- 创建空间时依赖该索引保证唯一性，重复 slug 会被映射为 409 冲突
+-- 迁移：已有数据库需先清理重复 slug，再执行
+--   REMOVE INDEX space_slug_unique_idx ON space;
+--   DEFINE INDEX space_slug_unique_idx ON space COLUMNS slug UNIQUE;
 DEFINE INDEX space_slug_unique_idx ON space COLUMNS slug UNIQUE;
 -- 保留owner索引用于查询
 DEFINE INDEX space_owner_slug_idx ON space COLUMNS owner_id, slug;
@@ -84,6 +92,7 @@ DEFINE FIELD change_type ON document_version TYPE string DEFAULT "Updated" ASSER
 DEFINE FIELD author_id ON document_version TYPE string ASSERT $value != NONE;
 DEFINE FIELD parent_version_id ON document_version TYPE option<record(document_version)>;
 DEFINE FIELD is_current ON document_version TYPE bool DEFAULT false;
+DEFINE FIELD pinned ON document_version TYPE bool DEFAULT false;
 DEFINE FIELD word_count ON document_version TYPE number DEFAULT 0;
 DEFINE FIELD created_at ON document_version TYPE datetime DEFAULT time::now();
 
@@ -93,6 +102,18 @@ DEFINE INDEX version_number_idx ON document_version COLUMNS document_id, version
 DEFINE INDEX version_current_idx ON document_version COLUMNS document_id, is_current;
 DEFINE INDEX version_author_idx ON document_version COLUMNS author_id;
 
+-- 文档草稿表（自动保存，每个用户每篇文档一份，不影响已保存内容）
+DEFINE TABLE document_draft SCHEMAFULL;
+DEFINE FIELD id ON document_draft TYPE record(document_draft);
+DEFINE FIELD document_id ON document_draft TYPE string ASSERT $value != NONE;
+DEFINE FIELD user_id ON document_draft TYPE string ASSERT $value != NONE;
+DEFINE FIELD title ON document_draft TYPE option<string>;
+DEFINE FIELD content ON document_draft TYPE string DEFAULT "";
+DEFINE FIELD updated_at ON document_draft TYPE datetime DEFAULT time::now();
+
+-- 草稿索引
+DEFINE INDEX draft_document_user_idx ON document_draft COLUMNS document_id, user_id UNIQUE;
+
 -- 文档权限表 (扩展Rainbow-Auth的RBAC)
 DEFINE TABLE document_permission SCHEMAFULL;
 DEFINE FIELD id ON document_permission TYPE record(document_permission);
@@ -121,6 +142,8 @@ DEFINE FIELD author_id ON comment TYPE string ASSERT $value != NONE;
 DEFINE FIELD content ON comment TYPE string ASSERT $value != NONE AND string::len($value) > 0;
 DEFINE FIELD is_deleted ON comment TYPE bool DEFAULT false;
 DEFINE FIELD is_resolved ON comment TYPE bool DEFAULT false;
+DEFINE FIELD resolved_by ON comment TYPE option<string>;
+DEFINE FIELD resolved_at ON comment TYPE option<datetime>;
 DEFINE FIELD like_count ON comment TYPE number DEFAULT 0;
 DEFINE FIELD liked_by ON comment TYPE array<string> DEFAULT [];
 DEFINE FIELD edited_at ON comment TYPE datetime;
@@ -160,6 +183,20 @@ DEFINE INDEX tag_space_name_idx ON tag COLUMNS space_id, name UNIQUE;
 DEFINE INDEX tag_space_slug_idx ON tag COLUMNS space_id, slug UNIQUE;
 DEFINE INDEX tag_usage_idx ON tag COLUMNS usage_count;
 
+-- 文档模板表
+DEFINE TABLE document_template SCHEMAFULL;
+DEFINE FIELD id ON document_template TYPE record(document_template);
+DEFINE FIELD space_id ON document_template TYPE record(space) ASSERT $value != NONE;
+DEFINE FIELD name ON document_template TYPE string ASSERT $value != NONE AND string::len($value) > 0 AND string::len($value) <= 100;
+DEFINE FIELD description ON document_template TYPE option<string>;
+DEFINE FIELD title_pattern ON document_template TYPE string ASSERT $value != NONE AND string::len($value) > 0; -- 支持 {{date}}、{{author}}
+DEFINE FIELD content ON document_template TYPE string DEFAULT "";
+DEFINE FIELD created_by ON document_template TYPE string ASSERT $value != NONE;
+DEFINE FIELD created_at ON document_template TYPE datetime DEFAULT time::now();
+DEFINE FIELD updated_at ON document_template TYPE datetime DEFAULT time::now();
+
+DEFINE INDEX document_template_space_idx ON document_template COLUMNS space_id;
+
 -- 文档标签关联表
 DEFINE TABLE document_tag SCHEMAFULL;
 DEFINE FIELD id ON document_tag TYPE record(document_tag);
@@ -227,6 +264,17 @@ DEFINE INDEX view_document_idx ON document_view COLUMNS document_id;
 DEFINE INDEX view_user_idx ON document_view COLUMNS user_id;
 DEFINE INDEX view_date_idx ON document_view COLUMNS viewed_at;
 
+-- 用户最近浏览记录表（用于"最近浏览"列表，只保留滚动窗口内的记录）
+DEFINE TABLE document_access_log SCHEMAFULL;
+DEFINE FIELD id ON document_access_log TYPE record(document_access_log);
+DEFINE FIELD user_id ON document_access_log TYPE string ASSERT $value != NONE;
+DEFINE FIELD document_id ON document_access_log TYPE record(document) ASSERT $value != NONE;
+DEFINE FIELD accessed_at ON document_access_log TYPE datetime DEFAULT time::now();
+
+-- 最近浏览索引
+DEFINE INDEX access_log_user_idx ON document_access_log COLUMNS user_id, accessed_at;
+DEFINE INDEX access_log_date_idx ON document_access_log COLUMNS accessed_at;
+
 -- =====================================
 -- 系统日志和审计
 -- =====================================
@@ -236,11 +284,14 @@ DEFINE TABLE activity_log SCHEMAFULL;
 DEFINE FIELD id ON activity_log TYPE record(activity_log);
 DEFINE FIELD user_id ON activity_log TYPE string ASSERT $value != NONE;
 DEFINE FIELD action ON activity_log TYPE string ASSERT $value != NONE;
-DEFINE FIELD resource_type ON activity_log TYPE string ASSERT $value INSIDE ["space", "document", "comment", "tag", "version"];
+DEFINE FIELD resource_type ON activity_log TYPE string ASSERT $value INSIDE ["space", "document", "comment", "tag", "version", "member", "publication"];
 DEFINE FIELD resource_id ON activity_log TYPE string ASSERT $value != NONE;
-DEFINE FIELD ip_address ON activity_log TYPE string;
-DEFINE FIELD user_agent ON activity_log TYPE string;
-DEFINE FIELD details ON activity_log TYPE object DEFAULT {};
+DEFINE FIELD space_id ON activity_log TYPE option<string>; -- 所属空间（不带 space: 前缀），用于空间动态
+DEFINE FIELD ip_address ON activity_log TYPE option<string>;
+DEFINE FIELD user_agent ON activity_log TYPE option<string>;
+-- details 中的 actor_name、title 等键不固定，需要 FLEXIBLE，否则 SCHEMAFULL 会丢弃嵌套字段
+-- 迁移：已有数据库执行以上三条 DEFINE FIELD 覆盖旧定义
+DEFINE FIELD details ON activity_log FLEXIBLE TYPE object DEFAULT {};
 DEFINE FIELD created_at ON activity_log TYPE datetime DEFAULT time::now();
 
 -- 活动日志索引
@@ -248,6 +299,7 @@ DEFINE INDEX activity_user_idx ON activity_log COLUMNS user_id;
 DEFINE INDEX activity_resource_idx ON activity_log COLUMNS resource_type, resource_id;
 DEFINE INDEX activity_created_idx ON activity_log COLUMNS created_at;
 DEFINE INDEX activity_action_idx ON activity_log COLUMNS action;
+DEFINE INDEX activity_space_idx ON activity_log COLUMNS space_id, created_at;
 
 -- =====================================
 -- 文件和媒体管理
@@ -265,6 +317,7 @@ DEFINE FIELD mime_type ON file_upload TYPE string ASSERT $value != NONE;
 DEFINE FIELD uploaded_by ON file_upload TYPE string ASSERT $value != NONE;
 DEFINE FIELD space_id ON file_upload TYPE option<record(space)>;
 DEFINE FIELD document_id ON file_upload TYPE option<record(document)>;
+DEFINE FIELD thumbnail_path ON file_upload TYPE option<string>;
 DEFINE FIELD is_deleted ON file_upload TYPE bool DEFAULT false;
 DEFINE FIELD deleted_at ON file_upload TYPE datetime;
 DEFINE FIELD deleted_by ON file_upload TYPE string;
@@ -277,6 +330,24 @@ DEFINE INDEX file_document_idx ON file_upload COLUMNS document_id;
 DEFINE INDEX file_deleted_idx ON file_upload COLUMNS is_deleted;
 DEFINE INDEX file_type_idx ON file_upload COLUMNS file_type;
 
+-- 迁移：按已有文件统计空间的存储用量，只处理尚未统计的空间，可重复执行
+UPDATE space SET storage_used = math::sum((
+    SELECT VALUE file_size FROM file_upload WHERE space_id = $parent.id AND is_deleted = false
+)) WHERE storage_used = NONE;
+
+-- 文档附件关联表，文档删除后 pending_cleanup 置为 true
+DEFINE TABLE document_attachment SCHEMAFULL;
+DEFINE FIELD id ON document_attachment TYPE record(document_attachment);
+DEFINE FIELD document_id ON document_attachment TYPE record(document) ASSERT $value != NONE;
+DEFINE FIELD file_id ON document_attachment TYPE record(file_upload) ASSERT $value != NONE;
+DEFINE FIELD attached_by ON document_attachment TYPE string ASSERT $value != NONE;
+DEFINE FIELD pending_cleanup ON document_attachment TYPE bool DEFAULT false;
+DEFINE FIELD attached_at ON document_attachment TYPE datetime DEFAULT time::now();
+
+DEFINE INDEX document_attachment_unique_idx ON document_attachment COLUMNS document_id, file_id UNIQUE;
+DEFINE INDEX document_attachment_doc_idx ON document_attachment COLUMNS document_id;
+DEFINE INDEX document_attachment_file_idx ON document_attachment COLUMNS file_id;
+
 -- =====================================
 -- 通知系统
 -- =====================================
@@ -288,7 +359,8 @@ DEFINE FIELD user_id ON notification TYPE string ASSERT $value != NONE;
 DEFINE FIELD type ON notification TYPE string ASSERT $value INSIDE ["space_invitation", "document_shared", "comment_mention", "document_update", "system"];
 DEFINE FIELD title ON notification TYPE string ASSERT $value != NONE;
 DEFINE FIELD content ON notification TYPE string ASSERT $value != NONE;
-DEFINE FIELD data ON notification TYPE option<object>; -- 额外的数据，如邀请令牌、文档ID等
+DEFINE FIELD data ON notification FLEXIBLE TYPE option<object>; -- 按通知类型结构化的数据，如邀请令牌、文档ID等
+-- 以下四个字段只存在于旧版空间邀请通知，新记录写入 data
 DEFINE FIELD invite_token ON notification TYPE option<string>; -- 空间邀请令牌
 DEFINE FIELD space_name ON notification TYPE option<string>; -- 空间名称
 DEFINE FIELD role ON notification TYPE option<string>; -- 邀请角色
@@ -304,6 +376,31 @@ DEFINE INDEX notification_user_unread_idx ON notification COLUMNS user_id, is_re
 DEFINE INDEX notification_created_idx ON notification COLUMNS created_at;
 DEFINE INDEX notification_invite_token_idx ON notification COLUMNS invite_token;
 
+-- 迁移：把旧版空间邀请通知的独立字段写入 data，可重复执行
+UPDATE notification SET
+    data = {
+        space_name: space_name ?? "",
+        invite_token: invite_token,
+        role: role ?? "",
+        inviter_name: inviter_name ?? ""
+    },
+    invite_token = NONE,
+    space_name = NONE,
+    role = NONE,
+    inviter_name = NONE
+WHERE type = "space_invitation" AND data = NONE AND invite_token != NONE;
+
+-- 通知偏好表，记录ID与 user_id 相同，没有记录时全部类型默认开启
+DEFINE TABLE notification_preference SCHEMAFULL;
+DEFINE FIELD user_id ON notification_preference TYPE string ASSERT $value != NONE;
+DEFINE FIELD space_invitation ON notification_preference TYPE bool DEFAULT true;
+DEFINE FIELD comment_mention ON notification_preference TYPE bool DEFAULT true;
+DEFINE FIELD document_shared ON notification_preference TYPE bool DEFAULT true;
+DEFINE FIELD document_update ON notification_preference TYPE bool DEFAULT true;
+DEFINE FIELD updated_at ON notification_preference TYPE datetime DEFAULT time::now();
+
+DEFINE INDEX notification_preference_user_idx ON notification_preference COLUMNS user_id UNIQUE;
+
 
 
 
@@ -335,6 +432,15 @@ DEFINE FIELD seo_title ON TABLE space_publication TYPE option<string>;
 DEFINE FIELD seo_description ON TABLE space_publication TYPE option<string>;
 DEFINE FIELD seo_keywords ON TABLE space_publication TYPE array<string> DEFAULT [];
 
+-- 自定义域名
+DEFINE FIELD custom_domain ON TABLE space_publication TYPE option<string>;
+
+-- 访问密码（argon2 哈希，为空表示不需要密码）
+DEFINE FIELD access_password_hash ON TABLE space_publication TYPE option<string>;
+
+-- 过期时间（过期后不再公开访问，定时任务将其标记为未发布）
+DEFINE FIELD expires_at ON TABLE space_publication TYPE option<datetime>;
+
 -- 状态和时间戳
 DEFINE FIELD is_active ON TABLE space_publication TYPE bool DEFAULT true;
 DEFINE FIELD is_deleted ON TABLE space_publication TYPE bool DEFAULT false;
@@ -344,9 +450,19 @@ DEFINE FIELD updated_at ON TABLE space_publication TYPE datetime DEFAULT time::n
 DEFINE FIELD deleted_at ON TABLE space_publication TYPE option<datetime>;
 
 -- 索引
+-- 创建发布时依赖该唯一索引保证 slug 唯一，重复 slug 会被映射为 409 冲突
+-- 迁移：已有数据库需先清理重复 slug，再执行
+--   REMOVE INDEX idx_space_publication_slug ON TABLE space_publication;
+--   DEFINE INDEX idx_space_publication_slug ON TABLE space_publication COLUMNS slug UNIQUE;
 DEFINE INDEX idx_space_publication_slug ON TABLE space_publication COLUMNS slug UNIQUE;
 DEFINE INDEX idx_space_publication_space_id ON TABLE space_publication COLUMNS space_id;
 DEFINE INDEX idx_space_publication_active ON TABLE space_publication COLUMNS is_active;
+-- 自定义域名同样由唯一索引保证唯一（未设置域名的记录不参与唯一性校验），删除发布时会清空其域名
+-- 迁移：已有数据库需先清理重复域名，再执行
+--   REMOVE INDEX idx_space_publication_custom_domain ON TABLE space_publication;
+--   DEFINE INDEX idx_space_publication_custom_domain ON TABLE space_publication COLUMNS custom_domain UNIQUE;
+DEFINE INDEX idx_space_publication_custom_domain ON TABLE space_publication COLUMNS custom_domain UNIQUE;
+DEFINE INDEX idx_space_publication_expires_at ON TABLE space_publication COLUMNS expires_at;
 
 -- 发布的文档快照表
 -- 存储发布时文档的完整快照
@@ -361,6 +477,7 @@ DEFINE FIELD title ON TABLE publication_document TYPE string ASSERT $value != NO
 DEFINE FIELD slug ON TABLE publication_document TYPE string ASSERT $value != NONE;
 DEFINE FIELD content ON TABLE publication_document TYPE string ASSERT $value != NONE;
 DEFINE FIELD excerpt ON TABLE publication_document TYPE option<string>;
+DEFINE FIELD content_hash ON TABLE publication_document TYPE string DEFAULT '';
 
 -- 文档结构
 DEFINE FIELD parent_id ON TABLE publication_document TYPE option<string>;
@@ -369,6 +486,7 @@ DEFINE FIELD order_index ON TABLE publication_document TYPE number DEFAULT 0;
 -- 文档元数据
 DEFINE FIELD word_count ON TABLE publication_document TYPE number DEFAULT 0;
 DEFINE FIELD reading_time ON TABLE publication_document TYPE number DEFAULT 0;
+DEFINE FIELD is_public ON TABLE publication_document TYPE bool DEFAULT false;
 
 -- 时间戳
 DEFINE FIELD created_at ON TABLE publication_document TYPE datetime DEFAULT time::now();
@@ -394,15 +512,50 @@ DEFINE FIELD views_today ON TABLE publication_analytics TYPE number DEFAULT 0;
 DEFINE FIELD views_week ON TABLE publication_analytics TYPE number DEFAULT 0;
 DEFINE FIELD views_month ON TABLE publication_analytics TYPE number DEFAULT 0;
 
--- 最热门文档
+-- 最热门文档（已改为从 publication_document_view 统计，保留字段兼容旧数据）
 DEFINE FIELD popular_documents ON TABLE publication_analytics TYPE array<object> DEFAULT [];
 
+-- 最近一次访问时间（用于日/周/月计数翻转）
+DEFINE FIELD last_view_at ON TABLE publication_analytics TYPE option<datetime>;
+
 -- 更新时间
 DEFINE FIELD updated_at ON TABLE publication_analytics TYPE datetime DEFAULT time::now();
 
 -- 索引
 DEFINE INDEX idx_publication_analytics_publication_id ON TABLE publication_analytics COLUMNS publication_id UNIQUE;
 
+-- 发布中每篇文档的浏览量，记录ID为 [publication_id, doc_id]，浏览时原子递增
+DEFINE TABLE publication_document_view SCHEMAFULL;
+
+DEFINE FIELD id ON TABLE publication_document_view TYPE record(publication_document_view);
+DEFINE FIELD publication_id ON TABLE publication_document_view TYPE string ASSERT $value != NONE;
+DEFINE FIELD doc_id ON TABLE publication_document_view TYPE string ASSERT $value != NONE;
+DEFINE FIELD title ON TABLE publication_document_view TYPE string DEFAULT "";
+DEFINE FIELD views ON TABLE publication_document_view TYPE number DEFAULT 0;
+
+DEFINE INDEX idx_publication_document_view_views ON TABLE publication_document_view COLUMNS publication_id, views;
+
+-- 发布访客表（用于独立访客去重，只保存访客哈希）
+DEFINE TABLE publication_visitor SCHEMAFULL;
+
+DEFINE FIELD id ON TABLE publication_visitor TYPE record(publication_visitor);
+DEFINE FIELD publication_id ON TABLE publication_visitor TYPE string ASSERT $value != NONE;
+DEFINE FIELD visitor_hash ON TABLE publication_visitor TYPE string ASSERT $value != NONE;
+DEFINE FIELD counted_at ON TABLE publication_visitor TYPE datetime DEFAULT time::now();
+DEFINE FIELD last_seen_at ON TABLE publication_visitor TYPE datetime DEFAULT time::now();
+
+DEFINE INDEX idx_publication_visitor_unique ON TABLE publication_visitor COLUMNS publication_id, visitor_hash UNIQUE;
+
+-- 发布按天的浏览量（用于趋势统计），记录ID为 [publication_id, 当天零点]
+DEFINE TABLE publication_daily_view SCHEMAFULL;
+
+DEFINE FIELD id ON TABLE publication_daily_view TYPE record(publication_daily_view);
+DEFINE FIELD publication_id ON TABLE publication_daily_view TYPE string ASSERT $value != NONE;
+DEFINE FIELD day ON TABLE publication_daily_view TYPE datetime ASSERT $value != NONE;
+DEFINE FIELD views ON TABLE publication_daily_view TYPE number DEFAULT 0;
+
+DEFINE INDEX idx_publication_daily_view_day ON TABLE publication_daily_view COLUMNS publication_id, day;
+
 -- 自定义域名表
 DEFINE TABLE publication_domain SCHEMAFULL;
 
@@ -446,6 +599,30 @@ DEFINE FIELD published_at ON TABLE publication_history TYPE datetime DEFAULT tim
 DEFINE INDEX idx_publication_history_publication_id ON TABLE publication_history COLUMNS publication_id;
 DEFINE INDEX idx_publication_history_version ON TABLE publication_history COLUMNS publication_id, version UNIQUE;
 
+-- 单文档分享表（分享时的文档快照，通过独立 slug 公开访问）
+DEFINE TABLE document_share SCHEMAFULL;
+
+DEFINE FIELD id ON TABLE document_share TYPE record(document_share);
+DEFINE FIELD document_id ON TABLE document_share TYPE string ASSERT $value != NONE;
+DEFINE FIELD space_id ON TABLE document_share TYPE string ASSERT $value != NONE;
+DEFINE FIELD slug ON TABLE document_share TYPE string ASSERT $value != NONE;
+DEFINE FIELD theme ON TABLE document_share TYPE string DEFAULT 'default';
+
+-- 文档内容快照
+DEFINE FIELD title ON TABLE document_share TYPE string ASSERT $value != NONE;
+DEFINE FIELD content ON TABLE document_share TYPE string;
+DEFINE FIELD excerpt ON TABLE document_share TYPE option<string>;
+DEFINE FIELD word_count ON TABLE document_share TYPE number DEFAULT 0;
+DEFINE FIELD reading_time ON TABLE document_share TYPE number DEFAULT 0;
+
+DEFINE FIELD shared_by ON TABLE document_share TYPE string ASSERT $value != NONE;
+DEFINE FIELD expires_at ON TABLE document_share TYPE option<datetime>;
+DEFINE FIELD created_at ON TABLE document_share TYPE datetime DEFAULT time::now();
+
+-- 索引
+DEFINE INDEX idx_document_share_slug ON TABLE document_share COLUMNS slug UNIQUE;
+DEFINE INDEX idx_document_share_document_id ON TABLE document_share COLUMNS document_id;
+
 
 
 -- 空间成员表扩展
@@ -484,7 +661,7 @@ DEFINE FIELD role ON space_invitation TYPE string DEFAULT "member" ASSERT $value
 DEFINE FIELD permissions ON space_invitation TYPE array<string> DEFAULT ["docs.read"];
 DEFINE FIELD invited_by ON space_invitation TYPE string ASSERT $value != NONE;
 DEFINE FIELD message ON space_invitation TYPE option<string>; -- 邀请消息
-DEFINE FIELD max_uses ON space_invitation TYPE number DEFAULT 1; -- 最大使用次数
+DEFINE FIELD max_uses ON space_invitation TYPE number DEFAULT 1; -- 最大使用次数，0 表示过期前不限次数
 DEFINE FIELD used_count ON space_invitation TYPE number DEFAULT 0; -- 已使用次数
 DEFINE FIELD expires_at ON space_invitation TYPE datetime ASSERT $value != NONE;
 DEFINE FIELD created_at ON space_invitation TYPE datetime DEFAULT time::now();
@@ -496,6 +673,51 @@ DEFINE INDEX space_invitation_space_idx ON space_invitation COLUMNS space_id;
 DEFINE INDEX space_invitation_inviter_idx ON space_invitation COLUMNS invited_by;
 DEFINE INDEX space_invitation_email_idx ON space_invitation COLUMNS email;
 
+-- Webhook 表：空间内的事件推送订阅
+DEFINE TABLE webhook SCHEMAFULL;
+DEFINE FIELD id ON webhook TYPE record(webhook);
+DEFINE FIELD space_id ON webhook TYPE record(space) ASSERT $value != NONE;
+DEFINE FIELD url ON webhook TYPE string ASSERT $value != NONE;
+DEFINE FIELD secret ON webhook TYPE string ASSERT $value != NONE; -- 请求体 HMAC-SHA256 签名密钥
+DEFINE FIELD events ON webhook TYPE array<string> ASSERT array::len($value) > 0; -- 订阅的事件，如 document.updated
+DEFINE FIELD is_active ON webhook TYPE bool DEFAULT true;
+DEFINE FIELD created_by ON webhook TYPE string ASSERT $value != NONE;
+DEFINE FIELD created_at ON webhook TYPE datetime DEFAULT time::now();
+DEFINE FIELD updated_at ON webhook TYPE datetime DEFAULT time::now();
+
+DEFINE INDEX webhook_space_idx ON webhook COLUMNS space_id;
+
+-- Webhook 投递记录表
+DEFINE TABLE webhook_delivery SCHEMAFULL;
+DEFINE FIELD id ON webhook_delivery TYPE record(webhook_delivery);
+DEFINE FIELD webhook_id ON webhook_delivery TYPE record(webhook) ASSERT $value != NONE;
+DEFINE FIELD event ON webhook_delivery TYPE string ASSERT $value != NONE;
+DEFINE FIELD payload ON webhook_delivery FLEXIBLE TYPE object; -- 实际发送的请求体
+DEFINE FIELD status ON webhook_delivery TYPE string DEFAULT "pending" ASSERT $value INSIDE ["pending", "succeeded", "failed"];
+DEFINE FIELD attempts ON webhook_delivery TYPE number DEFAULT 0;
+DEFINE FIELD response_status ON webhook_delivery TYPE option<number>; -- 最后一次请求的响应状态码
+DEFINE FIELD error ON webhook_delivery TYPE option<string>; -- 最后一次失败的原因
+DEFINE FIELD created_at ON webhook_delivery TYPE datetime DEFAULT time::now();
+DEFINE FIELD delivered_at ON webhook_delivery TYPE option<datetime>;
+
+DEFINE INDEX webhook_delivery_webhook_idx ON webhook_delivery COLUMNS webhook_id, created_at;
+
+-- API 密钥表：服务间调用使用的长期凭据，限定在单个空间
+DEFINE TABLE api_key SCHEMAFULL;
+DEFINE FIELD id ON api_key TYPE record(api_key);
+DEFINE FIELD space_id ON api_key TYPE record(space) ASSERT $value != NONE;
+DEFINE FIELD name ON api_key TYPE string ASSERT $value != NONE;
+DEFINE FIELD key_prefix ON api_key TYPE string ASSERT $value != NONE; -- 密钥开头的可见部分，用于查找
+DEFINE FIELD key_hash ON api_key TYPE string ASSERT $value != NONE; -- argon2 哈希，不保存明文
+DEFINE FIELD permissions ON api_key TYPE array<string> DEFAULT ["docs.read"];
+DEFINE FIELD created_by ON api_key TYPE string ASSERT $value != NONE;
+DEFINE FIELD last_used_at ON api_key TYPE option<datetime>;
+DEFINE FIELD revoked_at ON api_key TYPE option<datetime>; -- 吊销时间，吊销后不能再用于认证
+DEFINE FIELD created_at ON api_key TYPE datetime DEFAULT time::now();
+
+DEFINE INDEX api_key_prefix_idx ON api_key COLUMNS key_prefix UNIQUE;
+DEFINE INDEX api_key_space_idx ON api_key COLUMNS space_id;
+
 -- =====================================
 -- 初始数据插入
 -- =====================================
@@ -7,6 +7,8 @@ pub struct Config {
     pub auth: AuthConfig,
     pub server: ServerConfig,
     pub features: FeatureConfig,
+    pub markdown: MarkdownConfig,
+    pub files: FileConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +20,8 @@ pub struct DatabaseConfig {
     pub database: String,
     pub connection_timeout: u64,
     pub max_connections: u32,
+    pub autostart: bool,                 // 连接失败时是否在本机自动启动 SurrealDB
+    pub autostart_timeout: u64,          // 自动启动后等待数据库可用的最长秒数
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +30,8 @@ pub struct AuthConfig {
     pub jwt_expiration: u64,
     pub rainbow_auth_url: Option<String>,  // Rainbow-Auth服务地址
     pub integration_mode: bool,            // 是否集成Rainbow-Auth
+    pub internal_api_key: Option<String>,  // 调用Rainbow-Auth内部接口的API密钥
+    pub user_resolve_url: Option<String>,  // 用户名解析接口地址，Rainbow-Auth 未提供时不解析 @提及
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,14 +39,50 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub app_url: String,
+    pub cors_allowed_origins: Vec<String>,  // 允许跨域访问的来源，为空时不允许跨域
+    pub dev_mode: bool,                     // 开发模式下未配置来源时允许任意来源
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FeatureConfig {
     pub enable_pdf_export: bool,
     pub enable_notifications: bool,
+    pub enable_invitation_emails: bool,  // 是否通过Rainbow-Auth发送邀请邮件
     pub enable_comments: bool,
     pub enable_versioning: bool,
+    pub max_versions_per_document: u32,  // 0 表示不限制
+    pub max_comment_depth: u32,          // 评论回复的最大嵌套层数
+    pub max_invitations_per_hour: u32,   // 每个邀请者在单个空间每小时最多发出的邀请数，0 表示不限制
+    pub publication_cache_ttl_secs: u64, // 公开发布文档树的缓存时间（秒），0 表示不缓存
+    pub max_document_title_length: u32,  // 文档标题最大字符数，不超过 200
+    pub max_document_content_bytes: u64, // 文档正文最大字节数（UTF-8）
+    pub hybrid_search_vector_weight: f64, // 混合搜索中向量排名的权重（0~1），其余为关键词排名
+    pub webhook_allow_private_targets: bool, // 是否允许 webhook 推送到回环、内网等非公网地址
+}
+
+/// 上传文件相关配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileConfig {
+    pub max_file_size: u64,              // 单个文件的最大字节数
+    pub allowed_mime_types: Vec<String>, // 允许上传的 MIME 类型，为空时使用内置列表
+    pub space_storage_quota: u64,        // 每个空间的总存储配额（字节），0 表示不限制
+    pub thumbnail_max_dimension: u32,    // 图片缩略图的最大边长（像素）
+    pub orphan_file_retention_hours: u32, // 未被引用的文件保留多久后清理，0 表示不清理
+}
+
+/// Markdown 渲染扩展开关，默认与 GitHub 风格一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkdownConfig {
+    pub enable_tables: bool,
+    pub enable_footnotes: bool,
+    pub enable_strikethrough: bool,
+    pub enable_tasklists: bool,
+    pub enable_smart_punctuation: bool,
+    pub highlight_theme: String,         // 代码高亮主题，取值为 syntect 内置主题名
+    pub excerpt_length: usize,           // 摘要最大字符数
+    pub excerpt_strategy: String,        // first_paragraph、first_chars 或 skip_headings
+    pub words_per_minute: u32,           // 以空格分词文字的阅读速度
+    pub cjk_chars_per_minute: u32,       // 中日韩文字的阅读速度
 }
 
 impl Config {
@@ -59,6 +101,14 @@ impl Config {
                 .unwrap_or_else(|_| "10".to_string())
                 .parse()
                 .unwrap_or(10),
+            autostart: env::var("DATABASE_AUTOSTART")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            autostart_timeout: env::var("DATABASE_AUTOSTART_TIMEOUT")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
         };
 
         let auth = AuthConfig {
@@ -73,6 +123,8 @@ impl Config {
                 .unwrap_or_else(|_| "false".to_string())
                 .parse()
                 .unwrap_or(false),
+            internal_api_key: env::var("RAINBOW_AUTH_INTERNAL_API_KEY").ok().filter(|key| !key.is_empty()),
+            user_resolve_url: env::var("RAINBOW_AUTH_USER_RESOLVE_URL").ok().filter(|url| !url.is_empty()),
         };
 
         let server = ServerConfig {
@@ -82,6 +134,16 @@ impl Config {
                 .parse()
                 .unwrap_or(3000),
             app_url: env::var("APP_URL").unwrap_or_else(|_| "http://localhost:3000".to_string()),
+            cors_allowed_origins: env::var("CORS_ALLOWED_ORIGINS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect(),
+            dev_mode: env::var("DEV_MODE")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
         };
 
         let features = FeatureConfig {
@@ -93,6 +155,10 @@ impl Config {
                 .unwrap_or_else(|_| "true".to_string())
                 .parse()
                 .unwrap_or(true),
+            enable_invitation_emails: env::var("ENABLE_INVITATION_EMAILS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
             enable_comments: env::var("ENABLE_COMMENTS")
                 .unwrap_or_else(|_| "true".to_string())
                 .parse()
@@ -101,6 +167,106 @@ impl Config {
                 .unwrap_or_else(|_| "true".to_string())
                 .parse()
                 .unwrap_or(true),
+            max_versions_per_document: env::var("MAX_VERSIONS_PER_DOCUMENT")
+                .unwrap_or_else(|_| "100".to_string())
+                .parse()
+                .unwrap_or(100),
+            max_comment_depth: env::var("MAX_COMMENT_DEPTH")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            max_invitations_per_hour: env::var("MAX_INVITATIONS_PER_HOUR")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .unwrap_or(20),
+            publication_cache_ttl_secs: env::var("PUBLICATION_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            max_document_title_length: env::var("MAX_DOCUMENT_TITLE_LENGTH")
+                .unwrap_or_else(|_| "200".to_string())
+                .parse()
+                .unwrap_or(200),
+            max_document_content_bytes: env::var("MAX_DOCUMENT_CONTENT_BYTES")
+                .unwrap_or_else(|_| "1048576".to_string())
+                .parse()
+                .unwrap_or(1048576),
+            hybrid_search_vector_weight: env::var("HYBRID_SEARCH_VECTOR_WEIGHT")
+                .unwrap_or_else(|_| "0.5".to_string())
+                .parse()
+                .ok()
+                .filter(|weight: &f64| (0.0..=1.0).contains(weight))
+                .unwrap_or(0.5),
+            webhook_allow_private_targets: env::var("WEBHOOK_ALLOW_PRIVATE_TARGETS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+        };
+
+        let markdown = MarkdownConfig {
+            enable_tables: env::var("MARKDOWN_ENABLE_TABLES")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            enable_footnotes: env::var("MARKDOWN_ENABLE_FOOTNOTES")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            enable_strikethrough: env::var("MARKDOWN_ENABLE_STRIKETHROUGH")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            enable_tasklists: env::var("MARKDOWN_ENABLE_TASKLISTS")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            enable_smart_punctuation: env::var("MARKDOWN_ENABLE_SMART_PUNCTUATION")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            highlight_theme: env::var("MARKDOWN_HIGHLIGHT_THEME")
+                .unwrap_or_else(|_| "base16-ocean.dark".to_string()),
+            excerpt_length: env::var("MARKDOWN_EXCERPT_LENGTH")
+                .unwrap_or_else(|_| "200".to_string())
+                .parse()
+                .unwrap_or(200),
+            excerpt_strategy: env::var("MARKDOWN_EXCERPT_STRATEGY")
+                .unwrap_or_else(|_| "skip_headings".to_string()),
+            words_per_minute: env::var("READING_WORDS_PER_MINUTE")
+                .unwrap_or_else(|_| "200".to_string())
+                .parse()
+                .unwrap_or(200),
+            cjk_chars_per_minute: env::var("READING_CJK_CHARS_PER_MINUTE")
+                .unwrap_or_else(|_| "400".to_string())
+                .parse()
+                .unwrap_or(400),
+        };
+
+        let files = FileConfig {
+            max_file_size: env::var("MAX_FILE_SIZE")
+                .unwrap_or_else(|_| "10485760".to_string())
+                .parse()
+                .unwrap_or(10485760),
+            allowed_mime_types: env::var("ALLOWED_UPLOAD_MIME_TYPES")
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            space_storage_quota: env::var("SPACE_STORAGE_QUOTA")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            thumbnail_max_dimension: env::var("THUMBNAIL_MAX_DIMENSION")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .ok()
+                .filter(|&dimension: &u32| dimension > 0)
+                .unwrap_or(300),
+            orphan_file_retention_hours: env::var("ORPHAN_FILE_RETENTION_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .unwrap_or(24),
         };
 
         Ok(Config {
@@ -108,6 +274,8 @@ impl Config {
             auth,
             server,
             features,
+            markdown,
+            files,
         })
     }
 }
\ No newline at end of file
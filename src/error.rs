@@ -3,13 +3,16 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use serde::Serialize;
 use serde_json::json;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum AppError {
+    /// 数据库错误。查询失败和业务代码中构造的数据库错误统一使用这个变体，
+    /// 对外都返回 DATABASE_ERROR
     #[error("Database error: {0}")]
-    Database(#[from] surrealdb::Error),
+    Database(String),
     
     #[error("Authentication error: {0}")]
     Authentication(String),
@@ -49,33 +52,94 @@ pub enum AppError {
 
     #[error("External service error: {0}")]
     External(String),
+
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
+    /// 写入基于过期的数据（乐观并发冲突），附带当前数据供客户端合并
+    #[error("Conflict: {message}")]
+    StaleWrite {
+        message: String,
+        current: serde_json::Value,
+    },
 }
 
-impl AppError {
-    pub fn database_error(msg: impl Into<String>) -> Self {
-        Self::Internal(anyhow::anyhow!(msg.into()))
-    }
+/// 错误响应中稳定的错误码，客户端应根据错误码而不是 message 判断错误类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    DatabaseError,
+    Unauthenticated,
+    InvalidToken,
+    Forbidden,
+    ValidationFailed,
+    InvalidJson,
+    NotFound,
+    Conflict,
+    StaleWrite,
+    InternalError,
+    ConfigurationError,
+    ExternalServiceError,
+    TooManyRequests,
 }
 
-// Type alias for backward compatibility (defined below)
+/// 错误响应携带的原始信息，本地化中间件据此按 Accept-Language 重写响应体
+#[derive(Debug, Clone)]
+pub struct ErrorDetails {
+    pub code: ErrorCode,
+    pub message: String,
+    pub current: Option<serde_json::Value>,
+}
 
-// Convenience constructors
-impl AppError {
-    pub fn BadRequest(msg: String) -> Self {
-        Self::Validation(msg)
+impl ErrorDetails {
+    /// 错误响应体。error 与 message 相同，保留给旧版客户端
+    pub fn body(&self, message: &str) -> serde_json::Value {
+        let mut body = json!({
+            "code": self.code,
+            "message": message,
+            "error": message,
+        });
+        if let Some(current) = &self.current {
+            body["current"] = current.clone();
+        }
+        body
     }
+}
 
-    pub fn DatabaseError(msg: String) -> Self {
-        Self::database_error(msg)
+impl AppError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Database(_) => ErrorCode::DatabaseError,
+            AppError::Authentication(_) => ErrorCode::Unauthenticated,
+            AppError::Jwt(_) => ErrorCode::InvalidToken,
+            AppError::Authorization(_) => ErrorCode::Forbidden,
+            AppError::Validation(_) | AppError::ValidationErrors(_) => ErrorCode::ValidationFailed,
+            AppError::Json(_) => ErrorCode::InvalidJson,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::StaleWrite { .. } => ErrorCode::StaleWrite,
+            AppError::Internal(_) | AppError::Io(_) => ErrorCode::InternalError,
+            AppError::Configuration(_) => ErrorCode::ConfigurationError,
+            AppError::Http(_) | AppError::External(_) => ErrorCode::ExternalServiceError,
+            AppError::TooManyRequests(_) => ErrorCode::TooManyRequests,
+        }
     }
+}
 
-    pub fn InternalServerError(msg: String) -> Self {
-        Self::Internal(anyhow::anyhow!(msg))
+impl From<surrealdb::Error> for AppError {
+    fn from(error: surrealdb::Error) -> Self {
+        Self::Database(error.to_string())
     }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let code = self.code();
+        let current = match &self {
+            AppError::StaleWrite { current, .. } => Some(current.clone()),
+            _ => None,
+        };
+
         let (status, error_message) = match self {
             AppError::Database(ref e) => {
                 tracing::error!("Database error: {}", e);
@@ -91,7 +155,7 @@ impl IntoResponse for AppError {
             }
             AppError::Validation(ref msg) => {
                 tracing::warn!("Validation error: {}", msg);
-                (StatusCode::BAD_REQUEST, msg.as_str())
+                (StatusCode::UNPROCESSABLE_ENTITY, msg.as_str())
             }
             AppError::NotFound(ref msg) => {
                 tracing::info!("Not found: {}", msg);
@@ -123,7 +187,7 @@ impl IntoResponse for AppError {
             }
             AppError::ValidationErrors(ref e) => {
                 tracing::warn!("Validation errors: {}", e);
-                (StatusCode::BAD_REQUEST, "Validation failed")
+                (StatusCode::UNPROCESSABLE_ENTITY, "Validation failed")
             }
             AppError::Configuration(ref msg) => {
                 tracing::error!("Configuration error: {}", msg);
@@ -133,13 +197,24 @@ impl IntoResponse for AppError {
                 tracing::error!("External service error: {}", msg);
                 (StatusCode::BAD_GATEWAY, msg.as_str())
             }
+            AppError::TooManyRequests(ref msg) => {
+                tracing::warn!("Too many requests: {}", msg);
+                (StatusCode::TOO_MANY_REQUESTS, msg.as_str())
+            }
+            AppError::StaleWrite { ref message, .. } => {
+                tracing::warn!("Conflict: {}", message);
+                (StatusCode::CONFLICT, message.as_str())
+            }
         };
 
-        let body = Json(json!({
-            "error": error_message,
-        }));
-
-        (status, body).into_response()
+        let details = ErrorDetails {
+            code,
+            message: error_message.to_string(),
+            current,
+        };
+        let mut response = (status, Json(details.body(error_message))).into_response();
+        response.extensions_mut().insert(details);
+        response
     }
 }
 
@@ -163,11 +238,127 @@ impl AppError {
     pub fn conflict(msg: impl Into<String>) -> Self {
         Self::Conflict(msg.into())
     }
+
+    /// 将违反唯一索引的数据库错误转换为冲突错误，其他错误保持为数据库错误
+    pub fn from_unique_violation(error: surrealdb::Error, index: &str, conflict_msg: impl Into<String>) -> Self {
+        if is_unique_violation(&error.to_string(), index) {
+            Self::Conflict(conflict_msg.into())
+        } else {
+            Self::from(error)
+        }
+    }
+
+    pub fn database_error(msg: impl Into<String>) -> Self {
+        Self::Database(msg.into())
+    }
     
     pub fn internal_server_error(msg: impl Into<String>) -> Self {
         Self::Internal(anyhow::anyhow!(msg.into()))
     }
 }
 
+/// 判断数据库错误信息是否为指定唯一索引的冲突
+///
+/// SurrealDB 的错误信息形如：Database index `idx` already contains 'value', with record `table:id`
+pub fn is_unique_violation(message: &str, index: &str) -> bool {
+    message.contains(&format!("index `{}` already contains", index))
+}
+
 pub type Result<T> = std::result::Result<T, AppError>;
-pub type ApiError = AppError;
\ No newline at end of file
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::HttpBody;
+
+    async fn response_json(error: AppError) -> (StatusCode, serde_json::Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_not_found_from_space_and_document_serialize_identically() {
+        let (space_status, space) = response_json(AppError::NotFound("Space not found".to_string())).await;
+        let (document_status, document) = response_json(AppError::not_found("Document not found")).await;
+
+        assert_eq!(space_status, StatusCode::NOT_FOUND);
+        assert_eq!(space_status, document_status);
+        assert_eq!(space["code"], document["code"]);
+        let keys = |body: &serde_json::Value| body.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys(&space), keys(&document));
+        assert_eq!(document["message"], "Document not found");
+
+        // 业务代码构造的数据库错误与查询失败一样返回 DATABASE_ERROR，不暴露细节
+        let (status, body) = response_json(AppError::database_error("Failed to parse create result: bad field")).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], "DATABASE_ERROR");
+        assert_eq!(body["message"], "Database error occurred");
+        assert_eq!(keys(&body), keys(&space));
+    }
+
+    #[derive(Debug, serde::Deserialize, validator::Validate)]
+    struct RenameRequest {
+        #[validate(length(min = 1))]
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn test_each_variant_maps_to_status_and_code() {
+        use axum::{body::Body, http::Request, routing::post, Router};
+        use tower::ServiceExt;
+        use validator::Validate;
+
+        // 与路由处理函数一样通过 ? 把校验错误转换为 AppError
+        let app = Router::new().route("/rename", post(|Json(request): Json<RenameRequest>| async move {
+            request.validate()?;
+            Ok::<_, AppError>(Json(request.name))
+        }));
+        let request = Request::post("/rename")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"name": ""}"#))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.extensions().get::<ErrorDetails>().unwrap().code, ErrorCode::ValidationFailed);
+
+        let cases = vec![
+            (AppError::Conflict("Tag name already exists in this space".to_string()), StatusCode::CONFLICT, "CONFLICT"),
+            (AppError::Validation("expires_at must be in the future".to_string()), StatusCode::UNPROCESSABLE_ENTITY, "VALIDATION_FAILED"),
+            (AppError::Authorization("Access denied to this space".to_string()), StatusCode::FORBIDDEN, "FORBIDDEN"),
+            (AppError::Authentication("Invalid API key".to_string()), StatusCode::UNAUTHORIZED, "UNAUTHENTICATED"),
+            (AppError::NotFound("Document not found".to_string()), StatusCode::NOT_FOUND, "NOT_FOUND"),
+            (AppError::TooManyRequests("Too many invitations".to_string()), StatusCode::TOO_MANY_REQUESTS, "TOO_MANY_REQUESTS"),
+            (AppError::database_error("connection reset"), StatusCode::INTERNAL_SERVER_ERROR, "DATABASE_ERROR"),
+            (AppError::internal_server_error("Publication ID is missing"), StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
+            (
+                AppError::StaleWrite { message: "Document was modified".to_string(), current: json!({ "version": 3 }) },
+                StatusCode::CONFLICT,
+                "STALE_WRITE",
+            ),
+        ];
+
+        for (error, expected_status, expected_code) in cases {
+            let (status, body) = response_json(error).await;
+            assert_eq!(status, expected_status, "{}", expected_code);
+            assert_eq!(body["code"], expected_code);
+            assert!(body["message"].as_str().is_some_and(|message| !message.is_empty()));
+            assert_eq!(body["error"], body["message"]);
+            assert_eq!(body.get("current").is_some(), expected_code == "STALE_WRITE");
+        }
+    }
+
+    #[test]
+    fn test_is_unique_violation() {
+        let message = "There was a problem with the database: Database index `space_slug_unique_idx` \
+            already contains 'docs', with record `space:abc123`";
+        assert!(is_unique_violation(message, "space_slug_unique_idx"));
+        assert!(!is_unique_violation(message, "idx_space_publication_slug"));
+        assert!(!is_unique_violation("Database record `space:abc` already exists", "space_slug_unique_idx"));
+    }
+}
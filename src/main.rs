@@ -1,11 +1,12 @@
 use std::sync::Arc;
 use axum::{
+    middleware,
     routing::{Router, post, get, delete},
     Extension,
 };
-use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use tracing::{info, warn};
+use tokio::sync::watch;
 use tokio::time::{interval, Duration};
 
 mod routes;
@@ -19,20 +20,14 @@ mod state;
 use crate::{
     config::Config,
     state::AppState,
-    services::{
-        database::Database,
-        auth::AuthService,
-        spaces::SpaceService,
-        space_member::SpaceMemberService,
-        documents::DocumentService,
-        comments::CommentService,
-        publication::PublicationService,
-        search::SearchService,
-        versions::VersionService,
-        tags::TagService,
-        file_upload::FileUploadService,
+    services::database::Database,
+    utils::{
+        cors,
+        db_autostart,
+        i18n,
+        request_id,
+        shutdown,
     },
-    utils::markdown::MarkdownProcessor,
 };
 
 #[tokio::main]
@@ -62,45 +57,31 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // 初始化数据库连接（已安装或非安装模式）
-    // 如果数据库连接失败，尝试自动启动数据库
-    let db = match Database::new(&config).await {
+    // 连接失败且开启了 DATABASE_AUTOSTART 时，在本机启动数据库并轮询等待其可用
+    let (db, autostarted_pid) = match connect_database(&config).await {
         Ok(db) => {
-            match db.verify_connection().await {
-                Ok(_) => {
-                    info!("Database connection established successfully");
-                    db
-                }
-                Err(e) => {
-                    warn!("Database connection failed: {}", e);
-                    info!("Attempting to auto-start database...");
-                    
-                    // 尝试自动启动数据库
-                    if let Err(start_err) = auto_start_database(&config).await {
-                        return Err(anyhow::anyhow!("Failed to auto-start database: {}. Original error: {}", start_err, e));
-                    }
-                    
-                    // 重新尝试连接
-                    let db = Database::new(&config).await?;
-                    db.verify_connection().await?;
-                    info!("Database auto-started and connected successfully");
-                    db
-                }
-            }
+            info!("Database connection established successfully");
+            (db, None)
         }
-        Err(e) => {
-            warn!("Failed to create database connection: {}", e);
+        Err(e) if config.database.autostart => {
+            warn!("Database connection failed: {}", e);
             info!("Attempting to auto-start database...");
-            
-            // 尝试自动启动数据库
-            if let Err(start_err) = auto_start_database(&config).await {
-                return Err(anyhow::anyhow!("Failed to auto-start database: {}. Original error: {}", start_err, e));
-            }
-            
-            // 重新尝试连接
-            let db = Database::new(&config).await?;
-            db.verify_connection().await?;
+
+            let pid = db_autostart::start_local_database(&config.database)
+                .map_err(|start_err| anyhow::anyhow!("Failed to auto-start database: {}. Original error: {}", start_err, e))?;
+
+            let timeout = Duration::from_secs(config.database.autostart_timeout);
+            let db = db_autostart::poll_until_ready(timeout, Duration::from_millis(500), || connect_database(&config))
+                .await
+                .map_err(|e| anyhow::anyhow!("Database did not become ready within {}s: {}", timeout.as_secs(), e))?;
             info!("Database auto-started and connected successfully");
-            db
+            (db, Some(pid))
+        }
+        Err(e) => {
+            return Err(anyhow::anyhow!(
+                "Database connection failed: {}. Start SurrealDB or set DATABASE_AUTOSTART=true to start a local instance",
+                e
+            ));
         }
     };
     
@@ -109,56 +90,51 @@ async fn main() -> anyhow::Result<()> {
     // 创建共享的数据库实例
     let shared_db = Arc::new(db.clone());
 
-    // 创建认证服务
-    let auth_service = Arc::new(AuthService::new(config.clone()));
-
-    // 创建业务服务
-    let space_service = Arc::new(SpaceService::new(shared_db.clone()));
-    let space_member_service = Arc::new(SpaceMemberService::new(shared_db.clone(), config.clone()));
-    let file_upload_service = Arc::new(FileUploadService::new(shared_db.clone(), auth_service.clone()));
-    let tag_service = Arc::new(TagService::new(shared_db.clone(), auth_service.clone()));
-    
-    let markdown_processor = Arc::new(MarkdownProcessor::new());
-    let search_service = Arc::new(SearchService::new(shared_db.clone(), auth_service.clone()));
-    let version_service = Arc::new(VersionService::new(shared_db.clone(), auth_service.clone()));
-    let document_service = Arc::new(DocumentService::new(
-        shared_db.clone(),
-        auth_service.clone(),
-        markdown_processor.clone(),
-    ).with_search_service(search_service.clone()).with_version_service(version_service.clone()));
-    let comment_service = Arc::new(CommentService::new(shared_db.clone(), auth_service.clone()));
-    let publication_service = Arc::new(PublicationService::new(shared_db.clone()));
-
-    // 启动缓存清理任务
-    let cleanup_auth = auth_service.clone();
-    tokio::spawn(async move {
+    // 创建认证和业务服务
+    let app_state = AppState::new(shared_db.clone(), config.clone());
+    let auth_service = app_state.auth_service.clone();
+
+    // 启动缓存与孤立文件清理任务，服务关闭时通过 cleanup_stop 通知退出
+    let cleanup_auth = app_state.auth_service.clone();
+    let cleanup_members = app_state.space_member_service.clone();
+    let cleanup_files = app_state.file_upload_service.clone();
+    let cleanup_publications = app_state.publication_service.clone();
+    let cleanup_documents = app_state.document_service.clone();
+    let orphan_file_retention = config.files.orphan_file_retention_hours;
+    let (cleanup_stop, mut cleanup_stopped) = watch::channel(false);
+    let cleanup_task = tokio::spawn(async move {
         let mut interval = interval(Duration::from_secs(1800)); // 每30分钟清理一次
         loop {
-            interval.tick().await;
-            cleanup_auth.cleanup_cache().await;
+            tokio::select! {
+                _ = interval.tick() => {
+                    cleanup_auth.cleanup_cache().await;
+                    cleanup_members.cleanup_rate_limits().await;
+                    cleanup_publications.cleanup_tree_cache().await;
+                    cleanup_publications.cleanup_rate_limits().await;
+                    if let Err(e) = cleanup_publications.deactivate_expired_publications().await {
+                        warn!("Expired publication cleanup failed: {}", e);
+                    }
+                    if let Err(e) = cleanup_documents.prune_access_log().await {
+                        warn!("Document access log cleanup failed: {}", e);
+                    }
+                    if orphan_file_retention > 0 {
+                        let older_than = chrono::Duration::hours(orphan_file_retention as i64);
+                        if let Err(e) = cleanup_files.cleanup_orphans(older_than).await {
+                            warn!("Orphaned file cleanup failed: {}", e);
+                        }
+                    }
+                }
+                _ = cleanup_stopped.changed() => break,
+            }
         }
     });
 
-    // 创建 app state
-    let app_state = AppState {
-        db: shared_db.clone(),
-        config: config.clone(),
-        auth_service: auth_service.clone(),
-        space_service: space_service.clone(),
-        space_member_service: space_member_service.clone(),
-        file_upload_service: file_upload_service.clone(),
-        tag_service: tag_service.clone(),
-        document_service: document_service.clone(),
-        comment_service: comment_service.clone(),
-        publication_service: publication_service.clone(),
-        search_service: search_service.clone(),
-        version_service: version_service.clone(),
-    };
-
     // 创建路由
     let mut app = Router::new()
         .nest("/api/docs/spaces", routes::spaces::router())
         .nest("/api/docs/spaces", routes::space_members::router())
+        .nest("/api/docs/spaces", routes::webhooks::router())
+        .nest("/api/docs/spaces", routes::api_keys::router())
         .nest("/api/docs/files", routes::files::router())
         .nest("/api/docs/tags", routes::tags::router())
         .nest("/api/docs/documents", routes::documents::router())
@@ -169,6 +145,7 @@ async fn main() -> anyhow::Result<()> {
         .nest("/api/docs/stats", routes::stats::router())
         .nest("/api/docs/versions", routes::versions::router())
         .nest("/api/docs", vectors_router())
+        .merge(routes::openapi::router())
         .with_state(Arc::new(app_state));
 
     // 如果是安装模式，额外添加安装路由
@@ -181,75 +158,41 @@ async fn main() -> anyhow::Result<()> {
         .layer(Extension(shared_db))
         .layer(Extension(config.clone()))
         .layer(Extension(auth_service.clone()))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        );
+        .layer(middleware::from_fn(i18n::localize_errors_middleware))
+        .layer(cors::cors_layer(&config.server))
+        // 最外层分配请求关联ID，后续中间件和服务的日志都在该请求的 span 中
+        .layer(middleware::from_fn(request_id::request_id_middleware));
 
-    // 启动服务器
+    // 启动服务器，收到 Ctrl-C / SIGTERM 后等待处理中的请求完成再退出
     let addr = "0.0.0.0:3000";
     info!("Rainbow-Docs server listening on {}", addr);
-    axum::Server::bind(&addr.parse()?)
+    let served = axum::Server::bind(&addr.parse()?)
         .serve(app.into_make_service())
-        .await?;
+        .with_graceful_shutdown(shutdown::shutdown_signal())
+        .await;
+    info!("HTTP server stopped");
+
+    // 停止后台任务
+    let _ = cleanup_stop.send(true);
+    if let Err(e) = cleanup_task.await {
+        warn!("Cleanup task did not stop cleanly: {}", e);
+    }
+
+    // 只停止本次由服务自动启动的数据库
+    if let Some(pid) = autostarted_pid {
+        if let Err(e) = db_autostart::stop_local_database(pid) {
+            warn!("{}", e);
+        }
+    }
 
+    served?;
     Ok(())
 }
 
-// 自动启动数据库的函数
-async fn auto_start_database(config: &Config) -> anyhow::Result<()> {
-    use std::process::Command;
-    use std::fs;
-    use std::path::Path;
-    
-    info!("Auto-starting SurrealDB database service...");
-    
-    // 创建数据目录（如果不存在）
-    let data_dir = "./data";
-    if !Path::new(data_dir).exists() {
-        fs::create_dir_all(data_dir)
-            .map_err(|e| anyhow::anyhow!("Failed to create data directory: {}", e))?;
-    }
-    
-    // 构建数据库文件路径
-    let db_file = format!("{}/rainbow.db", data_dir);
-    
-    // 从配置中读取数据库认证信息
-    let database_user = config.database.user.clone();
-    let database_pass = config.database.pass.clone();
-    let database_url = config.database.url.clone();
-    
-    // 构建启动命令
-    let mut cmd = Command::new("surreal");
-    cmd.arg("start")
-       .arg("--auth")
-       .arg("--user").arg(&database_user)
-       .arg("--pass").arg(&database_pass)
-       .arg("--bind").arg(&database_url)
-       .arg(format!("file://{}", db_file));
-    
-    // 在后台启动数据库
-    info!("Executing: surreal start --auth --user {} --pass *** --bind {} file://{}", 
-           database_user, database_url, db_file);
-    
-    let child = cmd.spawn()
-        .map_err(|e| anyhow::anyhow!("Failed to start SurrealDB: {}. Please make sure SurrealDB is installed.", e))?;
-    
-    // 保存进程ID
-    let pid = child.id();
-    fs::write(".surreal_pid", pid.to_string())
-        .map_err(|e| anyhow::anyhow!("Failed to save database PID: {}", e))?;
-    
-    info!("SurrealDB process started (PID: {})", pid);
-    
-    // 等待数据库启动
-    info!("Waiting for database service to be ready...");
-    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-    
-    info!("Database service should be ready now");
-    Ok(())
+async fn connect_database(config: &Config) -> anyhow::Result<Database> {
+    let db = Database::new(config).await?;
+    db.verify_connection().await?;
+    Ok(db)
 }
 
 fn vectors_router() -> Router<Arc<AppState>> {
@@ -271,19 +214,15 @@ async fn start_installer_only_mode(config: Config) -> anyhow::Result<()> {
     // 创建仅包含安装路由的应用
     let app = Router::new()
         .nest("/api/install", installer_routes())
-        .layer(Extension(config))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        );
+        .layer(cors::cors_layer(&config.server))
+        .layer(Extension(config));
     
     // 启动服务器
     let addr = "0.0.0.0:3000";
     info!("Rainbow-Docs installer-only mode listening on {}", addr);
     axum::Server::bind(&addr.parse()?)
         .serve(app.into_make_service())
+        .with_graceful_shutdown(shutdown::shutdown_signal())
         .await?;
     
     Ok(())
@@ -0,0 +1,123 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+use validator::{Validate, ValidationError};
+
+/// API 密钥可以被授予的权限，只包含空间内的文档操作，
+/// 不允许 docs.admin、spaces.write 等全局权限
+pub const API_KEY_PERMISSIONS: &[&str] = &[
+    "docs.read",
+    "docs.write",
+    "docs.update",
+    "docs.delete",
+    "docs.comment.read",
+    "docs.comment.create",
+    "docs.comment.update",
+    "docs.comment.delete",
+    "docs.tag.create",
+    "docs.tag.update",
+    "docs.tag.delete",
+    "docs.tag.manage",
+];
+
+// 用于从数据库读取的内部结构
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyDb {
+    pub id: Option<Thing>,
+    pub space_id: Thing,
+    pub name: String,
+    pub key_prefix: String,
+    pub key_hash: String,
+    pub permissions: Vec<String>,
+    pub created_by: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Option<String>,
+    pub space_id: String,
+    pub name: String,
+    /// 密钥开头的可见部分，用于查找和在列表中辨认密钥
+    pub key_prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub permissions: Vec<String>,
+    pub created_by: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiKeyDb> for ApiKey {
+    fn from(db: ApiKeyDb) -> Self {
+        Self {
+            id: db.id.map(|thing| thing.to_string()),
+            space_id: db.space_id.to_string(),
+            name: db.name,
+            key_prefix: db.key_prefix,
+            key_hash: db.key_hash,
+            permissions: db.permissions,
+            created_by: db.created_by,
+            last_used_at: db.last_used_at,
+            revoked_at: db.revoked_at,
+            created_at: db.created_at,
+        }
+    }
+}
+
+impl ApiKey {
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    /// 未吊销的密钥只能访问所属空间；permission 为 None 时只检查空间
+    pub fn allows(&self, space_id: &str, permission: Option<&str>) -> bool {
+        !self.is_revoked()
+            && same_space(&self.space_id, space_id)
+            && permission.is_none_or(|permission| self.permissions.iter().any(|p| p == permission))
+    }
+}
+
+/// 通过 API 密钥认证的请求所属的空间
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKeyScope {
+    pub key_id: String,
+    pub space_id: String,
+}
+
+impl ApiKeyScope {
+    pub fn covers_space(&self, space_id: &str) -> bool {
+        same_space(&self.space_id, space_id)
+    }
+}
+
+fn same_space(a: &str, b: &str) -> bool {
+    a.strip_prefix("space:").unwrap_or(a) == b.strip_prefix("space:").unwrap_or(b)
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1, message = "At least one permission is required"), custom = "validate_api_key_permissions")]
+    pub permissions: Vec<String>,
+}
+
+fn validate_api_key_permissions(permissions: &[String]) -> Result<(), ValidationError> {
+    if permissions.iter().all(|p| API_KEY_PERMISSIONS.contains(&p.as_str())) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("unsupported_api_key_permission"))
+    }
+}
+
+/// 创建密钥的响应，完整密钥只在这里返回一次
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
@@ -11,6 +11,10 @@ pub struct Comment {
     pub author_id: String,
     pub content: String,
     pub is_resolved: bool,
+    #[serde(default)]
+    pub resolved_by: Option<String>,
+    #[serde(default)]
+    pub resolved_at: Option<DateTime<Utc>>,
     pub metadata: CommentMetadata,
     pub liked_by: Vec<String>, // 点赞用户列表
     pub is_deleted: bool,
@@ -42,6 +46,7 @@ pub struct CreateCommentRequest {
     #[validate(length(min = 1, max = 2000, message = "Content must be between 1 and 2000 characters"))]
     pub content: String,
     
+    #[serde(alias = "parent_comment_id")]
     pub parent_id: Option<String>, // 回复的评论ID
     pub metadata: Option<CommentMetadata>,
 }
@@ -64,6 +69,8 @@ pub struct CommentResponse {
     pub author_info: Option<CommentAuthor>, // 作者信息
     pub content: String,
     pub is_resolved: bool,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
     pub metadata: CommentMetadata,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -123,6 +130,8 @@ impl Comment {
             author_id,
             content,
             is_resolved: false,
+            resolved_by: None,
+            resolved_at: None,
             metadata: CommentMetadata::default(),
             liked_by: Vec::new(),
             is_deleted: false,
@@ -195,6 +204,8 @@ impl From<Comment> for CommentResponse {
             author_info: None, // 需要在服务层填充
             content: comment.content,
             is_resolved: comment.is_resolved,
+            resolved_by: comment.resolved_by,
+            resolved_at: comment.resolved_at,
             metadata: comment.metadata,
             created_at: comment.created_at.unwrap_or_else(Utc::now),
             updated_at: comment.updated_at.unwrap_or_else(Utc::now),
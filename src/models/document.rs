@@ -3,8 +3,11 @@ use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use validator::Validate;
 use surrealdb::sql::Thing;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::utils::pagination::Paginated;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Document {
     pub id: Option<String>,
     pub space_id: String,
@@ -29,7 +32,7 @@ pub struct Document {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DocumentMetadata {
     #[serde(default)]
     pub tags: Vec<String>,
@@ -40,7 +43,7 @@ pub struct DocumentMetadata {
     pub reading_time: Option<u32>, // in minutes
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SeoMetadata {
     pub title: Option<String>,
     pub description: Option<String>,
@@ -71,15 +74,19 @@ impl Default for SeoMetadata {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Validate)]
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct CreateDocumentRequest {
     #[validate(length(min = 1, max = 200, message = "Title must be between 1 and 200 characters"))]
     pub title: String,
     
+    /// 为空时根据标题自动生成，并在空间内追加 -2、-3 等后缀保证唯一
+    #[serde(default)]
     #[validate(length(min = 1, max = 100, message = "Slug must be between 1 and 100 characters"))]
     #[validate(regex(path = "crate::models::document::SLUG_REGEX", message = "Slug can only contain lowercase letters, numbers, and hyphens"))]
     pub slug: String,
     
+    /// 字符数硬上限；服务端还会按配置的字节数检查，见 DocumentLimits
+    #[validate(length(max = 10485760, message = "Content must not exceed 10485760 characters"))]
     pub content: Option<String>,
     pub excerpt: Option<String>,
     pub is_public: Option<bool>,
@@ -88,17 +95,76 @@ pub struct CreateDocumentRequest {
     pub metadata: Option<DocumentMetadata>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Validate)]
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct UpdateDocumentRequest {
     #[validate(length(min = 1, max = 200, message = "Title must be between 1 and 200 characters"))]
     pub title: Option<String>,
+
+    #[validate(length(min = 1, max = 100, message = "Slug must be between 1 and 100 characters"))]
+    #[validate(regex(path = "crate::models::document::SLUG_REGEX", message = "Slug can only contain lowercase letters, numbers, and hyphens"))]
+    #[serde(default)]
+    pub slug: Option<String>,
     
+    #[validate(length(max = 10485760, message = "Content must not exceed 10485760 characters"))]
     pub content: Option<String>,
     pub excerpt: Option<String>,
     pub is_public: Option<bool>,
     pub parent_id: Option<String>,
     pub order_index: Option<i32>,
     pub metadata: Option<DocumentMetadata>,
+
+    /// 客户端读取时文档的 updated_at；与当前值不一致时拒绝写入。为空时强制写入
+    #[serde(default)]
+    pub expected_updated_at: Option<DateTime<Utc>>,
+
+    /// slug 变更后，是否把同空间其他文档中指向旧 slug 的链接改写为新 slug
+    #[serde(default)]
+    pub rewrite_backlinks: bool,
+}
+
+/// 文档更新结果，backlinks_updated 为因 slug 变更而改写链接的文档数，
+/// backlinks_failed 为改写失败或编辑者无写权限、仍指向旧 slug 的文档 ID
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DocumentUpdateResult {
+    #[serde(flatten)]
+    pub document: Document,
+    pub backlinks_updated: usize,
+    pub backlinks_failed: Vec<String>,
+}
+
+/// 将文档（连同子树）转移到另一个空间
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct TransferDocumentRequest {
+    #[validate(length(min = 1, message = "target_space_id is required"))]
+    pub target_space_id: String,
+    pub new_parent_id: Option<String>,
+}
+
+/// 文档草稿（按用户隔离的自动保存内容）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentDraft {
+    pub document_id: String,
+    pub user_id: String,
+    pub title: Option<String>,
+    pub content: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct SaveDraftRequest {
+    #[validate(length(min = 1, max = 200, message = "Title must be between 1 and 200 characters"))]
+    pub title: Option<String>,
+
+    #[validate(length(max = 10485760, message = "Content must not exceed 10485760 characters"))]
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ReorderDocumentsRequest {
+    pub space_id: String,
+    pub parent_id: Option<String>,
+    #[validate(length(min = 1, message = "ordered_ids must not be empty"))]
+    pub ordered_ids: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -123,7 +189,7 @@ pub struct DocumentResponse {
     pub breadcrumbs: Option<Vec<BreadcrumbItem>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct DocumentListItem {
     pub id: String,
     pub title: String,
@@ -140,7 +206,7 @@ pub struct DocumentListItem {
     pub children_count: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct DocumentListResponse {
     pub documents: Vec<DocumentListItem>,
     pub total: u32,
@@ -149,6 +215,18 @@ pub struct DocumentListResponse {
     pub total_pages: u32,
 }
 
+impl From<Paginated<DocumentListItem>> for DocumentListResponse {
+    fn from(page: Paginated<DocumentListItem>) -> Self {
+        Self {
+            documents: page.items,
+            total: page.total,
+            page: page.page,
+            limit: page.limit,
+            total_pages: page.total_pages,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BreadcrumbItem {
     pub id: String,
@@ -156,7 +234,8 @@ pub struct BreadcrumbItem {
     pub slug: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct DocumentQuery {
     pub page: Option<u32>,
     pub limit: Option<u32>,
@@ -164,11 +243,115 @@ pub struct DocumentQuery {
     pub parent_id: Option<String>,
     pub is_public: Option<bool>,
     pub author_id: Option<String>,
+    /// 逗号分隔的标签名，如 `tags=rust,api`
+    #[serde(default, deserialize_with = "deserialize_tag_list")]
     pub tags: Option<Vec<String>>,
+    /// 多个标签的匹配方式，默认要求全部命中
+    pub tag_match: Option<TagMatch>,
     pub sort: Option<String>, // "title", "created_at", "updated_at", "order_index"
     pub order: Option<String>, // "asc", "desc"
 }
 
+/// 标签过滤语义：all 为 AND，any 为 OR
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    #[default]
+    #[serde(alias = "and")]
+    All,
+    #[serde(alias = "or")]
+    Any,
+}
+
+/// 查询字符串无法直接表示数组，这里同时接受逗号分隔字符串和 JSON 数组
+fn deserialize_tag_list<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TagList {
+        Joined(String),
+        List(Vec<String>),
+    }
+
+    let tags = match Option::<TagList>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(TagList::Joined(joined)) => joined.split(',').map(str::to_string).collect::<Vec<_>>(),
+        Some(TagList::List(list)) => list,
+    };
+    let tags: Vec<String> = tags
+        .into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+
+    Ok(if tags.is_empty() { None } else { Some(tags) })
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteDocumentQuery {
+    /// 为 false 时不级联删除，子文档挂到被删除文档的父级
+    pub cascade: Option<bool>,
+}
+
+/// 最近文档的类型：viewed 为最近浏览，edited 为最近编辑（来自版本历史）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecentKind {
+    #[default]
+    Viewed,
+    Edited,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecentDocumentsQuery {
+    pub kind: Option<RecentKind>,
+    pub limit: Option<usize>,
+}
+
+/// 比较两篇文档，a、b 为文档ID
+#[derive(Debug, Deserialize)]
+pub struct CompareDocumentsQuery {
+    pub a: String,
+    pub b: String,
+}
+
+/// 最近文档条目，last_activity_at 为该用户最近一次浏览或编辑的时间
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentDocument {
+    #[serde(flatten)]
+    pub document: Document,
+    pub last_activity_at: DateTime<Utc>,
+}
+
+/// 无法解析到同空间文档的站内链接，line 从 1 开始
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BrokenLink {
+    pub target: String,
+    pub text: String,
+    pub line: usize,
+}
+
+/// 单个文档的链接检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentLinkReport {
+    pub document_id: String,
+    pub title: String,
+    pub slug: String,
+    pub broken_links: Vec<BrokenLink>,
+}
+
+/// 空间链接检查汇总，documents 只包含存在失效链接的文档
+#[derive(Debug, Clone, Serialize)]
+pub struct SpaceLinkReport {
+    pub space_id: String,
+    pub documents_checked: usize,
+    pub broken_link_count: usize,
+    pub documents: Vec<DocumentLinkReport>,
+}
+
 impl Default for DocumentQuery {
     fn default() -> Self {
         Self {
@@ -179,6 +362,7 @@ impl Default for DocumentQuery {
             is_public: None,
             author_id: None,
             tags: None,
+            tag_match: None,
             sort: Some("order_index".to_string()),
             order: Some("asc".to_string()),
         }
@@ -494,4 +678,19 @@ mod tests {
         assert!(doc.can_read(Some("user_456"), false));
         assert!(!doc.can_read(None, false));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_document_query_tag_parsing() {
+        let query: DocumentQuery = serde_json::from_value(serde_json::json!({
+            "tags": "rust, api,,",
+            "tag_match": "or"
+        }))
+        .unwrap();
+        assert_eq!(query.tags, Some(vec!["rust".to_string(), "api".to_string()]));
+        assert_eq!(query.tag_match, Some(TagMatch::Any));
+
+        let query: DocumentQuery = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(query.tags, None);
+        assert_eq!(query.tag_match.unwrap_or_default(), TagMatch::All);
+    }
+}
@@ -14,12 +14,30 @@ pub struct FileUpload {
     pub uploaded_by: String,
     pub space_id: Option<Thing>,
     pub document_id: Option<Thing>,
+    #[serde(default)]
+    pub thumbnail_path: Option<String>,  // 仅图片且缩略图生成成功时存在
     pub is_deleted: bool,
     pub deleted_at: Option<Datetime>,
     pub deleted_by: Option<String>,
     pub created_at: Datetime,
 }
 
+/// 文档与附件文件的关联。文档被删除后 pending_cleanup 置为 true，等待清理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentAttachment {
+    pub id: Option<Thing>,
+    pub document_id: Thing,
+    pub file_id: Thing,
+    pub attached_by: String,
+    pub pending_cleanup: bool,
+    pub attached_at: Datetime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttachFileRequest {
+    pub file_id: String,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct UploadFileRequest {
     pub space_id: Option<String>,
@@ -53,6 +71,13 @@ pub struct FileListResponse {
     pub total_pages: i64,
 }
 
+/// 一次孤立文件清理的结果
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct OrphanCleanupReport {
+    pub files_removed: usize,
+    pub bytes_freed: u64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct FileQuery {
     pub space_id: Option<String>,
@@ -83,6 +108,7 @@ impl FileUpload {
             uploaded_by,
             space_id: None,
             document_id: None,
+            thumbnail_path: None,
             is_deleted: false,
             deleted_at: None,
             deleted_by: None,
@@ -100,6 +126,11 @@ impl FileUpload {
         self
     }
 
+    pub fn with_thumbnail(mut self, thumbnail_path: Option<String>) -> Self {
+        self.thumbnail_path = thumbnail_path;
+        self
+    }
+
     pub fn is_image(&self) -> bool {
         self.mime_type.starts_with("image/")
     }
@@ -115,19 +146,31 @@ impl FileUpload {
     }
 }
 
+impl DocumentAttachment {
+    pub fn new(document_id: Thing, file_id: Thing, attached_by: String) -> Self {
+        Self {
+            id: None,
+            document_id,
+            file_id,
+            attached_by,
+            pending_cleanup: false,
+            attached_at: Datetime::default(),
+        }
+    }
+}
+
 impl From<FileUpload> for FileResponse {
     fn from(file: FileUpload) -> Self {
         let id = file.id.as_ref().map(|t| t.to_string()).unwrap_or_default();
         let space_id = file.space_id.as_ref().map(|t| t.to_string());
         let document_id = file.document_id.as_ref().map(|t| t.to_string());
         
-        // 生成文件访问URL
-        let url = format!("/api/files/{}/download", id);
-        let thumbnail_url = if file.is_image() {
-            Some(format!("/api/files/{}/thumbnail", id))
-        } else {
-            None
-        };
+        // 生成文件访问URL，文件路由挂载在 /api/docs/files 下
+        let url = format!("/api/docs/files/{}/download", id);
+        let thumbnail_url = file
+            .thumbnail_path
+            .as_ref()
+            .map(|_| format!("/api/docs/files/{}/thumbnail", id));
 
         Self {
             id,
@@ -1,5 +1,7 @@
+pub mod api_key;
 pub mod space;
 pub mod space_member;
+pub mod space_export;
 pub mod document;
 pub mod comment;
 pub mod version;
@@ -8,4 +10,7 @@ pub mod tag;
 pub mod search;
 pub mod file;
 pub mod notification;
-pub mod publication;
\ No newline at end of file
+pub mod publication;
+pub mod template;
+pub mod webhook;
+pub mod stats;
@@ -2,7 +2,9 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::Thing;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::utils::pagination::{self, Paginated};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum NotificationType {
     SpaceInvitation,
@@ -12,6 +14,32 @@ pub enum NotificationType {
     System,
 }
 
+/// 空间邀请通知的数据
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpaceInvitationData {
+    pub space_name: String,
+    pub invite_token: String,
+    pub role: String,
+    pub inviter_name: String,
+}
+
+/// 评论提及通知的数据
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommentMentionData {
+    pub document_id: String,
+    pub comment_id: Option<String>,
+    pub mentioned_by: String,
+}
+
+/// 通知携带的结构化数据，按通知类型解析，序列化时直接输出内部对象
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NotificationData {
+    SpaceInvitation(SpaceInvitationData),
+    CommentMention(CommentMentionData),
+    Other(serde_json::Value),
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct NotificationDb {
     pub id: Option<Thing>,
@@ -21,9 +49,14 @@ pub struct NotificationDb {
     pub title: String,
     pub content: String,
     pub data: Option<serde_json::Value>,
+    /// 旧版空间邀请通知把数据存成独立字段，新记录只写 data
+    #[serde(default)]
     pub invite_token: Option<String>,
+    #[serde(default)]
     pub space_name: Option<String>,
+    #[serde(default)]
     pub role: Option<String>,
+    #[serde(default)]
     pub inviter_name: Option<String>,
     pub is_read: bool,
     pub read_at: Option<DateTime<Utc>>,
@@ -39,11 +72,7 @@ pub struct Notification {
     pub notification_type: NotificationType,
     pub title: String,
     pub content: String,
-    pub data: Option<serde_json::Value>,
-    pub invite_token: Option<String>,
-    pub space_name: Option<String>,
-    pub role: Option<String>,
-    pub inviter_name: Option<String>,
+    pub data: Option<NotificationData>,
     pub is_read: bool,
     pub read_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
@@ -57,7 +86,7 @@ pub struct CreateNotificationRequest {
     pub notification_type: NotificationType,
     pub title: String,
     pub content: String,
-    pub data: Option<serde_json::Value>,
+    pub data: Option<NotificationData>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -65,30 +94,262 @@ pub struct UpdateNotificationRequest {
     pub is_read: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct NotificationListQuery {
     pub page: Option<u32>,
     pub limit: Option<u32>,
+    /// 按已读状态过滤
+    pub is_read: Option<bool>,
+    /// 按通知类型过滤
+    #[serde(rename = "type")]
+    pub notification_type: Option<NotificationType>,
+    /// 旧参数，等同于 is_read=false
     pub unread_only: Option<bool>,
 }
 
+impl NotificationListQuery {
+    /// 实际生效的已读过滤条件，显式的 is_read 优先于 unread_only
+    pub fn read_filter(&self) -> Option<bool> {
+        self.is_read.or(if self.unread_only == Some(true) { Some(false) } else { None })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificationListResponse {
+    pub notifications: Vec<Notification>,
+    pub total: u32,
+    pub page: u32,
+    pub limit: u32,
+    pub total_pages: u32,
+}
+
+impl NotificationListResponse {
+    pub fn new(notifications: Vec<Notification>, total: u32, page: u32, limit: u32) -> Self {
+        Self {
+            notifications,
+            total,
+            page,
+            limit,
+            total_pages: pagination::total_pages(total, limit),
+        }
+    }
+}
+
+impl From<Paginated<Notification>> for NotificationListResponse {
+    fn from(page: Paginated<Notification>) -> Self {
+        Self {
+            notifications: page.items,
+            total: page.total,
+            page: page.page,
+            limit: page.limit,
+            total_pages: page.total_pages,
+        }
+    }
+}
+
+impl Notification {
+    /// 标记为已读并返回状态是否发生变化，已读的通知保留原来的 read_at
+    pub fn mark_read(&mut self, at: DateTime<Utc>) -> bool {
+        if self.is_read {
+            return false;
+        }
+        self.is_read = true;
+        self.read_at = Some(at);
+        true
+    }
+}
+
+/// 用户的通知偏好，各类型默认开启。系统通知不能关闭
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    #[serde(default = "enabled")]
+    pub space_invitation: bool,
+    #[serde(default = "enabled")]
+    pub comment_mention: bool,
+    #[serde(default = "enabled")]
+    pub document_shared: bool,
+    #[serde(default = "enabled")]
+    pub document_update: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            space_invitation: true,
+            comment_mention: true,
+            document_shared: true,
+            document_update: true,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// 是否接收该类型的通知
+    pub fn allows(&self, notification_type: NotificationType) -> bool {
+        match notification_type {
+            NotificationType::SpaceInvitation => self.space_invitation,
+            NotificationType::CommentMention => self.comment_mention,
+            NotificationType::DocumentShared => self.document_shared,
+            NotificationType::DocumentUpdate => self.document_update,
+            NotificationType::System => true,
+        }
+    }
+
+    /// 应用部分更新，未提供的字段保持不变
+    pub fn apply(&mut self, update: UpdateNotificationPreferencesRequest) {
+        if let Some(value) = update.space_invitation {
+            self.space_invitation = value;
+        }
+        if let Some(value) = update.comment_mention {
+            self.comment_mention = value;
+        }
+        if let Some(value) = update.document_shared {
+            self.document_shared = value;
+        }
+        if let Some(value) = update.document_update {
+            self.document_update = value;
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateNotificationPreferencesRequest {
+    pub space_invitation: Option<bool>,
+    pub comment_mention: Option<bool>,
+    pub document_shared: Option<bool>,
+    pub document_update: Option<bool>,
+}
+
+impl NotificationDb {
+    /// 按通知类型解析 data 字段。data 缺失的旧版空间邀请记录从独立字段读取
+    pub fn typed_data(&self) -> Option<NotificationData> {
+        let raw = self.data.clone().filter(|value| !value.is_null());
+
+        match self.notification_type {
+            NotificationType::SpaceInvitation => {
+                if let Some(data) = raw.as_ref().and_then(|value| serde_json::from_value(value.clone()).ok()) {
+                    return Some(NotificationData::SpaceInvitation(data));
+                }
+                match &self.invite_token {
+                    Some(invite_token) => Some(NotificationData::SpaceInvitation(SpaceInvitationData {
+                        space_name: self.space_name.clone().unwrap_or_default(),
+                        invite_token: invite_token.clone(),
+                        role: self.role.clone().unwrap_or_default(),
+                        inviter_name: self.inviter_name.clone().unwrap_or_default(),
+                    })),
+                    None => raw.map(NotificationData::Other),
+                }
+            }
+            NotificationType::CommentMention => raw.map(|value| {
+                serde_json::from_value(value.clone())
+                    .map(NotificationData::CommentMention)
+                    .unwrap_or(NotificationData::Other(value))
+            }),
+            _ => raw.map(NotificationData::Other),
+        }
+    }
+}
+
 impl From<NotificationDb> for Notification {
     fn from(db: NotificationDb) -> Self {
+        let data = db.typed_data();
         Self {
             id: db.id.map(|thing| thing.id.to_string()),
             user_id: db.user_id,
             notification_type: db.notification_type,
             title: db.title,
             content: db.content,
-            data: db.data,
-            invite_token: db.invite_token,
-            space_name: db.space_name,
-            role: db.role,
-            inviter_name: db.inviter_name,
+            data,
             is_read: db.is_read,
             read_at: db.read_at,
             created_at: db.created_at,
             updated_at: db.updated_at,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn invitation_data() -> SpaceInvitationData {
+        SpaceInvitationData {
+            space_name: "项目文档".to_string(),
+            invite_token: "abc123-def456".to_string(),
+            role: "editor".to_string(),
+            inviter_name: "张三".to_string(),
+        }
+    }
+
+    fn invitation_row(extra: serde_json::Value) -> NotificationDb {
+        let mut row = json!({
+            "user_id": "user_1",
+            "type": "space_invitation",
+            "title": "张三 邀请您加入 项目文档 空间",
+            "content": "",
+            "is_read": false,
+            "read_at": null,
+            "created_at": "2024-01-15T10:30:00Z",
+            "updated_at": "2024-01-15T10:30:00Z",
+        });
+        row.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(row).unwrap()
+    }
+
+    #[test]
+    fn test_space_invitation_round_trip() {
+        // 与创建通知时写入 data 的内容一致
+        let stored = serde_json::to_value(NotificationData::SpaceInvitation(invitation_data())).unwrap();
+        let notification: Notification = invitation_row(json!({ "data": stored })).into();
+
+        assert_eq!(notification.data, Some(NotificationData::SpaceInvitation(invitation_data())));
+
+        let response = serde_json::to_value(&notification).unwrap();
+        assert_eq!(response["data"]["invite_token"], "abc123-def456");
+        assert!(response.get("invite_token").is_none());
+
+        let parsed: Notification = serde_json::from_value(response).unwrap();
+        assert_eq!(parsed.data, notification.data);
+    }
+
+    #[test]
+    fn test_legacy_invitation_columns_are_read_into_data() {
+        let legacy = invitation_row(json!({
+            "data": null,
+            "invite_token": "abc123-def456",
+            "space_name": "项目文档",
+            "role": "editor",
+            "inviter_name": "张三",
+        }));
+
+        assert_eq!(legacy.typed_data(), Some(NotificationData::SpaceInvitation(invitation_data())));
+    }
+
+    #[test]
+    fn test_preferences_default_to_enabled_and_merge_updates() {
+        let stored: NotificationPreferences = serde_json::from_value(json!({ "comment_mention": false })).unwrap();
+        assert!(!stored.allows(NotificationType::CommentMention));
+        assert!(stored.allows(NotificationType::SpaceInvitation));
+
+        let mut preferences = NotificationPreferences::default();
+        preferences.apply(UpdateNotificationPreferencesRequest {
+            space_invitation: Some(false),
+            ..Default::default()
+        });
+        assert!(!preferences.allows(NotificationType::SpaceInvitation));
+        assert!(preferences.allows(NotificationType::CommentMention));
+        assert!(preferences.allows(NotificationType::System));
+    }
+
+    #[test]
+    fn test_unknown_data_is_kept_as_is() {
+        let mut row = invitation_row(json!({ "data": { "document_id": "doc_1" } }));
+        row.notification_type = NotificationType::DocumentShared;
+
+        assert_eq!(row.typed_data(), Some(NotificationData::Other(json!({ "document_id": "doc_1" }))));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::models::space_member::MemberRole;
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::{Datetime, Thing};
 use std::collections::HashMap;
@@ -6,7 +7,8 @@ use std::collections::HashMap;
 pub struct DocumentPermission {
     pub id: Option<Thing>,
     pub resource_type: ResourceType,
-    pub resource_id: Thing,
+    /// 资源ID（不带表前缀），与 schema 中的 string 字段一致
+    pub resource_id: String,
     pub user_id: Option<String>,
     pub role_id: Option<String>,
     pub permissions: Vec<String>,
@@ -33,6 +35,32 @@ pub struct GrantPermissionRequest {
     pub expires_at: Option<Datetime>,
 }
 
+/// 文档级授权的访问级别，写权限包含读权限
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentAccess {
+    Read,
+    Write,
+}
+
+impl DocumentAccess {
+    pub fn permissions(&self) -> Vec<String> {
+        match self {
+            DocumentAccess::Read => vec!["docs.read".to_string()],
+            DocumentAccess::Write => vec!["docs.read".to_string(), "docs.write".to_string()],
+        }
+    }
+}
+
+/// 为单个文档（及其子文档）授权，user_id 与 role 必须且只能指定一个
+#[derive(Debug, Deserialize)]
+pub struct GrantDocumentPermissionRequest {
+    pub user_id: Option<String>,
+    pub role: Option<MemberRole>,
+    pub access: DocumentAccess,
+    pub expires_at: Option<Datetime>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserPermissions {
     pub user_id: String,
@@ -44,7 +72,7 @@ pub struct UserPermissions {
 impl DocumentPermission {
     pub fn new(
         resource_type: ResourceType,
-        resource_id: Thing,
+        resource_id: String,
         permissions: Vec<String>,
         granted_by: String,
     ) -> Self {
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use surrealdb::sql::Thing;
+use utoipa::ToSchema;
 use validator::Validate;
 use std::collections::HashMap;
 
@@ -28,6 +29,18 @@ pub struct SpacePublicationDb {
     pub seo_description: Option<String>,
     pub seo_keywords: Vec<String>,
     
+    // 自定义域名
+    #[serde(default)]
+    pub custom_domain: Option<String>,
+    
+    // 访问密码的 argon2 哈希，为空表示不需要密码
+    #[serde(default)]
+    pub access_password_hash: Option<String>,
+    
+    // 过期时间，过期后不再公开访问
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    
     // 状态和时间戳
     pub is_active: bool,
     pub is_deleted: bool,
@@ -61,6 +74,17 @@ pub struct SpacePublication {
     pub seo_description: Option<String>,
     pub seo_keywords: Vec<String>,
     
+    // 自定义域名
+    pub custom_domain: Option<String>,
+    
+    // 访问密码的 argon2 哈希，为空表示不需要密码
+    #[serde(default)]
+    pub access_password_hash: Option<String>,
+    
+    // 过期时间，过期后不再公开访问
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    
     // 状态和时间戳
     pub is_active: bool,
     pub is_deleted: bool,
@@ -98,6 +122,10 @@ pub struct PublicationDocumentDb {
     pub content: String,
     pub excerpt: Option<String>,
     
+    // 标题和内容的哈希，用于重新发布时比较变更
+    #[serde(default)]
+    pub content_hash: String,
+    
     // 文档结构
     pub parent_id: Option<String>,
     pub order_index: u32,
@@ -106,11 +134,15 @@ pub struct PublicationDocumentDb {
     pub word_count: u32,
     pub reading_time: u32,
     
+    // 源文档发布时是否公开，旧快照没有此字段时视为非公开
+    #[serde(default)]
+    pub is_public: bool,
+    
     pub created_at: Option<DateTime<Utc>>,
 }
 
 /// 发布的文档快照
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PublicationDocument {
     pub id: Option<String>,
     pub publication_id: String,
@@ -121,6 +153,7 @@ pub struct PublicationDocument {
     pub slug: String,
     pub content: String,
     pub excerpt: Option<String>,
+    pub content_hash: String,
     
     // 文档结构
     pub parent_id: Option<String>,
@@ -129,6 +162,8 @@ pub struct PublicationDocument {
     // 文档元数据
     pub word_count: u32,
     pub reading_time: u32,
+    #[serde(default)]
+    pub is_public: bool,
     
     pub created_at: Option<DateTime<Utc>>,
 }
@@ -151,6 +186,10 @@ pub struct PublicationAnalyticsDb {
     // 最热门文档
     pub popular_documents: Vec<PopularDocument>,
     
+    // 最近一次访问时间，用于判断日/周/月计数是否需要清零
+    #[serde(default)]
+    pub last_view_at: Option<DateTime<Utc>>,
+    
     pub updated_at: Option<DateTime<Utc>>,
 }
 
@@ -172,6 +211,10 @@ pub struct PublicationAnalytics {
     // 最热门文档
     pub popular_documents: Vec<PopularDocument>,
     
+    // 最近一次访问时间，用于判断日/周/月计数是否需要清零
+    #[serde(default)]
+    pub last_view_at: Option<DateTime<Utc>>,
+    
     pub updated_at: Option<DateTime<Utc>>,
 }
 
@@ -258,7 +301,7 @@ pub enum ChangeType {
 }
 
 /// 创建发布请求
-#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 pub struct CreatePublicationRequest {
     #[validate(length(min = 1, max = 100))]
     #[validate(regex = "SLUG_REGEX")]
@@ -284,10 +327,17 @@ pub struct CreatePublicationRequest {
     pub seo_title: Option<String>,
     pub seo_description: Option<String>,
     pub seo_keywords: Option<Vec<String>>,
+    
+    /// 设置后需要先输入密码才能阅读发布的文档
+    #[validate(length(min = 4, max = 128))]
+    pub access_password: Option<String>,
+    
+    /// 过期后公开访问返回 404，定时任务会将其标记为未发布
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 /// 更新发布请求
-#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 pub struct UpdatePublicationRequest {
     #[validate(length(min = 1, max = 200))]
     pub title: Option<String>,
@@ -308,10 +358,134 @@ pub struct UpdatePublicationRequest {
     pub seo_title: Option<String>,
     pub seo_description: Option<String>,
     pub seo_keywords: Option<Vec<String>>,
+    
+    /// 空字符串表示取消密码保护
+    #[validate(custom = "validate_optional_access_password")]
+    pub access_password: Option<String>,
+    
+    /// 只能设置为将来的时间，用于延长或提前过期；显式传 null 取消过期时间，不传则保持不变
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub expires_at: Option<Option<DateTime<Utc>>>,
 }
 
-/// 发布响应
+/// 区分未提供的字段（None）和显式传入的 null（Some(None)）
+fn deserialize_explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+fn validate_optional_access_password(password: &str) -> Result<(), validator::ValidationError> {
+    let len = password.chars().count();
+    if len == 0 || (4..=128).contains(&len) {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new("access_password_length"))
+    }
+}
+
+/// 输入访问密码的请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct PublicationAccessRequest {
+    pub password: String,
+}
+
+/// 访问密码校验通过后签发的令牌，阅读文档时放在 X-Publication-Token 请求头中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicationAccessToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 数据库中的单文档分享记录（使用Thing类型的ID）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedDocumentDb {
+    pub id: Option<Thing>,
+    pub document_id: String,
+    pub space_id: String,
+    pub slug: String,
+    pub theme: String,
+    
+    // 文档内容快照
+    pub title: String,
+    pub content: String,
+    pub excerpt: Option<String>,
+    pub word_count: u32,
+    pub reading_time: u32,
+    
+    pub shared_by: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// 单文档分享：分享时的文档快照，通过独立的 slug 公开访问，不会公开所在空间
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedDocument {
+    pub id: Option<String>,
+    pub document_id: String,
+    pub space_id: String,
+    pub slug: String,
+    pub theme: String,
+    
+    // 文档内容快照
+    pub title: String,
+    pub content: String,
+    pub excerpt: Option<String>,
+    pub word_count: u32,
+    pub reading_time: u32,
+    
+    pub shared_by: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// 分享单个文档请求
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ShareDocumentRequest {
+    /// 不提供时根据标题生成，并附加随机后缀避免被猜到
+    #[validate(length(min = 1, max = 100))]
+    #[validate(regex = "SLUG_REGEX")]
+    pub slug: Option<String>,
+    pub theme: Option<String>,
+    /// 过期时间，为空时一直有效
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 分享非公开文档时必须显式设置为 true
+    pub include_private: Option<bool>,
+}
+
+/// 创建分享的响应，附带公开访问链接
+#[derive(Debug, Clone, Serialize)]
+pub struct SharedDocumentResponse {
+    #[serde(flatten)]
+    pub share: SharedDocument,
+    pub public_url: String,
+}
+
+/// 公开访问分享链接时返回的内容，不包含文档和空间的ID
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedDocumentView {
+    pub slug: String,
+    pub title: String,
+    pub content: String,
+    pub excerpt: Option<String>,
+    pub theme: String,
+    pub word_count: u32,
+    pub reading_time: u32,
+    pub shared_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// 设置自定义域名请求（domain 为空时清除自定义域名）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetCustomDomainRequest {
+    pub domain: Option<String>,
+}
+
+/// 发布响应
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PublicationResponse {
     pub id: String,
     pub space_id: String,
@@ -326,6 +500,8 @@ pub struct PublicationResponse {
     pub public_url: String,
     pub preview_url: String,
     pub custom_domain: Option<String>,
+    pub is_password_protected: bool,
+    pub expires_at: Option<DateTime<Utc>>,
     
     // 统计信息
     pub document_count: u32,
@@ -339,7 +515,7 @@ pub struct PublicationResponse {
 }
 
 /// 发布文档树节点
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PublicationDocumentNode {
     pub id: String,
     pub title: String,
@@ -366,6 +542,12 @@ impl UpdatePublicationRequest {
     }
 }
 
+impl ShareDocumentRequest {
+    pub fn validate(&self) -> Result<(), validator::ValidationErrors> {
+        Validate::validate(self)
+    }
+}
+
 impl From<SpacePublicationDb> for SpacePublication {
     fn from(db: SpacePublicationDb) -> Self {
         Self {
@@ -385,6 +567,9 @@ impl From<SpacePublicationDb> for SpacePublication {
             seo_title: db.seo_title,
             seo_description: db.seo_description,
             seo_keywords: db.seo_keywords,
+            custom_domain: db.custom_domain,
+            access_password_hash: db.access_password_hash,
+            expires_at: db.expires_at,
             is_active: db.is_active,
             is_deleted: db.is_deleted,
             published_by: db.published_by,
@@ -405,15 +590,53 @@ impl From<PublicationDocumentDb> for PublicationDocument {
             slug: db.slug,
             content: db.content,
             excerpt: db.excerpt,
+            content_hash: db.content_hash,
             parent_id: db.parent_id,
             order_index: db.order_index,
             word_count: db.word_count,
             reading_time: db.reading_time,
+            is_public: db.is_public,
             created_at: db.created_at,
         }
     }
 }
 
+impl From<SharedDocumentDb> for SharedDocument {
+    fn from(db: SharedDocumentDb) -> Self {
+        Self {
+            id: db.id.map(|thing| thing.to_string()),
+            document_id: db.document_id,
+            space_id: db.space_id,
+            slug: db.slug,
+            theme: db.theme,
+            title: db.title,
+            content: db.content,
+            excerpt: db.excerpt,
+            word_count: db.word_count,
+            reading_time: db.reading_time,
+            shared_by: db.shared_by,
+            expires_at: db.expires_at,
+            created_at: db.created_at,
+        }
+    }
+}
+
+impl From<SharedDocument> for SharedDocumentView {
+    fn from(share: SharedDocument) -> Self {
+        Self {
+            slug: share.slug,
+            title: share.title,
+            content: share.content,
+            excerpt: share.excerpt,
+            theme: share.theme,
+            word_count: share.word_count,
+            reading_time: share.reading_time,
+            shared_at: share.created_at,
+            expires_at: share.expires_at,
+        }
+    }
+}
+
 impl From<PublicationAnalyticsDb> for PublicationAnalytics {
     fn from(db: PublicationAnalyticsDb) -> Self {
         Self {
@@ -425,6 +648,7 @@ impl From<PublicationAnalyticsDb> for PublicationAnalytics {
             views_week: db.views_week,
             views_month: db.views_month,
             popular_documents: db.popular_documents,
+            last_view_at: db.last_view_at,
             updated_at: db.updated_at,
         }
     }
@@ -445,9 +669,12 @@ impl From<PublicationHistoryDb> for PublicationHistory {
 }
 
 impl SpacePublication {
-    /// 生成公开访问URL
+    /// 生成公开访问URL（设置了自定义域名时使用自定义域名）
     pub fn get_public_url(&self, base_url: &str) -> String {
-        format!("{}/p/{}", base_url, self.slug)
+        match &self.custom_domain {
+            Some(domain) => format!("https://{}", domain),
+            None => format!("{}/p/{}", base_url, self.slug),
+        }
     }
     
     /// 生成预览URL
@@ -459,4 +686,23 @@ impl SpacePublication {
     pub fn can_update(&self) -> bool {
         self.is_active && !self.is_deleted
     }
-}
\ No newline at end of file
+
+    pub fn is_password_protected(&self) -> bool {
+        self.access_password_hash.is_some()
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+impl SharedDocument {
+    /// 生成公开访问URL
+    pub fn get_public_url(&self, base_url: &str) -> String {
+        format!("{}/s/{}", base_url, self.slug)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
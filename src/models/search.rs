@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::{Datetime, Thing};
+use utoipa::ToSchema;
+
+use crate::utils::pagination;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchIndex {
@@ -22,7 +25,9 @@ pub struct SearchRequest {
     pub tags: Option<Vec<String>>,
     pub author_id: Option<String>,
     pub page: Option<i64>,
-    pub per_page: Option<i64>,
+    /// 兼容旧参数名 per_page
+    #[serde(alias = "per_page")]
+    pub limit: Option<i64>,
     pub sort_by: Option<SearchSortBy>,
 }
 
@@ -34,7 +39,7 @@ pub enum SearchSortBy {
     Title,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SearchResult {
     pub document_id: String,
     pub space_id: String,
@@ -42,25 +47,30 @@ pub struct SearchResult {
     pub excerpt: String,
     pub tags: Vec<String>,
     pub author_id: String,
+    #[schema(value_type = String, format = DateTime)]
     pub last_updated: Datetime,
     pub score: f64,
+    /// 命中位置附近的正文片段，匹配词用 <mark> 包裹，其余内容已做 HTML 转义
+    pub snippet: String,
     pub highlights: Vec<SearchHighlight>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SearchHighlight {
     pub field: String,
+    /// start/end 为 text 中的字符偏移（非字节偏移）
     pub text: String,
     pub start: usize,
     pub end: usize,
 }
 
-#[derive(Debug, Serialize)]
+/// 分页字段与 DocumentListResponse 保持一致
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SearchResponse {
     pub results: Vec<SearchResult>,
-    pub total_count: i64,
+    pub total: i64,
     pub page: i64,
-    pub per_page: i64,
+    pub limit: i64,
     pub total_pages: i64,
     pub query: String,
     pub took: i64,
@@ -125,7 +135,7 @@ impl SearchRequest {
             tags: None,
             author_id: None,
             page: Some(1),
-            per_page: Some(20),
+            limit: Some(20),
             sort_by: Some(SearchSortBy::Relevance),
         }
     }
@@ -140,9 +150,9 @@ impl SearchRequest {
         self
     }
 
-    pub fn with_pagination(mut self, page: i64, per_page: i64) -> Self {
+    pub fn with_pagination(mut self, page: i64, limit: i64) -> Self {
         self.page = Some(page);
-        self.per_page = Some(per_page);
+        self.limit = Some(limit);
         self
     }
 
@@ -155,18 +165,19 @@ impl SearchRequest {
 impl SearchResponse {
     pub fn new(
         results: Vec<SearchResult>,
-        total_count: i64,
+        total: i64,
         page: i64,
-        per_page: i64,
+        limit: i64,
         query: String,
         took: i64,
     ) -> Self {
-        let total_pages = (total_count + per_page - 1) / per_page;
+        let to_u32 = |value: i64| u32::try_from(value.max(0)).unwrap_or(u32::MAX);
+        let total_pages = pagination::total_pages(to_u32(total), to_u32(limit)).into();
         Self {
             results,
-            total_count,
+            total,
             page,
-            per_page,
+            limit,
             total_pages,
             query,
             took,
@@ -3,6 +3,9 @@ use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use validator::Validate;
 use surrealdb::sql::Thing;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::utils::pagination::Paginated;
 
 // 用于从数据库读取的内部结构
 #[derive(Debug, Clone, Deserialize)]
@@ -15,6 +18,9 @@ pub struct SpaceDb {
     pub is_public: bool,
     #[serde(default)]
     pub is_deleted: Option<bool>,
+    /// 归档后空间只读
+    #[serde(default)]
+    pub is_archived: Option<bool>,
     pub owner_id: String,
     #[serde(default)]
     pub settings: SpaceSettings,
@@ -42,6 +48,9 @@ pub struct Space {
     pub is_public: bool,
     #[serde(default)]
     pub is_deleted: Option<bool>,
+    /// 归档后空间只读
+    #[serde(default)]
+    pub is_archived: Option<bool>,
     pub owner_id: String,
     #[serde(default)]
     pub settings: SpaceSettings,
@@ -59,7 +68,7 @@ pub struct Space {
     pub updated_by: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(default)]
 pub struct SpaceSettings {
     pub theme: String,
@@ -69,9 +78,24 @@ pub struct SpaceSettings {
     pub analytics_id: Option<String>,
     pub custom_css: Option<String>,
     pub navigation: NavigationSettings,
+    /// 渲染文档时在默认白名单之外额外允许的 HTML 标签
+    pub allowed_html_tags: Vec<String>,
+    /// 是否允许该空间的发布注入自定义脚本
+    pub allow_custom_js: bool,
+    /// 空间级上传限制，只能在全局配置的基础上收紧
+    pub upload: SpaceUploadSettings,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 未设置的项沿用全局配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct SpaceUploadSettings {
+    pub max_file_size: Option<u64>,
+    pub allowed_mime_types: Option<Vec<String>>,
+    pub storage_quota: Option<u64>,  // 空间总存储配额（字节）
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(default)]
 pub struct NavigationSettings {
     pub show_breadcrumbs: bool,
@@ -80,7 +104,7 @@ pub struct NavigationSettings {
     pub custom_links: Vec<CustomLink>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CustomLink {
     pub title: String,
     pub url: String,
@@ -98,10 +122,20 @@ impl Default for SpaceSettings {
             analytics_id: None,
             custom_css: None,
             navigation: NavigationSettings::default(),
+            allowed_html_tags: Vec::new(),
+            allow_custom_js: false,
+            upload: SpaceUploadSettings::default(),
         }
     }
 }
 
+impl SpaceSettings {
+    /// 自定义脚本只有在空间显式开启后才能保存，空脚本始终允许
+    pub fn permits_custom_js(&self, custom_js: Option<&str>) -> bool {
+        self.allow_custom_js || custom_js.map_or(true, |js| js.trim().is_empty())
+    }
+}
+
 impl Default for NavigationSettings {
     fn default() -> Self {
         Self {
@@ -113,13 +147,16 @@ impl Default for NavigationSettings {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Validate)]
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct CreateSpaceRequest {
     #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
     pub name: String,
     
+    /// 为空时根据名称自动生成，已被占用时追加 -2、-3 等后缀
+    #[serde(default)]
     #[validate(length(min = 1, max = 50, message = "Slug must be between 1 and 50 characters"))]
     #[validate(regex(path = "crate::models::space::SLUG_REGEX", message = "Slug can only contain lowercase letters, numbers, and hyphens"))]
+    #[validate(custom = "validate_space_slug_not_reserved")]
     pub slug: String,
     
     #[validate(length(max = 500, message = "Description cannot exceed 500 characters"))]
@@ -130,7 +167,18 @@ pub struct CreateSpaceRequest {
     pub settings: Option<SpaceSettings>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Validate)]
+/// 与 /api/docs/documents 下固定路径段同名的 slug，这类空间的文档会被固定路由遮蔽
+pub const RESERVED_SPACE_SLUGS: &[&str] = &["recent", "compare", "reorder", "from-template", "create", "id"];
+
+fn validate_space_slug_not_reserved(slug: &str) -> Result<(), validator::ValidationError> {
+    if RESERVED_SPACE_SLUGS.contains(&slug) {
+        Err(validator::ValidationError::new("reserved_space_slug"))
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct UpdateSpaceRequest {
     #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
     pub name: Option<String>,
@@ -143,7 +191,7 @@ pub struct UpdateSpaceRequest {
     pub settings: Option<SpaceSettings>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SpaceResponse {
     pub id: String,
     pub name: String,
@@ -151,6 +199,7 @@ pub struct SpaceResponse {
     pub description: Option<String>,
     pub avatar_url: Option<String>,
     pub is_public: bool,
+    pub is_archived: bool,
     pub owner_id: String,
     pub settings: SpaceSettings,
     pub created_at: DateTime<Utc>,
@@ -158,7 +207,7 @@ pub struct SpaceResponse {
     pub stats: Option<SpaceStats>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct SpaceStats {
     pub document_count: u32,
     pub public_document_count: u32,
@@ -179,7 +228,7 @@ impl Default for SpaceStats {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SpaceListResponse {
     pub spaces: Vec<SpaceResponse>,
     pub total: u32,
@@ -188,17 +237,80 @@ pub struct SpaceListResponse {
     pub total_pages: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl From<Paginated<SpaceResponse>> for SpaceListResponse {
+    fn from(page: Paginated<SpaceResponse>) -> Self {
+        Self {
+            spaces: page.items,
+            total: page.total,
+            page: page.page,
+            limit: page.limit,
+            total_pages: page.total_pages,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SpaceListQuery {
     pub page: Option<u32>,
     pub limit: Option<u32>,
     pub search: Option<String>,
     pub owner_id: Option<String>,
     pub is_public: Option<bool>,
+    /// 是否包含已归档的空间，默认不包含
+    pub include_archived: Option<bool>,
     pub sort: Option<String>, // "name", "created_at", "updated_at"
     pub order: Option<String>, // "asc", "desc"
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct TransferOwnershipRequest {
+    #[validate(length(min = 1, message = "New owner ID is required"))]
+    pub new_owner_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpaceActivityQuery {
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+/// 空间动态中的一条记录，已补充操作者名称和资源标题
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActivityFeedEntry {
+    pub id: String,
+    pub action: String,
+    pub resource_type: String,
+    pub resource_id: String,
+    pub resource_title: Option<String>,
+    pub actor_id: String,
+    pub actor_name: String,
+    /// 可直接展示的描述，如 `alice created document "Intro"`
+    pub summary: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityFeedResponse {
+    pub activities: Vec<ActivityFeedEntry>,
+    pub total: u32,
+    pub page: u32,
+    pub limit: u32,
+    pub total_pages: u32,
+}
+
+impl From<Paginated<ActivityFeedEntry>> for ActivityFeedResponse {
+    fn from(page: Paginated<ActivityFeedEntry>) -> Self {
+        Self {
+            activities: page.items,
+            total: page.total,
+            page: page.page,
+            limit: page.limit,
+            total_pages: page.total_pages,
+        }
+    }
+}
+
 impl Default for SpaceListQuery {
     fn default() -> Self {
         Self {
@@ -207,6 +319,7 @@ impl Default for SpaceListQuery {
             search: None,
             owner_id: None,
             is_public: None,
+            include_archived: None,
             sort: Some("updated_at".to_string()),
             order: Some("desc".to_string()),
         }
@@ -228,6 +341,7 @@ impl Space {
             avatar_url: None,
             is_public: false,
             is_deleted: Some(false),
+            is_archived: Some(false),
             owner_id,
             settings: SpaceSettings::default(),
             theme_config: Some(SpaceSettings::default()),
@@ -269,6 +383,7 @@ impl From<SpaceDb> for Space {
             avatar_url: db.avatar_url,
             is_public: db.is_public,
             is_deleted: db.is_deleted,
+            is_archived: db.is_archived,
             owner_id: db.owner_id,
             settings: db.settings,
             theme_config: db.theme_config,
@@ -291,6 +406,7 @@ impl From<Space> for SpaceResponse {
             description: space.description,
             avatar_url: space.avatar_url,
             is_public: space.is_public,
+            is_archived: space.is_archived.unwrap_or(false),
             owner_id: space.owner_id,
             settings: space.settings,
             created_at: space.created_at.unwrap_or_else(Utc::now),
@@ -338,6 +454,17 @@ mod tests {
         assert!(space.can_access(None));
     }
 
+    #[test]
+    fn test_custom_js_requires_space_opt_in() {
+        let mut settings = SpaceSettings::default();
+        assert!(settings.permits_custom_js(None));
+        assert!(settings.permits_custom_js(Some("  ")));
+        assert!(!settings.permits_custom_js(Some("alert(1)")));
+
+        settings.allow_custom_js = true;
+        assert!(settings.permits_custom_js(Some("alert(1)")));
+    }
+
     #[test]
     fn test_slug_validation() {
         let valid_slugs = vec!["test", "test-123", "my-awesome-space"];
@@ -351,4 +478,21 @@ mod tests {
             assert!(!SLUG_REGEX.is_match(slug), "Should be invalid: {}", slug);
         }
     }
+
+    #[test]
+    fn test_reserved_slugs_are_rejected() {
+        let request = |slug: &str| CreateSpaceRequest {
+            name: "Space".to_string(),
+            slug: slug.to_string(),
+            description: None,
+            avatar_url: None,
+            is_public: None,
+            settings: None,
+        };
+
+        // /api/docs/documents/recent 是最近文档列表，不能再作为空间 slug
+        assert!(request("recent").validate().is_err());
+        assert!(request("compare").validate().is_err());
+        assert!(request("recent-docs").validate().is_ok());
+    }
 }
\ No newline at end of file
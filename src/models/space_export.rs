@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::document::DocumentMetadata;
+use crate::models::space::{SpaceResponse, SpaceSettings};
+
+/// 导出包格式版本，结构发生不兼容的变化时递增
+pub const SPACE_BUNDLE_VERSION: u32 = 1;
+
+/// 空间的完整导出包，用于备份和在实例之间迁移。
+/// 包内的 id 只用于表示文档树、标签和评论之间的引用关系，导入时会分配新的 id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpaceBundle {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub space: BundleSpace,
+    /// 按文档树顺序排列，父文档总在子文档之前
+    pub documents: Vec<BundleDocument>,
+    #[serde(default)]
+    pub tags: Vec<BundleTag>,
+    #[serde(default)]
+    pub comments: Vec<BundleComment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleSpace {
+    pub name: String,
+    pub slug: String,
+    pub description: Option<String>,
+    pub avatar_url: Option<String>,
+    pub is_public: bool,
+    #[serde(default)]
+    pub settings: SpaceSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleDocument {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub title: String,
+    pub slug: String,
+    pub content: String,
+    pub excerpt: Option<String>,
+    pub is_public: bool,
+    pub order_index: i32,
+    pub author_id: String,
+    #[serde(default)]
+    pub word_count: u32,
+    #[serde(default)]
+    pub reading_time: u32,
+    pub metadata: DocumentMetadata,
+    /// 关联的标签，引用 tags 中的 id
+    #[serde(default)]
+    pub tag_ids: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleTag {
+    pub id: String,
+    pub name: String,
+    pub slug: String,
+    pub description: Option<String>,
+    pub color: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleComment {
+    pub id: String,
+    pub document_id: String,
+    pub parent_id: Option<String>,
+    pub author_id: String,
+    pub content: String,
+    pub is_resolved: bool,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// 导入结果
+#[derive(Debug, Serialize)]
+pub struct SpaceImportResult {
+    pub space: SpaceResponse,
+    pub document_count: usize,
+    pub tag_count: usize,
+    pub comment_count: usize,
+}
@@ -80,6 +80,28 @@ impl Default for MemberStatus {
 }
 
 impl MemberRole {
+    /// 数据库中存储的角色名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemberRole::Owner => "owner",
+            MemberRole::Admin => "admin",
+            MemberRole::Editor => "editor",
+            MemberRole::Viewer => "viewer",
+            MemberRole::Member => "member",
+        }
+    }
+
+    /// 解析数据库中的角色名称，未知角色按普通成员处理
+    pub fn from_db_str(role: &str) -> Self {
+        match role {
+            "owner" => MemberRole::Owner,
+            "admin" => MemberRole::Admin,
+            "editor" => MemberRole::Editor,
+            "viewer" => MemberRole::Viewer,
+            _ => MemberRole::Member,
+        }
+    }
+
     pub fn default_permissions(&self) -> Vec<String> {
         match self {
             MemberRole::Owner => vec![
@@ -133,6 +155,10 @@ pub struct InviteMemberRequest {
     
     #[validate(range(min = 1, max = 365, message = "Expiration days must be between 1 and 365"))]
     pub expires_in_days: Option<u32>, // 邀请过期天数，默认7天
+
+    /// 邀请链接可使用次数，默认1次，0 表示过期前不限次数
+    #[validate(range(min = 0, max = 10000, message = "max_uses must be between 0 and 10000"))]
+    pub max_uses: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
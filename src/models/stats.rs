@@ -0,0 +1,106 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::space::SpaceStats;
+
+/// 单个空间的统计，用于仪表盘中的分空间列表
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpaceStatsEntry {
+    pub space_id: String,
+    #[serde(flatten)]
+    pub stats: SpaceStats,
+}
+
+/// 用户拥有或已加入的全部空间的统计汇总
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UserDashboard {
+    pub total_spaces: u32,
+    pub total_documents: u32,
+    pub public_documents: u32,
+    pub total_comments: u32,
+    pub total_views: u32,
+    pub last_activity: Option<DateTime<Utc>>,
+    pub spaces: Vec<SpaceStatsEntry>,
+}
+
+impl UserDashboard {
+    pub fn from_spaces(spaces: Vec<SpaceStatsEntry>) -> Self {
+        let mut dashboard = Self {
+            total_spaces: spaces.len() as u32,
+            ..Default::default()
+        };
+        for entry in &spaces {
+            dashboard.total_documents += entry.stats.document_count;
+            dashboard.public_documents += entry.stats.public_document_count;
+            dashboard.total_comments += entry.stats.comment_count;
+            dashboard.total_views += entry.stats.view_count;
+            dashboard.last_activity = dashboard.last_activity.max(entry.stats.last_activity);
+        }
+        dashboard.spaces = spaces;
+        dashboard
+    }
+}
+
+/// 趋势统计的指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsMetric {
+    /// 发布页面的浏览量
+    Views,
+    /// 新建文档数
+    Creations,
+    /// 文档编辑次数，来自版本历史
+    Edits,
+}
+
+/// 趋势统计的时间粒度，按周统计时每个区间从周一开始
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    #[default]
+    Day,
+    Week,
+}
+
+impl Granularity {
+    /// 日期所在区间的第一天
+    pub fn bucket_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => date,
+            Granularity::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        }
+    }
+
+    pub fn step(self) -> Duration {
+        match self {
+            Granularity::Day => Duration::days(1),
+            Granularity::Week => Duration::weeks(1),
+        }
+    }
+}
+
+/// 查询参数，from/to 为包含在内的日期（UTC），默认最近 30 天
+#[derive(Debug, Deserialize)]
+pub struct TimeseriesQuery {
+    pub metric: StatsMetric,
+    #[serde(default)]
+    pub granularity: Granularity,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimeseriesPoint {
+    pub bucket: NaiveDate,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpaceTimeseries {
+    pub space_id: String,
+    pub metric: StatsMetric,
+    pub granularity: Granularity,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub points: Vec<TimeseriesPoint>,
+}
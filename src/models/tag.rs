@@ -61,6 +61,37 @@ pub struct TagDocumentRequest {
     pub tag_ids: Vec<String>,
 }
 
+/// 批量为空间内的多个文档打标签，按名称引用标签，缺失的标签会自动创建
+#[derive(Debug, Validate, Deserialize)]
+pub struct BulkAssignTagsRequest {
+    #[validate(length(min = 1))]
+    pub space_id: String,
+    #[validate(length(min = 1, max = 100))]
+    pub document_ids: Vec<String>,
+    #[validate(length(min = 1, max = 20))]
+    pub tag_names: Vec<String>,
+}
+
+/// 按名称重命名空间内的标签
+#[derive(Debug, Validate, Deserialize)]
+pub struct RenameTagRequest {
+    #[validate(length(min = 1))]
+    pub space_id: String,
+    #[validate(length(min = 1, max = 50))]
+    pub new_name: String,
+}
+
+/// 将若干标签合并到目标标签，源标签在合并后删除
+#[derive(Debug, Validate, Deserialize)]
+pub struct MergeTagsRequest {
+    #[validate(length(min = 1))]
+    pub space_id: String,
+    #[validate(length(min = 1, max = 50))]
+    pub source_names: Vec<String>,
+    #[validate(length(min = 1, max = 50))]
+    pub target_name: String,
+}
+
 impl Tag {
     pub fn new(name: String, color: String, created_by: String) -> Self {
         let slug = Self::generate_slug(&name);
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::{Datetime, Thing};
+use validator::Validate;
+
+/// 空间内的文档模板，标题模式和正文支持 `{{date}}`、`{{author}}` 占位符
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentTemplate {
+    pub id: Option<Thing>,
+    pub space_id: Thing,
+    pub name: String,
+    pub description: Option<String>,
+    pub title_pattern: String,
+    pub content: String,
+    pub created_by: String,
+    pub created_at: Datetime,
+    pub updated_at: Datetime,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct CreateTemplateRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    #[validate(length(min = 1, max = 200))]
+    pub title_pattern: String,
+    #[serde(default)]
+    pub content: String,
+}
+
+/// 基于模板创建文档时可覆盖的字段，未提供标题时使用模板的标题模式
+#[derive(Debug, Default, Deserialize)]
+pub struct TemplateOverrides {
+    pub title: Option<String>,
+    pub slug: String,
+    pub parent_id: Option<String>,
+    pub is_public: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplyTemplateRequest {
+    pub space_id: String,
+    pub template_id: String,
+    #[serde(flatten)]
+    pub overrides: TemplateOverrides,
+}
+
+/// 渲染占位符时使用的上下文
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    pub date: DateTime<Utc>,
+    pub author: String,
+}
+
+impl DocumentTemplate {
+    pub fn new(space_id: Thing, name: String, title_pattern: String, content: String, created_by: String) -> Self {
+        Self {
+            id: None,
+            space_id,
+            name,
+            description: None,
+            title_pattern,
+            content,
+            created_by,
+            created_at: Datetime::default(),
+            updated_at: Datetime::default(),
+        }
+    }
+
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+}
+
+impl TemplateContext {
+    /// 替换已知占位符，未知占位符原样保留
+    pub fn render(&self, text: &str) -> String {
+        text.replace("{{date}}", &self.date.format("%Y-%m-%d").to_string())
+            .replace("{{author}}", &self.author)
+    }
+}
+
@@ -13,6 +13,8 @@ pub struct DocumentVersion {
     pub author_id: String,
     pub created_at: Datetime,
     pub is_current: bool,
+    #[serde(default)]
+    pub pinned: bool,
     pub change_type: VersionChangeType,
     pub parent_version_id: Option<Thing>,
 }
@@ -55,6 +57,7 @@ impl DocumentVersion {
             author_id,
             created_at: Datetime::default(),
             is_current: false,
+            pinned: false,
             change_type,
             parent_version_id: None,
         }
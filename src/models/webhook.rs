@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+use validator::Validate;
+
+/// 可订阅的事件，序列化为 `<资源>.<动作>` 形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "document.created")]
+    DocumentCreated,
+    #[serde(rename = "document.updated")]
+    DocumentUpdated,
+    #[serde(rename = "publication.published")]
+    PublicationPublished,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::DocumentCreated => "document.created",
+            WebhookEvent::DocumentUpdated => "document.updated",
+            WebhookEvent::PublicationPublished => "publication.published",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+// 用于从数据库读取的内部结构
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookDb {
+    pub id: Option<Thing>,
+    pub space_id: Thing,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub is_active: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: Option<String>,
+    pub space_id: String,
+    pub url: String,
+    /// 签名密钥只在创建时返回一次
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub is_active: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<WebhookDb> for Webhook {
+    fn from(db: WebhookDb) -> Self {
+        Self {
+            id: db.id.map(|thing| thing.to_string()),
+            space_id: db.space_id.to_string(),
+            url: db.url,
+            secret: db.secret,
+            events: db.events,
+            is_active: db.is_active,
+            created_by: db.created_by,
+            created_at: db.created_at,
+            updated_at: db.updated_at,
+        }
+    }
+}
+
+impl Webhook {
+    pub fn subscribes_to(&self, event: WebhookEvent) -> bool {
+        self.is_active && self.events.contains(&event)
+    }
+}
+
+/// 创建 webhook 的响应，附带签名密钥
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateWebhookRequest {
+    #[validate(url, length(max = 2048))]
+    pub url: String,
+    #[validate(length(min = 1, message = "At least one event is required"))]
+    pub events: Vec<WebhookEvent>,
+    /// 不提供时自动生成
+    #[validate(length(min = 16, max = 256))]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookDeliveryDb {
+    pub id: Option<Thing>,
+    pub webhook_id: Thing,
+    pub event: WebhookEvent,
+    pub payload: serde_json::Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: u32,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: Option<String>,
+    pub webhook_id: String,
+    pub event: WebhookEvent,
+    pub payload: serde_json::Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: u32,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl From<WebhookDeliveryDb> for WebhookDelivery {
+    fn from(db: WebhookDeliveryDb) -> Self {
+        Self {
+            id: db.id.map(|thing| thing.to_string()),
+            webhook_id: db.webhook_id.to_string(),
+            event: db.event,
+            payload: db.payload,
+            status: db.status,
+            attempts: db.attempts,
+            response_status: db.response_status,
+            error: db.error,
+            created_at: db.created_at,
+            delivered_at: db.delivered_at,
+        }
+    }
+}
+
+/// 推送给接收方的请求体
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// 投递ID，与 X-Rainbow-Delivery 请求头一致，接收方可据此去重
+    pub delivery_id: String,
+    pub event: WebhookEvent,
+    /// 所属空间（不带 space: 前缀）
+    pub space_id: String,
+    pub occurred_at: DateTime<Utc>,
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookDeliveryQuery {
+    pub limit: Option<u32>,
+}
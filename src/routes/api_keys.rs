@@ -0,0 +1,88 @@
+use crate::{AppState, error::{AppError, Result}};
+use crate::models::api_key::CreateApiKeyRequest;
+use crate::services::auth::User;
+use crate::utils::redact;
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+
+pub fn router() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/:space_slug/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/:space_slug/api-keys/:key_id", delete(revoke_api_key))
+}
+
+/// 获取空间 id 并检查管理权限，API 密钥只允许空间所有者和管理员管理
+async fn managed_space_id(app_state: &AppState, space_slug: &str, user: &User) -> Result<String> {
+    let space = app_state.space_service.get_space_by_slug(space_slug, Some(user)).await?;
+
+    if !app_state.space_member_service.check_permission(&space.id, &user.id, "spaces.manage").await? {
+        return Err(AppError::Authorization("Only space owners and admins can manage API keys".to_string()));
+    }
+
+    Ok(space.id)
+}
+
+/// 获取空间的 API 密钥列表（不含密钥本身）
+/// GET /api/docs/spaces/:space_slug/api-keys
+async fn list_api_keys(
+    State(app_state): State<Arc<AppState>>,
+    Path(space_slug): Path<String>,
+    user: User,
+) -> Result<Json<Value>> {
+    let space_id = managed_space_id(&app_state, &space_slug, &user).await?;
+
+    let api_keys = app_state.api_key_service.list_api_keys(&space_id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": api_keys,
+        "message": "API keys retrieved successfully"
+    })))
+}
+
+/// 创建 API 密钥，完整密钥只在这里返回一次
+/// POST /api/docs/spaces/:space_slug/api-keys
+async fn create_api_key(
+    State(app_state): State<Arc<AppState>>,
+    Path(space_slug): Path<String>,
+    user: User,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<Json<Value>> {
+    let space_id = managed_space_id(&app_state, &space_slug, &user).await?;
+
+    let api_key = app_state.api_key_service.create_api_key(&space_id, &user.id, request).await?;
+
+    info!("User {} created API key {} in space: {}", redact::user_id(&user.id), api_key.api_key.key_prefix, space_slug);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": api_key,
+        "message": "API key created successfully"
+    })))
+}
+
+/// 吊销 API 密钥
+/// DELETE /api/docs/spaces/:space_slug/api-keys/:key_id
+async fn revoke_api_key(
+    State(app_state): State<Arc<AppState>>,
+    Path((space_slug, key_id)): Path<(String, String)>,
+    user: User,
+) -> Result<Json<Value>> {
+    let space_id = managed_space_id(&app_state, &space_slug, &user).await?;
+
+    app_state.api_key_service.revoke_api_key(&space_id, &key_id).await?;
+
+    info!("User {} revoked API key {} in space: {}", redact::user_id(&user.id), key_id, space_slug);
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "API key revoked successfully"
+    })))
+}
@@ -10,8 +10,8 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 use crate::{
-    error::ApiError,
-    models::comment::{Comment, CreateCommentRequest, UpdateCommentRequest},
+    error::AppError,
+    models::comment::{Comment, CommentResponse, CreateCommentRequest, UpdateCommentRequest},
     services::{auth::AuthService, comments::CommentService},
 };
 
@@ -20,6 +20,14 @@ pub struct CommentQuery {
     pub page: Option<i64>,
     pub per_page: Option<i64>,
     pub sort: Option<String>,
+    /// 是否包含已解决的评论，默认隐藏
+    pub include_resolved: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ResolveCommentQuery {
+    /// 是否同时处理该评论下的所有回复
+    pub cascade: Option<bool>,
 }
 
 #[derive(Serialize)]
@@ -36,7 +44,7 @@ pub async fn get_document_comments(
     Query(query): Query<CommentQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<CommentListResponse>, ApiError> {
+) -> Result<Json<CommentListResponse>, AppError> {
     let comment_service = &app_state.comment_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -46,12 +54,14 @@ pub async fn get_document_comments(
     let page = query.page.unwrap_or(1);
     let per_page = query.per_page.unwrap_or(20);
 
+    let include_resolved = query.include_resolved.unwrap_or(false);
+
     let comments = comment_service
-        .get_document_comments(&document_id, page, per_page)
+        .get_document_comments(&document_id, include_resolved, page, per_page)
         .await?;
 
     let total_count = comment_service
-        .get_document_comments_count(&document_id)
+        .get_document_comments_count(&document_id, include_resolved)
         .await?;
 
     let total_pages = (total_count + per_page - 1) / per_page;
@@ -65,12 +75,28 @@ pub async fn get_document_comments(
     }))
 }
 
+pub async fn get_comment_thread(
+    Path(document_id): Path<String>,
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<Vec<CommentResponse>>, AppError> {
+    let comment_service = &app_state.comment_service;
+    let auth_service = &app_state.auth_service;
+    auth_service
+        .check_permission(&user_id, "docs.comment.read", Some(&document_id))
+        .await?;
+
+    let thread = comment_service.get_comment_thread(&document_id).await?;
+
+    Ok(Json(thread))
+}
+
 pub async fn create_comment(
     Path(document_id): Path<String>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
     Json(request): Json<CreateCommentRequest>,
-) -> Result<Json<Comment>, ApiError> {
+) -> Result<Json<Comment>, AppError> {
     let comment_service = &app_state.comment_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -88,7 +114,7 @@ pub async fn get_comment(
     Path(comment_id): Path<String>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<Comment>, ApiError> {
+) -> Result<Json<Comment>, AppError> {
     let comment_service = &app_state.comment_service;
     let auth_service = &app_state.auth_service;
     let comment = comment_service.get_comment(&comment_id).await?;
@@ -105,7 +131,7 @@ pub async fn update_comment(
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
     Json(request): Json<UpdateCommentRequest>,
-) -> Result<Json<Comment>, ApiError> {
+) -> Result<Json<Comment>, AppError> {
     let comment_service = &app_state.comment_service;
     let auth_service = &app_state.auth_service;
     let comment = comment_service.get_comment(&comment_id).await?;
@@ -127,7 +153,7 @@ pub async fn delete_comment(
     Path(comment_id): Path<String>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<StatusCode, ApiError> {
+) -> Result<StatusCode, AppError> {
     let comment_service = &app_state.comment_service;
     let auth_service = &app_state.auth_service;
     let comment = comment_service.get_comment(&comment_id).await?;
@@ -148,7 +174,7 @@ pub async fn get_comment_replies(
     Query(query): Query<CommentQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<CommentListResponse>, ApiError> {
+) -> Result<Json<CommentListResponse>, AppError> {
     let comment_service = &app_state.comment_service;
     let auth_service = &app_state.auth_service;
     let comment = comment_service.get_comment(&comment_id).await?;
@@ -179,11 +205,64 @@ pub async fn get_comment_replies(
     }))
 }
 
+/// 解决评论需要评论所在空间的文档编辑权限
+async fn require_space_edit_permission(
+    app_state: &crate::AppState,
+    comment: &Comment,
+    user_id: &str,
+) -> Result<(), AppError> {
+    let document_id = comment.document_id.strip_prefix("document:").unwrap_or(&comment.document_id);
+    let document = app_state.document_service.get_document_by_id(document_id).await?;
+
+    if !app_state.space_member_service.can_access_space(&document.space_id, Some(user_id)).await? {
+        return Err(AppError::Authorization("Access denied to this space".to_string()));
+    }
+    if !app_state.space_member_service.check_permission(&document.space_id, user_id, "docs.write").await? {
+        return Err(AppError::Authorization("Permission denied: docs.write required".to_string()));
+    }
+
+    Ok(())
+}
+
+pub async fn resolve_comment(
+    Path(comment_id): Path<String>,
+    Query(query): Query<ResolveCommentQuery>,
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<Comment>, AppError> {
+    let comment_service = &app_state.comment_service;
+    let comment = comment_service.get_comment(&comment_id).await?;
+    require_space_edit_permission(&app_state, &comment, &user_id).await?;
+
+    let resolved = comment_service
+        .resolve_comment(&comment_id, &user_id, query.cascade.unwrap_or(false))
+        .await?;
+
+    Ok(Json(resolved))
+}
+
+pub async fn unresolve_comment(
+    Path(comment_id): Path<String>,
+    Query(query): Query<ResolveCommentQuery>,
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<Comment>, AppError> {
+    let comment_service = &app_state.comment_service;
+    let comment = comment_service.get_comment(&comment_id).await?;
+    require_space_edit_permission(&app_state, &comment, &user_id).await?;
+
+    let reopened = comment_service
+        .unresolve_comment(&comment_id, query.cascade.unwrap_or(false))
+        .await?;
+
+    Ok(Json(reopened))
+}
+
 pub async fn toggle_comment_like(
     Path(comment_id): Path<String>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<Comment>, ApiError> {
+) -> Result<Json<Comment>, AppError> {
     let comment_service = &app_state.comment_service;
     let auth_service = &app_state.auth_service;
     let comment = comment_service.get_comment(&comment_id).await?;
@@ -202,7 +281,10 @@ pub async fn toggle_comment_like(
 pub fn router() -> Router<Arc<crate::AppState>> {
     Router::new()
         .route("/document/:document_id", get(get_document_comments).post(create_comment))
+        .route("/document/:document_id/thread", get(get_comment_thread))
         .route("/:comment_id", get(get_comment).put(update_comment).delete(delete_comment))
         .route("/:comment_id/replies", get(get_comment_replies))
         .route("/:comment_id/like", post(toggle_comment_like))
+        .route("/:comment_id/resolve", post(resolve_comment))
+        .route("/:comment_id/unresolve", post(unresolve_comment))
 }
\ No newline at end of file
@@ -10,10 +10,10 @@ use std::sync::Arc;
 use tracing::{error, info};
 
 use crate::{
-    error::ApiError,
+    error::AppError,
     models::file::{FileQuery, UploadFileRequest},
     services::{file_upload::FileUploadService, auth::AuthService},
-    utils::auth::extract_user_from_header,
+    utils::{auth::extract_user_from_header, download, redact},
 };
 
 pub fn router() -> Router<Arc<crate::AppState>> {
@@ -28,7 +28,7 @@ async fn upload_file(
     State(app_state): State<Arc<crate::AppState>>,
     headers: axum::http::HeaderMap,
     mut multipart: Multipart,
-) -> Result<impl IntoResponse, ApiError> {
+) -> Result<impl IntoResponse, AppError> {
     let service = &app_state.file_upload_service;
     let auth_service = &app_state.auth_service;
     let user_id = extract_user_from_header(&headers, &auth_service).await?;
@@ -40,24 +40,24 @@ async fn upload_file(
     
     // 预处理multipart数据，提取参数
     while let Some(field) = multipart.next_field().await.map_err(|e| {
-        ApiError::bad_request(format!("Failed to read multipart field: {}", e))
+        AppError::bad_request(format!("Failed to read multipart field: {}", e))
     })? {
         let field_name = field.name().unwrap_or("");
         
         match field_name {
             "space_id" => {
                 space_id = Some(field.text().await.map_err(|e| {
-                    ApiError::bad_request(format!("Failed to read space_id: {}", e))
+                    AppError::bad_request(format!("Failed to read space_id: {}", e))
                 })?);
             }
             "document_id" => {
                 document_id = Some(field.text().await.map_err(|e| {
-                    ApiError::bad_request(format!("Failed to read document_id: {}", e))
+                    AppError::bad_request(format!("Failed to read document_id: {}", e))
                 })?);
             }
             "description" => {
                 description = Some(field.text().await.map_err(|e| {
-                    ApiError::bad_request(format!("Failed to read description: {}", e))
+                    AppError::bad_request(format!("Failed to read description: {}", e))
                 })?);
             }
             "file" => {
@@ -70,12 +70,12 @@ async fn upload_file(
                 let filename = field.file_name().map(|s| s.to_string());
                 let content_type = field.content_type().map(|s| s.to_string());
                 let data = field.bytes().await.map_err(|e| {
-                    ApiError::bad_request(format!("Failed to read file data: {}", e))
+                    AppError::bad_request(format!("Failed to read file data: {}", e))
                 })?;
                 
                 // 检查文件大小
                 if data.len() > 10 * 1024 * 1024 { // 10MB limit
-                    return Err(ApiError::bad_request(
+                    return Err(AppError::bad_request(
                         "File size exceeds maximum allowed size of 10MB".to_string()
                     ));
                 }
@@ -90,12 +90,12 @@ async fn upload_file(
                 let file_response = service.upload_file_from_bytes(
                     &user_id,
                     data,
-                    filename.ok_or_else(|| ApiError::bad_request("No filename provided".to_string()))?,
+                    filename.ok_or_else(|| AppError::bad_request("No filename provided".to_string()))?,
                     content_type,
                     request
                 ).await?;
                 
-                info!("File uploaded by user {}", user_id);
+                info!("File uploaded by user {}", redact::user_id(&user_id));
                 return Ok((StatusCode::CREATED, Json(file_response)));
             }
             _ => {
@@ -104,14 +104,14 @@ async fn upload_file(
         }
     }
     
-    Err(ApiError::bad_request("No file found in request".to_string()))
+    Err(AppError::bad_request("No file found in request".to_string()))
 }
 
 async fn list_files(
     State(app_state): State<Arc<crate::AppState>>,
     headers: axum::http::HeaderMap,
     Query(query): Query<FileQuery>,
-) -> Result<impl IntoResponse, ApiError> {
+) -> Result<impl IntoResponse, AppError> {
     let service = &app_state.file_upload_service;
     let auth_service = &app_state.auth_service;
     let user_id = extract_user_from_header(&headers, &auth_service).await?;
@@ -124,7 +124,7 @@ async fn get_file_info(
     State(app_state): State<Arc<crate::AppState>>,
     headers: axum::http::HeaderMap,
     Path(file_id): Path<String>,
-) -> Result<impl IntoResponse, ApiError> {
+) -> Result<impl IntoResponse, AppError> {
     let service = &app_state.file_upload_service;
     let auth_service = &app_state.auth_service;
     let _user_id = extract_user_from_header(&headers, &auth_service).await?;
@@ -138,37 +138,30 @@ async fn download_file(
     State(app_state): State<Arc<crate::AppState>>,
     headers: axum::http::HeaderMap,
     Path(file_id): Path<String>,
-) -> Result<impl IntoResponse, ApiError> {
+) -> Result<Response, AppError> {
     let service = &app_state.file_upload_service;
     let auth_service = &app_state.auth_service;
     let _user_id = extract_user_from_header(&headers, &auth_service).await?;
     
-    let (content, mime_type, original_name) = service.get_file_content(&file_id).await?;
-    
-    let headers = [
-        (header::CONTENT_TYPE, mime_type),
-        (
-            header::CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{}\"", original_name),
-        ),
-    ];
+    let file = service.get_file(&file_id).await?;
+    let range = headers.get(header::RANGE).and_then(|value| value.to_str().ok());
 
-    Ok((headers, content))
+    download::stream_file(std::path::Path::new(&file.file_path), &file.mime_type, &file.original_name, range).await
 }
 
 async fn get_thumbnail(
     State(app_state): State<Arc<crate::AppState>>,
     headers: axum::http::HeaderMap,
     Path(file_id): Path<String>,
-) -> Result<impl IntoResponse, ApiError> {
+) -> Result<impl IntoResponse, AppError> {
     let service = &app_state.file_upload_service;
     let auth_service = &app_state.auth_service;
     let _user_id = extract_user_from_header(&headers, &auth_service).await?;
     
-    let thumbnail_content = service.get_thumbnail(&file_id).await?;
+    let (thumbnail_content, content_type) = service.get_thumbnail(&file_id).await?;
     
     let headers = [
-        (header::CONTENT_TYPE, "image/jpeg".to_string()),
+        (header::CONTENT_TYPE, content_type),
         (header::CACHE_CONTROL, "public, max-age=86400".to_string()),
     ];
 
@@ -179,14 +172,14 @@ async fn delete_file(
     State(app_state): State<Arc<crate::AppState>>,
     headers: axum::http::HeaderMap,
     Path(file_id): Path<String>,
-) -> Result<impl IntoResponse, ApiError> {
+) -> Result<impl IntoResponse, AppError> {
     let service = &app_state.file_upload_service;
     let auth_service = &app_state.auth_service;
     let user_id = extract_user_from_header(&headers, &auth_service).await?;
     
     service.delete_file(&user_id, &file_id).await?;
     
-    info!("File {} deleted by user {}", file_id, user_id);
+    info!("File {} deleted by user {}", file_id, redact::user_id(&user_id));
     Ok((
         StatusCode::OK,
         Json(json!({ "message": "File deleted successfully" })),
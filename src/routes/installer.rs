@@ -1,7 +1,6 @@
 #[cfg(feature = "installer")]
 use axum::{
     extract::Query,
-    http::StatusCode,
     response::Json,
     routing::{get, post},
     Router,
@@ -10,7 +9,10 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 #[cfg(feature = "installer")]
-use crate::utils::installer::{InstallationChecker, wizard::{InstallationWizard, InstallConfig}};
+use crate::{
+    error::Result,
+    utils::installer::{InstallationChecker, wizard::{InstallationWizard, InstallConfig}},
+};
 
 use crate::AppState;
 
@@ -40,15 +42,14 @@ pub fn installer_routes() -> Router {
 }
 
 #[cfg(feature = "installer")]
-async fn check_install_status() -> Result<Json<InstallResponse>, StatusCode> {
-    match InstallationChecker::check_installation_status() {
-        Ok(status) => Ok(Json(InstallResponse {
-            status: "success".to_string(),
-            message: "Installation status retrieved".to_string(),
-            data: Some(serde_json::to_value(status).unwrap()),
-        })),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+async fn check_install_status() -> Result<Json<InstallResponse>> {
+    let status = InstallationChecker::check_installation_status()?;
+
+    Ok(Json(InstallResponse {
+        status: "success".to_string(),
+        message: "Installation status retrieved".to_string(),
+        data: Some(serde_json::to_value(status)?),
+    }))
 }
 
 #[cfg(feature = "installer")]
@@ -73,18 +74,14 @@ async fn get_install_steps(Query(query): Query<InstallQuery>) -> Json<InstallRes
 }
 
 #[cfg(feature = "installer")]
-async fn perform_install(Json(config): Json<InstallConfig>) -> Result<Json<InstallResponse>, StatusCode> {
+async fn perform_install(Json(config): Json<InstallConfig>) -> Result<Json<InstallResponse>> {
     // 检查是否已安装
-    match InstallationChecker::check_installation_status() {
-        Ok(status) if status.is_installed => {
-            return Ok(Json(InstallResponse {
-                status: "error".to_string(),
-                message: "System is already installed".to_string(),
-                data: None,
-            }));
-        }
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-        _ => {}
+    if InstallationChecker::check_installation_status()?.is_installed {
+        return Ok(Json(InstallResponse {
+            status: "error".to_string(),
+            message: "System is already installed".to_string(),
+            data: None,
+        }));
     }
     
     // 执行安装
@@ -1,7 +1,9 @@
+pub mod api_keys;
 pub mod comments;
 pub mod documents;
 pub mod files;
 pub mod notifications;
+pub mod openapi;
 pub mod publication;
 pub mod search;
 pub mod spaces;
@@ -10,6 +12,7 @@ pub mod stats;
 pub mod tags;
 pub mod versions;
 pub mod vectors;
+pub mod webhooks;
 
 #[cfg(feature = "installer")]
 pub mod installer;
\ No newline at end of file
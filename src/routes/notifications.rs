@@ -1,6 +1,7 @@
 use crate::{AppState, error::Result};
-use crate::models::notification::NotificationListQuery;
+use crate::models::notification::{NotificationListQuery, UpdateNotificationPreferencesRequest};
 use crate::services::auth::User;
+use crate::utils::redact;
 use axum::{
     extract::{Path, Query, State},
     response::Json,
@@ -15,11 +16,15 @@ pub fn router() -> Router<Arc<crate::AppState>> {
     Router::new()
         .route("/", get(list_notifications))
         .route("/unread-count", get(get_unread_count))
+        .route("/preferences", get(get_preferences).put(update_preferences))
+        .route("/read-all", post(mark_all_as_read))
+        .route("/:notification_id/read", post(mark_as_read))
+        // 旧接口，保留给已有客户端
         .route("/:notification_id", put(mark_as_read).delete(delete_notification))
         .route("/mark-all-read", post(mark_all_as_read))
 }
 
-/// 获取通知列表
+/// 分页获取通知列表，支持 is_read、type 过滤
 /// GET /api/docs/notifications
 async fn list_notifications(
     State(app_state): State<Arc<AppState>>,
@@ -33,16 +38,13 @@ async fn list_notifications(
         app_state.config.clone(),
     );
 
-    let (notifications, total) = notification_service
-        .get_user_notifications(&user.id, query)
+    let notifications = notification_service
+        .list_notifications(&user.id, query)
         .await?;
 
     Ok(Json(json!({
         "success": true,
-        "data": {
-            "notifications": notifications,
-            "total": total
-        },
+        "data": notifications,
         "message": "Notifications retrieved successfully"
     })))
 }
@@ -59,7 +61,7 @@ async fn get_unread_count(
         app_state.config.clone(),
     );
 
-    let count = notification_service.get_unread_count(&user.id).await?;
+    let count = notification_service.unread_count(&user.id).await?;
 
     Ok(Json(json!({
         "success": true,
@@ -70,8 +72,53 @@ async fn get_unread_count(
     })))
 }
 
+/// 获取通知偏好
+/// GET /api/docs/notifications/preferences
+async fn get_preferences(
+    State(app_state): State<Arc<AppState>>,
+    user: User,
+) -> Result<Json<Value>> {
+    let notification_service = crate::services::notification::NotificationService::new(
+        app_state.db.clone(),
+        app_state.auth_service.clone(),
+        app_state.config.clone(),
+    );
+
+    let preferences = notification_service.get_preferences(&user.id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": preferences,
+        "message": "Notification preferences retrieved successfully"
+    })))
+}
+
+/// 更新通知偏好，只需提交要修改的类型
+/// PUT /api/docs/notifications/preferences
+async fn update_preferences(
+    State(app_state): State<Arc<AppState>>,
+    user: User,
+    Json(request): Json<UpdateNotificationPreferencesRequest>,
+) -> Result<Json<Value>> {
+    let notification_service = crate::services::notification::NotificationService::new(
+        app_state.db.clone(),
+        app_state.auth_service.clone(),
+        app_state.config.clone(),
+    );
+
+    let preferences = notification_service.update_preferences(&user.id, request).await?;
+
+    info!("User {} updated notification preferences", redact::user_id(&user.id));
+
+    Ok(Json(json!({
+        "success": true,
+        "data": preferences,
+        "message": "Notification preferences updated successfully"
+    })))
+}
+
 /// 标记通知为已读
-/// PUT /api/docs/notifications/:notification_id
+/// POST /api/docs/notifications/:notification_id/read
 async fn mark_as_read(
     State(app_state): State<Arc<AppState>>,
     Path(notification_id): Path<String>,
@@ -84,10 +131,10 @@ async fn mark_as_read(
     );
 
     let notification = notification_service
-        .mark_as_read(&user.id, &notification_id)
+        .mark_read(&user.id, &notification_id)
         .await?;
 
-    info!("User {} marked notification {} as read", user.id, notification_id);
+    info!("User {} marked notification {} as read", redact::user_id(&user.id), notification_id);
 
     Ok(Json(json!({
         "success": true,
@@ -97,7 +144,7 @@ async fn mark_as_read(
 }
 
 /// 标记所有通知为已读
-/// POST /api/docs/notifications/mark-all-read
+/// POST /api/docs/notifications/read-all
 async fn mark_all_as_read(
     State(app_state): State<Arc<AppState>>,
     user: User,
@@ -108,9 +155,9 @@ async fn mark_all_as_read(
         app_state.config.clone(),
     );
 
-    let count = notification_service.mark_all_as_read(&user.id).await?;
+    let count = notification_service.mark_all_read(&user.id).await?;
 
-    info!("User {} marked {} notifications as read", user.id, count);
+    info!("User {} marked {} notifications as read", redact::user_id(&user.id), count);
 
     Ok(Json(json!({
         "success": true,
@@ -138,7 +185,7 @@ async fn delete_notification(
         .delete_notification(&user.id, &notification_id)
         .await?;
 
-    info!("User {} deleted notification {}", user.id, notification_id);
+    info!("User {} deleted notification {}", redact::user_id(&user.id), notification_id);
 
     Ok(Json(json!({
         "success": true,
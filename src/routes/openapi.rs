@@ -0,0 +1,198 @@
+use axum::Router;
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi, ToSchema,
+};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::models::{
+    document::{
+        CreateDocumentRequest, Document, DocumentListItem, DocumentListResponse, DocumentMetadata,
+        DocumentUpdateResult, SeoMetadata, TagMatch, UpdateDocumentRequest,
+    },
+    publication::{
+        CreatePublicationRequest, PublicationDocument, PublicationDocumentNode, PublicationResponse,
+        UpdatePublicationRequest,
+    },
+    search::{SearchHighlight, SearchResponse, SearchResult},
+    space::{
+        CreateSpaceRequest, CustomLink, NavigationSettings, SpaceListResponse, SpaceResponse, SpaceSettings,
+        SpaceStats, SpaceUploadSettings, UpdateSpaceRequest,
+    },
+};
+use crate::routes::{documents, publication, search, spaces};
+use crate::AppState;
+
+pub const OPENAPI_JSON_PATH: &str = "/api/docs/openapi.json";
+pub const SWAGGER_UI_PATH: &str = "/api/docs/swagger";
+
+/// 大部分接口返回的统一结构：`{ "success": true, "data": ..., "message": ... }`
+#[derive(Serialize, ToSchema)]
+#[aliases(
+    SpaceEnvelope = ApiResponse<SpaceResponse>,
+    SpaceListEnvelope = ApiResponse<SpaceListResponse>,
+    DocumentEnvelope = ApiResponse<Document>,
+    DocumentListEnvelope = ApiResponse<DocumentListResponse>,
+    DocumentUpdateEnvelope = ApiResponse<DocumentUpdateResult>,
+    PublicationEnvelope = ApiResponse<PublicationResponse>,
+    PublicationListEnvelope = ApiResponse<Vec<PublicationResponse>>,
+    PublicationTreeEnvelope = ApiResponse<Vec<PublicationDocumentNode>>,
+    PublicationDocumentEnvelope = ApiResponse<PublicationDocument>,
+)]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub data: T,
+    pub message: Option<String>,
+}
+
+/// 删除等没有返回数据的操作，data 为 null
+#[derive(Serialize, ToSchema)]
+pub struct EmptyResponse {
+    pub success: bool,
+    pub message: Option<String>,
+}
+
+/// 错误响应，写入冲突（409）时 current 为服务端的当前版本。
+/// message 按 Accept-Language 本地化，error 与 message 相同
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// 稳定的错误码，如 NOT_FOUND、FORBIDDEN
+    pub code: String,
+    pub message: String,
+    pub error: String,
+    #[schema(value_type = Option<Object>)]
+    pub current: Option<serde_json::Value>,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Rainbow-Docs API", description = "Rainbow-Docs 文档系统接口"),
+    paths(
+        spaces::list_spaces,
+        spaces::create_space,
+        spaces::get_space,
+        spaces::update_space,
+        spaces::delete_space,
+        documents::list_documents,
+        documents::create_document,
+        documents::get_document,
+        documents::update_document,
+        documents::delete_document,
+        documents::get_document_by_id,
+        search::search_documents,
+        search::search_suggestions,
+        search::search_within_space,
+        publication::publish_space,
+        publication::list_publications,
+        publication::update_publication,
+        publication::delete_publication,
+        publication::get_publication_tree,
+        publication::get_publication_document,
+    ),
+    components(schemas(
+        SpaceEnvelope,
+        SpaceListEnvelope,
+        DocumentEnvelope,
+        DocumentListEnvelope,
+        DocumentUpdateEnvelope,
+        PublicationEnvelope,
+        PublicationListEnvelope,
+        PublicationTreeEnvelope,
+        PublicationDocumentEnvelope,
+        EmptyResponse,
+        ErrorResponse,
+        SpaceResponse,
+        SpaceListResponse,
+        SpaceSettings,
+        SpaceUploadSettings,
+        NavigationSettings,
+        CustomLink,
+        SpaceStats,
+        CreateSpaceRequest,
+        UpdateSpaceRequest,
+        Document,
+        DocumentMetadata,
+        SeoMetadata,
+        DocumentListResponse,
+        DocumentListItem,
+        DocumentUpdateResult,
+        CreateDocumentRequest,
+        UpdateDocumentRequest,
+        TagMatch,
+        SearchResponse,
+        SearchResult,
+        SearchHighlight,
+        search::SuggestResponse,
+        PublicationResponse,
+        PublicationDocumentNode,
+        PublicationDocument,
+        CreatePublicationRequest,
+        UpdatePublicationRequest,
+    )),
+    modifiers(&SecurityAddon),
+    tags(
+        (name = "spaces", description = "空间管理"),
+        (name = "documents", description = "文档管理"),
+        (name = "search", description = "全文搜索"),
+        (name = "publications", description = "空间发布"),
+    )
+)]
+pub struct ApiDoc;
+
+/// 注册 Bearer 认证方式，需要登录的接口通过 security(("bearer_auth" = [])) 引用
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
+
+/// GET /api/docs/openapi.json 返回生成的规范，/api/docs/swagger 提供 Swagger UI
+pub fn router() -> Router<Arc<AppState>> {
+    SwaggerUi::new(SWAGGER_UI_PATH).url(OPENAPI_JSON_PATH, ApiDoc::openapi()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_generated_spec_documents_core_endpoints() {
+        let json = ApiDoc::openapi().to_json().unwrap();
+        let spec: Value = serde_json::from_str(&json).unwrap();
+        let paths = spec["paths"].as_object().unwrap();
+
+        for (path, method) in [
+            ("/api/docs/spaces", "get"),
+            ("/api/docs/spaces", "post"),
+            ("/api/docs/spaces/{slug}", "put"),
+            ("/api/docs/documents/{space_slug}", "get"),
+            ("/api/docs/documents/{space_slug}/{doc_slug}", "delete"),
+            ("/api/docs/documents/id/{doc_id}", "get"),
+            ("/api/docs/search", "get"),
+            ("/api/docs/publications/spaces/{space_id}/publish", "post"),
+            ("/api/docs/publications/p/{slug}/docs/{doc_slug}", "get"),
+        ] {
+            assert!(paths.get(path).and_then(|item| item.get(method)).is_some(), "{} {} is not documented", method, path);
+        }
+
+        let create_space = &paths["/api/docs/spaces"]["post"];
+        assert_eq!(
+            create_space["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/CreateSpaceRequest"
+        );
+        assert_eq!(
+            create_space["responses"]["403"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ErrorResponse"
+        );
+        assert!(spec["components"]["securitySchemes"]["bearer_auth"].is_object());
+    }
+}
@@ -2,29 +2,32 @@ use crate::{
     AppState,
     error::{AppError, Result},
     models::publication::*,
-    services::auth::User,
+    services::{auth::User, publication::{visitor_hash, ACCESS_TOKEN_HEADER}},
+    utils::{feed::FeedFormat, http_cache, redact},
 };
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
     routing::{get, post, put, delete},
     Router,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::sync::Arc;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
 /// 发布相关的路由
 pub fn router() -> Router<Arc<AppState>> {
-    Router::new()
+    let router = Router::new()
         // 管理端点（需要认证）
         .route("/spaces/:space_id/publish", post(publish_space))
         .route("/spaces/:space_id/publications", get(list_publications))
         .route("/publications/:publication_id", put(update_publication).delete(delete_publication))
         .route("/publications/:publication_id/republish", post(republish))
         .route("/publications/:publication_id/unpublish", post(unpublish))
+        .route("/publications/:publication_id/domain", put(set_custom_domain))
+        .route("/cache/stats", get(get_cache_stats))
         
         // 预览端点（需要认证）
         .route("/publications/:publication_id", get(get_publication_preview))
@@ -32,13 +35,41 @@ pub fn router() -> Router<Arc<AppState>> {
         .route("/publications/:publication_id/docs/:doc_slug", get(get_publication_document_preview))
         
         // 公开访问端点（无需认证）
+        .route("/host", get(get_publication_by_host))
         .route("/p/:slug", get(get_publication))
+        .route("/p/:slug/access", post(request_publication_access))
         .route("/p/:slug/tree", get(get_publication_tree))
         .route("/p/:slug/docs/:doc_slug", get(get_publication_document))
+        .route("/p/:slug/docs/:doc_slug/seo", get(get_publication_document_seo))
+        .route("/p/:slug/feed.xml", get(get_publication_feed))
+        .route("/p/:slug/sitemap.xml", get(get_publication_sitemap))
+        .route("/shared/:slug", get(get_shared_document));
+
+    // PDF 导出依赖 wkhtmltopdf，只在启用 pdf-export 特性编译时提供
+    #[cfg(feature = "pdf-export")]
+    let router = router.route("/publications/:publication_id/export", get(export_publication));
+
+    router
 }
 
 /// 发布空间
 /// POST /api/docs/publications/spaces/:space_id/publish
+#[utoipa::path(
+    post,
+    path = "/api/docs/publications/spaces/{space_id}/publish",
+    tag = "publications",
+    params(("space_id" = String, Path, description = "空间 ID，可带 space: 前缀")),
+    request_body = CreatePublicationRequest,
+    responses(
+        (status = 200, description = "发布成功", body = PublicationEnvelope),
+        (status = 422, description = "参数校验失败", body = ErrorResponse),
+        (status = 401, description = "未登录", body = ErrorResponse),
+        (status = 403, description = "缺少 spaces.manage 权限", body = ErrorResponse),
+        (status = 404, description = "空间不存在", body = ErrorResponse),
+        (status = 409, description = "发布 slug 已被使用", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn publish_space(
     State(app_state): State<Arc<AppState>>,
     Path(space_id): Path<String>,
@@ -61,6 +92,9 @@ async fn publish_space(
     if !app_state.space_member_service.check_permission(&space.id, &user.id, "spaces.manage").await? {
         return Err(AppError::Authorization("Only space owners and admins can publish".to_string()));
     }
+    if !space.settings.permits_custom_js(request.custom_js.as_deref()) {
+        return Err(AppError::Authorization("Custom JavaScript is not enabled for this space".to_string()));
+    }
 
     // 创建发布
     let result = app_state.publication_service.create_publication(
@@ -69,7 +103,7 @@ async fn publish_space(
         request,
     ).await?;
 
-    info!("User {} published space {} as {}", user.id, space_id, result.slug);
+    info!("User {} published space {} as {}", redact::user_id(&user.id), space_id, result.slug);
 
     Ok(Json(json!({
         "success": true,
@@ -80,17 +114,33 @@ async fn publish_space(
 
 /// 获取空间的发布列表
 /// GET /api/docs/publications/spaces/:space_id/publications
+#[utoipa::path(
+    get,
+    path = "/api/docs/publications/spaces/{space_id}/publications",
+    tag = "publications",
+    params(
+        ("space_id" = String, Path, description = "空间 ID，可带 space: 前缀"),
+        ("include_inactive" = Option<bool>, Query, description = "是否包含已取消发布的记录"),
+    ),
+    responses(
+        (status = 200, description = "发布列表", body = PublicationListEnvelope),
+        (status = 401, description = "未登录", body = ErrorResponse),
+        (status = 403, description = "无权访问该空间", body = ErrorResponse),
+        (status = 404, description = "空间不存在", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn list_publications(
     State(app_state): State<Arc<AppState>>,
     Path(space_id): Path<String>,
     Query(params): Query<ListPublicationsQuery>,
     user: User,
 ) -> Result<Json<Value>> {
-    info!("list_publications called with space_id: {}", space_id);
+    debug!("list_publications called with space_id: {}", space_id);
     
     // 处理space_id格式 - 移除"space:"前缀如果存在
     let clean_space_id = space_id.strip_prefix("space:").unwrap_or(&space_id);
-    info!("clean_space_id: {}", clean_space_id);
+    debug!("clean_space_id: {}", clean_space_id);
     
     // 检查用户是否有权限查看此空间
     let space = app_state.space_service.get_space_by_id(clean_space_id, Some(&user)).await?;
@@ -111,6 +161,21 @@ async fn list_publications(
 
 /// 更新发布
 /// PUT /api/docs/publications/publications/:publication_id
+#[utoipa::path(
+    put,
+    path = "/api/docs/publications/publications/{publication_id}",
+    tag = "publications",
+    params(("publication_id" = String, Path, description = "发布 ID")),
+    request_body = UpdatePublicationRequest,
+    responses(
+        (status = 200, description = "更新成功", body = PublicationEnvelope),
+        (status = 422, description = "参数校验失败", body = ErrorResponse),
+        (status = 401, description = "未登录", body = ErrorResponse),
+        (status = 403, description = "缺少 spaces.manage 权限", body = ErrorResponse),
+        (status = 404, description = "发布不存在", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn update_publication(
     State(app_state): State<Arc<AppState>>,
     Path(publication_id): Path<String>,
@@ -124,6 +189,13 @@ async fn update_publication(
     if !app_state.space_member_service.check_permission(&publication.space_id, &user.id, "spaces.manage").await? {
         return Err(AppError::Authorization("Only space owners and admins can update publications".to_string()));
     }
+    if request.custom_js.is_some() {
+        let space_id = publication.space_id.strip_prefix("space:").unwrap_or(&publication.space_id);
+        let space = app_state.space_service.get_space_by_id(space_id, Some(&user)).await?;
+        if !space.settings.permits_custom_js(request.custom_js.as_deref()) {
+            return Err(AppError::Authorization("Custom JavaScript is not enabled for this space".to_string()));
+        }
+    }
 
     let result = app_state.publication_service.update_publication(
         &publication_id,
@@ -131,7 +203,7 @@ async fn update_publication(
         request,
     ).await?;
 
-    info!("User {} updated publication {}", user.id, publication_id);
+    info!("User {} updated publication {}", redact::user_id(&user.id), publication_id);
 
     Ok(Json(json!({
         "success": true,
@@ -140,6 +212,33 @@ async fn update_publication(
     })))
 }
 
+/// 设置发布的自定义域名
+/// PUT /api/docs/publications/publications/:publication_id/domain
+async fn set_custom_domain(
+    State(app_state): State<Arc<AppState>>,
+    Path(publication_id): Path<String>,
+    user: User,
+    Json(request): Json<SetCustomDomainRequest>,
+) -> Result<Json<Value>> {
+    let publication = app_state.publication_service.get_publication_by_id(&publication_id).await?;
+
+    if !app_state.space_member_service.check_permission(&publication.space_id, &user.id, "spaces.manage").await? {
+        return Err(AppError::Authorization("Only space owners and admins can change the publication domain".to_string()));
+    }
+
+    let result = app_state.publication_service
+        .set_custom_domain(&publication_id, request.domain.as_deref())
+        .await?;
+
+    info!("User {} set custom domain of publication {} to {:?}", redact::user_id(&user.id), publication_id, result.custom_domain);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": result,
+        "message": "Custom domain updated successfully"
+    })))
+}
+
 /// 重新发布（更新内容）
 /// POST /api/docs/publications/publications/:publication_id/republish
 async fn republish(
@@ -162,7 +261,7 @@ async fn republish(
         request.change_summary,
     ).await?;
 
-    info!("User {} republished {} (v{})", user.id, result.slug, result.version);
+    info!("User {} republished {} (v{})", redact::user_id(&user.id), result.slug, result.version);
 
     Ok(Json(json!({
         "success": true,
@@ -188,7 +287,7 @@ async fn unpublish(
 
     app_state.publication_service.unpublish(&publication_id).await?;
 
-    info!("User {} unpublished publication {}", user.id, publication_id);
+    info!("User {} unpublished publication {}", redact::user_id(&user.id), publication_id);
 
     Ok(Json(json!({
         "success": true,
@@ -199,6 +298,19 @@ async fn unpublish(
 
 /// 删除发布
 /// DELETE /api/docs/publications/publications/:publication_id
+#[utoipa::path(
+    delete,
+    path = "/api/docs/publications/publications/{publication_id}",
+    tag = "publications",
+    params(("publication_id" = String, Path, description = "发布 ID")),
+    responses(
+        (status = 200, description = "删除成功", body = EmptyResponse),
+        (status = 401, description = "未登录", body = ErrorResponse),
+        (status = 403, description = "缺少 spaces.manage 权限", body = ErrorResponse),
+        (status = 404, description = "发布不存在", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn delete_publication(
     State(app_state): State<Arc<AppState>>,
     Path(publication_id): Path<String>,
@@ -215,7 +327,7 @@ async fn delete_publication(
 
     app_state.publication_service.delete_publication(&publication_id).await?;
 
-    info!("User {} deleted publication {}", user.id, publication_id);
+    info!("User {} deleted publication {}", redact::user_id(&user.id), publication_id);
 
     Ok(Json(json!({
         "success": true,
@@ -292,6 +404,56 @@ async fn get_publication_tree_preview(
     })))
 }
 
+#[cfg(feature = "pdf-export")]
+#[derive(Debug, Deserialize)]
+pub struct ExportPublicationQuery {
+    pub format: Option<String>,
+}
+
+/// 导出整个发布为 PDF
+/// GET /api/docs/publications/publications/:publication_id/export?format=pdf
+#[cfg(feature = "pdf-export")]
+async fn export_publication(
+    State(app_state): State<Arc<AppState>>,
+    Path(publication_id): Path<String>,
+    Query(query): Query<ExportPublicationQuery>,
+    user: User,
+) -> Result<impl IntoResponse> {
+    let format = query.format.unwrap_or_else(|| "pdf".to_string());
+    if format != "pdf" {
+        return Err(AppError::Validation(format!("Unsupported export format: {}", format)));
+    }
+    if !app_state.config.features.enable_pdf_export {
+        return Err(AppError::Validation("PDF export is disabled".to_string()));
+    }
+
+    let publication = app_state.publication_service.get_publication_by_id(&publication_id).await?;
+
+    // 检查用户权限
+    if !app_state.space_member_service.can_access_space(&publication.space_id, Some(&user.id)).await? {
+        return Err(AppError::Authorization("Access denied to this publication".to_string()));
+    }
+
+    let space_id = publication.space_id.strip_prefix("space:").unwrap_or(&publication.space_id);
+    let space = app_state.space_service.get_space_by_id(space_id, Some(&user)).await?;
+
+    let pdf = app_state.pdf_export_service
+        .render_publication(&publication, &app_state.publication_service, &space.settings.allowed_html_tags)
+        .await?;
+
+    info!("User {} exported publication {} as PDF", redact::user_id(&user.id), publication_id);
+
+    let headers = [
+        (header::CONTENT_TYPE, "application/pdf".to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.pdf\"", publication.slug),
+        ),
+    ];
+
+    Ok((headers, pdf))
+}
+
 /// 获取发布的文档内容（预览模式）
 /// GET /api/docs/publications/publications/:publication_id/docs/:doc_slug
 async fn get_publication_document_preview(
@@ -325,9 +487,38 @@ async fn get_publication(
     Path(slug): Path<String>,
 ) -> Result<Json<Value>> {
     let publication = app_state.publication_service.get_publication_by_slug(&slug).await?;
-    
-    // 构建公开的发布信息
-    let public_info = json!({
+
+    Ok(Json(json!({
+        "success": true,
+        "data": public_publication_info(&publication),
+        "message": "Publication retrieved successfully"
+    })))
+}
+
+/// 通过自定义域名获取发布详情（公开访问）
+/// GET /api/docs/publications/host
+///
+/// 前端部署在自定义域名下时，根据请求的 Host（或代理转发的 X-Forwarded-Host）找到对应的发布
+async fn get_publication_by_host(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Value>> {
+    let host = header_str(&headers, "x-forwarded-host")
+        .or_else(|| header_str(&headers, header::HOST.as_str()))
+        .ok_or_else(|| AppError::Validation("Host header is missing".to_string()))?;
+
+    let publication = app_state.publication_service.get_publication_by_host(host).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": public_publication_info(&publication),
+        "message": "Publication retrieved successfully"
+    })))
+}
+
+/// 构建公开的发布信息
+fn public_publication_info(publication: &SpacePublication) -> Value {
+    json!({
         "slug": publication.slug,
         "title": publication.title,
         "description": publication.description,
@@ -340,67 +531,240 @@ async fn get_publication(
         "enable_comments": publication.enable_comments,
         "custom_css": publication.custom_css,
         "custom_js": publication.custom_js,
+        "custom_domain": publication.custom_domain,
+        "is_password_protected": publication.is_password_protected(),
         "seo_title": publication.seo_title,
         "seo_description": publication.seo_description,
         "seo_keywords": publication.seo_keywords,
-    });
+    })
+}
+
+/// 文档树缓存的命中情况，仅管理员可见，用于排查缓存问题
+/// GET /api/docs/publications/cache/stats
+async fn get_cache_stats(
+    State(app_state): State<Arc<AppState>>,
+    user: User,
+) -> Result<Json<Value>> {
+    if !user.permissions.contains(&"docs.admin".to_string()) {
+        return Err(AppError::Authorization("Permission denied: docs.admin required".to_string()));
+    }
+
+    let stats = app_state.publication_service.tree_cache_stats().await;
 
     Ok(Json(json!({
         "success": true,
-        "data": public_info,
-        "message": "Publication retrieved successfully"
+        "data": { "tree": stats },
+        "message": "Cache stats retrieved successfully"
+    })))
+}
+
+/// 输入访问密码，换取阅读受保护发布的访问令牌（公开访问）
+/// POST /api/docs/publications/p/:slug/access
+async fn request_publication_access(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<PublicationAccessRequest>,
+) -> Result<Json<Value>> {
+    let access = app_state.publication_service
+        .verify_publication_password(&slug, &request.password, client_ip(&headers))
+        .await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": access,
+        "message": "Publication access granted"
     })))
 }
 
 /// 获取发布的文档树（公开访问）
 /// GET /api/docs/publications/p/:slug/tree
+#[utoipa::path(
+    get,
+    path = "/api/docs/publications/p/{slug}/tree",
+    tag = "publications",
+    params(("slug" = String, Path, description = "发布 slug")),
+    responses(
+        (status = 200, description = "发布的文档树", body = PublicationTreeEnvelope),
+        (status = 401, description = "发布设置了访问密码，缺少或无效的访问令牌", body = ErrorResponse),
+        (status = 404, description = "发布不存在", body = ErrorResponse),
+    )
+)]
 async fn get_publication_tree(
     State(app_state): State<Arc<AppState>>,
     Path(slug): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Json<Value>> {
     // 先获取发布信息
     let publication = app_state.publication_service.get_publication_by_slug(&slug).await?;
-    
-    if let Some(pub_id) = &publication.id {
-        let tree = app_state.publication_service.get_publication_tree(pub_id).await?;
-        
-        Ok(Json(json!({
-            "success": true,
-            "data": tree,
-            "message": "Document tree retrieved successfully"
-        })))
-    } else {
-        Err(AppError::Internal(anyhow::anyhow!("Publication ID is missing")))
-    }
+    app_state.publication_service
+        .authorize_publication_access(&publication, header_str(&headers, ACCESS_TOKEN_HEADER))?;
+    let tree = app_state.publication_service.get_cached_publication_tree(&publication).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": tree.as_slice(),
+        "message": "Document tree retrieved successfully"
+    })))
 }
 
 /// 获取发布的文档内容（公开访问）
 /// GET /api/docs/publications/p/:slug/docs/:doc_slug
+#[utoipa::path(
+    get,
+    path = "/api/docs/publications/p/{slug}/docs/{doc_slug}",
+    tag = "publications",
+    params(
+        ("slug" = String, Path, description = "发布 slug"),
+        ("doc_slug" = String, Path, description = "文档 slug"),
+    ),
+    responses(
+        (status = 200, description = "发布的文档快照，带 ETag 和 Last-Modified", body = PublicationDocumentEnvelope),
+        (status = 304, description = "If-None-Match 或 If-Modified-Since 命中，内容未变化"),
+        (status = 401, description = "发布设置了访问密码，缺少或无效的访问令牌", body = ErrorResponse),
+        (status = 404, description = "发布或文档不存在", body = ErrorResponse),
+    )
+)]
 async fn get_publication_document(
     State(app_state): State<Arc<AppState>>,
     Path((slug, doc_slug)): Path<(String, String)>,
-) -> Result<Json<Value>> {
+    headers: HeaderMap,
+) -> Result<Response> {
     // 先获取发布信息
     let publication = app_state.publication_service.get_publication_by_slug(&slug).await?;
+    app_state.publication_service
+        .authorize_publication_access(&publication, header_str(&headers, ACCESS_TOKEN_HEADER))?;
     
     if let Some(pub_id) = &publication.id {
         let document = app_state.publication_service.get_publication_document(pub_id, &doc_slug).await?;
         
-        // 记录访问统计
+        // 记录访问统计（失败不影响文档读取）
         if let Some(doc_id) = &document.id {
-            let _ = app_state.publication_service.track_document_view(pub_id, doc_id).await;
+            let visitor = visitor_hash(pub_id, client_ip(&headers), header_str(&headers, header::USER_AGENT.as_str()));
+            if let Err(e) = app_state.publication_service
+                .track_document_view(pub_id, doc_id, &document.title, visitor.as_deref())
+                .await
+            {
+                warn!("Failed to track view for publication {}: {}", pub_id, e);
+            }
         }
         
-        Ok(Json(json!({
-            "success": true,
-            "data": document,
-            "message": "Document retrieved successfully"
-        })))
+        // 快照在发布时生成，用作 Last-Modified；内容和版本号共同决定 ETag
+        let last_modified = document.created_at.or(publication.published_at);
+        http_cache::conditional_json(
+            &headers,
+            publication.version,
+            last_modified,
+            &json!({
+                "success": true,
+                "data": document,
+                "message": "Document retrieved successfully"
+            }),
+        )
     } else {
         Err(AppError::Internal(anyhow::anyhow!("Publication ID is missing")))
     }
 }
 
+/// 通过分享链接访问单个文档
+/// GET /api/docs/publications/shared/:slug
+async fn get_shared_document(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let document = app_state.publication_service.get_shared_document(&slug).await?;
+
+    // 分享内容是不变的快照，用分享时间作为 Last-Modified
+    let shared_at = document.shared_at;
+    http_cache::conditional_json(
+        &headers,
+        1,
+        shared_at,
+        &json!({
+            "success": true,
+            "data": document,
+            "message": "Shared document retrieved successfully"
+        }),
+    )
+}
+
+/// 获取发布文档的 JSON-LD 结构化数据（公开访问），前端直接内联到 <script type="application/ld+json">
+/// GET /api/docs/publications/p/:slug/docs/:doc_slug/seo
+async fn get_publication_document_seo(
+    State(app_state): State<Arc<AppState>>,
+    Path((slug, doc_slug)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let publication = app_state.publication_service.get_publication_by_slug(&slug).await?;
+    app_state.publication_service
+        .authorize_publication_access(&publication, header_str(&headers, ACCESS_TOKEN_HEADER))?;
+
+    let (document, json_ld) = app_state.publication_service
+        .get_document_json_ld(&publication, &doc_slug)
+        .await?;
+
+    http_cache::conditional_json(
+        &headers,
+        publication.version,
+        document.created_at.or(publication.published_at),
+        &json!({
+            "success": true,
+            "data": json_ld,
+            "message": "Structured data retrieved successfully"
+        }),
+    )
+}
+
+/// 发布的订阅源（公开访问），默认 RSS，?format=atom 时输出 Atom
+/// GET /api/docs/publications/p/:slug/feed.xml
+async fn get_publication_feed(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    Query(query): Query<FeedQuery>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let publication = app_state.publication_service.get_publication_by_slug(&slug).await?;
+    app_state.publication_service
+        .authorize_publication_access(&publication, header_str(&headers, ACCESS_TOKEN_HEADER))?;
+
+    let publication_id = publication.id.as_deref()
+        .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Publication ID is missing")))?;
+    let format = query.format.unwrap_or_default();
+    let xml = app_state.publication_service.generate_feed(publication_id, format).await?;
+
+    Ok(([(header::CONTENT_TYPE, format.content_type())], xml).into_response())
+}
+
+/// 发布的 sitemap.xml（公开访问）
+/// GET /api/docs/publications/p/:slug/sitemap.xml
+async fn get_publication_sitemap(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let publication = app_state.publication_service.get_publication_by_slug(&slug).await?;
+    app_state.publication_service
+        .authorize_publication_access(&publication, header_str(&headers, ACCESS_TOKEN_HEADER))?;
+
+    let publication_id = publication.id.as_deref()
+        .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Publication ID is missing")))?;
+    let xml = app_state.publication_service.generate_sitemap(publication_id).await?;
+
+    Ok(([(header::CONTENT_TYPE, "application/xml; charset=utf-8")], xml).into_response())
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// 从代理头中取客户端 IP：优先 X-Forwarded-For 的第一个地址，其次 X-Real-IP
+fn client_ip(headers: &HeaderMap) -> Option<&str> {
+    header_str(headers, "x-forwarded-for")
+        .and_then(|value| value.split(',').next())
+        .or_else(|| header_str(headers, "x-real-ip"))
+}
+
 // ===== 请求结构体 =====
 
 #[derive(Debug, Deserialize)]
@@ -408,6 +772,11 @@ struct ListPublicationsQuery {
     include_inactive: Option<bool>,
 }
 
+#[derive(Debug, Deserialize)]
+struct FeedQuery {
+    format: Option<FeedFormat>,
+}
+
 #[derive(Debug, Deserialize)]
 struct RepublishRequest {
     change_summary: Option<String>,
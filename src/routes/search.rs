@@ -7,31 +7,44 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    error::ApiError,
+    error::AppError,
     models::search::{SearchRequest, SearchResponse},
     services::{auth::AuthService, search::SearchService},
 };
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SearchQuery {
     pub q: String,
     pub space_id: Option<String>,
     pub tags: Option<String>, // 逗号分隔的标签
     pub author_id: Option<String>,
     pub page: Option<i64>,
-    pub per_page: Option<i64>,
+    /// 兼容旧参数名 per_page
+    #[serde(alias = "per_page")]
+    pub limit: Option<i64>,
     pub sort: Option<String>,
+    /// "accessible" 表示搜索用户能访问的所有空间
+    pub scope: Option<String>,
+    /// "keyword"（默认）或 "hybrid"，hybrid 同时做关键词和向量检索并融合排名
+    pub mode: Option<String>,
+    /// hybrid 模式的查询向量，逗号分隔，需与文档向量使用同一嵌入模型生成
+    pub vector: Option<String>,
+    /// hybrid 模式中向量排名的权重（0~1），默认取 HYBRID_SEARCH_VECTOR_WEIGHT
+    pub vector_weight: Option<f64>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SuggestQuery {
     pub q: String,
     pub limit: Option<i64>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct SuggestResponse {
     pub suggestions: Vec<String>,
     pub query: String,
@@ -43,11 +56,24 @@ pub struct ReindexResponse {
     pub indexed_count: i64,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/docs/search",
+    tag = "search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "搜索结果", body = SearchResponse),
+        (status = 422, description = "scope、mode 或 hybrid 参数无效", body = ErrorResponse),
+        (status = 401, description = "未登录", body = ErrorResponse),
+        (status = 403, description = "缺少 docs.read 权限", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn search_documents(
     Query(query): Query<SearchQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<SearchResponse>, ApiError> {
+) -> Result<Json<SearchResponse>, AppError> {
     let search_service = &app_state.search_service;
     let auth_service = &app_state.auth_service;
     // 检查基本搜索权限
@@ -73,22 +99,69 @@ pub async fn search_documents(
         tags,
         author_id: query.author_id,
         page: query.page,
-        per_page: query.per_page,
+        limit: query.limit,
         sort_by,
     };
 
-    let response = search_service
-        .search(&user_id, search_request)
-        .await?;
+    let all_accessible = match query.scope.as_deref() {
+        Some("accessible") => true,
+        Some(other) if other != "default" => {
+            return Err(AppError::Validation(format!("Unknown search scope: {}", other)));
+        }
+        _ => false,
+    };
+
+    let response = match query.mode.as_deref() {
+        Some("hybrid") => {
+            let query_vector = parse_query_vector(query.vector.as_deref())?;
+            search_service
+                .hybrid_search(&user_id, search_request, query_vector, query.vector_weight, all_accessible)
+                .await?
+        }
+        Some(other) if other != "keyword" => {
+            return Err(AppError::Validation(format!("Unknown search mode: {}", other)));
+        }
+        _ if all_accessible => search_service
+            .search_all_accessible(&user_id, search_request)
+            .await?,
+        _ => search_service
+            .search(&user_id, search_request)
+            .await?,
+    };
 
     Ok(Json(response))
 }
 
+/// 解析逗号分隔的查询向量
+fn parse_query_vector(value: Option<&str>) -> Result<Vec<f32>, AppError> {
+    let value = value
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| AppError::Validation("vector is required when mode=hybrid".to_string()))?;
+
+    value
+        .split(',')
+        .map(|component| component.trim().parse::<f32>().ok().filter(|x| x.is_finite()))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| AppError::Validation("vector must be a comma-separated list of numbers".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/docs/search/suggest",
+    tag = "search",
+    params(SuggestQuery),
+    responses(
+        (status = 200, description = "搜索建议", body = SuggestResponse),
+        (status = 401, description = "未登录", body = ErrorResponse),
+        (status = 403, description = "缺少 docs.read 权限", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn search_suggestions(
     Query(query): Query<SuggestQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<SuggestResponse>, ApiError> {
+) -> Result<Json<SuggestResponse>, AppError> {
     let search_service = &app_state.search_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -109,7 +182,7 @@ pub async fn search_suggestions(
 pub async fn reindex_documents(
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<ReindexResponse>, ApiError> {
+) -> Result<Json<ReindexResponse>, AppError> {
     let search_service = &app_state.search_service;
     let auth_service = &app_state.auth_service;
     // 只有文档管理员可以重建索引
@@ -125,12 +198,27 @@ pub async fn reindex_documents(
     }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/docs/search/spaces/{space_id}",
+    tag = "search",
+    params(
+        ("space_id" = String, Path, description = "空间 ID"),
+        SearchQuery,
+    ),
+    responses(
+        (status = 200, description = "搜索结果", body = SearchResponse),
+        (status = 401, description = "未登录", body = ErrorResponse),
+        (status = 403, description = "无权访问该空间", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn search_within_space(
     axum::extract::Path(space_id): axum::extract::Path<String>,
     Query(mut query): Query<SearchQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<SearchResponse>, ApiError> {
+) -> Result<Json<SearchResponse>, AppError> {
     let search_service = &app_state.search_service;
     let auth_service = &app_state.auth_service;
     // 检查空间访问权限  
@@ -157,7 +245,7 @@ pub async fn search_within_space(
         tags,
         author_id: query.author_id,
         page: query.page,
-        per_page: query.per_page,
+        limit: query.limit,
         sort_by,
     };
 
@@ -172,7 +260,7 @@ pub async fn search_by_tags(
     Query(query): Query<SearchQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<SearchResponse>, ApiError> {
+) -> Result<Json<SearchResponse>, AppError> {
     let search_service = &app_state.search_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -181,7 +269,7 @@ pub async fn search_by_tags(
 
     // 确保有标签查询
     if query.tags.is_none() || query.tags.as_ref().unwrap().is_empty() {
-        return Err(ApiError::BadRequest("Tags parameter is required".to_string()));
+        return Err(AppError::Validation("Tags parameter is required".to_string()));
     }
 
     let tags = query.tags
@@ -193,7 +281,7 @@ pub async fn search_by_tags(
         tags,
         author_id: query.author_id,
         page: query.page,
-        per_page: query.per_page,
+        limit: query.limit,
         sort_by: Some(crate::models::search::SearchSortBy::Relevance),
     };
 
@@ -211,4 +299,96 @@ pub fn router() -> Router<Arc<crate::AppState>> {
         .route("/reindex", post(reindex_documents))
         .route("/spaces/:space_id", get(search_within_space))
         .route("/tags", get(search_by_tags))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::space::CreateSpaceRequest;
+    use crate::models::space_member::{AcceptInvitationRequest, InviteMemberRequest, MemberRole};
+    use crate::services::{auth::User, vector::{VectorData, VectorService}};
+    use crate::state::{test_support::{response_json, test_app, test_app_state}, AppState};
+    use axum::{body::Body, http::{Request, StatusCode}};
+    use tower::ServiceExt;
+
+    fn test_user(name: &str, run: &str) -> User {
+        User {
+            id: format!("{}-{}", name, run),
+            email: format!("{}@example.com", name),
+            roles: vec!["user".to_string()],
+            permissions: vec![],
+            profile: None,
+            api_key: None,
+        }
+    }
+
+    async fn hybrid_search(state: &Arc<AppState>, user_id: &str, keyword: &str, space_id: &str, vector: &[f32]) -> (StatusCode, serde_json::Value) {
+        let vector: Vec<String> = vector.iter().map(|x| x.to_string()).collect();
+        let uri = format!(
+            "/api/docs/search?q={}&space_id={}&scope=accessible&mode=hybrid&vector={}",
+            keyword, space_id, vector.join(",")
+        );
+        let response = test_app("/api/docs/search", router(), state)
+            .layer(Extension(user_id.to_string()))
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        response_json(response).await
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_hybrid_search_returns_keyword_and_vector_hits_to_members_only() {
+        let state = test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let owner = test_user("owner", &run);
+        let member = test_user("member", &run);
+        let outsider = test_user("outsider", &run);
+        let keyword = format!("kw{}", run);
+
+        let space = state.space_service.create_space(CreateSpaceRequest {
+            name: "Private".to_string(),
+            slug: format!("hybrid-{}", run),
+            description: None,
+            avatar_url: None,
+            is_public: Some(false),
+            settings: None,
+        }, &owner).await.unwrap();
+        let invitation = state.space_member_service.invite_member(&space.id, &owner, InviteMemberRequest {
+            email: None,
+            user_id: Some(member.id.clone()),
+            role: MemberRole::Viewer,
+            message: None,
+            expires_in_days: None,
+            max_uses: None,
+        }).await.unwrap();
+        state.space_member_service.accept_invitation(&member.id, AcceptInvitationRequest {
+            invite_token: invitation.invite_token,
+        }).await.unwrap();
+
+        // 一篇只能被关键词命中，一篇只能被向量命中
+        let request = serde_json::from_value(serde_json::json!({ "title": format!("Notes {}", keyword), "slug": "notes", "content": keyword })).unwrap();
+        let keyword_hit = state.document_service.create_document(&space.id, &owner.id, request).await.unwrap();
+        let request = serde_json::from_value(serde_json::json!({ "title": "Architecture", "slug": "architecture", "content": "Overview" })).unwrap();
+        let vector_hit = state.document_service.create_document(&space.id, &owner.id, request).await.unwrap();
+        let embedding = vec![0.2, 0.4, 0.9];
+        let vector_doc_id = vector_hit.id.as_deref().unwrap().trim_start_matches("document:");
+        VectorService::new(state.db.clone())
+            .store_vector(vector_doc_id, VectorData { embedding: embedding.clone(), model: "test".to_string(), dimension: 3, metadata: None })
+            .await
+            .unwrap();
+
+        let (status, body) = hybrid_search(&state, &member.id, &keyword, &space.id, &embedding).await;
+        assert_eq!(status, StatusCode::OK);
+        let mut found: Vec<&str> = body["results"].as_array().unwrap().iter().map(|r| r["document_id"].as_str().unwrap()).collect();
+        found.sort();
+        let mut expected = vec![keyword_hit.id.as_deref().unwrap(), vector_hit.id.as_deref().unwrap()];
+        expected.sort();
+        assert_eq!(found, expected);
+
+        // 非成员看不到私有空间中的任何结果
+        let (status, body) = hybrid_search(&state, &outsider.id, &keyword, &space.id, &embedding).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total"], 0);
+        assert!(body["results"].as_array().unwrap().is_empty());
+    }
+}
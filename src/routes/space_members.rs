@@ -1,6 +1,7 @@
 use crate::{AppState, error::{AppError, Result}};
 use crate::models::space_member::{InviteMemberRequest, UpdateMemberRequest, AcceptInvitationRequest};
 use crate::services::auth::User;
+use crate::utils::redact;
 use axum::{
     extract::{Path, State},
     http::StatusCode,
@@ -52,7 +53,7 @@ async fn invite_member(
     
     let invitation = app_state.space_member_service.invite_member(&space.id, &user, request).await?;
 
-    info!("User {} invited new member to space: {}", user.id, space_slug);
+    info!("User {} invited new member to space: {}", redact::user_id(&user.id), space_slug);
 
     Ok(Json(json!({
         "success": true,
@@ -70,7 +71,7 @@ async fn accept_invitation(
 ) -> Result<Json<Value>> {
     let member = app_state.space_member_service.accept_invitation(&user.id, request).await?;
 
-    info!("User {} accepted invitation to space", user.id);
+    info!("User {} accepted invitation to space", redact::user_id(&user.id));
 
     Ok(Json(json!({
         "success": true,
@@ -92,7 +93,7 @@ async fn update_member(
     
     let updated_member = app_state.space_member_service.update_member(&space.id, &member_user_id, &user, request).await?;
 
-    info!("User {} updated member {} in space: {}", user.id, member_user_id, space_slug);
+    info!("User {} updated member {} in space: {}", redact::user_id(&user.id), redact::user_id(&member_user_id), space_slug);
 
     Ok(Json(json!({
         "success": true,
@@ -113,7 +114,7 @@ async fn remove_member(
     
     app_state.space_member_service.remove_member(&space.id, &member_user_id, &user).await?;
 
-    info!("User {} removed member {} from space: {}", user.id, member_user_id, space_slug);
+    info!("User {} removed member {} from space: {}", redact::user_id(&user.id), redact::user_id(&member_user_id), space_slug);
 
     Ok(Json(json!({
         "success": true,
@@ -135,6 +136,7 @@ mod tests {
             role: MemberRole::Member,
             message: Some("Welcome to our space!".to_string()),
             expires_in_days: Some(7),
+            max_uses: Some(3),
         };
         
         assert!(valid_request.validate().is_ok());
@@ -145,8 +147,20 @@ mod tests {
             role: MemberRole::Member,
             message: None,
             expires_in_days: Some(7),
+            max_uses: None,
         };
         
         assert!(invalid_request.validate().is_err());
+
+        let negative_uses = InviteMemberRequest {
+            email: None,
+            user_id: None,
+            role: MemberRole::Member,
+            message: None,
+            expires_in_days: Some(7),
+            max_uses: Some(-1),
+        };
+
+        assert!(negative_uses.validate().is_err());
     }
 }
\ No newline at end of file
@@ -1,13 +1,17 @@
 use crate::{AppState, error::{AppError, Result}};
-use crate::models::space::{CreateSpaceRequest, UpdateSpaceRequest, SpaceListQuery};
+use crate::models::template::CreateTemplateRequest;
+use crate::models::space_export::SpaceBundle;
+use crate::models::space::{CreateSpaceRequest, UpdateSpaceRequest, SpaceListQuery, SpaceActivityQuery, TransferOwnershipRequest};
 use crate::services::auth::{User, OptionalUser};
+use crate::utils::{pagination::PageRequest, redact};
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json},
     routing::{get, post, put, delete},
     Router,
 };
+use serde::Deserialize;
 use serde_json::{json, Value};
 use std::sync::Arc;
 use tracing::{info, warn};
@@ -19,10 +23,29 @@ pub fn router() -> Router<Arc<AppState>> {
         .route("/create/stats", get(handle_legacy_create_stats)) // Legacy frontend support
         .route("/:slug", get(get_space).put(update_space).delete(delete_space))
         .route("/:slug/stats", get(get_space_stats))
+        .route("/:slug/activity", get(get_space_activity))
+        .route("/:slug/transfer-ownership", post(transfer_ownership))
+        .route("/:slug/archive", post(archive_space).delete(unarchive_space))
+        .route("/:slug/templates", get(list_templates).post(create_template))
+        .route("/:slug/export", get(export_space))
+        .route("/import", post(import_space).layer(DefaultBodyLimit::max(MAX_IMPORT_BUNDLE_BYTES)))
 }
 
+/// 导入包的最大字节数，空间的全部文档都在一个 JSON 请求体中
+const MAX_IMPORT_BUNDLE_BYTES: usize = 50 * 1024 * 1024;
+
 /// 获取空间列表
 /// GET /api/spaces
+#[utoipa::path(
+    get,
+    path = "/api/docs/spaces",
+    tag = "spaces",
+    params(SpaceListQuery),
+    responses(
+        (status = 200, description = "空间列表", body = SpaceListEnvelope),
+        (status = 422, description = "查询参数无效", body = ErrorResponse),
+    )
+)]
 async fn list_spaces(
     State(app_state): State<Arc<AppState>>,
     Query(query): Query<SpaceListQuery>,
@@ -39,6 +62,20 @@ async fn list_spaces(
 
 /// 创建新空间
 /// POST /api/spaces
+#[utoipa::path(
+    post,
+    path = "/api/docs/spaces",
+    tag = "spaces",
+    request_body = CreateSpaceRequest,
+    responses(
+        (status = 200, description = "创建成功", body = SpaceEnvelope),
+        (status = 422, description = "参数校验失败", body = ErrorResponse),
+        (status = 401, description = "未登录", body = ErrorResponse),
+        (status = 403, description = "缺少 spaces.write 权限", body = ErrorResponse),
+        (status = 409, description = "slug 已被使用", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn create_space(
     State(app_state): State<Arc<AppState>>,
     user: User,
@@ -50,7 +87,7 @@ async fn create_space(
     }
     let result = app_state.space_service.create_space(request, &user).await?;
 
-    info!("User {} created space: {}", user.id, result.slug);
+    info!("User {} created space: {}", redact::user_id(&user.id), result.slug);
 
     Ok(Json(json!({
         "success": true,
@@ -61,6 +98,17 @@ async fn create_space(
 
 /// 获取空间详情
 /// GET /api/spaces/:slug
+#[utoipa::path(
+    get,
+    path = "/api/docs/spaces/{slug}",
+    tag = "spaces",
+    params(("slug" = String, Path, description = "空间 slug")),
+    responses(
+        (status = 200, description = "空间详情", body = SpaceEnvelope),
+        (status = 403, description = "无权访问私有空间", body = ErrorResponse),
+        (status = 404, description = "空间不存在", body = ErrorResponse),
+    )
+)]
 async fn get_space(
     State(app_state): State<Arc<AppState>>,
     Path(slug): Path<String>,
@@ -77,6 +125,21 @@ async fn get_space(
 
 /// 更新空间信息
 /// PUT /api/spaces/:slug
+#[utoipa::path(
+    put,
+    path = "/api/docs/spaces/{slug}",
+    tag = "spaces",
+    params(("slug" = String, Path, description = "空间 slug")),
+    request_body = UpdateSpaceRequest,
+    responses(
+        (status = 200, description = "更新成功", body = SpaceEnvelope),
+        (status = 422, description = "参数校验失败", body = ErrorResponse),
+        (status = 401, description = "未登录", body = ErrorResponse),
+        (status = 403, description = "无权修改该空间", body = ErrorResponse),
+        (status = 404, description = "空间不存在", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn update_space(
     State(app_state): State<Arc<AppState>>,
     Path(slug): Path<String>,
@@ -85,7 +148,7 @@ async fn update_space(
 ) -> Result<Json<Value>> {
     let result = app_state.space_service.update_space(&slug, request, &user).await?;
 
-    info!("User {} updated space: {}", user.id, slug);
+    info!("User {} updated space: {}", redact::user_id(&user.id), slug);
 
     Ok(Json(json!({
         "success": true,
@@ -96,6 +159,19 @@ async fn update_space(
 
 /// 删除空间
 /// DELETE /api/spaces/:slug
+#[utoipa::path(
+    delete,
+    path = "/api/docs/spaces/{slug}",
+    tag = "spaces",
+    params(("slug" = String, Path, description = "空间 slug")),
+    responses(
+        (status = 200, description = "删除成功", body = EmptyResponse),
+        (status = 401, description = "未登录", body = ErrorResponse),
+        (status = 403, description = "只有所有者可以删除空间", body = ErrorResponse),
+        (status = 404, description = "空间不存在", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn delete_space(
     State(app_state): State<Arc<AppState>>,
     Path(slug): Path<String>,
@@ -103,7 +179,7 @@ async fn delete_space(
 ) -> Result<Json<Value>> {
     app_state.space_service.delete_space(&slug, &user).await?;
 
-    info!("User {} deleted space: {}", user.id, slug);
+    info!("User {} deleted space: {}", redact::user_id(&user.id), slug);
 
     Ok(Json(json!({
         "success": true,
@@ -112,6 +188,102 @@ async fn delete_space(
     })))
 }
 
+/// 归档空间（只读）
+/// POST /api/spaces/:slug/archive
+async fn archive_space(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    user: User,
+) -> Result<Json<Value>> {
+    let space = app_state.space_service.archive_space(&slug, &user).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": space,
+        "message": "Space archived successfully"
+    })))
+}
+
+/// 取消归档
+/// DELETE /api/spaces/:slug/archive
+async fn unarchive_space(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    user: User,
+) -> Result<Json<Value>> {
+    let space = app_state.space_service.unarchive_space(&slug, &user).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": space,
+        "message": "Space unarchived successfully"
+    })))
+}
+
+/// 获取空间内的文档模板
+/// GET /api/spaces/:slug/templates
+async fn list_templates(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    user: User,
+) -> Result<Json<Value>> {
+    let space = app_state.space_service.get_space_by_slug(&slug, Some(&user)).await?;
+    if !app_state.space_member_service.can_access_space(&space.id, Some(&user.id)).await? {
+        return Err(AppError::Authorization("Access denied to this space".to_string()));
+    }
+
+    let templates = app_state.template_service.list_templates(&space.id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": templates,
+        "message": "Templates retrieved successfully"
+    })))
+}
+
+/// 创建文档模板
+/// POST /api/spaces/:slug/templates
+async fn create_template(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    user: User,
+    Json(request): Json<CreateTemplateRequest>,
+) -> Result<Json<Value>> {
+    let space = app_state.space_service.get_space_by_slug(&slug, Some(&user)).await?;
+    if !app_state.space_member_service.check_permission(&space.id, &user.id, "docs.write").await? {
+        return Err(AppError::Authorization("Permission denied: docs.write required".to_string()));
+    }
+
+    let template = app_state.template_service.create_template(&space.id, &user.id, request).await?;
+
+    info!("User {} created template {} in space {}", redact::user_id(&user.id), template.name, slug);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": template,
+        "message": "Template created successfully"
+    })))
+}
+
+/// 转让空间所有权
+/// POST /api/spaces/:slug/transfer-ownership
+async fn transfer_ownership(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    user: User,
+    Json(request): Json<TransferOwnershipRequest>,
+) -> Result<Json<Value>> {
+    let space = app_state.space_service.transfer_ownership(&slug, request, &user).await?;
+
+    info!("User {} transferred ownership of space {} to {}", redact::user_id(&user.id), slug, redact::user_id(&space.owner_id));
+
+    Ok(Json(json!({
+        "success": true,
+        "data": space,
+        "message": "Space ownership transferred successfully"
+    })))
+}
+
 /// 获取空间统计信息
 /// GET /api/spaces/:slug/stats
 async fn get_space_stats(
@@ -132,6 +304,110 @@ async fn get_space_stats(
     })))
 }
 
+/// 获取空间动态
+/// GET /api/docs/spaces/:slug/activity?page=1&limit=20
+async fn get_space_activity(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    Query(query): Query<SpaceActivityQuery>,
+    user: User,
+) -> Result<Json<Value>> {
+    let space = app_state.space_service.get_space_by_slug(&slug, Some(&user)).await?;
+
+    if !app_state.space_member_service.can_access_space(&space.id, Some(&user.id)).await? {
+        return Err(AppError::Authorization("Access denied to this space".to_string()));
+    }
+
+    let activity = app_state
+        .space_service
+        .get_space_activity(&space.id, PageRequest::new(query.page, query.limit))
+        .await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": activity,
+        "message": "Space activity retrieved successfully"
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<String>,
+}
+
+/// 导出空间文档。markdown 为 zip 格式的文档树，json 为可重新导入的完整导出包
+/// GET /api/spaces/:slug/export?format=markdown|json
+async fn export_space(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    Query(query): Query<ExportQuery>,
+    user: User,
+) -> Result<impl IntoResponse> {
+    let space = app_state.space_service.get_space_by_slug(&slug, Some(&user)).await?;
+
+    if !app_state.space_member_service.can_access_space(&space.id, Some(&user.id)).await? {
+        return Err(AppError::Authorization("Access denied to this space".to_string()));
+    }
+    if !app_state.space_member_service.check_permission(&space.id, &user.id, "docs.read").await? {
+        return Err(AppError::Authorization("Permission denied: docs.read required".to_string()));
+    }
+
+    let format = query.format.unwrap_or_else(|| "markdown".to_string());
+    let response = match format.as_str() {
+        "markdown" => {
+            let archive = app_state.document_service.export_space_markdown(&space.id, &space.name).await?;
+            let headers = [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.zip\"", space.slug),
+                ),
+            ];
+            (headers, archive).into_response()
+        }
+        "json" => {
+            let bundle = app_state.space_export_service.export_space(&space.id).await?;
+            let headers = [(
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.json\"", space.slug),
+            )];
+            (headers, Json(bundle)).into_response()
+        }
+        _ => return Err(AppError::Validation(format!("Unsupported export format: {}", format))),
+    };
+
+    info!("User {} exported space {} as {}", redact::user_id(&user.id), slug, format);
+
+    Ok(response)
+}
+
+/// 从 JSON 导出包导入空间，导入者成为新空间的所有者
+/// POST /api/spaces/import
+async fn import_space(
+    State(app_state): State<Arc<AppState>>,
+    user: User,
+    Json(bundle): Json<SpaceBundle>,
+) -> Result<Json<Value>> {
+    if !user.permissions.contains(&"spaces.write".to_string()) && !user.permissions.contains(&"docs.admin".to_string()) {
+        return Err(AppError::Authorization("Permission denied: spaces.write required".to_string()));
+    }
+
+    let result = app_state.space_export_service.import_space(bundle, &user.id).await?;
+
+    info!(
+        "User {} imported space {} with {} documents",
+        redact::user_id(&user.id),
+        result.space.slug,
+        result.document_count
+    );
+
+    Ok(Json(json!({
+        "success": true,
+        "data": result,
+        "message": "Space imported successfully"
+    })))
+}
+
 /// Legacy handler for frontend calls to /create (should use POST /)
 async fn handle_legacy_create(
     State(app_state): State<Arc<AppState>>,
@@ -147,7 +423,7 @@ async fn handle_legacy_create_stats(
     State(_app_state): State<Arc<AppState>>,
     OptionalUser(_user): OptionalUser,
 ) -> Result<Json<Value>> {
-    Err(AppError::BadRequest(
+    Err(AppError::Validation(
         "Invalid endpoint. Please use '/api/docs/spaces/{slug}/stats' instead.".to_string()
     ))
 }
@@ -156,17 +432,10 @@ async fn handle_legacy_create_stats(
 mod tests {
     use super::*;
     use crate::models::space::CreateSpaceRequest;
-    use axum::http::StatusCode;
-    use axum_test::TestServer;
-
-    // 注意：这些测试需要实际的数据库连接
-    // 在实际项目中，应该使用测试数据库或模拟
-
-    async fn create_test_server() -> TestServer {
-        let app = Router::new()
-            .nest("/api/spaces", router());
-        TestServer::new(app).unwrap()
-    }
+    use crate::state::test_support::{bearer_token, response_json, test_app, test_app_state};
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+    use validator::Validate;
 
     #[tokio::test]
     async fn test_create_space_validation() {
@@ -212,4 +481,43 @@ mod tests {
             assert!(request.validate().is_err(), "Should be invalid: {}", slug);
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_private_space_is_forbidden_to_non_members() {
+        let state = test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let owner = User {
+            id: format!("alice-{}", run),
+            email: "alice@example.com".to_string(),
+            roles: vec!["user".to_string()],
+            permissions: vec![],
+            profile: None,
+            api_key: None,
+        };
+        let slug = format!("private-{}", run);
+        let request = CreateSpaceRequest {
+            name: "Private".to_string(),
+            slug: slug.clone(),
+            description: None,
+            avatar_url: None,
+            is_public: Some(false),
+            settings: None,
+        };
+        state.space_service.create_space(request, &owner).await.unwrap();
+
+        let response = test_app("/api/docs/spaces", router(), &state)
+            .oneshot(
+                Request::get(format!("/api/docs/spaces/{}", slug))
+                    .header(header::AUTHORIZATION, bearer_token(&state, &format!("bob-{}", run)))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let (status, body) = response_json(response).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "FORBIDDEN");
+    }
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
     response::Json,
     routing::get,
     Router,
@@ -10,6 +10,7 @@ use serde_json::json;
 
 use crate::{
     error::Result,
+    models::stats::TimeseriesQuery,
     services::auth::User,
 };
 
@@ -103,11 +104,11 @@ pub async fn get_document_stats(
     let doc_count_query = "SELECT count() as total FROM document WHERE is_deleted = false GROUP ALL";
     let mut doc_result = db.query(doc_count_query)
         .await
-        .map_err(|e| crate::error::ApiError::DatabaseError(e.to_string()))?;
+        .map_err(|e| crate::error::AppError::database_error(e.to_string()))?;
     
     let doc_records: Vec<serde_json::Value> = doc_result
         .take(0)
-        .map_err(|e| crate::error::ApiError::DatabaseError(e.to_string()))?;
+        .map_err(|e| crate::error::AppError::database_error(e.to_string()))?;
     
     let total_documents = doc_records
         .first()
@@ -119,11 +120,11 @@ pub async fn get_document_stats(
     let space_count_query = "SELECT count() as total FROM space WHERE is_deleted = false GROUP ALL";
     let mut space_result = db.query(space_count_query)
         .await
-        .map_err(|e| crate::error::ApiError::DatabaseError(e.to_string()))?;
+        .map_err(|e| crate::error::AppError::database_error(e.to_string()))?;
     
     let space_records: Vec<serde_json::Value> = space_result
         .take(0)
-        .map_err(|e| crate::error::ApiError::DatabaseError(e.to_string()))?;
+        .map_err(|e| crate::error::AppError::database_error(e.to_string()))?;
     
     let total_spaces = space_records
         .first()
@@ -142,11 +143,11 @@ pub async fn get_document_stats(
     
     let mut today_result = db.query(today_docs_query)
         .await
-        .map_err(|e| crate::error::ApiError::DatabaseError(e.to_string()))?;
+        .map_err(|e| crate::error::AppError::database_error(e.to_string()))?;
     
     let today_records: Vec<serde_json::Value> = today_result
         .take(0)
-        .map_err(|e| crate::error::ApiError::DatabaseError(e.to_string()))?;
+        .map_err(|e| crate::error::AppError::database_error(e.to_string()))?;
     
     let documents_created_today = today_records
         .first()
@@ -159,11 +160,11 @@ pub async fn get_document_stats(
     
     let mut active_result = db.query(active_spaces_query)
         .await
-        .map_err(|e| crate::error::ApiError::DatabaseError(e.to_string()))?;
+        .map_err(|e| crate::error::AppError::database_error(e.to_string()))?;
     
     let space_records: Vec<serde_json::Value> = active_result
         .take(0)
-        .map_err(|e| crate::error::ApiError::DatabaseError(e.to_string()))?;
+        .map_err(|e| crate::error::AppError::database_error(e.to_string()))?;
     
     // 为每个空间获取文档数量
     let mut most_active_spaces = Vec::new();
@@ -182,11 +183,11 @@ pub async fn get_document_stats(
         let mut doc_count_result = db.query(doc_count_query)
             .bind(("space_id", space_id.clone()))
             .await
-            .map_err(|e| crate::error::ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| crate::error::AppError::database_error(e.to_string()))?;
         
         let doc_count_records: Vec<serde_json::Value> = doc_count_result
             .take(0)
-            .map_err(|e| crate::error::ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| crate::error::AppError::database_error(e.to_string()))?;
         
         let document_count = doc_count_records
             .first()
@@ -219,8 +220,46 @@ pub async fn get_document_stats(
     })))
 }
 
+/// 当前用户拥有或已加入的全部空间的统计汇总
+pub async fn get_dashboard_stats(
+    State(app_state): State<Arc<crate::AppState>>,
+    user: User,
+) -> Result<Json<serde_json::Value>> {
+    let dashboard = app_state.stats_service.user_dashboard(&user.id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": dashboard
+    })))
+}
+
+/// 空间的浏览、新建、编辑趋势，调用者需要有空间的访问权限
+pub async fn get_space_timeseries(
+    State(app_state): State<Arc<crate::AppState>>,
+    user: User,
+    Path(space_id): Path<String>,
+    Query(query): Query<TimeseriesQuery>,
+) -> Result<Json<serde_json::Value>> {
+    let clean_space_id = space_id.strip_prefix("space:").unwrap_or(&space_id);
+    if !app_state.space_member_service.can_access_space(clean_space_id, Some(&user.id)).await? {
+        return Err(crate::error::AppError::Authorization("Access denied to this space".to_string()));
+    }
+
+    let timeseries = app_state
+        .stats_service
+        .space_timeseries(clean_space_id, query.metric, query.from, query.to, query.granularity)
+        .await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": timeseries
+    })))
+}
+
 pub fn router() -> Router<Arc<crate::AppState>> {
     Router::new()
         .route("/search", get(get_search_stats))
         .route("/documents", get(get_document_stats))
+        .route("/dashboard", get(get_dashboard_stats))
+        .route("/:space_id/timeseries", get(get_space_timeseries))
 }
\ No newline at end of file
@@ -7,12 +7,14 @@ use axum::{
     Router,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use validator::Validate;
 
 use crate::{
-    error::ApiError,
-    models::tag::{Tag, DocumentTag, CreateTagRequest, UpdateTagRequest, TagDocumentRequest},
-    services::{auth::AuthService, tags::{TagService, TagStatistics}},
+    error::AppError,
+    models::tag::{Tag, DocumentTag, CreateTagRequest, UpdateTagRequest, TagDocumentRequest, BulkAssignTagsRequest, RenameTagRequest, MergeTagsRequest},
+    services::{auth::AuthService, tags::{TagService, TagStatistics, TagWithCount}},
 };
 
 #[derive(Deserialize)]
@@ -21,6 +23,8 @@ pub struct TagQuery {
     pub page: Option<i64>,
     pub per_page: Option<i64>,
     pub search: Option<String>,
+    /// 为 true 时返回每个标签关联的文档数量，并按数量降序排列
+    pub with_counts: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -31,7 +35,7 @@ pub struct PopularTagsQuery {
 
 #[derive(Serialize)]
 pub struct TagListResponse {
-    pub tags: Vec<Tag>,
+    pub tags: Vec<TagWithCount>,
     pub total_count: i64,
     pub page: i64,
     pub per_page: i64,
@@ -57,7 +61,7 @@ pub async fn get_tags(
     Query(query): Query<TagQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<TagListResponse>, ApiError> {
+) -> Result<Json<TagListResponse>, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     // 检查读取权限
@@ -74,14 +78,24 @@ pub async fn get_tags(
     let page = query.page.unwrap_or(1);
     let per_page = query.per_page.unwrap_or(20);
 
-    let tags = if let Some(search_query) = &query.search {
+    let tags: Vec<TagWithCount> = if let Some(search_query) = &query.search {
         tag_service
             .search_tags(query.space_id.as_deref(), search_query, per_page)
             .await?
+            .into_iter()
+            .map(TagWithCount::from)
+            .collect()
+    } else if query.with_counts.unwrap_or(false) {
+        tag_service
+            .get_tags_with_counts(query.space_id.as_deref(), page, per_page)
+            .await?
     } else {
         tag_service
             .get_tags_by_space(query.space_id.as_deref(), page, per_page)
             .await?
+            .into_iter()
+            .map(TagWithCount::from)
+            .collect()
     };
 
     // 简化实现，实际应该查询真实总数
@@ -101,7 +115,7 @@ pub async fn create_tag(
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
     Json(request): Json<CreateTagRequest>,
-) -> Result<Json<Tag>, ApiError> {
+) -> Result<Json<Tag>, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     // 检查创建权限
@@ -123,7 +137,7 @@ pub async fn get_tag(
     Path(tag_id): Path<String>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<Tag>, ApiError> {
+) -> Result<Json<Tag>, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     let tag = tag_service.get_tag(&tag_id).await?;
@@ -147,7 +161,7 @@ pub async fn update_tag(
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
     Json(request): Json<UpdateTagRequest>,
-) -> Result<Json<Tag>, ApiError> {
+) -> Result<Json<Tag>, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     let tag = tag_service.get_tag(&tag_id).await?;
@@ -171,7 +185,7 @@ pub async fn delete_tag(
     Path(tag_id): Path<String>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<StatusCode, ApiError> {
+) -> Result<StatusCode, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     let tag = tag_service.get_tag(&tag_id).await?;
@@ -195,7 +209,7 @@ pub async fn get_popular_tags(
     Query(query): Query<PopularTagsQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<Vec<Tag>>, ApiError> {
+) -> Result<Json<Vec<Tag>>, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     // 检查读取权限
@@ -221,7 +235,7 @@ pub async fn tag_document(
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
     Json(request): Json<TagDocumentRequest>,
-) -> Result<Json<Vec<DocumentTag>>, ApiError> {
+) -> Result<Json<Vec<DocumentTag>>, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     // 检查文档标签权限
@@ -233,11 +247,81 @@ pub async fn tag_document(
     Ok(Json(document_tags))
 }
 
+pub async fn bulk_assign_tags(
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+    Json(request): Json<BulkAssignTagsRequest>,
+) -> Result<Json<HashMap<String, Vec<Tag>>>, AppError> {
+    request.validate()?;
+
+    let tag_service = &app_state.tag_service;
+    let auth_service = &app_state.auth_service;
+    // 批量打标签需要空间的编辑权限
+    if !auth_service
+        .check_permission(&user_id, "docs.write", Some(&request.space_id))
+        .await?
+    {
+        return Err(AppError::Authorization("Permission denied: docs.write required".to_string()));
+    }
+
+    let document_tags = tag_service
+        .assign_tags_bulk(&request.space_id, request.document_ids, request.tag_names, &user_id)
+        .await?;
+    Ok(Json(document_tags))
+}
+
+pub async fn rename_tag(
+    Path(name): Path<String>,
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+    Json(request): Json<RenameTagRequest>,
+) -> Result<Json<Tag>, AppError> {
+    request.validate()?;
+
+    let tag_service = &app_state.tag_service;
+    let auth_service = &app_state.auth_service;
+    // 重命名会影响空间内所有文档的标签展示，需要编辑权限
+    if !auth_service
+        .check_permission(&user_id, "docs.write", Some(&request.space_id))
+        .await?
+    {
+        return Err(AppError::Authorization("Permission denied: docs.write required".to_string()));
+    }
+
+    let tag = tag_service
+        .rename_tag(&request.space_id, &name, &request.new_name)
+        .await?;
+    Ok(Json(tag))
+}
+
+pub async fn merge_tags(
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+    Json(request): Json<MergeTagsRequest>,
+) -> Result<Json<Tag>, AppError> {
+    request.validate()?;
+
+    let tag_service = &app_state.tag_service;
+    let auth_service = &app_state.auth_service;
+    // 合并会删除源标签，需要编辑权限
+    if !auth_service
+        .check_permission(&user_id, "docs.write", Some(&request.space_id))
+        .await?
+    {
+        return Err(AppError::Authorization("Permission denied: docs.write required".to_string()));
+    }
+
+    let tag = tag_service
+        .merge_tags(&request.space_id, request.source_names, &request.target_name, &user_id)
+        .await?;
+    Ok(Json(tag))
+}
+
 pub async fn untag_document(
     Path((document_id, tag_id)): Path<(String, String)>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<StatusCode, ApiError> {
+) -> Result<StatusCode, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     // 检查文档标签权限
@@ -253,7 +337,7 @@ pub async fn get_document_tags(
     Path(document_id): Path<String>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<DocumentTagsResponse>, ApiError> {
+) -> Result<Json<DocumentTagsResponse>, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     // 检查文档读取权限
@@ -274,7 +358,7 @@ pub async fn get_documents_by_tag(
     Query(query): Query<TagQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<TagDocumentsResponse>, ApiError> {
+) -> Result<Json<TagDocumentsResponse>, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     let tag = tag_service.get_tag(&tag_id).await?;
@@ -313,7 +397,7 @@ pub async fn get_tag_statistics(
     Query(query): Query<TagQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<TagStatistics>, ApiError> {
+) -> Result<Json<TagStatistics>, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     // 检查统计查看权限
@@ -338,7 +422,7 @@ pub async fn suggest_tags(
     Query(query): Query<TagQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<Vec<Tag>>, ApiError> {
+) -> Result<Json<Vec<Tag>>, AppError> {
     let tag_service = &app_state.tag_service;
     let auth_service = &app_state.auth_service;
     // 检查读取权限
@@ -371,6 +455,10 @@ pub fn router() -> Router<Arc<crate::AppState>> {
         .route("/:tag_id", get(get_tag).put(update_tag).delete(delete_tag))
         .route("/:tag_id/documents", get(get_documents_by_tag))
         .route("/documents/tag", post(tag_document))
+        .route("/bulk-assign", post(bulk_assign_tags))
+        .route("/merge", post(merge_tags))
+        // PUT /:tag_id 已用于按 ID 更新标签，按名称重命名使用独立前缀
+        .route("/by-name/:name", put(rename_tag))
         .route("/documents/:document_id", get(get_document_tags))
         .route("/documents/:document_id/tags/:tag_id", delete(untag_document))
 }
\ No newline at end of file
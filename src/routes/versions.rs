@@ -10,11 +10,11 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 use crate::{
-    error::ApiError,
+    error::AppError,
     models::version::{DocumentVersion, CreateVersionRequest},
     services::{
         auth::AuthService, 
-        versions::{VersionService, VersionComparison, VersionHistorySummary},
+        versions::{VersionService, VersionComparison, VersionDiff, VersionHistorySummary},
     },
 };
 
@@ -45,12 +45,18 @@ pub struct CompareVersionsQuery {
     pub to_version: String,
 }
 
+#[derive(Deserialize)]
+pub struct VersionDiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
+
 pub async fn get_document_versions(
     Path(document_id): Path<String>,
     Query(query): Query<VersionQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<VersionListResponse>, ApiError> {
+) -> Result<Json<VersionListResponse>, AppError> {
     let version_service = &app_state.version_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -88,7 +94,7 @@ pub async fn create_document_version(
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
     Json(request): Json<CreateVersionRequest>,
-) -> Result<Json<DocumentVersion>, ApiError> {
+) -> Result<Json<DocumentVersion>, AppError> {
     let version_service = &app_state.version_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -106,7 +112,7 @@ pub async fn get_version(
     Path((document_id, version_id)): Path<(String, String)>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<DocumentVersion>, ApiError> {
+) -> Result<Json<DocumentVersion>, AppError> {
     let version_service = &app_state.version_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -117,7 +123,7 @@ pub async fn get_version(
 
     // 验证版本属于指定文档
     if version.document_id.to_string() != format!("document:{}", document_id) {
-        return Err(ApiError::NotFound("Version not found".to_string()));
+        return Err(AppError::NotFound("Version not found".to_string()));
     }
 
     Ok(Json(version))
@@ -127,7 +133,7 @@ pub async fn get_current_version(
     Path(document_id): Path<String>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<Option<DocumentVersion>>, ApiError> {
+) -> Result<Json<Option<DocumentVersion>>, AppError> {
     let version_service = &app_state.version_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -146,7 +152,32 @@ pub async fn restore_version(
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
     Json(_request): Json<RestoreVersionRequest>,
-) -> Result<Json<DocumentVersion>, ApiError> {
+) -> Result<Json<DocumentVersion>, AppError> {
+    let version_service = &app_state.version_service;
+    let auth_service = &app_state.auth_service;
+    auth_service
+        .check_permission(&user_id, "docs.update", Some(&document_id))
+        .await?;
+
+    let version = version_service.get_version(&version_id).await?;
+
+    // 验证版本属于指定文档
+    if version.document_id.to_string() != format!("document:{}", document_id) {
+        return Err(AppError::Validation("Version does not belong to document".to_string()));
+    }
+
+    let restored_version = version_service
+        .restore_version(&document_id, version.version_number, &user_id, &app_state.document_service)
+        .await?;
+
+    Ok(Json(restored_version))
+}
+
+pub async fn restore_version_by_number(
+    Path((document_id, version_number)): Path<(String, i32)>,
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<DocumentVersion>, AppError> {
     let version_service = &app_state.version_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -154,7 +185,7 @@ pub async fn restore_version(
         .await?;
 
     let restored_version = version_service
-        .restore_version(&document_id, &version_id, &user_id)
+        .restore_version(&document_id, version_number, &user_id, &app_state.document_service)
         .await?;
 
     Ok(Json(restored_version))
@@ -165,7 +196,7 @@ pub async fn compare_versions(
     Query(query): Query<CompareVersionsQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<VersionComparison>, ApiError> {
+) -> Result<Json<VersionComparison>, AppError> {
     let version_service = &app_state.version_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -183,7 +214,7 @@ pub async fn get_version_history_summary(
     Path(document_id): Path<String>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<VersionHistorySummary>, ApiError> {
+) -> Result<Json<VersionHistorySummary>, AppError> {
     let version_service = &app_state.version_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -201,7 +232,7 @@ pub async fn delete_version(
     Path((document_id, version_id)): Path<(String, String)>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<StatusCode, ApiError> {
+) -> Result<StatusCode, AppError> {
     let version_service = &app_state.version_service;
     let auth_service = &app_state.auth_service;
     // 只有文档管理员或版本作者可以删除版本
@@ -219,7 +250,7 @@ pub async fn get_version_diff(
     Query(query): Query<CompareVersionsQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<VersionComparison>, ApiError> {
+) -> Result<Json<VersionComparison>, AppError> {
     let version_service = &app_state.version_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -234,12 +265,79 @@ pub async fn get_version_diff(
     Ok(Json(comparison))
 }
 
+pub async fn diff_versions(
+    Path(document_id): Path<String>,
+    Query(query): Query<VersionDiffQuery>,
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<VersionDiff>, AppError> {
+    let version_service = &app_state.version_service;
+    let auth_service = &app_state.auth_service;
+    auth_service
+        .check_permission(&user_id, "docs.read", Some(&document_id))
+        .await?;
+
+    let diff = version_service
+        .diff_versions(&document_id, query.from, query.to)
+        .await?;
+
+    Ok(Json(diff))
+}
+
+pub async fn prune_versions(
+    Path(document_id): Path<String>,
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let version_service = &app_state.version_service;
+    let auth_service = &app_state.auth_service;
+    auth_service
+        .check_permission(&user_id, "docs.admin", Some(&document_id))
+        .await?;
+
+    let pruned = version_service.prune_versions(&document_id).await?;
+
+    Ok(Json(serde_json::json!({ "pruned": pruned })))
+}
+
+pub async fn pin_version(
+    Path((document_id, version_number)): Path<(String, i32)>,
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<DocumentVersion>, AppError> {
+    let version_service = &app_state.version_service;
+    let auth_service = &app_state.auth_service;
+    auth_service
+        .check_permission(&user_id, "docs.update", Some(&document_id))
+        .await?;
+
+    let version = version_service.pin_version(&document_id, version_number).await?;
+
+    Ok(Json(version))
+}
+
+pub async fn unpin_version(
+    Path((document_id, version_number)): Path<(String, i32)>,
+    State(app_state): State<Arc<crate::AppState>>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<DocumentVersion>, AppError> {
+    let version_service = &app_state.version_service;
+    let auth_service = &app_state.auth_service;
+    auth_service
+        .check_permission(&user_id, "docs.update", Some(&document_id))
+        .await?;
+
+    let version = version_service.unpin_version(&document_id, version_number).await?;
+
+    Ok(Json(version))
+}
+
 pub async fn get_versions_by_date_range(
     Path(document_id): Path<String>,
     Query(query): Query<DateRangeQuery>,
     State(app_state): State<Arc<crate::AppState>>,
     Extension(user_id): Extension<String>,
-) -> Result<Json<Vec<DocumentVersion>>, ApiError> {
+) -> Result<Json<Vec<DocumentVersion>>, AppError> {
     let version_service = &app_state.version_service;
     let auth_service = &app_state.auth_service;
     auth_service
@@ -270,4 +368,8 @@ pub fn router() -> Router<Arc<crate::AppState>> {
         .route("/:document_id/versions/:version_id", get(get_version).delete(delete_version))
         .route("/:document_id/versions/:version_id/restore", post(restore_version))
         .route("/:document_id/versions/:version_id/diff", get(get_version_diff))
+        .route("/:document_id/diff", get(diff_versions))
+        .route("/:document_id/restore/:version", post(restore_version_by_number))
+        .route("/:document_id/prune", post(prune_versions))
+        .route("/:document_id/pin/:version", post(pin_version).delete(unpin_version))
 }
\ No newline at end of file
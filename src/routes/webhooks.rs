@@ -0,0 +1,108 @@
+use crate::{AppState, error::{AppError, Result}};
+use crate::models::webhook::{CreateWebhookRequest, WebhookDeliveryQuery};
+use crate::services::auth::User;
+use crate::utils::redact;
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+
+pub fn router() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/:space_slug/webhooks", get(list_webhooks).post(create_webhook))
+        .route("/:space_slug/webhooks/:webhook_id", delete(delete_webhook))
+        .route("/:space_slug/webhooks/:webhook_id/deliveries", get(list_deliveries))
+}
+
+/// 获取空间 id 并检查管理权限，webhook 只允许空间所有者和管理员配置
+async fn managed_space_id(app_state: &AppState, space_slug: &str, user: &User) -> Result<String> {
+    let space = app_state.space_service.get_space_by_slug(space_slug, Some(user)).await?;
+
+    if !app_state.space_member_service.check_permission(&space.id, &user.id, "spaces.manage").await? {
+        return Err(AppError::Authorization("Only space owners and admins can manage webhooks".to_string()));
+    }
+
+    Ok(space.id)
+}
+
+/// 获取空间的 webhook 列表
+/// GET /api/docs/spaces/:space_slug/webhooks
+async fn list_webhooks(
+    State(app_state): State<Arc<AppState>>,
+    Path(space_slug): Path<String>,
+    user: User,
+) -> Result<Json<Value>> {
+    let space_id = managed_space_id(&app_state, &space_slug, &user).await?;
+
+    let webhooks = app_state.webhook_service.list_webhooks(&space_id).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": webhooks,
+        "message": "Webhooks retrieved successfully"
+    })))
+}
+
+/// 创建 webhook，签名密钥只在这里返回一次
+/// POST /api/docs/spaces/:space_slug/webhooks
+async fn create_webhook(
+    State(app_state): State<Arc<AppState>>,
+    Path(space_slug): Path<String>,
+    user: User,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Result<Json<Value>> {
+    let space_id = managed_space_id(&app_state, &space_slug, &user).await?;
+
+    let webhook = app_state.webhook_service.create_webhook(&space_id, &user.id, request).await?;
+
+    info!("User {} created webhook in space: {}", redact::user_id(&user.id), space_slug);
+
+    Ok(Json(json!({
+        "success": true,
+        "data": webhook,
+        "message": "Webhook created successfully"
+    })))
+}
+
+/// 删除 webhook
+/// DELETE /api/docs/spaces/:space_slug/webhooks/:webhook_id
+async fn delete_webhook(
+    State(app_state): State<Arc<AppState>>,
+    Path((space_slug, webhook_id)): Path<(String, String)>,
+    user: User,
+) -> Result<Json<Value>> {
+    let space_id = managed_space_id(&app_state, &space_slug, &user).await?;
+
+    app_state.webhook_service.delete_webhook(&space_id, &webhook_id).await?;
+
+    info!("User {} deleted webhook {} from space: {}", redact::user_id(&user.id), webhook_id, space_slug);
+
+    Ok(Json(json!({
+        "success": true,
+        "message": "Webhook deleted successfully"
+    })))
+}
+
+/// 获取 webhook 最近的投递记录
+/// GET /api/docs/spaces/:space_slug/webhooks/:webhook_id/deliveries
+async fn list_deliveries(
+    State(app_state): State<Arc<AppState>>,
+    Path((space_slug, webhook_id)): Path<(String, String)>,
+    Query(query): Query<WebhookDeliveryQuery>,
+    user: User,
+) -> Result<Json<Value>> {
+    let space_id = managed_space_id(&app_state, &space_slug, &user).await?;
+
+    let deliveries = app_state.webhook_service.list_deliveries(&space_id, &webhook_id, query.limit).await?;
+
+    Ok(Json(json!({
+        "success": true,
+        "data": deliveries,
+        "message": "Webhook deliveries retrieved successfully"
+    })))
+}
@@ -0,0 +1,99 @@
+use crate::services::auth::User;
+use crate::services::database::Database;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::warn;
+
+/// 一条待写入 activity_log 的记录。
+/// action 约定为 `<resource_type>_<动作>`，如 `document_updated`、`member_removed`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActivityRecord {
+    pub user_id: String,
+    pub action: String,
+    pub resource_type: String,
+    pub resource_id: String,
+    /// 所属空间（不带 space: 前缀）
+    pub space_id: String,
+    /// 写入时的操作者名称和资源标题快照，资源删除后动态仍然可读
+    pub details: Value,
+}
+
+impl ActivityRecord {
+    pub fn new(actor_id: &str, action: &str, resource_type: &str, resource_id: &str, space_id: &str) -> Self {
+        let resource_prefix = format!("{}:", resource_type);
+        Self {
+            user_id: actor_id.to_string(),
+            action: action.to_string(),
+            resource_type: resource_type.to_string(),
+            resource_id: resource_id.strip_prefix(resource_prefix.as_str()).unwrap_or(resource_id).to_string(),
+            space_id: space_id.strip_prefix("space:").unwrap_or(space_id).to_string(),
+            details: Value::Object(Default::default()),
+        }
+    }
+
+    pub fn with_actor_name(mut self, actor_name: impl Into<String>) -> Self {
+        self.details["actor_name"] = Value::String(actor_name.into());
+        self
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.details["title"] = Value::String(title.into());
+        self
+    }
+}
+
+/// 各服务共用的活动日志写入器，保证写入的记录格式一致
+#[derive(Clone)]
+pub struct ActivityLogger {
+    db: Arc<Database>,
+}
+
+impl ActivityLogger {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// 写入活动日志。日志不是主流程的一部分，失败只记录警告
+    pub async fn log(&self, record: ActivityRecord) {
+        // CREATE 语句本身的错误（如字段校验失败）需要 check 才能拿到
+        let result = self.db.client
+            .query("CREATE activity_log CONTENT $record")
+            .bind(("record", &record))
+            .await
+            .and_then(|response| response.check());
+
+        if let Err(e) = result {
+            warn!(
+                "Failed to log activity {} on {}:{}: {}",
+                record.action, record.resource_type, record.resource_id, e
+            );
+        }
+    }
+}
+
+/// 操作者显示名称：优先使用 profile 中的名称，其次邮箱，最后用户ID
+pub fn actor_display_name(user: &User) -> String {
+    user.profile
+        .as_ref()
+        .and_then(|p| p.display_name.clone())
+        .filter(|name| !name.is_empty())
+        .or_else(|| Some(user.email.clone()).filter(|email| !email.is_empty() && email != "unknown@example.com"))
+        .unwrap_or_else(|| user.id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_strips_table_prefixes() {
+        let record = ActivityRecord::new("user-1", "document_updated", "document", "document:abc", "space:s1")
+            .with_actor_name("alice")
+            .with_title("Intro");
+
+        assert_eq!(record.resource_id, "abc");
+        assert_eq!(record.space_id, "s1");
+        assert_eq!(record.details, serde_json::json!({ "actor_name": "alice", "title": "Intro" }));
+    }
+}
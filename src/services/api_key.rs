@@ -0,0 +1,284 @@
+use std::sync::Arc;
+
+use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use surrealdb::sql::Thing;
+use tracing::{info, warn};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::{AppError, Result},
+    models::api_key::{ApiKey, ApiKeyDb, ApiKeyScope, CreateApiKeyRequest, CreatedApiKey},
+    services::{auth::{User, UserProfile}, database::Database},
+};
+
+/// 密钥统一以此开头，便于在日志和代码仓库中识别泄露的密钥
+pub const API_KEY_PREFIX: &str = "rdk_";
+/// API 密钥认证得到的主体 id 为 `api_key:<密钥记录ID>`
+pub const API_KEY_PRINCIPAL_PREFIX: &str = "api_key:";
+/// 查找用的可见部分长度
+const LOOKUP_ID_LEN: usize = 12;
+
+#[derive(Clone)]
+pub struct ApiKeyService {
+    db: Arc<Database>,
+}
+
+impl ApiKeyService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    pub async fn create_api_key(
+        &self,
+        space_id: &str,
+        creator_id: &str,
+        request: CreateApiKeyRequest,
+    ) -> Result<CreatedApiKey> {
+        request.validate()?;
+
+        let mut permissions: Vec<String> = Vec::with_capacity(request.permissions.len());
+        for permission in request.permissions {
+            if !permissions.contains(&permission) {
+                permissions.push(permission);
+            }
+        }
+
+        let (key, key_prefix) = generate_api_key();
+        let key_hash = hash_api_key(&key)?;
+
+        let query = r#"
+            CREATE api_key SET
+                space_id = $space_id,
+                name = $name,
+                key_prefix = $key_prefix,
+                key_hash = $key_hash,
+                permissions = $permissions,
+                created_by = $created_by,
+                created_at = time::now()
+        "#;
+
+        let created: Vec<ApiKeyDb> = self.db.client
+            .query(query)
+            .bind(("space_id", space_thing(space_id)))
+            .bind(("name", &request.name))
+            .bind(("key_prefix", &key_prefix))
+            .bind(("key_hash", &key_hash))
+            .bind(("permissions", &permissions))
+            .bind(("created_by", creator_id))
+            .await?
+            .take(0)?;
+
+        let api_key: ApiKey = created.into_iter()
+            .next()
+            .map(ApiKey::from)
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Failed to create API key")))?;
+
+        info!("Created API key {} for space {}", api_key.key_prefix, space_id);
+        Ok(CreatedApiKey { api_key, key })
+    }
+
+    pub async fn list_api_keys(&self, space_id: &str) -> Result<Vec<ApiKey>> {
+        let keys: Vec<ApiKeyDb> = self.db.client
+            .query("SELECT * FROM api_key WHERE space_id = $space_id ORDER BY created_at DESC")
+            .bind(("space_id", space_thing(space_id)))
+            .await?
+            .take(0)?;
+
+        Ok(keys.into_iter().map(ApiKey::from).collect())
+    }
+
+    /// 吊销密钥，记录保留以便审计
+    pub async fn revoke_api_key(&self, space_id: &str, key_id: &str) -> Result<()> {
+        let revoked: Vec<ApiKeyDb> = self.db.client
+            .query("UPDATE type::thing('api_key', $id) SET revoked_at = time::now()
+                WHERE space_id = $space_id AND revoked_at = NONE RETURN AFTER")
+            .bind(("id", raw_key_id(key_id)))
+            .bind(("space_id", space_thing(space_id)))
+            .await?
+            .take(0)?;
+
+        if revoked.is_empty() {
+            return Err(AppError::NotFound("API key not found".to_string()));
+        }
+
+        info!("Revoked API key {} in space {}", key_id, space_id);
+        Ok(())
+    }
+
+    /// 校验请求携带的密钥，返回限定在密钥所属空间的主体
+    pub async fn authenticate(&self, key: &str) -> Result<User> {
+        let lookup = key_prefix(key).ok_or_else(invalid_api_key)?;
+
+        let keys: Vec<ApiKeyDb> = self.db.client
+            .query("SELECT * FROM api_key WHERE key_prefix = $key_prefix LIMIT 1")
+            .bind(("key_prefix", lookup))
+            .await?
+            .take(0)?;
+        let record = keys.into_iter().next().map(ApiKey::from).ok_or_else(invalid_api_key)?;
+
+        // Argon2 校验是 CPU 密集操作，放到阻塞线程池中执行，避免每个 API 请求都占用异步运行时
+        let (principal, record) = {
+            let key = key.to_string();
+            tokio::task::spawn_blocking(move || api_key_principal(&key, &record).map(|principal| (principal, record)))
+                .await
+                .map_err(|e| AppError::Internal(anyhow::anyhow!("API key verification task failed: {}", e)))??
+        };
+
+        // 最近使用时间只用于展示，写入失败不影响本次请求
+        if let Err(e) = self.db.client
+            .query("UPDATE type::thing('api_key', $id) SET last_used_at = time::now()")
+            .bind(("id", raw_key_id(record.id.as_deref().unwrap_or_default())))
+            .await
+        {
+            warn!("Failed to update last_used_at of API key {}: {}", record.key_prefix, e);
+        }
+
+        Ok(principal)
+    }
+
+    /// API 密钥主体在空间中是否有指定权限，permission 为 None 时只检查能否访问空间
+    pub async fn principal_allows(&self, principal_id: &str, space_id: &str, permission: Option<&str>) -> Result<bool> {
+        let Some(key_id) = principal_id.strip_prefix(API_KEY_PRINCIPAL_PREFIX) else {
+            return Ok(false);
+        };
+
+        let keys: Vec<ApiKeyDb> = self.db.client
+            .query("SELECT * FROM type::thing('api_key', $id)")
+            .bind(("id", key_id))
+            .await?
+            .take(0)?;
+
+        Ok(keys.into_iter()
+            .next()
+            .map(ApiKey::from)
+            .is_some_and(|key| key.allows(space_id, permission)))
+    }
+}
+
+/// 生成新密钥，返回 (完整密钥, 查找前缀)。格式为 `rdk_<12位查找ID>_<32位随机串>`
+pub fn generate_api_key() -> (String, String) {
+    let lookup_id: String = Uuid::new_v4().simple().to_string().chars().take(LOOKUP_ID_LEN).collect();
+    let key_prefix = format!("{}{}", API_KEY_PREFIX, lookup_id);
+    let key = format!("{}_{}", key_prefix, Uuid::new_v4().simple());
+    (key, key_prefix)
+}
+
+/// 取出密钥中用于查找记录的前缀，格式不对时返回 None
+pub fn key_prefix(key: &str) -> Option<&str> {
+    let (lookup_id, secret) = key.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+
+    (lookup_id.len() == LOOKUP_ID_LEN && !secret.is_empty())
+        .then(|| &key[..API_KEY_PREFIX.len() + LOOKUP_ID_LEN])
+}
+
+pub fn hash_api_key(key: &str) -> Result<String> {
+    // 盐取自随机 UUID，不依赖 password_hash 的 rand_core 特性
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to generate salt: {}", e)))?;
+    Argon2::default()
+        .hash_password(key.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to hash API key: {}", e)))
+}
+
+/// 核对密钥与记录，通过时构造限定在该空间、只带密钥权限的主体
+pub fn api_key_principal(key: &str, record: &ApiKey) -> Result<User> {
+    if record.is_revoked() {
+        return Err(AppError::Authentication("API key has been revoked".to_string()));
+    }
+
+    let hash = PasswordHash::new(&record.key_hash)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid API key hash: {}", e)))?;
+    Argon2::default()
+        .verify_password(key.as_bytes(), &hash)
+        .map_err(|_| invalid_api_key())?;
+
+    let key_id = record.id.as_deref().ok_or_else(invalid_api_key)?;
+    let key_id = raw_key_id(key_id);
+
+    Ok(User {
+        id: format!("{}{}", API_KEY_PRINCIPAL_PREFIX, key_id),
+        email: String::new(),
+        roles: vec!["api_key".to_string()],
+        permissions: record.permissions.clone(),
+        profile: Some(UserProfile {
+            first_name: None,
+            last_name: None,
+            display_name: Some(record.name.clone()),
+            avatar_url: None,
+        }),
+        api_key: Some(ApiKeyScope {
+            key_id: key_id.to_string(),
+            space_id: record.space_id.strip_prefix("space:").unwrap_or(&record.space_id).to_string(),
+        }),
+    })
+}
+
+fn invalid_api_key() -> AppError {
+    AppError::Authentication("Invalid API key".to_string())
+}
+
+fn space_thing(space_id: &str) -> Thing {
+    Thing::from(("space", space_id.strip_prefix("space:").unwrap_or(space_id)))
+}
+
+fn raw_key_id(key_id: &str) -> &str {
+    key_id.strip_prefix("api_key:").unwrap_or(key_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn stored_key(key: &str, key_prefix: &str) -> ApiKey {
+        ApiKey {
+            id: Some("api_key:ci".to_string()),
+            space_id: "space:handbook".to_string(),
+            name: "CI sync".to_string(),
+            key_prefix: key_prefix.to_string(),
+            key_hash: hash_api_key(key).unwrap(),
+            permissions: vec!["docs.read".to_string(), "docs.write".to_string()],
+            created_by: "alice".to_string(),
+            last_used_at: None,
+            revoked_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_valid_key_authenticates_as_scoped_principal() {
+        let (key, prefix) = generate_api_key();
+        assert_eq!(key_prefix(&key), Some(prefix.as_str()));
+        let record = stored_key(&key, &prefix);
+
+        let principal = api_key_principal(&key, &record).unwrap();
+        assert_eq!(principal.id, "api_key:ci");
+        assert_eq!(principal.permissions, vec!["docs.read", "docs.write"]);
+        assert_eq!(
+            principal.api_key,
+            Some(ApiKeyScope { key_id: "ci".to_string(), space_id: "handbook".to_string() })
+        );
+
+        assert!(record.allows("space:handbook", Some("docs.write")));
+        assert!(!record.allows("space:handbook", Some("docs.delete")));
+        assert!(!record.allows("space:other", None));
+
+        // 同一前缀但随机部分不同的密钥不能通过
+        let forged = format!("{}_{}", prefix, Uuid::new_v4().simple());
+        assert!(matches!(api_key_principal(&forged, &record), Err(AppError::Authentication(_))));
+        assert_eq!(key_prefix("Bearer abc"), None);
+        assert_eq!(key_prefix("rdk_short_secret"), None);
+    }
+
+    #[test]
+    fn test_revoked_key_is_rejected() {
+        let (key, prefix) = generate_api_key();
+        let mut record = stored_key(&key, &prefix);
+        record.revoked_at = Some(Utc::now());
+
+        assert!(matches!(api_key_principal(&key, &record), Err(AppError::Authentication(_))));
+        assert!(!record.allows("space:handbook", Some("docs.read")));
+    }
+}
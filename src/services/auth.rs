@@ -1,12 +1,14 @@
 use crate::config::Config;
 use crate::error::{AppError, Result};
+use crate::models::api_key::ApiKeyScope;
+use crate::services::api_key::ApiKeyService;
+use crate::utils::redact;
 use axum::{
     async_trait,
     extract::{FromRequestParts, State},
-    headers::{authorization::Bearer, Authorization},
-    http::{request::Parts, StatusCode},
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
     Extension,
-    RequestPartsExt, TypedHeader,
+    RequestPartsExt,
 };
 use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
 use reqwest::Client;
@@ -17,12 +19,16 @@ use tokio::sync::RwLock;
 use chrono::{DateTime, Utc, Duration};
 use tracing::{info, warn, error, debug};
 
+/// 调用 Rainbow-Auth 内部接口时携带 API 密钥的请求头
+pub const INTERNAL_API_KEY_HEADER: &str = "X-Internal-API-Key";
+
 #[derive(Clone)]
 pub struct AuthService {
     config: Config,
     http_client: Client,
     user_cache: Arc<RwLock<HashMap<String, CachedUser>>>,
     permission_cache: Arc<RwLock<HashMap<String, CachedPermission>>>,
+    api_key_service: Option<Arc<ApiKeyService>>,
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +58,9 @@ pub struct User {
     pub roles: Vec<String>,
     pub permissions: Vec<String>,
     pub profile: Option<UserProfile>,
+    /// 通过 API 密钥认证时密钥所属的空间，JWT 认证的用户为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<ApiKeyScope>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,6 +93,17 @@ pub struct PermissionData {
     pub permission: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RainbowAuthResolveResponse {
+    pub data: Vec<ResolvedUsername>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolvedUsername {
+    pub id: String,
+    pub username: String,
+}
+
 impl AuthService {
     pub fn new(config: Config) -> Self {
         let http_client = Client::builder()
@@ -96,16 +116,30 @@ impl AuthService {
             http_client,
             user_cache: Arc::new(RwLock::new(HashMap::new())),
             permission_cache: Arc::new(RwLock::new(HashMap::new())),
+            api_key_service: None,
         }
     }
 
+    pub fn with_api_key_service(mut self, api_key_service: Arc<ApiKeyService>) -> Self {
+        self.api_key_service = Some(api_key_service);
+        self
+    }
+
+    /// 校验 `Authorization: ApiKey <key>` 携带的密钥，返回限定在密钥所属空间的主体
+    pub async fn authenticate_api_key(&self, key: &str) -> Result<User> {
+        let api_key_service = self.api_key_service.as_ref()
+            .ok_or_else(|| AppError::Authentication("API key authentication is not enabled".to_string()))?;
+
+        api_key_service.authenticate(key).await
+    }
+
     pub fn verify_jwt(&self, token: &str) -> Result<Claims> {
         let decoding_key = DecodingKey::from_secret(self.config.auth.jwt_secret.as_ref());
         let validation = Validation::new(Algorithm::HS256);
 
         match decode::<Claims>(token, &decoding_key, &validation) {
             Ok(token_data) => {
-                info!("JWT token verified for user: {}", token_data.claims.sub);
+                debug!("JWT token verified for user: {}", redact::user_id(&token_data.claims.sub));
                 Ok(token_data.claims)
             }
             Err(e) => {
@@ -162,6 +196,7 @@ impl AuthService {
             roles,
             permissions,
             profile: None, // 可以后续扩展获取用户档案
+            api_key: None,
         };
 
         // 缓存用户数据
@@ -233,6 +268,49 @@ impl AuthService {
         Ok((roles, permissions))
     }
 
+    /// 将用户名解析为用户ID，未知的用户名不会出现在结果中。
+    /// 独立模式下没有用户目录，用户名即用户ID，由调用方再做成员校验。
+    /// 集成模式下 Rainbow-Auth 本身不提供按用户名查询的接口，需通过 RAINBOW_AUTH_USER_RESOLVE_URL
+    /// 配置一个解析服务（POST {"usernames": [...]}，返回 {"data": [{"id", "username"}]}），未配置时不解析任何提及。
+    pub async fn resolve_usernames(&self, usernames: &[String]) -> Result<HashMap<String, String>> {
+        if usernames.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        if !self.config.auth.integration_mode {
+            return Ok(usernames.iter().map(|name| (name.clone(), name.clone())).collect());
+        }
+
+        let Some(url) = &self.config.auth.user_resolve_url else {
+            debug!("RAINBOW_AUTH_USER_RESOLVE_URL not configured, skipping {} mention(s)", usernames.len());
+            return Ok(HashMap::new());
+        };
+
+        let mut request = self.http_client
+            .post(url)
+            .json(&serde_json::json!({ "usernames": usernames }));
+        if let Some(api_key) = &self.config.auth.internal_api_key {
+            request = request.header(INTERNAL_API_KEY_HEADER, api_key);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::External(format!("Failed to resolve usernames: {}", e)))?;
+
+        if !response.status().is_success() {
+            warn!("Rainbow-Auth returned error status when resolving usernames: {}", response.status());
+            return Ok(HashMap::new());
+        }
+
+        let resolved: RainbowAuthResolveResponse = response.json().await
+            .map_err(|e| AppError::External(format!("Invalid username resolution response: {}", e)))?;
+
+        Ok(resolved.data
+            .into_iter()
+            .map(|user| (user.username, user.id))
+            .collect())
+    }
+
     pub async fn check_permission(&self, user_id: &str, permission: &str, resource_id: Option<&str>) -> Result<bool> {
         if !self.config.auth.integration_mode {
             // 独立模式：简单权限检查
@@ -326,10 +404,12 @@ where
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
         // Extract the authorization header
-        let TypedHeader(Authorization(bearer)) = parts
-            .extract::<TypedHeader<Authorization<Bearer>>>()
-            .await
-            .map_err(|_| AppError::Authentication("Missing authorization header".to_string()))?;
+        let credentials = parts.headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| AppError::Authentication("Missing authorization header".to_string()))
+            .and_then(|value| Credentials::parse(value)
+                .ok_or_else(|| AppError::Authentication("Invalid authorization header format".to_string())))?;
 
         // Get auth service from app state
         let Extension(auth_service): Extension<Arc<AuthService>> = parts
@@ -337,12 +417,17 @@ where
             .await
             .map_err(|_| AppError::Internal(anyhow::anyhow!("Auth service not found")))?;
 
+        let token = match credentials {
+            Credentials::Bearer(token) => token,
+            Credentials::ApiKey(key) => return auth_service.authenticate_api_key(key).await,
+        };
+
         // Verify JWT token
-        let claims = auth_service.verify_jwt(bearer.token())?;
+        let claims = auth_service.verify_jwt(token)?;
 
         // Get user details from Rainbow-Auth if integration is enabled
         if auth_service.config.auth.integration_mode {
-            auth_service.get_user_from_rainbow_auth(&claims.sub, bearer.token()).await
+            auth_service.get_user_from_rainbow_auth(&claims.sub, token).await
         } else {
             // Standalone mode: create user from JWT claims with default values
             Ok(User {
@@ -351,11 +436,38 @@ where
                 roles: vec!["user".to_string()], // 默认角色
                 permissions: vec!["docs.read".to_string(), "docs.write".to_string(), "spaces.read".to_string(), "spaces.write".to_string()], // 默认权限
                 profile: None,
+                api_key: None,
             })
         }
     }
 }
 
+/// Authorization 请求头携带的凭据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Credentials<'a> {
+    Bearer(&'a str),
+    ApiKey(&'a str),
+}
+
+impl<'a> Credentials<'a> {
+    /// 解析 `Bearer <jwt>` 或 `ApiKey <key>`，方案名不区分大小写
+    fn parse(header: &'a str) -> Option<Self> {
+        let (scheme, value) = header.trim().split_once(' ')?;
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+
+        if scheme.eq_ignore_ascii_case("Bearer") {
+            Some(Credentials::Bearer(value))
+        } else if scheme.eq_ignore_ascii_case("ApiKey") {
+            Some(Credentials::ApiKey(value))
+        } else {
+            None
+        }
+    }
+}
+
 // Optional authentication extractor
 pub struct OptionalUser(pub Option<User>);
 
@@ -1,22 +1,55 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use surrealdb::{sql::Thing, Surreal, engine::remote::ws::Client};
+use tracing::warn;
 use validator::Validate;
 
 use crate::{
-    error::ApiError,
-    models::comment::{Comment, CreateCommentRequest, UpdateCommentRequest},
-    services::{auth::AuthService, database::Database},
+    error::AppError,
+    models::comment::{Comment, CommentResponse, CreateCommentRequest, UpdateCommentRequest},
+    models::notification::{CommentMentionData, NotificationPreferences, NotificationType},
+    services::{auth::AuthService, database::Database, space_member::SpaceMemberService, spaces::ensure_document_space_writable},
+    services::notification::{load_notification_preferences, notification_allowed},
+    utils::redact,
 };
 
+lazy_static::lazy_static! {
+    // @ 前面必须是行首或非单词字符，避免把邮箱地址当作提及
+    static ref MENTION_REGEX: regex::Regex =
+        regex::Regex::new(r"(?:^|[^\w@.])@([A-Za-z0-9_][A-Za-z0-9_.-]*)").unwrap();
+}
+
 #[derive(Clone)]
 pub struct CommentService {
     db: Arc<Database>,
     auth_service: Arc<AuthService>,
+    space_member_service: Option<Arc<SpaceMemberService>>,
+    max_depth: usize,
 }
 
+/// 未配置时允许的评论回复嵌套层数
+const DEFAULT_MAX_COMMENT_DEPTH: usize = 5;
+
 impl CommentService {
     pub fn new(db: Arc<Database>, auth_service: Arc<AuthService>) -> Self {
-        Self { db, auth_service }
+        Self {
+            db,
+            auth_service,
+            space_member_service: None,
+            max_depth: DEFAULT_MAX_COMMENT_DEPTH,
+        }
+    }
+
+    /// 设置空间成员服务，用于校验被 @ 提及的用户能否访问文档所在空间
+    pub fn with_space_member_service(mut self, space_member_service: Arc<SpaceMemberService>) -> Self {
+        self.space_member_service = Some(space_member_service);
+        self
+    }
+
+    /// 设置回复的最大嵌套层数，顶层评论为第 0 层
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
     }
 
     pub async fn create_comment(
@@ -24,8 +57,9 @@ impl CommentService {
         document_id: &str,
         author_id: &str,
         request: CreateCommentRequest,
-    ) -> Result<Comment, ApiError> {
+    ) -> Result<Comment, AppError> {
         request.validate()?;
+        ensure_document_space_writable(&self.db, document_id).await?;
 
         let document_thing = Thing::from(("document", document_id));
         let parent_id = if let Some(parent_id_str) = &request.parent_id {
@@ -41,28 +75,49 @@ impl CommentService {
         );
 
         if let Some(parent_id) = parent_id {
+            self.check_reply_target(document_id, &parent_id.id.to_string()).await?;
             comment = comment.with_parent(parent_id.to_string());
         }
 
+        let mentions = self.resolve_mentions(document_id, author_id, &comment.content).await;
+        comment.metadata.mentions = mentions.iter().map(|(user_id, _)| user_id.clone()).collect();
+
         let created: Vec<Comment> = self.db.client
             .create("comment")
             .content(comment)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        created
+        let created = created
             .into_iter()
             .next()
-            .ok_or_else(|| ApiError::InternalServerError("Failed to create comment".to_string()))
+            .ok_or_else(|| AppError::internal_server_error("Failed to create comment".to_string()))?;
+
+        // 通知失败不影响评论创建
+        let mentioned_ids: Vec<String> = mentions.iter().map(|(user_id, _)| user_id.clone()).collect();
+        let preferences = load_notification_preferences(&self.db, &mentioned_ids).await.unwrap_or_else(|e| {
+            warn!("Failed to load notification preferences: {}", e);
+            HashMap::new()
+        });
+        for (user_id, document_title) in filter_by_preferences(&mentions, &preferences) {
+            if let Err(e) = self
+                .create_mention_notification(user_id, author_id, document_id, document_title, created.id.as_deref())
+                .await
+            {
+                warn!("Failed to notify mentioned user {}: {}", redact::user_id(user_id), e);
+            }
+        }
+
+        Ok(created)
     }
 
-    pub async fn get_comment(&self, comment_id: &str) -> Result<Comment, ApiError> {
+    pub async fn get_comment(&self, comment_id: &str) -> Result<Comment, AppError> {
         let comment: Option<Comment> = self.db.client
             .select(("comment", comment_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        comment.ok_or_else(|| ApiError::NotFound("Comment not found".to_string()))
+        comment.ok_or_else(|| AppError::NotFound("Comment not found".to_string()))
     }
 
     pub async fn update_comment(
@@ -70,7 +125,7 @@ impl CommentService {
         comment_id: &str,
         editor_id: &str,
         request: UpdateCommentRequest,
-    ) -> Result<Comment, ApiError> {
+    ) -> Result<Comment, AppError> {
         request.validate()?;
 
         let mut comment = self.get_comment(comment_id).await?;
@@ -83,12 +138,12 @@ impl CommentService {
             .update(("comment", comment_id))
             .content(comment)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        updated.ok_or_else(|| ApiError::InternalServerError("Failed to update comment".to_string()))
+        updated.ok_or_else(|| AppError::internal_server_error("Failed to update comment".to_string()))
     }
 
-    pub async fn delete_comment(&self, comment_id: &str, deleter_id: &str) -> Result<(), ApiError> {
+    pub async fn delete_comment(&self, comment_id: &str, deleter_id: &str) -> Result<(), AppError> {
         let mut comment = self.get_comment(comment_id).await?;
         comment.soft_delete(deleter_id.to_string());
 
@@ -96,27 +151,76 @@ impl CommentService {
             .update(("comment", comment_id))
             .content(comment)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(())
     }
 
+    /// 标记评论为已解决；cascade 为 true 时同时解决其下所有回复
+    pub async fn resolve_comment(
+        &self,
+        comment_id: &str,
+        resolver_id: &str,
+        cascade: bool,
+    ) -> Result<Comment, AppError> {
+        let ids = self.resolution_targets(comment_id, cascade).await?;
+
+        let _: Vec<surrealdb::sql::Value> = self.db.client
+            .query("UPDATE comment SET is_resolved = true, resolved_by = $resolver_id, resolved_at = time::now(), updated_at = time::now() WHERE id INSIDE $ids")
+            .bind(("ids", ids))
+            .bind(("resolver_id", resolver_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        self.get_comment(comment_id).await
+    }
+
+    /// 重新打开已解决的评论；cascade 为 true 时同时重新打开其下所有回复
+    pub async fn unresolve_comment(&self, comment_id: &str, cascade: bool) -> Result<Comment, AppError> {
+        let ids = self.resolution_targets(comment_id, cascade).await?;
+
+        let _: Vec<surrealdb::sql::Value> = self.db.client
+            .query("UPDATE comment SET is_resolved = false, resolved_by = NONE, resolved_at = NONE, updated_at = time::now() WHERE id INSIDE $ids")
+            .bind(("ids", ids))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        self.get_comment(comment_id).await
+    }
+
     pub async fn get_document_comments(
         &self,
         document_id: &str,
+        include_resolved: bool,
         page: i64,
         per_page: i64,
-    ) -> Result<Vec<Comment>, ApiError> {
+    ) -> Result<Vec<Comment>, AppError> {
         let offset = (page - 1) * per_page;
         
-        let query = "
+        let query = if include_resolved {
+            "
             SELECT * FROM comment 
             WHERE document_id = $document_id 
             AND parent_id IS NULL 
             AND is_deleted = false
             ORDER BY created_at DESC
             LIMIT $limit START $offset
-        ";
+        "
+        } else {
+            "
+            SELECT * FROM comment 
+            WHERE document_id = $document_id 
+            AND parent_id IS NULL 
+            AND is_deleted = false
+            AND is_resolved = false
+            ORDER BY created_at DESC
+            LIMIT $limit START $offset
+        "
+        };
 
         let comments: Vec<Comment> = self.db.client
             .query(query)
@@ -124,29 +228,40 @@ impl CommentService {
             .bind(("limit", per_page))
             .bind(("offset", offset))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(comments)
     }
 
-    pub async fn get_document_comments_count(&self, document_id: &str) -> Result<i64, ApiError> {
-        let query = "
+    pub async fn get_document_comments_count(&self, document_id: &str, include_resolved: bool) -> Result<i64, AppError> {
+        let query = if include_resolved {
+            "
             SELECT count() FROM comment 
             WHERE document_id = $document_id 
             AND parent_id IS NULL 
             AND is_deleted = false
             GROUP ALL
-        ";
+        "
+        } else {
+            "
+            SELECT count() FROM comment 
+            WHERE document_id = $document_id 
+            AND parent_id IS NULL 
+            AND is_deleted = false
+            AND is_resolved = false
+            GROUP ALL
+        "
+        };
 
         let result: Vec<surrealdb::sql::Value> = self.db.client
             .query(query)
             .bind(("document_id", Thing::from(("document", document_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let count = result
             .first()
@@ -167,7 +282,7 @@ impl CommentService {
         parent_id: &str,
         page: i64,
         per_page: i64,
-    ) -> Result<Vec<Comment>, ApiError> {
+    ) -> Result<Vec<Comment>, AppError> {
         let offset = (page - 1) * per_page;
         
         let query = "
@@ -184,14 +299,14 @@ impl CommentService {
             .bind(("limit", per_page))
             .bind(("offset", offset))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(replies)
     }
 
-    pub async fn get_comment_replies_count(&self, parent_id: &str) -> Result<i64, ApiError> {
+    pub async fn get_comment_replies_count(&self, parent_id: &str) -> Result<i64, AppError> {
         let query = "
             SELECT count() FROM comment 
             WHERE parent_id = $parent_id 
@@ -203,9 +318,9 @@ impl CommentService {
             .query(query)
             .bind(("parent_id", Thing::from(("comment", parent_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let count = result
             .first()
@@ -225,7 +340,7 @@ impl CommentService {
         &self,
         comment_id: &str,
         user_id: &str,
-    ) -> Result<Comment, ApiError> {
+    ) -> Result<Comment, AppError> {
         let mut comment = self.get_comment(comment_id).await?;
         
         if comment.liked_by.contains(&user_id.to_string()) {
@@ -238,28 +353,29 @@ impl CommentService {
             .update(("comment", comment_id))
             .content(comment)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        updated.ok_or_else(|| ApiError::InternalServerError("Failed to update comment".to_string()))
+        updated.ok_or_else(|| AppError::internal_server_error("Failed to update comment".to_string()))
     }
 
-    pub async fn get_comment_thread(&self, comment_id: &str) -> Result<Vec<Comment>, ApiError> {
+    /// 获取文档的评论树，每一层按创建时间升序排列
+    pub async fn get_comment_thread(&self, document_id: &str) -> Result<Vec<CommentResponse>, AppError> {
         let query = "
             SELECT * FROM comment 
-            WHERE parent_id = $comment_id 
+            WHERE document_id = $document_id 
             AND is_deleted = false
             ORDER BY created_at ASC
         ";
 
-        let thread: Vec<Comment> = self.db.client
+        let comments: Vec<Comment> = self.db.client
             .query(query)
-            .bind(("comment_id", Thing::from(("comment", comment_id))))
+            .bind(("document_id", Thing::from(("document", document_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        Ok(thread)
+        Ok(build_comment_tree(comments))
     }
 
     pub async fn search_comments(
@@ -268,7 +384,7 @@ impl CommentService {
         query: &str,
         page: i64,
         per_page: i64,
-    ) -> Result<Vec<Comment>, ApiError> {
+    ) -> Result<Vec<Comment>, AppError> {
         let offset = (page - 1) * per_page;
         
         let search_query = "
@@ -287,10 +403,389 @@ impl CommentService {
             .bind(("limit", per_page))
             .bind(("offset", offset))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(comments)
     }
-}
\ No newline at end of file
+
+    /// 解析评论中的 @ 提及，返回可访问文档所在空间的用户ID及文档标题。
+    /// 解析失败只记录日志，不阻止评论创建。
+    async fn resolve_mentions(&self, document_id: &str, author_id: &str, content: &str) -> Vec<(String, String)> {
+        let handles = parse_mentions(content);
+        if handles.is_empty() {
+            return Vec::new();
+        }
+        let Some(space_member_service) = &self.space_member_service else {
+            warn!("Space member service not configured, skipping mention notifications");
+            return Vec::new();
+        };
+
+        let resolved = match self.auth_service.resolve_usernames(&handles).await {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                warn!("Failed to resolve mentioned usernames: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let document: Option<MentionedDocument> = match self.db.client
+            .query("SELECT title, space_id FROM type::thing('document', $document_id)")
+            .bind(("document_id", strip_table(document_id)))
+            .await
+        {
+            Ok(mut response) => response.take(0).unwrap_or_default(),
+            Err(e) => {
+                warn!("Failed to load document {} for mentions: {}", document_id, e);
+                None
+            }
+        };
+        let Some(document) = document else {
+            return Vec::new();
+        };
+        let space_id = document.space_id.id.to_string();
+
+        let mut accessible = HashSet::new();
+        for user_id in resolved.values() {
+            match space_member_service.can_access_space(&space_id, Some(user_id)).await {
+                Ok(true) => {
+                    accessible.insert(user_id.clone());
+                }
+                Ok(false) => {}
+                Err(e) => warn!("Failed to check space access for {}: {}", redact::user_id(user_id), e),
+            }
+        }
+
+        plan_mention_notifications(&handles, &resolved, &accessible, author_id)
+            .into_iter()
+            .map(|user_id| (user_id, document.title.clone()))
+            .collect()
+    }
+
+    async fn create_mention_notification(
+        &self,
+        user_id: &str,
+        author_id: &str,
+        document_id: &str,
+        document_title: &str,
+        comment_id: Option<&str>,
+    ) -> Result<(), AppError> {
+        let query = r#"
+            CREATE notification SET
+                user_id = $user_id,
+                type = "comment_mention",
+                title = $title,
+                content = $content,
+                data = $data,
+                is_read = false,
+                created_at = time::now(),
+                updated_at = time::now()
+        "#;
+
+        let _: Vec<serde_json::Value> = self.db.client
+            .query(query)
+            .bind(("user_id", user_id))
+            .bind(("title", format!("您在《{}》的评论中被提及", document_title)))
+            .bind(("content", format!("{} 在文档《{}》的评论中提到了您", author_id, document_title)))
+            .bind(("data", CommentMentionData {
+                document_id: strip_table(document_id).to_string(),
+                comment_id: comment_id.map(|id| strip_table(id).to_string()),
+                mentioned_by: author_id.to_string(),
+            }))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 解决/重新打开操作涉及的评论记录，cascade 时包含全部后代回复
+    async fn resolution_targets(&self, comment_id: &str, cascade: bool) -> Result<Vec<Thing>, AppError> {
+        let comment = self.get_comment(comment_id).await?;
+        let mut ids = vec![strip_table(comment_id).to_string()];
+
+        if cascade {
+            let comments: Vec<Comment> = self.db.client
+                .query("SELECT * FROM comment WHERE document_id = $document_id AND is_deleted = false")
+                .bind(("document_id", Thing::from(("document", strip_table(&comment.document_id)))))
+                .await
+                .map_err(|e| AppError::database_error(e.to_string()))?
+                .take(0)
+                .map_err(|e| AppError::database_error(e.to_string()))?;
+            ids.extend(collect_descendant_ids(&comments, strip_table(comment_id)));
+        }
+
+        Ok(ids
+            .iter()
+            .map(|id| Thing::from(("comment", id.as_str())))
+            .collect())
+    }
+
+    /// 校验回复目标：父评论必须属于同一文档，且回复后不超过最大嵌套层数
+    async fn check_reply_target(&self, document_id: &str, parent_id: &str) -> Result<(), AppError> {
+        let parent = self.get_comment(parent_id).await?;
+        if parent.is_deleted {
+            return Err(AppError::NotFound("Parent comment not found".to_string()));
+        }
+        if strip_table(&parent.document_id) != strip_table(document_id) {
+            return Err(AppError::Validation("Cannot reply to a comment on a different document".to_string()));
+        }
+
+        // 沿父链向上计算父评论所在层级，超过上限即可停止
+        let mut depth = 0;
+        let mut current = parent;
+        while let Some(ancestor_id) = current.parent_id.clone() {
+            depth += 1;
+            if depth >= self.max_depth {
+                break;
+            }
+            current = self.get_comment(strip_table(&ancestor_id)).await?;
+        }
+
+        if depth + 1 > self.max_depth {
+            return Err(AppError::Validation(format!(
+                "Replies cannot be nested more than {} levels deep",
+                self.max_depth
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct MentionedDocument {
+    title: String,
+    space_id: Thing,
+}
+
+/// 提取评论中的 @用户名，去重并保持出现顺序
+fn parse_mentions(content: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    MENTION_REGEX
+        .captures_iter(content)
+        .filter_map(|caps| caps.get(1))
+        .map(|m| m.as_str().trim_end_matches(|c| c == '.' || c == '-').to_string())
+        .filter(|handle| !handle.is_empty() && seen.insert(handle.clone()))
+        .collect()
+}
+
+/// 计算需要通知的用户：用户名必须能解析、用户必须能访问空间，且不通知作者本人
+fn plan_mention_notifications(
+    handles: &[String],
+    resolved: &HashMap<String, String>,
+    accessible: &HashSet<String>,
+    author_id: &str,
+) -> Vec<String> {
+    let mut notified = HashSet::new();
+    handles
+        .iter()
+        .filter_map(|handle| resolved.get(handle))
+        .filter(|user_id| accessible.contains(*user_id) && user_id.as_str() != author_id)
+        .filter(|user_id| notified.insert((*user_id).clone()))
+        .cloned()
+        .collect()
+}
+
+/// 去掉关闭了评论提及通知的用户
+fn filter_by_preferences<'a>(
+    mentions: &'a [(String, String)],
+    preferences: &HashMap<String, NotificationPreferences>,
+) -> Vec<&'a (String, String)> {
+    mentions
+        .iter()
+        .filter(|(user_id, _)| notification_allowed(preferences, user_id, NotificationType::CommentMention))
+        .collect()
+}
+
+/// 去掉记录 ID 的表名前缀，如 "comment:abc" -> "abc"
+fn strip_table(id: &str) -> &str {
+    id.split_once(':').map(|(_, raw)| raw).unwrap_or(id)
+}
+
+/// 收集某条评论下的全部后代回复 ID（不含自身）
+fn collect_descendant_ids(comments: &[Comment], root_id: &str) -> Vec<String> {
+    let mut children_map: std::collections::HashMap<&str, Vec<&str>> = std::collections::HashMap::new();
+    for comment in comments {
+        if let (Some(id), Some(parent_id)) = (comment.id.as_deref(), comment.parent_id.as_deref()) {
+            children_map.entry(strip_table(parent_id)).or_default().push(strip_table(id));
+        }
+    }
+
+    let mut descendants = Vec::new();
+    let mut stack = vec![root_id];
+    while let Some(current) = stack.pop() {
+        for child in children_map.get(current).into_iter().flatten() {
+            if *child != root_id && !descendants.iter().any(|d: &String| d == child) {
+                descendants.push(child.to_string());
+                stack.push(child);
+            }
+        }
+    }
+    descendants
+}
+
+/// 将平铺的评论组装成树；父评论缺失（如已删除）的回复作为顶层评论展示
+fn build_comment_tree(comments: Vec<Comment>) -> Vec<CommentResponse> {
+    let mut children_map: std::collections::HashMap<String, Vec<Comment>> = std::collections::HashMap::new();
+    let known: std::collections::HashSet<String> = comments
+        .iter()
+        .filter_map(|comment| comment.id.as_deref().map(|id| strip_table(id).to_string()))
+        .collect();
+
+    let mut roots = Vec::new();
+    for comment in comments {
+        match comment.parent_id.as_deref().map(strip_table) {
+            Some(parent_id) if known.contains(parent_id) => {
+                children_map.entry(parent_id.to_string()).or_default().push(comment);
+            }
+            _ => roots.push(comment),
+        }
+    }
+
+    fn attach(
+        mut comments: Vec<Comment>,
+        children_map: &mut std::collections::HashMap<String, Vec<Comment>>,
+    ) -> Vec<CommentResponse> {
+        comments.sort_by_key(|comment| comment.created_at);
+        comments
+            .into_iter()
+            .map(|comment| {
+                let children = comment.id.as_deref()
+                    .and_then(|id| children_map.remove(strip_table(id)))
+                    .unwrap_or_default();
+                let replies = attach(children, children_map);
+                let mut response: CommentResponse = comment.into();
+                response.replies = Some(replies);
+                response
+            })
+            .collect()
+    }
+
+    attach(roots, &mut children_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn comment(id: &str, parent_id: Option<&str>, minute: i64) -> Comment {
+        let mut comment = Comment::new(
+            "document:doc1".to_string(),
+            "user1".to_string(),
+            format!("comment {}", id),
+        );
+        comment.id = Some(format!("comment:{}", id));
+        comment.parent_id = parent_id.map(|p| format!("comment:{}", p));
+        comment.created_at = Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minute));
+        comment
+    }
+
+    #[test]
+    fn test_two_level_reply_chain() {
+        let comments = vec![
+            comment("reply2", Some("reply1"), 3),
+            comment("root", None, 0),
+            comment("reply1b", Some("root"), 2),
+            comment("reply1", Some("root"), 1),
+        ];
+
+        let tree = build_comment_tree(comments);
+
+        assert_eq!(tree.len(), 1);
+        let root = &tree[0];
+        assert_eq!(root.id, "comment:root");
+        let replies = root.replies.as_ref().unwrap();
+        let reply_ids: Vec<&str> = replies.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(reply_ids, vec!["comment:reply1", "comment:reply1b"]);
+
+        let nested = replies[0].replies.as_ref().unwrap();
+        assert_eq!(nested.len(), 1);
+        assert_eq!(nested[0].id, "comment:reply2");
+        assert!(nested[0].replies.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_cascade_collects_all_replies() {
+        let comments = vec![
+            comment("root", None, 0),
+            comment("reply1", Some("root"), 1),
+            comment("reply2", Some("reply1"), 2),
+            comment("other", None, 3),
+            comment("other_reply", Some("other"), 4),
+        ];
+
+        let mut descendants = collect_descendant_ids(&comments, "root");
+        descendants.sort();
+
+        assert_eq!(descendants, vec!["reply1".to_string(), "reply2".to_string()]);
+        assert!(collect_descendant_ids(&comments, "reply2").is_empty());
+    }
+
+    #[test]
+    fn test_mentions_notify_only_known_users() {
+        let content = "Ping @alice and @bob, cc @ghost. Mail me at carol@example.com or @alice again.";
+        let handles = parse_mentions(content);
+        assert_eq!(handles, vec!["alice".to_string(), "bob".to_string(), "ghost".to_string()]);
+
+        let resolved: HashMap<String, String> = [
+            ("alice".to_string(), "user_alice".to_string()),
+            ("bob".to_string(), "user_bob".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let accessible: HashSet<String> = ["user_alice".to_string(), "user_bob".to_string()]
+            .into_iter()
+            .collect();
+
+        let notified = plan_mention_notifications(&handles, &resolved, &accessible, "user_author");
+
+        assert_eq!(notified, vec!["user_alice".to_string(), "user_bob".to_string()]);
+    }
+
+    #[test]
+    fn test_mentions_skip_author_and_outsiders() {
+        let handles = vec!["me".to_string(), "outsider".to_string()];
+        let resolved: HashMap<String, String> = [
+            ("me".to_string(), "user_me".to_string()),
+            ("outsider".to_string(), "user_outsider".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let accessible: HashSet<String> = ["user_me".to_string()].into_iter().collect();
+
+        assert!(plan_mention_notifications(&handles, &resolved, &accessible, "user_me").is_empty());
+    }
+
+    #[test]
+    fn test_disabled_comment_mention_produces_no_notification() {
+        let mentions = vec![
+            ("user_muted".to_string(), "设计文档".to_string()),
+            ("user_bob".to_string(), "设计文档".to_string()),
+        ];
+        let preferences: HashMap<String, NotificationPreferences> = [(
+            "user_muted".to_string(),
+            NotificationPreferences { comment_mention: false, ..Default::default() },
+        )]
+        .into_iter()
+        .collect();
+
+        let notified: Vec<&str> = filter_by_preferences(&mentions, &preferences)
+            .into_iter()
+            .map(|(user_id, _)| user_id.as_str())
+            .collect();
+        assert_eq!(notified, vec!["user_bob"]);
+
+        assert!(filter_by_preferences(&mentions[..1], &preferences).is_empty());
+    }
+
+    #[test]
+    fn test_orphaned_reply_becomes_root() {
+        let tree = build_comment_tree(vec![comment("reply", Some("deleted"), 0)]);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].id, "comment:reply");
+    }
+}
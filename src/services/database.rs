@@ -460,4 +460,13 @@ pub struct DatabaseHealth {
     pub connected: bool,
     pub response_time_ms: u64,
     pub error: Option<String>,
-}
\ No newline at end of file
+}
+
+/// 依赖数据库的测试使用的连接，读取 .env 中的数据库配置，数据库需已导入 schemas/docs_schema.sql。
+/// 这类测试标记为 #[ignore]，通过 `cargo test -- --ignored` 运行
+#[cfg(test)]
+pub async fn test_database() -> Arc<Database> {
+    dotenv::dotenv().ok();
+    let config = Config::from_env().expect("test database configuration");
+    Arc::new(Database::new(&config).await.expect("test database connection"))
+}
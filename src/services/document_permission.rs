@@ -0,0 +1,281 @@
+use crate::error::{AppError, Result};
+use crate::models::permission::{DocumentPermission, GrantDocumentPermissionRequest, ResourceType};
+use crate::models::space_member::MemberRole;
+use crate::services::database::Database;
+use crate::services::space_member::SpaceMemberService;
+use crate::utils::redact;
+use std::sync::Arc;
+use tracing::info;
+
+/// 文档级授权只覆盖读写权限，删除、管理等权限仍由空间角色决定
+const DOCUMENT_SCOPED_PERMISSIONS: [&str; 2] = ["docs.read", "docs.write"];
+
+/// 文档级权限（ACL）服务。
+///
+/// 授权作用于文档及其所有子文档，优先级为：
+/// 空间所有者 > 祖先链上最近一级的显式授权（同级用户授权优先于角色授权）> 空间角色
+pub struct DocumentPermissionService {
+    db: Arc<Database>,
+    space_member_service: Arc<SpaceMemberService>,
+}
+
+impl DocumentPermissionService {
+    pub fn new(db: Arc<Database>, space_member_service: Arc<SpaceMemberService>) -> Self {
+        Self {
+            db,
+            space_member_service,
+        }
+    }
+
+    /// 为用户或角色授予文档权限，同一对象在同一文档上的旧授权会被替换
+    pub async fn grant_permission(
+        &self,
+        document_id: &str,
+        request: GrantDocumentPermissionRequest,
+        granted_by: &str,
+    ) -> Result<DocumentPermission> {
+        let document_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+
+        let mut permission = DocumentPermission::new(
+            ResourceType::Document,
+            document_id.to_string(),
+            request.access.permissions(),
+            granted_by.to_string(),
+        );
+        permission = match (request.user_id, request.role) {
+            (Some(user_id), None) => permission.for_user(user_id),
+            (None, Some(role)) => permission.for_role(role.as_str().to_string()),
+            _ => {
+                return Err(AppError::Validation(
+                    "Exactly one of user_id or role must be provided".to_string(),
+                ))
+            }
+        };
+        if let Some(expires_at) = request.expires_at {
+            permission = permission.with_expiry(expires_at);
+        }
+
+        let mut result = self.db.client
+            .query(grant_statements(&permission))
+            .bind(("resource_id", &permission.resource_id))
+            .bind(("user_id", &permission.user_id))
+            .bind(("role_id", &permission.role_id))
+            .bind(("permissions", &permission.permissions))
+            .bind(("granted_by", &permission.granted_by))
+            .bind(("expires_at", &permission.expires_at))
+            .await?
+            .check()?;
+
+        let created: Vec<DocumentPermission> = result.take(1)?;
+        let created = created
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Failed to create document permission")))?;
+
+        info!(
+            "Granted {:?} on document {} to user {:?} / role {:?}",
+            created.permissions, document_id, created.user_id.as_deref().map(redact::user_id), created.role_id
+        );
+        Ok(created)
+    }
+
+    /// 列出直接授予在某个文档上的权限
+    pub async fn list_permissions(&self, document_id: &str) -> Result<Vec<DocumentPermission>> {
+        let document_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+        self.permissions_for_documents(&[document_id.to_string()]).await
+    }
+
+    /// 检查用户对文档的权限。
+    /// ancestor_ids 从当前文档开始逐级向上，用于让父文档上的授权作用到子文档
+    pub async fn check_permission(
+        &self,
+        space_id: &str,
+        ancestor_ids: &[String],
+        user_id: &str,
+        permission: &str,
+    ) -> Result<bool> {
+        let is_owner = self.space_member_service.is_space_owner(space_id, user_id).await?;
+
+        let explicit = if is_owner || !DOCUMENT_SCOPED_PERMISSIONS.contains(&permission) {
+            None
+        } else {
+            let chain: Vec<String> = ancestor_ids
+                .iter()
+                .map(|id| id.strip_prefix("document:").unwrap_or(id).to_string())
+                .collect();
+            let grants = self.permissions_for_documents(&chain).await?;
+            let role = self.space_member_service.get_member_role(space_id, user_id).await?;
+            resolve_explicit_grant(&chain, &grants, user_id, role.as_ref()).map(|perms| perms.to_vec())
+        };
+
+        match explicit_decision(is_owner, explicit.as_deref(), permission) {
+            Some(allowed) => Ok(allowed),
+            None if permission == "docs.read" => {
+                self.space_member_service.can_access_space(space_id, Some(user_id)).await
+            }
+            None => self.space_member_service.check_permission(space_id, user_id, permission).await,
+        }
+    }
+
+    async fn permissions_for_documents(&self, document_ids: &[String]) -> Result<Vec<DocumentPermission>> {
+        if document_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let permissions: Vec<DocumentPermission> = self.db.client
+            .query(
+                "SELECT * FROM document_permission
+                 WHERE resource_type = 'Document' AND resource_id INSIDE $resource_ids
+                 ORDER BY granted_at DESC",
+            )
+            .bind(("resource_ids", document_ids))
+            .await?
+            .take(0)?;
+
+        Ok(permissions.into_iter().filter(|p| !p.is_expired()).collect())
+    }
+}
+
+/// 替换授权的语句：先删除同一对象的旧授权，再创建新授权。
+/// 未设置的 user_id / role_id / expires_at 在库中是 NONE，而 Option 绑定的是 null，
+/// 所以按授权对象拼出 `= NONE` 条件，并且只写入有值的字段
+fn grant_statements(permission: &DocumentPermission) -> String {
+    let (scope_match, scope_set) = if permission.user_id.is_some() {
+        ("user_id = $user_id AND role_id = NONE", "user_id = $user_id")
+    } else {
+        ("user_id = NONE AND role_id = $role_id", "role_id = $role_id")
+    };
+    let expiry = if permission.expires_at.is_some() {
+        "expires_at = <datetime> $expires_at,"
+    } else {
+        ""
+    };
+
+    format!(
+        "BEGIN TRANSACTION;
+         DELETE document_permission WHERE resource_type = 'Document' AND resource_id = $resource_id AND {scope_match};
+         CREATE document_permission SET
+            resource_type = 'Document',
+            resource_id = $resource_id,
+            {scope_set},
+            permissions = $permissions,
+            granted_by = $granted_by,
+            granted_at = time::now(),
+            {expiry}
+            is_inherited = false;
+         COMMIT TRANSACTION;",
+    )
+}
+
+/// 在祖先链上查找生效的显式授权：离当前文档最近的一级生效，
+/// 同一级上用户授权优先于角色授权。没有任何授权时返回 None
+fn resolve_explicit_grant<'a>(
+    chain: &[String],
+    grants: &'a [DocumentPermission],
+    user_id: &str,
+    role: Option<&MemberRole>,
+) -> Option<&'a [String]> {
+    let role_id = role.map(|r| r.as_str());
+
+    chain.iter().find_map(|document_id| {
+        let on_document = || grants.iter().filter(move |g| &g.resource_id == document_id);
+        on_document()
+            .find(|g| g.user_id.as_deref() == Some(user_id))
+            .or_else(|| on_document().find(|g| role_id.is_some() && g.role_id.as_deref() == role_id))
+            .map(|g| g.permissions.as_slice())
+    })
+}
+
+/// 所有者始终放行；文档级权限有显式授权时以授权为准；
+/// 返回 None 表示回退到空间角色判断
+fn explicit_decision(is_owner: bool, explicit: Option<&[String]>, permission: &str) -> Option<bool> {
+    if is_owner {
+        return Some(true);
+    }
+    if !DOCUMENT_SCOPED_PERMISSIONS.contains(&permission) {
+        return None;
+    }
+    explicit.map(|perms| perms.iter().any(|p| p == permission))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::permission::DocumentAccess;
+
+    fn grant_for_user(document_id: &str, user_id: &str, access: DocumentAccess) -> DocumentPermission {
+        DocumentPermission::new(ResourceType::Document, document_id.to_string(), access.permissions(), "owner".to_string())
+            .for_user(user_id.to_string())
+    }
+
+    fn grant_for_role(document_id: &str, role: MemberRole, access: DocumentAccess) -> DocumentPermission {
+        DocumentPermission::new(ResourceType::Document, document_id.to_string(), access.permissions(), "owner".to_string())
+            .for_role(role.as_str().to_string())
+    }
+
+    /// 模拟完整判断：没有显式授权时使用空间角色的默认权限
+    fn allowed(chain: &[&str], grants: &[DocumentPermission], user_id: &str, role: MemberRole, is_owner: bool, permission: &str) -> bool {
+        let chain: Vec<String> = chain.iter().map(|s| s.to_string()).collect();
+        let explicit = resolve_explicit_grant(&chain, grants, user_id, Some(&role));
+        explicit_decision(is_owner, explicit, permission).unwrap_or_else(|| role.can_perform(permission))
+    }
+
+    #[test]
+    fn test_replacing_a_grant_matches_absent_scope_as_none() {
+        let user_grant = grant_statements(&grant_for_user("guide", "viewer-1", DocumentAccess::Write));
+        assert!(user_grant.contains("AND user_id = $user_id AND role_id = NONE;"));
+        assert!(!user_grant.contains("role_id = $role_id"));
+        assert!(!user_grant.contains("expires_at"));
+
+        let role_grant = grant_statements(
+            &grant_for_role("guide", MemberRole::Editor, DocumentAccess::Read).with_expiry(surrealdb::sql::Datetime::default()),
+        );
+        assert!(role_grant.contains("AND user_id = NONE AND role_id = $role_id;"));
+        assert!(!role_grant.contains("user_id = $user_id"));
+        assert!(role_grant.contains("expires_at = <datetime> $expires_at,"));
+        assert!(role_grant.trim_start().starts_with("BEGIN TRANSACTION;"));
+        assert!(role_grant.trim_end().ends_with("COMMIT TRANSACTION;"));
+    }
+
+    #[test]
+    fn test_viewer_granted_write_on_one_document_only() {
+        let grants = vec![grant_for_user("guide", "viewer-1", DocumentAccess::Write)];
+
+        // 被授权的文档及其子文档可写
+        assert!(allowed(&["guide"], &grants, "viewer-1", MemberRole::Viewer, false, "docs.write"));
+        assert!(allowed(&["guide-setup", "guide"], &grants, "viewer-1", MemberRole::Viewer, false, "docs.write"));
+
+        // 其他文档仍按查看者角色处理
+        assert!(!allowed(&["roadmap"], &grants, "viewer-1", MemberRole::Viewer, false, "docs.write"));
+        assert!(allowed(&["roadmap"], &grants, "viewer-1", MemberRole::Viewer, false, "docs.read"));
+
+        // 授权只针对该用户
+        assert!(!allowed(&["guide"], &grants, "viewer-2", MemberRole::Viewer, false, "docs.write"));
+    }
+
+    #[test]
+    fn test_nearest_grant_and_user_grant_take_precedence() {
+        let grants = vec![
+            grant_for_role("handbook", MemberRole::Editor, DocumentAccess::Read),
+            grant_for_role("handbook-drafts", MemberRole::Editor, DocumentAccess::Write),
+            grant_for_user("handbook", "editor-1", DocumentAccess::Write),
+        ];
+
+        // 角色授权可以收紧空间默认权限
+        assert!(!allowed(&["handbook"], &grants, "editor-2", MemberRole::Editor, false, "docs.write"));
+        // 离文档最近的一级授权生效
+        assert!(allowed(&["handbook-drafts", "handbook"], &grants, "editor-2", MemberRole::Editor, false, "docs.write"));
+        // 同一级上用户授权优先于角色授权
+        assert!(allowed(&["handbook"], &grants, "editor-1", MemberRole::Editor, false, "docs.write"));
+    }
+
+    #[test]
+    fn test_owner_always_retains_access() {
+        let grants = vec![grant_for_role("secret", MemberRole::Owner, DocumentAccess::Read)];
+
+        assert!(allowed(&["secret"], &grants, "owner-1", MemberRole::Owner, true, "docs.write"));
+        assert!(allowed(&["secret"], &grants, "owner-1", MemberRole::Owner, true, "docs.delete"));
+        // 删除等权限不受文档级授权影响
+        assert_eq!(explicit_decision(false, Some(&["docs.read".to_string()]), "docs.delete"), None);
+    }
+}
@@ -1,14 +1,17 @@
 use std::sync::Arc;
 use surrealdb::{sql::Thing, Surreal, engine::remote::ws::Client};
 use validator::Validate;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 
 use crate::{
-    error::ApiError,
-    models::document::{Document, CreateDocumentRequest, UpdateDocumentRequest, DocumentTreeNode, DocumentMetadata},
+    config::FeatureConfig,
+    error::AppError,
+    models::document::{Document, CreateDocumentRequest, UpdateDocumentRequest, DocumentTreeNode, DocumentMetadata, DocumentDraft, SaveDraftRequest, TagMatch, RecentKind, RecentDocument, BrokenLink, DocumentLinkReport, SpaceLinkReport, DocumentUpdateResult, DocumentListItem},
     models::version::{CreateVersionRequest, VersionChangeType},
-    services::{auth::AuthService, search::SearchService, versions::VersionService, database::Database},
-    utils::markdown::MarkdownProcessor,
+    models::webhook::WebhookEvent,
+    services::{activity::{ActivityLogger, ActivityRecord}, auth::AuthService, search::SearchService, versions::VersionService, database::Database, file_upload::FileUploadService, space_member::SpaceMemberService, document_permission::DocumentPermissionService, webhook::{self, WebhookService}, spaces::{ensure_space_writable, ensure_document_space_writable}},
+    utils::{export, redact, pagination::{PageRequest, Paginated}, slug::{slug_from_title, unique_slug}, markdown::{internal_link_slug, MarkdownLink, MarkdownProcessor, TocEntry, TocNode}, diff::{diff_lines, diff_text, similarity, DiffSpan}},
 };
 
 #[derive(Clone)]
@@ -18,6 +21,84 @@ pub struct DocumentService {
     markdown_processor: Arc<MarkdownProcessor>,
     search_service: Option<Arc<SearchService>>,
     version_service: Option<Arc<VersionService>>,
+    file_upload_service: Option<Arc<FileUploadService>>,
+    space_member_service: Option<Arc<SpaceMemberService>>,
+    document_permission_service: Option<Arc<DocumentPermissionService>>,
+    webhook_service: Option<Arc<WebhookService>>,
+    limits: DocumentLimits,
+    activity: ActivityLogger,
+}
+
+/// 最近文档列表默认返回条数
+const RECENT_DOCUMENTS_DEFAULT_LIMIT: usize = 20;
+/// 最近文档列表最多返回条数
+const RECENT_DOCUMENTS_MAX_LIMIT: usize = 50;
+/// 去重前最多扫描的原始记录数
+const RECENT_SCAN_LIMIT: usize = 500;
+/// 浏览记录保留天数，超出窗口的记录由后台任务清理
+const ACCESS_LOG_RETENTION_DAYS: u32 = 30;
+/// 带版本条件的文档更新。绑定的时间经 serde 序列化后是字符串，必须转换为 datetime 再比较，
+/// 否则与存储的 datetime 永远不相等
+const CONDITIONAL_UPDATE_QUERY: &str = "UPDATE type::thing('document', $id) CONTENT $document
+    WHERE updated_at = <datetime> $expected_updated_at";
+/// 查询空间内以指定前缀开头的已有 slug。document_space_slug_idx 唯一索引也覆盖已软删除的文档，
+/// 所以这里不能过滤 is_deleted，否则生成的 slug 可能与回收站中的文档冲突
+const TAKEN_SLUGS_QUERY: &str = "SELECT VALUE slug FROM document WHERE space_id = $space_id AND string::starts_with(slug, $base)";
+/// 标题字符数上限，与 CreateDocumentRequest 上的校验一致
+const MAX_TITLE_CHARS: usize = 200;
+/// 自动生成的 slug 长度上限，与 CreateDocumentRequest 上的校验一致
+const MAX_SLUG_CHARS: usize = 100;
+
+/// 文档标题和正文的长度限制。validator 的 length 按字符计算，
+/// 中文等多字节文字的实际大小可能是字符数的3倍，所以正文按字节检查
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocumentLimits {
+    pub max_title_chars: usize,
+    pub max_content_bytes: usize,
+}
+
+impl Default for DocumentLimits {
+    fn default() -> Self {
+        Self {
+            max_title_chars: MAX_TITLE_CHARS,
+            max_content_bytes: 1024 * 1024,
+        }
+    }
+}
+
+impl From<&FeatureConfig> for DocumentLimits {
+    fn from(config: &FeatureConfig) -> Self {
+        Self {
+            max_title_chars: (config.max_document_title_length as usize).clamp(1, MAX_TITLE_CHARS),
+            max_content_bytes: usize::try_from(config.max_document_content_bytes).unwrap_or(usize::MAX),
+        }
+    }
+}
+
+impl DocumentLimits {
+    /// 检查请求中出现的标题和正文，超出时返回带上限的校验错误
+    pub fn check(&self, title: Option<&str>, content: Option<&str>) -> Result<(), AppError> {
+        if let Some(title) = title {
+            if title.chars().count() > self.max_title_chars {
+                return Err(AppError::Validation(format!(
+                    "Title must not exceed {} characters",
+                    self.max_title_chars
+                )));
+            }
+        }
+
+        if let Some(content) = content {
+            if content.len() > self.max_content_bytes {
+                return Err(AppError::Validation(format!(
+                    "Content must not exceed {} bytes (got {} bytes)",
+                    self.max_content_bytes,
+                    content.len()
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl DocumentService {
@@ -27,11 +108,17 @@ impl DocumentService {
         markdown_processor: Arc<MarkdownProcessor>,
     ) -> Self {
         Self {
+            activity: ActivityLogger::new(db.clone()),
             db,
             auth_service,
             markdown_processor,
             search_service: None,
             version_service: None,
+            file_upload_service: None,
+            space_member_service: None,
+            document_permission_service: None,
+            webhook_service: None,
+            limits: DocumentLimits::default(),
         }
     }
 
@@ -45,12 +132,37 @@ impl DocumentService {
         self
     }
 
+    pub fn with_file_upload_service(mut self, file_upload_service: Arc<FileUploadService>) -> Self {
+        self.file_upload_service = Some(file_upload_service);
+        self
+    }
+
+    pub fn with_space_member_service(mut self, space_member_service: Arc<SpaceMemberService>) -> Self {
+        self.space_member_service = Some(space_member_service);
+        self
+    }
+
+    pub fn with_document_permission_service(mut self, document_permission_service: Arc<DocumentPermissionService>) -> Self {
+        self.document_permission_service = Some(document_permission_service);
+        self
+    }
+
+    pub fn with_webhook_service(mut self, webhook_service: Arc<WebhookService>) -> Self {
+        self.webhook_service = Some(webhook_service);
+        self
+    }
+
+    pub fn with_document_limits(mut self, limits: DocumentLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     pub async fn list_documents(
         &self,
         space_id: &str,
         query: crate::models::document::DocumentQuery,
         _user: Option<&crate::services::auth::User>,
-    ) -> Result<serde_json::Value, ApiError> {
+    ) -> Result<serde_json::Value, AppError> {
         use crate::models::document::{DocumentQuery, DocumentListItem, DocumentListResponse};
         
         // 提取实际的空间ID（去掉"space:"前缀，如果存在）
@@ -63,60 +175,115 @@ impl DocumentService {
         // 注意：权限检查已经在路由层完成，这里不再重复检查
 
         // 构建查询条件
-        let page = query.page.unwrap_or(1);
-        let limit = query.limit.unwrap_or(20);
-        let offset = (page - 1) * limit;
+        let page = PageRequest::new(query.page, query.limit);
 
         // 使用Thing类型来查询
         let space_thing = Thing::from(("space", actual_space_id));
 
-        // 查询文档列表
-        let mut documents_query = self.db.client.query(
-            "SELECT * FROM document 
-             WHERE space_id = $space_id 
-             AND is_deleted = false 
-             ORDER BY order_index ASC, created_at DESC 
-             LIMIT $limit START $offset"
+        // 组合过滤条件，分页查询和总数统计共用同一组条件
+        let mut conditions = vec!["space_id = $space_id", "is_deleted = false"];
+        if query.search.is_some() {
+            conditions.push("(title CONTAINS $search OR content CONTAINS $search)");
+        }
+        if query.parent_id.is_some() {
+            conditions.push("parent_id = type::thing('document', $parent_id)");
+        }
+        if query.is_public.is_some() {
+            conditions.push("is_public = $is_public");
+        }
+        if query.author_id.is_some() {
+            conditions.push("author_id = $author_id");
+        }
+
+        // 标签过滤：先找出满足 AND/OR 语义的文档，再作为条件参与分页
+        let tag_filter = query.tags.as_ref().filter(|tags| !tags.is_empty());
+        let mut tagged_ids: Vec<Thing> = Vec::new();
+        if let Some(tags) = tag_filter {
+            let rows: Vec<DocumentTagRow> = self.db.client
+                .query("SELECT document_id, tag_id.name AS tag_name FROM document_tag WHERE tag_id.name INSIDE $tags AND tag_id.space_id = $space_id")
+                .bind(("tags", tags.clone()))
+                .bind(("space_id", space_thing.clone()))
+                .await
+                .map_err(|e| AppError::database_error(e.to_string()))?
+                .take(0)
+                .map_err(|e| AppError::database_error(e.to_string()))?;
+
+            let matching = documents_matching_tags(
+                rows.iter().filter_map(|row| {
+                    row.tag_name.as_deref().map(|name| (row.document_id.id.to_string(), name))
+                }),
+                tags,
+                query.tag_match.unwrap_or_default(),
+            );
+            tagged_ids = matching
+                .iter()
+                .map(|id| Thing::from(("document", id.as_str())))
+                .collect();
+            conditions.push("id INSIDE $tagged_ids");
+        }
+
+        let where_clause = conditions.join(" AND ");
+        let sql = format!(
+            "SELECT * FROM document WHERE {where_clause} ORDER BY order_index ASC, created_at DESC LIMIT $limit START $offset;
+             SELECT count() AS total FROM document WHERE {where_clause} GROUP ALL;",
+            where_clause = where_clause
         );
-        
-        documents_query = documents_query
-            .bind(("space_id", space_thing.clone()))
-            .bind(("limit", limit))
-            .bind(("offset", offset));
 
-        // 添加搜索条件
-        if let Some(search) = &query.search {
-            documents_query = self.db.client.query(
-                "SELECT * FROM document 
-                 WHERE space_id = $space_id 
-                 AND is_deleted = false 
-                 AND (title CONTAINS $search OR content CONTAINS $search)
-                 ORDER BY order_index ASC, created_at DESC 
-                 LIMIT $limit START $offset"
-            )
+        let mut documents_query = self.db.client
+            .query(sql)
             .bind(("space_id", space_thing.clone()))
-            .bind(("search", search))
-            .bind(("limit", limit))
-            .bind(("offset", offset));
+            .bind(("limit", page.limit))
+            .bind(("offset", page.offset()));
+        if let Some(search) = &query.search {
+            documents_query = documents_query.bind(("search", search.clone()));
+        }
+        if let Some(parent_id) = &query.parent_id {
+            let parent_id = parent_id.strip_prefix("document:").unwrap_or(parent_id);
+            documents_query = documents_query.bind(("parent_id", parent_id.to_string()));
+        }
+        if let Some(is_public) = query.is_public {
+            documents_query = documents_query.bind(("is_public", is_public));
+        }
+        if let Some(author_id) = &query.author_id {
+            documents_query = documents_query.bind(("author_id", author_id.clone()));
+        }
+        if tag_filter.is_some() {
+            documents_query = documents_query.bind(("tagged_ids", tagged_ids));
         }
 
         let mut result = documents_query.await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let documents_db: Vec<crate::models::document::DocumentDb> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        // 转换为DocumentListItem
-        let documents: Vec<DocumentListItem> = documents_db.into_iter()
-            .map(|db| {
-                let doc: Document = db.into();
-                doc.into()
-            })
+        let total_rows: Vec<CountRow> = result
+            .take(1)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        let total = total_rows.first().map(|row| row.total).unwrap_or(0);
+
+        // 查询当前页文档关联的标签
+        let page_document_ids: Vec<Thing> = documents_db.iter()
+            .filter_map(|db| db.id.clone())
             .collect();
 
+        let tag_rows: Vec<DocumentTagRow> = self.db.client
+            .query("SELECT document_id, tag_id.name AS tag_name FROM document_tag WHERE document_id IN $document_ids")
+            .bind(("document_ids", page_document_ids))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let mut tags_map: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
+        for row in tag_rows {
+            if let Some(tag_name) = row.tag_name {
+                tags_map.entry(row.document_id.to_string()).or_default().push(tag_name);
+            }
+        }
 
-        // 暂时使用简单的总数计算 - 由于分页问题，暂时查询所有文档获取总数
+        // 查询空间内全部文档，用于统计子文档数量
         let all_docs_query = self.db.client.query(
             "SELECT * FROM document 
              WHERE space_id = $space_id 
@@ -124,39 +291,55 @@ impl DocumentService {
         ).bind(("space_id", space_thing.clone()));
 
         let mut all_docs_result = all_docs_query.await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let all_docs: Vec<crate::models::document::DocumentDb> = all_docs_result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        let total = all_docs.len() as u32;
+        // 复用全量查询结果统计子文档数量，避免逐条查询
+        let children_counts = count_live_children(&all_docs);
 
-        let total_pages = (total + limit - 1) / limit;
+        // 转换为DocumentListItem
+        let documents: Vec<DocumentListItem> = documents_db.into_iter()
+            .map(|db| {
+                let doc: Document = db.into();
+                let doc_id = doc.id.clone().unwrap_or_default();
+                let mut item: DocumentListItem = doc.into();
+                item.children_count = children_counts.get(&doc_id).copied().unwrap_or(0);
+                if let Some(tags) = tags_map.remove(&doc_id) {
+                    for tag in tags {
+                        if !item.tags.contains(&tag) {
+                            item.tags.push(tag);
+                        }
+                    }
+                }
+                item
+            })
+            .collect();
 
-        let response = DocumentListResponse {
-            documents,
-            total,
-            page,
-            limit,
-            total_pages,
-        };
+        let response = DocumentListResponse::from(Paginated::new(documents, total, page));
 
         Ok(serde_json::to_value(response)
-            .map_err(|e| ApiError::Internal(anyhow::anyhow!("Serialization error: {}", e)))?)
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Serialization error: {}", e)))?)
     }
 
     pub async fn create_document(
         &self,
         space_id: &str,
         author_id: &str,
-        request: CreateDocumentRequest,
-    ) -> Result<Document, ApiError> {
+        mut request: CreateDocumentRequest,
+    ) -> Result<Document, AppError> {
+        if request.slug.trim().is_empty() {
+            request.slug = self.generate_document_slug(space_id, &request.title).await?;
+        }
         request.validate()?;
+        self.limits.check(Some(&request.title), request.content.as_deref())?;
+        ensure_space_writable(&self.db, space_id).await?;
 
         // 检查slug在空间内是否唯一
         if self.document_slug_exists(space_id, &request.slug).await? {
-            return Err(ApiError::Conflict("Document slug already exists in this space".to_string()));
+            return Err(AppError::Conflict("Document slug already exists in this space".to_string()));
         }
 
         // 提取space_id的实际ID部分（去掉"space:"前缀）
@@ -227,16 +410,16 @@ impl DocumentService {
         
         let mut result = query_builder
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
             
         let created: Vec<crate::models::document::DocumentDb> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let created_document_db = created
             .into_iter()
             .next()
-            .ok_or_else(|| ApiError::InternalServerError("Failed to create document".to_string()))?;
+            .ok_or_else(|| AppError::internal_server_error("Failed to create document".to_string()))?;
         
         // 转换为普通的 Document
         let created_document: Document = created_document_db.into();
@@ -271,16 +454,29 @@ impl DocumentService {
             ).await;
         }
 
+        let record = ActivityRecord::new(
+            author_id,
+            "document_created",
+            "document",
+            created_document.id.as_deref().unwrap_or_default(),
+            space_id,
+        ).with_title(created_document.title.clone());
+        self.activity.log(record).await;
+
+        if let Some(webhook_service) = &self.webhook_service {
+            webhook_service.dispatch(space_id, WebhookEvent::DocumentCreated, webhook::document_event_data(&created_document));
+        }
+
         Ok(created_document)
     }
 
-    pub async fn get_document(&self, document_id: &str) -> Result<Document, ApiError> {
+    pub async fn get_document(&self, document_id: &str) -> Result<Document, AppError> {
         let document: Option<Document> = self.db.client
             .select(("document", document_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        document.ok_or_else(|| ApiError::NotFound("Document not found".to_string()))
+        document.ok_or_else(|| AppError::NotFound("Document not found".to_string()))
     }
 
     pub async fn update_document(
@@ -288,15 +484,219 @@ impl DocumentService {
         document_id: &str,
         editor_id: &str,
         request: UpdateDocumentRequest,
-    ) -> Result<Document, ApiError> {
+    ) -> Result<DocumentUpdateResult, AppError> {
+        // 只有显式开启时才需要记录旧 slug。空间文档在改名前加载，加载失败时不会留下已改名但未改写链接的状态
+        let backlink_candidates = if request.rewrite_backlinks && request.slug.is_some() {
+            let current = self.get_document(document_id).await?;
+            let documents = self.space_documents_for_links(&current.space_id).await?;
+            Some((current.slug, documents))
+        } else {
+            None
+        };
+
+        let document = self.update_document_with_change(
+            document_id,
+            editor_id,
+            request,
+            "Document updated".to_string(),
+            VersionChangeType::Updated,
+        ).await?;
+
+        // 保存成功后清除该用户的草稿
+        if let Err(e) = self.discard_draft(document_id, editor_id).await {
+            tracing::warn!("Failed to clear draft of document {} for user {}: {}", document_id, editor_id, e);
+        }
+
+        // 改名已经提交，单个文档改写失败不再让整个请求失败，而是在结果中列出
+        let (backlinks_updated, backlinks_failed) = match backlink_candidates {
+            Some((old_slug, documents)) if old_slug != document.slug => {
+                let documents = with_renamed_document(documents, &document);
+                self.rewrite_backlinks(documents, &old_slug, &document.slug, editor_id).await
+            }
+            _ => (0, Vec::new()),
+        };
+
+        if let Some(webhook_service) = &self.webhook_service {
+            webhook_service.dispatch(&document.space_id, WebhookEvent::DocumentUpdated, webhook::document_event_data(&document));
+        }
+
+        Ok(DocumentUpdateResult { document, backlinks_updated, backlinks_failed })
+    }
+
+    /// 把指向旧 slug 的站内链接改写为新 slug，每个被修改的文档都会创建新版本。
+    /// 只改写编辑者有 docs.write 权限的文档，返回被修改的文档数和改写失败或无权改写的文档 ID
+    async fn rewrite_backlinks(
+        &self,
+        documents: Vec<Document>,
+        old_slug: &str,
+        new_slug: &str,
+        editor_id: &str,
+    ) -> (usize, Vec<String>) {
+        let mut updated = 0;
+        let mut failed = Vec::new();
+
+        for document in documents {
+            let links = self.markdown_processor.extract_links(&document.content);
+            let Some(content) = rewrite_slug_links(&document.content, &links, old_slug, new_slug) else {
+                continue;
+            };
+            let Some(linker_id) = document.id.as_deref() else {
+                continue;
+            };
+            let linker_id = linker_id.strip_prefix("document:").unwrap_or(linker_id);
+
+            // 编辑者对引用文档没有写权限时不改写，保留旧链接并列入失败列表
+            match self.check_document_permission(&document, editor_id, "docs.write").await {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::warn!("User {} cannot write document {}, skipped rewriting links to {}", redact::user_id(editor_id), linker_id, old_slug);
+                    failed.push(linker_id.to_string());
+                    continue;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to check write permission on document {}: {}", linker_id, e);
+                    failed.push(linker_id.to_string());
+                    continue;
+                }
+            }
+
+            let request = UpdateDocumentRequest {
+                title: None,
+                slug: None,
+                content: Some(content),
+                excerpt: None,
+                is_public: None,
+                parent_id: None,
+                order_index: None,
+                metadata: None,
+                expected_updated_at: None,
+                rewrite_backlinks: false,
+            };
+            let result = self.update_document_with_change(
+                linker_id,
+                editor_id,
+                request,
+                format!("Updated links from \"{}\" to \"{}\"", old_slug, new_slug),
+                VersionChangeType::Updated,
+            ).await;
+
+            match result {
+                Ok(_) => updated += 1,
+                Err(e) => {
+                    tracing::error!("Failed to rewrite links from {} to {} in document {}: {}", old_slug, new_slug, linker_id, e);
+                    failed.push(linker_id.to_string());
+                }
+            }
+        }
+
+        tracing::info!("Rewrote links from {} to {} in {} documents, {} failed", old_slug, new_slug, updated, failed.len());
+        (updated, failed)
+    }
+
+    /// 保存草稿：按文档和用户覆盖写入，不修改文档本身，也不创建版本
+    pub async fn save_draft(
+        &self,
+        document_id: &str,
+        editor_id: &str,
+        request: SaveDraftRequest,
+    ) -> Result<DocumentDraft, AppError> {
+        request.validate()?;
+        self.limits.check(request.title.as_deref(), Some(&request.content))?;
+
+        let actual_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+        // 确认文档存在且未被删除，回收站中的文档不能保存草稿
+        self.get_document_by_id(actual_id).await?;
+
+        let drafts: Vec<DocumentDraft> = self.db.client
+            .query("UPDATE type::thing('document_draft', [$document_id, $user_id]) CONTENT {
+                    document_id: $document_id,
+                    user_id: $user_id,
+                    title: $title,
+                    content: $content,
+                    updated_at: time::now()
+                } RETURN document_id, user_id, title, content, updated_at")
+            .bind(("document_id", actual_id))
+            .bind(("user_id", editor_id))
+            .bind(("title", request.title))
+            .bind(("content", request.content))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        drafts
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::internal_server_error("Failed to save draft".to_string()))
+    }
+
+    /// 获取当前用户的草稿
+    pub async fn get_draft(&self, document_id: &str, user_id: &str) -> Result<Option<DocumentDraft>, AppError> {
+        let actual_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+
+        let drafts: Vec<DocumentDraft> = self.db.client
+            .query("SELECT document_id, user_id, title, content, updated_at
+                FROM type::thing('document_draft', [$document_id, $user_id])")
+            .bind(("document_id", actual_id))
+            .bind(("user_id", user_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(drafts.into_iter().next())
+    }
+
+    /// 丢弃当前用户的草稿
+    pub async fn discard_draft(&self, document_id: &str, user_id: &str) -> Result<(), AppError> {
+        let actual_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+
+        self.db.client
+            .query("DELETE type::thing('document_draft', [$document_id, $user_id])")
+            .bind(("document_id", actual_id))
+            .bind(("user_id", user_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 更新文档并以指定的变更类型和摘要记录新版本
+    pub async fn update_document_with_change(
+        &self,
+        document_id: &str,
+        editor_id: &str,
+        request: UpdateDocumentRequest,
+        version_summary: String,
+        change_type: VersionChangeType,
+    ) -> Result<Document, AppError> {
         request.validate()?;
+        self.limits.check(request.title.as_deref(), request.content.as_deref())?;
 
         let mut document = self.get_document(document_id).await?;
+        ensure_space_writable(&self.db, &document.space_id).await?;
+
+        let expected_updated_at = request.expected_updated_at;
+        if !is_expected_revision(document.updated_at, expected_updated_at) {
+            return Err(stale_write_error(&document));
+        }
 
         if let Some(title) = request.title {
             document.title = title;
         }
 
+        if let Some(slug) = request.slug {
+            if slug != document.slug {
+                let space_id = document.space_id.strip_prefix("space:").unwrap_or(&document.space_id);
+                if self.document_slug_exists(space_id, &slug).await? {
+                    return Err(AppError::Conflict("Document slug already exists in this space".to_string()));
+                }
+                document.slug = slug;
+            }
+        }
+
         if let Some(content) = request.content {
             let processed = self.markdown_processor.process(&content).await?;
             document.content = content;
@@ -316,14 +716,34 @@ impl DocumentService {
         document.updated_by = Some(editor_id.to_string());
         document.updated_at = Some(chrono::Utc::now());
 
-        let updated: Option<Document> = self.db.client
-            .update(("document", document_id))
-            .content(document.clone())
-            .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+        let updated: Option<Document> = match expected_updated_at {
+            // 带条件写入，避免读取和写入之间被其他人修改
+            Some(expected) => {
+                let updated: Vec<Document> = self.db.client
+                    .query(CONDITIONAL_UPDATE_QUERY)
+                    .bind(("id", document_id.strip_prefix("document:").unwrap_or(document_id)))
+                    .bind(("document", document.clone()))
+                    .bind(("expected_updated_at", expected))
+                    .await
+                    .map_err(|e| AppError::database_error(e.to_string()))?
+                    .take(0)
+                    .map_err(|e| AppError::database_error(e.to_string()))?;
+
+                if updated.is_empty() {
+                    let current = self.get_document(document_id).await?;
+                    return Err(stale_write_error(&current));
+                }
+                updated.into_iter().next()
+            }
+            None => self.db.client
+                .update(("document", document_id))
+                .content(document.clone())
+                .await
+                .map_err(|e| AppError::database_error(e.to_string()))?,
+        };
 
         let updated_document = updated
-            .ok_or_else(|| ApiError::InternalServerError("Failed to update document".to_string()))?;
+            .ok_or_else(|| AppError::internal_server_error("Failed to update document".to_string()))?;
 
         // 更新搜索索引
         if let Some(search_service) = &self.search_service {
@@ -339,13 +759,22 @@ impl DocumentService {
             ).await;
         }
 
+        let record = document_change_activity(
+            editor_id,
+            document_id,
+            &updated_document.space_id,
+            &updated_document.title,
+            &change_type,
+        );
+        self.activity.log(record).await;
+
         // 创建新版本
         if let Some(version_service) = &self.version_service {
             let version_request = CreateVersionRequest {
                 title: updated_document.title.clone(),
                 content: updated_document.content.clone(),
-                summary: Some("Document updated".to_string()),
-                change_type: VersionChangeType::Updated,
+                summary: Some(version_summary),
+                change_type,
             };
             
             let _ = version_service.create_version(
@@ -358,31 +787,313 @@ impl DocumentService {
         Ok(updated_document)
     }
 
-    pub async fn delete_document(&self, document_id: &str, deleter_id: &str) -> Result<(), ApiError> {
-        let mut document = self.get_document(document_id).await?;
-        
-        document.soft_delete(deleter_id.to_string());
+    /// 软删除文档。cascade 为 true 时连同整个子树一起删除，
+    /// 否则将直接子文档挂到被删除文档的父级（或根级）下
+    pub async fn delete_document(&self, document_id: &str, deleter_id: &str, cascade: bool) -> Result<(), AppError> {
+        let actual_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+        let document = self.get_document_by_id(actual_id).await?;
+        ensure_space_writable(&self.db, &document.space_id).await?;
+        let actual_space_id = document.space_id.strip_prefix("space:").unwrap_or(&document.space_id);
 
-        let _: Option<Document> = self.db.client
-            .update(("document", document_id))
-            .content(document)
+        let all_documents_db: Vec<crate::models::document::DocumentDb> = self.db.client
+            .query("SELECT * FROM document WHERE space_id = $space_id AND is_deleted = false")
+            .bind(("space_id", Thing::from(("space", actual_space_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let parent_map: std::collections::HashMap<String, Option<String>> = all_documents_db
+            .into_iter()
+            .filter_map(|doc| {
+                let id = doc.id?.id.to_string();
+                Some((id, doc.parent_id.map(|p| p.id.to_string())))
+            })
+            .collect();
+
+        let plan = plan_deletion(&parent_map, actual_id, cascade);
+
+        let mut sql = String::from("BEGIN TRANSACTION;\n");
+        let mut bindings = std::collections::HashMap::new();
+        for (index, id) in plan.deleted.iter().enumerate() {
+            sql.push_str(&format!(
+                "UPDATE type::thing('document', $deleted_{index}) SET is_deleted = true, deleted_by = $deleter_id, deleted_at = <datetime> $deleted_at;\n",
+                index = index
+            ));
+            bindings.insert(format!("deleted_{}", index), serde_json::Value::String(id.clone()));
+        }
+        for (index, (id, new_parent)) in plan.reparented.iter().enumerate() {
+            if new_parent.is_some() {
+                sql.push_str(&format!(
+                    "UPDATE type::thing('document', $child_{index}) SET parent_id = type::thing('document', $parent_{index}), updated_by = $deleter_id;\n",
+                    index = index
+                ));
+            } else {
+                sql.push_str(&format!(
+                    "UPDATE type::thing('document', $child_{index}) SET parent_id = NONE, updated_by = $deleter_id;\n",
+                    index = index
+                ));
+            }
+            bindings.insert(format!("child_{}", index), serde_json::Value::String(id.clone()));
+            if let Some(parent) = new_parent {
+                bindings.insert(format!("parent_{}", index), serde_json::Value::String(parent.clone()));
+            }
+        }
+        sql.push_str("COMMIT TRANSACTION;");
+        bindings.insert("deleter_id".to_string(), serde_json::Value::String(deleter_id.to_string()));
+        bindings.insert(
+            "deleted_at".to_string(),
+            serde_json::to_value(Utc::now()).map_err(|e| AppError::Internal(anyhow::anyhow!("Serialization error: {}", e)))?,
+        );
+
+        self.db.client
+            .query(sql)
+            .bind(bindings)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            // 事务失败时不能继续清理搜索索引和附件
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        tracing::info!(
+            "User {} deleted {} documents (cascade: {}), reparented {} children",
+            redact::user_id(deleter_id),
+            plan.deleted.len(),
+            cascade,
+            plan.reparented.len()
+        );
 
         // 从搜索索引中删除
         if let Some(search_service) = &self.search_service {
-            let _ = search_service.delete_index(document_id).await;
+            for id in &plan.deleted {
+                let _ = search_service.delete_index(id).await;
+            }
+        }
+
+        // 被删除文档的附件等待清理，恢复文档时取消标记
+        if let Some(file_upload_service) = &self.file_upload_service {
+            if let Err(e) = file_upload_service.mark_attachments_for_cleanup(&plan.deleted, true).await {
+                tracing::warn!("Failed to mark attachments of deleted documents for cleanup: {}", e);
+            }
         }
 
+        // 级联删除的子文档不单独记录，动态中只出现被删除的文档本身
+        let record = ActivityRecord::new(deleter_id, "document_deleted", "document", actual_id, &document.space_id)
+            .with_title(document.title.clone());
+        self.activity.log(record).await;
+
         Ok(())
     }
 
+    /// 将空间内全部文档按树结构导出为 Markdown ZIP 包
+    pub async fn export_space_markdown(&self, space_id: &str, space_name: &str) -> Result<Vec<u8>, AppError> {
+        let actual_space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+
+        let tree = self.get_document_tree(actual_space_id).await?;
+        let entries = export::plan_export_paths(&tree);
+
+        let documents_db: Vec<crate::models::document::DocumentDb> = self.db.client
+            .query("SELECT * FROM document WHERE space_id = $space_id AND is_deleted = false")
+            .bind(("space_id", Thing::from(("space", actual_space_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let contents: std::collections::HashMap<String, String> = documents_db
+            .into_iter()
+            .map(|db| db.into())
+            .filter_map(|doc: Document| doc.id.map(|id| (id, doc.content)))
+            .collect();
+
+        let mut files = vec![(
+            "index.md".to_string(),
+            export::build_index(space_name, &entries).into_bytes(),
+        )];
+
+        // 打包文档中引用的已上传文件
+        let mut assets = std::collections::HashMap::new();
+        if let Some(file_upload_service) = &self.file_upload_service {
+            for entry in &entries {
+                let content = contents.get(&entry.document_id).map(|c| c.as_str()).unwrap_or("");
+                for file_id in export::collect_uploaded_file_ids(content) {
+                    if assets.contains_key(&file_id) {
+                        continue;
+                    }
+                    match file_upload_service.get_file_content(&file_id).await {
+                        Ok((bytes, _mime_type, original_name)) => {
+                            let path = export::asset_path(&file_id, &original_name);
+                            files.push((path.clone(), bytes));
+                            assets.insert(file_id, path);
+                        }
+                        Err(e) => {
+                            tracing::warn!("Skipping missing asset {} during export: {}", file_id, e);
+                        }
+                    }
+                }
+            }
+        }
+
+        for entry in &entries {
+            let content = contents.get(&entry.document_id).map(|c| c.as_str()).unwrap_or("");
+            let rewritten = export::rewrite_uploaded_file_links(content, entry.depth, &assets);
+            files.push((entry.path.clone(), rewritten.into_bytes()));
+        }
+
+        tracing::info!(
+            "Exported {} documents and {} assets from space {}",
+            entries.len(),
+            assets.len(),
+            actual_space_id
+        );
+
+        export::write_zip(files)
+    }
+
+    /// 根据ID获取文档，包括已软删除的文档
+    pub async fn get_document_including_deleted(&self, document_id: &str) -> Result<Document, AppError> {
+        let actual_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+
+        let documents_db: Vec<crate::models::document::DocumentDb> = self.db.client
+            .query("SELECT * FROM document WHERE id = $id")
+            .bind(("id", Thing::from(("document", actual_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        documents_db
+            .into_iter()
+            .next()
+            .map(|db| db.into())
+            .ok_or_else(|| AppError::NotFound("Document not found".to_string()))
+    }
+
+    /// 恢复已软删除的文档，同一次级联删除中被删除的子文档会一并恢复
+    pub async fn restore_document(&self, document_id: &str, restorer_id: &str) -> Result<Document, AppError> {
+        let actual_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+
+        let document = self.get_document_including_deleted(actual_id).await?;
+
+        if !document.is_deleted {
+            return Err(AppError::Validation("Document is not deleted".to_string()));
+        }
+        ensure_space_writable(&self.db, &document.space_id).await?;
+
+        let actual_space_id = document.space_id.strip_prefix("space:").unwrap_or(&document.space_id).to_string();
+
+        // 父文档仍处于删除状态时不允许单独恢复，需先恢复父文档
+        if let Some(parent_id) = &document.parent_id {
+            let parent_id = parent_id.strip_prefix("document:").unwrap_or(parent_id);
+            let parent = self.get_document_including_deleted(parent_id).await?;
+            if parent.is_deleted {
+                return Err(AppError::Conflict(
+                    "Parent document is deleted; restore the parent document first".to_string(),
+                ));
+            }
+        }
+
+        if self.document_slug_exists(&actual_space_id, &document.slug).await? {
+            return Err(AppError::Conflict("Document slug already exists in this space".to_string()));
+        }
+
+        // 找出与该文档在同一次级联删除中被删除的后代文档
+        let deleted_db: Vec<crate::models::document::DocumentDb> = self.db.client
+            .query("SELECT * FROM document WHERE space_id = $space_id AND is_deleted = true")
+            .bind(("space_id", Thing::from(("space", actual_space_id.as_str()))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let parent_map: std::collections::HashMap<String, Option<String>> = deleted_db
+            .into_iter()
+            .filter(|doc| doc.deleted_at == document.deleted_at && doc.deleted_by == document.deleted_by)
+            .filter_map(|doc| {
+                let id = doc.id?.id.to_string();
+                Some((id, doc.parent_id.map(|p| p.id.to_string())))
+            })
+            .collect();
+
+        let restored_ids = plan_deletion(&parent_map, actual_id, true).deleted;
+
+        let mut sql = String::from("BEGIN TRANSACTION;\n");
+        let mut bindings = std::collections::HashMap::new();
+        for (index, id) in restored_ids.iter().enumerate() {
+            sql.push_str(&format!(
+                "UPDATE type::thing('document', $restored_{index}) SET is_deleted = false, deleted_at = NONE, deleted_by = NONE, updated_by = $restorer_id;\n",
+                index = index
+            ));
+            bindings.insert(format!("restored_{}", index), serde_json::Value::String(id.clone()));
+        }
+        sql.push_str("COMMIT TRANSACTION;");
+        bindings.insert("restorer_id".to_string(), serde_json::Value::String(restorer_id.to_string()));
+
+        self.db.client
+            .query(sql)
+            .bind(bindings)
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        tracing::info!("User {} restored {} documents", redact::user_id(restorer_id), restored_ids.len());
+
+        if let Some(file_upload_service) = &self.file_upload_service {
+            if let Err(e) = file_upload_service.mark_attachments_for_cleanup(&restored_ids, false).await {
+                tracing::warn!("Failed to restore attachments of restored documents: {}", e);
+            }
+        }
+
+        // 重新加入搜索索引
+        if let Some(search_service) = &self.search_service {
+            for id in &restored_ids {
+                if let Ok(restored) = self.get_document_by_id(id).await {
+                    let _ = search_service.update_document_index(
+                        id,
+                        &restored.space_id,
+                        &restored.title,
+                        &restored.content,
+                        &restored.excerpt.clone().unwrap_or_default(),
+                        restored.metadata.tags.clone(),
+                        &restored.author_id,
+                        restored.is_public,
+                    ).await;
+                }
+            }
+        }
+
+        self.get_document_by_id(actual_id).await
+    }
+
+    /// 获取空间内已软删除的文档（回收站）
+    pub async fn list_deleted_documents(&self, space_id: &str) -> Result<Vec<Document>, AppError> {
+        let actual_space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+
+        let query = "
+            SELECT * FROM document
+            WHERE space_id = $space_id
+            AND is_deleted = true
+            ORDER BY deleted_at DESC
+        ";
+
+        let documents_db: Vec<crate::models::document::DocumentDb> = self.db.client
+            .query(query)
+            .bind(("space_id", Thing::from(("space", actual_space_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(documents_db.into_iter().map(|db| db.into()).collect())
+    }
+
     pub async fn get_space_documents(
         &self,
         space_id: &str,
         page: i64,
         per_page: i64,
-    ) -> Result<Vec<Document>, ApiError> {
+    ) -> Result<Vec<Document>, AppError> {
         let offset = (page - 1) * per_page;
         
         let query = "
@@ -399,9 +1110,9 @@ impl DocumentService {
             .bind(("limit", per_page))
             .bind(("offset", offset))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(documents)
     }
@@ -409,7 +1120,7 @@ impl DocumentService {
     pub async fn get_document_children(
         &self,
         parent_id: &str,
-    ) -> Result<Vec<Document>, ApiError> {
+    ) -> Result<Vec<Document>, AppError> {
         let query = "
             SELECT * FROM document 
             WHERE parent_id = $parent_id 
@@ -421,9 +1132,9 @@ impl DocumentService {
             .query(query)
             .bind(("parent_id", Thing::from(("document", parent_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let children: Vec<Document> = children_db.into_iter()
             .map(|db| db.into())
@@ -435,7 +1146,7 @@ impl DocumentService {
     pub async fn get_document_children_by_id(
         &self,
         parent_id: &str,
-    ) -> Result<Vec<Document>, ApiError> {
+    ) -> Result<Vec<Document>, AppError> {
         // 分离前缀和实际ID
         let actual_id = if parent_id.starts_with("document:") {
             parent_id.strip_prefix("document:").unwrap()
@@ -446,7 +1157,7 @@ impl DocumentService {
         self.get_document_children(actual_id).await
     }
 
-    pub async fn get_document_tree(&self, space_id: &str) -> Result<Vec<DocumentTreeNode>, ApiError> {
+    pub async fn get_document_tree(&self, space_id: &str) -> Result<Vec<DocumentTreeNode>, AppError> {
         tracing::debug!("Getting document tree for space_id: {}", space_id);
         
         // 提取实际的空间ID（去掉"space:"前缀，如果存在）
@@ -471,9 +1182,9 @@ impl DocumentService {
             .query(query)
             .bind(("space_id", space_thing))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
             
         tracing::debug!("Found {} documents in database", all_documents_db.len());
             
@@ -557,11 +1268,15 @@ impl DocumentService {
         new_parent_id: Option<String>,
         new_order_index: Option<i32>,
         mover_id: &str,
-    ) -> Result<Document, ApiError> {
+    ) -> Result<Document, AppError> {
         let mut document = self.get_document(document_id).await?;
+        ensure_space_writable(&self.db, &document.space_id).await?;
 
         if let Some(parent_id) = new_parent_id {
             self.verify_parent_document(&document.space_id.to_string(), &parent_id).await?;
+            if self.would_create_cycle(document_id, &parent_id).await? {
+                return Err(AppError::Validation("Cannot move a document under its own descendant".to_string()));
+            }
             document.parent_id = Some(parent_id);
         } else {
             document.parent_id = None;
@@ -578,18 +1293,279 @@ impl DocumentService {
             .update(("document", document_id))
             .content(document.clone())
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        updated.ok_or_else(|| ApiError::InternalServerError("Failed to move document".to_string()))
-    }
+        let moved = updated.ok_or_else(|| AppError::internal_server_error("Failed to move document".to_string()))?;
 
-    pub async fn get_document_breadcrumbs(&self, document_id: &str) -> Result<Vec<Document>, ApiError> {
-        let mut breadcrumbs = Vec::new();
-        let mut current_id = Some(document_id.to_string());
+        let record = ActivityRecord::new(mover_id, "document_moved", "document", document_id, &moved.space_id)
+            .with_title(moved.title.clone());
+        self.activity.log(record).await;
 
-        while let Some(id) = current_id {
-            let document = self.get_document(&id).await?;
-            current_id = document.parent_id.as_ref().map(|p| p.to_string());
+        Ok(moved)
+    }
+
+    /// 将文档连同整个子树转移到另一个空间。
+    /// 目标空间中 slug 冲突的文档会自动追加 -copy 后缀；权限检查由调用方负责
+    pub async fn transfer_document(
+        &self,
+        document_id: &str,
+        target_space_id: &str,
+        new_parent_id: Option<&str>,
+        mover_id: &str,
+    ) -> Result<Document, AppError> {
+        let actual_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+        let target_space_id = target_space_id.strip_prefix("space:").unwrap_or(target_space_id);
+        let document = self.get_document_by_id(actual_id).await?;
+        let source_space_id = document.space_id.strip_prefix("space:").unwrap_or(&document.space_id);
+
+        if source_space_id == target_space_id {
+            return Err(AppError::Validation("Document is already in the target space; use move instead".to_string()));
+        }
+        // 源空间和目标空间都不能处于归档状态
+        ensure_space_writable(&self.db, source_space_id).await?;
+        ensure_space_writable(&self.db, target_space_id).await?;
+
+        let new_parent_id = new_parent_id.map(|id| id.strip_prefix("document:").unwrap_or(id));
+        if let Some(parent_id) = new_parent_id {
+            self.verify_parent_document(target_space_id, parent_id).await?;
+        }
+
+        // 子树包含已删除的后代，保证回收站中的文档与父级保持在同一空间
+        let source_documents: Vec<crate::models::document::DocumentDb> = self.db.client
+            .query("SELECT * FROM document WHERE space_id = $space_id")
+            .bind(("space_id", Thing::from(("space", source_space_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let parent_map: std::collections::HashMap<String, Option<String>> = source_documents
+            .iter()
+            .filter_map(|doc| {
+                let id = doc.id.as_ref()?.id.to_string();
+                Some((id, doc.parent_id.as_ref().map(|p| p.id.to_string())))
+            })
+            .collect();
+        let subtree = plan_deletion(&parent_map, actual_id, true).deleted;
+
+        // document_space_slug_idx 同样覆盖回收站中的文档，冲突集合需要包含已删除的文档
+        let target_slugs: Vec<String> = self.db.client
+            .query("SELECT VALUE slug FROM document WHERE space_id = $space_id")
+            .bind(("space_id", Thing::from(("space", target_space_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let moved: Vec<(&String, &crate::models::document::DocumentDb)> = subtree
+            .iter()
+            .filter_map(|id| {
+                source_documents
+                    .iter()
+                    .find(|doc| doc.id.as_ref().map(|thing| thing.id.to_string()).as_deref() == Some(id.as_str()))
+                    .map(|doc| (id, doc))
+            })
+            .collect();
+        let slugs = resolve_slug_collisions(
+            moved.iter().map(|(_, doc)| doc.slug.as_str()),
+            &target_slugs.into_iter().collect(),
+        );
+
+        let mut sql = String::from("BEGIN TRANSACTION;\n");
+        let mut bindings = std::collections::HashMap::new();
+        for (index, ((id, _), slug)) in moved.iter().zip(&slugs).enumerate() {
+            let parent_clause = if id.as_str() != actual_id {
+                ""
+            } else if new_parent_id.is_some() {
+                ", parent_id = type::thing('document', $new_parent_id)"
+            } else {
+                ", parent_id = NONE"
+            };
+            sql.push_str(&format!(
+                "UPDATE type::thing('document', $id_{index}) SET space_id = type::thing('space', $target_space_id), slug = $slug_{index}{parent}, updated_by = $mover_id, updated_at = time::now();\n",
+                index = index,
+                parent = parent_clause
+            ));
+            bindings.insert(format!("id_{}", index), serde_json::Value::String(id.to_string()));
+            bindings.insert(format!("slug_{}", index), serde_json::Value::String(slug.clone()));
+        }
+        sql.push_str("COMMIT TRANSACTION;");
+        bindings.insert("target_space_id".to_string(), serde_json::Value::String(target_space_id.to_string()));
+        bindings.insert("mover_id".to_string(), serde_json::Value::String(mover_id.to_string()));
+        if let Some(parent_id) = new_parent_id {
+            bindings.insert("new_parent_id".to_string(), serde_json::Value::String(parent_id.to_string()));
+        }
+
+        self.db.client
+            .query(sql)
+            .bind(bindings)
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        tracing::info!(
+            "User {} transferred {} documents from space {} to space {}",
+            redact::user_id(mover_id),
+            moved.len(),
+            source_space_id,
+            target_space_id
+        );
+
+        // 更新搜索索引中的所属空间
+        if let Some(search_service) = &self.search_service {
+            let target_space = Thing::from(("space", target_space_id)).to_string();
+            for (id, doc) in moved.iter().filter(|(_, doc)| !doc.is_deleted) {
+                let _ = search_service.update_document_index(
+                    id,
+                    &target_space,
+                    &doc.title,
+                    &doc.content,
+                    &doc.excerpt.clone().unwrap_or_default(),
+                    doc.metadata.tags.clone(),
+                    &doc.author_id,
+                    doc.is_public,
+                ).await;
+            }
+        }
+
+        self.get_document_by_id(actual_id).await
+    }
+
+    /// 判断将文档移动到 new_parent_id 之下是否会形成循环
+    pub async fn would_create_cycle(&self, document_id: &str, new_parent_id: &str) -> Result<bool, AppError> {
+        let document_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+        let new_parent_id = new_parent_id.strip_prefix("document:").unwrap_or(new_parent_id);
+
+        if document_id == new_parent_id {
+            return Ok(true);
+        }
+
+        let document = self.get_document_by_id(document_id).await?;
+        let actual_space_id = document.space_id.strip_prefix("space:").unwrap_or(&document.space_id);
+
+        // 一次性加载空间内的父子关系，避免逐级查询
+        let all_documents_db: Vec<crate::models::document::DocumentDb> = self.db.client
+            .query("SELECT * FROM document WHERE space_id = $space_id AND is_deleted = false")
+            .bind(("space_id", Thing::from(("space", actual_space_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let parent_map: std::collections::HashMap<String, Option<String>> = all_documents_db
+            .into_iter()
+            .filter_map(|doc| {
+                let id = doc.id?.id.to_string();
+                Some((id, doc.parent_id.map(|p| p.id.to_string())))
+            })
+            .collect();
+
+        Ok(creates_cycle(&parent_map, document_id, new_parent_id))
+    }
+
+    /// 批量重排同级文档：按 ordered_ids 的顺序在单个事务中重写 order_index
+    pub async fn reorder_documents(
+        &self,
+        space_id: &str,
+        parent_id: Option<&str>,
+        ordered_ids: Vec<String>,
+        mover_id: &str,
+    ) -> Result<Vec<Document>, AppError> {
+        let actual_space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+        let actual_parent_id = parent_id.map(|p| p.strip_prefix("document:").unwrap_or(p));
+        ensure_space_writable(&self.db, actual_space_id).await?;
+
+        let ordered_ids: Vec<String> = ordered_ids
+            .iter()
+            .map(|id| id.strip_prefix("document:").unwrap_or(id).to_string())
+            .collect();
+
+        if let Some(parent_id) = actual_parent_id {
+            self.verify_parent_document(actual_space_id, parent_id).await?;
+        }
+
+        // 查询当前父节点下的全部同级文档
+        let query = if actual_parent_id.is_some() {
+            "
+                SELECT * FROM document
+                WHERE space_id = $space_id
+                AND parent_id = $parent_id
+                AND is_deleted = false
+            "
+        } else {
+            "
+                SELECT * FROM document
+                WHERE space_id = $space_id
+                AND (parent_id IS NONE OR parent_id IS NULL)
+                AND is_deleted = false
+            "
+        };
+
+        let mut query_builder = self.db.client
+            .query(query)
+            .bind(("space_id", Thing::from(("space", actual_space_id))));
+
+        if let Some(parent_id) = actual_parent_id {
+            query_builder = query_builder.bind(("parent_id", Thing::from(("document", parent_id))));
+        }
+
+        let siblings_db: Vec<crate::models::document::DocumentDb> = query_builder
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let sibling_ids: Vec<String> = siblings_db
+            .iter()
+            .filter_map(|doc| doc.id.as_ref().map(|thing| thing.id.to_string()))
+            .collect();
+
+        validate_sibling_order(&sibling_ids, &ordered_ids)?;
+
+        // 在单个事务中更新所有同级文档，避免部分失败导致顺序错乱
+        let mut sql = String::from("BEGIN TRANSACTION;\n");
+        let mut bindings = std::collections::HashMap::new();
+        for (index, id) in ordered_ids.iter().enumerate() {
+            sql.push_str(&format!(
+                "UPDATE type::thing('document', $id_{index}) SET order_index = {index}, updated_by = $mover_id, updated_at = time::now();\n",
+                index = index
+            ));
+            bindings.insert(format!("id_{}", index), serde_json::Value::String(id.clone()));
+        }
+        sql.push_str("COMMIT TRANSACTION;");
+        bindings.insert("mover_id".to_string(), serde_json::Value::String(mover_id.to_string()));
+
+        self.db.client
+            .query(sql)
+            .bind(bindings)
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        tracing::info!(
+            "User {} reordered {} documents in space {}",
+            redact::user_id(mover_id),
+            ordered_ids.len(),
+            actual_space_id
+        );
+
+        let mut reordered = Vec::with_capacity(ordered_ids.len());
+        for id in &ordered_ids {
+            reordered.push(self.get_document_by_id(id).await?);
+        }
+
+        Ok(reordered)
+    }
+
+    pub async fn get_document_breadcrumbs(&self, document_id: &str) -> Result<Vec<Document>, AppError> {
+        let mut breadcrumbs = Vec::new();
+        let mut current_id = Some(document_id.to_string());
+
+        while let Some(id) = current_id {
+            let document = self.get_document(&id).await?;
+            current_id = document.parent_id.as_ref().map(|p| p.to_string());
             breadcrumbs.push(document);
         }
 
@@ -597,7 +1573,32 @@ impl DocumentService {
         Ok(breadcrumbs)
     }
 
-    pub async fn get_document_breadcrumbs_by_id(&self, document_id: &str) -> Result<Vec<Document>, ApiError> {
+    /// 获取文档目录，锚点与渲染后 HTML 中标题的 id 一致
+    pub fn get_document_toc(&self, document: &Document) -> Vec<TocNode> {
+        self.markdown_processor.table_of_contents(&document.content)
+    }
+
+    /// 比较任意两篇文档（如一组译文）的标题大纲、标题和正文，两篇文档都需要请求者可读
+    pub async fn compare_documents(&self, id_a: &str, id_b: &str, user_id: &str) -> Result<DocumentComparison, AppError> {
+        let a = self.get_document_by_id(id_a).await?;
+        let b = self.get_document_by_id(id_b).await?;
+
+        for document in [&a, &b] {
+            // 公开文档同样受所在空间的访问控制，私有空间中的公开文档不能被非成员读取
+            if !self.can_access_document_space(document, Some(user_id)).await? {
+                return Err(AppError::Authorization("Access denied to this space".to_string()));
+            }
+            if !document.is_public && !self.check_document_permission(document, user_id, "docs.read").await? {
+                return Err(AppError::Authorization("Permission denied: docs.read required".to_string()));
+            }
+        }
+
+        let outline_a = self.markdown_processor.extract_toc(&a.content)?;
+        let outline_b = self.markdown_processor.extract_toc(&b.content)?;
+        Ok(compare_document_contents(&a, &b, &outline_a, &outline_b))
+    }
+
+    pub async fn get_document_breadcrumbs_by_id(&self, document_id: &str) -> Result<Vec<Document>, AppError> {
         // 分离前缀和实际ID
         let actual_id = if document_id.starts_with("document:") {
             document_id.strip_prefix("document:").unwrap()
@@ -631,15 +1632,16 @@ impl DocumentService {
         new_title: Option<String>,
         new_slug: Option<String>,
         duplicator_id: &str,
-    ) -> Result<Document, ApiError> {
+    ) -> Result<Document, AppError> {
         let original = self.get_document(document_id).await?;
-        
+        ensure_space_writable(&self.db, &original.space_id).await?;
+
         let title = new_title.unwrap_or_else(|| format!("{} (Copy)", original.title));
         let slug = new_slug.unwrap_or_else(|| format!("{}-copy", original.slug));
 
         // 检查新slug是否唯一
         if self.document_slug_exists(&original.space_id.to_string(), &slug).await? {
-            return Err(ApiError::Conflict("New slug already exists".to_string()));
+            return Err(AppError::Conflict("New slug already exists".to_string()));
         }
 
         let mut new_document = Document::new(
@@ -659,12 +1661,12 @@ impl DocumentService {
             .create("document")
             .content(new_document)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let created_document = created
             .into_iter()
             .next()
-            .ok_or_else(|| ApiError::InternalServerError("Failed to duplicate document".to_string()))?;
+            .ok_or_else(|| AppError::internal_server_error("Failed to duplicate document".to_string()))?;
 
         // 更新搜索索引
         if let Some(search_service) = &self.search_service {
@@ -683,7 +1685,7 @@ impl DocumentService {
         Ok(created_document)
     }
 
-    pub async fn get_document_by_slug(&self, space_id: &str, slug: &str) -> Result<Document, ApiError> {
+    pub async fn get_document_by_slug(&self, space_id: &str, slug: &str) -> Result<Document, AppError> {
         let query = "
             SELECT * FROM document 
             WHERE space_id = $space_id 
@@ -696,20 +1698,20 @@ impl DocumentService {
             .bind(("space_id", Thing::from(("space", space_id))))
             .bind(("slug", slug))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let documents: Vec<Document> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         documents.into_iter()
             .next()
-            .ok_or_else(|| ApiError::NotFound("Document not found".to_string()))
+            .ok_or_else(|| AppError::NotFound("Document not found".to_string()))
     }
 
-    pub async fn get_document_by_id(&self, id: &str) -> Result<Document, ApiError> {
+    pub async fn get_document_by_id(&self, id: &str) -> Result<Document, AppError> {
         // 添加调试日志
-        tracing::info!("Searching for document with ID: '{}'", id);
+        tracing::debug!("Searching for document with ID: '{}'", id);
         
         // 分离前缀和实际ID
         let actual_id = if id.starts_with("document:") {
@@ -718,7 +1720,7 @@ impl DocumentService {
             id
         };
         
-        tracing::info!("Using actual_id for Thing: '{}'", actual_id);
+        tracing::debug!("Using actual_id for Thing: '{}'", actual_id);
 
         let query = "
             SELECT * FROM document 
@@ -730,24 +1732,320 @@ impl DocumentService {
             .query(query)
             .bind(("id", Thing::from(("document", actual_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let documents_db: Vec<crate::models::document::DocumentDb> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        tracing::info!("Found {} documents from database", documents_db.len());
+        tracing::debug!("Found {} documents from database", documents_db.len());
 
         let document_db = documents_db.into_iter()
             .next()
-            .ok_or_else(|| ApiError::NotFound("Document not found".to_string()))?;
+            .ok_or_else(|| AppError::NotFound("Document not found".to_string()))?;
         
         // 转换为普通的 Document
         let document: Document = document_db.into();
         Ok(document)
     }
 
-    async fn document_slug_exists(&self, space_id: &str, slug: &str) -> Result<bool, ApiError> {
+    /// 记录用户浏览文档，超出保留窗口的旧记录由后台任务统一清理
+    pub async fn record_document_access(&self, user_id: &str, document_id: &str) -> Result<(), AppError> {
+        let actual_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+
+        self.db.client
+            .query("CREATE document_access_log SET user_id = $user_id, document_id = $document_id, accessed_at = time::now()")
+            .bind(("user_id", user_id))
+            .bind(("document_id", Thing::from(("document", actual_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 清理超出保留窗口的浏览记录，由后台清理任务定期调用
+    pub async fn prune_access_log(&self) -> Result<(), AppError> {
+        let query = format!(
+            "DELETE document_access_log WHERE accessed_at < time::now() - {}d",
+            ACCESS_LOG_RETENTION_DAYS
+        );
+
+        self.db.client
+            .query(query)
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 检查文档中的站内链接，返回无法解析到同空间未删除文档的链接
+    pub async fn validate_links(&self, document_id: &str) -> Result<DocumentLinkReport, AppError> {
+        let document = self.get_document_by_id(document_id).await?;
+        let documents = self.space_documents_for_links(&document.space_id).await?;
+        let slugs: std::collections::HashSet<String> = documents.into_iter().map(|d| d.slug).collect();
+
+        Ok(self.link_report(&document, &slugs))
+    }
+
+    /// 检查整个空间的站内链接，汇总所有失效链接
+    pub async fn validate_space_links(&self, space_id: &str) -> Result<SpaceLinkReport, AppError> {
+        let documents = self.space_documents_for_links(space_id).await?;
+        let slugs: std::collections::HashSet<String> = documents.iter().map(|d| d.slug.clone()).collect();
+
+        let documents_checked = documents.len();
+        let reports: Vec<DocumentLinkReport> = documents
+            .iter()
+            .map(|document| self.link_report(document, &slugs))
+            .filter(|report| !report.broken_links.is_empty())
+            .collect();
+
+        Ok(SpaceLinkReport {
+            space_id: space_id.strip_prefix("space:").unwrap_or(space_id).to_string(),
+            documents_checked,
+            broken_link_count: reports.iter().map(|r| r.broken_links.len()).sum(),
+            documents: reports,
+        })
+    }
+
+    /// 获取同空间内链接到该文档（按 slug 或 ID）的文档。
+    /// 请求者必须能读取目标文档，且只返回其有读取权限的文档，未登录用户只能看到公开文档
+    pub async fn get_backlinks(&self, document_id: &str, user_id: Option<&str>) -> Result<Vec<DocumentListItem>, AppError> {
+        let target = self.get_document_by_id(document_id).await?;
+        if !self.can_read_document(&target, user_id).await? {
+            return Err(AppError::Authorization("Permission denied: docs.read required".to_string()));
+        }
+        let target_id = target.id.clone().unwrap_or_default();
+        let target_raw_id = target_id.strip_prefix("document:").unwrap_or(&target_id).to_string();
+
+        let mut backlinks = Vec::new();
+        for document in self.space_documents_for_links(&target.space_id).await? {
+            if document.id == target.id {
+                continue;
+            }
+            let links = self.markdown_processor.extract_links(&document.content);
+            if !links_to_document(&links, &target.slug, &target_raw_id) {
+                continue;
+            }
+
+            if self.can_read_document(&document, user_id).await? {
+                backlinks.push(DocumentListItem::from(document));
+            }
+        }
+
+        Ok(backlinks)
+    }
+
+    /// 公开空间对所有人开放，私有空间只对所有者和成员开放；空间不存在时视为无权访问
+    pub async fn can_access_document_space(&self, document: &Document, user_id: Option<&str>) -> Result<bool, AppError> {
+        let space_id = document.space_id.strip_prefix("space:").unwrap_or(&document.space_id);
+        let is_public: Option<bool> = self.db.client
+            .query("SELECT VALUE is_public FROM $space WHERE is_deleted = false")
+            .bind(("space", Thing::from(("space", space_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        match is_public {
+            None => Ok(false),
+            Some(true) => Ok(true),
+            Some(false) => {
+                let space_member_service = self.space_member_service.as_ref().ok_or_else(|| {
+                    AppError::Internal(anyhow::anyhow!("Space member service is not configured"))
+                })?;
+                space_member_service.can_access_space(&document.space_id, user_id).await
+            }
+        }
+    }
+
+    /// 所在空间可访问时，公开文档所有人可读，其余文档需要登录并拥有 docs.read 权限
+    async fn can_read_document(&self, document: &Document, user_id: Option<&str>) -> Result<bool, AppError> {
+        // 公开文档同样受所在空间的访问控制，私有空间中的公开文档不能被非成员读取
+        if !self.can_access_document_space(document, user_id).await? {
+            return Ok(false);
+        }
+        match user_id {
+            _ if document.is_public => Ok(true),
+            Some(user_id) => self.check_document_permission(document, user_id, "docs.read").await,
+            None => Ok(false),
+        }
+    }
+
+    fn link_report(&self, document: &Document, slugs: &std::collections::HashSet<String>) -> DocumentLinkReport {
+        let links = self.markdown_processor.extract_links(&document.content);
+        DocumentLinkReport {
+            document_id: document.id.clone().unwrap_or_default(),
+            title: document.title.clone(),
+            slug: document.slug.clone(),
+            broken_links: find_broken_links(links, slugs),
+        }
+    }
+
+    async fn space_documents_for_links(&self, space_id: &str) -> Result<Vec<Document>, AppError> {
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+
+        let documents_db: Vec<crate::models::document::DocumentDb> = self.db.client
+            .query("SELECT * FROM document WHERE space_id = $space_id AND is_deleted = false")
+            .bind(("space_id", Thing::from(("space", space_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(documents_db.into_iter().map(Document::from).collect())
+    }
+
+    /// 检查用户对文档的权限：文档及其祖先上的显式授权优先于空间角色，空间所有者始终放行
+    pub async fn check_document_permission(&self, document: &Document, user_id: &str, permission: &str) -> Result<bool, AppError> {
+        let Some(document_permission_service) = &self.document_permission_service else {
+            let space_member_service = self.space_member_service.as_ref().ok_or_else(|| {
+                AppError::Internal(anyhow::anyhow!("Space member service is not configured"))
+            })?;
+            return space_member_service.check_permission(&document.space_id, user_id, permission).await;
+        };
+
+        let document_id = document.id.as_ref().ok_or_else(|| {
+            AppError::Internal(anyhow::anyhow!("Document ID is missing"))
+        })?;
+        let ancestor_ids: Vec<String> = self
+            .get_document_breadcrumbs_by_id(document_id)
+            .await?
+            .into_iter()
+            .rev()
+            .filter_map(|doc| doc.id)
+            .collect();
+
+        document_permission_service
+            .check_permission(&document.space_id, &ancestor_ids, user_id, permission)
+            .await
+    }
+
+    /// 获取用户最近浏览或编辑的文档，每篇文档只出现一次，只返回用户仍可访问的空间中的文档
+    pub async fn recent_documents(
+        &self,
+        user_id: &str,
+        kind: RecentKind,
+        limit: Option<usize>,
+    ) -> Result<Vec<RecentDocument>, AppError> {
+        let limit = limit
+            .unwrap_or(RECENT_DOCUMENTS_DEFAULT_LIMIT)
+            .clamp(1, RECENT_DOCUMENTS_MAX_LIMIT);
+
+        // 编辑时间取自版本历史，每次保存都会产生一个版本
+        let query = match kind {
+            RecentKind::Viewed => format!(
+                "SELECT document_id, accessed_at AS occurred_at FROM document_access_log
+                 WHERE user_id = $user_id ORDER BY occurred_at DESC LIMIT {}",
+                RECENT_SCAN_LIMIT
+            ),
+            RecentKind::Edited => format!(
+                "SELECT document_id, created_at AS occurred_at FROM document_version
+                 WHERE author_id = $user_id ORDER BY occurred_at DESC LIMIT {}",
+                RECENT_SCAN_LIMIT
+            ),
+        };
+
+        let rows: Vec<DocumentActivityRow> = self.db.client
+            .query(query)
+            .bind(("user_id", user_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let mut things: HashMap<String, Thing> = HashMap::new();
+        let activity: Vec<(String, DateTime<Utc>)> = rows
+            .into_iter()
+            .map(|row| {
+                let key = row.document_id.to_string();
+                things.insert(key.clone(), row.document_id);
+                (key, row.occurred_at)
+            })
+            .collect();
+
+        // 不在这里截断到 limit，给已删除或无权访问的文档留出余量
+        let entries = latest_distinct(activity, RECENT_SCAN_LIMIT);
+        if entries.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<Thing> = entries
+            .iter()
+            .filter_map(|(id, _)| things.remove(id))
+            .collect();
+
+        let documents: Vec<crate::models::document::DocumentDb> = self.db.client
+            .query("SELECT * FROM document WHERE id INSIDE $ids AND is_deleted = false")
+            .bind(("ids", ids))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let mut by_id: HashMap<String, Document> = documents
+            .into_iter()
+            .filter_map(|doc| {
+                let id = doc.id.as_ref()?.to_string();
+                Some((id, Document::from(doc)))
+            })
+            .collect();
+
+        let mut space_access: HashMap<String, bool> = HashMap::new();
+        let mut recent = Vec::new();
+        for (id, occurred_at) in entries {
+            if recent.len() >= limit {
+                break;
+            }
+            let Some(document) = by_id.remove(&id) else {
+                continue;
+            };
+
+            if let Some(space_member_service) = &self.space_member_service {
+                let allowed = match space_access.get(&document.space_id) {
+                    Some(allowed) => *allowed,
+                    None => {
+                        let allowed = space_member_service
+                            .can_access_space(&document.space_id, Some(user_id))
+                            .await?;
+                        space_access.insert(document.space_id.clone(), allowed);
+                        allowed
+                    }
+                };
+                if !allowed {
+                    continue;
+                }
+            }
+
+            recent.push(RecentDocument {
+                document,
+                last_activity_at: occurred_at,
+            });
+        }
+
+        Ok(recent)
+    }
+
+    /// 根据标题生成空间内未被占用的 slug，重名时追加 -2、-3 等后缀
+    async fn generate_document_slug(&self, space_id: &str, title: &str) -> Result<String, AppError> {
+        let base = slug_from_title(title, MAX_SLUG_CHARS);
+
+        let taken: Vec<String> = self.db.client
+            .query(TAKEN_SLUGS_QUERY)
+            .bind(("space_id", Thing::from(("space", space_id))))
+            .bind(("base", &base))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(unique_slug(&base, &taken, MAX_SLUG_CHARS))
+    }
+
+    async fn document_slug_exists(&self, space_id: &str, slug: &str) -> Result<bool, AppError> {
         let query = "
             SELECT count() FROM document 
             WHERE space_id = $space_id 
@@ -761,9 +2059,9 @@ impl DocumentService {
             .bind(("space_id", Thing::from(("space", space_id))))
             .bind(("slug", slug))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let count = result
             .first()
@@ -773,7 +2071,7 @@ impl DocumentService {
         Ok(count > 0)
     }
 
-    async fn verify_parent_document(&self, space_id: &str, parent_id: &str) -> Result<(), ApiError> {
+    async fn verify_parent_document(&self, space_id: &str, parent_id: &str) -> Result<(), AppError> {
         let query = "
             SELECT id FROM document 
             WHERE id = $parent_id 
@@ -786,16 +2084,965 @@ impl DocumentService {
             .bind(("parent_id", Thing::from(("document", parent_id))))
             .bind(("space_id", Thing::from(("space", space_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
             
         let result: Vec<serde_json::Value> = response
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         if result.is_empty() {
-            return Err(ApiError::NotFound("Parent document not found".to_string()));
+            return Err(AppError::NotFound("Parent document not found".to_string()));
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// 两篇文档的比较结果，差异中 a 为旧文本、b 为新文本
+#[derive(Debug, serde::Serialize)]
+pub struct DocumentComparison {
+    pub a: ComparedDocument,
+    pub b: ComparedDocument,
+    /// 标题大纲的逐项差异，每项形如 "## 安装"
+    pub outline_diff: Vec<DiffSpan>,
+    pub title_diff: Vec<DiffSpan>,
+    pub content_diff: Vec<DiffSpan>,
+    /// 正文相似度（0~1）
+    pub similarity: f64,
+}
+
+/// 比较结果中每篇文档的摘要信息，不包含正文
+#[derive(Debug, serde::Serialize)]
+pub struct ComparedDocument {
+    pub id: Option<String>,
+    pub space_id: String,
+    pub title: String,
+    pub word_count: u32,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<&Document> for ComparedDocument {
+    fn from(document: &Document) -> Self {
+        Self {
+            id: document.id.clone(),
+            space_id: document.space_id.clone(),
+            title: document.title.clone(),
+            word_count: document.word_count,
+            updated_at: document.updated_at,
+        }
+    }
+}
+
+/// 标题大纲按标题逐项比较（级别和文字都相同才算未变化），标题和正文做词级差异
+pub fn compare_document_contents(
+    a: &Document,
+    b: &Document,
+    outline_a: &[TocEntry],
+    outline_b: &[TocEntry],
+) -> DocumentComparison {
+    let outline = |entries: &[TocEntry]| -> Vec<String> {
+        entries.iter().map(|entry| format!("{} {}", "#".repeat(entry.level as usize), entry.title)).collect()
+    };
+    let content_diff = diff_text(&a.content, &b.content);
+
+    DocumentComparison {
+        a: ComparedDocument::from(a),
+        b: ComparedDocument::from(b),
+        outline_diff: diff_lines(&outline(outline_a), &outline(outline_b)),
+        title_diff: diff_text(&a.title, &b.title),
+        similarity: similarity(&content_diff),
+        content_diff,
+    }
+}
+
+/// document_tag 关联查询结果
+#[derive(Debug, serde::Deserialize)]
+struct DocumentTagRow {
+    document_id: Thing,
+    tag_name: Option<String>,
+}
+
+/// 文档内容变更对应的活动记录，每次变更只写一条
+fn document_change_activity(
+    editor_id: &str,
+    document_id: &str,
+    space_id: &str,
+    title: &str,
+    change_type: &VersionChangeType,
+) -> ActivityRecord {
+    let action = match change_type {
+        VersionChangeType::Created => "document_created",
+        VersionChangeType::Updated => "document_updated",
+        VersionChangeType::Restored => "document_restored",
+        VersionChangeType::Merged => "document_merged",
+    };
+    ActivityRecord::new(editor_id, action, "document", document_id, space_id).with_title(title)
+}
+
+/// 浏览记录或版本历史中的一次文档活动
+#[derive(Debug, serde::Deserialize)]
+struct DocumentActivityRow {
+    document_id: Thing,
+    occurred_at: DateTime<Utc>,
+}
+
+/// 按文档去重，只保留每篇文档最近一次的时间，按时间倒序返回前 limit 条
+fn latest_distinct<I>(rows: I, limit: usize) -> Vec<(String, DateTime<Utc>)>
+where
+    I: IntoIterator<Item = (String, DateTime<Utc>)>,
+{
+    let mut latest: HashMap<String, DateTime<Utc>> = HashMap::new();
+    for (document_id, at) in rows {
+        latest
+            .entry(document_id)
+            .and_modify(|current| {
+                if at > *current {
+                    *current = at;
+                }
+            })
+            .or_insert(at);
+    }
+
+    let mut entries: Vec<(String, DateTime<Utc>)> = latest.into_iter().collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    entries.truncate(limit);
+    entries
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CountRow {
+    total: u32,
+}
+
+/// 根据 (document_id, tag_name) 关联筛选文档：All 要求包含全部标签，Any 只需命中其一
+fn documents_matching_tags<'a, I>(
+    rows: I,
+    tags: &[String],
+    mode: TagMatch,
+) -> std::collections::HashSet<String>
+where
+    I: IntoIterator<Item = (String, &'a str)>,
+{
+    let wanted: std::collections::HashSet<&str> = tags.iter().map(|tag| tag.as_str()).collect();
+    let mut matched: std::collections::HashMap<String, std::collections::HashSet<&'a str>> =
+        std::collections::HashMap::new();
+    for (document_id, tag_name) in rows {
+        if wanted.contains(tag_name) {
+            matched.entry(document_id).or_default().insert(tag_name);
+        }
+    }
+
+    matched
+        .into_iter()
+        .filter(|(_, found)| match mode {
+            TagMatch::All => found.len() == wanted.len(),
+            TagMatch::Any => !found.is_empty(),
+        })
+        .map(|(document_id, _)| document_id)
+        .collect()
+}
+
+/// 统计每个文档未删除的直接子文档数量
+fn count_live_children(
+    documents: &[crate::models::document::DocumentDb],
+) -> std::collections::HashMap<String, u32> {
+    let mut counts = std::collections::HashMap::new();
+    for doc in documents.iter().filter(|doc| !doc.is_deleted) {
+        if let Some(parent_id) = &doc.parent_id {
+            *counts.entry(parent_id.to_string()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// 基于父子映射判断移动是否会形成循环，与 would_create_cycle 的遍历逻辑一致
+fn creates_cycle(
+    parent_map: &std::collections::HashMap<String, Option<String>>,
+    document_id: &str,
+    new_parent_id: &str,
+) -> bool {
+    let mut visited = std::collections::HashSet::new();
+    let mut current = Some(new_parent_id.to_string());
+
+    while let Some(id) = current {
+        if id == document_id {
+            return true;
+        }
+        if !visited.insert(id.clone()) {
+            break;
+        }
+        current = parent_map.get(&id).cloned().flatten();
+    }
+
+    false
+}
+
+/// 删除计划：需要软删除的文档，以及需要重新挂载的子文档及其新父级
+#[derive(Debug, Default, PartialEq)]
+struct DeletionPlan {
+    deleted: Vec<String>,
+    reparented: Vec<(String, Option<String>)>,
+}
+
+fn plan_deletion(
+    parent_map: &std::collections::HashMap<String, Option<String>>,
+    root_id: &str,
+    cascade: bool,
+) -> DeletionPlan {
+    let mut children_map: std::collections::HashMap<&str, Vec<&str>> = std::collections::HashMap::new();
+    for (id, parent) in parent_map {
+        if let Some(parent) = parent {
+            children_map.entry(parent.as_str()).or_default().push(id.as_str());
+        }
+    }
+    for children in children_map.values_mut() {
+        children.sort();
+    }
+
+    let mut plan = DeletionPlan::default();
+    plan.deleted.push(root_id.to_string());
+
+    if cascade {
+        // 广度优先收集整个子树
+        let mut visited = std::collections::HashSet::new();
+        visited.insert(root_id.to_string());
+        let mut queue = std::collections::VecDeque::new();
+        queue.push_back(root_id.to_string());
+        while let Some(id) = queue.pop_front() {
+            if let Some(children) = children_map.get(id.as_str()) {
+                for child in children {
+                    if visited.insert(child.to_string()) {
+                        plan.deleted.push(child.to_string());
+                        queue.push_back(child.to_string());
+                    }
+                }
+            }
+        }
+    } else {
+        let new_parent = parent_map.get(root_id).cloned().flatten();
+        if let Some(children) = children_map.get(root_id) {
+            for child in children {
+                plan.reparented.push((child.to_string(), new_parent.clone()));
+            }
+        }
+    }
+
+    plan
+}
+
+/// 校验重排列表与实际同级文档集合完全一致（无重复、无缺失、无外来ID）
+fn validate_sibling_order(sibling_ids: &[String], ordered_ids: &[String]) -> Result<(), AppError> {
+    let siblings: std::collections::HashSet<&str> = sibling_ids.iter().map(|s| s.as_str()).collect();
+    let mut seen = std::collections::HashSet::new();
+
+    for id in ordered_ids {
+        if !seen.insert(id.as_str()) {
+            return Err(AppError::Validation(format!("Duplicate document id in reorder list: {}", id)));
+        }
+        if !siblings.contains(id.as_str()) {
+            return Err(AppError::Validation(format!(
+                "Document {} does not belong to the given parent and space",
+                id
+            )));
+        }
+    }
+
+    if seen.len() != siblings.len() {
+        return Err(AppError::Validation(
+            "Reorder list must contain every sibling document".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// 为转移到目标空间的文档分配不冲突的 slug：冲突时依次尝试 -copy、-copy-2 ...
+pub(crate) fn resolve_slug_collisions<'a>(
+    slugs: impl Iterator<Item = &'a str>,
+    existing: &std::collections::HashSet<String>,
+) -> Vec<String> {
+    let mut taken = existing.clone();
+    slugs
+        .map(|slug| {
+            let mut candidate = slug.to_string();
+            let mut attempt = 1;
+            while taken.contains(&candidate) {
+                candidate = if attempt == 1 {
+                    format!("{}-copy", slug)
+                } else {
+                    format!("{}-copy-{}", slug, attempt)
+                };
+                attempt += 1;
+            }
+            taken.insert(candidate.clone());
+            candidate
+        })
+        .collect()
+}
+
+/// 乐观并发检查：未提供期望值时视为强制写入
+fn is_expected_revision(
+    current: Option<chrono::DateTime<Utc>>,
+    expected: Option<chrono::DateTime<Utc>>,
+) -> bool {
+    match expected {
+        Some(expected) => current == Some(expected),
+        None => true,
+    }
+}
+
+fn stale_write_error(current: &Document) -> AppError {
+    AppError::StaleWrite {
+        message: "Document was modified by someone else".to_string(),
+        current: serde_json::to_value(current).unwrap_or_default(),
+    }
+}
+
+/// 改名前加载的空间文档中，被改名的文档换成改名后的版本，避免用旧正文覆盖本次更新
+fn with_renamed_document(documents: Vec<Document>, renamed: &Document) -> Vec<Document> {
+    documents
+        .into_iter()
+        .map(|document| if document.id == renamed.id { renamed.clone() } else { document })
+        .collect()
+}
+
+/// 改写指向 old_slug 的站内链接，保留相对路径、`.md` 后缀和锚点。没有需要改写的链接时返回 None
+fn rewrite_slug_links(content: &str, links: &[MarkdownLink], old_slug: &str, new_slug: &str) -> Option<String> {
+    let mut rewritten = content.to_string();
+    let mut changed = false;
+
+    for link in links {
+        if internal_link_slug(&link.url).as_deref() != Some(old_slug) {
+            continue;
+        }
+
+        let split_at = link.url.find(['#', '?']).unwrap_or(link.url.len());
+        let (path, suffix) = link.url.split_at(split_at);
+        let (dir, file) = match path.rsplit_once('/') {
+            Some((dir, file)) => (format!("{}/", dir), file),
+            None => (String::new(), path),
+        };
+        let new_file = file.replacen(old_slug, new_slug, 1);
+        let new_url = format!("{}{}{}", dir, new_file, suffix);
+
+        // URL 后面只能是右括号或标题前的空格，避免误改以旧 slug 开头的其他链接
+        for terminator in [")", " "] {
+            let from = format!("]({}{}", link.url, terminator);
+            if rewritten.contains(&from) {
+                rewritten = rewritten.replace(&from, &format!("]({}{}", new_url, terminator));
+                changed = true;
+            }
+        }
+    }
+
+    changed.then_some(rewritten)
+}
+
+/// 链接中是否有指向目标文档的站内链接（按 slug 或不带前缀的文档ID匹配）
+fn links_to_document(links: &[MarkdownLink], slug: &str, raw_id: &str) -> bool {
+    links.iter().any(|link| {
+        internal_link_slug(&link.url).is_some_and(|target| target == slug || target == raw_id)
+    })
+}
+
+/// 找出目标 slug 不在空间文档集合中的站内链接，外部链接不检查
+fn find_broken_links(links: Vec<MarkdownLink>, slugs: &std::collections::HashSet<String>) -> Vec<BrokenLink> {
+    links
+        .into_iter()
+        .filter_map(|link| {
+            let target = internal_link_slug(&link.url)?;
+            (!slugs.contains(&target)).then(|| BrokenLink {
+                target,
+                text: link.text,
+                line: link.line,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{diff::DiffSpanKind, markdown::MarkdownOptions};
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn test_user(name: &str, run: &str) -> crate::services::auth::User {
+        crate::services::auth::User {
+            id: format!("{}-{}", name, run),
+            email: format!("{}@example.com", name),
+            roles: vec!["user".to_string()],
+            permissions: vec![],
+            profile: None,
+            api_key: None,
+        }
+    }
+
+    /// 在测试数据库中为 owner 创建空间，返回空间ID
+    async fn create_test_space(state: &crate::state::AppState, owner: &crate::services::auth::User, slug: &str, is_public: bool) -> String {
+        let request = crate::models::space::CreateSpaceRequest {
+            name: "Docs".to_string(),
+            slug: slug.to_string(),
+            description: None,
+            avatar_url: None,
+            is_public: Some(is_public),
+            settings: None,
+        };
+        state.space_service.create_space(request, owner).await.unwrap().id
+    }
+
+    async fn create_test_document(state: &crate::state::AppState, space_id: &str, author_id: &str, request: serde_json::Value) -> Document {
+        let request: CreateDocumentRequest = serde_json::from_value(request).unwrap();
+        state.document_service.create_document(space_id, author_id, request).await.unwrap()
+    }
+
+    fn update_request(request: serde_json::Value) -> UpdateDocumentRequest {
+        serde_json::from_value(request).unwrap()
+    }
+
+    /// 通过邀请把 member 以指定角色加入 owner 的空间
+    async fn add_test_member(
+        state: &crate::state::AppState,
+        space_id: &str,
+        owner: &crate::services::auth::User,
+        member: &crate::services::auth::User,
+        role: crate::models::space_member::MemberRole,
+    ) {
+        let invitation = state.space_member_service.invite_member(space_id, owner, crate::models::space_member::InviteMemberRequest {
+            email: None,
+            user_id: Some(member.id.clone()),
+            role,
+            message: None,
+            expires_in_days: None,
+            max_uses: None,
+        }).await.unwrap();
+        state.space_member_service.accept_invitation(&member.id, crate::models::space_member::AcceptInvitationRequest {
+            invite_token: invitation.invite_token,
+        }).await.unwrap();
+    }
+
+    #[test]
+    fn test_validate_sibling_order_accepts_permutation() {
+        let siblings = ids(&["a", "b", "c"]);
+        assert!(validate_sibling_order(&siblings, &ids(&["c", "a", "b"])).is_ok());
+    }
+
+    #[test]
+    fn test_validate_sibling_order_rejects_foreign_id() {
+        let siblings = ids(&["a", "b", "c"]);
+        assert!(validate_sibling_order(&siblings, &ids(&["a", "b", "x"])).is_err());
+    }
+
+    #[test]
+    fn test_validate_sibling_order_rejects_missing_or_duplicate() {
+        let siblings = ids(&["a", "b", "c"]);
+        assert!(validate_sibling_order(&siblings, &ids(&["a", "b"])).is_err());
+        assert!(validate_sibling_order(&siblings, &ids(&["a", "a", "b", "c"])).is_err());
+    }
+
+    #[test]
+    fn test_move_under_descendant_creates_cycle() {
+        // a -> b -> c 三层结构
+        let mut parent_map = std::collections::HashMap::new();
+        parent_map.insert("a".to_string(), None);
+        parent_map.insert("b".to_string(), Some("a".to_string()));
+        parent_map.insert("c".to_string(), Some("b".to_string()));
+
+        assert!(creates_cycle(&parent_map, "a", "c"));
+        assert!(creates_cycle(&parent_map, "a", "b"));
+        assert!(creates_cycle(&parent_map, "b", "b"));
+        assert!(!creates_cycle(&parent_map, "c", "a"));
+        assert!(!creates_cycle(&parent_map, "b", "a"));
+    }
+
+    fn small_tree() -> std::collections::HashMap<String, Option<String>> {
+        // root -> a -> (a1, a2), root -> b
+        let mut parent_map = std::collections::HashMap::new();
+        parent_map.insert("root".to_string(), None);
+        parent_map.insert("a".to_string(), Some("root".to_string()));
+        parent_map.insert("a1".to_string(), Some("a".to_string()));
+        parent_map.insert("a2".to_string(), Some("a".to_string()));
+        parent_map.insert("b".to_string(), Some("root".to_string()));
+        parent_map
+    }
+
+    #[test]
+    fn test_cascade_delete_collects_subtree() {
+        let plan = plan_deletion(&small_tree(), "a", true);
+        assert_eq!(plan.deleted, ids(&["a", "a1", "a2"]));
+        assert!(plan.reparented.is_empty());
+
+        let plan = plan_deletion(&small_tree(), "root", true);
+        assert_eq!(plan.deleted.len(), 5);
+    }
+
+    #[test]
+    fn test_non_cascade_delete_reparents_children() {
+        let plan = plan_deletion(&small_tree(), "a", false);
+        assert_eq!(plan.deleted, ids(&["a"]));
+        assert_eq!(
+            plan.reparented,
+            vec![
+                ("a1".to_string(), Some("root".to_string())),
+                ("a2".to_string(), Some("root".to_string())),
+            ]
+        );
+
+        // 删除根级文档时子文档移动到根级
+        let plan = plan_deletion(&small_tree(), "root", false);
+        assert_eq!(
+            plan.reparented,
+            vec![("a".to_string(), None), ("b".to_string(), None)]
+        );
+    }
+
+    fn document_db(id: &str, parent_id: Option<&str>, is_deleted: bool) -> crate::models::document::DocumentDb {
+        crate::models::document::DocumentDb {
+            id: Some(Thing::from(("document", id))),
+            space_id: Thing::from(("space", "space_123")),
+            title: id.to_string(),
+            slug: id.to_string(),
+            content: String::new(),
+            excerpt: None,
+            is_public: false,
+            parent_id: parent_id.map(|p| Thing::from(("document", p))),
+            order_index: 0,
+            author_id: "user_123".to_string(),
+            last_editor_id: None,
+            view_count: 0,
+            word_count: 0,
+            reading_time: 0,
+            metadata: Default::default(),
+            updated_by: None,
+            is_deleted,
+            deleted_at: None,
+            deleted_by: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_children_count_excludes_deleted_children() {
+        let documents = vec![
+            document_db("parent", None, false),
+            document_db("child1", Some("parent"), false),
+            document_db("child2", Some("parent"), false),
+            document_db("child3", Some("parent"), true),
+        ];
+
+        let counts = count_live_children(&documents);
+        let parent_id = Thing::from(("document", "parent")).to_string();
+        assert_eq!(counts.get(&parent_id), Some(&2));
+        assert_eq!(counts.get(&Thing::from(("document", "child1")).to_string()), None);
+    }
+
+    #[test]
+    fn test_stale_update_is_rejected() {
+        let read_at = chrono::Utc::now();
+        let saved_by_other = read_at + chrono::Duration::seconds(5);
+
+        // 读取后文档被他人保存，再以旧的 updated_at 写入应被拒绝
+        assert!(!is_expected_revision(Some(saved_by_other), Some(read_at)));
+        assert!(is_expected_revision(Some(read_at), Some(read_at)));
+        // 未提供期望值时保持原来的强制写入行为
+        assert!(is_expected_revision(Some(saved_by_other), None));
+    }
+
+    #[test]
+    fn test_transfer_renames_colliding_slugs() {
+        let existing: std::collections::HashSet<String> =
+            ["intro", "guide", "guide-copy"].iter().map(|s| s.to_string()).collect();
+
+        let slugs = resolve_slug_collisions(["intro", "guide", "faq"].into_iter(), &existing);
+        assert_eq!(slugs, vec!["intro-copy", "guide-copy-2", "faq"]);
+    }
+
+    #[test]
+    fn test_transfer_moves_whole_subtree() {
+        let mut parent_map = std::collections::HashMap::new();
+        parent_map.insert("root".to_string(), None);
+        parent_map.insert("a".to_string(), Some("root".to_string()));
+        parent_map.insert("a1".to_string(), Some("a".to_string()));
+        parent_map.insert("other".to_string(), None);
+
+        let mut subtree = plan_deletion(&parent_map, "a", true).deleted;
+        subtree.sort();
+        assert_eq!(subtree, vec!["a".to_string(), "a1".to_string()]);
+    }
+
+    fn tagged(pairs: &[(&str, &'static str)]) -> Vec<(String, &'static str)> {
+        pairs.iter().map(|(doc, tag)| (doc.to_string(), *tag)).collect()
+    }
+
+    #[test]
+    fn test_tag_filter_all_requires_every_tag() {
+        let rows = tagged(&[
+            ("a", "rust"), ("a", "api"),
+            ("b", "rust"),
+            ("c", "rust"), ("c", "api"), ("c", "guide"),
+        ]);
+
+        let mut matched: Vec<String> =
+            documents_matching_tags(rows, &ids(&["rust", "api"]), TagMatch::All).into_iter().collect();
+        matched.sort();
+
+        // c 拥有请求标签的超集，同样应当命中
+        assert_eq!(matched, ids(&["a", "c"]));
+    }
+
+    #[test]
+    fn test_tag_filter_any_requires_one_tag() {
+        let rows = tagged(&[
+            ("a", "rust"), ("a", "api"),
+            ("b", "guide"),
+            ("c", "api"), ("c", "guide"),
+        ]);
+
+        let mut matched: Vec<String> =
+            documents_matching_tags(rows, &ids(&["rust", "api"]), TagMatch::Any).into_iter().collect();
+        matched.sort();
+
+        assert_eq!(matched, ids(&["a", "c"]));
+    }
+
+    #[test]
+    fn test_recent_documents_dedup_keeps_latest_timestamp() {
+        use chrono::TimeZone;
+        let at = |hour| Utc.with_ymd_and_hms(2024, 5, 1, hour, 0, 0).unwrap();
+
+        // a 被浏览五次，记录顺序是乱的
+        let rows = vec![
+            ("document:a".to_string(), at(3)),
+            ("document:b".to_string(), at(4)),
+            ("document:a".to_string(), at(9)),
+            ("document:a".to_string(), at(1)),
+            ("document:c".to_string(), at(6)),
+            ("document:a".to_string(), at(7)),
+            ("document:a".to_string(), at(2)),
+        ];
+
+        let recent = latest_distinct(rows.clone(), 10);
+        assert_eq!(
+            recent,
+            vec![
+                ("document:a".to_string(), at(9)),
+                ("document:c".to_string(), at(6)),
+                ("document:b".to_string(), at(4)),
+            ]
+        );
+
+        assert_eq!(latest_distinct(rows, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_document_update_writes_one_document_updated_row() {
+        let record = document_change_activity(
+            "user-1",
+            "document:doc-1",
+            "space:s1",
+            "Intro",
+            &VersionChangeType::Updated,
+        );
+
+        assert_eq!(record.action, "document_updated");
+        assert_eq!(record.resource_type, "document");
+        assert_eq!(record.resource_id, "doc-1");
+        assert_eq!(record.space_id, "s1");
+        assert_eq!(record.details["title"], "Intro");
+
+        // 版本恢复走同一条更新路径，但记录为 restored 而不是再写一条 updated
+        let restored = document_change_activity("user-1", "doc-1", "s1", "Intro", &VersionChangeType::Restored);
+        assert_eq!(restored.action, "document_restored");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_conditional_update_rejects_stale_revision() {
+        let state = crate::state::test_support::test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let alice = test_user("alice", &run);
+        let space_id = create_test_space(&state, &alice, &format!("revision-{}", run), true).await;
+        let document = create_test_document(&state, &space_id, &alice.id, serde_json::json!({ "title": "Guide", "slug": "guide" })).await;
+        let document_id = document.id.as_deref().unwrap().trim_start_matches("document:").to_string();
+        let current = document.updated_at.unwrap();
+
+        // 基于旧版本的写入返回 409 和当前数据
+        let stale = update_request(serde_json::json!({
+            "title": "Stale edit",
+            "expected_updated_at": current - chrono::Duration::seconds(1),
+        }));
+        let error = state.document_service.update_document(&document_id, &alice.id, stale).await.unwrap_err();
+        assert!(matches!(error, AppError::StaleWrite { .. }));
+        assert_eq!(axum::response::IntoResponse::into_response(error).status(), axum::http::StatusCode::CONFLICT);
+
+        // 基于最新版本的写入成功
+        let fresh = update_request(serde_json::json!({
+            "title": "Fresh edit",
+            "expected_updated_at": current,
+        }));
+        let updated = state.document_service.update_document(&document_id, &alice.id, fresh).await.unwrap();
+        assert_eq!(updated.document.title, "Fresh edit");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_archived_space_rejects_transfer_reorder_and_duplicate() {
+        let state = crate::state::test_support::test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let alice = test_user("alice", &run);
+        let archived_slug = format!("archived-{}", run);
+        let archived_id = create_test_space(&state, &alice, &archived_slug, true).await;
+        let open_id = create_test_space(&state, &alice, &format!("open-{}", run), true).await;
+        let archived_doc = create_test_document(&state, &archived_id, &alice.id, serde_json::json!({ "title": "Old", "slug": "old" })).await;
+        let open_doc = create_test_document(&state, &open_id, &alice.id, serde_json::json!({ "title": "New", "slug": "new" })).await;
+        let archived_doc_id = archived_doc.id.as_deref().unwrap().trim_start_matches("document:").to_string();
+        let open_doc_id = open_doc.id.as_deref().unwrap().trim_start_matches("document:").to_string();
+        state.space_service.archive_space(&archived_slug, &alice).await.unwrap();
+
+        let is_archived_error = |error: AppError| matches!(error, AppError::Authorization(ref message) if message == "Space is archived");
+
+        // 迁出和迁入归档空间都被拒绝
+        let error = state.document_service.transfer_document(&archived_doc_id, &open_id, None, &alice.id).await.unwrap_err();
+        assert!(is_archived_error(error));
+        let error = state.document_service.transfer_document(&open_doc_id, &archived_id, None, &alice.id).await.unwrap_err();
+        assert!(is_archived_error(error));
+
+        let error = state.document_service.reorder_documents(&archived_id, None, vec![archived_doc_id.clone()], &alice.id).await.unwrap_err();
+        assert!(is_archived_error(error));
+
+        let error = state.document_service.duplicate_document(&archived_doc_id, None, None, &alice.id).await.unwrap_err();
+        assert!(is_archived_error(error));
+
+        // 未归档空间中的文档不受影响
+        assert!(state.document_service.duplicate_document(&open_doc_id, None, None, &alice.id).await.is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_rename_skips_backlinks_the_editor_cannot_write() {
+        let state = crate::state::test_support::test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let alice = test_user("alice", &run);
+        let bob = test_user("bob", &run);
+        let space_id = create_test_space(&state, &alice, &format!("links-{}", run), true).await;
+        add_test_member(&state, &space_id, &alice, &bob, crate::models::space_member::MemberRole::Editor).await;
+
+        let target = create_test_document(&state, &space_id, &alice.id, serde_json::json!({ "title": "Install", "slug": "install" })).await;
+        let open = create_test_document(&state, &space_id, &alice.id, serde_json::json!({ "title": "Open", "slug": "open", "content": "See [install](./install)." })).await;
+        let locked = create_test_document(&state, &space_id, &alice.id, serde_json::json!({ "title": "Locked", "slug": "locked", "content": "See [install](./install)." })).await;
+        let raw_id = |document: &Document| document.id.as_deref().unwrap().trim_start_matches("document:").to_string();
+
+        // 显式授权覆盖空间角色，bob 对 locked 只有读权限
+        state.document_permission_service.grant_permission(&raw_id(&locked), crate::models::permission::GrantDocumentPermissionRequest {
+            user_id: Some(bob.id.clone()),
+            role: None,
+            access: crate::models::permission::DocumentAccess::Read,
+            expires_at: None,
+        }, &alice.id).await.unwrap();
+
+        let rename = update_request(serde_json::json!({ "slug": "setup", "rewrite_backlinks": true }));
+        let result = state.document_service.update_document(&raw_id(&target), &bob.id, rename).await.unwrap();
+
+        assert_eq!(result.backlinks_updated, 1);
+        assert_eq!(result.backlinks_failed, vec![raw_id(&locked)]);
+        assert_eq!(state.document_service.get_document(&raw_id(&open)).await.unwrap().content, "See [install](./setup).");
+        assert_eq!(state.document_service.get_document(&raw_id(&locked)).await.unwrap().content, "See [install](./install).");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_backlinks_in_private_space_hidden_from_non_members() {
+        let state = crate::state::test_support::test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let alice = test_user("alice", &run);
+        let bob = test_user("bob", &run);
+        let mallory = test_user("mallory", &run);
+        let space_id = create_test_space(&state, &alice, &format!("private-links-{}", run), false).await;
+        add_test_member(&state, &space_id, &alice, &bob, crate::models::space_member::MemberRole::Viewer).await;
+
+        let target = create_test_document(&state, &space_id, &alice.id, serde_json::json!({ "title": "Install", "slug": "install", "is_public": true })).await;
+        let linker = create_test_document(&state, &space_id, &alice.id, serde_json::json!({ "title": "Guide", "slug": "guide", "is_public": true, "content": "See [install](./install)." })).await;
+        let target_id = target.id.as_deref().unwrap().trim_start_matches("document:").to_string();
+
+        // 成员可以看到空间内的引用文档
+        let backlinks = state.document_service.get_backlinks(&target_id, Some(&bob.id)).await.unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(Some(&backlinks[0].id), linker.id.as_ref());
+
+        // 非成员和匿名用户即使文档公开也无法读取私有空间中的文档和引用列表
+        let error = state.document_service.get_backlinks(&target_id, Some(&mallory.id)).await.unwrap_err();
+        assert!(matches!(error, AppError::Authorization(_)));
+        let error = state.document_service.get_backlinks(&target_id, None).await.unwrap_err();
+        assert!(matches!(error, AppError::Authorization(_)));
+    }
+
+    #[test]
+    fn test_find_broken_links_reports_missing_slugs_only() {
+        let link = |url: &str, line: usize| MarkdownLink { text: url.to_string(), url: url.to_string(), line };
+        let slugs: std::collections::HashSet<String> = ["setup", "faq"].iter().map(|s| s.to_string()).collect();
+
+        let broken = find_broken_links(
+            vec![link("./setup", 2), link("old-install", 5), link("https://example.com", 7), link("faq#top", 9)],
+            &slugs,
+        );
+
+        assert_eq!(broken, vec![BrokenLink { target: "old-install".to_string(), text: "old-install".to_string(), line: 5 }]);
+    }
+
+    #[test]
+    fn test_slug_rename_rewrites_linker_and_leaves_others() {
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
+        let linker = "Start with [install](./install-guide) or [the FAQ](../install-guide.md#faq \"FAQ\").";
+        let unrelated = "See [installer](./install-guide-v2) and [home](https://example.com/install-guide).";
+        let mixed = "[old](install-guide) [newer](install-guide-v2)";
+
+        let rewritten = rewrite_slug_links(linker, &processor.extract_links(linker), "install-guide", "setup");
+        assert_eq!(
+            rewritten.as_deref(),
+            Some("Start with [install](./setup) or [the FAQ](../setup.md#faq \"FAQ\").")
+        );
+
+        assert_eq!(rewrite_slug_links(unrelated, &processor.extract_links(unrelated), "install-guide", "setup"), None);
+        assert_eq!(
+            rewrite_slug_links(mixed, &processor.extract_links(mixed), "install-guide", "setup").as_deref(),
+            Some("[old](setup) [newer](install-guide-v2)")
+        );
+    }
+
+    #[test]
+    fn test_renamed_document_is_rewritten_from_its_updated_content() {
+        let loaded = vec![
+            document_with("intro", "Intro", "See [guide](./install-guide)."),
+            document_with("install-guide", "Install", "Old body, [self](./install-guide)."),
+        ];
+        let mut renamed = document_with("install-guide", "Install", "New body, [self](./install-guide).");
+        renamed.slug = "setup".to_string();
+
+        let documents = with_renamed_document(loaded, &renamed);
+
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].content, "See [guide](./install-guide).");
+        assert_eq!(documents[1].content, "New body, [self](./install-guide).");
+        assert_eq!(documents[1].slug, "setup");
+    }
+
+    #[test]
+    fn test_backlinks_match_slug_or_id_links_only() {
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
+        let by_slug = "Read the [overview](./overview) first.";
+        let by_id = "Details in [this doc](/docs/id/abc123#usage).";
+        let unrelated = "Nothing to see, except [elsewhere](./roadmap).";
+
+        assert!(links_to_document(&processor.extract_links(by_slug), "overview", "abc123"));
+        assert!(links_to_document(&processor.extract_links(by_id), "overview", "abc123"));
+        assert!(!links_to_document(&processor.extract_links(unrelated), "overview", "abc123"));
+    }
+
+    fn create_request(title: &str, content: &str) -> CreateDocumentRequest {
+        CreateDocumentRequest {
+            title: title.to_string(),
+            slug: "notes".to_string(),
+            content: Some(content.to_string()),
+            excerpt: None,
+            is_public: None,
+            parent_id: None,
+            order_index: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_over_limit_content_is_rejected_by_bytes() {
+        let limits = DocumentLimits { max_title_chars: 200, max_content_bytes: 3000 };
+        // 1001 个汉字只有 1001 个字符，但有 3003 字节
+        let request = create_request("笔记", &"文".repeat(1001));
+        assert!(request.validate().is_ok());
+
+        match limits.check(Some(&request.title), request.content.as_deref()) {
+            Err(AppError::Validation(message)) => {
+                assert!(message.contains("3000 bytes"), "{}", message);
+            }
+            other => panic!("expected validation error, got {:?}", other),
+        }
+
+        let long_title = "t".repeat(81);
+        let limits = DocumentLimits { max_title_chars: 80, ..limits };
+        assert!(matches!(limits.check(Some(&long_title), None), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_near_limit_content_is_accepted() {
+        let limits = DocumentLimits { max_title_chars: 200, max_content_bytes: 3000 };
+        let request = create_request(&"标".repeat(200), &"文".repeat(1000));
+        assert_eq!(request.content.as_deref().map(str::len), Some(3000));
+
+        assert!(request.validate().is_ok());
+        assert!(limits.check(Some(&request.title), request.content.as_deref()).is_ok());
+        assert!(limits.check(None, None).is_ok());
+    }
+
+    fn document_with(id: &str, title: &str, content: &str) -> Document {
+        let mut document = Document::new("space:handbook".to_string(), title.to_string(), id.to_string(), "alice".to_string());
+        document.id = Some(format!("document:{}", id));
+        document.content = content.to_string();
+        document
+    }
+
+    #[test]
+    fn test_compare_partially_overlapping_documents() {
+        let a = document_with("install", "Install", "# Install\n\nRun the installer.\n\n## Configure\n\nEdit the file.");
+        let b = document_with("setup", "Setup", "# Install\n\nRun the setup script.\n\n## Upgrade\n\nEdit the file.");
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
+
+        let comparison = compare_document_contents(
+            &a,
+            &b,
+            &processor.extract_toc(&a.content).unwrap(),
+            &processor.extract_toc(&b.content).unwrap(),
+        );
+
+        let outline: Vec<(DiffSpanKind, &str)> = comparison.outline_diff.iter()
+            .map(|span| (span.kind, span.text.as_str()))
+            .collect();
+        assert_eq!(outline, vec![
+            (DiffSpanKind::Unchanged, "# Install"),
+            (DiffSpanKind::Removed, "## Configure"),
+            (DiffSpanKind::Added, "## Upgrade"),
+        ]);
+
+        let removed: String = comparison.content_diff.iter()
+            .filter(|span| span.kind == DiffSpanKind::Removed)
+            .map(|span| span.text.as_str())
+            .collect();
+        let added: String = comparison.content_diff.iter()
+            .filter(|span| span.kind == DiffSpanKind::Added)
+            .map(|span| span.text.as_str())
+            .collect();
+        assert_eq!(removed, "installerConfigure");
+        assert_eq!(added, "setup scriptUpgrade");
+        assert_eq!(comparison.title_diff.len(), 2);
+        assert!(comparison.similarity > 0.5 && comparison.similarity < 1.0);
+        assert_eq!(comparison.b.id.as_deref(), Some("document:setup"));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_generated_slug_skips_slugs_of_deleted_documents() {
+        let state = crate::state::test_support::test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let alice = test_user("alice", &run);
+        let space_id = create_test_space(&state, &alice, &format!("slugs-{}", run), true).await;
+
+        let deleted = create_test_document(&state, &space_id, &alice.id, serde_json::json!({ "title": "Release Notes" })).await;
+        assert_eq!(deleted.slug, "release-notes");
+        let deleted_id = deleted.id.as_deref().unwrap().trim_start_matches("document:").to_string();
+        state.document_service.delete_document(&deleted_id, &alice.id, false).await.unwrap();
+
+        // 唯一索引覆盖回收站中的文档，同名文档需要追加后缀
+        let created = create_test_document(&state, &space_id, &alice.id, serde_json::json!({ "title": "Release Notes" })).await;
+        assert_eq!(created.slug, "release-notes-2");
+    }
+}
@@ -1,19 +1,25 @@
+use std::collections::HashSet;
 use std::sync::Arc;
-use std::path::Path;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
 use axum::extract::Multipart;
+use chrono::Utc;
 use anyhow::Result;
 use surrealdb::sql::{Datetime, Thing};
 use uuid::Uuid;
 use tracing::{info, error, warn};
 use tokio::fs as async_fs;
-use image::ImageFormat;
+use image::{DynamicImage, ImageFormat};
 use mime_guess::from_path;
+use serde::Deserialize;
 use validator::Validate;
 
 use crate::{
-    error::ApiError,
-    models::file::{FileUpload, FileResponse, FileListResponse, FileQuery, UploadFileRequest},
-    services::{database::Database, auth::AuthService},
+    config::FileConfig,
+    error::AppError,
+    models::space::{SpaceSettings, SpaceUploadSettings},
+    models::file::{DocumentAttachment, FileUpload, FileResponse, FileListResponse, FileQuery, OrphanCleanupReport, UploadFileRequest},
+    services::{database::Database, auth::AuthService, space_member::SpaceMemberService},
 };
 
 #[derive(Clone)]
@@ -21,7 +27,9 @@ pub struct FileUploadService {
     db: Arc<Database>,
     auth_service: Arc<AuthService>,
     upload_dir: String,
-    max_file_size: usize,
+    upload_policy: UploadPolicy,
+    thumbnail_max_dimension: u32,
+    space_member_service: Option<Arc<SpaceMemberService>>,
 }
 
 impl FileUploadService {
@@ -30,31 +38,43 @@ impl FileUploadService {
             db,
             auth_service,
             upload_dir: std::env::var("UPLOAD_DIR").unwrap_or_else(|_| "./uploads".to_string()),
-            max_file_size: std::env::var("MAX_FILE_SIZE")
-                .unwrap_or_else(|_| "10485760".to_string()) // 10MB default
-                .parse()
-                .unwrap_or(10485760),
+            upload_policy: UploadPolicy::default(),
+            thumbnail_max_dimension: 300,
+            space_member_service: None,
         }
     }
 
+    pub fn with_upload_policy(mut self, upload_policy: UploadPolicy) -> Self {
+        self.upload_policy = upload_policy;
+        self
+    }
+
+    pub fn with_thumbnail_max_dimension(mut self, max_dimension: u32) -> Self {
+        self.thumbnail_max_dimension = max_dimension;
+        self
+    }
+
+    /// 设置空间成员服务，用于校验上传者能否写入目标空间
+    pub fn with_space_member_service(mut self, space_member_service: Arc<SpaceMemberService>) -> Self {
+        self.space_member_service = Some(space_member_service);
+        self
+    }
+
     pub async fn upload_file(
         &self,
         user_id: &str,
         mut multipart: Multipart,
         request: UploadFileRequest,
-    ) -> Result<FileResponse, ApiError> {
+    ) -> Result<FileResponse, AppError> {
         request.validate()?;
 
-        // 确保上传目录存在
-        self.ensure_upload_dir_exists().await?;
-
         let mut file_data = None;
         let mut filename = None;
         let mut content_type = None;
 
         // 处理 multipart 数据
         while let Some(field) = multipart.next_field().await.map_err(|e| {
-            ApiError::bad_request(format!("Failed to read multipart field: {}", e))
+            AppError::bad_request(format!("Failed to read multipart field: {}", e))
         })? {
             let field_name = field.name().unwrap_or("");
             
@@ -63,16 +83,11 @@ impl FileUploadService {
                 content_type = field.content_type().map(|s| s.to_string());
                 
                 let data = field.bytes().await.map_err(|e| {
-                    ApiError::bad_request(format!("Failed to read file data: {}", e))
+                    AppError::bad_request(format!("Failed to read file data: {}", e))
                 })?;
 
-                // 检查文件大小
-                if data.len() > self.max_file_size {
-                    return Err(ApiError::bad_request(format!(
-                        "File size exceeds maximum allowed size of {} bytes",
-                        self.max_file_size
-                    )));
-                }
+                // 尽早拒绝超过全局上限的文件，空间级限制在保存前检查
+                self.upload_policy.check_size(data.len() as u64)?;
 
                 file_data = Some(data);
                 break;
@@ -80,93 +95,14 @@ impl FileUploadService {
         }
 
         let file_data = file_data.ok_or_else(|| {
-            ApiError::bad_request("No file found in request".to_string())
+            AppError::bad_request("No file found in request".to_string())
         })?;
 
         let original_name = filename.ok_or_else(|| {
-            ApiError::bad_request("No filename provided".to_string())
-        })?;
-
-        // 生成唯一文件名
-        let file_extension = Path::new(&original_name)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("");
-        
-        let unique_filename = if file_extension.is_empty() {
-            Uuid::new_v4().to_string()
-        } else {
-            format!("{}.{}", Uuid::new_v4(), file_extension)
-        };
-
-        // 确定 MIME 类型
-        let mime_type = content_type.unwrap_or_else(|| {
-            from_path(&original_name).first_or_octet_stream().to_string()
-        });
-
-        // 验证文件类型
-        self.validate_file_type(&mime_type)?;
-
-        // 创建文件路径
-        let file_path = Path::new(&self.upload_dir).join(&unique_filename);
-        
-        // 保存文件
-        async_fs::write(&file_path, &file_data).await.map_err(|e| {
-            error!("Failed to save file: {}", e);
-            ApiError::internal_server_error("Failed to save file".to_string())
-        })?;
-
-        // 如果是图片，生成缩略图
-        if mime_type.starts_with("image/") {
-            if let Err(e) = self.generate_thumbnail(&file_path, &unique_filename).await {
-                warn!("Failed to generate thumbnail for {}: {}", unique_filename, e);
-            }
-        }
-
-        // 确定文件类型
-        let file_type = self.determine_file_type(&mime_type);
-
-        // 保存到数据库
-        let mut file_upload = FileUpload::new(
-            unique_filename.clone(),
-            original_name,
-            file_path.to_string_lossy().to_string(),
-            file_data.len() as i64,
-            file_type,
-            mime_type,
-            user_id.to_string(),
-        );
-
-        // 设置关联的空间或文档
-        if let Some(space_id) = &request.space_id {
-            if let Ok(space_thing) = space_id.parse::<Thing>() {
-                file_upload = file_upload.with_space(space_thing);
-            }
-        }
-
-        if let Some(document_id) = &request.document_id {
-            if let Ok(doc_thing) = document_id.parse::<Thing>() {
-                file_upload = file_upload.with_document(doc_thing);
-            }
-        }
-
-        let created_files: Vec<FileUpload> = self.db.client
-            .create("file_upload")
-            .content(file_upload)
-            .await
-            .map_err(|e| {
-                error!("Failed to save file to database: {}", e);
-                ApiError::internal_server_error("Failed to save file metadata".to_string())
-            })?;
-
-        let created_file = created_files.into_iter().next();
-
-        let created_file = created_file.ok_or_else(|| {
-            ApiError::internal_server_error("Failed to create file record".to_string())
+            AppError::bad_request("No filename provided".to_string())
         })?;
 
-        info!("File uploaded successfully: {}", unique_filename);
-        Ok(created_file.into())
+        self.upload_file_from_bytes(user_id, file_data, original_name, content_type, request).await
     }
 
     pub async fn upload_file_from_bytes(
@@ -176,20 +112,43 @@ impl FileUploadService {
         original_name: String,
         content_type: Option<String>,
         request: UploadFileRequest,
-    ) -> Result<FileResponse, ApiError> {
+    ) -> Result<FileResponse, AppError> {
         request.validate()?;
 
         // 确保上传目录存在
         self.ensure_upload_dir_exists().await?;
 
-        // 检查文件大小
-        if file_data.len() > self.max_file_size {
-            return Err(ApiError::bad_request(format!(
-                "File size exceeds maximum allowed size of {} bytes",
-                self.max_file_size
-            )));
+        // 确定 MIME 类型
+        let mime_type = normalize_mime_type(&content_type.unwrap_or_else(|| {
+            from_path(&original_name).first_or_octet_stream().to_string()
+        }));
+
+        // 检查大小、类型和空间配额，通过后预占空间的存储用量
+        let space_id = self.upload_space(&request).await?;
+        let file_size = file_data.len() as u64;
+        self.reserve_upload(user_id, space_id.as_ref(), file_size, &mime_type).await?;
+
+        let stored = self
+            .store_file(user_id, &file_data, original_name, mime_type, space_id.clone(), &request)
+            .await;
+        if stored.is_err() {
+            if let Some(space_id) = &space_id {
+                self.release_space_storage(space_id, file_size).await;
+            }
         }
+        stored
+    }
 
+    /// 保存文件内容和元数据，并在指定文档时自动关联为附件
+    async fn store_file(
+        &self,
+        user_id: &str,
+        file_data: &[u8],
+        original_name: String,
+        mime_type: String,
+        space_id: Option<Thing>,
+        request: &UploadFileRequest,
+    ) -> Result<FileResponse, AppError> {
         // 生成唯一文件名
         let file_extension = Path::new(&original_name)
             .extension()
@@ -202,29 +161,27 @@ impl FileUploadService {
             format!("{}.{}", Uuid::new_v4(), file_extension)
         };
 
-        // 确定 MIME 类型
-        let mime_type = content_type.unwrap_or_else(|| {
-            from_path(&original_name).first_or_octet_stream().to_string()
-        });
-
-        // 验证文件类型
-        self.validate_file_type(&mime_type)?;
-
         // 创建文件路径
         let file_path = Path::new(&self.upload_dir).join(&unique_filename);
         
         // 保存文件
-        async_fs::write(&file_path, &file_data).await.map_err(|e| {
+        async_fs::write(&file_path, file_data).await.map_err(|e| {
             error!("Failed to save file: {}", e);
-            ApiError::internal_server_error("Failed to save file".to_string())
+            AppError::internal_server_error("Failed to save file".to_string())
         })?;
 
-        // 如果是图片，生成缩略图
-        if mime_type.starts_with("image/") {
-            if let Err(e) = self.generate_thumbnail(&file_path, &unique_filename).await {
-                warn!("Failed to generate thumbnail for {}: {}", unique_filename, e);
+        // 如果是图片，生成缩略图；格式不支持或生成失败时仍保留原文件
+        let thumbnail_path = if mime_type.starts_with("image/") {
+            match self.generate_thumbnail(file_data, &unique_filename).await {
+                Ok(path) => path,
+                Err(e) => {
+                    warn!("Failed to generate thumbnail for {}: {}", unique_filename, e);
+                    None
+                }
             }
-        }
+        } else {
+            None
+        };
 
         // 确定文件类型
         let file_type = self.determine_file_type(&mime_type);
@@ -238,13 +195,12 @@ impl FileUploadService {
             file_type,
             mime_type,
             user_id.to_string(),
-        );
+        )
+        .with_thumbnail(thumbnail_path);
 
         // 设置关联的空间或文档
-        if let Some(space_id) = &request.space_id {
-            if let Ok(space_thing) = space_id.parse::<Thing>() {
-                file_upload = file_upload.with_space(space_thing);
-            }
+        if let Some(space_thing) = space_id {
+            file_upload = file_upload.with_space(space_thing);
         }
 
         if let Some(document_id) = &request.document_id {
@@ -259,35 +215,113 @@ impl FileUploadService {
             .await
             .map_err(|e| {
                 error!("Failed to save file to database: {}", e);
-                ApiError::internal_server_error("Failed to save file metadata".to_string())
+                AppError::internal_server_error("Failed to save file metadata".to_string())
             })?;
 
         let created_file = created_files.into_iter().next();
 
         let created_file = created_file.ok_or_else(|| {
-            ApiError::internal_server_error("Failed to create file record".to_string())
+            AppError::internal_server_error("Failed to create file record".to_string())
         })?;
 
+        self.attach_uploaded_file(&created_file, user_id).await;
+
         info!("File uploaded successfully: {}", unique_filename);
         Ok(created_file.into())
     }
 
-    pub async fn get_file(&self, file_id: &str) -> Result<FileUpload, ApiError> {
+    /// 上传计入配额的空间：优先使用请求中的空间，否则取关联文档所在的空间
+    async fn upload_space(&self, request: &UploadFileRequest) -> Result<Option<Thing>, AppError> {
+        if let Some(space_thing) = request.space_id.as_ref().and_then(|id| id.parse::<Thing>().ok()) {
+            return Ok(Some(space_thing));
+        }
+        let Some(doc_thing) = request.document_id.as_ref().and_then(|id| id.parse::<Thing>().ok()) else {
+            return Ok(None);
+        };
+
+        let space_ids: Vec<Thing> = self.db.client
+            .query("SELECT VALUE space_id FROM $document")
+            .bind(("document", doc_thing))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)?;
+        Ok(space_ids.into_iter().next())
+    }
+
+    /// 按全局配置与空间设置检查上传，通过后原子地增加空间的存储用量。
+    /// 上传者必须对目标空间有 docs.write 权限，否则任何人都能占满其他空间的配额。
+    /// 检查与预占之间有并发上传时，由条件更新保证不会超出配额
+    async fn reserve_upload(&self, user_id: &str, space_id: Option<&Thing>, file_size: u64, mime_type: &str) -> Result<(), AppError> {
+        let Some(space_id) = space_id else {
+            return self.upload_policy.check(file_size, mime_type, None);
+        };
+
+        let space_member_service = self.space_member_service.as_ref().ok_or_else(|| {
+            AppError::Internal(anyhow::anyhow!("Space member service is not configured"))
+        })?;
+        if !space_member_service.check_permission(&space_id.id.to_raw(), user_id, "docs.write").await? {
+            return Err(AppError::forbidden("Permission denied: docs.write required".to_string()));
+        }
+
+        let states: Vec<SpaceStorage> = self.db.client
+            .query("SELECT settings, storage_used FROM $space")
+            .bind(("space", space_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)?;
+        let state = states
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::not_found("Space not found".to_string()))?;
+
+        let policy = self.upload_policy.for_space(&state.settings.upload);
+        policy.check(file_size, mime_type, Some(state.storage_used.unwrap_or(0)))?;
+
+        let reserved: Vec<serde_json::Value> = self.db.client
+            .query(
+                "UPDATE $space SET storage_used = (storage_used ?? 0) + $size \
+                 WHERE $quota = 0 OR (storage_used ?? 0) + $size <= $quota RETURN storage_used",
+            )
+            .bind(("space", space_id))
+            .bind(("size", file_size))
+            .bind(("quota", policy.storage_quota))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)?;
+        if reserved.is_empty() {
+            return Err(quota_exceeded(policy.storage_quota));
+        }
+        Ok(())
+    }
+
+    /// 归还空间的存储用量，失败时只记录警告
+    async fn release_space_storage(&self, space_id: &Thing, file_size: u64) {
+        let result = self.db.client
+            .query("UPDATE $space SET storage_used = math::max([(storage_used ?? 0) - $size, 0])")
+            .bind(("space", space_id))
+            .bind(("size", file_size))
+            .await;
+        if let Err(e) = result {
+            warn!("Failed to release {} bytes of storage for space {}: {}", file_size, space_id, e);
+        }
+    }
+
+    pub async fn get_file(&self, file_id: &str) -> Result<FileUpload, AppError> {
         let file_thing = file_id.parse::<Thing>()
-            .map_err(|_| ApiError::bad_request("Invalid file ID".to_string()))?;
+            .map_err(|_| AppError::bad_request("Invalid file ID".to_string()))?;
 
         let file: Option<FileUpload> = self.db.client
             .select(("file_upload", file_thing.id))
             .await
             .map_err(|e| {
                 error!("Failed to get file: {}", e);
-                ApiError::internal_server_error("Failed to retrieve file".to_string())
+                AppError::internal_server_error("Failed to retrieve file".to_string())
             })?;
 
-        let file = file.ok_or_else(|| ApiError::not_found("File not found".to_string()))?;
+        let file = file.ok_or_else(|| AppError::not_found("File not found".to_string()))?;
 
         if file.is_deleted {
-            return Err(ApiError::not_found("File not found".to_string()));
+            return Err(AppError::not_found("File not found".to_string()));
         }
 
         Ok(file)
@@ -297,7 +331,7 @@ impl FileUploadService {
         &self,
         user_id: &str,
         query: FileQuery,
-    ) -> Result<FileListResponse, ApiError> {
+    ) -> Result<FileListResponse, AppError> {
         let page = query.page.unwrap_or(1).max(1);
         let per_page = query.per_page.unwrap_or(20).min(100).max(1);
         let offset = (page - 1) * per_page;
@@ -337,7 +371,7 @@ impl FileUploadService {
             .await
             .map_err(|e| {
                 error!("Failed to count files: {}", e);
-                ApiError::internal_server_error("Failed to count files".to_string())
+                AppError::internal_server_error("Failed to count files".to_string())
             })?
             .take(0)?;
 
@@ -354,7 +388,7 @@ impl FileUploadService {
             .await
             .map_err(|e| {
                 error!("Failed to list files: {}", e);
-                ApiError::internal_server_error("Failed to list files".to_string())
+                AppError::internal_server_error("Failed to list files".to_string())
             })?
             .take(0)?;
 
@@ -370,9 +404,9 @@ impl FileUploadService {
         })
     }
 
-    pub async fn delete_file(&self, user_id: &str, file_id: &str) -> Result<(), ApiError> {
+    pub async fn delete_file(&self, user_id: &str, file_id: &str) -> Result<(), AppError> {
         let file_thing = file_id.parse::<Thing>()
-            .map_err(|_| ApiError::bad_request("Invalid file ID".to_string()))?;
+            .map_err(|_| AppError::bad_request("Invalid file ID".to_string()))?;
 
         let mut file: FileUpload = self.get_file(file_id).await?;
 
@@ -384,7 +418,7 @@ impl FileUploadService {
                 let space_id_str = space_id_string
                     .split(':')
                     .nth(1)
-                    .ok_or_else(|| ApiError::internal_server_error("Invalid space ID format".to_string()))?;
+                    .ok_or_else(|| AppError::internal_server_error("Invalid space ID format".to_string()))?;
                 
                 // 检查用户是否有空间的管理权限
                 match self.auth_service.check_permission(user_id, "docs.admin", Some(space_id_str)).await {
@@ -392,17 +426,19 @@ impl FileUploadService {
                         // 用户有管理权限，可以删除
                     }
                     Err(_) => {
-                        return Err(ApiError::forbidden("Permission denied: You can only delete your own files or need admin permission".to_string()));
+                        return Err(AppError::forbidden("Permission denied: You can only delete your own files or need admin permission".to_string()));
                     }
                 }
             } else {
                 // 没有关联空间的文件，只有上传者可以删除
-                return Err(ApiError::forbidden("Permission denied: You can only delete your own files".to_string()));
+                return Err(AppError::forbidden("Permission denied: You can only delete your own files".to_string()));
             }
         }
 
         // 标记为删除
         file.mark_deleted(user_id.to_string());
+        let space_id = file.space_id.clone();
+        let file_size = file.file_size.max(0) as u64;
 
         // 更新数据库
         let _: Option<FileUpload> = self.db.client
@@ -411,51 +447,231 @@ impl FileUploadService {
             .await
             .map_err(|e| {
                 error!("Failed to delete file: {}", e);
-                ApiError::internal_server_error("Failed to delete file".to_string())
+                AppError::internal_server_error("Failed to delete file".to_string())
             })?;
 
+        if let Some(space_id) = &space_id {
+            self.release_space_storage(space_id, file_size).await;
+        }
+
         info!("File marked as deleted: {}", file_id);
         Ok(())
     }
 
-    pub async fn get_file_content(&self, file_id: &str) -> Result<(Vec<u8>, String, String), ApiError> {
+    pub async fn get_file_content(&self, file_id: &str) -> Result<(Vec<u8>, String, String), AppError> {
         let file = self.get_file(file_id).await?;
         
         let content = async_fs::read(&file.file_path).await.map_err(|e| {
             error!("Failed to read file content: {}", e);
-            ApiError::internal_server_error("Failed to read file".to_string())
+            AppError::internal_server_error("Failed to read file".to_string())
         })?;
 
         Ok((content, file.mime_type, file.original_name))
     }
 
-    pub async fn get_thumbnail(&self, file_id: &str) -> Result<Vec<u8>, ApiError> {
+    /// 返回缩略图内容及其 Content-Type
+    pub async fn get_thumbnail(&self, file_id: &str) -> Result<(Vec<u8>, String), AppError> {
         let file = self.get_file(file_id).await?;
         
         if !file.is_image() {
-            return Err(ApiError::bad_request("File is not an image".to_string()));
+            return Err(AppError::bad_request("File is not an image".to_string()));
         }
 
-        let thumbnail_path = self.get_thumbnail_path(&file.filename);
+        // 早期上传的图片没有记录缩略图路径，缩略图固定为 JPEG
+        let thumbnail_path = match &file.thumbnail_path {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(&self.upload_dir).join("thumbnails").join(format!("thumb_{}", file.filename)),
+        };
+        let content_type = match &file.thumbnail_path {
+            Some(path) => from_path(path).first_or_octet_stream().to_string(),
+            None => "image/jpeg".to_string(),
+        };
         
         if !thumbnail_path.exists() {
-            return Err(ApiError::not_found("Thumbnail not found".to_string()));
+            return Err(AppError::not_found("Thumbnail not found".to_string()));
         }
 
         let content = async_fs::read(&thumbnail_path).await.map_err(|e| {
             error!("Failed to read thumbnail: {}", e);
-            ApiError::internal_server_error("Failed to read thumbnail".to_string())
+            AppError::internal_server_error("Failed to read thumbnail".to_string())
         })?;
 
-        Ok(content)
+        Ok((content, content_type))
+    }
+
+    /// 把文件关联到文档，文件必须在文档所在空间或由调用者上传。已关联时只取消待清理标记
+    pub async fn attach_file(&self, document_id: &str, document_space_id: &str, file_id: &str, user_id: &str) -> Result<FileResponse, AppError> {
+        let file = self.get_file(&file_thing(file_id).to_string()).await?;
+        ensure_file_attachable(&file, document_space_id, user_id)?;
+        self.record_attachment(document_thing(document_id), file_thing(file_id), user_id).await?;
+        Ok(file.into())
+    }
+
+    /// 按关联顺序列出文档的附件，已删除的文件不返回
+    pub async fn list_attachments(&self, document_id: &str) -> Result<Vec<FileResponse>, AppError> {
+        let attachments: Vec<DocumentAttachment> = self.db.client
+            .query("SELECT * FROM document_attachment WHERE document_id = $document_id ORDER BY attached_at ASC")
+            .bind(("document_id", document_thing(document_id)))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)?;
+        if attachments.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let file_ids: Vec<Thing> = attachments.iter().map(|a| a.file_id.clone()).collect();
+        let files: Vec<FileUpload> = self.db.client
+            .query("SELECT * FROM file_upload WHERE id INSIDE $file_ids AND is_deleted = false")
+            .bind(("file_ids", file_ids))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)?;
+
+        Ok(attached_files(&attachments, files))
     }
 
-    async fn ensure_upload_dir_exists(&self) -> Result<(), ApiError> {
+    /// 取消文件与文档的关联，文件本身保留
+    pub async fn detach_file(&self, document_id: &str, file_id: &str) -> Result<(), AppError> {
+        let removed: Vec<DocumentAttachment> = self.db.client
+            .query("DELETE document_attachment WHERE document_id = $document_id AND file_id = $file_id RETURN BEFORE")
+            .bind(("document_id", document_thing(document_id)))
+            .bind(("file_id", file_thing(file_id)))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)?;
+
+        if removed.is_empty() {
+            return Err(AppError::not_found("Attachment not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// 设置文档附件的待清理标记：文档删除时标记，恢复时取消
+    pub async fn mark_attachments_for_cleanup(&self, document_ids: &[String], pending_cleanup: bool) -> Result<(), AppError> {
+        let documents: Vec<Thing> = document_ids.iter().map(|id| document_thing(id)).collect();
+        self.db.client
+            .query("UPDATE document_attachment SET pending_cleanup = $pending_cleanup WHERE document_id INSIDE $documents")
+            .bind(("pending_cleanup", pending_cleanup))
+            .bind(("documents", documents))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        Ok(())
+    }
+
+    /// 删除上传时间超过 older_than 且没有被引用的文件，包括磁盘上的文件、缩略图和数据库记录。
+    /// 有效的附件关联、空间头像、文档或发布的封面，以及文档、历史版本、草稿、评论、模板和发布正文中的链接都算作引用；
+    /// 附件所在文档进入回收站的时间不足 older_than 时仍保留，以便恢复文档
+    pub async fn cleanup_orphans(&self, older_than: chrono::Duration) -> Result<OrphanCleanupReport, AppError> {
+        let cutoff = Datetime::from(Utc::now() - older_than);
+
+        // 早期上传的文件只记录了 document_id，文档仍存在时视为已引用
+        let candidates: Vec<FileUpload> = self.db.client
+            .query("SELECT * FROM file_upload WHERE created_at < <datetime> $cutoff AND (document_id = NONE OR document_id.id = NONE)")
+            .bind(("cutoff", &cutoff))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)?;
+        if candidates.is_empty() {
+            return Ok(OrphanCleanupReport::default());
+        }
+
+        let file_ids: Vec<Thing> = candidates.iter().filter_map(|file| file.id.clone()).collect();
+        let mut response = self.db.client
+            .query(ORPHAN_REFERENCES_QUERY)
+            .bind(("files", file_ids))
+            .bind(("cutoff", &cutoff))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let attached: Vec<Thing> = response.take(0)?;
+        let attached: HashSet<String> = attached.iter().map(|id| id.to_string()).collect();
+        let mut referencing_texts: Vec<String> = Vec::new();
+        for statement in 1..response.num_statements() {
+            let texts: Vec<String> = response.take(statement)?;
+            referencing_texts.extend(texts);
+        }
+
+        let mut report = OrphanCleanupReport::default();
+        for file in orphaned_files(candidates, &attached, &referencing_texts) {
+            match self.remove_orphan(&file).await {
+                Ok(()) => {
+                    report.files_removed += 1;
+                    report.bytes_freed += file.file_size.max(0) as u64;
+                }
+                Err(e) => warn!("Failed to remove orphaned file {}: {}", file.filename, e),
+            }
+        }
+
+        if report.files_removed > 0 {
+            info!("Removed {} orphaned files, freed {} bytes", report.files_removed, report.bytes_freed);
+        }
+        Ok(report)
+    }
+
+    /// 先删除数据库记录再删除磁盘文件，磁盘删除失败只会留下无记录的文件
+    async fn remove_orphan(&self, file: &FileUpload) -> Result<(), AppError> {
+        let Some(file_id) = &file.id else {
+            return Ok(());
+        };
+        self.db.client
+            .query("DELETE document_attachment WHERE file_id = $file; DELETE $file;")
+            .bind(("file", file_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        for path in std::iter::once(file.file_path.as_str()).chain(file.thumbnail_path.as_deref()) {
+            if let Err(e) = async_fs::remove_file(path).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Failed to remove {}: {}", path, e);
+                }
+            }
+        }
+
+        // 已删除的文件在删除时就归还了空间存储
+        if let (false, Some(space_id)) = (file.is_deleted, &file.space_id) {
+            self.release_space_storage(space_id, file.file_size.max(0) as u64).await;
+        }
+        Ok(())
+    }
+
+    /// 上传时指定了文档的文件自动成为该文档的附件，关联失败不影响上传
+    async fn attach_uploaded_file(&self, file: &FileUpload, user_id: &str) {
+        let (Some(document_id), Some(file_id)) = (&file.document_id, &file.id) else {
+            return;
+        };
+        if let Err(e) = self.record_attachment(document_id.clone(), file_id.clone(), user_id).await {
+            warn!("Failed to attach uploaded file {} to document {}: {}", file_id, document_id, e);
+        }
+    }
+
+    async fn record_attachment(&self, document_id: Thing, file_id: Thing, user_id: &str) -> Result<(), AppError> {
+        let existing: Vec<DocumentAttachment> = self.db.client
+            .query("UPDATE document_attachment SET pending_cleanup = false WHERE document_id = $document_id AND file_id = $file_id")
+            .bind(("document_id", &document_id))
+            .bind(("file_id", &file_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)?;
+        if !existing.is_empty() {
+            return Ok(());
+        }
+
+        let _: Vec<DocumentAttachment> = self.db.client
+            .create("document_attachment")
+            .content(DocumentAttachment::new(document_id, file_id, user_id.to_string()))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn ensure_upload_dir_exists(&self) -> Result<(), AppError> {
         let upload_path = Path::new(&self.upload_dir);
         if !upload_path.exists() {
             async_fs::create_dir_all(upload_path).await.map_err(|e| {
                 error!("Failed to create upload directory: {}", e);
-                ApiError::internal_server_error("Failed to create upload directory".to_string())
+                AppError::internal_server_error("Failed to create upload directory".to_string())
             })?;
         }
 
@@ -463,41 +679,13 @@ impl FileUploadService {
         if !thumbnails_path.exists() {
             async_fs::create_dir_all(thumbnails_path).await.map_err(|e| {
                 error!("Failed to create thumbnails directory: {}", e);
-                ApiError::internal_server_error("Failed to create thumbnails directory".to_string())
+                AppError::internal_server_error("Failed to create thumbnails directory".to_string())
             })?;
         }
 
         Ok(())
     }
 
-    fn validate_file_type(&self, mime_type: &str) -> Result<(), ApiError> {
-        let allowed_types = [
-            // 图片
-            "image/jpeg", "image/jpg", "image/png", "image/gif", "image/webp", "image/svg+xml",
-            // 文档
-            "application/pdf", "application/msword", 
-            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
-            "application/vnd.ms-excel",
-            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-            "application/vnd.ms-powerpoint",
-            "application/vnd.openxmlformats-officedocument.presentationml.presentation",
-            // 文本
-            "text/plain", "text/markdown", "text/csv",
-            // 代码
-            "application/json", "application/xml", "text/html", "text/css", "text/javascript",
-            // 压缩文件
-            "application/zip", "application/x-tar", "application/gzip",
-        ];
-
-        if !allowed_types.contains(&mime_type) {
-            return Err(ApiError::bad_request(format!(
-                "File type '{}' is not allowed", mime_type
-            )));
-        }
-
-        Ok(())
-    }
-
     fn determine_file_type(&self, mime_type: &str) -> String {
         match mime_type {
             t if t.starts_with("image/") => "image".to_string(),
@@ -513,19 +701,488 @@ impl FileUploadService {
         }
     }
 
-    async fn generate_thumbnail(&self, file_path: &Path, filename: &str) -> Result<()> {
-        let img = image::open(file_path)?;
-        let thumbnail = img.thumbnail(300, 300);
-        
-        let thumbnail_path = self.get_thumbnail_path(filename);
-        thumbnail.save_with_format(&thumbnail_path, ImageFormat::Jpeg)?;
-        
+    /// 生成缩略图并保存到上传目录的 thumbnails 下，返回缩略图路径。
+    /// 无法解码的图片格式（如 SVG）返回 None
+    async fn generate_thumbnail(&self, data: &[u8], filename: &str) -> Result<Option<String>> {
+        // 解码和缩放是 CPU 密集操作，放到阻塞线程池中执行，避免占用异步运行时
+        let data = data.to_vec();
+        let max_dimension = self.thumbnail_max_dimension;
+        let thumbnail = tokio::task::spawn_blocking(move || make_thumbnail(&data, max_dimension)).await??;
+        let Some(thumbnail) = thumbnail else {
+            return Ok(None);
+        };
+
+        let stem = Path::new(filename).file_stem().and_then(|s| s.to_str()).unwrap_or(filename);
+        let thumbnail_path = Path::new(&self.upload_dir)
+            .join("thumbnails")
+            .join(format!("thumb_{}.{}", stem, thumbnail.extension));
+        async_fs::write(&thumbnail_path, &thumbnail.data).await?;
+
+        Ok(Some(thumbnail_path.to_string_lossy().to_string()))
+    }
+}
+
+/// 查询孤立文件候选的引用：第一条语句返回有效的附件关联，其余语句返回可能引用文件的文本。
+/// 绑定的时间经 serde 序列化后是字符串，必须转换为 datetime 再比较
+const ORPHAN_REFERENCES_QUERY: &str = "SELECT VALUE file_id FROM document_attachment WHERE file_id INSIDE $files \
+        AND (pending_cleanup = false OR document_id.deleted_at = NONE OR document_id.deleted_at > <datetime> $cutoff); \
+    SELECT VALUE cover_image FROM document WHERE cover_image != NONE; \
+    SELECT VALUE content FROM document WHERE content CONTAINS '/files/' \
+        AND (is_deleted = false OR deleted_at = NONE OR deleted_at > <datetime> $cutoff); \
+    SELECT VALUE content FROM document_version WHERE content CONTAINS '/files/'; \
+    SELECT VALUE content FROM document_draft WHERE content CONTAINS '/files/'; \
+    SELECT VALUE content FROM comment WHERE content CONTAINS '/files/'; \
+    SELECT VALUE content FROM document_template WHERE content CONTAINS '/files/'; \
+    SELECT VALUE avatar_url FROM space WHERE avatar_url != NONE; \
+    SELECT VALUE cover_image FROM space_publication WHERE cover_image != NONE; \
+    SELECT VALUE content FROM publication_document WHERE content CONTAINS '/files/';";
+
+/// 默认允许上传的 MIME 类型
+const DEFAULT_ALLOWED_MIME_TYPES: &[&str] = &[
+    // 图片
+    "image/jpeg", "image/jpg", "image/png", "image/gif", "image/webp", "image/svg+xml",
+    // 文档
+    "application/pdf", "application/msword", 
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.ms-excel",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.ms-powerpoint",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    // 文本
+    "text/plain", "text/markdown", "text/csv",
+    // 代码
+    "application/json", "application/xml", "text/html", "text/css", "text/javascript",
+    // 压缩文件
+    "application/zip", "application/x-tar", "application/gzip",
+];
+
+/// 上传限制：单文件大小上限、允许的 MIME 类型和每个空间的存储配额（0 表示不限制）
+#[derive(Debug, Clone)]
+pub struct UploadPolicy {
+    pub max_file_size: u64,
+    pub allowed_mime_types: Vec<String>,
+    pub storage_quota: u64,
+}
+
+impl Default for UploadPolicy {
+    fn default() -> Self {
+        Self {
+            max_file_size: 10 * 1024 * 1024,
+            allowed_mime_types: DEFAULT_ALLOWED_MIME_TYPES.iter().map(|t| t.to_string()).collect(),
+            storage_quota: 0,
+        }
+    }
+}
+
+impl From<&FileConfig> for UploadPolicy {
+    fn from(config: &FileConfig) -> Self {
+        let allowed_mime_types = if config.allowed_mime_types.is_empty() {
+            UploadPolicy::default().allowed_mime_types
+        } else {
+            config.allowed_mime_types.iter().map(|t| normalize_mime_type(t)).collect()
+        };
+        Self {
+            max_file_size: config.max_file_size,
+            allowed_mime_types,
+            storage_quota: config.space_storage_quota,
+        }
+    }
+}
+
+impl UploadPolicy {
+    /// 叠加空间自己的上传设置。空间设置只能收紧全局限制，不能放宽
+    pub fn for_space(&self, settings: &SpaceUploadSettings) -> Self {
+        let max_file_size = settings
+            .max_file_size
+            .map_or(self.max_file_size, |size| size.min(self.max_file_size));
+        let allowed_mime_types = match &settings.allowed_mime_types {
+            Some(types) => types
+                .iter()
+                .map(|t| normalize_mime_type(t))
+                .filter(|t| self.allows_type(t))
+                .collect(),
+            None => self.allowed_mime_types.clone(),
+        };
+        let storage_quota = match (self.storage_quota, settings.storage_quota.filter(|&q| q > 0)) {
+            (0, space_quota) => space_quota.unwrap_or(0),
+            (global, Some(space_quota)) => global.min(space_quota),
+            (global, None) => global,
+        };
+        Self { max_file_size, allowed_mime_types, storage_quota }
+    }
+
+    pub fn check_size(&self, file_size: u64) -> Result<(), AppError> {
+        if file_size > self.max_file_size {
+            return Err(AppError::bad_request(format!(
+                "File size exceeds maximum allowed size of {} bytes",
+                self.max_file_size
+            )));
+        }
         Ok(())
     }
 
-    fn get_thumbnail_path(&self, filename: &str) -> std::path::PathBuf {
-        Path::new(&self.upload_dir)
-            .join("thumbnails")
-            .join(format!("thumb_{}", filename))
+    /// 检查单个文件。storage_used 为所属空间已用的存储，不属于任何空间时为 None
+    pub fn check(&self, file_size: u64, mime_type: &str, storage_used: Option<u64>) -> Result<(), AppError> {
+        self.check_size(file_size)?;
+
+        if !self.allows_type(mime_type) {
+            return Err(AppError::bad_request(format!(
+                "File type '{}' is not allowed", mime_type
+            )));
+        }
+
+        if let Some(used) = storage_used {
+            if self.storage_quota > 0 && used.saturating_add(file_size) > self.storage_quota {
+                return Err(quota_exceeded(self.storage_quota));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn allows_type(&self, mime_type: &str) -> bool {
+        self.allowed_mime_types.iter().any(|t| t == mime_type)
+    }
+}
+
+fn quota_exceeded(storage_quota: u64) -> AppError {
+    AppError::forbidden(format!(
+        "Space storage quota of {} bytes exceeded", storage_quota
+    ))
+}
+
+/// 去掉 Content-Type 中的参数并转为小写，如 `Text/Plain; charset=utf-8` -> `text/plain`
+fn normalize_mime_type(mime_type: &str) -> String {
+    mime_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase()
+}
+
+/// 空间的上传设置与当前存储用量
+#[derive(Debug, Deserialize)]
+struct SpaceStorage {
+    #[serde(default)]
+    settings: SpaceSettings,
+    storage_used: Option<u64>,
+}
+
+/// 编码后的缩略图
+struct Thumbnail {
+    data: Vec<u8>,
+    extension: &'static str,
+}
+
+/// 把图片等比缩放到最长边不超过 max_dimension，小图保持原尺寸。
+/// JPEG 原图输出 JPEG，其余格式输出 PNG 以保留透明通道；不支持的格式返回 None
+fn make_thumbnail(data: &[u8], max_dimension: u32) -> Result<Option<Thumbnail>> {
+    let format = match image::guess_format(data) {
+        Ok(format @ (ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Gif | ImageFormat::WebP)) => format,
+        _ => return Ok(None),
+    };
+
+    let img = image::load_from_memory_with_format(data, format)?;
+    let img = if img.width() > max_dimension || img.height() > max_dimension {
+        img.thumbnail(max_dimension, max_dimension)
+    } else {
+        img
+    };
+
+    let (img, output_format, extension) = if format == ImageFormat::Jpeg {
+        (DynamicImage::ImageRgb8(img.to_rgb8()), ImageFormat::Jpeg, "jpg")
+    } else {
+        (img, ImageFormat::Png, "png")
+    };
+
+    let mut encoded = Cursor::new(Vec::new());
+    img.write_to(&mut encoded, output_format)?;
+
+    Ok(Some(Thumbnail { data: encoded.into_inner(), extension }))
+}
+
+fn document_thing(document_id: &str) -> Thing {
+    Thing::from(("document", document_id.strip_prefix("document:").unwrap_or(document_id)))
+}
+
+fn file_thing(file_id: &str) -> Thing {
+    Thing::from(("file_upload", file_id.strip_prefix("file_upload:").unwrap_or(file_id)))
+}
+
+/// 从候选文件中挑出没有被引用的文件：不在有效附件中，引用文本里也没有出现文件ID或存储文件名
+fn orphaned_files(candidates: Vec<FileUpload>, attached: &HashSet<String>, referencing_texts: &[String]) -> Vec<FileUpload> {
+    candidates
+        .into_iter()
+        .filter(|file| {
+            let Some(id) = &file.id else {
+                return false;
+            };
+            if attached.contains(&id.to_string()) {
+                return false;
+            }
+            let key = id.id.to_raw();
+            !referencing_texts
+                .iter()
+                .any(|text| text.contains(&key) || text.contains(&file.filename))
+        })
+        .collect()
+}
+
+/// 只能关联同一空间内的文件或自己上传的文件，避免通过文件 ID 引用其他空间的文件
+fn ensure_file_attachable(file: &FileUpload, document_space_id: &str, user_id: &str) -> Result<(), AppError> {
+    let raw_space_id = document_space_id.strip_prefix("space:").unwrap_or(document_space_id);
+    let same_space = file.space_id.as_ref().map_or(false, |space| space.id.to_raw() == raw_space_id);
+    if same_space || file.uploaded_by == user_id {
+        return Ok(());
     }
-}
\ No newline at end of file
+    Err(AppError::forbidden("Permission denied: file belongs to another space".to_string()))
+}
+
+/// 按关联顺序返回附件对应的文件，找不到（已删除）的文件跳过
+fn attached_files(attachments: &[DocumentAttachment], files: Vec<FileUpload>) -> Vec<FileResponse> {
+    let mut by_id: std::collections::HashMap<String, FileUpload> = files
+        .into_iter()
+        .filter_map(|file| Some((file.id.as_ref()?.to_string(), file)))
+        .collect();
+
+    attachments
+        .iter()
+        .filter_map(|attachment| by_id.remove(&attachment.file_id.to_string()))
+        .map(FileResponse::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uploaded(id: &str, name: &str) -> FileUpload {
+        let mut file = FileUpload::new(
+            format!("{}.png", id),
+            name.to_string(),
+            format!("./uploads/{}.png", id),
+            1024,
+            "image".to_string(),
+            "image/png".to_string(),
+            "user_1".to_string(),
+        );
+        file.id = Some(file_thing(id));
+        file
+    }
+
+    #[test]
+    fn test_attach_two_files_and_list_them() {
+        let document = document_thing("document:doc1");
+        let attachments = vec![
+            DocumentAttachment::new(document.clone(), file_thing("diagram"), "user_1".to_string()),
+            DocumentAttachment::new(document, file_thing("file_upload:screenshot"), "user_1".to_string()),
+        ];
+        // 查询结果的顺序与关联顺序无关，未关联的文件不会出现
+        let files = vec![uploaded("screenshot", "screen.png"), uploaded("unrelated", "other.png"), uploaded("diagram", "arch.png")];
+
+        let listed = attached_files(&attachments, files);
+
+        let names: Vec<&str> = listed.iter().map(|f| f.original_name.as_str()).collect();
+        assert_eq!(names, vec!["arch.png", "screen.png"]);
+        assert_eq!(listed[0].id, "file_upload:diagram");
+    }
+
+    #[test]
+    fn test_file_links_point_at_the_mounted_files_routes() {
+        let mut file = uploaded("diagram", "arch.png");
+        assert_eq!(FileResponse::from(file.clone()).thumbnail_url, None);
+
+        file.thumbnail_path = Some("./uploads/thumbnails/thumb_diagram.png".to_string());
+        let response = FileResponse::from(file);
+        assert_eq!(response.url, "/api/docs/files/file_upload:diagram/download");
+        assert_eq!(response.thumbnail_url.as_deref(), Some("/api/docs/files/file_upload:diagram/thumbnail"));
+    }
+
+    #[test]
+    fn test_deleted_attachment_files_are_skipped() {
+        let attachments = vec![DocumentAttachment::new(document_thing("doc1"), file_thing("gone"), "user_1".to_string())];
+        assert!(attached_files(&attachments, Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_only_same_space_or_own_files_can_be_attached() {
+        let mut file = uploaded("diagram", "arch.png");
+        file.space_id = Some(Thing::from(("space", "handbook")));
+
+        // 同空间的文件任何有写权限的成员都可以关联，空间 ID 带不带前缀都一样
+        assert!(ensure_file_attachable(&file, "space:handbook", "user_2").is_ok());
+        assert!(ensure_file_attachable(&file, "handbook", "user_2").is_ok());
+        // 上传者可以把自己的文件关联到其他空间的文档
+        assert!(ensure_file_attachable(&file, "space:other", "user_1").is_ok());
+
+        match ensure_file_attachable(&file, "space:other", "user_2") {
+            Err(AppError::Authorization(msg)) => assert_eq!(msg, "Permission denied: file belongs to another space"),
+            other => panic!("expected other users' files in other spaces to be refused, got {:?}", other),
+        }
+        file.space_id = None;
+        assert!(matches!(ensure_file_attachable(&file, "space:handbook", "user_2"), Err(AppError::Authorization(_))));
+    }
+
+    fn encode_png(width: u32, height: u32) -> Vec<u8> {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(width, height, image::Rgba([30, 120, 200, 255])));
+        let mut encoded = Cursor::new(Vec::new());
+        img.write_to(&mut encoded, ImageFormat::Png).unwrap();
+        encoded.into_inner()
+    }
+
+    #[test]
+    fn test_png_thumbnail_is_smaller_and_keeps_aspect_ratio() {
+        let thumbnail = make_thumbnail(&encode_png(400, 200), 100).unwrap().unwrap();
+
+        assert_eq!(thumbnail.extension, "png");
+        let decoded = image::load_from_memory_with_format(&thumbnail.data, ImageFormat::Png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (100, 50));
+    }
+
+    #[test]
+    fn test_small_images_are_not_upscaled() {
+        let thumbnail = make_thumbnail(&encode_png(40, 60), 100).unwrap().unwrap();
+
+        let decoded = image::load_from_memory(&thumbnail.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (40, 60));
+    }
+
+    #[test]
+    fn test_unsupported_images_are_skipped() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"></svg>"#;
+        assert!(make_thumbnail(svg, 100).unwrap().is_none());
+        assert!(make_thumbnail(b"plain text", 100).unwrap().is_none());
+        // 文件头是 PNG 但内容损坏时返回错误，由调用方记录警告
+        assert!(make_thumbnail(&encode_png(4, 4)[..16], 100).is_err());
+    }
+
+    fn upload_policy(max_file_size: u64, allowed: &[&str], storage_quota: u64) -> UploadPolicy {
+        UploadPolicy {
+            max_file_size,
+            allowed_mime_types: allowed.iter().map(|t| t.to_string()).collect(),
+            storage_quota,
+        }
+    }
+
+    #[test]
+    fn test_oversized_file_is_rejected() {
+        let policy = upload_policy(1024, &["image/png"], 0);
+
+        assert!(policy.check(1024, "image/png", None).is_ok());
+        assert!(matches!(policy.check(1025, "image/png", None), Err(AppError::Validation(_))));
+
+        // 空间设置只能把上限调小
+        let space = SpaceUploadSettings { max_file_size: Some(4096), ..Default::default() };
+        assert_eq!(policy.for_space(&space).max_file_size, 1024);
+        let space = SpaceUploadSettings { max_file_size: Some(512), ..Default::default() };
+        assert!(matches!(policy.for_space(&space).check(600, "image/png", Some(0)), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_disallowed_type_is_rejected() {
+        let policy = upload_policy(1024, &["image/png", "application/pdf"], 0);
+
+        assert!(policy.check(10, &normalize_mime_type("Application/PDF; name=a.pdf"), None).is_ok());
+        let err = policy.check(10, "application/x-msdownload", None).unwrap_err();
+        assert!(matches!(err, AppError::Validation(ref msg) if msg.contains("application/x-msdownload")));
+
+        // 空间白名单中超出全局范围的类型会被忽略
+        let space = SpaceUploadSettings {
+            allowed_mime_types: Some(vec!["application/pdf".to_string(), "application/x-msdownload".to_string()]),
+            ..Default::default()
+        };
+        let space_policy = policy.for_space(&space);
+        assert_eq!(space_policy.allowed_mime_types, vec!["application/pdf"]);
+        assert!(space_policy.check(10, "image/png", Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_upload_exceeding_space_quota_is_forbidden() {
+        let policy = upload_policy(1024, &["image/png"], 2048);
+
+        assert!(policy.check(1000, "image/png", Some(1048)).is_ok());
+        assert!(matches!(policy.check(1000, "image/png", Some(1049)), Err(AppError::Authorization(_))));
+        // 不属于任何空间的文件不计配额
+        assert!(policy.check(1000, "image/png", None).is_ok());
+
+        let space = SpaceUploadSettings { storage_quota: Some(1500), ..Default::default() };
+        assert_eq!(policy.for_space(&space).storage_quota, 1500);
+        let space = SpaceUploadSettings { storage_quota: Some(1 << 40), ..Default::default() };
+        assert_eq!(policy.for_space(&space).storage_quota, 2048);
+        // 全局不限制时使用空间自己的配额
+        assert_eq!(upload_policy(1024, &[], 0).for_space(&space).storage_quota, 1 << 40);
+    }
+
+    #[test]
+    fn test_only_unreferenced_files_are_orphans() {
+        let attached: HashSet<String> = [file_thing("attached").to_string()].into_iter().collect();
+        let referencing_texts = vec!["![cover](/api/docs/files/file_upload:published/download)".to_string()];
+        let candidates = vec![uploaded("attached", "a.png"), uploaded("orphan", "o.png"), uploaded("published", "p.png")];
+
+        let orphans = orphaned_files(candidates, &attached, &referencing_texts);
+
+        let names: Vec<&str> = orphans.iter().map(|f| f.original_name.as_str()).collect();
+        assert_eq!(names, vec!["o.png"]);
+    }
+
+    async fn upload_text(state: &crate::state::AppState, user_id: &str, name: &str) -> FileResponse {
+        let request = UploadFileRequest { space_id: None, document_id: None, description: None };
+        state.file_upload_service
+            .upload_file_from_bytes(user_id, axum::body::Bytes::from_static(b"notes"), name.to_string(), Some("text/plain".to_string()), request)
+            .await
+            .unwrap()
+    }
+
+    /// 把文件的上传时间调到两天前
+    async fn age_file(state: &crate::state::AppState, file_id: &str) {
+        state.db.client
+            .query("UPDATE $file SET created_at = time::now() - 2d")
+            .bind(("file", file_thing(file_id)))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_cleanup_removes_only_old_unreferenced_files() {
+        let state = crate::state::test_support::test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let user_id = format!("alice-{}", run);
+
+        let old_orphan = upload_text(&state, &user_id, "old-orphan.txt").await;
+        let old_linked = upload_text(&state, &user_id, "old-linked.txt").await;
+        let recent = upload_text(&state, &user_id, "recent.txt").await;
+        age_file(&state, &old_orphan.id).await;
+        age_file(&state, &old_linked.id).await;
+
+        // 文档正文中的下载链接算作引用
+        let owner = crate::services::auth::User {
+            id: user_id.clone(),
+            email: "alice@example.com".to_string(),
+            roles: vec!["user".to_string()],
+            permissions: vec![],
+            profile: None,
+            api_key: None,
+        };
+        let space = state.space_service.create_space(crate::models::space::CreateSpaceRequest {
+            name: "Docs".to_string(),
+            slug: format!("files-{}", run),
+            description: None,
+            avatar_url: None,
+            is_public: Some(true),
+            settings: None,
+        }, &owner).await.unwrap();
+        let document = serde_json::from_value(serde_json::json!({
+            "title": "Notes",
+            "slug": "notes",
+            "content": format!("[notes](/api/docs/files/{}/download)", old_linked.id),
+        })).unwrap();
+        state.document_service.create_document(&space.id, &user_id, document).await.unwrap();
+
+        let report = state.file_upload_service.cleanup_orphans(chrono::Duration::days(1)).await.unwrap();
+        assert!(report.files_removed >= 1);
+
+        let service = &state.file_upload_service;
+        assert!(matches!(service.get_file(&old_orphan.id).await, Err(AppError::NotFound(_))));
+        assert!(service.get_file(&old_linked.id).await.is_ok());
+        assert!(service.get_file(&recent.id).await.is_ok());
+    }
+}
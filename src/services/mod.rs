@@ -1,13 +1,22 @@
+pub mod activity;
+pub mod api_key;
 pub mod auth;
 pub mod comments;
 pub mod database;
 pub mod documents;
+pub mod document_permission;
 pub mod file_upload;
 pub mod search;
 pub mod spaces;
+pub mod space_export;
+pub mod stats;
 pub mod space_member;
 pub mod tags;
+pub mod templates;
 pub mod versions;
 pub mod notification;
+#[cfg(feature = "pdf-export")]
+pub mod pdf_export;
 pub mod publication;
-pub mod vector;
\ No newline at end of file
+pub mod vector;
+pub mod webhook;
\ No newline at end of file
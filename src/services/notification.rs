@@ -1,18 +1,21 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use chrono::Utc;
-use serde_json::json;
-use tracing::{info, error};
+use serde::Deserialize;
+use tracing::{debug, error, info, trace, warn};
 
 use crate::{
     error::{AppError, Result},
     models::{
         notification::{
             Notification, NotificationDb, CreateNotificationRequest,
-            UpdateNotificationRequest, NotificationListQuery, NotificationType,
+            UpdateNotificationRequest, NotificationListQuery, NotificationListResponse, NotificationType,
+            NotificationData, SpaceInvitationData, NotificationPreferences, UpdateNotificationPreferencesRequest,
         },
     },
     services::{database::Database, auth::{AuthService, User}},
     config::Config,
+    utils::{pagination::{PageRequest, Paginated}, redact},
 };
 
 pub struct NotificationService {
@@ -26,8 +29,17 @@ impl NotificationService {
         Self { db, auth_service, config }
     }
 
-    /// 创建通知
-    pub async fn create_notification(&self, request: CreateNotificationRequest) -> Result<Notification> {
+    /// 创建通知，目标用户关闭了该类型时跳过并返回 None
+    pub async fn create_notification(&self, request: CreateNotificationRequest) -> Result<Option<Notification>> {
+        if !notification_enabled(&self.db, &request.user_id, request.notification_type).await? {
+            debug!(
+                "User {} disabled {:?} notifications, skipping",
+                redact::user_id(&request.user_id),
+                request.notification_type
+            );
+            return Ok(None);
+        }
+
         let query = r#"
             CREATE notification SET
                 user_id = $user_id,
@@ -50,57 +62,52 @@ impl NotificationService {
             .await
             .map_err(|e| {
                 error!("Failed to create notification: {}", e);
-                AppError::Database(e)
+                AppError::from(e)
             })?;
 
         let created: Vec<NotificationDb> = result.take(0)
             .map_err(|e| {
                 error!("Failed to retrieve created notification: {}", e);
-                AppError::Database(e.into())
+                AppError::from(e)
             })?;
 
         let notification = created.into_iter().next()
             .ok_or_else(|| {
-                error!("No notification was created for user {}", request.user_id);
+                error!("No notification was created for user {}", redact::user_id(&request.user_id));
                 AppError::Internal(anyhow::anyhow!("Failed to create notification"))
             })?;
 
-        info!("Created notification for user {}: {}", request.user_id, request.title);
+        info!("Created notification for user {}: {}", redact::user_id(&request.user_id), request.title);
 
-        Ok(notification.into())
+        Ok(Some(notification.into()))
     }
 
-    /// 获取用户通知列表
-    pub async fn get_user_notifications(&self, user_id: &str, query_params: NotificationListQuery) -> Result<(Vec<Notification>, u64)> {
-        let page = query_params.page.unwrap_or(1).max(1);
-        let limit = query_params.limit.unwrap_or(20).min(100);
-        let offset = (page - 1) * limit;
+    /// 分页获取用户通知，可按已读状态和类型过滤
+    pub async fn list_notifications(&self, user_id: &str, query_params: NotificationListQuery) -> Result<NotificationListResponse> {
+        let page = PageRequest::new(query_params.page, query_params.limit);
 
-        // 构建查询条件
-        let mut where_clause = "WHERE user_id = $user_id".to_string();
-        if query_params.unread_only.unwrap_or(false) {
-            where_clause.push_str(" AND is_read = false");
-        }
+        let where_clause = notification_where_clause(&query_params);
 
-        // 查询通知
         let query = format!(
             "SELECT * FROM notification {} ORDER BY created_at DESC LIMIT {} START {}",
-            where_clause, limit, offset
+            where_clause, page.limit, page.offset()
         );
 
         let notifications: Vec<NotificationDb> = self.db.client
             .query(&query)
             .bind(("user_id", user_id))
+            .bind(("is_read", query_params.read_filter()))
+            .bind(("type", query_params.notification_type))
             .await
             .map_err(|e| {
                 error!("Failed to query notifications: {}", e);
-                AppError::Database(e)
+                AppError::from(e)
             })?
             .take(0)?;
 
         // 调试：记录查询到的通知
         for notification in &notifications {
-            info!("Retrieved notification - ID: {:?}, data: {:?}", 
+            trace!("Retrieved notification - ID: {:?}, data: {:?}", 
                   notification.id, notification.data);
         }
 
@@ -113,24 +120,38 @@ impl NotificationService {
         let total: Option<u64> = self.db.client
             .query(&count_query)
             .bind(("user_id", user_id))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .bind(("is_read", query_params.read_filter()))
+            .bind(("type", query_params.notification_type))
+            .await?
             .take("total")?;
 
-        Ok((
-            notifications.into_iter().map(Into::into).collect(),
-            total.unwrap_or(0),
-        ))
+        let total = u32::try_from(total.unwrap_or(0)).unwrap_or(u32::MAX);
+        Ok(Paginated::new(notifications, total, page).map(Notification::from).into())
     }
 
-    /// 标记通知为已读
-    pub async fn mark_as_read(&self, user_id: &str, notification_id: &str) -> Result<Notification> {
+    /// 标记通知为已读，重复标记不会改变首次的已读时间
+    pub async fn mark_read(&self, user_id: &str, notification_id: &str) -> Result<Notification> {
+        let existing: Vec<NotificationDb> = self.db.client
+            .query("SELECT * FROM type::thing('notification', $id) WHERE user_id = $user_id")
+            .bind(("id", notification_id))
+            .bind(("user_id", user_id))
+            .await?
+            .take(0)?;
+
+        let mut notification: Notification = existing.into_iter().next()
+            .ok_or_else(|| AppError::NotFound("Notification not found".to_string()))?
+            .into();
+
+        if !notification.mark_read(Utc::now()) {
+            return Ok(notification);
+        }
+
         let query = r#"
-            UPDATE notification SET
+            UPDATE type::thing('notification', $id) SET
                 is_read = true,
                 read_at = time::now(),
                 updated_at = time::now()
-            WHERE id = type::thing('notification', $id) AND user_id = $user_id
+            WHERE user_id = $user_id
         "#;
 
         let mut result = self.db.client
@@ -140,14 +161,14 @@ impl NotificationService {
             .await
             .map_err(|e| {
                 error!("Failed to mark notification as read: {}", e);
-                AppError::Database(e)
+                AppError::from(e)
             })?;
 
         let updated: Vec<NotificationDb> = result
             .take(0)
             .map_err(|e| {
                 error!("Failed to take updated notification: {}", e);
-                AppError::Database(e.into())
+                AppError::from(e)
             })?;
 
         let notification = updated.into_iter().next()
@@ -157,7 +178,7 @@ impl NotificationService {
     }
 
     /// 标记所有通知为已读
-    pub async fn mark_all_as_read(&self, user_id: &str) -> Result<u64> {
+    pub async fn mark_all_read(&self, user_id: &str) -> Result<u64> {
         let query = r#"
             UPDATE notification SET
                 is_read = true,
@@ -169,27 +190,67 @@ impl NotificationService {
         let result: Vec<NotificationDb> = self.db.client
             .query(query)
             .bind(("user_id", user_id))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
         Ok(result.len() as u64)
     }
 
     /// 获取未读通知数量
-    pub async fn get_unread_count(&self, user_id: &str) -> Result<u64> {
+    pub async fn unread_count(&self, user_id: &str) -> Result<u64> {
         let query = "SELECT count() as total FROM notification WHERE user_id = $user_id AND is_read = false GROUP ALL";
 
         let total: Option<u64> = self.db.client
             .query(query)
             .bind(("user_id", user_id))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take("total")?;
 
         Ok(total.unwrap_or(0))
     }
 
+    /// 获取用户的通知偏好，未设置过时全部开启
+    pub async fn get_preferences(&self, user_id: &str) -> Result<NotificationPreferences> {
+        let key = preference_key(user_id).to_string();
+        let mut preferences = load_notification_preferences(&self.db, std::slice::from_ref(&key)).await?;
+        Ok(preferences.remove(&key).unwrap_or_default())
+    }
+
+    /// 更新用户的通知偏好，未提供的类型保持原值
+    pub async fn update_preferences(
+        &self,
+        user_id: &str,
+        request: UpdateNotificationPreferencesRequest,
+    ) -> Result<NotificationPreferences> {
+        let mut preferences = self.get_preferences(user_id).await?;
+        preferences.apply(request);
+
+        let query = r#"
+            UPDATE type::thing('notification_preference', $user_id) SET
+                user_id = $user_id,
+                space_invitation = $space_invitation,
+                comment_mention = $comment_mention,
+                document_shared = $document_shared,
+                document_update = $document_update,
+                updated_at = time::now()
+        "#;
+
+        self.db.client
+            .query(query)
+            .bind(("user_id", preference_key(user_id)))
+            .bind(("space_invitation", preferences.space_invitation))
+            .bind(("comment_mention", preferences.comment_mention))
+            .bind(("document_shared", preferences.document_shared))
+            .bind(("document_update", preferences.document_update))
+            .await
+            .map_err(|e| {
+                error!("Failed to update notification preferences: {}", e);
+                AppError::from(e)
+            })?;
+
+        Ok(preferences)
+    }
+
     /// 删除通知
     pub async fn delete_notification(&self, user_id: &str, notification_id: &str) -> Result<()> {
         let query = "DELETE notification:$id WHERE user_id = $user_id";
@@ -198,8 +259,7 @@ impl NotificationService {
             .query(query)
             .bind(("id", notification_id))
             .bind(("user_id", user_id))
-            .await
-            .map_err(|e| AppError::Database(e))?;
+            .await?;
 
         Ok(())
     }
@@ -218,11 +278,11 @@ impl NotificationService {
     ) -> Result<()> {
         // 如果提供了用户ID，创建站内通知
         if let Some(user_id) = to_user_id {
-            let notification_data = json!({
-                "space_name": space_name,
-                "invite_token": invite_token,
-                "role": role,
-                "inviter_name": inviter_name,
+            let notification_data = NotificationData::SpaceInvitation(SpaceInvitationData {
+                space_name: space_name.to_string(),
+                invite_token: invite_token.to_string(),
+                role: role.to_string(),
+                inviter_name: inviter_name.to_string(),
             });
 
             self.create_notification(CreateNotificationRequest {
@@ -280,4 +340,152 @@ impl NotificationService {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize)]
+struct PreferenceRow {
+    user_id: String,
+    #[serde(flatten)]
+    preferences: NotificationPreferences,
+}
+
+/// 偏好记录按不带 "user:" 前缀的用户ID存储
+fn preference_key(user_id: &str) -> &str {
+    user_id.strip_prefix("user:").unwrap_or(user_id)
+}
+
+/// 批量读取用户的通知偏好，没有偏好记录的用户不会出现在结果中
+pub async fn load_notification_preferences(
+    db: &Database,
+    user_ids: &[String],
+) -> Result<HashMap<String, NotificationPreferences>> {
+    let keys: Vec<&str> = user_ids.iter().map(|id| preference_key(id)).collect();
+    let rows: Vec<serde_json::Value> = db.client
+        .query("SELECT * FROM notification_preference WHERE user_id INSIDE $user_ids")
+        .bind(("user_ids", keys))
+        .await?
+        .take(0)?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| match serde_json::from_value::<PreferenceRow>(row) {
+            Ok(row) => Some((row.user_id, row.preferences)),
+            Err(e) => {
+                warn!("Skipping malformed notification preference record: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// 用户是否接收某类通知，没有偏好记录时默认接收
+pub fn notification_allowed(
+    preferences: &HashMap<String, NotificationPreferences>,
+    user_id: &str,
+    notification_type: NotificationType,
+) -> bool {
+    preferences
+        .get(preference_key(user_id))
+        .is_none_or(|preferences| preferences.allows(notification_type))
+}
+
+/// 查询单个用户是否接收某类通知
+pub async fn notification_enabled(db: &Database, user_id: &str, notification_type: NotificationType) -> Result<bool> {
+    let preferences = load_notification_preferences(db, &[user_id.to_string()]).await?;
+    Ok(notification_allowed(&preferences, user_id, notification_type))
+}
+
+/// 根据过滤条件构造通知查询的 WHERE 子句，参数通过 $user_id、$is_read、$type 绑定
+fn notification_where_clause(query: &NotificationListQuery) -> String {
+    let mut where_clause = "WHERE user_id = $user_id".to_string();
+    if query.read_filter().is_some() {
+        where_clause.push_str(" AND is_read = $is_read");
+    }
+    if query.notification_type.is_some() {
+        where_clause.push_str(" AND type = $type");
+    }
+    where_clause
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_marking_one_of_three_read_leaves_two_unread() {
+        let db = crate::services::database::test_database().await;
+        let service = NotificationService::new(db.clone(), Arc::new(AuthService::new(db.config.clone())), db.config.clone());
+        let user_id = format!("user-{}", uuid::Uuid::new_v4().simple());
+
+        let mut created = Vec::new();
+        for title in ["a", "b", "c"] {
+            let notification = service
+                .create_notification(CreateNotificationRequest {
+                    user_id: user_id.clone(),
+                    notification_type: NotificationType::CommentMention,
+                    title: title.to_string(),
+                    content: String::new(),
+                    data: None,
+                })
+                .await
+                .unwrap()
+                .expect("notifications are enabled by default");
+            created.push(notification);
+        }
+        assert_eq!(service.unread_count(&user_id).await.unwrap(), 3);
+
+        let second_id = created[1].id.clone().unwrap();
+        let first_read = service.mark_read(&user_id, &second_id).await.unwrap();
+        assert!(first_read.is_read);
+        assert_eq!(service.unread_count(&user_id).await.unwrap(), 2);
+
+        // 重复标记不改变计数和首次已读时间
+        let second_read = service.mark_read(&user_id, &second_id).await.unwrap();
+        assert_eq!(second_read.read_at, first_read.read_at);
+        assert_eq!(service.unread_count(&user_id).await.unwrap(), 2);
+
+        // 其他用户不能标记别人的通知
+        assert!(matches!(service.mark_read("someone-else", &second_id).await, Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_where_clause_follows_filters() {
+        assert_eq!(notification_where_clause(&NotificationListQuery::default()), "WHERE user_id = $user_id");
+
+        let unread_mentions = NotificationListQuery {
+            is_read: Some(false),
+            notification_type: Some(NotificationType::CommentMention),
+            ..Default::default()
+        };
+        assert_eq!(
+            notification_where_clause(&unread_mentions),
+            "WHERE user_id = $user_id AND is_read = $is_read AND type = $type"
+        );
+
+        let legacy = NotificationListQuery { unread_only: Some(true), ..Default::default() };
+        assert_eq!(legacy.read_filter(), Some(false));
+        let explicit = NotificationListQuery { is_read: Some(true), unread_only: Some(true), ..Default::default() };
+        assert_eq!(explicit.read_filter(), Some(true));
+    }
+
+    #[test]
+    fn test_users_without_preferences_receive_everything() {
+        let preferences: HashMap<String, NotificationPreferences> = [(
+            "user_muted".to_string(),
+            NotificationPreferences { comment_mention: false, ..Default::default() },
+        )]
+        .into_iter()
+        .collect();
+
+        assert!(!notification_allowed(&preferences, "user:user_muted", NotificationType::CommentMention));
+        assert!(notification_allowed(&preferences, "user_muted", NotificationType::SpaceInvitation));
+        assert!(notification_allowed(&preferences, "user_other", NotificationType::CommentMention));
+    }
+
+    #[test]
+    fn test_list_envelope_counts_pages() {
+        let response = NotificationListResponse::new(Vec::new(), 45, 2, 20);
+        assert_eq!((response.total, response.page, response.limit, response.total_pages), (45, 2, 20, 3));
+    }
+}
@@ -0,0 +1,276 @@
+use std::process::Stdio;
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tracing::{error, info};
+
+use crate::{
+    error::AppError,
+    models::publication::{PublicationDocumentNode, SpacePublication},
+    services::publication::PublicationService,
+    utils::markdown::MarkdownProcessor,
+};
+
+/// PDF 中的一个章节（顶级文档及其所有子文档）
+#[derive(Debug, Clone)]
+pub struct PdfSection {
+    pub html: String,
+}
+
+/// PDF 封面信息
+#[derive(Debug, Clone)]
+pub struct PdfCover {
+    pub title: String,
+    pub description: Option<String>,
+    pub cover_image: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct PdfExportService {
+    markdown_processor: Arc<MarkdownProcessor>,
+    renderer_path: String,
+}
+
+impl PdfExportService {
+    pub fn new(markdown_processor: Arc<MarkdownProcessor>) -> Self {
+        Self {
+            markdown_processor,
+            renderer_path: std::env::var("WKHTMLTOPDF_PATH").unwrap_or_else(|_| "wkhtmltopdf".to_string()),
+        }
+    }
+
+    /// 将单个文档渲染为 PDF，allowed_tags 为空间额外允许的 HTML 标签
+    pub async fn render_document(
+        &self,
+        title: &str,
+        content: &str,
+        custom_css: Option<&str>,
+        allowed_tags: &[String],
+    ) -> Result<Vec<u8>, AppError> {
+        let processed = self.markdown_processor.process_with_allowed_tags(content, allowed_tags).await?;
+        let sections = vec![PdfSection { html: processed.html }];
+
+        let html = build_pdf_html(title, None, &sections, custom_css);
+        self.run_renderer(&html).await
+    }
+
+    /// 将整个发布渲染为 PDF，包含封面页，顶级文档之间分页
+    pub async fn render_publication(
+        &self,
+        publication: &SpacePublication,
+        publication_service: &PublicationService,
+        allowed_tags: &[String],
+    ) -> Result<Vec<u8>, AppError> {
+        let publication_id = publication.id.clone().unwrap_or_default();
+        let tree = publication_service.get_publication_tree(&publication_id).await?;
+
+        let mut sections = Vec::new();
+        for node in &tree {
+            let mut section_html = String::new();
+            self.append_node_html(node, &publication_id, publication_service, allowed_tags, 1, &mut section_html)
+                .await?;
+            sections.push(PdfSection { html: section_html });
+        }
+
+        let cover = PdfCover {
+            title: publication.title.clone(),
+            description: publication.description.clone(),
+            cover_image: publication.cover_image.clone(),
+        };
+
+        let html = build_pdf_html(
+            &publication.title,
+            Some(&cover),
+            &sections,
+            publication.custom_css.as_deref(),
+        );
+
+        info!("Rendering publication {} with {} top-level sections to PDF", publication_id, sections.len());
+        self.run_renderer(&html).await
+    }
+
+    /// 按树顺序递归拼接文档 HTML
+    fn append_node_html<'a>(
+        &'a self,
+        node: &'a PublicationDocumentNode,
+        publication_id: &'a str,
+        publication_service: &'a PublicationService,
+        allowed_tags: &'a [String],
+        depth: usize,
+        output: &'a mut String,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), AppError>> + Send + 'a>> {
+        Box::pin(async move {
+            let document = publication_service
+                .get_publication_document(publication_id, &node.slug)
+                .await?;
+            let processed = self.markdown_processor
+                .process_with_allowed_tags(&document.content, allowed_tags)
+                .await?;
+
+            let level = depth.min(6);
+            output.push_str(&format!(
+                "<h{level} class=\"doc-title\">{}</h{level}>\n{}\n",
+                escape_html(&document.title),
+                processed.html,
+                level = level
+            ));
+
+            for child in &node.children {
+                self.append_node_html(child, publication_id, publication_service, allowed_tags, depth + 1, output)
+                    .await?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// 调用 wkhtmltopdf 将 HTML 转换为 PDF（通过 stdin/stdout 传输）
+    async fn run_renderer(&self, html: &str) -> Result<Vec<u8>, AppError> {
+        let mut child = Command::new(&self.renderer_path)
+            .args(["--quiet", "--encoding", "utf-8", "-", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                error!("Failed to start PDF renderer {}: {}", self.renderer_path, e);
+                AppError::internal_server_error("PDF renderer is not available".to_string())
+            })?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(html.as_bytes())
+                .await
+                .map_err(|e| AppError::internal_server_error(format!("Failed to write to PDF renderer: {}", e)))?;
+        }
+
+        let mut pdf = Vec::new();
+        if let Some(mut stdout) = child.stdout.take() {
+            stdout
+                .read_to_end(&mut pdf)
+                .await
+                .map_err(|e| AppError::internal_server_error(format!("Failed to read PDF output: {}", e)))?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| AppError::internal_server_error(format!("PDF renderer failed: {}", e)))?;
+
+        if !output.status.success() || pdf.is_empty() {
+            error!(
+                "PDF renderer exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            return Err(AppError::internal_server_error("Failed to render PDF".to_string()));
+        }
+
+        Ok(pdf)
+    }
+}
+
+/// 组装完整的 HTML 文档：可选封面页，章节之间插入分页
+pub fn build_pdf_html(
+    title: &str,
+    cover: Option<&PdfCover>,
+    sections: &[PdfSection],
+    custom_css: Option<&str>,
+) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    html.push_str("<style>\n");
+    html.push_str(".page-break { page-break-after: always; }\n");
+    html.push_str(".cover { text-align: center; padding-top: 30%; }\n");
+    html.push_str(".cover img { max-width: 80%; margin-top: 2em; }\n");
+    if let Some(css) = custom_css {
+        html.push_str(&sanitize_css(css));
+        html.push('\n');
+    }
+    html.push_str("</style>\n</head>\n<body>\n");
+
+    if let Some(cover) = cover {
+        html.push_str("<section class=\"cover\">\n");
+        html.push_str(&format!("<h1>{}</h1>\n", escape_html(&cover.title)));
+        if let Some(description) = &cover.description {
+            html.push_str(&format!("<p>{}</p>\n", escape_html(description)));
+        }
+        if let Some(image) = &cover.cover_image {
+            html.push_str(&format!("<img src=\"{}\" alt=\"cover\">\n", escape_html(image)));
+        }
+        html.push_str("</section>\n");
+        if !sections.is_empty() {
+            html.push_str("<div class=\"page-break\"></div>\n");
+        }
+    }
+
+    for (index, section) in sections.iter().enumerate() {
+        if index > 0 {
+            html.push_str("<div class=\"page-break\"></div>\n");
+        }
+        html.push_str("<section class=\"document\">\n");
+        html.push_str(&section.html);
+        html.push_str("\n</section>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// 自定义 CSS 原样放在 <style> 中，HTML 实体在这里不会被解码，
+/// 因此把 < 替换为 CSS 转义 \3c，防止 </style> 提前闭合样式块并注入标签
+fn sanitize_css(css: &str) -> String {
+    css.replace('<', "\\3c ")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(text: &str) -> PdfSection {
+        PdfSection {
+            html: format!("<p>{}</p>", text),
+        }
+    }
+
+    #[test]
+    fn test_page_breaks_between_top_level_sections() {
+        let html = build_pdf_html("Book", None, &[section("a"), section("b"), section("c")], None);
+        assert_eq!(html.matches("class=\"page-break\"").count(), 2);
+    }
+
+    #[test]
+    fn test_cover_page_and_custom_css() {
+        let cover = PdfCover {
+            title: "My <Book>".to_string(),
+            description: None,
+            cover_image: Some("/cover.png".to_string()),
+        };
+        let html = build_pdf_html("My Book", Some(&cover), &[section("a")], Some("body { color: red; }"));
+
+        assert!(html.contains("<h1>My &lt;Book&gt;</h1>"));
+        assert!(html.contains("<img src=\"/cover.png\""));
+        assert!(html.contains("body { color: red; }"));
+        // 封面页之后分页
+        assert_eq!(html.matches("class=\"page-break\"").count(), 1);
+    }
+
+    #[test]
+    fn test_custom_css_cannot_close_style_block() {
+        let css = "body { color: red; }</style><script>alert(1)</script><style>";
+        let html = build_pdf_html("Book", None, &[section("a")], Some(css));
+
+        assert_eq!(html.matches("</style>").count(), 1);
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("body { color: red; }"));
+    }
+}
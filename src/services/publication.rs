@@ -1,23 +1,156 @@
 use crate::{
-    services::database::Database,
-    error::{ApiError, Result},
+    services::{database::Database, webhook::WebhookService},
+    error::{AppError, Result},
     models::{
         publication::*,
         document::{Document, DocumentTreeNode},
+        webhook::WebhookEvent,
+    },
+    utils::{
+        feed::{self, FeedChannel, FeedEntry, FeedFormat},
+        sitemap::{self, SitemapUrl},
+        rate_limit::SlidingWindowLimiter,
+        slug::slug_from_title,
+        ttl_cache::{CacheStats, TtlCache},
     },
 };
+use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
 use surrealdb::sql::Thing;
-use chrono::Utc;
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc};
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
-use tracing::{info, warn, error};
+use tokio::sync::Mutex;
+use tracing::{debug, error, info, trace, warn};
+use uuid::Uuid;
+
+/// 公开访问的文档树缓存默认保留时间
+const DEFAULT_TREE_CACHE_TTL_SECS: i64 = 300;
+/// 自动生成的分享 slug 中取自标题的部分最长字符数
+const SHARE_SLUG_TITLE_CHARS: usize = 60;
+/// 读取受密码保护的发布时携带访问令牌的请求头
+pub const ACCESS_TOKEN_HEADER: &str = "X-Publication-Token";
+/// 访问令牌有效期
+const ACCESS_TOKEN_TTL_MINUTES: i64 = 30;
+/// 同一客户端对同一发布在窗口内最多尝试的访问密码次数，防止暴力破解和占满 CPU
+const MAX_ACCESS_ATTEMPTS: u32 = 10;
+const ACCESS_ATTEMPT_WINDOW_MINUTES: i64 = 15;
+/// 订阅源最多列出的文档数
+const FEED_ITEM_LIMIT: usize = 20;
+/// 按天累计浏览量。绑定的时间经 serde 序列化后是字符串，写入 datetime 字段前必须转换
+const DAILY_VIEW_UPSERT_QUERY: &str = "UPDATE type::thing('publication_daily_view', [$publication_id, $day]) SET
+    publication_id = $publication_id,
+    day = <datetime> $day,
+    views = (views ?? 0) + 1";
+
+/// 创建发布。过期时间在同一条语句中转换为 datetime 写入，不会出现发布已公开而过期时间没写上的情况
+const PUBLICATION_CREATE_QUERY: &str = "CREATE space_publication SET
+    space_id = $publication.space_id,
+    slug = $publication.slug,
+    version = $publication.version,
+    title = $publication.title,
+    description = $publication.description,
+    cover_image = $publication.cover_image,
+    theme = $publication.theme,
+    include_private_docs = $publication.include_private_docs,
+    enable_search = $publication.enable_search,
+    enable_comments = $publication.enable_comments,
+    custom_css = $publication.custom_css,
+    custom_js = $publication.custom_js,
+    seo_title = $publication.seo_title,
+    seo_description = $publication.seo_description,
+    seo_keywords = $publication.seo_keywords,
+    access_password_hash = $publication.access_password_hash,
+    expires_at = IF $publication.expires_at THEN <datetime> $publication.expires_at ELSE NONE END,
+    is_active = $publication.is_active,
+    is_deleted = $publication.is_deleted,
+    published_by = $publication.published_by";
+
+/// 更新发布设置，过期时间与其他字段一起写入；为 NONE 时清除过期时间
+const PUBLICATION_UPDATE_QUERY: &str = "UPDATE $id SET
+    title = $title,
+    description = $description,
+    cover_image = $cover_image,
+    theme = $theme,
+    enable_search = $enable_search,
+    enable_comments = $enable_comments,
+    custom_css = $custom_css,
+    custom_js = $custom_js,
+    seo_title = $seo_title,
+    seo_description = $seo_description,
+    seo_keywords = $seo_keywords,
+    access_password_hash = $access_password_hash,
+    expires_at = IF $expires_at THEN <datetime> $expires_at ELSE NONE END,
+    updated_at = time::now()";
+
+/// 创建单文档分享。过期时间在同一条语句中转换为 datetime 写入，不会出现分享已创建而过期时间没写上的情况
+const SHARE_CREATE_QUERY: &str = "CREATE document_share SET
+    document_id = $share.document_id,
+    space_id = $share.space_id,
+    slug = $share.slug,
+    theme = $share.theme,
+    title = $share.title,
+    content = $share.content,
+    excerpt = $share.excerpt,
+    word_count = $share.word_count,
+    reading_time = $share.reading_time,
+    shared_by = $share.shared_by,
+    expires_at = IF $share.expires_at THEN <datetime> $share.expires_at ELSE NONE END";
+
+/// 按 (发布ID, 版本号) 缓存的文档树
+type TreeCache = TtlCache<(String, u32), Arc<Vec<PublicationDocumentNode>>>;
 
 pub struct PublicationService {
     db: Arc<Database>,
+    tree_cache: TreeCache,
+    webhook_service: Option<Arc<WebhookService>>,
+    access_tokens: Option<AccessTokenSigner>,
+    /// 按 (发布 slug, 客户端 IP) 限制访问密码的尝试次数
+    access_limiter: Arc<Mutex<SlidingWindowLimiter>>,
 }
 
 impl PublicationService {
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        Self {
+            db,
+            tree_cache: TreeCache::new(Duration::seconds(DEFAULT_TREE_CACHE_TTL_SECS)),
+            webhook_service: None,
+            access_tokens: None,
+            access_limiter: Arc::new(Mutex::new(SlidingWindowLimiter::new(
+                MAX_ACCESS_ATTEMPTS,
+                Duration::minutes(ACCESS_ATTEMPT_WINDOW_MINUTES),
+            ))),
+        }
+    }
+
+    /// 用于签发受密码保护发布的访问令牌，未设置时无法通过密码访问
+    pub fn with_access_token_secret(mut self, secret: &str) -> Self {
+        self.access_tokens = Some(AccessTokenSigner::new(secret, Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)));
+        self
+    }
+
+    /// 公开文档树的缓存时间，为 0 时不缓存
+    pub fn with_tree_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.tree_cache = TreeCache::new(ttl);
+        self
+    }
+
+    pub fn with_webhook_service(mut self, webhook_service: Arc<WebhookService>) -> Self {
+        self.webhook_service = Some(webhook_service);
+        self
+    }
+
+    /// 首次发布和重新发布都推送 publication.published 事件，
+    /// 只修改发布设置（如 SEO 信息）的 update_publication 不推送
+    fn notify_published(&self, response: &PublicationResponse) {
+        if let Some(webhook_service) = &self.webhook_service {
+            webhook_service.dispatch(
+                &response.space_id,
+                WebhookEvent::PublicationPublished,
+                serde_json::json!({ "publication": response }),
+            );
+        }
     }
 
     /// 创建新的发布
@@ -29,19 +162,15 @@ impl PublicationService {
     ) -> Result<PublicationResponse> {
         // 验证请求
         request.validate()
-            .map_err(|e| ApiError::Validation(e.to_string()))?;
-
-        // 检查slug是否已被使用
-        if self.slug_exists(&request.slug).await? {
-            return Err(ApiError::Conflict(format!("Slug '{}' already exists", request.slug)));
-        }
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+        ensure_future_expiry(request.expires_at, Utc::now())?;
 
         // 获取最新版本号
         let latest_version = self.get_latest_version(space_id).await?;
         let new_version = (latest_version + 1) as u32;
 
         // 创建发布记录
-        let publication = SpacePublication {
+        let publication = SpacePublicationDb {
             id: None,
             space_id: space_id.to_string(),
             slug: request.slug,
@@ -58,6 +187,9 @@ impl PublicationService {
             seo_title: request.seo_title,
             seo_description: request.seo_description,
             seo_keywords: request.seo_keywords.unwrap_or_default(),
+            custom_domain: None,
+            access_password_hash: request.access_password.as_deref().map(hash_access_password).transpose()?,
+            expires_at: request.expires_at,
             is_active: true,
             is_deleted: false,
             published_by: publisher_id.to_string(),
@@ -67,32 +199,41 @@ impl PublicationService {
         };
 
         // 保存到数据库
-        let created: Vec<SpacePublication> = self.db.client
-            .create("space_publication")
-            .content(publication)
+        // slug 唯一性由唯一索引保证：直接创建并捕获冲突，避免先查后写的竞态
+        // 按 Thing 类型反序列化创建结果，直接得到规范的记录ID
+        let created: Vec<SpacePublicationDb> = self.db.client
+            .query(PUBLICATION_CREATE_QUERY)
+            .bind(("publication", &publication))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
-
-        let created_publication = created.into_iter()
-            .next()
-            .ok_or_else(|| ApiError::InternalServerError("Failed to create publication".to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::from_unique_violation(
+                e,
+                "idx_space_publication_slug",
+                format!("Slug '{}' already exists", publication.slug),
+            ))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
+        let created_publication = created_publication(created)?;
         let publication_id = created_publication.id.as_ref()
-            .ok_or_else(|| ApiError::InternalServerError("Publication ID is missing".to_string()))?;
+            .ok_or_else(|| AppError::internal_server_error("Publication ID is missing".to_string()))?;
 
         // 创建文档快照
-        let document_count = self.create_document_snapshots(
+        let snapshots = self.create_document_snapshots(
             publication_id,
             space_id,
             created_publication.include_private_docs,
         ).await?;
+        let document_count = snapshots.len() as u32;
 
-        // 创建发布历史记录
+        // 创建发布历史记录（首次发布时所有文档都是新增）
         self.create_publication_history(
             publication_id,
             new_version as i32,
             publisher_id,
             "Initial publication",
+            compute_changed_documents(&[], &snapshots),
         ).await?;
 
         // 初始化访问统计
@@ -102,7 +243,9 @@ impl PublicationService {
             created_publication.slug, space_id, document_count);
 
         // 构建响应
-        Ok(self.build_publication_response(created_publication, document_count, 0).await?)
+        let response = self.build_publication_response(created_publication, document_count, 0).await?;
+        self.notify_published(&response);
+        Ok(response)
     }
 
     /// 更新现有发布
@@ -114,13 +257,14 @@ impl PublicationService {
     ) -> Result<PublicationResponse> {
         // 验证请求
         request.validate()
-            .map_err(|e| ApiError::Validation(e.to_string()))?;
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+        ensure_future_expiry(request.expires_at.flatten(), Utc::now())?;
 
         // 获取现有发布
         let mut publication = self.get_publication_by_id(publication_id).await?;
 
         if !publication.can_update() {
-            return Err(ApiError::BadRequest("Publication cannot be updated".to_string()));
+            return Err(AppError::Validation("Publication cannot be updated".to_string()));
         }
 
         // 更新字段
@@ -157,24 +301,20 @@ impl PublicationService {
         if let Some(keywords) = request.seo_keywords {
             publication.seo_keywords = keywords;
         }
+        if let Some(password) = request.access_password {
+            publication.access_password_hash = match password.as_str() {
+                "" => None,
+                password => Some(hash_access_password(password)?),
+            };
+        }
 
-        // 更新数据库
-        let query = "UPDATE $id SET 
-            title = $title,
-            description = $description,
-            cover_image = $cover_image,
-            theme = $theme,
-            enable_search = $enable_search,
-            enable_comments = $enable_comments,
-            custom_css = $custom_css,
-            custom_js = $custom_js,
-            seo_title = $seo_title,
-            seo_description = $seo_description,
-            seo_keywords = $seo_keywords,
-            updated_at = time::now()";
+        if let Some(expires_at) = request.expires_at {
+            publication.expires_at = expires_at;
+        }
 
+        // 更新数据库
         self.db.client
-            .query(query)
+            .query(PUBLICATION_UPDATE_QUERY)
             .bind(("id", self.get_publication_thing(publication_id)))
             .bind(("title", &publication.title))
             .bind(("description", &publication.description))
@@ -187,8 +327,13 @@ impl PublicationService {
             .bind(("seo_title", &publication.seo_title))
             .bind(("seo_description", &publication.seo_description))
             .bind(("seo_keywords", &publication.seo_keywords))
+            .bind(("access_password_hash", &publication.access_password_hash))
+            .bind(("expires_at", publication.expires_at))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        self.invalidate_tree_cache(publication_id).await;
 
         // 获取文档数量和访问统计
         let document_count = self.get_document_count(publication_id).await?;
@@ -208,7 +353,7 @@ impl PublicationService {
         let mut publication = self.get_publication_by_id(publication_id).await?;
 
         if !publication.can_update() {
-            return Err(ApiError::BadRequest("Publication cannot be republished".to_string()));
+            return Err(AppError::Validation("Publication cannot be republished".to_string()));
         }
 
         // 增加版本号
@@ -221,17 +366,22 @@ impl PublicationService {
             .bind(("id", self.get_publication_thing(publication_id)))
             .bind(("version", publication.version))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        // 记录上一版本的快照摘要，用于计算变更
+        let previous_snapshots = self.get_snapshot_digests(publication_id).await?;
 
         // 删除旧的文档快照
         self.delete_document_snapshots(publication_id).await?;
 
         // 创建新的文档快照
-        let document_count = self.create_document_snapshots(
+        let snapshots = self.create_document_snapshots(
             publication_id,
             &publication.space_id,
             publication.include_private_docs,
         ).await?;
+        let document_count = snapshots.len() as u32;
+        self.invalidate_tree_cache(publication_id).await;
 
         // 创建发布历史记录
         self.create_publication_history(
@@ -239,6 +389,7 @@ impl PublicationService {
             publication.version as i32,
             publisher_id,
             &change_summary.unwrap_or_else(|| "Content update".to_string()),
+            compute_changed_documents(&previous_snapshots, &snapshots),
         ).await?;
 
         info!("Republished {} (v{}) with {} documents", 
@@ -247,7 +398,9 @@ impl PublicationService {
         // 获取访问统计
         let analytics = self.get_analytics(publication_id).await?;
 
-        Ok(self.build_publication_response(publication, document_count, analytics.total_views).await?)
+        let response = self.build_publication_response(publication, document_count, analytics.total_views).await?;
+        self.notify_published(&response);
+        Ok(response)
     }
 
     /// 取消发布
@@ -258,7 +411,8 @@ impl PublicationService {
             .query(query)
             .bind(("id", self.get_publication_thing(publication_id)))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        self.invalidate_tree_cache(publication_id).await;
 
         info!("Unpublished publication {}", publication_id);
         Ok(())
@@ -266,15 +420,17 @@ impl PublicationService {
 
     /// 删除发布
     pub async fn delete_publication(&self, publication_id: &str) -> Result<()> {
-        info!("Deleting publication: {}", publication_id);
+        debug!("Deleting publication: {}", publication_id);
         
-        let query = "UPDATE $id SET is_deleted = true, deleted_at = time::now()";
+        // 唯一索引同样覆盖已删除的发布，删除时释放其自定义域名
+        let query = "UPDATE $id SET is_deleted = true, deleted_at = time::now(), custom_domain = NONE";
         
         self.db.client
             .query(query)
             .bind(("id", self.get_publication_thing(publication_id)))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        self.invalidate_tree_cache(publication_id).await;
 
         info!("Deleted publication {}", publication_id);
         Ok(())
@@ -286,29 +442,27 @@ impl PublicationService {
         space_id: &str,
         include_inactive: bool,
     ) -> Result<Vec<PublicationResponse>> {
-        let query = if include_inactive {
-            "SELECT * FROM space_publication 
+        let query = "SELECT * FROM space_publication 
             WHERE space_id = $space_id AND is_deleted = false
-            ORDER BY version DESC"
-        } else {
-            "SELECT * FROM space_publication 
-            WHERE space_id = $space_id AND is_deleted = false AND is_active = true
-            ORDER BY version DESC"
-        };
+            ORDER BY version DESC";
 
         let mut result = self.db.client
             .query(query)
             .bind(("space_id", space_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let publications_db: Vec<SpacePublicationDb> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
+        let now = Utc::now();
         let mut responses = Vec::new();
         for pub_item_db in publications_db {
             let pub_item: SpacePublication = pub_item_db.into();
+            if !listed_in_management(&pub_item, include_inactive, now) {
+                continue;
+            }
             if let Some(pub_id) = &pub_item.id {
                 let document_count = self.get_document_count(pub_id).await?;
                 let analytics = self.get_analytics(pub_id).await?;
@@ -324,31 +478,31 @@ impl PublicationService {
         &self,
         publication_id: &str,
     ) -> Result<Vec<PublicationDocumentNode>> {
-        info!("Getting publication tree for publication_id: {}", publication_id);
+        debug!("Getting publication tree for publication_id: {}", publication_id);
         
         let query = "SELECT * FROM publication_document 
             WHERE publication_id = $publication_id 
             ORDER BY order_index ASC";
 
         let formatted_id = self.format_publication_id(publication_id);
-        info!("Using formatted_id for query: {}", formatted_id);
+        debug!("Using formatted_id for query: {}", formatted_id);
 
         let mut result = self.db.client
             .query(query)
             .bind(("publication_id", formatted_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let documents_db: Vec<PublicationDocumentDb> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
             
         let documents: Vec<PublicationDocument> = documents_db.into_iter().map(|db| db.into()).collect();
-        info!("Found {} documents in publication_document table", documents.len());
+        debug!("Found {} documents in publication_document table", documents.len());
         
         // 打印每个文档的详细信息
         for doc in &documents {
-            info!("Published doc: title={}, id={:?}, parent_id={:?}, original_doc_id={}", 
+            trace!("Published doc: title={}, id={:?}, parent_id={:?}, original_doc_id={}", 
                 doc.title, doc.id, doc.parent_id, doc.original_doc_id);
         }
 
@@ -356,6 +510,40 @@ impl PublicationService {
         self.build_document_tree(documents)
     }
 
+    /// 公开访问的文档树。发布内容只在重新发布时变化，按发布ID和版本号缓存，
+    /// 同一发布并发的首次请求只查询一次数据库
+    pub async fn get_cached_publication_tree(
+        &self,
+        publication: &SpacePublication,
+    ) -> Result<Arc<Vec<PublicationDocumentNode>>> {
+        let publication_id = publication.id.as_deref()
+            .ok_or_else(|| AppError::internal_server_error("Publication ID is missing".to_string()))?;
+        let key = (self.format_publication_id(publication_id), publication.version);
+
+        self.tree_cache
+            .get_or_load(key, || async { self.get_publication_tree(publication_id).await.map(Arc::new) })
+            .await
+    }
+
+    pub async fn tree_cache_stats(&self) -> CacheStats {
+        self.tree_cache.stats().await
+    }
+
+    /// 清理过期的文档树缓存
+    pub async fn cleanup_tree_cache(&self) {
+        self.tree_cache.cleanup().await;
+    }
+
+    /// 清理访问密码限流器中窗口外的记录
+    pub async fn cleanup_rate_limits(&self) {
+        self.access_limiter.lock().await.cleanup(Utc::now());
+    }
+
+    async fn invalidate_tree_cache(&self, publication_id: &str) {
+        let publication_id = self.format_publication_id(publication_id);
+        self.tree_cache.invalidate_matching(|(id, _)| *id == publication_id).await;
+    }
+
     /// 获取发布的单个文档
     pub async fn get_publication_document(
         &self,
@@ -370,16 +558,213 @@ impl PublicationService {
             .bind(("publication_id", self.format_publication_id(publication_id)))
             .bind(("slug", doc_slug))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let documents_db: Vec<PublicationDocumentDb> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         documents_db.into_iter()
             .map(|db| db.into())
             .next()
-            .ok_or_else(|| ApiError::NotFound("Document not found".to_string()))
+            .ok_or_else(|| AppError::NotFound("Document not found".to_string()))
+    }
+
+    /// 校验发布的访问密码，通过后签发短期访问令牌
+    pub async fn verify_publication_password(
+        &self,
+        slug: &str,
+        password: &str,
+        client_ip: Option<&str>,
+    ) -> Result<PublicationAccessToken> {
+        let limiter_key = format!("{}:{}", slug, client_ip.unwrap_or("unknown"));
+        if !self.access_limiter.lock().await.try_acquire(&limiter_key, Utc::now()) {
+            return Err(AppError::TooManyRequests(format!(
+                "Too many password attempts, at most {} every {} minutes",
+                MAX_ACCESS_ATTEMPTS, ACCESS_ATTEMPT_WINDOW_MINUTES
+            )));
+        }
+
+        let publication = self.get_publication_by_slug(slug).await?;
+        let Some(password_hash) = publication.access_password_hash.clone() else {
+            return Err(AppError::Validation("Publication is not password protected".to_string()));
+        };
+        // Argon2 校验是 CPU 和内存密集操作，放到阻塞线程池中执行，避免占用异步运行时
+        let password = password.to_string();
+        let verified = tokio::task::spawn_blocking(move || verify_access_password(&password, &password_hash))
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Password verification task failed: {}", e)))?;
+        if !verified {
+            return Err(AppError::Authentication("Incorrect publication password".to_string()));
+        }
+
+        self.access_token_signer()?.issue(&publication, Utc::now())
+    }
+
+    /// 受密码保护的发布需要有效的访问令牌，未设置密码的发布直接放行
+    pub fn authorize_publication_access(&self, publication: &SpacePublication, token: Option<&str>) -> Result<()> {
+        if !publication.is_password_protected() {
+            return Ok(());
+        }
+        let token = token.ok_or_else(|| AppError::Authentication("Publication password required".to_string()))?;
+
+        self.access_token_signer()?.verify(token, publication, Utc::now())
+    }
+
+    fn access_token_signer(&self) -> Result<&AccessTokenSigner> {
+        self.access_tokens.as_ref()
+            .ok_or_else(|| AppError::internal_server_error("Publication access tokens are not configured".to_string()))
+    }
+
+    /// 分享单个文档：保存文档当前内容的快照，通过独立的 slug 公开访问
+    pub async fn publish_document(
+        &self,
+        document_id: &str,
+        sharer_id: &str,
+        request: ShareDocumentRequest,
+    ) -> Result<SharedDocumentResponse> {
+        request.validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+
+        let clean_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+        let documents_db: Vec<crate::models::document::DocumentDb> = self.db.client
+            .query("SELECT * FROM $id WHERE is_deleted = false")
+            .bind(("id", Thing::from(("document", clean_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let document: Document = documents_db.into_iter()
+            .next()
+            .map(|db| db.into())
+            .ok_or_else(|| AppError::NotFound("Document not found".to_string()))?;
+
+        let share = build_document_share(&document, sharer_id, request, Utc::now())?;
+
+        let created: Vec<SharedDocumentDb> = self.db.client
+            .query(SHARE_CREATE_QUERY)
+            .bind(("share", &share))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::from_unique_violation(
+                e,
+                "idx_document_share_slug",
+                format!("Slug '{}' already exists", share.slug),
+            ))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let share: SharedDocument = created.into_iter()
+            .next()
+            .map(|db| db.into())
+            .ok_or_else(|| AppError::internal_server_error("Failed to share document".to_string()))?;
+
+        info!("Shared document {} as {}", share.document_id, share.slug);
+
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://129.226.169.63:4173".to_string());
+        let public_url = share.get_public_url(&frontend_url);
+        Ok(SharedDocumentResponse { share, public_url })
+    }
+
+    /// 通过分享 slug 获取文档快照，已过期的分享视为不存在
+    pub async fn get_shared_document(&self, slug: &str) -> Result<SharedDocumentView> {
+        let shares_db: Vec<SharedDocumentDb> = self.db.client
+            .query("SELECT * FROM document_share WHERE slug = $slug")
+            .bind(("slug", slug))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let share = shares_db.into_iter()
+            .map(SharedDocument::from)
+            .next()
+            .ok_or_else(|| AppError::NotFound("Shared document not found".to_string()))?;
+
+        resolve_shared_document(share, Utc::now())
+    }
+
+    /// 生成发布的订阅源，列出最近新增或修改的文档
+    pub async fn generate_feed(&self, publication_id: &str, format: FeedFormat) -> Result<String> {
+        let publication = self.get_publication_by_id(publication_id).await?;
+        let formatted_id = self.format_publication_id(publication_id);
+
+        let documents_db: Vec<PublicationDocumentDb> = self.db.client
+            .query("SELECT * FROM publication_document WHERE publication_id = $publication_id")
+            .bind(("publication_id", &formatted_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let history_db: Vec<PublicationHistoryDb> = self.db.client
+            .query("SELECT * FROM publication_history WHERE publication_id = $publication_id")
+            .bind(("publication_id", &formatted_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        let history: Vec<PublicationHistory> = history_db.into_iter().map(|db| db.into()).collect();
+
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://129.226.169.63:4173".to_string());
+        let site_url = publication.get_public_url(&frontend_url);
+        let updated_at = publication.updated_at.or(publication.published_at).unwrap_or_else(Utc::now);
+
+        let entries = recent_feed_entries(
+            documents_db.into_iter().map(|db| db.into()).collect(),
+            &history,
+            &site_url,
+            updated_at,
+            FEED_ITEM_LIMIT,
+        );
+        let channel = FeedChannel {
+            title: &publication.title,
+            description: publication.description.as_deref(),
+            link: &site_url,
+            updated_at,
+        };
+
+        Ok(feed::render(format, &channel, &entries))
+    }
+
+    /// 生成发布的 sitemap.xml，列出所有公开文档的链接
+    pub async fn generate_sitemap(&self, publication_id: &str) -> Result<String> {
+        let publication = self.get_publication_by_id(publication_id).await?;
+
+        let documents_db: Vec<PublicationDocumentDb> = self.db.client
+            .query("SELECT * FROM publication_document WHERE publication_id = $publication_id
+                ORDER BY order_index ASC")
+            .bind(("publication_id", self.format_publication_id(publication_id)))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        let documents: Vec<PublicationDocument> = documents_db.into_iter().map(|db| db.into()).collect();
+
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://129.226.169.63:4173".to_string());
+        let site_url = publication.get_public_url(&frontend_url);
+        let fallback = publication.updated_at.or(publication.published_at).unwrap_or_else(Utc::now);
+
+        let urls = sitemap_urls(&documents, publication.include_private_docs, &site_url, fallback);
+        Ok(sitemap::render(&urls))
+    }
+
+    /// 生成发布文档的 JSON-LD 结构化数据，供前端内联到页面中
+    pub async fn get_document_json_ld(
+        &self,
+        publication: &SpacePublication,
+        doc_slug: &str,
+    ) -> Result<(PublicationDocument, serde_json::Value)> {
+        let publication_id = publication.id.as_deref()
+            .ok_or_else(|| AppError::internal_server_error("Publication ID is missing".to_string()))?;
+        let document = self.get_publication_document(publication_id, doc_slug).await?;
+
+        let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://129.226.169.63:4173".to_string());
+        let json_ld = document_json_ld(publication, &document, &publication.get_public_url(&frontend_url));
+
+        Ok((document, json_ld))
     }
 
     // ===== 私有辅助方法 =====
@@ -399,31 +784,6 @@ impl PublicationService {
         Thing::from(("space_publication", clean_id))
     }
 
-    /// 检查slug是否已存在
-    async fn slug_exists(&self, slug: &str) -> Result<bool> {
-        let query = "SELECT count() as total FROM space_publication 
-            WHERE slug = $slug AND is_active = true AND is_deleted = false
-            GROUP ALL";
-
-        let mut result = self.db.client
-            .query(query)
-            .bind(("slug", slug))
-            .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
-
-        let records: Vec<serde_json::Value> = result
-            .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
-
-        let count = records
-            .first()
-            .and_then(|v| v.get("total"))
-            .and_then(|v| v.as_i64())
-            .unwrap_or(0);
-
-        Ok(count > 0)
-    }
-
     /// 获取空间的最新版本号
     async fn get_latest_version(&self, space_id: &str) -> Result<i32> {
         let query = "SELECT version FROM space_publication 
@@ -434,11 +794,11 @@ impl PublicationService {
             .query(query)
             .bind(("space_id", space_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let versions: Vec<serde_json::Value> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(versions
             .first()
@@ -447,29 +807,29 @@ impl PublicationService {
             .unwrap_or(0) as i32)
     }
 
-    /// 创建文档快照
+    /// 创建文档快照，返回创建的快照摘要
     async fn create_document_snapshots(
         &self,
         publication_id: &str,
         space_id: &str,
         include_private: bool,
-    ) -> Result<u32> {
-        info!("Creating document snapshots for space_id: {}, include_private: {}", space_id, include_private);
+    ) -> Result<Vec<SnapshotDigest>> {
+        debug!("Creating document snapshots for space_id: {}, include_private: {}", space_id, include_private);
         
         // 处理 space_id 格式：去掉 "space:" 前缀
         let clean_space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
-        info!("Using clean_space_id for document query: {}", clean_space_id);
+        debug!("Using clean_space_id for document query: {}", clean_space_id);
         
         // 首先尝试查询所有文档来调试
         let debug_query = "SELECT id, space_id FROM document LIMIT 5";
         let debug_result: Vec<serde_json::Value> = self.db.client
             .query(debug_query)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
         
-        info!("Sample documents from database: {:?}", debug_result);
+        trace!("Sample documents from database: {:?}", debug_result);
         
         // 获取要发布的文档
         // 注意：space_id 在数据库中是 Thing 类型，需要使用 Thing 进行查询
@@ -483,34 +843,35 @@ impl PublicationService {
             ORDER BY order_index ASC, created_at ASC"
         };
         
-        info!("Document query: {}", query);
-        info!("Query binding - space_id: space:{}", clean_space_id);
+        trace!("Document query: {}", query);
+        trace!("Query binding - space_id: space:{}", clean_space_id);
 
         let mut result = self.db.client
             .query(query)
             .bind(("space_id", Thing::from(("space", clean_space_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let documents_db: Vec<crate::models::document::DocumentDb> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
         
         let documents: Vec<Document> = documents_db.into_iter().map(|db| db.into()).collect();
 
         let document_count = documents.len() as u32;
-        info!("Found {} documents to publish", document_count);
+        debug!("Found {} documents to publish", document_count);
         
         // 打印所有文档的详细信息
         for doc in &documents {
-            info!("Document: title={}, id={:?}, parent_id={:?}, is_public={}", 
+            trace!("Document: title={}, id={:?}, parent_id={:?}, is_public={}", 
                 doc.title, doc.id, doc.parent_id, doc.is_public);
         }
 
         // 创建快照
+        let mut digests = Vec::with_capacity(documents.len());
         for doc in documents {
             if let Some(doc_id) = &doc.id {
-                info!("Creating snapshot for document: {} ({})", doc.title, doc_id);
+                trace!("Creating snapshot for document: {} ({})", doc.title, doc_id);
                 // 创建业务模型用于插入（按照Space服务的模式）
                 let snapshot = PublicationDocument {
                     id: None,
@@ -520,22 +881,45 @@ impl PublicationService {
                     slug: doc.slug.clone(),
                     content: doc.content.clone(),
                     excerpt: doc.excerpt.clone(),
+                    content_hash: snapshot_content_hash(&doc.title, &doc.content),
                     parent_id: doc.parent_id.clone(),  // 保持原始的parent_id格式
                     order_index: doc.order_index as u32,
                     word_count: doc.word_count,
                     reading_time: doc.reading_time,
+                    is_public: doc.is_public,
                     created_at: None,  // 让数据库使用默认值
                 };
 
+                digests.push(SnapshotDigest {
+                    original_doc_id: snapshot.original_doc_id.clone(),
+                    title: snapshot.title.clone(),
+                    content_hash: snapshot.content_hash.clone(),
+                });
+
                 let _: Vec<PublicationDocument> = self.db.client
                     .create("publication_document")
                     .content(snapshot)
                     .await
-                    .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+                    .map_err(|e| AppError::database_error(e.to_string()))?;
             }
         }
 
-        Ok(document_count)
+        Ok(digests)
+    }
+
+    /// 获取发布当前快照的摘要
+    async fn get_snapshot_digests(&self, publication_id: &str) -> Result<Vec<SnapshotDigest>> {
+        let query = "SELECT original_doc_id, title, content_hash FROM publication_document 
+            WHERE publication_id = $publication_id
+            ORDER BY order_index ASC";
+
+        self.db.client
+            .query(query)
+            .bind(("publication_id", self.format_publication_id(publication_id)))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))
     }
 
     /// 删除文档快照
@@ -546,7 +930,7 @@ impl PublicationService {
             .query(query)
             .bind(("publication_id", self.format_publication_id(publication_id)))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(())
     }
@@ -558,13 +942,14 @@ impl PublicationService {
         version: i32,
         publisher_id: &str,
         change_summary: &str,
+        changed_documents: Vec<ChangedDocument>,
     ) -> Result<()> {
         let history = PublicationHistory {
             id: None,
             publication_id: publication_id.to_string(),
             version: version as u32,
             change_summary: Some(change_summary.to_string()),
-            changed_documents: vec![], // TODO: 实现文档变更检测
+            changed_documents,
             published_by: publisher_id.to_string(),
             published_at: None,  // 让数据库使用默认值
         };
@@ -573,7 +958,7 @@ impl PublicationService {
             .create("publication_history")
             .content(history)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(())
     }
@@ -589,6 +974,7 @@ impl PublicationService {
             views_week: 0,
             views_month: 0,
             popular_documents: vec![],
+            last_view_at: None,
             updated_at: None,  // 让数据库使用默认值
         };
 
@@ -596,30 +982,30 @@ impl PublicationService {
             .create("publication_analytics")
             .content(analytics)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(())
     }
 
     /// 获取发布
     pub async fn get_publication_by_id(&self, publication_id: &str) -> Result<SpacePublication> {
-        info!("Getting publication by id: {}", publication_id);
+        debug!("Getting publication by id: {}", publication_id);
         
         // 处理 ID 格式：去掉可能的表前缀
         let clean_id = publication_id.strip_prefix("space_publication:").unwrap_or(publication_id);
-        info!("Using clean_id: {}", clean_id);
+        debug!("Using clean_id: {}", clean_id);
         
         let publications_db: Option<SpacePublicationDb> = self.db.client
             .query("SELECT * FROM $id WHERE is_deleted = false")
             .bind(("id", Thing::from(("space_publication", clean_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         publications_db
             .map(|db| db.into())
-            .ok_or_else(|| ApiError::NotFound("Publication not found".to_string()))
+            .ok_or_else(|| AppError::NotFound("Publication not found".to_string()))
     }
 
     /// 通过slug获取发布
@@ -631,16 +1017,101 @@ impl PublicationService {
             .query(query)
             .bind(("slug", slug))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let publications_db: Vec<SpacePublicationDb> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        publications_db.into_iter()
+        let publication = publications_db.into_iter()
             .map(|db| db.into())
             .next()
-            .ok_or_else(|| ApiError::NotFound("Publication not found".to_string()))
+            .ok_or_else(|| AppError::NotFound("Publication not found".to_string()))?;
+
+        ensure_publicly_accessible(publication, Utc::now())
+    }
+
+    /// 通过请求的 Host 头获取绑定了自定义域名的发布
+    pub async fn get_publication_by_host(&self, host: &str) -> Result<SpacePublication> {
+        let domain = normalize_custom_domain(host)
+            .map_err(|_| AppError::NotFound("Publication not found".to_string()))?;
+
+        let query = "SELECT * FROM space_publication 
+            WHERE custom_domain = $domain AND is_active = true AND is_deleted = false";
+
+        let publications_db: Vec<SpacePublicationDb> = self.db.client
+            .query(query)
+            .bind(("domain", domain))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let publication = publications_db.into_iter()
+            .map(|db| db.into())
+            .next()
+            .ok_or_else(|| AppError::NotFound("Publication not found".to_string()))?;
+
+        ensure_publicly_accessible(publication, Utc::now())
+    }
+
+    /// 将已过期但仍处于发布状态的发布标记为未发布，返回处理的数量
+    pub async fn deactivate_expired_publications(&self) -> Result<usize> {
+        let expired: Vec<SpacePublicationDb> = self.db.client
+            .query("UPDATE space_publication SET is_active = false, updated_at = time::now()
+                WHERE is_active = true AND is_deleted = false AND expires_at != NONE AND expires_at <= time::now()
+                RETURN AFTER")
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        for publication in &expired {
+            if let Some(id) = &publication.id {
+                self.invalidate_tree_cache(&id.to_string()).await;
+            }
+        }
+        if !expired.is_empty() {
+            info!("Deactivated {} expired publications", expired.len());
+        }
+
+        Ok(expired.len())
+    }
+
+    /// 设置或清除发布的自定义域名，域名在所有发布中唯一
+    pub async fn set_custom_domain(
+        &self,
+        publication_id: &str,
+        domain: Option<&str>,
+    ) -> Result<PublicationResponse> {
+        let mut publication = self.get_publication_by_id(publication_id).await?;
+
+        let domain = match domain.map(str::trim).filter(|d| !d.is_empty()) {
+            Some(domain) => Some(normalize_custom_domain(domain)?),
+            None => None,
+        };
+
+        // 域名唯一性由唯一索引保证：直接写入并捕获冲突，避免先查后写的竞态
+        self.db.client
+            .query("UPDATE $id SET custom_domain = $domain, updated_at = time::now()")
+            .bind(("id", self.get_publication_thing(publication_id)))
+            .bind(("domain", &domain))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::from_unique_violation(
+                e,
+                "idx_space_publication_custom_domain",
+                format!("Domain {} is already used by another publication", domain.as_deref().unwrap_or_default()),
+            ))?;
+
+        info!("Set custom domain of publication {} to {:?}", publication_id, domain);
+        publication.custom_domain = domain;
+
+        let document_count = self.get_document_count(publication_id).await?;
+        let analytics = self.get_analytics(publication_id).await?;
+
+        Ok(self.build_publication_response(publication, document_count, analytics.total_views).await?)
     }
 
     /// 获取文档数量
@@ -652,11 +1123,11 @@ impl PublicationService {
             .query(query)
             .bind(("publication_id", publication_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let records: Vec<serde_json::Value> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let count = records
             .first()
@@ -675,16 +1146,28 @@ impl PublicationService {
             .query(query)
             .bind(("publication_id", publication_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let analytics_db: Vec<PublicationAnalyticsDb> = result
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        analytics_db.into_iter()
+        let mut analytics: PublicationAnalytics = analytics_db.into_iter()
             .map(|db| db.into())
             .next()
-            .ok_or_else(|| ApiError::NotFound("Analytics not found".to_string()))
+            .ok_or_else(|| AppError::NotFound("Analytics not found".to_string()))?;
+
+        analytics.popular_documents = self.db.client
+            .query("SELECT doc_id, title, views FROM publication_document_view
+                WHERE publication_id = $publication_id ORDER BY views DESC LIMIT $limit")
+            .bind(("publication_id", publication_id))
+            .bind(("limit", MAX_POPULAR_DOCUMENTS))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(analytics)
     }
 
     /// 构建发布响应
@@ -700,6 +1183,9 @@ impl PublicationService {
         // 先调用方法获取URL
         let public_url = publication.get_public_url(&frontend_url);
         let preview_url = publication.get_preview_url(&frontend_url);
+        let custom_domain = publication.custom_domain.clone();
+        let is_password_protected = publication.is_password_protected();
+        let expires_at = publication.expires_at;
 
         Ok(PublicationResponse {
             id: publication.id.clone().unwrap_or_default(),
@@ -712,7 +1198,9 @@ impl PublicationService {
             theme: publication.theme,
             public_url,
             preview_url,
-            custom_domain: None, // TODO: 实现自定义域名
+            custom_domain,
+            is_password_protected,
+            expires_at,
             document_count,
             total_views,
             is_active: publication.is_active,
@@ -724,7 +1212,7 @@ impl PublicationService {
 
     /// 构建文档树
     fn build_document_tree(&self, documents: Vec<PublicationDocument>) -> Result<Vec<PublicationDocumentNode>> {
-        info!("Building document tree for {} documents", documents.len());
+        debug!("Building document tree for {} documents", documents.len());
         
         let mut doc_map = std::collections::HashMap::new();
         let mut children_map: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
@@ -741,7 +1229,7 @@ impl PublicationService {
         // 第一次遍历：创建所有节点并识别父子关系
         for doc in documents {
             if let Some(doc_id) = &doc.id {
-                info!("Processing document: {} (id: {}, parent: {:?}, original: {})", 
+                trace!("Processing document: {} (id: {}, parent: {:?}, original: {})", 
                     doc.title, doc_id, doc.parent_id, doc.original_doc_id);
                     
                 let node = PublicationDocumentNode {
@@ -758,12 +1246,12 @@ impl PublicationService {
                 if let Some(parent_id) = &doc.parent_id {
                     // 将原始文档的parent_id转换为发布文档的parent_id
                     if let Some(published_parent_id) = original_to_published.get(parent_id) {
-                        info!("Mapping parent {} to {}", parent_id, published_parent_id);
+                        trace!("Mapping parent {} to {}", parent_id, published_parent_id);
                         children_map.entry(published_parent_id.clone())
                             .or_insert_with(Vec::new)
                             .push(doc_id.clone());
                     } else {
-                        info!("Warning: parent {} not found in mapping, treating as root", parent_id);
+                        warn!("Parent {} not found in mapping, treating as root", parent_id);
                         root_docs.push(doc_id.clone());
                     }
                 } else {
@@ -810,12 +1298,1114 @@ impl PublicationService {
     }
 
     /// 记录文档访问
+    ///
+    /// `visitor_hash` 为访客的匿名标识（见 [`visitor_hash`]），为空时只记录浏览量，不计独立访客。
     pub async fn track_document_view(
         &self,
         publication_id: &str,
         document_id: &str,
+        document_title: &str,
+        visitor_hash: Option<&str>,
     ) -> Result<()> {
-        // TODO: 实现访问统计
+        let now = Utc::now();
+
+        let analytics = match self.get_analytics(publication_id).await {
+            Ok(analytics) => analytics,
+            Err(AppError::NotFound(_)) => {
+                // 旧的发布可能没有统计记录，首次访问时补建
+                self.init_analytics(publication_id).await?;
+                self.get_analytics(publication_id).await?
+            }
+            Err(e) => return Err(e),
+        };
+
+        let is_new_visitor = match visitor_hash {
+            Some(hash) => self.record_visitor(publication_id, hash, now).await?,
+            None => false,
+        };
+
+        // 计数在数据库中原子递增，并发访问不会丢失；只有是否清零由上次访问时间决定
+        let resets = period_resets(analytics.last_view_at, now);
+        self.db.client
+            .query("UPDATE publication_analytics SET
+                total_views += 1,
+                unique_visitors += $visitor_increment,
+                views_today = IF $reset_day THEN 1 ELSE views_today + 1 END,
+                views_week = IF $reset_week THEN 1 ELSE views_week + 1 END,
+                views_month = IF $reset_month THEN 1 ELSE views_month + 1 END,
+                last_view_at = <datetime> $now,
+                updated_at = time::now()
+                WHERE publication_id = $publication_id;
+             UPDATE type::thing('publication_document_view', [$publication_id, $document_id]) SET
+                publication_id = $publication_id,
+                doc_id = $document_id,
+                title = $document_title,
+                views += 1;")
+            .bind(("publication_id", publication_id))
+            .bind(("document_id", document_id))
+            .bind(("document_title", document_title))
+            .bind(("visitor_increment", u64::from(is_new_visitor)))
+            .bind(("reset_day", resets.day))
+            .bind(("reset_week", resets.week))
+            .bind(("reset_month", resets.month))
+            .bind(("now", now))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        // 按天累计浏览量，供空间趋势统计使用。记录ID由发布ID和日期组成，当天首次访问时创建
+        self.db.client
+            .query(DAILY_VIEW_UPSERT_QUERY)
+            .bind(("publication_id", publication_id))
+            .bind(("day", Utc.from_utc_datetime(&now.date_naive().and_time(NaiveTime::MIN))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 记录访客，返回本次访问是否应计为新的独立访客
+    async fn record_visitor(
+        &self,
+        publication_id: &str,
+        visitor_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool> {
+        #[derive(serde::Deserialize)]
+        struct VisitorRow {
+            counted_at: DateTime<Utc>,
+        }
+
+        let existing: Vec<VisitorRow> = self.db.client
+            .query("SELECT counted_at FROM publication_visitor
+                WHERE publication_id = $publication_id AND visitor_hash = $visitor_hash")
+            .bind(("publication_id", publication_id))
+            .bind(("visitor_hash", visitor_hash))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let query = match existing.first() {
+            None => "CREATE publication_visitor CONTENT {
+                    publication_id: $publication_id,
+                    visitor_hash: $visitor_hash,
+                    counted_at: <datetime> $now,
+                    last_seen_at: <datetime> $now
+                }",
+            Some(row) if now - row.counted_at >= Duration::hours(VISITOR_WINDOW_HOURS) => {
+                "UPDATE publication_visitor SET counted_at = <datetime> $now, last_seen_at = <datetime> $now
+                    WHERE publication_id = $publication_id AND visitor_hash = $visitor_hash"
+            }
+            Some(_) => {
+                self.db.client
+                    .query("UPDATE publication_visitor SET last_seen_at = <datetime> $now
+                        WHERE publication_id = $publication_id AND visitor_hash = $visitor_hash")
+                    .bind(("publication_id", publication_id))
+                    .bind(("visitor_hash", visitor_hash))
+                    .bind(("now", now))
+                    .await
+                    .map_err(|e| AppError::database_error(e.to_string()))?
+                    .check()
+                    .map_err(|e| AppError::database_error(e.to_string()))?;
+                return Ok(false);
+            }
+        };
+
+        let counted = self.db.client
+            .query(query)
+            .bind(("publication_id", publication_id))
+            .bind(("visitor_hash", visitor_hash))
+            .bind(("now", now))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check();
+
+        // 同一访客的并发首次访问只有一个能通过唯一索引，其余不计为新访客
+        if let Err(e) = counted {
+            debug!("Visitor of publication {} not counted: {}", publication_id, e);
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+}
+
+/// 文档快照摘要，用于比较两次发布之间的变更
+#[derive(Debug, Clone, serde::Deserialize)]
+struct SnapshotDigest {
+    original_doc_id: String,
+    title: String,
+    #[serde(default)]
+    content_hash: String,
+}
+
+/// 计算快照的内容哈希（标题和正文任一变化都视为修改）
+pub fn snapshot_content_hash(title: &str, content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(title.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(content.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// 取出 create 返回的发布记录，发布ID直接来自记录自身的 Thing，不再按 slug 回查
+fn created_publication(created: Vec<SpacePublicationDb>) -> Result<SpacePublication> {
+    let publication: SpacePublication = created.into_iter()
+        .next()
+        .map(SpacePublication::from)
+        .ok_or_else(|| AppError::internal_server_error("Failed to create publication".to_string()))?;
+
+    if publication.id.is_none() {
+        return Err(AppError::internal_server_error("Publication ID is missing".to_string()));
+    }
+    Ok(publication)
+}
+
+fn ensure_future_expiry(expires_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Result<()> {
+    if expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return Err(AppError::Validation("expires_at must be in the future".to_string()));
+    }
+    Ok(())
+}
+
+/// 公开访问时过期的发布视为不存在，定时任务标记之前也一样
+pub fn ensure_publicly_accessible(publication: SpacePublication, now: DateTime<Utc>) -> Result<SpacePublication> {
+    if publication.is_expired(now) {
+        return Err(AppError::NotFound("Publication has expired".to_string()));
+    }
+    Ok(publication)
+}
+
+/// 管理列表默认只列出发布中的记录，但过期的发布仍然列出，方便所有者查看和处理
+pub fn listed_in_management(publication: &SpacePublication, include_inactive: bool, now: DateTime<Utc>) -> bool {
+    include_inactive || publication.is_active || publication.is_expired(now)
+}
+
+pub fn hash_access_password(password: &str) -> Result<String> {
+    // 盐取自随机 UUID，不依赖 password_hash 的 rand_core 特性
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())
+        .map_err(|e| AppError::internal_server_error(format!("Failed to generate salt: {}", e)))?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::internal_server_error(format!("Failed to hash access password: {}", e)))
+}
+
+pub fn verify_access_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AccessClaims {
+    /// 发布ID
+    sub: String,
+    /// 密码哈希的指纹，修改或取消密码后旧令牌随之失效
+    pwd: String,
+    exp: i64,
+}
+
+/// 签发和校验发布访问令牌（HS256）
+pub struct AccessTokenSigner {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    ttl: Duration,
+}
+
+impl AccessTokenSigner {
+    /// 签名密钥由 secret 派生，避免访问令牌被当作用户 JWT 接受
+    pub fn new(secret: &str, ttl: Duration) -> Self {
+        let key = Sha256::new()
+            .chain_update(b"publication-access:")
+            .chain_update(secret.as_bytes())
+            .finalize();
+        Self {
+            encoding_key: EncodingKey::from_secret(&key),
+            decoding_key: DecodingKey::from_secret(&key),
+            ttl,
+        }
+    }
+
+    pub fn issue(&self, publication: &SpacePublication, now: DateTime<Utc>) -> Result<PublicationAccessToken> {
+        let expires_at = now + self.ttl;
+        let claims = AccessClaims {
+            sub: publication.id.clone().unwrap_or_default(),
+            pwd: password_fingerprint(publication),
+            exp: expires_at.timestamp(),
+        };
+
+        let token = encode(&Header::new(Algorithm::HS256), &claims, &self.encoding_key)
+            .map_err(|e| AppError::internal_server_error(format!("Failed to sign access token: {}", e)))?;
+        Ok(PublicationAccessToken { token, expires_at })
+    }
+
+    pub fn verify(&self, token: &str, publication: &SpacePublication, now: DateTime<Utc>) -> Result<()> {
+        // 过期时间按传入的 now 判断，不使用 jsonwebtoken 自带的检查
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.validate_exp = false;
+
+        let claims = decode::<AccessClaims>(token, &self.decoding_key, &validation)
+            .map_err(|_| AppError::Authentication("Invalid publication access token".to_string()))?
+            .claims;
+
+        if claims.exp <= now.timestamp() {
+            return Err(AppError::Authentication("Publication access token has expired".to_string()));
+        }
+        if publication.id.as_deref() != Some(claims.sub.as_str()) || claims.pwd != password_fingerprint(publication) {
+            return Err(AppError::Authentication("Invalid publication access token".to_string()));
+        }
+
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn password_fingerprint(publication: &SpacePublication) -> String {
+    let hash = Sha256::digest(publication.access_password_hash.as_deref().unwrap_or_default().as_bytes());
+    format!("{:x}", hash)[..16].to_string()
+}
+
+/// 根据文档生成分享快照。非公开文档需要请求中显式设置 include_private，
+/// 快照生成后文档再修改或改为私有都不影响已分享的内容
+pub fn build_document_share(
+    document: &Document,
+    sharer_id: &str,
+    request: ShareDocumentRequest,
+    now: DateTime<Utc>,
+) -> Result<SharedDocument> {
+    if !document.is_public && !request.include_private.unwrap_or(false) {
+        return Err(AppError::Authorization(
+            "Document is private; set include_private to share it".to_string(),
+        ));
+    }
+    ensure_future_expiry(request.expires_at, now)?;
+
+    let document_id = document.id.clone()
+        .ok_or_else(|| AppError::internal_server_error("Document ID is missing".to_string()))?;
+    let slug = request.slug.unwrap_or_else(|| {
+        let suffix: String = Uuid::new_v4().simple().to_string().chars().take(8).collect();
+        format!("{}-{}", slug_from_title(&document.title, SHARE_SLUG_TITLE_CHARS), suffix)
+    });
+
+    Ok(SharedDocument {
+        id: None,
+        document_id,
+        space_id: document.space_id.clone(),
+        slug,
+        theme: request.theme.unwrap_or_else(|| "default".to_string()),
+        title: document.title.clone(),
+        content: document.content.clone(),
+        excerpt: document.excerpt.clone(),
+        word_count: document.word_count,
+        reading_time: document.reading_time,
+        shared_by: sharer_id.to_string(),
+        expires_at: request.expires_at,
+        created_at: None,  // 让数据库使用默认值
+    })
+}
+
+/// 过期的分享按不存在处理，避免泄露链接曾经有效
+pub fn resolve_shared_document(share: SharedDocument, now: DateTime<Utc>) -> Result<SharedDocumentView> {
+    if share.is_expired(now) {
+        return Err(AppError::NotFound("Shared document not found".to_string()));
+    }
+
+    Ok(share.into())
+}
+
+/// 每篇文档以最近一次新增或修改它的发布时间作为更新时间，没有对应历史记录时使用快照时间，
+/// 按更新时间倒序取前 limit 篇
+pub fn recent_feed_entries(
+    documents: Vec<PublicationDocument>,
+    history: &[PublicationHistory],
+    site_url: &str,
+    fallback: DateTime<Utc>,
+    limit: usize,
+) -> Vec<FeedEntry> {
+    let mut dated: Vec<(DateTime<Utc>, PublicationDocument)> = documents
+        .into_iter()
+        .map(|doc| {
+            let changed_at = history
+                .iter()
+                .filter(|entry| entry.changed_documents.iter().any(|changed| {
+                    changed.doc_id == doc.original_doc_id
+                        && matches!(changed.change_type, ChangeType::Added | ChangeType::Modified)
+                }))
+                .filter_map(|entry| entry.published_at)
+                .max();
+            (changed_at.or(doc.created_at).unwrap_or(fallback), doc)
+        })
+        .collect();
+
+    dated.sort_by(|(a_time, a), (b_time, b)| b_time.cmp(a_time).then(a.order_index.cmp(&b.order_index)));
+
+    dated
+        .into_iter()
+        .take(limit)
+        .map(|(updated_at, doc)| FeedEntry {
+            link: document_url(site_url, &doc.slug),
+            title: doc.title,
+            summary: doc.excerpt,
+            updated_at,
+        })
+        .collect()
+}
+
+/// 发布中文档的公开链接
+fn document_url(site_url: &str, doc_slug: &str) -> String {
+    format!("{}/docs/{}", site_url, doc_slug)
+}
+
+/// 生成 schema.org TechArticle 结构化数据。标题和描述优先使用发布的 SEO 设置，
+/// 未设置时使用文档标题和摘要；作者为发布站点本身
+pub fn document_json_ld(
+    publication: &SpacePublication,
+    document: &PublicationDocument,
+    site_url: &str,
+) -> serde_json::Value {
+    let non_empty = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+    let canonical_url = document_url(site_url, &document.slug);
+    let site = serde_json::json!({
+        "@type": "Organization",
+        "name": publication.title,
+        "url": site_url,
+    });
+
+    let mut json_ld = serde_json::json!({
+        "@context": "https://schema.org",
+        "@type": "TechArticle",
+        "headline": non_empty(&publication.seo_title).unwrap_or_else(|| document.title.clone()),
+        "url": canonical_url,
+        "mainEntityOfPage": { "@type": "WebPage", "@id": canonical_url },
+        "author": site,
+        "publisher": site,
+        "wordCount": document.word_count,
+    });
+
+    if let Some(description) = non_empty(&publication.seo_description).or_else(|| non_empty(&document.excerpt)) {
+        json_ld["description"] = description.into();
+    }
+    if let Some(published_at) = document.created_at.or(publication.published_at) {
+        json_ld["datePublished"] = published_at.to_rfc3339().into();
+    }
+    if let Some(updated_at) = publication.updated_at {
+        json_ld["dateModified"] = updated_at.to_rfc3339().into();
+    }
+    if !publication.seo_keywords.is_empty() {
+        json_ld["keywords"] = publication.seo_keywords.join(", ").into();
+    }
+
+    json_ld
+}
+
+/// 只列出公开文档，lastmod 取快照时间。发布包含私有文档时按快照的 is_public 过滤，
+/// 不把私有文档暴露给搜索引擎；未包含私有文档的发布中所有快照都是公开的
+pub fn sitemap_urls(
+    documents: &[PublicationDocument],
+    include_private_docs: bool,
+    site_url: &str,
+    fallback: DateTime<Utc>,
+) -> Vec<SitemapUrl> {
+    documents
+        .iter()
+        .filter(|doc| doc.is_public || !include_private_docs)
+        .map(|doc| SitemapUrl {
+            loc: document_url(site_url, &doc.slug),
+            lastmod: doc.created_at.unwrap_or(fallback),
+        })
+        .collect()
+}
+
+/// 比较前后两个版本的快照，按新版本顺序列出新增和修改的文档，再列出被移除的文档
+fn compute_changed_documents(previous: &[SnapshotDigest], current: &[SnapshotDigest]) -> Vec<ChangedDocument> {
+    let previous_map: std::collections::HashMap<&str, &SnapshotDigest> = previous
+        .iter()
+        .map(|digest| (digest.original_doc_id.as_str(), digest))
+        .collect();
+    let current_ids: std::collections::HashSet<&str> = current
+        .iter()
+        .map(|digest| digest.original_doc_id.as_str())
+        .collect();
+
+    let mut changes = Vec::new();
+    for digest in current {
+        let change_type = match previous_map.get(digest.original_doc_id.as_str()) {
+            None => ChangeType::Added,
+            Some(old) if old.content_hash != digest.content_hash => ChangeType::Modified,
+            Some(_) => continue,
+        };
+        changes.push(ChangedDocument {
+            doc_id: digest.original_doc_id.clone(),
+            title: digest.title.clone(),
+            change_type,
+        });
+    }
+
+    for digest in previous {
+        if !current_ids.contains(digest.original_doc_id.as_str()) {
+            changes.push(ChangedDocument {
+                doc_id: digest.original_doc_id.clone(),
+                title: digest.title.clone(),
+                change_type: ChangeType::Deleted,
+            });
+        }
+    }
+
+    changes
+}
+
+/// 规范化并校验自定义域名：忽略大小写、端口和末尾的点，只接受普通的主机名
+pub fn normalize_custom_domain(input: &str) -> Result<String> {
+    let invalid = || AppError::Validation(format!("Invalid domain: {}", input));
+
+    let host = input.trim().to_ascii_lowercase();
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => name.to_string(),
+        _ => host,
+    };
+    let host = host.trim_end_matches('.');
+
+    if host.is_empty() || host.len() > 253 {
+        return Err(invalid());
+    }
+
+    let labels: Vec<&str> = host.split('.').collect();
+    if labels.len() < 2 {
+        return Err(invalid());
+    }
+
+    for label in &labels {
+        let valid = !label.is_empty()
+            && label.len() <= 63
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !label.starts_with('-')
+            && !label.ends_with('-');
+        if !valid {
+            return Err(invalid());
+        }
+    }
+
+    // 顶级域名不能是纯数字（排除 IP 地址）
+    if labels.last().map_or(true, |tld| tld.chars().all(|c| c.is_ascii_digit())) {
+        return Err(invalid());
+    }
+
+    Ok(host.to_string())
+}
+
+/// 独立访客去重窗口：同一访客在该时间内的重复访问只计一次
+const VISITOR_WINDOW_HOURS: i64 = 24;
+
+/// 由客户端 IP 和 User-Agent 生成匿名访客标识，按发布加盐，避免跨发布关联访客
+pub fn visitor_hash(publication_id: &str, client_ip: Option<&str>, user_agent: Option<&str>) -> Option<String> {
+    let ip = client_ip.map(str::trim).filter(|ip| !ip.is_empty())?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(publication_id.as_bytes());
+    hasher.update(b"|");
+    hasher.update(ip.as_bytes());
+    hasher.update(b"|");
+    hasher.update(user_agent.unwrap_or_default().as_bytes());
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// 统计中返回的热门文档数量上限
+const MAX_POPULAR_DOCUMENTS: usize = 10;
+
+/// 与上次访问相比，日/周/月计数是否需要从 1 重新开始
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PeriodResets {
+    pub day: bool,
+    pub week: bool,
+    pub month: bool,
+}
+
+/// 日/周/月按 UTC 自然日、ISO 周和自然月划分，月份需同时比较年份
+pub fn period_resets(last_view_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> PeriodResets {
+    match last_view_at {
+        Some(last) => PeriodResets {
+            day: last.date_naive() != now.date_naive(),
+            week: last.iso_week() != now.iso_week(),
+            month: (last.year(), last.month()) != (now.year(), now.month()),
+        },
+        None => PeriodResets::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+    }
+
+    fn created_record(id: Option<&str>, slug: &str, version: u32) -> SpacePublicationDb {
+        SpacePublicationDb {
+            id: id.map(|id| Thing::from(("space_publication", id))),
+            space_id: "space:abc".to_string(),
+            slug: slug.to_string(),
+            version,
+            title: "Docs".to_string(),
+            description: None,
+            cover_image: None,
+            theme: "default".to_string(),
+            include_private_docs: false,
+            enable_search: true,
+            enable_comments: false,
+            custom_css: None,
+            custom_js: None,
+            seo_title: None,
+            seo_description: None,
+            seo_keywords: vec![],
+            custom_domain: None,
+            access_password_hash: None,
+            expires_at: None,
+            is_active: true,
+            is_deleted: false,
+            published_by: "user_1".to_string(),
+            published_at: None,
+            updated_at: None,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_created_publication_id_comes_from_created_record() {
+        // 两个版本共用同一个 slug 时，ID 仍然来自各自创建的记录
+        let first = created_publication(vec![created_record(Some("a1b2c3"), "guide", 1)]).unwrap();
+        let second = created_publication(vec![created_record(Some("d4e5f6"), "guide", 2)]).unwrap();
+
+        assert_eq!(first.id.as_deref(), Some("space_publication:a1b2c3"));
+        assert_eq!(second.id.as_deref(), Some("space_publication:d4e5f6"));
+        assert_eq!(second.version, 2);
+    }
+
+    #[test]
+    fn test_created_publication_without_record_id_is_an_error() {
+        assert!(matches!(created_publication(vec![]), Err(AppError::Internal(_))));
+        assert!(matches!(
+            created_publication(vec![created_record(None, "guide", 1)]),
+            Err(AppError::Internal(_))
+        ));
+    }
+
+    #[test]
+    fn test_same_day_accumulates() {
+        assert_eq!(period_resets(Some(at(2024, 5, 15, 8)), at(2024, 5, 15, 20)), PeriodResets::default());
+        // 首次访问直接在初始计数上累加
+        assert_eq!(period_resets(None, at(2024, 5, 15, 20)), PeriodResets::default());
+    }
+
+    #[test]
+    fn test_period_rollovers() {
+        let resets = |day, week, month| PeriodResets { day, week, month };
+
+        // 周三 -> 周四：只有日计数清零
+        assert_eq!(period_resets(Some(at(2024, 5, 15, 23)), at(2024, 5, 16, 1)), resets(true, false, false));
+        // 周日 -> 周一：日、周清零，同月
+        assert_eq!(period_resets(Some(at(2024, 5, 19, 23)), at(2024, 5, 20, 1)), resets(true, true, false));
+        // 4月30日（周二）-> 5月1日（周三）：日、月清零，同一 ISO 周
+        assert_eq!(period_resets(Some(at(2024, 4, 30, 23)), at(2024, 5, 1, 1)), resets(true, false, true));
+        // 跨年：同一月份数但不同年份也要清零
+        assert_eq!(period_resets(Some(at(2023, 5, 15, 12)), at(2024, 5, 15, 12)), resets(true, true, true));
+    }
+
+    fn digest(id: &str, title: &str, content: &str) -> SnapshotDigest {
+        SnapshotDigest {
+            original_doc_id: id.to_string(),
+            title: title.to_string(),
+            content_hash: snapshot_content_hash(title, content),
+        }
+    }
+
+    #[test]
+    fn test_republish_with_one_modified_document() {
+        let first = vec![digest("doc1", "Intro", "hello"), digest("doc2", "Guide", "steps")];
+        let second = vec![digest("doc1", "Intro", "hello"), digest("doc2", "Guide", "new steps")];
+
+        let changes = compute_changed_documents(&first, &second);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].doc_id, "doc2");
+        assert!(matches!(changes[0].change_type, ChangeType::Modified));
+    }
+
+    #[test]
+    fn test_added_and_removed_documents() {
+        let first = vec![digest("doc1", "Intro", "hello"), digest("doc2", "Guide", "steps")];
+        let second = vec![digest("doc1", "Intro", "hello"), digest("doc3", "FAQ", "q")];
+
+        let changes = compute_changed_documents(&first, &second);
+        let summary: Vec<(&str, &str)> = changes
+            .iter()
+            .map(|c| (c.doc_id.as_str(), match c.change_type {
+                ChangeType::Added => "added",
+                ChangeType::Modified => "modified",
+                ChangeType::Deleted => "deleted",
+            }))
+            .collect();
+        assert_eq!(summary, vec![("doc3", "added"), ("doc2", "deleted")]);
+
+        // 首次发布时所有文档都是新增
+        assert_eq!(compute_changed_documents(&[], &first).len(), 2);
+    }
+
+    #[test]
+    fn test_normalize_custom_domain() {
+        assert_eq!(normalize_custom_domain("Docs.Example.com").unwrap(), "docs.example.com");
+        assert_eq!(normalize_custom_domain("docs.example.com:8080").unwrap(), "docs.example.com");
+        assert_eq!(normalize_custom_domain("docs.example.com.").unwrap(), "docs.example.com");
+
+        for invalid in ["", "localhost", "https://docs.example.com", "docs.example.com/path",
+            "-docs.example.com", "docs..example.com", "192.168.0.1", "docs_site.example.com"] {
+            assert!(normalize_custom_domain(invalid).is_err(), "{} should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn test_visitor_hash() {
+        let a = visitor_hash("pub1", Some("1.2.3.4"), Some("Mozilla"));
+        assert_eq!(a, visitor_hash("pub1", Some(" 1.2.3.4 "), Some("Mozilla")));
+        assert_ne!(a, visitor_hash("pub2", Some("1.2.3.4"), Some("Mozilla")));
+        assert_ne!(a, visitor_hash("pub1", Some("1.2.3.4"), Some("Safari")));
+        assert!(visitor_hash("pub1", None, Some("Mozilla")).is_none());
+    }
+
+    fn shareable_document(is_public: bool) -> Document {
+        let mut document = Document::new(
+            "space:handbook".to_string(),
+            "快速开始".to_string(),
+            "getting-started".to_string(),
+            "alice".to_string(),
+        );
+        document.id = Some("document:intro".to_string());
+        document.content = "# 快速开始\n\n安装后运行。".to_string();
+        document.is_public = is_public;
+        document
+    }
+
+    fn share_request() -> ShareDocumentRequest {
+        ShareDocumentRequest { slug: None, theme: None, expires_at: None, include_private: None }
+    }
+
+    #[test]
+    fn test_shared_document_resolves_by_public_slug() {
+        let now = at(2024, 5, 1, 9);
+        let mut document = shareable_document(true);
+        let share = build_document_share(&document, "alice", share_request(), now).unwrap();
+
+        assert!(share.slug.starts_with("kuai-su-kai-shi-"));
+        assert_eq!(share.document_id, "document:intro");
+        assert_eq!(share.get_public_url("https://docs.example.com"), format!("https://docs.example.com/s/{}", share.slug));
+
+        // 模拟 create 返回的记录，再按 slug 读取
+        let mut record = serde_json::to_value(&share).unwrap();
+        record["id"] = serde_json::to_value(Thing::from(("document_share", "s1"))).unwrap();
+        let stored: SharedDocument = serde_json::from_value::<SharedDocumentDb>(record).unwrap().into();
+        assert_eq!(stored.id.as_deref(), Some("document_share:s1"));
+
+        // 分享之后的修改不影响快照
+        document.content = "已修改".to_string();
+        let view = resolve_shared_document(stored, now).unwrap();
+        assert_eq!(view.slug, share.slug);
+        assert_eq!(view.title, "快速开始");
+        assert_eq!(view.content, "# 快速开始\n\n安装后运行。");
+        assert_eq!(view.theme, "default");
+
+        // 公开内容不带空间和文档ID
+        let json = serde_json::to_value(&view).unwrap();
+        assert!(json.get("space_id").is_none() && json.get("document_id").is_none());
+    }
+
+    #[test]
+    fn test_private_and_expired_shares() {
+        let now = at(2024, 5, 1, 9);
+        let document = shareable_document(false);
+
+        assert!(build_document_share(&document, "alice", share_request(), now).is_err());
+
+        let request = ShareDocumentRequest {
+            slug: Some("intro".to_string()),
+            expires_at: Some(at(2024, 5, 2, 9)),
+            include_private: Some(true),
+            ..share_request()
+        };
+        let share = build_document_share(&document, "alice", request, now).unwrap();
+        assert_eq!(share.slug, "intro");
+
+        assert!(resolve_shared_document(share.clone(), at(2024, 5, 2, 8)).is_ok());
+        assert!(matches!(resolve_shared_document(share, at(2024, 5, 2, 9)), Err(AppError::NotFound(_))));
+
+        let expired = ShareDocumentRequest { expires_at: Some(now), ..share_request() };
+        assert!(build_document_share(&shareable_document(true), "alice", expired, now).is_err());
+    }
+
+    fn protected_publication(id: &str, password: Option<&str>) -> SpacePublication {
+        SpacePublication {
+            id: Some(format!("space_publication:{}", id)),
+            space_id: "space:handbook".to_string(),
+            slug: "handbook".to_string(),
+            version: 1,
+            title: "Handbook".to_string(),
+            description: None,
+            cover_image: None,
+            theme: "default".to_string(),
+            include_private_docs: false,
+            enable_search: true,
+            enable_comments: false,
+            custom_css: None,
+            custom_js: None,
+            seo_title: None,
+            seo_description: None,
+            seo_keywords: vec![],
+            custom_domain: None,
+            access_password_hash: password.map(|p| hash_access_password(p).unwrap()),
+            expires_at: None,
+            is_active: true,
+            is_deleted: false,
+            published_by: "alice".to_string(),
+            published_at: None,
+            updated_at: None,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_access_password_check() {
+        let publication = protected_publication("p1", Some("open sesame"));
+        let hash = publication.access_password_hash.as_deref().unwrap();
+
+        assert!(publication.is_password_protected());
+        assert!(verify_access_password("open sesame", hash));
+        assert!(!verify_access_password("open sesame!", hash));
+        assert!(!verify_access_password("", hash));
+        assert!(!verify_access_password("open sesame", "not-a-hash"));
+    }
+
+    #[test]
+    fn test_access_token_expiry_and_scope() {
+        let signer = AccessTokenSigner::new("jwt-secret", Duration::minutes(30));
+        let publication = protected_publication("p1", Some("open sesame"));
+        let now = at(2024, 5, 1, 9);
+
+        let access = signer.issue(&publication, now).unwrap();
+        assert_eq!(access.expires_at, now + Duration::minutes(30));
+        assert!(signer.verify(&access.token, &publication, now + Duration::minutes(29)).is_ok());
+        assert!(matches!(
+            signer.verify(&access.token, &publication, now + Duration::minutes(30)),
+            Err(AppError::Authentication(_))
+        ));
+
+        // 令牌不能用于其他发布，修改密码后旧令牌失效
+        let other = protected_publication("p2", Some("open sesame"));
+        assert!(signer.verify(&access.token, &other, now).is_err());
+        let mut changed = publication.clone();
+        changed.access_password_hash = Some(hash_access_password("new password").unwrap());
+        assert!(signer.verify(&access.token, &changed, now).is_err());
+
+        // 其他密钥签发的令牌无效
+        let forged = AccessTokenSigner::new("other-secret", Duration::minutes(30)).issue(&publication, now).unwrap();
+        assert!(signer.verify(&forged.token, &publication, now).is_err());
+        assert!(signer.verify("garbage", &publication, now).is_err());
+    }
+
+    #[test]
+    fn test_expired_publication_hidden_from_public_but_listed_for_owner() {
+        let now = at(2024, 5, 1, 9);
+        let mut publication = protected_publication("p1", None);
+        publication.expires_at = Some(at(2024, 4, 30, 9));
+
+        assert!(publication.is_expired(now));
+        assert!(matches!(ensure_publicly_accessible(publication.clone(), now), Err(AppError::NotFound(_))));
+        assert!(listed_in_management(&publication, false, now));
+
+        // 定时任务标记为未发布之后，所有者仍然能在默认列表中看到
+        publication.is_active = false;
+        assert!(matches!(ensure_publicly_accessible(publication.clone(), now), Err(AppError::NotFound(_))));
+        assert!(listed_in_management(&publication, false, now));
+
+        // 未过期的发布不受影响，手动取消发布的仍然只在 include_inactive 时列出
+        let mut live = protected_publication("p2", None);
+        live.expires_at = Some(at(2024, 5, 2, 9));
+        assert!(ensure_publicly_accessible(live.clone(), now).is_ok());
+        live.is_active = false;
+        assert!(!listed_in_management(&live, false, now));
+        assert!(listed_in_management(&live, true, now));
+
+        assert!(ensure_future_expiry(Some(now), now).is_err());
+        assert!(ensure_future_expiry(None, now).is_ok());
+    }
+
+    fn published_doc(id: &str, title: &str, order_index: u32) -> PublicationDocument {
+        PublicationDocument {
+            id: None,
+            publication_id: "space_publication:p1".to_string(),
+            original_doc_id: id.to_string(),
+            title: title.to_string(),
+            slug: id.trim_start_matches("document:").to_string(),
+            content: String::new(),
+            excerpt: Some(format!("{} excerpt", title)),
+            content_hash: String::new(),
+            parent_id: None,
+            order_index,
+            word_count: 0,
+            reading_time: 0,
+            is_public: true,
+            created_at: Some(at(2024, 5, 3, 9)),
+        }
+    }
+
+    fn history(version: u32, published_at: DateTime<Utc>, changed: &[(&str, ChangeType)]) -> PublicationHistory {
+        PublicationHistory {
+            id: None,
+            publication_id: "space_publication:p1".to_string(),
+            version,
+            change_summary: None,
+            changed_documents: changed.iter()
+                .map(|(id, change_type)| ChangedDocument {
+                    doc_id: id.to_string(),
+                    title: String::new(),
+                    change_type: change_type.clone(),
+                })
+                .collect(),
+            published_by: "alice".to_string(),
+            published_at: Some(published_at),
+        }
+    }
+
+    #[test]
+    fn test_json_ld_falls_back_to_document_fields() {
+        let mut publication = protected_publication("p1", None);
+        publication.published_at = Some(at(2024, 5, 1, 9));
+        publication.seo_title = Some("  ".to_string());
+        let document = published_doc("document:install", "Install & Run", 1);
+        let site_url = "https://docs.example.com/p/guide";
+
+        let json_ld = document_json_ld(&publication, &document, site_url);
+        // 序列化后能重新解析，且包含规范链接
+        let parsed: serde_json::Value = serde_json::from_str(&json_ld.to_string()).unwrap();
+        assert_eq!(parsed, json_ld);
+        assert_eq!(json_ld["@context"], "https://schema.org");
+        assert_eq!(json_ld["@type"], "TechArticle");
+        assert_eq!(json_ld["url"], "https://docs.example.com/p/guide/docs/install");
+        assert_eq!(json_ld["mainEntityOfPage"]["@id"], json_ld["url"]);
+        assert_eq!(json_ld["headline"], "Install & Run");
+        assert_eq!(json_ld["description"], "Install & Run excerpt");
+        assert_eq!(json_ld["datePublished"], "2024-05-03T09:00:00+00:00");
+        assert_eq!(json_ld["author"]["name"], publication.title.as_str());
+        assert!(json_ld.get("keywords").is_none());
+
+        publication.seo_title = Some("Guide".to_string());
+        publication.seo_description = Some("Everything about the guide".to_string());
+        publication.seo_keywords = vec!["guide".to_string(), "install".to_string()];
+        let json_ld = document_json_ld(&publication, &document, site_url);
+        assert_eq!(json_ld["headline"], "Guide");
+        assert_eq!(json_ld["description"], "Everything about the guide");
+        assert_eq!(json_ld["keywords"], "guide, install");
+    }
+
+    #[test]
+    fn test_sitemap_lists_only_public_documents() {
+        let mut private = published_doc("document:roadmap", "Roadmap", 3);
+        private.is_public = false;
+        let mut documents = vec![
+            published_doc("document:intro", "Intro", 0),
+            published_doc("document:install", "Install", 1),
+            private,
+        ];
+        documents[1].created_at = None;
+        let site_url = "https://docs.example.com/p/guide";
+
+        let urls = sitemap_urls(&documents, true, site_url, at(2024, 5, 4, 9));
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[1].lastmod, at(2024, 5, 4, 9));
+
+        let xml = sitemap::render(&urls);
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">"));
+        let locs: Vec<&str> = xml.split("<loc>").skip(1)
+            .map(|rest| rest.split("</loc>").next().unwrap())
+            .collect();
+        assert_eq!(locs.len(), xml.matches("<url>").count());
+        assert_eq!(locs.len(), 2);
+        for loc in &locs {
+            let path = loc.strip_prefix("https://docs.example.com/p/guide/docs/").unwrap();
+            assert!(!path.is_empty() && path.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+        }
+        assert!(!xml.contains("roadmap"));
+        assert!(xml.contains("<lastmod>2024-05-03T09:00:00Z</lastmod>"));
+
+        // 不包含私有文档的发布，旧快照缺少 is_public 时也全部列出
+        assert_eq!(sitemap_urls(&documents, false, site_url, at(2024, 5, 4, 9)).len(), 3);
+    }
+
+    #[test]
+    fn test_feed_lists_recent_documents_newest_first() {
+        let documents = vec![
+            published_doc("document:intro", "Intro", 0),
+            published_doc("document:install", "Install & Run", 1),
+            published_doc("document:faq", "FAQ", 2),
+        ];
+        let history = vec![
+            history(1, at(2024, 5, 1, 9), &[
+                ("document:intro", ChangeType::Added),
+                ("document:install", ChangeType::Added),
+                ("document:faq", ChangeType::Added),
+            ]),
+            history(2, at(2024, 5, 2, 9), &[("document:install", ChangeType::Modified)]),
+            history(3, at(2024, 5, 3, 9), &[("document:faq", ChangeType::Modified), ("document:old", ChangeType::Deleted)]),
+        ];
+
+        let entries = recent_feed_entries(documents, &history, "https://docs.example.com/p/guide", at(2024, 5, 3, 9), 20);
+        let titles: Vec<&str> = entries.iter().map(|entry| entry.title.as_str()).collect();
+        assert_eq!(titles, vec!["FAQ", "Install & Run", "Intro"]);
+        assert_eq!(entries[0].updated_at, at(2024, 5, 3, 9));
+        assert_eq!(entries[2].link, "https://docs.example.com/p/guide/docs/intro");
+
+        let channel = FeedChannel {
+            title: "Guide",
+            description: None,
+            link: "https://docs.example.com/p/guide",
+            updated_at: at(2024, 5, 3, 9),
+        };
+        let rss = feed::render(FeedFormat::Rss, &channel, &entries);
+        let faq = rss.find("<title>FAQ</title>").unwrap();
+        let install = rss.find("<title>Install &amp; Run</title>").unwrap();
+        let intro = rss.find("<title>Intro</title>").unwrap();
+        assert!(faq < install && install < intro);
+        assert!(rss.contains("<link>https://docs.example.com/p/guide/docs/faq</link>"));
+        assert!(rss.contains("<pubDate>Thu, 2 May 2024 09:00:00 +0000</pubDate>"));
+        assert!(rss.contains("<description>Install &amp; Run excerpt</description>"));
+
+        let atom = feed::render(FeedFormat::Atom, &channel, &entries);
+        assert!(atom.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+        assert_eq!(atom.matches("<entry>").count(), 3);
+        assert!(atom.find("<updated>2024-05-03T09:00:00+00:00</updated>\n<summary>FAQ").is_some());
+
+        let limited = recent_feed_entries(vec![published_doc("document:intro", "Intro", 0)], &[], "https://x", at(2024, 1, 1, 0), 0);
+        assert!(limited.is_empty());
+    }
+
+    #[test]
+    fn test_update_request_distinguishes_null_expiry_from_missing() {
+        let missing: UpdatePublicationRequest = serde_json::from_str(r#"{"title":"Handbook"}"#).unwrap();
+        assert_eq!(missing.expires_at, None);
+
+        let cleared: UpdatePublicationRequest = serde_json::from_str(r#"{"expires_at":null}"#).unwrap();
+        assert_eq!(cleared.expires_at, Some(None));
+
+        let set: UpdatePublicationRequest = serde_json::from_str(r#"{"expires_at":"2024-05-02T09:00:00Z"}"#).unwrap();
+        assert_eq!(set.expires_at, Some(Some(at(2024, 5, 2, 9))));
+    }
+
+    /// 在测试数据库中创建公开空间和一篇公开文档，返回空间ID和文档
+    async fn create_public_document(state: &crate::state::AppState, owner_id: &str, slug: &str) -> (String, Document) {
+        let owner = crate::services::auth::User {
+            id: owner_id.to_string(),
+            email: "owner@example.com".to_string(),
+            roles: vec!["user".to_string()],
+            permissions: vec![],
+            profile: None,
+            api_key: None,
+        };
+        let space = state.space_service.create_space(crate::models::space::CreateSpaceRequest {
+            name: "Docs".to_string(),
+            slug: slug.to_string(),
+            description: None,
+            avatar_url: None,
+            is_public: Some(true),
+            settings: None,
+        }, &owner).await.unwrap();
+        let request = serde_json::from_value(serde_json::json!({ "title": "Guide", "slug": "guide", "content": "# Guide", "is_public": true })).unwrap();
+        let document = state.document_service.create_document(&space.id, owner_id, request).await.unwrap();
+        (space.id, document)
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_share_expiry_is_stored_and_enforced() {
+        let state = crate::state::test_support::test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let owner_id = format!("alice-{}", run);
+        let (_, document) = create_public_document(&state, &owner_id, &format!("share-{}", run)).await;
+        let expires_at = Utc.timestamp_opt(Utc::now().timestamp() + 86400, 0).unwrap();
+
+        let shared = state.publication_service.publish_document(document.id.as_deref().unwrap(), &owner_id, ShareDocumentRequest {
+            slug: Some(format!("guide-{}", run)),
+            theme: None,
+            expires_at: Some(expires_at),
+            include_private: None,
+        }).await.unwrap();
+
+        let view = state.publication_service.get_shared_document(&shared.share.slug).await.unwrap();
+        assert_eq!(view.title, "Guide");
+        assert_eq!(view.expires_at, Some(expires_at));
+
+        // 过期时间写入的是 datetime，到期后公开访问返回 404
+        state.db.client
+            .query("UPDATE document_share SET expires_at = time::now() - 1h WHERE slug = $slug")
+            .bind(("slug", &shared.share.slug))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        let error = state.publication_service.get_shared_document(&shared.share.slug).await.unwrap_err();
+        assert!(matches!(error, AppError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_expired_publication_denied_publicly_but_listed_for_owner() {
+        let state = crate::state::test_support::test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let owner_id = format!("alice-{}", run);
+        let (space_id, _) = create_public_document(&state, &owner_id, &format!("expiry-{}", run)).await;
+        let slug = format!("handbook-{}", run);
+        let expires_at = Utc.timestamp_opt(Utc::now().timestamp() + 86400, 0).unwrap();
+
+        let request = serde_json::from_value(serde_json::json!({ "slug": slug, "title": "Handbook", "expires_at": expires_at })).unwrap();
+        let created = state.publication_service.create_publication(&space_id, &owner_id, request).await.unwrap();
+        assert_eq!(state.publication_service.get_publication_by_slug(&slug).await.unwrap().expires_at, Some(expires_at));
+
+        // expires_at 为 null 时清除过期时间
+        let request = serde_json::from_value(serde_json::json!({ "expires_at": null })).unwrap();
+        let updated = state.publication_service.update_publication(&created.id, &owner_id, request).await.unwrap();
+        assert_eq!(updated.expires_at, None);
+        assert_eq!(state.publication_service.get_publication_by_slug(&slug).await.unwrap().expires_at, None);
+
+        // 让发布过期，并执行定时任务的下线处理
+        state.db.client
+            .query("UPDATE $id SET expires_at = time::now() - 1h")
+            .bind(("id", created.id.parse::<Thing>().unwrap()))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        state.publication_service.deactivate_expired_publications().await.unwrap();
+
+        let error = state.publication_service.get_publication_by_slug(&slug).await.unwrap_err();
+        assert!(matches!(error, AppError::NotFound(_)));
+        let listed = state.publication_service.list_publications(&space_id, false).await.unwrap();
+        assert!(listed.iter().any(|publication| publication.id == created.id));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_password_attempts_are_rate_limited_per_client() {
+        let service = PublicationService::new(crate::services::database::test_database().await);
+
+        for _ in 0..MAX_ACCESS_ATTEMPTS {
+            let result = service.verify_publication_password("missing-publication", "guess", Some("203.0.113.7")).await;
+            assert!(matches!(result, Err(AppError::NotFound(_))));
+        }
+        let result = service.verify_publication_password("missing-publication", "guess", Some("203.0.113.7")).await;
+        assert!(matches!(result, Err(AppError::TooManyRequests(_))));
+
+        // 其他客户端不受影响
+        let result = service.verify_publication_password("missing-publication", "guess", Some("203.0.113.8")).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+}
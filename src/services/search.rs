@@ -1,42 +1,88 @@
+use std::collections::HashMap;
 use std::sync::Arc;
-use surrealdb::sql::Thing;
+use surrealdb::sql::{Thing, Value};
 use std::time::Instant;
 
 use crate::{
-    error::ApiError,
+    error::AppError,
     models::search::{
         SearchIndex, SearchRequest, SearchResult, SearchResponse, 
         SearchSortBy, SearchHighlight
     },
-    services::{auth::AuthService, database::Database},
+    services::{
+        auth::AuthService,
+        database::Database,
+        space_member::SpaceMemberService,
+        spaces::SpaceService,
+        vector::{VectorSearchRequest, VectorService},
+    },
+    utils::pagination::PageRequest,
 };
 
 #[derive(Clone)]
 pub struct SearchService {
     db: Arc<Database>,
     auth_service: Arc<AuthService>,
+    space_service: Option<Arc<SpaceService>>,
+    space_member_service: Option<Arc<SpaceMemberService>>,
+    hybrid_vector_weight: f64,
+}
+
+/// 混合搜索未指定权重时向量排名的默认权重
+const DEFAULT_HYBRID_VECTOR_WEIGHT: f64 = 0.5;
+/// 混合搜索时关键词和向量两路各自取的候选数量
+const HYBRID_CANDIDATES: i64 = 100;
+/// 倒数排名融合的平滑常数，取论文中的常用值 60
+const RRF_K: f64 = 60.0;
+
+/// 搜索结果的可见范围
+#[derive(Clone, Copy)]
+enum SearchScope<'a> {
+    /// 公开文档或用户自己创建的文档
+    Default,
+    /// 用户拥有或已加入的空间中的文档，以及公开文档
+    Accessible(&'a [String]),
 }
 
 impl SearchService {
     pub fn new(db: Arc<Database>, auth_service: Arc<AuthService>) -> Self {
-        Self { db, auth_service }
+        Self {
+            db,
+            auth_service,
+            space_service: None,
+            space_member_service: None,
+            hybrid_vector_weight: DEFAULT_HYBRID_VECTOR_WEIGHT,
+        }
+    }
+
+    pub fn with_space_service(mut self, space_service: Arc<SpaceService>) -> Self {
+        self.space_service = Some(space_service);
+        self
+    }
+
+    pub fn with_space_member_service(mut self, space_member_service: Arc<SpaceMemberService>) -> Self {
+        self.space_member_service = Some(space_member_service);
+        self
+    }
+
+    pub fn with_hybrid_vector_weight(mut self, weight: f64) -> Self {
+        self.hybrid_vector_weight = weight;
+        self
     }
 
-    pub async fn create_or_update_index(&self, index: SearchIndex) -> Result<(), ApiError> {
+    pub async fn create_or_update_index(&self, index: SearchIndex) -> Result<(), AppError> {
         let created: Vec<SearchIndex> = self.db.client
             .create("search_index")
             .content(index)
-            .await
-            .map_err(|e| ApiError::Database(e))?;
+            .await?;
 
         Ok(())
     }
 
-    pub async fn delete_index(&self, document_id: &str) -> Result<(), ApiError> {
+    pub async fn delete_index(&self, document_id: &str) -> Result<(), AppError> {
         let _: Option<SearchIndex> = self.db.client
             .delete(("search_index", document_id))
-            .await
-            .map_err(|e| ApiError::Database(e))?;
+            .await?;
 
         Ok(())
     }
@@ -45,52 +91,203 @@ impl SearchService {
         &self,
         user_id: &str,
         request: SearchRequest,
-    ) -> Result<SearchResponse, ApiError> {
+    ) -> Result<SearchResponse, AppError> {
+        self.search_with_scope(user_id, request, SearchScope::Default).await
+    }
+
+    /// 搜索用户能访问的全部内容：自己拥有或已加入的空间中的文档，以及公开文档
+    pub async fn search_all_accessible(
+        &self,
+        user_id: &str,
+        request: SearchRequest,
+    ) -> Result<SearchResponse, AppError> {
+        let space_ids = self.accessible_space_ids(user_id).await?;
+        self.search_with_scope(user_id, request, SearchScope::Accessible(&space_ids)).await
+    }
+
+    /// 混合搜索：分别取关键词搜索和向量近邻搜索的前若干条，再按倒数排名融合（RRF）。
+    /// 查询向量由调用方用与文档向量相同的模型生成；vector_weight 为向量排名的权重（0~1），
+    /// 未指定时使用配置值。两路结果都按同样的可见范围和筛选条件过滤
+    pub async fn hybrid_search(
+        &self,
+        user_id: &str,
+        request: SearchRequest,
+        query_vector: Vec<f32>,
+        vector_weight: Option<f64>,
+        all_accessible: bool,
+    ) -> Result<SearchResponse, AppError> {
         let start_time = Instant::now();
-        
-        let page = request.page.unwrap_or(1);
-        let per_page = request.per_page.unwrap_or(20);
-        let offset = (page - 1) * per_page;
-
-        // 构建搜索查询
-        let mut query_parts = vec![
-            "SELECT * FROM search_index".to_string(),
-            "WHERE is_public = true OR author_id = $user_id".to_string(),
-        ];
-
-        let mut bindings: Vec<(String, String)> = vec![("user_id".to_string(), user_id.to_string())];
-
-        // 添加查询条件
-        if !request.query.is_empty() {
-            query_parts.push("AND (title CONTAINSTEXT $query OR content CONTAINSTEXT $query)".to_string());
-            bindings.push(("query".to_string(), request.query.clone()));
+
+        let vector_weight = vector_weight.unwrap_or(self.hybrid_vector_weight);
+        if !(0.0..=1.0).contains(&vector_weight) {
+            return Err(AppError::Validation("vector_weight must be between 0 and 1".to_string()));
+        }
+        if query_vector.is_empty() {
+            return Err(AppError::Validation("Query vector is required for hybrid search".to_string()));
         }
 
-        if let Some(space_id) = &request.space_id {
-            query_parts.push("AND space_id = $space_id".to_string());
-            bindings.push(("space_id".to_string(), space_id.clone()));
+        let space_ids = if all_accessible {
+            self.accessible_space_ids(user_id).await?
+        } else {
+            Vec::new()
+        };
+        let scope = if all_accessible {
+            SearchScope::Accessible(&space_ids)
+        } else {
+            SearchScope::Default
+        };
+        let (page, limit, offset) = normalize_pagination(request.page, request.limit);
+
+        // 关键词一路，没有关键词时只按向量排名
+        let keyword_results = if request.query.trim().is_empty() {
+            Vec::new()
+        } else {
+            let keyword_request = SearchRequest {
+                query: request.query.clone(),
+                space_id: request.space_id.clone(),
+                tags: request.tags.clone(),
+                author_id: request.author_id.clone(),
+                page: Some(1),
+                limit: Some(HYBRID_CANDIDATES),
+                sort_by: Some(SearchSortBy::Relevance),
+            };
+            self.search_with_scope(user_id, keyword_request, scope).await?.results
+        };
+
+        // 向量一路，同一文档可能有多条向量，只保留排名最高的一条
+        let vector_hits = VectorService::new(self.db.clone())
+            .search_similar(VectorSearchRequest {
+                query_vector,
+                space_id: request
+                    .space_id
+                    .as_deref()
+                    .map(|id| id.strip_prefix("space:").unwrap_or(id).to_string()),
+                limit: HYBRID_CANDIDATES as usize,
+                threshold: 0.0,
+                include_content: false,
+            })
+            .await?;
+        let mut vector_ids: Vec<String> = Vec::new();
+        for hit in vector_hits.results {
+            let key = document_key(&hit.document_id).to_string();
+            if !vector_ids.contains(&key) {
+                vector_ids.push(key);
+            }
         }
+        let vector_results = self
+            .load_vector_candidates(user_id, &request, &scope, &vector_ids)
+            .await?;
 
-        if let Some(author_id) = &request.author_id {
-            query_parts.push("AND author_id = $author_id".to_string());
-            bindings.push(("author_id".to_string(), author_id.clone()));
+        let keyword_ids: Vec<String> = keyword_results
+            .iter()
+            .map(|result| document_key(&result.document_id).to_string())
+            .collect();
+        let mut candidates: HashMap<String, SearchResult> = HashMap::new();
+        for result in vector_results.into_iter().chain(keyword_results) {
+            candidates.insert(document_key(&result.document_id).to_string(), result);
         }
+        // 向量排名只计入通过筛选的文档
+        let vector_ids: Vec<String> = vector_ids
+            .into_iter()
+            .filter(|id| candidates.contains_key(id))
+            .collect();
 
-        if let Some(tags) = &request.tags {
-            if !tags.is_empty() {
-                let tags_condition = tags.iter()
-                    .enumerate()
-                    .map(|(i, _)| format!("$tag_{} IN tags", i))
-                    .collect::<Vec<_>>()
-                    .join(" OR ");
-                query_parts.push(format!("AND ({})", tags_condition));
-                
-                for (i, tag) in tags.iter().enumerate() {
-                    bindings.push((format!("tag_{}", i), tag.clone()));
-                }
-            }
+        let fused = fuse_rankings(&keyword_ids, &vector_ids, vector_weight);
+        let total = fused.len() as i64;
+        let results = fused
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .filter_map(|(id, score)| {
+                candidates.remove(&id).map(|mut result| {
+                    result.score = score;
+                    result
+                })
+            })
+            .collect();
+
+        Ok(SearchResponse::new(
+            results,
+            total,
+            page,
+            limit,
+            request.query,
+            start_time.elapsed().as_millis() as i64,
+        ))
+    }
+
+    async fn accessible_space_ids(&self, user_id: &str) -> Result<Vec<String>, AppError> {
+        let owned = match &self.space_service {
+            Some(space_service) => space_service.get_owned_space_ids(user_id).await?,
+            None => Vec::new(),
+        };
+        let joined = match &self.space_member_service {
+            Some(space_member_service) => space_member_service.get_user_spaces(user_id).await?,
+            None => Vec::new(),
+        };
+        Ok(merge_space_ids(owned, joined))
+    }
+
+    /// 读取向量命中文档的索引记录，按关键词搜索相同的可见范围和筛选条件过滤，
+    /// 但不要求包含关键词
+    async fn load_vector_candidates(
+        &self,
+        user_id: &str,
+        request: &SearchRequest,
+        scope: &SearchScope<'_>,
+        document_ids: &[String],
+    ) -> Result<Vec<SearchResult>, AppError> {
+        if document_ids.is_empty() {
+            return Ok(Vec::new());
         }
 
+        let filter_request = SearchRequest {
+            query: String::new(),
+            space_id: request.space_id.clone(),
+            tags: request.tags.clone(),
+            author_id: request.author_id.clone(),
+            page: None,
+            limit: None,
+            sort_by: None,
+        };
+        let (where_clause, mut bindings) = build_search_filter(user_id, &filter_request, scope);
+        let things: Vec<Thing> = document_ids
+            .iter()
+            .map(|id| Thing::from(("document", id.as_str())))
+            .collect();
+        bindings.push(("document_ids".to_string(), record_array(things)));
+
+        let full_query = format!("SELECT * FROM search_index {} AND document_id INSIDE $document_ids", where_clause);
+        let mut db_query = self.db.client.query(&full_query);
+        for (key, value) in bindings {
+            db_query = db_query.bind((key, value));
+        }
+        let indexes: Vec<SearchIndex> = db_query.await?.take(0)?;
+
+        Ok(indexes
+            .into_iter()
+            .filter(|index| match scope {
+                SearchScope::Accessible(space_ids) => {
+                    is_visible_in_scope(&index.space_id.id.to_string(), index.is_public, space_ids)
+                }
+                SearchScope::Default => true,
+            })
+            .map(|index| self.to_search_result(index, &request.query))
+            .collect())
+    }
+
+    async fn search_with_scope(
+        &self,
+        user_id: &str,
+        request: SearchRequest,
+        scope: SearchScope<'_>,
+    ) -> Result<SearchResponse, AppError> {
+        let start_time = Instant::now();
+        
+        let (page, limit, offset) = normalize_pagination(request.page, request.limit);
+
+        let (where_clause, bindings) = build_search_filter(user_id, &request, &scope);
+
         // 排序  
         let sort_clause = match request.sort_by.as_ref().unwrap_or(&SearchSortBy::Relevance) {
             SearchSortBy::Relevance => "ORDER BY (title CONTAINSTEXT $query) DESC, last_updated DESC",
@@ -98,149 +295,129 @@ impl SearchService {
             SearchSortBy::UpdatedAt => "ORDER BY last_updated DESC",
             SearchSortBy::Title => "ORDER BY title ASC",
         };
-        query_parts.push(sort_clause.to_string());
 
-        // 分页
-        query_parts.push(format!("LIMIT {} START {}", per_page, offset));
-
-        let full_query = query_parts.join(" ");
+        let full_query = format!(
+            "SELECT * FROM search_index {} {} LIMIT {} START {}",
+            where_clause, sort_clause, limit, offset
+        );
 
         // 执行搜索查询
         let mut db_query = self.db.client.query(&full_query);
-        for (key, value) in bindings {
+        for (key, value) in bindings.clone() {
             db_query = db_query.bind((key, value));
         }
 
         let search_indexes: Vec<SearchIndex> = db_query
-            .await
-            .map_err(|e| ApiError::Database(e))?
-            .take(0)
-            .map_err(|e| ApiError::Database(e))?;
+            .await?
+            .take(0)?;
 
-        // 获取总数
-        let total_count = self.get_search_count(user_id, &request).await?;
+        // 使用相同条件的分组计数查询获取总数
+        let total = self.get_search_count(&where_clause, bindings).await?;
 
         // 转换为搜索结果
         let mut results = Vec::new();
         for index in search_indexes {
-            let highlights = self.generate_highlights(&index, &request.query);
-            let score = self.calculate_relevance_score(&index, &request.query);
-            
-            results.push(SearchResult {
-                document_id: index.document_id.to_string(),
-                space_id: index.space_id.to_string(),
-                title: index.title,
-                excerpt: index.excerpt,
-                tags: index.tags,
-                author_id: index.author_id,
-                last_updated: index.last_updated,
-                score,
-                highlights,
-            });
+            // 查询条件之外再做一次可见性校验，确保无权访问的私有文档不会出现在结果中
+            if let SearchScope::Accessible(space_ids) = &scope {
+                if !is_visible_in_scope(&index.space_id.id.to_string(), index.is_public, space_ids) {
+                    continue;
+                }
+            }
+
+            results.push(self.to_search_result(index, &request.query));
+        }
+
+        // 按相关度排序时以得分为准，标题命中的结果始终排在仅正文命中的结果之前
+        if matches!(request.sort_by.as_ref().unwrap_or(&SearchSortBy::Relevance), SearchSortBy::Relevance) {
+            results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
         }
 
         let took = start_time.elapsed().as_millis() as i64;
 
         Ok(SearchResponse::new(
             results,
-            total_count,
+            total,
             page,
-            per_page,
+            limit,
             request.query,
             took,
         ))
     }
 
-    async fn get_search_count(&self, user_id: &str, request: &SearchRequest) -> Result<i64, ApiError> {
-        let mut query_parts = vec![
-            "SELECT count() FROM search_index".to_string(),
-            "WHERE is_public = true OR author_id = $user_id".to_string(),
-        ];
-
-        let mut bindings: Vec<(String, String)> = vec![("user_id".to_string(), user_id.to_string())];
-
-        if !request.query.is_empty() {
-            query_parts.push("AND (title CONTAINSTEXT $query OR content CONTAINSTEXT $query)".to_string());
-            bindings.push(("query".to_string(), request.query.clone()));
-        }
-
-        if let Some(space_id) = &request.space_id {
-            query_parts.push("AND space_id = $space_id".to_string());
-            bindings.push(("space_id".to_string(), space_id.clone()));
-        }
-
-        if let Some(author_id) = &request.author_id {
-            query_parts.push("AND author_id = $author_id".to_string());
-            bindings.push(("author_id".to_string(), author_id.clone()));
-        }
-
-        if let Some(tags) = &request.tags {
-            if !tags.is_empty() {
-                let tags_condition = tags.iter()
-                    .enumerate()
-                    .map(|(i, _)| format!("$tag_{} IN tags", i))
-                    .collect::<Vec<_>>()
-                    .join(" OR ");
-                query_parts.push(format!("AND ({})", tags_condition));
-                
-                for (i, tag) in tags.iter().enumerate() {
-                    bindings.push((format!("tag_{}", i), tag.clone()));
-                }
-            }
-        }
-
-        query_parts.push("GROUP ALL".to_string());
-        let full_query = query_parts.join(" ");
+    async fn get_search_count(
+        &self,
+        where_clause: &str,
+        bindings: Vec<(String, Value)>,
+    ) -> Result<i64, AppError> {
+        let full_query = format!("SELECT count() AS total FROM search_index {} GROUP ALL", where_clause);
 
         let mut db_query = self.db.client.query(&full_query);
         for (key, value) in bindings {
             db_query = db_query.bind((key, value));
         }
 
-        let result: Vec<surrealdb::sql::Value> = db_query
-            .await
-            .map_err(|e| ApiError::Database(e))?
-            .take(0)
-            .map_err(|e| ApiError::Database(e))?;
+        let result: Vec<serde_json::Value> = db_query
+            .await?
+            .take(0)?;
 
         let count = result
             .first()
-            .and_then(|v| v.to_string().parse::<i64>().ok())
+            .and_then(|v| v.get("total"))
+            .and_then(|v| v.as_i64())
             .unwrap_or(0);
 
         Ok(count)
     }
 
+    fn to_search_result(&self, index: SearchIndex, query: &str) -> SearchResult {
+        let highlights = self.generate_highlights(&index, query);
+        let score = self.calculate_relevance_score(&index, query);
+        let snippet = build_snippet(&index.content, query, SNIPPET_MAX_CHARS);
+
+        SearchResult {
+            document_id: index.document_id.to_string(),
+            space_id: index.space_id.to_string(),
+            title: index.title,
+            excerpt: index.excerpt,
+            tags: index.tags,
+            author_id: index.author_id,
+            last_updated: index.last_updated,
+            score,
+            snippet,
+            highlights,
+        }
+    }
+
     fn generate_highlights(&self, index: &SearchIndex, query: &str) -> Vec<SearchHighlight> {
         let mut highlights = Vec::new();
-        
-        if query.is_empty() {
+        let terms = search_terms(query);
+        if terms.is_empty() {
             return highlights;
         }
 
-        let query_lower = query.to_lowercase();
-        
         // 在标题中查找高亮
-        if let Some(pos) = index.title.to_lowercase().find(&query_lower) {
+        let title: Vec<char> = index.title.chars().collect();
+        if let Some((pos, len)) = find_first_term(&title, &terms) {
             highlights.push(SearchHighlight {
                 field: "title".to_string(),
                 text: index.title.clone(),
                 start: pos,
-                end: pos + query.len(),
+                end: pos + len,
             });
         }
 
-        // 在内容中查找高亮
-        if let Some(pos) = index.content.to_lowercase().find(&query_lower) {
+        // 在内容中查找高亮，截取命中位置前后各 50 个字符
+        let content: Vec<char> = index.content.chars().collect();
+        if let Some((pos, len)) = find_first_term(&content, &terms) {
             let start = pos.saturating_sub(50);
-            let end = (pos + query.len() + 50).min(index.content.len());
-            let excerpt = &index.content[start..end];
-            
+            let end = (pos + len + 50).min(content.len());
+            let excerpt: String = content[start..end].iter().collect();
+
             highlights.push(SearchHighlight {
                 field: "content".to_string(),
                 text: format!("...{}...", excerpt),
-                start: pos - start,
-                end: pos - start + query.len(),
+                start: pos - start + 3,
+                end: pos - start + 3 + len,
             });
         }
 
@@ -248,45 +425,10 @@ impl SearchService {
     }
 
     fn calculate_relevance_score(&self, index: &SearchIndex, query: &str) -> f64 {
-        if query.is_empty() {
-            return 0.0;
-        }
-
-        let mut score = 0.0;
-        let query_lower = query.to_lowercase();
-
-        // 标题匹配权重更高
-        if index.title.to_lowercase().contains(&query_lower) {
-            score += 10.0;
-            
-            // 完全匹配标题权重最高
-            if index.title.to_lowercase() == query_lower {
-                score += 20.0;
-            }
-        }
-
-        // 内容匹配
-        let content_lower = index.content.to_lowercase();
-        let matches = content_lower.matches(&query_lower).count();
-        score += matches as f64 * 1.0;
-
-        // 标签匹配
-        for tag in &index.tags {
-            if tag.to_lowercase().contains(&query_lower) {
-                score += 5.0;
-            }
-        }
-
-        // 最近更新的文档得分略高
-        let days_since_update = (chrono::Utc::now().timestamp() - index.last_updated.timestamp()) / 86400;
-        if days_since_update < 30 {
-            score += 1.0;
-        }
-
-        score
+        relevance_score(index, query, chrono::Utc::now().timestamp())
     }
 
-    pub async fn suggest_search_terms(&self, user_id: &str, prefix: &str, limit: i64) -> Result<Vec<String>, ApiError> {
+    pub async fn suggest_search_terms(&self, user_id: &str, prefix: &str, limit: i64) -> Result<Vec<String>, AppError> {
         let query = "
             SELECT title, tags FROM search_index 
             WHERE is_public = true OR author_id = $user_id
@@ -299,10 +441,8 @@ impl SearchService {
             .bind(("user_id", user_id))
             .bind(("prefix", prefix))
             .bind(("limit", limit))
-            .await
-            .map_err(|e| ApiError::Database(e))?
-            .take(0)
-            .map_err(|e| ApiError::Database(e))?;
+            .await?
+            .take(0)?;
 
         let mut suggestions = Vec::new();
         
@@ -335,7 +475,10 @@ impl SearchService {
         tags: Vec<String>,
         author_id: &str,
         is_public: bool,
-    ) -> Result<(), ApiError> {
+    ) -> Result<(), AppError> {
+        // 调用方传入的ID可能带表前缀，统一去掉后再构造记录ID，才能与按 Thing 绑定的筛选条件匹配
+        let document_id = document_key(document_id);
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
         let index = SearchIndex::new(
             Thing::from(("document", document_id)),
             Thing::from(("space", space_id)),
@@ -350,7 +493,7 @@ impl SearchService {
         self.create_or_update_index(index).await
     }
 
-    pub async fn bulk_reindex(&self) -> Result<i64, ApiError> {
+    pub async fn bulk_reindex(&self) -> Result<i64, AppError> {
         // 获取所有文档并重建索引
         let query = "
             SELECT id, space_id, title, content, author_id, created_at, updated_at
@@ -360,10 +503,8 @@ impl SearchService {
 
         let documents: Vec<surrealdb::sql::Value> = self.db.client
             .query(query)
-            .await
-            .map_err(|e| ApiError::Database(e))?
-            .take(0)
-            .map_err(|e| ApiError::Database(e))?;
+            .await?
+            .take(0)?;
 
         let mut indexed_count = 0;
 
@@ -377,4 +518,514 @@ impl SearchService {
 
         Ok(indexed_count)
     }
-}
\ No newline at end of file
+}
+
+/// 构建搜索条件，分页查询和总数统计共用同一份 WHERE 子句和绑定参数。
+/// 绑定值使用 SurrealDB 的 Value，记录ID以 Thing 形式传入，才能和 search_index 中存储的记录比较
+fn build_search_filter(
+    user_id: &str,
+    request: &SearchRequest,
+    scope: &SearchScope<'_>,
+) -> (String, Vec<(String, Value)>) {
+    let mut conditions = Vec::new();
+    let mut bindings: Vec<(String, Value)> = Vec::new();
+
+    match scope {
+        SearchScope::Default => {
+            conditions.push("(is_public = true OR author_id = $user_id)".to_string());
+            bindings.push(("user_id".to_string(), user_id.into()));
+        }
+        SearchScope::Accessible(space_ids) => {
+            conditions.push("(space_id INSIDE $accessible_space_ids OR is_public = true)".to_string());
+            let things: Vec<Thing> = space_ids
+                .iter()
+                .map(|id| Thing::from(("space", id.as_str())))
+                .collect();
+            bindings.push(("accessible_space_ids".to_string(), record_array(things)));
+        }
+    }
+
+    if !request.query.is_empty() {
+        conditions.push("(title CONTAINSTEXT $query OR content CONTAINSTEXT $query)".to_string());
+        bindings.push(("query".to_string(), request.query.clone().into()));
+    }
+
+    if let Some(space_id) = &request.space_id {
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+        conditions.push("space_id = $space_id".to_string());
+        bindings.push(("space_id".to_string(), Thing::from(("space", space_id)).into()));
+    }
+
+    if let Some(author_id) = &request.author_id {
+        conditions.push("author_id = $author_id".to_string());
+        bindings.push(("author_id".to_string(), author_id.clone().into()));
+    }
+
+    if let Some(tags) = &request.tags {
+        if !tags.is_empty() {
+            let tags_condition = tags.iter()
+                .enumerate()
+                .map(|(i, _)| format!("$tag_{} IN tags", i))
+                .collect::<Vec<_>>()
+                .join(" OR ");
+            conditions.push(format!("({})", tags_condition));
+
+            for (i, tag) in tags.iter().enumerate() {
+                bindings.push((format!("tag_{}", i), tag.clone().into()));
+            }
+        }
+    }
+
+    (format!("WHERE {}", conditions.join(" AND ")), bindings)
+}
+
+/// 把记录ID列表转换为 SurrealDB 数组，用于 INSIDE 条件
+fn record_array(things: Vec<Thing>) -> Value {
+    Value::Array(things.into_iter().map(Value::from).collect::<Vec<Value>>().into())
+}
+
+/// 规范化分页参数，返回 (page, limit, offset)。搜索请求的分页参数为 i64，负数按 0 处理
+fn normalize_pagination(page: Option<i64>, limit: Option<i64>) -> (i64, i64, i64) {
+    let to_u32 = |value: i64| u32::try_from(value.max(0)).unwrap_or(u32::MAX);
+    let page = PageRequest::new(page.map(to_u32), limit.map(to_u32));
+    (page.page.into(), page.limit.into(), page.offset().into())
+}
+
+/// 合并用户拥有和已加入的空间ID，去重并保持顺序
+fn merge_space_ids(owned: Vec<String>, joined: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    owned
+        .into_iter()
+        .chain(joined)
+        .map(|id| id.strip_prefix("space:").map(str::to_string).unwrap_or(id))
+        .filter(|id| seen.insert(id.clone()))
+        .collect()
+}
+
+/// 文档在“可访问范围”搜索中是否可见：公开文档，或位于用户可访问的空间中
+fn is_visible_in_scope(space_id: &str, is_public: bool, accessible_space_ids: &[String]) -> bool {
+    let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+    is_public || accessible_space_ids.iter().any(|id| id == space_id)
+}
+
+/// 去掉 document: 前缀，关键词和向量两路的文档 ID 按此比较
+fn document_key(document_id: &str) -> &str {
+    document_id.strip_prefix("document:").unwrap_or(document_id)
+}
+
+/// 倒数排名融合：文档得分为各路 weight / (RRF_K + 排名) 之和，排名从 1 开始。
+/// 只用排名、不用原始分数，关键词得分和余弦相似度不需要换算到同一量纲。
+/// 得分相同时关键词结果在前；权重为 0 的一路不参与排名
+fn fuse_rankings(keyword_ids: &[String], vector_ids: &[String], vector_weight: f64) -> Vec<(String, f64)> {
+    let mut fused: Vec<(String, f64)> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+
+    for (ids, weight) in [(keyword_ids, 1.0 - vector_weight), (vector_ids, vector_weight)] {
+        if weight <= 0.0 {
+            continue;
+        }
+        for (rank, id) in ids.iter().enumerate() {
+            let score = weight / (RRF_K + rank as f64 + 1.0);
+            match positions.get(id.as_str()) {
+                Some(&position) => fused[position].1 += score,
+                None => {
+                    positions.insert(id, fused.len());
+                    fused.push((id.clone(), score));
+                }
+            }
+        }
+    }
+
+    fused.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    fused
+}
+
+/// 搜索结果片段的最大字符数
+const SNIPPET_MAX_CHARS: usize = 160;
+/// 片段边界向前/向后寻找空白字符的最大距离，超过则直接在字符边界截断（如中日韩文本）
+const WORD_BOUNDARY_LOOKAHEAD: usize = 15;
+const TITLE_MATCH_SCORE: f64 = 100.0;
+const MAX_CONTENT_MATCHES: usize = 20;
+const MAX_TAG_MATCHES: usize = 3;
+
+/// 计算相关度得分：标题命中的基础分高于正文、标签得分上限之和，保证标题命中排在前面
+fn relevance_score(index: &SearchIndex, query: &str, now: i64) -> f64 {
+    let terms = search_terms(query);
+    if terms.is_empty() {
+        return 0.0;
+    }
+
+    let mut score = 0.0;
+    let query_lower = query.trim().to_lowercase();
+
+    let title_lower = index.title.to_lowercase();
+    if terms.iter().any(|term| title_lower.contains(term.as_str())) {
+        score += TITLE_MATCH_SCORE;
+
+        // 完全匹配标题权重最高
+        if title_lower == query_lower {
+            score += 50.0;
+        }
+    }
+
+    // 内容匹配，次数封顶避免长文档刷分
+    let content_lower = index.content.to_lowercase();
+    let matches: usize = terms.iter().map(|term| content_lower.matches(term.as_str()).count()).sum();
+    score += matches.min(MAX_CONTENT_MATCHES) as f64;
+
+    // 标签匹配
+    let tag_matches = index.tags
+        .iter()
+        .filter(|tag| {
+            let tag = tag.to_lowercase();
+            terms.iter().any(|term| tag.contains(term.as_str()))
+        })
+        .count();
+    score += tag_matches.min(MAX_TAG_MATCHES) as f64 * 5.0;
+
+    // 最近更新的文档得分略高
+    let days_since_update = (now - index.last_updated.timestamp()) / 86400;
+    if days_since_update < 30 {
+        score += 1.0;
+    }
+
+    score
+}
+
+/// 将查询拆分为小写的检索词，长词优先以便高亮时优先匹配更长的词
+fn search_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| term.to_lowercase())
+        .collect();
+    terms.sort_by(|a, b| b.chars().count().cmp(&a.chars().count()));
+    terms.dedup();
+    terms
+}
+
+/// 逐字符小写，保证与原文字符一一对应，便于按字符偏移截取
+fn lower_char(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// 判断 text 在 pos 处是否以某个检索词开头，返回命中词的字符长度
+fn term_at(text: &[char], pos: usize, terms: &[Vec<char>]) -> Option<usize> {
+    terms.iter().find_map(|term| {
+        let end = pos + term.len();
+        if term.is_empty() || end > text.len() {
+            return None;
+        }
+        text[pos..end]
+            .iter()
+            .zip(term)
+            .all(|(a, b)| lower_char(*a) == *b)
+            .then_some(term.len())
+    })
+}
+
+fn term_chars(terms: &[String]) -> Vec<Vec<char>> {
+    terms.iter().map(|term| term.chars().map(lower_char).collect()).collect()
+}
+
+/// 查找第一个命中的检索词，返回 (字符偏移, 字符长度)
+fn find_first_term(text: &[char], terms: &[String]) -> Option<(usize, usize)> {
+    let terms = term_chars(terms);
+    (0..text.len()).find_map(|pos| term_at(text, pos, &terms).map(|len| (pos, len)))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 围绕正文中第一个命中位置截取片段，命中词用 <mark> 包裹。
+/// 按字符而非字节处理，对中日韩文本安全；有空白时尽量在单词边界截断。
+fn build_snippet(content: &str, query: &str, max_chars: usize) -> String {
+    let chars: Vec<char> = content.split_whitespace().collect::<Vec<_>>().join(" ").chars().collect();
+    if chars.is_empty() {
+        return String::new();
+    }
+
+    let terms = search_terms(query);
+    let term_chars = term_chars(&terms);
+    let hit = (0..chars.len()).find_map(|pos| term_at(&chars, pos, &term_chars).map(|len| (pos, len)));
+
+    let (mut start, mut end) = match hit {
+        Some((pos, len)) => {
+            let context = max_chars.saturating_sub(len) / 2;
+            let start = pos.saturating_sub(context);
+            (start, (start + max_chars).max(pos + len).min(chars.len()))
+        }
+        None => (0, max_chars.min(chars.len())),
+    };
+
+    // 起点不在开头时跳过被截断的半个单词
+    if start > 0 && !chars[start - 1].is_whitespace() {
+        let limit = hit.map(|(pos, _)| pos).unwrap_or(chars.len()).min(start + WORD_BOUNDARY_LOOKAHEAD);
+        if let Some(offset) = chars[start..limit].iter().position(|c| c.is_whitespace()) {
+            start += offset + 1;
+        }
+    }
+    // 终点不在结尾时回退到上一个空白处
+    if end < chars.len() && !chars[end].is_whitespace() {
+        let limit = hit.map(|(pos, len)| pos + len).unwrap_or(start).max(end.saturating_sub(WORD_BOUNDARY_LOOKAHEAD));
+        if let Some(offset) = chars[limit..end].iter().rposition(|c| c.is_whitespace()) {
+            end = limit + offset;
+        }
+    }
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let mut pos = start;
+    let mut plain = String::new();
+    while pos < end {
+        match term_at(&chars, pos, &term_chars).filter(|len| pos + len <= end) {
+            Some(len) => {
+                snippet.push_str(&escape_html(&plain));
+                plain.clear();
+                let matched: String = chars[pos..pos + len].iter().collect();
+                snippet.push_str("<mark>");
+                snippet.push_str(&escape_html(&matched));
+                snippet.push_str("</mark>");
+                pos += len;
+            }
+            None => {
+                plain.push(chars[pos]);
+                pos += 1;
+            }
+        }
+    }
+    snippet.push_str(&escape_html(plain.trim_end()));
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accessible_scope_hides_private_documents_in_other_spaces() {
+        // 用户拥有空间 A，同时是空间 A 的成员（重复记录应被合并），不属于空间 B
+        let space_ids = merge_space_ids(
+            vec!["space:space_a".to_string()],
+            vec!["space_a".to_string()],
+        );
+        assert_eq!(space_ids, vec!["space_a".to_string()]);
+
+        assert!(is_visible_in_scope("space:space_a", false, &space_ids));
+        assert!(!is_visible_in_scope("space:space_b", false, &space_ids));
+        // 空间 B 中公开的文档仍然可以被搜索到
+        assert!(is_visible_in_scope("space:space_b", true, &space_ids));
+    }
+
+    #[test]
+    fn test_accessible_filter_restricts_to_member_spaces() {
+        let space_ids = vec!["space_a".to_string()];
+        let request = SearchRequest::new("rust".to_string());
+
+        let (where_clause, bindings) = build_search_filter("user1", &request, &SearchScope::Accessible(&space_ids));
+
+        assert!(where_clause.starts_with("WHERE (space_id INSIDE $accessible_space_ids OR is_public = true) AND "));
+        assert!(!where_clause.contains("author_id = $user_id"));
+        let (_, accessible) = bindings.iter().find(|(key, _)| key == "accessible_space_ids").unwrap();
+        assert_eq!(accessible, &Value::Array(vec![Value::from(Thing::from(("space", "space_a")))].into()));
+    }
+
+    fn test_user(name: &str, run: &str) -> crate::services::auth::User {
+        crate::services::auth::User {
+            id: format!("{}-{}", name, run),
+            email: format!("{}@example.com", name),
+            roles: vec!["user".to_string()],
+            permissions: vec![],
+            profile: None,
+            api_key: None,
+        }
+    }
+
+    /// 创建私有空间和一篇私有文档，并通过邀请把 member 加入空间，返回空间ID
+    async fn create_private_space_with_member(
+        state: &crate::state::AppState,
+        owner: &crate::services::auth::User,
+        member: &crate::services::auth::User,
+        slug: &str,
+        document: serde_json::Value,
+    ) -> String {
+        let space = state.space_service.create_space(crate::models::space::CreateSpaceRequest {
+            name: "Private".to_string(),
+            slug: slug.to_string(),
+            description: None,
+            avatar_url: None,
+            is_public: Some(false),
+            settings: None,
+        }, owner).await.unwrap();
+        let request = serde_json::from_value(document).unwrap();
+        state.document_service.create_document(&space.id, &owner.id, request).await.unwrap();
+
+        let invitation = state.space_member_service.invite_member(&space.id, owner, crate::models::space_member::InviteMemberRequest {
+            email: None,
+            user_id: Some(member.id.clone()),
+            role: crate::models::space_member::MemberRole::Viewer,
+            message: None,
+            expires_in_days: None,
+            max_uses: None,
+        }).await.unwrap();
+        state.space_member_service.accept_invitation(&member.id, crate::models::space_member::AcceptInvitationRequest {
+            invite_token: invitation.invite_token,
+        }).await.unwrap();
+
+        space.id
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_member_finds_private_document_and_outsider_does_not() {
+        let state = crate::state::test_support::test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let owner = test_user("owner", &run);
+        let member = test_user("member", &run);
+        let outsider = test_user("outsider", &run);
+        let keyword = format!("kw{}", run);
+        let space_id = create_private_space_with_member(
+            &state,
+            &owner,
+            &member,
+            &format!("search-{}", run),
+            serde_json::json!({ "title": format!("Private {}", keyword), "slug": "private", "content": keyword }),
+        ).await;
+
+        let found = state.search_service
+            .search_all_accessible(&member.id, SearchRequest::new(keyword.clone()))
+            .await
+            .unwrap();
+        assert_eq!(found.total, 1);
+        assert_eq!(found.results.len(), 1);
+
+        // 按空间筛选时同样能命中
+        let mut in_space = SearchRequest::new(keyword.clone());
+        in_space.space_id = Some(space_id);
+        let found_in_space = state.search_service.search_all_accessible(&member.id, in_space).await.unwrap();
+        assert_eq!(found_in_space.results.len(), 1);
+
+        let hidden = state.search_service
+            .search_all_accessible(&outsider.id, SearchRequest::new(keyword))
+            .await
+            .unwrap();
+        assert_eq!(hidden.total, 0);
+        assert!(hidden.results.is_empty());
+    }
+
+    #[test]
+    fn test_second_page_slice_and_stable_total() {
+        let all: Vec<i64> = (0..25).collect();
+
+        let (page, limit, offset) = normalize_pagination(Some(2), Some(10));
+        let slice: Vec<i64> = all.iter().skip(offset as usize).take(limit as usize).copied().collect();
+        assert_eq!(slice, (10..20).collect::<Vec<_>>());
+
+        let response = SearchResponse::new(Vec::new(), all.len() as i64, page, limit, "q".to_string(), 0);
+        assert_eq!((response.total, response.page, response.limit, response.total_pages), (25, 2, 10, 3));
+
+        // 总数与页码无关
+        let (page, limit, _) = normalize_pagination(Some(3), Some(10));
+        let last = SearchResponse::new(Vec::new(), all.len() as i64, page, limit, "q".to_string(), 0);
+        assert_eq!(last.total, response.total);
+    }
+
+    #[test]
+    fn test_pagination_is_clamped() {
+        assert_eq!(normalize_pagination(None, None), (1, 20, 0));
+        assert_eq!(normalize_pagination(Some(0), Some(0)), (1, 1, 0));
+        assert_eq!(normalize_pagination(Some(-3), Some(1000)), (1, 100, 0));
+    }
+
+    #[test]
+    fn test_snippet_marks_terms_around_first_hit() {
+        let content = format!("{} the quick brown fox jumps over the lazy dog {}", "lorem ipsum ".repeat(30), "dolor sit ".repeat(30));
+        let snippet = build_snippet(&content, "Fox", 60);
+
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.ends_with('…'));
+        assert!(snippet.contains("<mark>fox</mark>"));
+        // 在单词边界截断，不出现半个单词
+        let inner = snippet.trim_matches('…');
+        assert!(inner.split(' ').all(|word| ["lorem", "ipsum", "dolor", "sit", "the", "quick", "brown", "<mark>fox</mark>", "jumps", "over", "lazy", "dog"].contains(&word)));
+    }
+
+    #[test]
+    fn test_snippet_is_utf8_safe_for_cjk() {
+        let content = format!("{}全文搜索{}", "这是一段很长的中文内容".repeat(20), "后面还有更多文字".repeat(20));
+        let snippet = build_snippet(&content, "全文搜索", 40);
+
+        assert!(snippet.contains("<mark>全文搜索</mark>"));
+        assert!(snippet.chars().filter(|c| *c != '…').count() <= 40 + "<mark></mark>".len());
+    }
+
+    #[test]
+    fn test_snippet_escapes_html_and_handles_no_hit() {
+        assert_eq!(build_snippet("a <b> & c", "b", 100), "a &lt;<mark>b</mark>&gt; &amp; c");
+        assert_eq!(build_snippet("short text", "missing", 100), "short text");
+        assert_eq!(build_snippet("", "anything", 100), "");
+    }
+
+    #[test]
+    fn test_hybrid_fusion_surfaces_keyword_and_semantic_winners() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        // exact 只在关键词搜索中排第一，semantic 只在向量搜索中排第一，shared 两路都排在末尾
+        let keyword = ids(&["exact", "k1", "k2", "k3", "shared"]);
+        let vector = ids(&["semantic", "v1", "v2", "v3", "shared"]);
+
+        let fused = fuse_rankings(&keyword, &vector, 0.5);
+        let top: Vec<&str> = fused.iter().take(3).map(|(id, _)| id.as_str()).collect();
+        assert!(top.contains(&"exact") && top.contains(&"semantic"));
+        // 两路结果合并去重
+        assert_eq!(fused.len(), 9);
+
+        let position = |fused: &[(String, f64)], id: &str| fused.iter().position(|(doc, _)| doc == id).unwrap();
+        let keyword_heavy = fuse_rankings(&keyword, &vector, 0.2);
+        assert!(position(&keyword_heavy, "exact") < position(&keyword_heavy, "semantic"));
+        let vector_heavy = fuse_rankings(&keyword, &vector, 0.8);
+        assert!(position(&vector_heavy, "semantic") < position(&vector_heavy, "exact"));
+
+        // 权重为 0 时退化为纯关键词排名
+        let keyword_only = fuse_rankings(&keyword, &vector, 0.0);
+        assert_eq!(keyword_only.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>(), keyword);
+    }
+
+    #[test]
+    fn test_title_match_outranks_body_only_match() {
+        let title_only = SearchIndex::new(
+            Thing::from(("document", "a")),
+            Thing::from(("space", "s")),
+            "Rust guide".to_string(),
+            "nothing relevant".to_string(),
+            String::new(),
+            "user".to_string(),
+        );
+        let body_heavy = SearchIndex::new(
+            Thing::from(("document", "b")),
+            Thing::from(("space", "s")),
+            "Other".to_string(),
+            "rust ".repeat(100),
+            String::new(),
+            "user".to_string(),
+        )
+        .with_tags(vec!["rust".to_string(), "rust-lang".to_string(), "rusty".to_string()]);
+
+        let now = chrono::Utc::now().timestamp();
+        assert!(relevance_score(&title_only, "rust", now) > relevance_score(&body_heavy, "rust", now));
+    }
+}
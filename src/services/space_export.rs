@@ -0,0 +1,640 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::Utc;
+use surrealdb::sql::Thing;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::comment::Comment,
+    models::document::{Document, DocumentDb},
+    models::space::{Space, SpaceDb, SpaceResponse, RESERVED_SPACE_SLUGS},
+    models::space_export::{
+        BundleComment, BundleDocument, BundleSpace, BundleTag, SpaceBundle, SpaceImportResult, SPACE_BUNDLE_VERSION,
+    },
+    models::tag::{DocumentTag, Tag},
+    services::{database::Database, documents::resolve_slug_collisions, search::SearchService},
+};
+
+/// 空间的完整导出与导入，用于备份和在实例之间迁移
+#[derive(Clone)]
+pub struct SpaceExportService {
+    db: Arc<Database>,
+    search_service: Option<Arc<SearchService>>,
+}
+
+impl SpaceExportService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db, search_service: None }
+    }
+
+    pub fn with_search_service(mut self, search_service: Arc<SearchService>) -> Self {
+        self.search_service = Some(search_service);
+        self
+    }
+
+    /// 导出空间、未删除的文档（含树结构）、标签和评论
+    pub async fn export_space(&self, space_id: &str) -> Result<SpaceBundle, AppError> {
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+        let space_thing = Thing::from(("space", space_id));
+
+        let mut response = self.db.client
+            .query(
+                "SELECT * FROM type::thing('space', $space_id) WHERE is_deleted != true;
+                 SELECT * FROM document WHERE space_id = $space AND is_deleted = false ORDER BY order_index ASC, created_at ASC;
+                 SELECT * FROM document_tag WHERE document_id.space_id = $space AND document_id.is_deleted = false;
+                 SELECT * FROM tag WHERE space_id = $space
+                     OR id INSIDE (SELECT VALUE tag_id FROM document_tag WHERE document_id.space_id = $space);
+                 SELECT * FROM comment WHERE document_id.space_id = $space AND is_deleted = false ORDER BY created_at ASC;",
+            )
+            .bind(("space_id", space_id))
+            .bind(("space", space_thing))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let spaces: Vec<SpaceDb> = response.take(0).map_err(|e| AppError::database_error(e.to_string()))?;
+        let documents: Vec<DocumentDb> = response.take(1).map_err(|e| AppError::database_error(e.to_string()))?;
+        let document_tags: Vec<DocumentTag> = response.take(2).map_err(|e| AppError::database_error(e.to_string()))?;
+        let tags: Vec<Tag> = response.take(3).map_err(|e| AppError::database_error(e.to_string()))?;
+        let comments: Vec<Comment> = response.take(4).map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let space: Space = spaces
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::NotFound("Space not found".to_string()))?
+            .into();
+        let documents: Vec<Document> = documents.into_iter().map(Into::into).collect();
+
+        let bundle = build_bundle(&space, documents, tags, &document_tags, comments);
+        info!(
+            "Exported space {} with {} documents, {} tags and {} comments",
+            space_id,
+            bundle.documents.len(),
+            bundle.tags.len(),
+            bundle.comments.len()
+        );
+        Ok(bundle)
+    }
+
+    /// 以新的 id 重建导出包中的空间，归属于 owner_id。
+    /// 空间 slug 已被占用时追加后缀；所有记录在同一个事务中创建
+    pub async fn import_space(&self, bundle: SpaceBundle, owner_id: &str) -> Result<SpaceImportResult, AppError> {
+        let taken_slugs: Vec<String> = self.db.client
+            .query("SELECT VALUE slug FROM space WHERE string::starts_with(slug, $slug)")
+            .bind(("slug", bundle.space.slug.clone()))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        // 保留的 slug 同样视为已占用
+        let taken_slugs: HashSet<String> = taken_slugs
+            .into_iter()
+            .chain(RESERVED_SPACE_SLUGS.iter().map(|slug| slug.to_string()))
+            .collect();
+
+        let (space_id, bundle) = remap_bundle(bundle, &taken_slugs, new_record_id)?;
+        let (sql, bindings) = build_import_statements(&space_id, owner_id, &bundle);
+
+        self.db.client
+            .query(sql)
+            .bind(bindings)
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| {
+                AppError::from_unique_violation(
+                    e,
+                    "space_slug_unique_idx",
+                    "Space slug already exists globally. Please retry the import.",
+                )
+            })?;
+
+        let space: Option<SpaceDb> = self.db.client
+            .select(("space", space_id.as_str()))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        let space = space.ok_or_else(|| AppError::internal_server_error("Failed to import space".to_string()))?;
+
+        // 导入的文档不经过 DocumentService，需要单独加入搜索索引
+        if let Some(search_service) = &self.search_service {
+            let tag_names: HashMap<&str, &str> = bundle.tags.iter().map(|tag| (tag.id.as_str(), tag.name.as_str())).collect();
+            for document in &bundle.documents {
+                let tags = document
+                    .tag_ids
+                    .iter()
+                    .filter_map(|id| tag_names.get(id.as_str()).map(|name| name.to_string()))
+                    .collect();
+                if let Err(e) = search_service
+                    .update_document_index(
+                        &document.id,
+                        &space_id,
+                        &document.title,
+                        &document.content,
+                        document.excerpt.as_deref().unwrap_or_default(),
+                        tags,
+                        owner_id,
+                        document.is_public,
+                    )
+                    .await
+                {
+                    warn!("Failed to index imported document {}: {}", document.id, e);
+                }
+            }
+        }
+
+        info!(
+            "Imported space {} as {} with {} documents",
+            bundle.space.name,
+            bundle.space.slug,
+            bundle.documents.len()
+        );
+
+        Ok(SpaceImportResult {
+            space: SpaceResponse::from(Space::from(space)),
+            document_count: bundle.documents.len(),
+            tag_count: bundle.tags.len(),
+            comment_count: bundle.comments.len(),
+        })
+    }
+}
+
+fn new_record_id() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// 由数据库记录生成导出包。父文档不在导出范围内的文档作为根文档，
+/// 所属文档未导出的评论和标签关联会被丢弃
+fn build_bundle(
+    space: &Space,
+    documents: Vec<Document>,
+    tags: Vec<Tag>,
+    document_tags: &[DocumentTag],
+    comments: Vec<Comment>,
+) -> SpaceBundle {
+    let document_ids: HashSet<String> = documents.iter().filter_map(|doc| doc.id.clone()).collect();
+
+    let mut tag_ids: HashMap<String, Vec<String>> = HashMap::new();
+    for relation in document_tags {
+        tag_ids
+            .entry(relation.document_id.to_string())
+            .or_default()
+            .push(relation.tag_id.to_string());
+    }
+
+    let documents: Vec<BundleDocument> = documents
+        .into_iter()
+        .filter_map(|doc| {
+            let id = doc.id?;
+            Some(BundleDocument {
+                tag_ids: tag_ids.remove(&id).unwrap_or_default(),
+                parent_id: doc.parent_id.filter(|parent| document_ids.contains(parent)),
+                id,
+                title: doc.title,
+                slug: doc.slug,
+                content: doc.content,
+                excerpt: doc.excerpt,
+                is_public: doc.is_public,
+                order_index: doc.order_index,
+                author_id: doc.author_id,
+                word_count: doc.word_count,
+                reading_time: doc.reading_time,
+                metadata: doc.metadata,
+                created_at: doc.created_at,
+                updated_at: doc.updated_at,
+            })
+        })
+        .collect();
+    let documents = parents_first(documents).unwrap_or_else(|unordered| unordered);
+
+    let tags = tags
+        .into_iter()
+        .filter_map(|tag| {
+            Some(BundleTag {
+                id: tag.id?.to_string(),
+                name: tag.name,
+                slug: tag.slug,
+                description: tag.description,
+                color: tag.color,
+            })
+        })
+        .collect();
+
+    let comment_ids: HashSet<String> = comments.iter().filter_map(|comment| comment.id.clone()).collect();
+    let comments = comments
+        .into_iter()
+        .filter(|comment| document_ids.contains(&comment.document_id))
+        .filter_map(|comment| {
+            Some(BundleComment {
+                id: comment.id?,
+                document_id: comment.document_id,
+                parent_id: comment.parent_id.filter(|parent| comment_ids.contains(parent)),
+                author_id: comment.author_id,
+                content: comment.content,
+                is_resolved: comment.is_resolved,
+                created_at: comment.created_at,
+            })
+        })
+        .collect();
+
+    SpaceBundle {
+        version: SPACE_BUNDLE_VERSION,
+        exported_at: Utc::now(),
+        space: BundleSpace {
+            name: space.name.clone(),
+            slug: space.slug.clone(),
+            description: space.description.clone(),
+            avatar_url: space.avatar_url.clone(),
+            is_public: space.is_public,
+            settings: space.settings.clone(),
+        },
+        documents,
+        tags,
+        comments,
+    }
+}
+
+/// 调整文档顺序，保证父文档排在子文档之前，其余保持原有顺序。
+/// 存在循环引用时返回 Err，携带原顺序的文档
+fn parents_first(documents: Vec<BundleDocument>) -> Result<Vec<BundleDocument>, Vec<BundleDocument>> {
+    let index: HashMap<&str, usize> = documents.iter().enumerate().map(|(i, doc)| (doc.id.as_str(), i)).collect();
+    let mut order = Vec::with_capacity(documents.len());
+    // 0 未访问，1 正在访问父链，2 已加入
+    let mut state = vec![0u8; documents.len()];
+
+    for start in 0..documents.len() {
+        let mut chain = Vec::new();
+        let mut current = Some(start);
+        while let Some(i) = current {
+            match state[i] {
+                2 => break,
+                1 => return Err(documents),
+                _ => {
+                    state[i] = 1;
+                    chain.push(i);
+                    current = documents[i].parent_id.as_deref().and_then(|parent| index.get(parent).copied());
+                }
+            }
+        }
+        for &i in chain.iter().rev() {
+            state[i] = 2;
+            order.push(i);
+        }
+    }
+
+    let mut slots: Vec<Option<BundleDocument>> = documents.into_iter().map(Some).collect();
+    Ok(order.into_iter().filter_map(|i| slots[i].take()).collect())
+}
+
+/// 为导入包中的所有记录分配新 id，返回新空间的 id 和改写后的导入包。
+/// 校验文档树：父文档必须在包内且不能形成循环；重名的标签合并为一个
+fn remap_bundle(
+    bundle: SpaceBundle,
+    taken_space_slugs: &HashSet<String>,
+    mut new_id: impl FnMut() -> String,
+) -> Result<(String, SpaceBundle), AppError> {
+    if bundle.version != SPACE_BUNDLE_VERSION {
+        return Err(AppError::Validation(format!(
+            "Unsupported bundle version {}, expected {}",
+            bundle.version, SPACE_BUNDLE_VERSION
+        )));
+    }
+
+    let SpaceBundle { exported_at, mut space, documents, tags, comments, .. } = bundle;
+
+    let document_ids: HashSet<&str> = documents.iter().map(|doc| doc.id.as_str()).collect();
+    if document_ids.len() != documents.len() {
+        return Err(AppError::Validation("Bundle contains duplicate document ids".to_string()));
+    }
+    if let Some(orphan) = documents
+        .iter()
+        .find(|doc| doc.parent_id.as_deref().is_some_and(|parent| !document_ids.contains(parent)))
+    {
+        return Err(AppError::Validation(format!("Document {} references a missing parent", orphan.id)));
+    }
+    let mut documents = parents_first(documents)
+        .map_err(|_| AppError::Validation("Bundle document tree contains a cycle".to_string()))?;
+
+    space.slug = resolve_slug_collisions(std::iter::once(space.slug.as_str()), taken_space_slugs).remove(0);
+
+    let mut tag_map: HashMap<String, String> = HashMap::new();
+    let mut tag_names: HashMap<String, String> = HashMap::new();
+    let mut new_tags = Vec::new();
+    for mut tag in tags {
+        if let Some(existing) = tag_names.get(&tag.name) {
+            tag_map.insert(tag.id, existing.clone());
+            continue;
+        }
+        let id = new_id();
+        tag_map.insert(std::mem::replace(&mut tag.id, id.clone()), id.clone());
+        tag_names.insert(tag.name.clone(), id);
+        new_tags.push(tag);
+    }
+    let tag_slugs = resolve_slug_collisions(new_tags.iter().map(|tag| tag.slug.as_str()), &HashSet::new());
+    for (tag, slug) in new_tags.iter_mut().zip(tag_slugs) {
+        tag.slug = slug;
+    }
+
+    let document_map: HashMap<String, String> = documents.iter().map(|doc| (doc.id.clone(), new_id())).collect();
+    let document_slugs = resolve_slug_collisions(documents.iter().map(|doc| doc.slug.as_str()), &HashSet::new());
+    for (document, slug) in documents.iter_mut().zip(document_slugs) {
+        document.id = document_map[&document.id].clone();
+        document.parent_id = document.parent_id.as_ref().map(|parent| document_map[parent].clone());
+        document.slug = slug;
+        let mut seen = HashSet::new();
+        document.tag_ids = document
+            .tag_ids
+            .iter()
+            .filter_map(|id| tag_map.get(id).cloned())
+            .filter(|id| seen.insert(id.clone()))
+            .collect();
+    }
+
+    let comment_map: HashMap<String, String> = comments
+        .iter()
+        .filter(|comment| document_map.contains_key(&comment.document_id))
+        .map(|comment| (comment.id.clone(), new_id()))
+        .collect();
+    let comments = comments
+        .into_iter()
+        .filter_map(|mut comment| {
+            comment.id = comment_map.get(&comment.id)?.clone();
+            comment.document_id = document_map[&comment.document_id].clone();
+            comment.parent_id = comment.parent_id.and_then(|parent| comment_map.get(&parent).cloned());
+            Some(comment)
+        })
+        .collect();
+
+    let remapped = SpaceBundle {
+        version: SPACE_BUNDLE_VERSION,
+        exported_at,
+        space,
+        documents,
+        tags: new_tags,
+        comments,
+    };
+    Ok((new_id(), remapped))
+}
+
+/// 生成导入事务。记录字段通过参数的属性引用，例如 `$doc_0.title`。
+/// 导出包中的作者 ID 无法验证，文档和评论的作者统一改为导入者
+fn build_import_statements(
+    space_id: &str,
+    owner_id: &str,
+    bundle: &SpaceBundle,
+) -> (String, HashMap<String, serde_json::Value>) {
+    let mut sql = String::from("BEGIN TRANSACTION;\n");
+    let mut bindings = HashMap::new();
+    bindings.insert("space_id".to_string(), serde_json::Value::String(space_id.to_string()));
+    bindings.insert("owner_id".to_string(), serde_json::Value::String(owner_id.to_string()));
+    bindings.insert("space".to_string(), serde_json::to_value(&bundle.space).unwrap_or_default());
+
+    sql.push_str(
+        "CREATE type::thing('space', $space_id) SET name = $space.name, slug = $space.slug, \
+         description = $space.description, avatar_url = $space.avatar_url, is_public = $space.is_public, \
+         settings = $space.settings, owner_id = $owner_id, created_by = $owner_id, updated_by = $owner_id;\n",
+    );
+
+    let tag_index: HashMap<&str, usize> = bundle.tags.iter().enumerate().map(|(i, tag)| (tag.id.as_str(), i)).collect();
+    let mut usage: HashMap<&str, i64> = HashMap::new();
+    for (index, document) in bundle.documents.iter().enumerate() {
+        let parent = if document.parent_id.is_some() {
+            format!("type::thing('document', $doc_{}.parent_id)", index)
+        } else {
+            "NONE".to_string()
+        };
+        sql.push_str(&format!(
+            "CREATE type::thing('document', $doc_{index}.id) SET space_id = type::thing('space', $space_id), \
+             parent_id = {parent}, title = $doc_{index}.title, slug = $doc_{index}.slug, content = $doc_{index}.content, \
+             excerpt = $doc_{index}.excerpt, is_public = $doc_{index}.is_public, order_index = $doc_{index}.order_index, \
+             author_id = $owner_id, word_count = $doc_{index}.word_count, \
+             reading_time = $doc_{index}.reading_time, metadata = $doc_{index}.metadata{timestamps};\n",
+            index = index,
+            parent = parent,
+            timestamps = timestamp_assignments(&format!("doc_{}", index), document.created_at.is_some(), document.updated_at.is_some()),
+        ));
+        for tag in document.tag_ids.iter().filter_map(|id| tag_index.get_key_value(id.as_str())) {
+            *usage.entry(tag.0).or_default() += 1;
+            sql.push_str(&format!(
+                "CREATE document_tag SET document_id = type::thing('document', $doc_{index}.id), \
+                 tag_id = type::thing('tag', $tag_{tag}.id), tagged_by = $owner_id;\n",
+                index = index,
+                tag = tag.1,
+            ));
+        }
+        bindings.insert(format!("doc_{}", index), serde_json::to_value(document).unwrap_or_default());
+    }
+
+    for (index, tag) in bundle.tags.iter().enumerate() {
+        sql.push_str(&format!(
+            "CREATE type::thing('tag', $tag_{index}.id) SET space_id = type::thing('space', $space_id), \
+             name = $tag_{index}.name, slug = $tag_{index}.slug, description = $tag_{index}.description, \
+             color = $tag_{index}.color, usage_count = {usage}, created_by = $owner_id;\n",
+            index = index,
+            usage = usage.get(tag.id.as_str()).copied().unwrap_or(0),
+        ));
+        bindings.insert(format!("tag_{}", index), serde_json::to_value(tag).unwrap_or_default());
+    }
+
+    for (index, comment) in bundle.comments.iter().enumerate() {
+        let parent = if comment.parent_id.is_some() {
+            format!("type::thing('comment', $comment_{}.parent_id)", index)
+        } else {
+            "NONE".to_string()
+        };
+        sql.push_str(&format!(
+            "CREATE type::thing('comment', $comment_{index}.id) SET \
+             document_id = type::thing('document', $comment_{index}.document_id), parent_id = {parent}, \
+             author_id = $owner_id, content = $comment_{index}.content, \
+             is_resolved = $comment_{index}.is_resolved, is_deleted = false, liked_by = [], \
+             metadata = {{ mentions: [], attachments: [], custom_fields: {{}} }}{timestamps};\n",
+            index = index,
+            parent = parent,
+            timestamps = timestamp_assignments(&format!("comment_{}", index), comment.created_at.is_some(), false),
+        ));
+        bindings.insert(format!("comment_{}", index), serde_json::to_value(comment).unwrap_or_default());
+    }
+
+    sql.push_str("COMMIT TRANSACTION;");
+    (sql, bindings)
+}
+
+/// 保留导出包中的时间戳，缺失时使用数据库默认值
+fn timestamp_assignments(param: &str, created_at: bool, updated_at: bool) -> String {
+    let mut assignments = String::new();
+    if created_at {
+        assignments.push_str(&format!(", created_at = <datetime> ${}.created_at", param));
+    }
+    if updated_at {
+        assignments.push_str(&format!(", updated_at = <datetime> ${}.updated_at", param));
+    }
+    assignments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::comment::CommentMetadata;
+    use crate::models::document::DocumentMetadata;
+    use surrealdb::sql::Datetime;
+
+    fn document(id: &str, parent: Option<&str>, slug: &str, order_index: i32) -> Document {
+        Document {
+            id: Some(format!("document:{}", id)),
+            space_id: "space:handbook".to_string(),
+            title: slug.to_uppercase(),
+            slug: slug.to_string(),
+            content: format!("# {}", slug),
+            excerpt: None,
+            is_public: true,
+            parent_id: parent.map(|p| format!("document:{}", p)),
+            order_index,
+            author_id: "alice".to_string(),
+            last_editor_id: None,
+            view_count: 0,
+            word_count: 1,
+            reading_time: 1,
+            metadata: DocumentMetadata::default(),
+            updated_by: None,
+            is_deleted: false,
+            deleted_at: None,
+            deleted_by: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn tag(id: &str, name: &str) -> Tag {
+        Tag {
+            id: Some(Thing::from(("tag", id))),
+            name: name.to_string(),
+            slug: name.to_string(),
+            description: None,
+            color: "#3b82f6".to_string(),
+            space_id: Some(Thing::from(("space", "handbook"))),
+            usage_count: 1,
+            created_by: "alice".to_string(),
+            created_at: Datetime::default(),
+            updated_at: Datetime::default(),
+        }
+    }
+
+    fn comment(id: &str, document_id: &str, parent: Option<&str>) -> Comment {
+        Comment {
+            id: Some(format!("comment:{}", id)),
+            document_id: format!("document:{}", document_id),
+            parent_id: parent.map(|p| format!("comment:{}", p)),
+            author_id: "bob".to_string(),
+            content: "Looks good".to_string(),
+            is_resolved: false,
+            resolved_by: None,
+            resolved_at: None,
+            metadata: CommentMetadata::default(),
+            liked_by: Vec::new(),
+            is_deleted: false,
+            deleted_at: None,
+            deleted_by: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    /// 按 slug 还原文档树，便于比较导入前后的结构
+    fn tree_by_slug(bundle: &SpaceBundle) -> Vec<(String, Option<String>)> {
+        let slugs: HashMap<&str, &str> = bundle.documents.iter().map(|doc| (doc.id.as_str(), doc.slug.as_str())).collect();
+        let mut tree: Vec<(String, Option<String>)> = bundle
+            .documents
+            .iter()
+            .map(|doc| (doc.slug.clone(), doc.parent_id.as_deref().map(|parent| slugs[parent].to_string())))
+            .collect();
+        tree.sort();
+        tree
+    }
+
+    #[test]
+    fn test_export_and_reimport_preserves_tree_and_tags() {
+        let space = Space::new("Handbook".to_string(), "handbook".to_string(), "alice".to_string());
+        // 子文档排在父文档之前，导出时会调整顺序
+        let documents = vec![
+            document("setup", Some("guide"), "setup", 0),
+            document("guide", None, "guide", 0),
+            document("faq", None, "faq", 1),
+            document("linux", Some("setup"), "linux", 0),
+        ];
+        let document_tags = vec![DocumentTag {
+            id: None,
+            document_id: Thing::from(("document", "setup")),
+            tag_id: Thing::from(("tag", "howto")),
+            tagged_by: "alice".to_string(),
+            tagged_at: Datetime::default(),
+        }];
+        let comments = vec![comment("c1", "guide", None), comment("c2", "guide", Some("c1"))];
+
+        let exported = build_bundle(&space, documents, vec![tag("howto", "howto")], &document_tags, comments);
+        let order: Vec<&str> = exported.documents.iter().map(|doc| doc.slug.as_str()).collect();
+        assert_eq!(order, vec!["guide", "setup", "faq", "linux"]);
+
+        let json = serde_json::to_string(&exported).unwrap();
+        let bundle: SpaceBundle = serde_json::from_str(&json).unwrap();
+
+        let mut counter = 0;
+        let taken: HashSet<String> = ["handbook".to_string()].into_iter().collect();
+        let (space_id, imported) = remap_bundle(bundle, &taken, || {
+            counter += 1;
+            format!("new{}", counter)
+        })
+        .unwrap();
+
+        assert_eq!(imported.space.slug, "handbook-copy");
+        assert!(space_id.starts_with("new"));
+        assert_eq!(tree_by_slug(&imported), tree_by_slug(&exported));
+
+        let old_ids: HashSet<&str> = exported.documents.iter().map(|doc| doc.id.as_str()).collect();
+        assert!(imported.documents.iter().all(|doc| !old_ids.contains(doc.id.as_str())));
+
+        let setup = imported.documents.iter().find(|doc| doc.slug == "setup").unwrap();
+        assert_eq!(setup.tag_ids, vec![imported.tags[0].id.clone()]);
+
+        let guide = imported.documents.iter().find(|doc| doc.slug == "guide").unwrap();
+        assert!(imported.comments.iter().all(|c| c.document_id == guide.id));
+        assert_eq!(imported.comments[1].parent_id.as_deref(), Some(imported.comments[0].id.as_str()));
+
+        let (sql, bindings) = build_import_statements(&space_id, "carol", &imported);
+        assert!(sql.starts_with("BEGIN TRANSACTION;") && sql.ends_with("COMMIT TRANSACTION;"));
+        assert_eq!(sql.matches("CREATE type::thing('document'").count(), 4);
+        assert_eq!(sql.matches("CREATE document_tag").count(), 1);
+        assert!(sql.contains("usage_count = 1"));
+        assert_eq!(bindings["owner_id"], "carol");
+        // 导出包中的作者（alice、bob）不会写入，文档和评论都归导入者所有
+        assert_eq!(bindings["doc_0"]["author_id"], "alice");
+        assert_eq!(sql.matches("author_id = $owner_id").count(), imported.documents.len() + imported.comments.len());
+        assert!(!sql.contains(".author_id"));
+    }
+
+    #[test]
+    fn test_import_rejects_broken_trees_and_unknown_versions() {
+        let space = Space::new("Handbook".to_string(), "handbook".to_string(), "alice".to_string());
+        let bundle = build_bundle(&space, vec![document("a", None, "a", 0)], Vec::new(), &[], Vec::new());
+
+        let mut cyclic = bundle.clone();
+        cyclic.documents = vec![
+            BundleDocument { parent_id: Some("b".to_string()), ..cyclic.documents[0].clone() },
+            BundleDocument {
+                id: "b".to_string(),
+                slug: "b".to_string(),
+                parent_id: Some("document:a".to_string()),
+                ..cyclic.documents[0].clone()
+            },
+        ];
+        assert!(matches!(remap_bundle(cyclic, &HashSet::new(), new_record_id), Err(AppError::Validation(_))));
+
+        let mut orphan = bundle.clone();
+        orphan.documents[0].parent_id = Some("document:missing".to_string());
+        assert!(matches!(remap_bundle(orphan, &HashSet::new(), new_record_id), Err(AppError::Validation(_))));
+
+        let mut future = bundle;
+        future.version = SPACE_BUNDLE_VERSION + 1;
+        assert!(matches!(remap_bundle(future, &HashSet::new(), new_record_id), Err(AppError::Validation(_))));
+    }
+}
@@ -1,20 +1,47 @@
 use crate::config::Config;
-use crate::error::{AppError, Result};
+use crate::error::{is_unique_violation, AppError, Result};
+use crate::models::notification::{NotificationType, SpaceInvitationData};
 use crate::models::space_member::{
     SpaceMember, SpaceMemberDb, SpaceInvitation, SpaceInvitationDb,
     InviteMemberRequest, UpdateMemberRequest, AcceptInvitationRequest,
     MemberStatus, SpaceMemberResponse, MemberRole
 };
-use crate::services::auth::User;
+use crate::services::activity::{actor_display_name, ActivityLogger, ActivityRecord};
+use crate::services::api_key::{ApiKeyService, API_KEY_PRINCIPAL_PREFIX};
+use crate::services::auth::{User, INTERNAL_API_KEY_HEADER};
 use crate::services::database::Database;
+use crate::services::notification::notification_enabled;
+use crate::utils::rate_limit::SlidingWindowLimiter;
+use crate::utils::redact;
 use serde_json::Value;
 use std::sync::Arc;
 use surrealdb::sql::Thing;
-use tracing::{info, warn, error};
+use tracing::{debug, error, info, trace, warn};
+use tokio::sync::Mutex;
 use validator::Validate;
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use uuid::Uuid;
 
+/// 调用 Rainbow-Auth 邮件接口的超时时间，避免上游挂起时邀请请求一直等待
+const EMAIL_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// 将各种形式的空间ID（`xxx`、`space:xxx`、`space:⟨xxx⟩`）统一转换为单层的 space Thing
+fn space_record(space_id: &str) -> Thing {
+    let mut raw = space_id.trim();
+    loop {
+        let unwrapped = raw
+            .strip_prefix("space:")
+            .unwrap_or(raw)
+            .trim_start_matches('⟨')
+            .trim_end_matches('⟩');
+        if unwrapped == raw {
+            break;
+        }
+        raw = unwrapped;
+    }
+    Thing::from(("space", raw))
+}
+
 /// 清理用户ID格式，确保和数据库存储格式一致
 fn clean_user_id_format(user_id: &str) -> String {
     // 只移除 user: 前缀，保留 ⟨⟩ 符号以匹配数据库格式
@@ -29,11 +56,35 @@ fn clean_user_id_format(user_id: &str) -> String {
 pub struct SpaceMemberService {
     db: Arc<Database>,
     config: Config,
+    activity: ActivityLogger,
+    /// 按 (空间, 邀请者) 限制邀请频率，防止批量发送邀请邮件
+    invite_limiter: Arc<Mutex<SlidingWindowLimiter>>,
+    http_client: reqwest::Client,
+    /// API 密钥主体不是空间成员，按密钥的空间和权限判断
+    api_keys: ApiKeyService,
 }
 
 impl SpaceMemberService {
     pub fn new(db: Arc<Database>, config: Config) -> Self {
-        Self { db, config }
+        let activity = ActivityLogger::new(db.clone());
+        let api_keys = ApiKeyService::new(db.clone());
+        let invite_limiter = SlidingWindowLimiter::new(config.features.max_invitations_per_hour, Duration::hours(1));
+        Self {
+            db,
+            config,
+            activity,
+            invite_limiter: Arc::new(Mutex::new(invite_limiter)),
+            http_client: reqwest::Client::builder()
+                .timeout(EMAIL_REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            api_keys,
+        }
+    }
+
+    /// 清理邀请限流中已过期的记录
+    pub async fn cleanup_rate_limits(&self) {
+        self.invite_limiter.lock().await.cleanup(Utc::now());
     }
 
     /// 检查用户是否为空间成员或所有者
@@ -41,10 +92,13 @@ impl SpaceMemberService {
         let Some(uid) = user_id else {
             return Ok(false);
         };
+        if uid.starts_with(API_KEY_PRINCIPAL_PREFIX) {
+            return self.api_keys.principal_allows(uid, space_id, None).await;
+        }
 
         // 清理user_id格式，确保和数据库存储格式一致
         let clean_user_id = clean_user_id_format(uid);
-        info!("Checking space access for clean_user_id: {} (original: {})", clean_user_id, uid);
+        debug!("Checking space access for clean_user_id: {} (original: {})", redact::user_id(&clean_user_id), redact::user_id(uid));
 
         // 提取实际的空间ID（去掉"space:"前缀，如果存在）
         let actual_space_id = if space_id.starts_with("space:") {
@@ -58,15 +112,14 @@ impl SpaceMemberService {
         let mut owner_result = self.db.client
             .query(owner_query)
             .bind(("space_id", Thing::from(("space", actual_space_id))))
-            .await
-            .map_err(|e| AppError::Database(e))?;
+            .await?;
 
         if let Ok(spaces) = owner_result.take::<Vec<Value>>(0) {
             if let Some(space) = spaces.first() {
                 if let Some(owner_id) = space.get("owner_id").and_then(|v| v.as_str()) {
                     let clean_owner_id = clean_user_id_format(owner_id);
                     if clean_owner_id == clean_user_id {
-                        info!("User is space owner, granting access");
+                        debug!("User is space owner, granting access");
                         return Ok(true);
                     }
                 }
@@ -79,21 +132,56 @@ impl SpaceMemberService {
             .query(member_query)
             .bind(("space_id", Thing::from(("space", actual_space_id))))
             .bind(("user_id", &clean_user_id))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
         let has_access = !member_result.is_empty();
         if has_access {
-            info!("User found as space member, granting access");
+            debug!("User found as space member, granting access");
         } else {
-            info!("User not found as space member or owner");
+            debug!("User not found as space member or owner");
         }
         Ok(has_access)
     }
 
+    /// 检查用户是否为空间所有者
+    pub async fn is_space_owner(&self, space_id: &str, user_id: &str) -> Result<bool> {
+        let owners: Vec<Value> = self.db.client
+            .query("SELECT owner_id FROM space WHERE id = $space_id")
+            .bind(("space_id", space_record(space_id)))
+            .await?
+            .take(0)?;
+
+        let clean_user_id = clean_user_id_format(user_id);
+        Ok(owners
+            .first()
+            .and_then(|space| space.get("owner_id"))
+            .and_then(|v| v.as_str())
+            .is_some_and(|owner_id| clean_user_id_format(owner_id) == clean_user_id))
+    }
+
+    /// 获取用户在空间中的角色，非成员返回 None
+    pub async fn get_member_role(&self, space_id: &str, user_id: &str) -> Result<Option<MemberRole>> {
+        let members: Vec<Value> = self.db.client
+            .query("SELECT role FROM space_member WHERE space_id = $space_id AND user_id = $user_id AND status = 'accepted'")
+            .bind(("space_id", space_record(space_id)))
+            .bind(("user_id", clean_user_id_format(user_id)))
+            .await?
+            .take(0)?;
+
+        Ok(members
+            .first()
+            .and_then(|member| member.get("role"))
+            .and_then(|v| v.as_str())
+            .map(MemberRole::from_db_str))
+    }
+
     /// 检查用户在空间中的权限
     pub async fn check_permission(&self, space_id: &str, user_id: &str, permission: &str) -> Result<bool> {
+        if user_id.starts_with(API_KEY_PRINCIPAL_PREFIX) {
+            return self.api_keys.principal_allows(user_id, space_id, Some(permission)).await;
+        }
+
         // 提取实际的空间ID（去掉"space:"前缀，如果存在）
         let actual_space_id = if space_id.starts_with("space:") {
             space_id.strip_prefix("space:").unwrap()
@@ -103,15 +191,14 @@ impl SpaceMemberService {
 
         // 清理user_id格式，确保和数据库存储格式一致
         let clean_user_id = clean_user_id_format(user_id);
-        info!("Checking permission '{}' for clean_user_id: {} (original: {}) in space: {}", permission, clean_user_id, user_id, actual_space_id);
+        debug!("Checking permission '{}' for clean_user_id: {} (original: {}) in space: {}", permission, redact::user_id(&clean_user_id), redact::user_id(user_id), actual_space_id);
 
         // 首先检查是否为空间所有者
         let owner_query = "SELECT owner_id FROM space WHERE id = $space_id";
         let mut owner_result = self.db.client
             .query(owner_query)
             .bind(("space_id", Thing::from(("space", actual_space_id))))
-            .await
-            .map_err(|e| AppError::Database(e))?;
+            .await?;
 
         if let Ok(spaces) = owner_result.take::<Vec<Value>>(0) {
             if let Some(space) = spaces.first() {
@@ -119,7 +206,7 @@ impl SpaceMemberService {
                     // 比较owner_id时也需要考虑格式一致性
                     let clean_owner_id = clean_user_id_format(owner_id);
                     if clean_owner_id == clean_user_id {
-                        info!("User is space owner, granting permission");
+                        debug!("User is space owner, granting permission");
                         return Ok(true); // 所有者拥有所有权限
                     }
                 }
@@ -132,29 +219,21 @@ impl SpaceMemberService {
             .query(member_query)
             .bind(("space_id", Thing::from(("space", actual_space_id))))
             .bind(("user_id", &clean_user_id))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
         if let Some(member) = members.first() {
             let role_str = member.get("role").and_then(|v| v.as_str()).unwrap_or("unknown");
             let permissions_array = member.get("permissions").and_then(|v| v.as_array());
             
-            info!("Found space member with role: {}, permissions: {:?}", role_str, permissions_array);
+            debug!("Found space member with role: {}, permissions: {:?}", role_str, permissions_array);
             
             // 解析角色
-            let member_role = match role_str {
-                "owner" => MemberRole::Owner,
-                "admin" => MemberRole::Admin,
-                "editor" => MemberRole::Editor,
-                "viewer" => MemberRole::Viewer,
-                "member" => MemberRole::Member,
-                _ => MemberRole::Member,
-            };
+            let member_role = MemberRole::from_db_str(role_str);
             
             // 检查角色默认权限
             if member_role.can_perform(permission) {
-                info!("Permission granted by role: {:?}", member_role);
+                debug!("Permission granted by role: {:?}", member_role);
                 return Ok(true);
             }
             
@@ -163,16 +242,16 @@ impl SpaceMemberService {
                 for perm in perms {
                     if let Some(perm_str) = perm.as_str() {
                         if perm_str == permission {
-                            info!("Permission granted by custom permissions");
+                            debug!("Permission granted by custom permissions");
                             return Ok(true);
                         }
                     }
                 }
             }
             
-            info!("Permission denied: role {:?} does not have permission '{}'", member_role, permission);
+            debug!("Permission denied: role {:?} does not have permission '{}'", member_role, permission);
         } else {
-            info!("No space member record found for user_id: {}", clean_user_id);
+            debug!("No space member record found for user_id: {}", redact::user_id(&clean_user_id));
         }
 
         Ok(false)
@@ -194,23 +273,29 @@ impl SpaceMemberService {
             }
         }
 
+        // 请求通过全部校验后才占用限流额度，无效请求不消耗邀请次数
+        let limiter_key = format!("{}:{}", space_id.strip_prefix("space:").unwrap_or(space_id), inviter.id);
+        if !self.invite_limiter.lock().await.try_acquire(&limiter_key, Utc::now()) {
+            return Err(AppError::TooManyRequests(format!(
+                "Invitation limit reached: at most {} invitations per hour in this space",
+                self.config.features.max_invitations_per_hour
+            )));
+        }
+
         // 生成邀请令牌
         let invite_token = Uuid::new_v4().to_string();
         let expires_in_days = request.expires_in_days.unwrap_or(7);
+        let max_uses = request.max_uses.unwrap_or(1) as u32;
 
-        // 提取纯净的space_id，避免嵌套Thing
-        let clean_space_id = if space_id.starts_with("space:") {
-            space_id.strip_prefix("space:").unwrap()
-        } else {
-            space_id
-        };
+        // 以单层 Thing 存储 space_id，接受邀请时直接取 .id 即可
+        let space_thing = space_record(space_id);
 
-        info!("Creating invitation with clean_space_id: {}", clean_space_id);
+        debug!("Creating invitation for space: {}", space_thing);
 
         // 使用 SQL 查询创建邀请记录，使用 SurrealDB 的时间函数和 duration 语法
         let query = format!(r#"
             CREATE space_invitation SET
-                space_id = type::thing('space', $space_id),
+                space_id = $space_id,
                 email = $email,
                 user_id = $user_id,
                 invite_token = $invite_token,
@@ -225,7 +310,7 @@ impl SpaceMemberService {
 
         let created: Vec<SpaceInvitationDb> = self.db.client
             .query(query)
-            .bind(("space_id", clean_space_id))
+            .bind(("space_id", space_thing))
             .bind(("email", request.email.clone()))
             .bind(("user_id", request.user_id.clone()))
             .bind(("invite_token", invite_token.clone()))
@@ -233,35 +318,28 @@ impl SpaceMemberService {
             .bind(("permissions", request.role.default_permissions()))
             .bind(("invited_by", inviter.id.clone()))
             .bind(("message", request.message.clone()))
-            .bind(("max_uses", 1))
+            .bind(("max_uses", max_uses))
             .bind(("used_count", 0))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
         let created_invitation = created.into_iter().next()
             .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Failed to create invitation")))?;
 
-        info!("User {} invited {} to space {}", inviter.id, 
-              request.email.as_deref().unwrap_or(request.user_id.as_deref().unwrap_or("unknown")), space_id);
-
-        // 获取邀请者显示名称，优先使用profile中的名称，否则使用用户ID
-        let inviter_name = inviter.profile.as_ref()
-            .and_then(|p| p.display_name.clone())
-            .filter(|name| !name.is_empty())
-            .or_else(|| {
-                // 如果email不是默认的unknown@example.com，则使用email
-                if inviter.email != "unknown@example.com" {
-                    Some(inviter.email.clone())
-                } else {
-                    // 否则使用用户ID
-                    Some(inviter.id.clone())
-                }
-            })
-            .unwrap_or_else(|| inviter.id.clone());
-        
-        info!("Inviter info - ID: {}, Email: {}, Display name: {}", 
-              inviter.id, inviter.email, inviter_name);
+        info!("User {} invited {} to space {}", redact::user_id(&inviter.id), 
+              invitee_for_log(&request), space_id);
+
+        // 获取邀请者显示名称，优先使用profile中的名称，其次邮箱，最后用户ID
+        let inviter_name = actor_display_name(inviter);
+
+        let invitee = request.user_id.as_deref().or(request.email.as_deref()).unwrap_or("invite link");
+        let record = ActivityRecord::new(&inviter.id, "member_invited", "member", invitee, space_id)
+            .with_actor_name(inviter_name.clone())
+            .with_title(invitee);
+        self.activity.log(record).await;
+
+        debug!("Inviter info - ID: {}, Email: {}, Display name: {}", 
+              redact::user_id(&inviter.id), redact::email(&inviter.email), inviter_name);
 
         // 发送邮件和通知
         self.send_invitation_notifications(
@@ -283,7 +361,7 @@ impl SpaceMemberService {
     /// 接受邀请
     pub async fn accept_invitation(&self, user_id: &str, request: AcceptInvitationRequest) -> Result<SpaceMember> {
         // 查找邀请 - 使用更简单的查询方法
-        info!("Searching for invitation with token: {}", &request.invite_token);
+        debug!("Searching for invitation by token");
         
         // 先获取所有邀请，然后在内存中过滤（避免参数绑定问题）
         let all_invitations: Vec<SpaceInvitationDb> = self.db.client
@@ -291,7 +369,7 @@ impl SpaceMemberService {
             .await
             .map_err(|e| {
                 error!("Failed to select invitations: {}", e);
-                AppError::Database(e)
+                AppError::from(e)
             })?;
             
         // 在内存中过滤出匹配的邀请
@@ -307,108 +385,62 @@ impl SpaceMemberService {
             .ok_or_else(|| AppError::NotFound("Invitation not found or expired".to_string()))?;
 
         // 检查邀请是否已用完
-        if invitation.used_count >= invitation.max_uses {
+        if !invitation_has_uses_left(invitation.used_count, invitation.max_uses) {
             return Err(AppError::Conflict("Invitation has been used up".to_string()));
         }
 
         // 检查是否已经是成员
-        if self.can_access_space(&invitation.space_id.id.to_string(), Some(user_id)).await? {
+        let space_id = invitation.space_id.id.to_raw();
+        if self.can_access_space(&space_id, Some(user_id)).await? {
             return Err(AppError::Conflict("User is already a member of this space".to_string()));
         }
 
-        // 使用 SQL 查询创建成员记录，让 SurrealDB 处理所有时间字段
-        let create_member_query = r#"
-            CREATE space_member SET
-                space_id = $space_id,
-                user_id = $user_id,
-                role = $role,
-                permissions = $permissions,
-                invited_by = $invited_by,
-                invited_at = time::now(),
-                accepted_at = time::now(),
-                status = 'accepted',
-                expires_at = NONE,
-                created_at = time::now(),
-                updated_at = time::now()
-        "#;
+        let invitation_id = invitation.id.as_ref()
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Invitation ID is missing")))?;
 
-        // 提取纯净的space_id和user_id，避免嵌套Thing
-        let raw_space_id = invitation.space_id.id.to_string();
-        info!("Raw space_id from invitation: {}", raw_space_id);
-        
-        // 处理可能的嵌套Thing格式 space:⟨⟨space:xxxxx⟩⟩
-        let clean_space_id = if raw_space_id.contains("⟨⟨space:") {
-            // 提取最内层的ID，从 space:⟨⟨space:xxxxx⟩⟩ 中提取 xxxxx
-            if let Some(start) = raw_space_id.find("⟨⟨space:") {
-                let after_start = &raw_space_id[start + 8..]; // 跳过 "⟨⟨space:"
-                if let Some(end) = after_start.find("⟩⟩") {
-                    &after_start[..end]
-                } else {
-                    &raw_space_id
-                }
-            } else {
-                &raw_space_id
-            }
-        } else if raw_space_id.starts_with("space:") {
-            raw_space_id.strip_prefix("space:").unwrap()
-        } else {
-            &raw_space_id
-        };
-        
         // 清理user_id格式，确保存储的是纯净的UUID
         let clean_user_id = clean_user_id_format(user_id);
 
-        info!("Creating space member with clean_space_id: {}, clean_user_id: {}", clean_space_id, clean_user_id);
+        debug!("Creating space member in space: {}, clean_user_id: {}", space_id, redact::user_id(&clean_user_id));
 
-        let mut create_result = self.db.client
-            .query(create_member_query)
-            .bind(("space_id", Thing::from(("space", clean_space_id))))
+        // 占用使用次数和创建成员在同一个事务中完成：条件更新保证并发接受时不会超过 max_uses，
+        // 成员创建失败时使用次数一并回滚
+        let mut response = self.db.client
+            .query(ACCEPT_INVITATION_QUERY)
+            .bind(("invitation_id", Thing::from(("space_invitation", invitation_id.id.to_string().as_str()))))
+            .bind(("space_id", Thing::from(("space", space_id.as_str()))))
             .bind(("user_id", clean_user_id))
             .bind(("role", invitation.role.clone()))
             .bind(("permissions", invitation.permissions.clone()))
             .bind(("invited_by", invitation.invited_by.clone()))
             .await
             .map_err(|e| {
-                error!("Failed to create space member: {}", e);
-                AppError::Database(e)
+                error!("Failed to accept invitation: {}", e);
+                AppError::from(e)
             })?;
 
-        let created_members: Vec<SpaceMemberDb> = create_result
-            .take(0)
+        let errors: Vec<String> = response.take_errors().into_values().map(|e| e.to_string()).collect();
+        if !errors.is_empty() {
+            error!("Accept invitation transaction failed: {}", errors.join("; "));
+            return Err(accept_invitation_error(&errors));
+        }
+
+        // 最后一条语句返回创建的成员
+        let last_statement = response.num_statements() - 1;
+        let created_members: Vec<SpaceMemberDb> = response
+            .take(last_statement)
             .map_err(|e| {
                 error!("Failed to take created member: {}", e);
-                AppError::Database(e.into())
+                AppError::from(e)
             })?;
 
         let created_member = created_members.into_iter().next()
             .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Failed to create member")))?;
 
-        // 更新邀请使用次数 - 使用简单的更新方法
-        if let Some(invitation_id) = &invitation.id {
-            let update_query = r#"
-                UPDATE $invitation_id SET
-                    used_count = used_count + 1,
-                    updated_at = time::now()
-            "#;
-            
-            let mut update_result = self.db.client
-                .query(update_query)
-                .bind(("invitation_id", Thing::from(("space_invitation", invitation_id.id.to_string().as_str()))))
-                .await
-                .map_err(|e| {
-                    error!("Failed to update invitation used_count: {}", e);
-                    AppError::Database(e)
-                })?;
-                
-            let _: Vec<Value> = update_result
-                .take(0)
-                .map_err(|e| {
-                    error!("Failed to take update results: {}", e);
-                    AppError::Database(e.into())
-                })?;
-        }
+        info!("User {} accepted invitation to space {}", redact::user_id(user_id), space_id);
 
-        info!("User {} accepted invitation to space {}", user_id, invitation.space_id.id.to_string());
+        let record = ActivityRecord::new(user_id, "member_joined", "member", user_id, &space_id);
+        self.activity.log(record).await;
 
         Ok(created_member.into())
     }
@@ -431,8 +463,7 @@ impl SpaceMemberService {
         let members: Vec<SpaceMemberDb> = self.db.client
             .query(query)
             .bind(("space_id", Thing::from(("space", actual_space_id))))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
         let member_responses = members.into_iter()
@@ -464,8 +495,7 @@ impl SpaceMemberService {
             .query(query)
             .bind(("space_id", Thing::from(("space", actual_space_id))))
             .bind(("user_id", member_user_id))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
         let mut member: SpaceMember = members.into_iter().next()
@@ -492,14 +522,18 @@ impl SpaceMemberService {
             .bind(("updated_at", member.updated_at))
             .bind(("space_id", Thing::from(("space", actual_space_id))))
             .bind(("user_id", member_user_id))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take((0, "AFTER"))?;
 
         let updated_member = updated
             .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Failed to update member")))?;
 
-        info!("User {} updated member {} in space {}", updater.id, member_user_id, space_id);
+        info!("User {} updated member {} in space {}", redact::user_id(&updater.id), redact::user_id(member_user_id), space_id);
+
+        let record = ActivityRecord::new(&updater.id, "member_updated", "member", member_user_id, space_id)
+            .with_actor_name(actor_display_name(updater))
+            .with_title(member_user_id);
+        self.activity.log(record).await;
 
         Ok(SpaceMemberResponse::from(SpaceMember::from(updated_member)))
     }
@@ -528,11 +562,15 @@ impl SpaceMemberService {
             .query("DELETE space_member WHERE space_id = $space_id AND user_id = $user_id")
             .bind(("space_id", Thing::from(("space", actual_space_id))))
             .bind(("user_id", member_user_id))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
-        info!("User {} removed member {} from space {}", remover.id, member_user_id, space_id);
+        info!("User {} removed member {} from space {}", redact::user_id(&remover.id), redact::user_id(member_user_id), space_id);
+
+        let record = ActivityRecord::new(&remover.id, "member_removed", "member", member_user_id, space_id)
+            .with_actor_name(actor_display_name(remover))
+            .with_title(member_user_id);
+        self.activity.log(record).await;
 
         Ok(())
     }
@@ -543,8 +581,7 @@ impl SpaceMemberService {
         let members: Vec<SpaceMemberDb> = self.db.client
             .query(query)
             .bind(("user_id", user_id))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
         let space_ids = members.into_iter()
@@ -569,16 +606,18 @@ impl SpaceMemberService {
         // 获取空间名称
         let space_name = self.get_space_name(space_id).await?;
 
-        // 如果提供了用户ID，创建站内通知
+        // 如果提供了用户ID，创建站内通知。站内通知失败不影响邀请邮件
         if let Some(user_id) = to_user_id {
-            self.create_space_invitation_notification(
+            if let Err(e) = self.create_space_invitation_notification(
                 user_id,
                 &space_name,
                 inviter_name,
                 invite_token,
                 role,
                 message,
-            ).await?;
+            ).await {
+                warn!("Failed to create invitation notification for user {}: {}", redact::user_id(user_id), e);
+            }
         }
 
         // 如果提供了邮箱，发送邮件通知
@@ -613,14 +652,14 @@ impl SpaceMemberService {
             .await
             .map_err(|e| {
                 error!("Failed to get space name for {}: {}", space_id, e);
-                AppError::Database(e)
+                AppError::from(e)
             })?;
 
         let spaces: Vec<serde_json::Value> = response.take(0)?;
         match spaces.into_iter().next() {
             Some(space_data) => {
                 let name = space_data["name"].as_str().unwrap_or("未知空间").to_string();
-                info!("Found space name: {} for space: {}", name, space_id);
+                debug!("Found space name: {} for space: {}", name, space_id);
                 Ok(name)
             }
             None => {
@@ -640,17 +679,20 @@ impl SpaceMemberService {
         role: &str,
         message: Option<&str>,
     ) -> Result<()> {
-        use serde_json::json;
+        // 用户关闭了邀请通知时只跳过站内通知，邀请邮件照常发送
+        if !notification_enabled(&self.db, user_id, NotificationType::SpaceInvitation).await? {
+            debug!("User {} disabled invitation notifications, skipping", redact::user_id(user_id));
+            return Ok(());
+        }
 
-        // 创建通知数据
-        let notification_data = json!({
-            "space_name": space_name,
-            "invite_token": invite_token,
-            "role": role,
-            "inviter_name": inviter_name,
-        });
+        let notification_data = SpaceInvitationData {
+            space_name: space_name.to_string(),
+            invite_token: invite_token.to_string(),
+            role: role.to_string(),
+            inviter_name: inviter_name.to_string(),
+        };
 
-        info!("Creating notification with data: {}", notification_data);
+        trace!("Creating notification with data: {:?}", notification_data);
 
         let title = format!("{} 邀请您加入 {} 空间", inviter_name, space_name);
         let content = format!(
@@ -661,20 +703,13 @@ impl SpaceMemberService {
             message.unwrap_or(""),
         );
 
-        // 最终解决方案：将invite_token作为独立字段存储，完全绕过data字段的问题
-        info!("Storing invite_token as separate field: {}", invite_token);
-
         let query = r#"
             CREATE notification SET
                 user_id = $user_id,
                 type = $type,
                 title = $title,
                 content = $content,
-                data = NONE,
-                invite_token = $invite_token,
-                space_name = $space_name,
-                role = $role,
-                inviter_name = $inviter_name,
+                data = $data,
                 is_read = false,
                 created_at = time::now(),
                 updated_at = time::now()
@@ -686,35 +721,32 @@ impl SpaceMemberService {
             .bind(("type", "space_invitation"))
             .bind(("title", &title))
             .bind(("content", &content))
-            .bind(("invite_token", invite_token))
-            .bind(("space_name", space_name))
-            .bind(("role", role))
-            .bind(("inviter_name", inviter_name))
+            .bind(("data", &notification_data))
             .await
             .map_err(|e| {
                 error!("Failed to create notification: {}", e);
-                AppError::Database(e)
+                AppError::from(e)
             })?;
 
         // 获取创建的通知记录
         let created_notifications: Vec<serde_json::Value> = result.take(0)
             .map_err(|e| {
                 error!("Failed to retrieve created notification: {}", e);
-                AppError::Database(e.into())
+                AppError::from(e)
             })?;
 
         if created_notifications.is_empty() {
-            error!("No notification was created for user {}", user_id);
+            error!("No notification was created for user {}", redact::user_id(user_id));
             return Err(AppError::Internal(anyhow::anyhow!("Failed to create notification")));
         }
 
         // 记录创建的通知详情
         if let Some(created_notification) = created_notifications.first() {
-            info!("Successfully created notification: {}", 
+            trace!("Successfully created notification: {}", 
                   serde_json::to_string_pretty(created_notification).unwrap_or_default());
         }
 
-        info!("Created space invitation notification for user {}", user_id);
+        info!("Created space invitation notification for user {}", redact::user_id(user_id));
         Ok(())
     }
 
@@ -729,44 +761,259 @@ impl SpaceMemberService {
         message: Option<&str>,
         expires_in_days: u64,
     ) -> Result<()> {
-       /*  use serde_json::json;
+        if !self.config.features.enable_invitation_emails {
+            debug!("Invitation emails are disabled, skipping email to {}", redact::email(to_email));
+            return Ok(());
+        }
 
         // 调用 Rainbow-Auth 的邮件服务
         let rainbow_auth_url = self.config.auth.rainbow_auth_url
             .as_ref()
             .ok_or_else(|| AppError::Configuration("Rainbow-Auth URL not configured".to_string()))?;
+        let api_key = self.config.auth.internal_api_key
+            .as_ref()
+            .ok_or_else(|| AppError::Configuration("Rainbow-Auth internal API key not configured".to_string()))?;
+
+        let payload = invitation_email_payload(
+            to_email,
+            space_name,
+            inviter_name,
+            invite_token,
+            role,
+            message,
+            expires_in_days,
+        );
+        send_internal_email(&self.http_client, rainbow_auth_url, api_key, &payload).await?;
+
+        info!("Sent invitation email to {}", redact::email(to_email));
+        Ok(())
+    }
+}
+
+/// 日志中的被邀请人，邮箱和用户ID都经过脱敏
+fn invitee_for_log(request: &InviteMemberRequest) -> String {
+    match (request.email.as_deref(), request.user_id.as_deref()) {
+        (Some(email), _) => redact::email(email).to_string(),
+        (None, Some(user_id)) => redact::user_id(user_id).to_string(),
+        (None, None) => "invite link".to_string(),
+    }
+}
+
+/// 邀请已用完时事务中抛出的错误标记
+const INVITATION_USED_UP: &str = "invitation_used_up";
+
+/// 接受邀请：占用一次使用次数并创建成员，任一步失败整个事务回滚
+const ACCEPT_INVITATION_QUERY: &str = r#"
+    BEGIN TRANSACTION;
+    LET $claimed = (UPDATE $invitation_id SET
+        used_count = used_count + 1,
+        updated_at = time::now()
+        WHERE max_uses = 0 OR used_count < max_uses);
+    IF array::len($claimed) = 0 { THROW "invitation_used_up" };
+    CREATE space_member SET
+        space_id = $space_id,
+        user_id = $user_id,
+        role = $role,
+        permissions = $permissions,
+        invited_by = $invited_by,
+        invited_at = time::now(),
+        accepted_at = time::now(),
+        status = 'accepted',
+        expires_at = NONE,
+        created_at = time::now(),
+        updated_at = time::now();
+    COMMIT TRANSACTION;
+"#;
+
+/// 将接受邀请事务的失败转换为对应的错误：使用次数已满或并发接受导致重复成员都是冲突
+fn accept_invitation_error(errors: &[String]) -> AppError {
+    if errors.iter().any(|e| e.contains(INVITATION_USED_UP)) {
+        AppError::Conflict("Invitation has been used up".to_string())
+    } else if errors.iter().any(|e| is_unique_violation(e, "space_member_unique_idx")) {
+        AppError::Conflict("User is already a member of this space".to_string())
+    } else {
+        AppError::database_error(errors.join("; "))
+    }
+}
+
+/// 邀请是否还有剩余使用次数，max_uses 为 0 表示过期前不限次数
+fn invitation_has_uses_left(used_count: u32, max_uses: u32) -> bool {
+    max_uses == 0 || used_count < max_uses
+}
+
+/// 构造 Rainbow-Auth 邮件通知接口的请求体
+fn invitation_email_payload(
+    to_email: &str,
+    space_name: &str,
+    inviter_name: &str,
+    invite_token: &str,
+    role: &str,
+    message: Option<&str>,
+    expires_in_days: u64,
+) -> Value {
+    serde_json::json!({
+        "to": to_email,
+        "notification_type": "space_invitation",
+        "data": {
+            "space_name": space_name,
+            "inviter_name": inviter_name,
+            "invite_token": invite_token,
+            "role": role,
+            "message": message,
+            "expires_in_days": expires_in_days,
+        }
+    })
+}
+
+/// 调用 Rainbow-Auth 的 /api/internal/email/notification，非 2xx 响应返回 External 错误
+async fn send_internal_email(
+    client: &reqwest::Client,
+    rainbow_auth_url: &str,
+    api_key: &str,
+    payload: &Value,
+) -> Result<()> {
+    let url = format!("{}/api/internal/email/notification", rainbow_auth_url.trim_end_matches('/'));
+
+    let response = client
+        .post(&url)
+        .header(INTERNAL_API_KEY_HEADER, api_key)
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| AppError::External(format!("Failed to send email: {}", e)))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        error!("Failed to send email notification ({}): {}", status, error_text);
+        return Err(AppError::External(format!("Email service error ({}): {}", status, error_text)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::{HeaderMap, StatusCode}, routing::post, Json, Router};
+    use std::sync::Mutex as StdMutex;
+
+    type Captured = Arc<StdMutex<Option<(Option<String>, Value)>>>;
+
+    /// 启动一个模拟 Rainbow-Auth 邮件接口的本地服务，记录收到的请求
+    fn spawn_mock_email_service(status: StatusCode) -> (String, Captured) {
+        let captured: Captured = Arc::new(StdMutex::new(None));
+
+        async fn handler(
+            State((captured, status)): State<(Captured, StatusCode)>,
+            headers: HeaderMap,
+            Json(body): Json<Value>,
+        ) -> StatusCode {
+            let api_key = headers
+                .get(INTERNAL_API_KEY_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            *captured.lock().unwrap() = Some((api_key, body));
+            status
+        }
+
+        let app = Router::new()
+            .route("/api/internal/email/notification", post(handler))
+            .with_state((captured.clone(), status));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
 
-        let url = format!("{}/api/internal/email/notification", rainbow_auth_url);
-
-        let email_data = json!({
-            "to": to_email,
-            "notification_type": "space_invitation",
-            "data": {
-                "space_name": space_name,
-                "inviter_name": inviter_name,
-                "invite_token": invite_token,
-                "role": role,
-                "message": message,
-                "expires_in_days": expires_in_days,
+        (format!("http://{}", addr), captured)
+    }
+
+    #[test]
+    fn test_invitation_space_id_is_single_thing() {
+        for input in ["abc", "space:abc", "space:⟨abc⟩", "space:⟨space:abc⟩", "space:⟨⟨space:abc⟩⟩"] {
+            let stored = space_record(input);
+
+            // 接受邀请时直接取 .id，得到的成员 space_id 不应再嵌套
+            let member_space_id = Thing::from(("space", stored.id.to_raw().as_str()));
+            assert_eq!(member_space_id.tb, "space");
+            assert_eq!(member_space_id.id.to_raw(), "abc", "input: {}", input);
+            assert_eq!(member_space_id.to_string(), "space:abc", "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_three_use_link_admits_exactly_three_users() {
+        let mut used_count = 0;
+        let mut admitted = Vec::new();
+
+        for user in ["alice", "bob", "carol", "dave"] {
+            if invitation_has_uses_left(used_count, 3) {
+                used_count += 1;
+                admitted.push(user);
             }
-        });
+        }
 
-        let client = reqwest::Client::new();
-        let response = client
-            .post(&url)
-            .header("X-Internal-API-Key", "todo-implement-api-key") // TODO: 实现内部API密钥
-            .json(&email_data)
-            .send()
-            .await
-            .map_err(|e| AppError::External(format!("Failed to send email: {}", e)))?;
+        assert_eq!(admitted, vec!["alice", "bob", "carol"]);
+        assert!(!invitation_has_uses_left(used_count, 3));
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            error!("Failed to send email notification: {}", error_text);
-            return Err(AppError::External(format!("Email service error: {}", error_text)));
-        } */
+    #[test]
+    fn test_accept_invitation_errors_map_to_conflicts() {
+        let used_up = accept_invitation_error(&[
+            "The query was not executed due to a failed transaction".to_string(),
+            "An error occurred: invitation_used_up".to_string(),
+        ]);
+        assert!(matches!(used_up, AppError::Conflict(msg) if msg == "Invitation has been used up"));
 
-        info!("Sent invitation email to {}", to_email);
-        Ok(())
+        let duplicate = accept_invitation_error(&[
+            "Database index `space_member_unique_idx` already contains [space:s1, 'u1'], with record `space_member:m1`".to_string(),
+        ]);
+        assert!(matches!(duplicate, AppError::Conflict(msg) if msg == "User is already a member of this space"));
+
+        assert!(matches!(accept_invitation_error(&["boom".to_string()]), AppError::Database(_)));
+    }
+
+    #[test]
+    fn test_zero_max_uses_is_unlimited() {
+        assert!(invitation_has_uses_left(0, 0));
+        assert!(invitation_has_uses_left(10_000, 0));
+        assert!(!invitation_has_uses_left(1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_invitation_email_posts_payload_with_api_key() {
+        let (url, captured) = spawn_mock_email_service(StatusCode::OK);
+        let payload = invitation_email_payload(
+            "bob@example.com",
+            "Handbook",
+            "alice",
+            "token-123",
+            "editor",
+            Some("welcome"),
+            7,
+        );
+
+        send_internal_email(&reqwest::Client::new(), &url, "secret-key", &payload).await.unwrap();
+
+        let (api_key, body) = captured.lock().unwrap().take().expect("request not received");
+        assert_eq!(api_key.as_deref(), Some("secret-key"));
+        assert_eq!(body["to"], "bob@example.com");
+        assert_eq!(body["notification_type"], "space_invitation");
+        assert_eq!(body["data"]["space_name"], "Handbook");
+        assert_eq!(body["data"]["inviter_name"], "alice");
+        assert_eq!(body["data"]["invite_token"], "token-123");
+        assert_eq!(body["data"]["role"], "editor");
+        assert_eq!(body["data"]["message"], "welcome");
+        assert_eq!(body["data"]["expires_in_days"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_invitation_email_non_2xx_is_external_error() {
+        let (url, _captured) = spawn_mock_email_service(StatusCode::INTERNAL_SERVER_ERROR);
+        let payload = invitation_email_payload("bob@example.com", "Handbook", "alice", "t", "viewer", None, 7);
+
+        let result = send_internal_email(&reqwest::Client::new(), &url, "secret-key", &payload).await;
+
+        assert!(matches!(result, Err(AppError::External(_))));
     }
-}
\ No newline at end of file
+}
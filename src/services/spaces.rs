@@ -2,35 +2,45 @@ use crate::config::Config;
 use crate::error::{AppError, Result};
 use crate::models::space::{
     Space, SpaceResponse, SpaceListResponse, SpaceListQuery, SpaceStats,
-    CreateSpaceRequest, UpdateSpaceRequest
+    CreateSpaceRequest, UpdateSpaceRequest, TransferOwnershipRequest, ActivityFeedEntry, ActivityFeedResponse,
+    RESERVED_SPACE_SLUGS,
 };
+use crate::models::space_member::MemberRole;
+use crate::services::activity::{actor_display_name, ActivityLogger, ActivityRecord};
 use crate::services::auth::User;
 use crate::services::database::Database;
+use crate::utils::pagination::{PageRequest, Paginated};
+use crate::utils::redact;
+use crate::utils::slug::{slug_from_title, unique_slug};
 use serde_json::Value;
 use std::sync::Arc;
 use surrealdb::sql::Thing;
-use tracing::{info, warn, error, debug};
+use tracing::{debug, error, info, trace};
 use validator::Validate;
 
+/// 自动生成的 slug 长度上限，与 CreateSpaceRequest 上的校验一致
+const MAX_SLUG_CHARS: usize = 50;
+
 pub struct SpaceService {
     db: Arc<Database>,
+    activity: ActivityLogger,
 }
 
 impl SpaceService {
     pub fn new(db: Arc<Database>) -> Self {
-        Self { db }
+        let activity = ActivityLogger::new(db.clone());
+        Self { db, activity }
     }
 
     /// 创建新的文档空间
-    pub async fn create_space(&self, request: CreateSpaceRequest, user: &User) -> Result<SpaceResponse> {
+    pub async fn create_space(&self, mut request: CreateSpaceRequest, user: &User) -> Result<SpaceResponse> {
+        if request.slug.trim().is_empty() {
+            request.slug = self.generate_space_slug(&request.name).await?;
+        }
+
         // 验证输入
         request.validate().map_err(|e| AppError::Validation(e.to_string()))?;
 
-        // 检查slug是否已存在（全局唯一）
-        if self.slug_exists(&request.slug).await? {
-            return Err(AppError::Conflict("Space slug already exists globally. Please choose a different slug.".to_string()));
-        }
-
         // 创建空间对象
         let mut space = Space::new(
             request.name,
@@ -54,14 +64,18 @@ impl SpaceService {
             space.settings = settings;
         }
 
-        // 保存到数据库
+        // 保存到数据库（slug 全局唯一由唯一索引保证，并发创建时只有一个会成功）
         let created_spaces: Vec<Space> = self.db.client
             .create("space")
             .content(space)
             .await
             .map_err(|e| {
                 error!("Failed to create space: {}", e);
-                AppError::Database(e)
+                AppError::from_unique_violation(
+                    e,
+                    "space_slug_unique_idx",
+                    "Space slug already exists globally. Please choose a different slug.",
+                )
             })?;
 
         let created_space = created_spaces.into_iter().next();
@@ -71,68 +85,33 @@ impl SpaceService {
             AppError::Internal(anyhow::anyhow!("Failed to create space"))
         })?;
 
-        info!("Created new space: {} by user: {}", request.slug, user.id);
+        info!("Created new space: {} by user: {}", request.slug, redact::user_id(&user.id));
 
         // 记录活动日志
-        self.log_activity(&user.id, "space_created", "space", &created_space.id.as_ref().unwrap_or(&String::new())).await?;
+        let space_id = created_space.id.clone().unwrap_or_default();
+        self.log_space_activity(user, "space_created", &space_id, &created_space.name).await;
 
         Ok(SpaceResponse::from(created_space))
     }
 
     /// 获取空间列表
     pub async fn list_spaces(&self, query: SpaceListQuery, user: Option<&User>) -> Result<SpaceListResponse> {
-        let page = query.page.unwrap_or(1);
-        let limit = query.limit.unwrap_or(20);
-        let offset = (page - 1) * limit;
-
-        // 构建查询条件
-        let mut where_conditions = Vec::new();
-        let mut params: std::collections::HashMap<String, serde_json::Value> = std::collections::HashMap::new();
-
-        // 权限过滤：只显示用户拥有的空间或用户加入的空间
-        // 公开空间应该通过直接链接访问，而不是在列表中显示
-        if let Some(user) = user {
-            info!("Listing spaces for user: {}", user.id);
-            
-            // 由于SurrealDB子查询语法问题，分两步获取：
-            // 1. 先获取用户拥有的空间
-            // 2. 再获取用户加入的空间，然后合并
-            
-            // 这里先查询用户拥有的空间
-            where_conditions.push("owner_id = $user_id");
-            params.insert("user_id".to_string(), user.id.clone().into());
-        } else {
-            // 未登录用户看不到任何空间列表
-            where_conditions.push("1 = 0");
-        }
-
-        // 基础过滤条件
-        where_conditions.push("is_deleted = false");
-
-        // 搜索过滤
-        if let Some(search) = &query.search {
-            where_conditions.push("(string::lowercase(name) CONTAINS string::lowercase($search) OR string::lowercase(description) CONTAINS string::lowercase($search))");
-            params.insert("search".to_string(), search.clone().into());
-        }
-
-        // 所有者过滤
-        if let Some(owner_id) = &query.owner_id {
-            where_conditions.push("owner_id = $owner_id");
-            params.insert("owner_id".to_string(), owner_id.clone().into());
-        }
-
-        // 公开性过滤
-        if let Some(is_public) = query.is_public {
-            where_conditions.push("is_public = $is_public");
-            params.insert("is_public".to_string(), is_public.into());
-        }
-
-        let where_clause = if where_conditions.is_empty() {
-            String::new()
-        } else {
-            format!("WHERE {}", where_conditions.join(" AND "))
+        let page = PageRequest::new(query.page, query.limit);
+
+        // 用户加入的空间（已接受的成员），与拥有的空间在同一个查询中过滤，保证分页和总数一致
+        let member_space_ids: Vec<Thing> = match user {
+            Some(user) => self
+                .get_user_member_space_ids(&user.id)
+                .await?
+                .iter()
+                .map(|id| Thing::from(("space", id.as_str())))
+                .collect(),
+            None => Vec::new(),
         };
 
+        let (where_conditions, params) = build_space_list_filter(&query, user.map(|u| u.id.as_str()));
+        let where_clause = format!("WHERE {}", where_conditions.join(" AND "));
+
         // 排序
         let sort_field = query.sort.unwrap_or_else(|| "updated_at".to_string());
         let sort_order = query.order.unwrap_or_else(|| "desc".to_string());
@@ -143,60 +122,41 @@ impl SpaceService {
         let count_result: Vec<serde_json::Value> = self.db.client
             .query(&count_query)
             .bind(params.clone())
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .bind(("member_space_ids", &member_space_ids))
+            .await?
             .take(0)?;
             
         let total = count_result.first()
             .and_then(|v| v.get("total"))
             .and_then(|v| v.as_u64())
             .unwrap_or(0) as u32;
-        let total_pages = (total + limit - 1) / limit;
 
         // 查询数据
         let data_query = format!(
             "SELECT * FROM space {} {} LIMIT {} START {}",
-            where_clause, order_clause, limit, offset
+            where_clause, order_clause, page.limit, page.offset()
         );
 
-        info!("Executing space list query: {}", data_query);
-        info!("Query params: {:?}", params);
+        debug!("Executing space list query: {}", data_query);
+        debug!("Query params: {:?}", params);
 
         // 首先获取数据库格式的数据
         let spaces_db: Vec<crate::models::space::SpaceDb> = self.db.client
             .query(&data_query)
             .bind(params)
+            .bind(("member_space_ids", &member_space_ids))
             .await
             .map_err(|e| {
                 error!("Failed to execute space list query: {}", e);
-                AppError::Database(e)
+                AppError::from(e)
             })?
             .take(0)?;
         
         // 转换为 Space 类型
-        let mut spaces: Vec<Space> = spaces_db.into_iter()
+        let spaces: Vec<Space> = spaces_db.into_iter()
             .map(|db| db.into())
             .collect();
 
-        // 如果是登录用户，还需要添加用户作为成员的空间
-        if let Some(user) = user {
-            let member_spaces = self.get_user_member_spaces(&user.id).await?;
-            info!("Found {} member spaces for user {}", member_spaces.len(), user.id);
-            
-            // 合并空间，避免重复
-            let existing_ids: std::collections::HashSet<String> = spaces.iter()
-                .filter_map(|s| s.id.clone())
-                .collect();
-            
-            for member_space in member_spaces {
-                if let Some(space_id) = &member_space.id {
-                    if !existing_ids.contains(space_id) {
-                        spaces.push(member_space);
-                    }
-                }
-            }
-        }
-
         // 转换为响应格式
         let mut space_responses = Vec::new();
         for space in spaces {
@@ -210,13 +170,18 @@ impl SpaceService {
 
         debug!("Listed {} spaces for user: {:?}", space_responses.len(), user.map(|u| &u.id));
 
-        Ok(SpaceListResponse {
-            spaces: space_responses,
-            total,
-            page,
-            limit,
-            total_pages,
-        })
+        Ok(Paginated::new(space_responses, total, page).into())
+    }
+
+    /// 获取用户拥有的未删除空间ID（不含"space:"前缀）
+    pub async fn get_owned_space_ids(&self, user_id: &str) -> Result<Vec<String>> {
+        let ids: Vec<Thing> = self.db.client
+            .query("SELECT VALUE id FROM space WHERE owner_id = $user_id AND is_deleted = false")
+            .bind(("user_id", user_id))
+            .await?
+            .take(0)?;
+
+        Ok(ids.into_iter().map(|id| id.id.to_string()).collect())
     }
 
     /// 根据slug获取空间详情
@@ -224,15 +189,14 @@ impl SpaceService {
         let space_db: Option<crate::models::space::SpaceDb> = self.db.client
             .query("SELECT * FROM space WHERE slug = $slug AND is_deleted = false")
             .bind(("slug", slug))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
         let space_db = space_db.ok_or_else(|| AppError::NotFound("Space not found".to_string()))?;
         let space: Space = space_db.into();
 
         // 检查基础访问权限（公开性和所有者）
-        if !space.can_access(user.map(|u| u.id.as_str())) {
+        if !can_access_space(&space, user) {
             // 注意：这里应该集成SpaceMemberService的权限检查
             // 但为了避免循环依赖，建议在调用方进行额外的成员权限检查
             return Err(AppError::Authorization("Access denied to this space".to_string()));
@@ -253,21 +217,20 @@ impl SpaceService {
     /// 根据ID获取空间详情
     pub async fn get_space_by_id(&self, id: &str, user: Option<&User>) -> Result<SpaceResponse> {
         let query_id = format!("space:{}", id);
-        info!("get_space_by_id: searching for id = {}", query_id);
+        debug!("get_space_by_id: searching for id = {}", query_id);
         
         let space_db: Option<crate::models::space::SpaceDb> = self.db.client
             .query("SELECT * FROM $id WHERE is_deleted = false")
             .bind(("id", Thing::from(("space", id))))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
-        info!("get_space_by_id: query result = {:?}", space_db.is_some());
+        debug!("get_space_by_id: query result = {:?}", space_db.is_some());
         let space_db = space_db.ok_or_else(|| AppError::NotFound("Space not found".to_string()))?;
         let space: Space = space_db.into();
 
         // 检查基础访问权限（公开性和所有者）
-        if !space.can_access(user.map(|u| u.id.as_str())) {
+        if !can_access_space(&space, user) {
             // 注意：这里应该集成SpaceMemberService的权限检查
             // 但为了避免循环依赖，建议在调用方进行额外的成员权限检查
             return Err(AppError::Authorization("Access denied to this space".to_string()));
@@ -294,9 +257,7 @@ impl SpaceService {
         let existing_space = self.get_space_by_slug(slug, Some(user)).await?;
 
         // 检查权限：只有所有者可以更新
-        if existing_space.owner_id != user.id {
-            return Err(AppError::Authorization("Only space owner can update space".to_string()));
-        }
+        ensure_space_owner(&existing_space, &user.id, "Only space owner can update space")?;
 
         // 构建更新数据
         let mut update_data = std::collections::HashMap::new();
@@ -328,8 +289,7 @@ impl SpaceService {
             .query("UPDATE space SET $data WHERE slug = $slug RETURN AFTER")
             .bind(("data", update_data))
             .bind(("slug", slug))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take((0, "AFTER"))?;
 
         let updated_space_db = updated_space_db.ok_or_else(|| {
@@ -337,10 +297,11 @@ impl SpaceService {
         })?;
         let updated_space: Space = updated_space_db.into();
 
-        info!("Updated space: {} by user: {}", slug, user.id);
+        info!("Updated space: {} by user: {}", slug, redact::user_id(&user.id));
 
         // 记录活动日志
-        self.log_activity(&user.id, "space_updated", "space", &updated_space.id.as_ref().unwrap_or(&String::new())).await?;
+        let space_id = updated_space.id.clone().unwrap_or_default();
+        self.log_space_activity(user, "space_updated", &space_id, &updated_space.name).await;
 
         Ok(SpaceResponse::from(updated_space))
     }
@@ -351,50 +312,138 @@ impl SpaceService {
         let existing_space = self.get_space_by_slug(slug, Some(user)).await?;
 
         // 检查权限：只有所有者可以删除
-        if existing_space.owner_id != user.id {
-            return Err(AppError::Authorization("Only space owner can delete space".to_string()));
-        }
+        ensure_space_owner(&existing_space, &user.id, "Only space owner can delete space")?;
 
-        // 检查空间是否有文档
-        let doc_count: Option<u32> = self.db.client
-            .query("SELECT count() FROM document WHERE space_id = $space_id")
-            .bind(("space_id", format!("space:{}", existing_space.id)))
-            .await
-            .map_err(|e| AppError::Database(e))?
-            .take((0, "count"))?;
+        // 检查空间是否有文档（包括回收站中的文档）
+        let raw_space_id = existing_space.id.strip_prefix("space:").unwrap_or(&existing_space.id);
+        let doc_count: Option<u64> = self.db.client
+            .query("SELECT count() AS total FROM document WHERE space_id = $space_id GROUP ALL")
+            .bind(("space_id", Thing::from(("space", raw_space_id))))
+            .await?
+            .take((0, "total"))?;
 
-        if let Some(count) = doc_count {
-            if count > 0 {
-                return Err(AppError::Conflict("Cannot delete space with existing documents".to_string()));
-            }
-        }
+        ensure_space_empty(doc_count.unwrap_or(0))?;
 
         // 删除空间
         let _: Option<crate::models::space::SpaceDb> = self.db.client
             .query("DELETE space WHERE slug = $slug")
             .bind(("slug", slug))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take(0)?;
 
-        info!("Deleted space: {} by user: {}", slug, user.id);
+        info!("Deleted space: {} by user: {}", slug, redact::user_id(&user.id));
 
         // 记录活动日志
-        self.log_activity(&user.id, "space_deleted", "space", &existing_space.id).await?;
+        self.log_space_activity(user, "space_deleted", &existing_space.id, &existing_space.name).await;
 
         Ok(())
     }
 
-    /// 检查slug是否已存在（全局检查）
-    async fn slug_exists(&self, slug: &str) -> Result<bool> {
-        let existing: Option<crate::models::space::SpaceDb> = self.db.client
-            .query("SELECT id FROM space WHERE slug = $slug AND is_deleted = false LIMIT 1")
-            .bind(("slug", slug))
-            .await
-            .map_err(|e| AppError::Database(e))?
+    /// 归档空间，归档后空间内容只读
+    pub async fn archive_space(&self, slug: &str, user: &User) -> Result<SpaceResponse> {
+        self.set_archived(slug, user, true).await
+    }
+
+    /// 取消归档，恢复空间写入
+    pub async fn unarchive_space(&self, slug: &str, user: &User) -> Result<SpaceResponse> {
+        self.set_archived(slug, user, false).await
+    }
+
+    async fn set_archived(&self, slug: &str, user: &User, is_archived: bool) -> Result<SpaceResponse> {
+        let existing_space = self.get_space_by_slug(slug, Some(user)).await?;
+        let message = if is_archived {
+            "Only space owner can archive space"
+        } else {
+            "Only space owner can unarchive space"
+        };
+        ensure_space_owner(&existing_space, &user.id, message)?;
+
+        let raw_space_id = existing_space.id.strip_prefix("space:").unwrap_or(&existing_space.id);
+        let updated_space_db: Option<crate::models::space::SpaceDb> = self.db.client
+            .query("UPDATE $space_id SET is_archived = $is_archived, updated_at = time::now() RETURN AFTER")
+            .bind(("space_id", Thing::from(("space", raw_space_id))))
+            .bind(("is_archived", is_archived))
+            .await?
             .take(0)?;
+        let updated_space: Space = updated_space_db
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Failed to update space archive state")))?
+            .into();
 
-        Ok(existing.is_some())
+        info!("Space {} archived={} by user: {}", slug, is_archived, redact::user_id(&user.id));
+
+        let action = if is_archived { "space_archived" } else { "space_unarchived" };
+        self.log_space_activity(user, action, raw_space_id, &updated_space.name).await;
+
+        Ok(SpaceResponse::from(updated_space))
+    }
+
+    /// 转让空间所有权：新所有者必须是已接受的成员（不是则自动以管理员身份加入），
+    /// 原所有者降为管理员
+    pub async fn transfer_ownership(&self, slug: &str, request: TransferOwnershipRequest, current_owner: &User) -> Result<SpaceResponse> {
+        request.validate().map_err(|e| AppError::Validation(e.to_string()))?;
+
+        let existing_space = self.get_space_by_slug(slug, Some(current_owner)).await?;
+        ensure_space_owner(&existing_space, &current_owner.id, "Only space owner can transfer ownership")?;
+
+        let new_owner_id = request.new_owner_id.trim();
+        let new_owner_id = new_owner_id.strip_prefix("user:").unwrap_or(new_owner_id);
+        if new_owner_id == current_owner.id {
+            return Err(AppError::Validation("New owner must be different from the current owner".to_string()));
+        }
+
+        let raw_space_id = existing_space.id.strip_prefix("space:").unwrap_or(&existing_space.id);
+        let space_thing = Thing::from(("space", raw_space_id));
+
+        let accepted: Vec<Value> = self.db.client
+            .query("SELECT id FROM space_member WHERE space_id = $space_id AND user_id = $user_id AND status = 'accepted'")
+            .bind(("space_id", &space_thing))
+            .bind(("user_id", new_owner_id))
+            .await?
+            .take(0)?;
+        if accepted.is_empty() {
+            info!("New owner {} is not a member of space {}, adding as admin", redact::user_id(new_owner_id), slug);
+        }
+
+        // 补成员记录和修改所有者在同一个事务中完成，任一步失败都不会留下一半的转让
+        let mut response = self.db.client
+            .query(transfer_ownership_statements(accepted.is_empty()))
+            .bind(("space_id", &space_thing))
+            .bind(("new_owner_id", new_owner_id))
+            .bind(("previous_owner_id", &current_owner.id))
+            .bind(("permissions", MemberRole::Admin.default_permissions()))
+            .await?
+            .check()?;
+
+        // 最后一条语句返回更新后的空间
+        let last_statement = response.num_statements() - 1;
+        let updated_space_db: Option<crate::models::space::SpaceDb> = response.take(last_statement)?;
+        let updated_space: Space = updated_space_db
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Failed to transfer space ownership")))?
+            .into();
+
+        info!("Transferred ownership of space {} from {} to {}", slug, redact::user_id(&current_owner.id), redact::user_id(new_owner_id));
+
+        let mut record = ActivityRecord::new(&current_owner.id, "space_ownership_transferred", "space", raw_space_id, raw_space_id)
+            .with_actor_name(actor_display_name(current_owner))
+            .with_title(&updated_space.name);
+        record.details["new_owner_id"] = Value::String(new_owner_id.to_string());
+        self.activity.log(record).await;
+
+        Ok(SpaceResponse::from(updated_space))
+    }
+
+    /// 根据名称生成全局未被占用的空间 slug，重名时追加 -2、-3 等后缀
+    async fn generate_space_slug(&self, name: &str) -> Result<String> {
+        let base = slug_from_title(name, MAX_SLUG_CHARS);
+
+        let mut taken: Vec<String> = self.db.client
+            .query("SELECT VALUE slug FROM space WHERE string::starts_with(slug, $base)")
+            .bind(("base", &base))
+            .await?
+            .take(0)?;
+        taken.extend(RESERVED_SPACE_SLUGS.iter().map(|slug| slug.to_string()));
+
+        Ok(unique_slug(&base, &taken, MAX_SLUG_CHARS))
     }
 
     /// 获取空间统计信息
@@ -403,40 +452,35 @@ impl SpaceService {
         let doc_count: Option<u32> = self.db.client
             .query("SELECT count() FROM document WHERE space_id = $space_id")
             .bind(("space_id", format!("space:{}", space_id)))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take((0, "count"))?;
 
         // 查询公开文档数量
         let public_doc_count: Option<u32> = self.db.client
             .query("SELECT count() FROM document WHERE space_id = $space_id AND is_public = true")
             .bind(("space_id", format!("space:{}", space_id)))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take((0, "count"))?;
 
         // 查询评论数量
         let comment_count: Option<u32> = self.db.client
             .query("SELECT count() FROM comment WHERE document_id IN (SELECT id FROM document WHERE space_id = $space_id)")
             .bind(("space_id", format!("space:{}", space_id)))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take((0, "count"))?;
 
         // 查询总浏览量
         let view_count: Option<u32> = self.db.client
             .query("SELECT math::sum(view_count) AS total_views FROM document WHERE space_id = $space_id")
             .bind(("space_id", format!("space:{}", space_id)))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take((0, "total_views"))?;
 
         // 查询最后活动时间
         let last_activity: Option<String> = self.db.client
             .query("SELECT updated_at FROM document WHERE space_id = $space_id ORDER BY updated_at DESC LIMIT 1")
             .bind(("space_id", format!("space:{}", space_id)))
-            .await
-            .map_err(|e| AppError::Database(e))?
+            .await?
             .take((0, "updated_at"))?;
 
         let last_activity = last_activity
@@ -452,9 +496,9 @@ impl SpaceService {
         })
     }
 
-    /// 获取用户作为成员的空间列表
-    async fn get_user_member_spaces(&self, user_id: &str) -> Result<Vec<Space>> {
-        info!("Getting member spaces for user: {}", user_id);
+    /// 获取用户作为已接受成员加入的空间ID（不含"space:"前缀）
+    async fn get_user_member_space_ids(&self, user_id: &str) -> Result<Vec<String>> {
+        debug!("Getting member spaces for user: {}", redact::user_id(user_id));
         
         // 清理user_id格式，去掉user:前缀以匹配数据库存储格式
         let clean_user_id = if user_id.starts_with("user:") {
@@ -462,7 +506,7 @@ impl SpaceService {
         } else {
             user_id
         };
-        info!("Querying member spaces with cleaned user_id: {} (original: {})", clean_user_id, user_id);
+        debug!("Querying member spaces with cleaned user_id: {} (original: {})", redact::user_id(clean_user_id), redact::user_id(user_id));
         
         // 查询用户是成员的space_id列表
         let member_query = "SELECT space_id FROM space_member WHERE user_id = $user_id AND status = 'accepted'";
@@ -472,31 +516,30 @@ impl SpaceService {
             .await
             .map_err(|e| {
                 error!("Failed to query space members: {}", e);
-                AppError::Database(e)
+                AppError::from(e)
             })?
             .take(0)?;
             
         // 如果没有找到结果，尝试查看所有space_member记录进行调试
         if member_results.is_empty() {
-            info!("No member spaces found for cleaned user_id: {} (original: {}), checking all space_member records for debugging", clean_user_id, user_id);
+            debug!("No member spaces found for cleaned user_id: {} (original: {}), checking all space_member records for debugging", redact::user_id(clean_user_id), redact::user_id(user_id));
             let all_members: Vec<serde_json::Value> = self.db.client
                 .query("SELECT user_id, space_id, status FROM space_member LIMIT 5")
-                .await
-                .map_err(|e| AppError::Database(e))?
+                .await?
                 .take(0)?;
             
             for member in &all_members {
-                info!("Found space_member record: {:?}", member);
+                trace!("Found space_member record: {:?}", member);
             }
         }
         
-        info!("Found {} space member records for user {}", member_results.len(), user_id);
+        debug!("Found {} space member records for user {}", member_results.len(), redact::user_id(user_id));
         
         // 提取space_id列表
         let mut space_ids = Vec::new();
         for result in member_results {
             if let Some(space_id_value) = result.get("space_id") {
-                info!("Processing space_id value: {:?}", space_id_value);
+                debug!("Processing space_id value: {:?}", space_id_value);
                 
                 // 处理SurrealDB Thing对象格式
                 if let Some(space_id_obj) = space_id_value.as_object() {
@@ -505,7 +548,7 @@ impl SpaceService {
                             if let Some(actual_id) = id_obj.get("String") {
                                 if let Some(space_id_str) = actual_id.as_str() {
                                     space_ids.push(space_id_str.to_string());
-                                    info!("Found member space_id: {}", space_id_str);
+                                    debug!("Found member space_id: {}", space_id_str);
                                 }
                             }
                         }
@@ -519,64 +562,279 @@ impl SpaceService {
                         space_id_str
                     };
                     space_ids.push(clean_id.to_string());
-                    info!("Found member space_id (string format): {}", clean_id);
+                    debug!("Found member space_id (string format): {}", clean_id);
                 }
             }
         }
         
-        if space_ids.is_empty() {
-            info!("No member spaces found for user {}", user_id);
-            return Ok(Vec::new());
-        }
-        
-        // 查询对应的space记录
-        let mut spaces = Vec::new();
-        for space_id in space_ids {
-            let space_query = "SELECT * FROM space WHERE id = $space_id AND is_deleted = false";
-            let space_results: Vec<crate::models::space::SpaceDb> = self.db.client
-                .query(space_query)
-                .bind(("space_id", Thing::from(("space", space_id.as_str()))))
-                .await
-                .map_err(|e| {
-                    error!("Failed to query space: {}", e);
-                    AppError::Database(e)
-                })?
+        debug!("Found {} member spaces for user {}", space_ids.len(), redact::user_id(user_id));
+        Ok(space_ids)
+    }
+
+    /// 记录活动日志
+    async fn log_space_activity(&self, user: &User, action: &str, space_id: &str, name: &str) {
+        let record = ActivityRecord::new(&user.id, action, "space", space_id, space_id)
+            .with_actor_name(actor_display_name(user))
+            .with_title(name);
+        self.activity.log(record).await;
+    }
+
+    /// 获取空间动态，按时间倒序分页
+    pub async fn get_space_activity(&self, space_id: &str, page: PageRequest) -> Result<ActivityFeedResponse> {
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+
+        let query = format!(
+            "SELECT * FROM activity_log WHERE space_id = $space_id ORDER BY created_at DESC LIMIT {} START {};
+             SELECT count() AS total FROM activity_log WHERE space_id = $space_id GROUP ALL;",
+            page.limit, page.offset()
+        );
+
+        let mut response = self.db.client
+            .query(query)
+            .bind(("space_id", space_id))
+            .await?;
+
+        let rows: Vec<ActivityLogRow> = response.take(0)?;
+        let total: Option<u32> = response.take((1, "total"))?;
+        let total = total.unwrap_or(0);
+
+        // 文档标题取当前值（包括已删除文档），查不到时使用写入时的快照
+        let document_ids: Vec<Thing> = rows
+            .iter()
+            .filter(|row| row.resource_type == "document")
+            .map(|row| Thing::from(("document", row.resource_id.as_str())))
+            .collect();
+
+        let mut titles = std::collections::HashMap::new();
+        if !document_ids.is_empty() {
+            let documents: Vec<TitleRow> = self.db.client
+                .query("SELECT id, title FROM document WHERE id INSIDE $ids")
+                .bind(("ids", document_ids))
+                .await?
                 .take(0)?;
-                
-            for space_db in space_results {
-                spaces.push(space_db.into());
+
+            for document in documents {
+                titles.insert(document.id.id.to_string(), document.title);
             }
         }
-        
-        info!("Retrieved {} actual spaces for user {}", spaces.len(), user_id);
-        Ok(spaces)
+
+        Ok(Paginated::new(rows, total, page)
+            .map(|row| build_feed_entry(row, &titles))
+            .into())
     }
+}
 
-    /// 记录活动日志
-    async fn log_activity(&self, user_id: &str, action: &str, resource_type: &str, resource_id: &str) -> Result<()> {
-        let activity = serde_json::json!({
-            "user_id": user_id,
-            "action": action,
-            "resource_type": resource_type,
-            "resource_id": resource_id,
-            "metadata": {},
-            "created_at": chrono::Utc::now()
-        });
-
-        let _: Option<Value> = self.db.client
-            .create("activity_log")
-            .content(activity)
-            .await
-            .map_err(|e| {
-                warn!("Failed to log activity: {}", e);
-                e
-            })
-            .ok()
-            .and_then(|mut result| result.pop());
+/// activity_log 原始记录
+#[derive(Debug, serde::Deserialize)]
+struct ActivityLogRow {
+    id: Thing,
+    user_id: String,
+    action: String,
+    resource_type: String,
+    resource_id: String,
+    #[serde(default)]
+    details: Value,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
 
-        Ok(())
+#[derive(Debug, serde::Deserialize)]
+struct TitleRow {
+    id: Thing,
+    title: String,
+}
+
+/// 将原始日志转换为可展示的动态条目
+fn build_feed_entry(row: ActivityLogRow, titles: &std::collections::HashMap<String, String>) -> ActivityFeedEntry {
+    let snapshot_title = row.details.get("title").and_then(|v| v.as_str()).map(str::to_string);
+    let resource_title = titles.get(&row.resource_id).cloned().or(snapshot_title);
+    let actor_name = row.details
+        .get("actor_name")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| row.user_id.clone());
+
+    let summary = describe_activity(&actor_name, &row.action, &row.resource_type, resource_title.as_deref());
+
+    ActivityFeedEntry {
+        id: row.id.to_string(),
+        action: row.action,
+        resource_type: row.resource_type,
+        resource_id: row.resource_id,
+        resource_title,
+        actor_id: row.user_id,
+        actor_name,
+        summary,
+        created_at: row.created_at,
     }
+}
 
+/// 生成动态描述，如 `alice created document "Intro"`
+fn describe_activity(actor: &str, action: &str, resource_type: &str, title: Option<&str>) -> String {
+    if action == "member_joined" {
+        return format!("{} joined the space", actor);
+    }
+    if action == "space_ownership_transferred" {
+        return match title {
+            Some(title) => format!("{} transferred ownership of space \"{}\"", actor, title),
+            None => format!("{} transferred ownership of the space", actor),
+        };
+    }
+
+    // action 约定为 `<resource_type>_<verb>`
+    let verb = action
+        .strip_prefix(resource_type)
+        .and_then(|rest| rest.strip_prefix('_'))
+        .map(str::to_string)
+        .unwrap_or_else(|| action.replace('_', " "));
+
+    match title {
+        Some(title) => format!("{} {} {} \"{}\"", actor, verb, resource_type, title),
+        None => format!("{} {} {}", actor, verb, resource_type),
+    }
+}
+
+/// 构建空间列表的过滤条件。
+/// 登录用户能看到自己拥有的空间和作为已接受成员加入的空间（`$member_space_ids` 由调用方绑定），
+/// 公开空间通过直接链接访问，不出现在列表中；未登录用户看不到任何空间
+fn build_space_list_filter(
+    query: &SpaceListQuery,
+    user_id: Option<&str>,
+) -> (Vec<&'static str>, std::collections::HashMap<String, serde_json::Value>) {
+    let mut where_conditions = Vec::new();
+    let mut params: std::collections::HashMap<String, serde_json::Value> = std::collections::HashMap::new();
+
+    if let Some(user_id) = user_id {
+        where_conditions.push("(owner_id = $user_id OR id INSIDE $member_space_ids)");
+        params.insert("user_id".to_string(), user_id.into());
+    } else {
+        where_conditions.push("1 = 0");
+    }
+
+    // 基础过滤条件
+    where_conditions.push("is_deleted = false");
+
+    // 搜索过滤
+    if let Some(search) = &query.search {
+        where_conditions.push("(string::lowercase(name) CONTAINS string::lowercase($search) OR string::lowercase(description) CONTAINS string::lowercase($search))");
+        params.insert("search".to_string(), search.clone().into());
+    }
+
+    // 所有者过滤
+    if let Some(owner_id) = &query.owner_id {
+        where_conditions.push("owner_id = $owner_id");
+        params.insert("owner_id".to_string(), owner_id.clone().into());
+    }
+
+    // 公开性过滤
+    if let Some(is_public) = query.is_public {
+        where_conditions.push("is_public = $is_public");
+        params.insert("is_public".to_string(), is_public.into());
+    }
+
+    // 默认隐藏已归档的空间
+    if !query.include_archived.unwrap_or(false) {
+        where_conditions.push("is_archived != true");
+    }
+
+    (where_conditions, params)
+}
+
+/// 检查空间是否可写，已归档的空间只允许读取和导出
+pub async fn ensure_space_writable(db: &Database, space_id: &str) -> Result<()> {
+    let raw_space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+    let is_archived: Option<bool> = db.client
+        .query("SELECT VALUE is_archived ?? false FROM space WHERE id = $space_id")
+        .bind(("space_id", Thing::from(("space", raw_space_id))))
+        .await?
+        .take(0)?;
+
+    ensure_not_archived(is_archived.unwrap_or(false))
+}
+
+/// 检查文档所在空间是否可写
+pub async fn ensure_document_space_writable(db: &Database, document_id: &str) -> Result<()> {
+    let raw_document_id = document_id.strip_prefix("document:").unwrap_or(document_id);
+    let is_archived: Option<bool> = db.client
+        .query("SELECT VALUE space_id.is_archived ?? false FROM document WHERE id = $document_id")
+        .bind(("document_id", Thing::from(("document", raw_document_id))))
+        .await?
+        .take(0)?;
+
+    ensure_not_archived(is_archived.unwrap_or(false))
+}
+
+/// 基础访问检查（公开性和所有者），API 密钥只能访问其所属空间
+fn can_access_space(space: &Space, user: Option<&User>) -> bool {
+    let key_scope_matches = user
+        .and_then(|u| u.api_key.as_ref())
+        .zip(space.id.as_deref())
+        .is_some_and(|(scope, space_id)| scope.covers_space(space_id));
+
+    key_scope_matches || space.can_access(user.map(|u| u.id.as_str()))
+}
+
+fn ensure_not_archived(is_archived: bool) -> Result<()> {
+    if is_archived {
+        return Err(AppError::Authorization("Space is archived".to_string()));
+    }
+    Ok(())
+}
+
+/// 只有空间所有者可以执行的操作（更新、删除、转让）
+fn ensure_space_owner(space: &SpaceResponse, user_id: &str, message: &str) -> Result<()> {
+    if space.owner_id != user_id {
+        return Err(AppError::Authorization(message.to_string()));
+    }
+    Ok(())
+}
+
+/// 将 $user_param 对应的用户设为空间的已接受管理员，已有成员记录（包括待接受或已移除的）时直接更新
+fn upsert_admin_member_statement(user_param: &str, invited_by_param: &str) -> String {
+    format!(
+        "IF array::len((SELECT id FROM space_member WHERE space_id = $space_id AND user_id = ${user})) > 0 THEN
+            (UPDATE space_member SET
+                role = 'admin',
+                permissions = $permissions,
+                status = 'accepted',
+                accepted_at = accepted_at ?? time::now(),
+                updated_at = time::now()
+            WHERE space_id = $space_id AND user_id = ${user})
+        ELSE
+            (CREATE space_member SET
+                space_id = $space_id,
+                user_id = ${user},
+                role = 'admin',
+                permissions = $permissions,
+                invited_by = ${invited_by},
+                status = 'accepted',
+                accepted_at = time::now())
+        END;",
+        user = user_param,
+        invited_by = invited_by_param,
+    )
+}
+
+/// 转让所有权的事务：新所有者不是已接受成员时补为管理员；原所有者保留管理员成员记录，
+/// 转让后仍能访问空间；最后修改所有者并返回更新后的空间
+fn transfer_ownership_statements(add_new_owner_member: bool) -> String {
+    let mut sql = String::from("BEGIN TRANSACTION;\n");
+    if add_new_owner_member {
+        sql.push_str(&upsert_admin_member_statement("new_owner_id", "previous_owner_id"));
+        sql.push('\n');
+    }
+    sql.push_str(&upsert_admin_member_statement("previous_owner_id", "new_owner_id"));
+    sql.push('\n');
+    sql.push_str("UPDATE $space_id SET owner_id = $new_owner_id, updated_at = time::now() RETURN AFTER;\n");
+    sql.push_str("COMMIT TRANSACTION;");
+    sql
+}
+
+/// 有文档的空间不能删除
+fn ensure_space_empty(document_count: u64) -> Result<()> {
+    if document_count > 0 {
+        return Err(AppError::Conflict("Cannot delete space with existing documents".to_string()));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -623,4 +881,322 @@ mod tests {
 
         assert!(invalid_request.validate().is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_created_document_produces_readable_feed_entry() {
+        let row = ActivityLogRow {
+            id: Thing::from(("activity_log", "a1")),
+            user_id: "user-1".to_string(),
+            action: "document_created".to_string(),
+            resource_type: "document".to_string(),
+            resource_id: "doc-1".to_string(),
+            details: serde_json::json!({ "actor_name": "alice", "title": "Draft title" }),
+            created_at: chrono::Utc::now(),
+        };
+        // 当前标题优先于写入时的快照
+        let titles = std::collections::HashMap::from([("doc-1".to_string(), "Intro".to_string())]);
+
+        let entry = build_feed_entry(row, &titles);
+
+        assert_eq!(entry.actor_id, "user-1");
+        assert_eq!(entry.actor_name, "alice");
+        assert_eq!(entry.resource_title.as_deref(), Some("Intro"));
+        assert_eq!(entry.summary, "alice created document \"Intro\"");
+    }
+
+    #[test]
+    fn test_feed_entry_falls_back_to_snapshot_and_user_id() {
+        let row = ActivityLogRow {
+            id: Thing::from(("activity_log", "a2")),
+            user_id: "user-2".to_string(),
+            action: "member_removed".to_string(),
+            resource_type: "member".to_string(),
+            resource_id: "user-3".to_string(),
+            details: Value::Null,
+            created_at: chrono::Utc::now(),
+        };
+
+        let entry = build_feed_entry(row, &std::collections::HashMap::new());
+
+        assert_eq!(entry.summary, "user-2 removed member");
+        assert_eq!(describe_activity("bob", "member_joined", "member", None), "bob joined the space");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_logged_activity_is_read_back_in_space_feed() {
+        let db = crate::services::database::test_database().await;
+        let service = SpaceService::new(db.clone());
+        let space_id = format!("feed-test-{}", uuid::Uuid::new_v4().simple());
+
+        ActivityLogger::new(db)
+            .log(
+                ActivityRecord::new("user-1", "document_created", "document", "document:feed-doc", &format!("space:{}", space_id))
+                    .with_actor_name("alice")
+                    .with_title("Intro"),
+            )
+            .await;
+
+        let feed = service.get_space_activity(&space_id, PageRequest::new(None, None)).await.unwrap();
+
+        assert_eq!(feed.total, 1);
+        let entry = &feed.activities[0];
+        assert_eq!(entry.actor_id, "user-1");
+        assert_eq!(entry.resource_id, "feed-doc");
+        // 文档不存在时使用写入时的快照
+        assert_eq!(entry.summary, "alice created document \"Intro\"");
+    }
+
+    #[test]
+    fn test_transfer_statements_keep_both_owners_as_members() {
+        let sql = transfer_ownership_statements(true);
+        assert!(sql.starts_with("BEGIN TRANSACTION;") && sql.ends_with("COMMIT TRANSACTION;"));
+        // 新所有者补为管理员，邀请人为原所有者；原所有者同样保留管理员记录
+        assert!(sql.contains("user_id = $new_owner_id,"));
+        assert!(sql.contains("invited_by = $previous_owner_id,"));
+        assert!(sql.contains("user_id = $previous_owner_id,"));
+        // 修改所有者是最后一条语句，返回值就是更新后的空间
+        assert!(sql.trim_end_matches("COMMIT TRANSACTION;").trim_end().ends_with("RETURN AFTER;"));
+
+        // 新所有者已是成员时不改动其角色
+        assert!(!transfer_ownership_statements(false).contains("user_id = $new_owner_id"));
+    }
+
+    fn test_user(name: &str, run: &str) -> User {
+        User {
+            id: format!("{}-{}", name, run),
+            email: format!("{}@example.com", name),
+            roles: vec!["user".to_string()],
+            permissions: vec![],
+            profile: None,
+            api_key: None,
+        }
+    }
+
+    async fn create_test_space(spaces: &SpaceService, slug: &str, owner: &User) -> SpaceResponse {
+        let request = CreateSpaceRequest {
+            name: "Team".to_string(),
+            slug: slug.to_string(),
+            description: None,
+            avatar_url: None,
+            is_public: Some(true),
+            settings: None,
+        };
+        spaces.create_space(request, owner).await.unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_nested_space_settings_survive_a_round_trip() {
+        let db = crate::services::database::test_database().await;
+        let spaces = SpaceService::new(db.clone());
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let alice = test_user("alice", &run);
+        let slug = format!("settings-{}", run);
+
+        let mut settings = crate::models::space::SpaceSettings::default();
+        settings.allowed_html_tags = vec!["iframe".to_string()];
+        settings.allow_custom_js = true;
+        settings.navigation.show_breadcrumbs = false;
+        settings.upload.max_file_size = Some(1024);
+        settings.upload.allowed_mime_types = Some(vec!["image/png".to_string()]);
+        let request = CreateSpaceRequest {
+            name: "Team".to_string(),
+            slug: slug.clone(),
+            description: None,
+            avatar_url: None,
+            is_public: Some(false),
+            settings: Some(settings),
+        };
+        spaces.create_space(request, &alice).await.unwrap();
+
+        let stored = spaces.get_space_by_slug(&slug, Some(&alice)).await.unwrap().settings;
+        assert_eq!(stored.allowed_html_tags, vec!["iframe".to_string()]);
+        assert!(stored.allow_custom_js);
+        assert!(!stored.navigation.show_breadcrumbs);
+        assert_eq!(stored.upload.max_file_size, Some(1024));
+        assert_eq!(stored.upload.allowed_mime_types, Some(vec!["image/png".to_string()]));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_previous_owner_cannot_delete_after_transfer() {
+        let db = crate::services::database::test_database().await;
+        let spaces = SpaceService::new(db.clone());
+        let members = crate::services::space_member::SpaceMemberService::new(db.clone(), db.config.clone());
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let (alice, bob) = (test_user("alice", &run), test_user("bob", &run));
+        let slug = format!("transfer-{}", run);
+
+        let space = create_test_space(&spaces, &slug, &alice).await;
+        let transferred = spaces
+            .transfer_ownership(&slug, TransferOwnershipRequest { new_owner_id: bob.id.clone() }, &alice)
+            .await
+            .unwrap();
+        assert_eq!(transferred.owner_id, bob.id);
+
+        // 原所有者按转让后写入的成员记录成为管理员：仍可编辑，但不能再删除空间
+        assert!(members.check_permission(&space.id, &alice.id, "docs.write").await.unwrap());
+        assert!(matches!(spaces.delete_space(&slug, &alice).await, Err(AppError::Authorization(_))));
+
+        spaces.delete_space(&slug, &bob).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_space_with_one_document_cannot_be_deleted() {
+        let db = crate::services::database::test_database().await;
+        let spaces = SpaceService::new(db.clone());
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let alice = test_user("alice", &run);
+        let slug = format!("delete-{}", run);
+
+        let space = create_test_space(&spaces, &slug, &alice).await;
+        let raw_space_id = space.id.strip_prefix("space:").unwrap_or(&space.id).to_string();
+        db.client
+            .query("CREATE document SET space_id = $space_id, title = 'Intro', slug = 'intro', author_id = $author_id")
+            .bind(("space_id", Thing::from(("space", raw_space_id.as_str()))))
+            .bind(("author_id", &alice.id))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+        // 有一篇文档时拒绝删除，空间保持原样
+        assert!(matches!(spaces.delete_space(&slug, &alice).await, Err(AppError::Conflict(_))));
+        let kept = spaces.get_space_by_slug(&slug, Some(&alice)).await.unwrap();
+        assert_eq!(kept.id, space.id);
+
+        // 文档删除后空间可以删除，之后再也查不到
+        db.client
+            .query("DELETE document WHERE space_id = $space_id")
+            .bind(("space_id", Thing::from(("space", raw_space_id.as_str()))))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        spaces.delete_space(&slug, &alice).await.unwrap();
+        assert!(matches!(spaces.get_space_by_slug(&slug, Some(&alice)).await, Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_owned_and_member_spaces_share_one_paginated_filter() {
+        let query = SpaceListQuery {
+            page: Some(2),
+            limit: Some(2),
+            search: None,
+            owner_id: None,
+            is_public: None,
+            include_archived: None,
+            sort: None,
+            order: None,
+        };
+
+        let (conditions, params) = build_space_list_filter(&query, Some("alice"));
+        assert_eq!(conditions[0], "(owner_id = $user_id OR id INSIDE $member_space_ids)");
+        assert_eq!(params["user_id"], "alice");
+        assert!(conditions.contains(&"is_archived != true"));
+
+        // 未登录用户看不到任何空间，非法分页参数被规范化
+        let (conditions, _) = build_space_list_filter(&query, None);
+        assert_eq!(conditions[0], "1 = 0");
+        assert_eq!(PageRequest::new(Some(0), Some(0)), PageRequest { page: 1, limit: 1 });
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_owned_and_member_spaces_are_counted_in_one_page_window() {
+        let db = crate::services::database::test_database().await;
+        let spaces = SpaceService::new(db.clone());
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let (alice, bob) = (test_user("alice", &run), test_user("bob", &run));
+
+        // alice 拥有 1 个空间并加入了 bob 的 2 个空间，bob 的第 4 个空间与她无关
+        let owned = create_test_space(&spaces, &format!("owned-{}", run), &alice).await;
+        let mut visible = vec![owned.slug.clone()];
+        for name in ["member-a", "member-b"] {
+            let space = create_test_space(&spaces, &format!("{}-{}", name, run), &bob).await;
+            let raw_space_id = space.id.strip_prefix("space:").unwrap_or(&space.id).to_string();
+            db.client
+                .query("CREATE space_member SET space_id = $space_id, user_id = $user_id, invited_by = $invited_by, status = 'accepted', accepted_at = time::now()")
+                .bind(("space_id", Thing::from(("space", raw_space_id.as_str()))))
+                .bind(("user_id", &alice.id))
+                .bind(("invited_by", &bob.id))
+                .await
+                .unwrap()
+                .check()
+                .unwrap();
+            visible.push(space.slug);
+        }
+        create_test_space(&spaces, &format!("unrelated-{}", run), &bob).await;
+
+        let query = |page| SpaceListQuery {
+            page: Some(page),
+            limit: Some(2),
+            search: None,
+            owner_id: None,
+            is_public: None,
+            include_archived: None,
+            sort: None,
+            order: None,
+        };
+        let first = spaces.list_spaces(query(1), Some(&alice)).await.unwrap();
+        let second = spaces.list_spaces(query(2), Some(&alice)).await.unwrap();
+
+        assert_eq!((first.total, first.total_pages, first.spaces.len()), (3, 2, 2));
+        assert_eq!((second.total, second.page, second.spaces.len()), (3, 2, 1));
+        let mut listed: Vec<String> = first.spaces.iter().chain(&second.spaces).map(|space| space.slug.clone()).collect();
+        listed.sort();
+        visible.sort();
+        assert_eq!(listed, visible);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_archived_space_blocks_writes_but_not_reads() {
+        let db = crate::services::database::test_database().await;
+        let spaces = SpaceService::new(db.clone());
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let (alice, bob) = (test_user("alice", &run), test_user("bob", &run));
+        let slug = format!("archive-{}", run);
+
+        let space = create_test_space(&spaces, &slug, &alice).await;
+        let raw_space_id = space.id.strip_prefix("space:").unwrap_or(&space.id).to_string();
+        let document: Option<Value> = db.client
+            .query("CREATE document SET space_id = $space_id, title = 'Intro', slug = 'intro', author_id = $author_id RETURN VALUE meta::id(id)")
+            .bind(("space_id", Thing::from(("space", raw_space_id.as_str()))))
+            .bind(("author_id", &alice.id))
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        let document_id = document.and_then(|id| id.as_str().map(str::to_string)).unwrap();
+
+        // 非所有者不能归档，空间保持可写
+        match spaces.archive_space(&slug, &bob).await {
+            Err(AppError::Authorization(msg)) => assert_eq!(msg, "Only space owner can archive space"),
+            other => panic!("expected owner check to refuse bob, got {:?}", other),
+        }
+        assert!(!spaces.get_space_by_slug(&slug, Some(&alice)).await.unwrap().is_archived);
+        assert!(ensure_space_writable(&db, &space.id).await.is_ok());
+
+        // 所有者归档后空间和其中的文档都拒绝写入
+        assert!(spaces.archive_space(&slug, &alice).await.unwrap().is_archived);
+        for result in [
+            ensure_space_writable(&db, &space.id).await,
+            ensure_document_space_writable(&db, &document_id).await,
+        ] {
+            match result {
+                Err(AppError::Authorization(msg)) => assert_eq!(msg, "Space is archived"),
+                other => panic!("expected archived error, got {:?}", other),
+            }
+        }
+
+        // 读取不受归档影响
+        assert!(spaces.get_space_by_slug(&slug, Some(&bob)).await.unwrap().is_archived);
+
+        // 取消归档后恢复写入
+        assert!(!spaces.unarchive_space(&slug, &alice).await.unwrap().is_archived);
+        assert!(ensure_document_space_writable(&db, &document_id).await.is_ok());
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::Deserialize;
+use surrealdb::sql::Thing;
+
+use crate::{
+    error::{AppError, Result},
+    models::space::SpaceStats,
+    models::stats::{Granularity, SpaceStatsEntry, SpaceTimeseries, StatsMetric, TimeseriesPoint, UserDashboard},
+    services::database::Database,
+};
+
+/// 单次趋势查询最多覆盖的天数
+pub const MAX_TIMESERIES_DAYS: i64 = 366;
+
+/// 趋势查询默认覆盖的天数
+const DEFAULT_TIMESERIES_DAYS: i64 = 30;
+
+#[derive(Clone)]
+pub struct StatsService {
+    db: Arc<Database>,
+}
+
+/// 按空间分组的文档统计
+#[derive(Debug, Deserialize)]
+struct DocumentStatsRow {
+    space_id: Thing,
+    document_count: u32,
+    public_document_count: u32,
+    view_count: Option<u32>,
+    last_activity: Option<DateTime<Utc>>,
+}
+
+/// 按天分组的计数
+#[derive(Debug, Deserialize)]
+struct DailyCountRow {
+    bucket: DateTime<Utc>,
+    count: u64,
+}
+
+/// 按空间分组的评论统计
+#[derive(Debug, Deserialize)]
+struct CommentStatsRow {
+    space_id: Thing,
+    comment_count: u32,
+}
+
+impl StatsService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// 汇总用户拥有或已加入的所有空间的统计。
+    /// 文档和评论各用一条按空间分组的查询，不随空间数量逐个查询
+    pub async fn user_dashboard(&self, user_id: &str) -> Result<UserDashboard> {
+        let user_id = user_id.strip_prefix("user:").unwrap_or(user_id);
+
+        let mut response = self.db.client
+            .query(
+                "LET $spaces = (SELECT VALUE id FROM space WHERE is_deleted != true AND (owner_id = $user_id \
+                     OR id INSIDE (SELECT VALUE space_id FROM space_member WHERE user_id = $user_id AND status = 'accepted'))); \
+                 SELECT space_id, count() AS document_count, count(is_public = true) AS public_document_count, \
+                     math::sum(view_count) AS view_count, time::max(updated_at) AS last_activity \
+                     FROM document WHERE space_id INSIDE $spaces AND is_deleted = false GROUP BY space_id; \
+                 SELECT document_id.space_id AS space_id, count() AS comment_count FROM comment \
+                     WHERE document_id.space_id INSIDE $spaces AND is_deleted = false GROUP BY space_id; \
+                 RETURN $spaces;",
+            )
+            .bind(("user_id", user_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let document_rows: Vec<DocumentStatsRow> = response.take(1)?;
+        let comment_rows: Vec<CommentStatsRow> = response.take(2)?;
+        let space_ids: Vec<Thing> = response.take(3)?;
+
+        Ok(UserDashboard::from_spaces(space_stats(&space_ids, document_rows, comment_rows)))
+    }
+
+    /// 按天或按周统计空间在 [from, to] 内的浏览、新建或编辑次数，没有数据的区间补 0。
+    /// 数据库中统一按天分组，按周汇总在内存中完成
+    pub async fn space_timeseries(
+        &self,
+        space_id: &str,
+        metric: StatsMetric,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        granularity: Granularity,
+    ) -> Result<SpaceTimeseries> {
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+        let (from, to) = timeseries_range(from, to, Utc::now().date_naive())?;
+
+        let query = timeseries_query(metric);
+
+        let rows: Vec<DailyCountRow> = self.db.client
+            .query(query)
+            .bind(("space_id", space_id))
+            .bind(("space", Thing::from(("space", space_id))))
+            .bind(("from", day_start(from)))
+            .bind(("to", day_start(to + Duration::days(1))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)?;
+
+        let daily: Vec<(NaiveDate, u64)> = rows.iter().map(|row| (row.bucket.date_naive(), row.count)).collect();
+
+        Ok(SpaceTimeseries {
+            space_id: format!("space:{}", space_id),
+            metric,
+            granularity,
+            from,
+            to,
+            points: fill_buckets(&daily, from, to, granularity),
+        })
+    }
+}
+
+/// 按指标选择按天聚合的查询。绑定的时间经 serde 序列化后是字符串，必须转换为 datetime 再比较
+fn timeseries_query(metric: StatsMetric) -> &'static str {
+    match metric {
+        StatsMetric::Views => {
+            "SELECT day AS bucket, math::sum(views) AS count FROM publication_daily_view \
+                 WHERE publication_id INSIDE (SELECT VALUE <string> id FROM space_publication \
+                     WHERE space_id INSIDE [$space_id, <string> $space]) \
+                 AND day >= <datetime> $from AND day < <datetime> $to GROUP BY bucket"
+        }
+        StatsMetric::Creations => {
+            "SELECT time::floor(created_at, 1d) AS bucket, count() AS count FROM document \
+                 WHERE space_id = $space AND created_at >= <datetime> $from AND created_at < <datetime> $to GROUP BY bucket"
+        }
+        StatsMetric::Edits => {
+            "SELECT time::floor(created_at, 1d) AS bucket, count() AS count FROM document_version \
+                 WHERE document_id.space_id = $space AND change_type != 'Created' \
+                 AND created_at >= <datetime> $from AND created_at < <datetime> $to GROUP BY bucket"
+        }
+    }
+}
+
+fn day_start(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN))
+}
+
+/// 补全查询区间：未指定 to 时为今天，未指定 from 时为 to 之前 30 天
+fn timeseries_range(from: Option<NaiveDate>, to: Option<NaiveDate>, today: NaiveDate) -> Result<(NaiveDate, NaiveDate)> {
+    let to = to.unwrap_or(today);
+    let from = from.unwrap_or(to - Duration::days(DEFAULT_TIMESERIES_DAYS - 1));
+    if from > to {
+        return Err(AppError::bad_request("'from' must not be later than 'to'"));
+    }
+    if (to - from).num_days() >= MAX_TIMESERIES_DAYS {
+        return Err(AppError::bad_request(format!(
+            "Time range must not exceed {} days", MAX_TIMESERIES_DAYS
+        )));
+    }
+    Ok((from, to))
+}
+
+/// 把按天的计数汇总到区间中，[from, to] 内的每个区间都会出现，没有数据的区间为 0
+fn fill_buckets(daily: &[(NaiveDate, u64)], from: NaiveDate, to: NaiveDate, granularity: Granularity) -> Vec<TimeseriesPoint> {
+    let mut buckets: BTreeMap<NaiveDate, u64> = BTreeMap::new();
+    let mut bucket = granularity.bucket_start(from);
+    while bucket <= to {
+        buckets.insert(bucket, 0);
+        bucket += granularity.step();
+    }
+
+    for &(day, count) in daily {
+        if (from..=to).contains(&day) {
+            *buckets.entry(granularity.bucket_start(day)).or_default() += count;
+        }
+    }
+
+    buckets
+        .into_iter()
+        .map(|(bucket, count)| TimeseriesPoint { bucket, count })
+        .collect()
+}
+
+/// 把分组查询的结果合并为每个空间一条统计，没有文档或评论的空间计为 0
+fn space_stats(
+    space_ids: &[Thing],
+    document_rows: Vec<DocumentStatsRow>,
+    comment_rows: Vec<CommentStatsRow>,
+) -> Vec<SpaceStatsEntry> {
+    let mut documents: HashMap<String, DocumentStatsRow> = document_rows
+        .into_iter()
+        .map(|row| (row.space_id.to_string(), row))
+        .collect();
+    let comments: HashMap<String, u32> = comment_rows
+        .into_iter()
+        .map(|row| (row.space_id.to_string(), row.comment_count))
+        .collect();
+
+    space_ids
+        .iter()
+        .map(|space_id| {
+            let key = space_id.to_string();
+            let mut stats = SpaceStats {
+                comment_count: comments.get(&key).copied().unwrap_or(0),
+                ..SpaceStats::default()
+            };
+            if let Some(row) = documents.remove(&key) {
+                stats.document_count = row.document_count;
+                stats.public_document_count = row.public_document_count;
+                stats.view_count = row.view_count.unwrap_or(0);
+                stats.last_activity = row.last_activity;
+            }
+            SpaceStatsEntry { space_id: key, stats }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space(id: &str) -> Thing {
+        Thing::from(("space", id))
+    }
+
+    fn documents(space_id: &str, document_count: u32, public_document_count: u32) -> DocumentStatsRow {
+        DocumentStatsRow {
+            space_id: space(space_id),
+            document_count,
+            public_document_count,
+            view_count: Some(document_count * 10),
+            last_activity: None,
+        }
+    }
+
+    #[test]
+    fn test_dashboard_sums_document_counts_across_two_spaces() {
+        let spaces = vec![space("docs"), space("handbook")];
+        let comments = vec![CommentStatsRow { space_id: space("handbook"), comment_count: 2 }];
+
+        let dashboard = UserDashboard::from_spaces(space_stats(
+            &spaces,
+            vec![documents("docs", 3, 1), documents("handbook", 4, 2)],
+            comments,
+        ));
+
+        assert_eq!(dashboard.total_spaces, 2);
+        assert_eq!(dashboard.total_documents, 7);
+        assert_eq!(dashboard.public_documents, 3);
+        assert_eq!(dashboard.total_comments, 2);
+        assert_eq!(dashboard.total_views, 70);
+        assert_eq!(dashboard.spaces[0].space_id, "space:docs");
+        assert_eq!(dashboard.spaces[0].stats.comment_count, 0);
+    }
+
+    #[test]
+    fn test_spaces_without_documents_still_count() {
+        let dashboard = UserDashboard::from_spaces(space_stats(&[space("empty")], Vec::new(), Vec::new()));
+
+        assert_eq!(dashboard.total_spaces, 1);
+        assert_eq!(dashboard.total_documents, 0);
+        assert_eq!(dashboard.spaces[0].stats, SpaceStats::default());
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_empty_days_are_filled_with_zero() {
+        let daily = vec![(date("2024-03-01"), 4), (date("2024-03-03"), 2)];
+
+        let points = fill_buckets(&daily, date("2024-03-01"), date("2024-03-03"), Granularity::Day);
+
+        let counts: Vec<(NaiveDate, u64)> = points.iter().map(|p| (p.bucket, p.count)).collect();
+        assert_eq!(
+            counts,
+            vec![(date("2024-03-01"), 4), (date("2024-03-02"), 0), (date("2024-03-03"), 2)]
+        );
+    }
+
+    #[test]
+    fn test_weekly_buckets_start_on_monday() {
+        // 2024-03-01 是周五
+        let daily = vec![(date("2024-03-01"), 1), (date("2024-03-04"), 2), (date("2024-03-10"), 3)];
+
+        let points = fill_buckets(&daily, date("2024-03-01"), date("2024-03-20"), Granularity::Week);
+
+        let counts: Vec<(NaiveDate, u64)> = points.iter().map(|p| (p.bucket, p.count)).collect();
+        assert_eq!(
+            counts,
+            vec![(date("2024-02-26"), 1), (date("2024-03-04"), 5), (date("2024-03-11"), 0), (date("2024-03-18"), 0)]
+        );
+    }
+
+    #[test]
+    fn test_timeseries_range_defaults_and_limits() {
+        let today = date("2024-03-31");
+
+        assert_eq!(timeseries_range(None, None, today).unwrap(), (date("2024-03-02"), today));
+        assert!(timeseries_range(Some(date("2024-04-02")), Some(date("2024-04-01")), today).is_err());
+        assert!(timeseries_range(Some(date("2023-01-01")), Some(date("2024-03-01")), today).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a running SurrealDB"]
+    async fn test_recorded_views_and_creations_read_back_by_day() {
+        let state = crate::state::test_support::test_app_state().await;
+        let run = uuid::Uuid::new_v4().simple().to_string();
+        let owner = crate::services::auth::User {
+            id: format!("alice-{}", run),
+            email: "alice@example.com".to_string(),
+            roles: vec!["user".to_string()],
+            permissions: vec![],
+            profile: None,
+            api_key: None,
+        };
+        let space = state.space_service.create_space(crate::models::space::CreateSpaceRequest {
+            name: "Docs".to_string(),
+            slug: format!("stats-{}", run),
+            description: None,
+            avatar_url: None,
+            is_public: Some(true),
+            settings: None,
+        }, &owner).await.unwrap();
+        let request = serde_json::from_value(serde_json::json!({ "title": "Guide", "slug": "guide", "is_public": true })).unwrap();
+        let document = state.document_service.create_document(&space.id, &owner.id, request).await.unwrap();
+        let request = serde_json::from_value(serde_json::json!({ "slug": format!("stats-{}", run), "title": "Docs" })).unwrap();
+        let publication = state.publication_service.create_publication(&space.id, &owner.id, request).await.unwrap();
+
+        // 两次访问累计到当天的记录中
+        for visitor in ["visitor-a", "visitor-b"] {
+            state.publication_service
+                .track_document_view(&publication.id, document.id.as_deref().unwrap(), "Guide", Some(visitor))
+                .await
+                .unwrap();
+        }
+
+        let today = Utc::now().date_naive();
+        let yesterday = today - Duration::days(1);
+        let counts = |timeseries: SpaceTimeseries| -> Vec<(NaiveDate, u64)> {
+            timeseries.points.iter().map(|p| (p.bucket, p.count)).collect()
+        };
+
+        let views = state.stats_service
+            .space_timeseries(&space.id, StatsMetric::Views, Some(yesterday), Some(today), Granularity::Day)
+            .await
+            .unwrap();
+        assert_eq!(counts(views), vec![(yesterday, 0), (today, 2)]);
+
+        let creations = state.stats_service
+            .space_timeseries(&space.id, StatsMetric::Creations, Some(yesterday), Some(today), Granularity::Day)
+            .await
+            .unwrap();
+        assert_eq!(counts(creations), vec![(yesterday, 0), (today, 1)]);
+    }
+}
@@ -1,9 +1,11 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use serde::Deserialize;
 use surrealdb::sql::Thing;
 use validator::Validate;
 
 use crate::{
-    error::ApiError,
+    error::AppError,
     models::tag::{Tag, DocumentTag, CreateTagRequest, UpdateTagRequest, TagDocumentRequest},
     services::{auth::AuthService, database::Database},
 };
@@ -23,12 +25,12 @@ impl TagService {
         &self,
         creator_id: &str,
         request: CreateTagRequest,
-    ) -> Result<Tag, ApiError> {
+    ) -> Result<Tag, AppError> {
         request.validate()?;
 
         // 检查标签名在空间内是否唯一
         if self.tag_exists_in_space(&request.space_id, &request.name).await? {
-            return Err(ApiError::Conflict("Tag name already exists in this space".to_string()));
+            return Err(AppError::Conflict("Tag name already exists in this space".to_string()));
         }
 
         let space_thing = if let Some(space_id) = &request.space_id {
@@ -45,21 +47,21 @@ impl TagService {
             .create("tag")
             .content(tag)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         created
             .into_iter()
             .next()
-            .ok_or_else(|| ApiError::InternalServerError("Failed to create tag".to_string()))
+            .ok_or_else(|| AppError::internal_server_error("Failed to create tag".to_string()))
     }
 
-    pub async fn get_tag(&self, tag_id: &str) -> Result<Tag, ApiError> {
+    pub async fn get_tag(&self, tag_id: &str) -> Result<Tag, AppError> {
         let tag: Option<Tag> = self.db.client
             .select(("tag", tag_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        tag.ok_or_else(|| ApiError::NotFound("Tag not found".to_string()))
+        tag.ok_or_else(|| AppError::NotFound("Tag not found".to_string()))
     }
 
     pub async fn update_tag(
@@ -67,7 +69,7 @@ impl TagService {
         tag_id: &str,
         updater_id: &str,
         request: UpdateTagRequest,
-    ) -> Result<Tag, ApiError> {
+    ) -> Result<Tag, AppError> {
         request.validate()?;
 
         let mut tag = self.get_tag(tag_id).await?;
@@ -75,7 +77,7 @@ impl TagService {
         if let Some(name) = request.name {
             // 检查新名称是否与其他标签冲突
             if name != tag.name && self.tag_exists_in_space(&tag.space_id.as_ref().map(|s| s.to_string()), &name).await? {
-                return Err(ApiError::Conflict("Tag name already exists in this space".to_string()));
+                return Err(AppError::Conflict("Tag name already exists in this space".to_string()));
             }
             tag.name = name;
             tag.slug = Tag::generate_slug(&tag.name);
@@ -95,27 +97,27 @@ impl TagService {
             .update(("tag", tag_id))
             .content(tag)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        updated.ok_or_else(|| ApiError::InternalServerError("Failed to update tag".to_string()))
+        updated.ok_or_else(|| AppError::internal_server_error("Failed to update tag".to_string()))
     }
 
-    pub async fn delete_tag(&self, tag_id: &str) -> Result<(), ApiError> {
+    pub async fn delete_tag(&self, tag_id: &str) -> Result<(), AppError> {
         // 首先删除所有与此标签关联的文档标签关系
         let query = "DELETE document_tag WHERE tag_id = $tag_id";
         let _: Vec<surrealdb::sql::Value> = self.db.client
             .query(query)
             .bind(("tag_id", Thing::from(("tag", tag_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         // 删除标签
         let _: Option<Tag> = self.db.client
             .delete(("tag", tag_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(())
     }
@@ -125,7 +127,7 @@ impl TagService {
         space_id: Option<&str>,
         page: i64,
         per_page: i64,
-    ) -> Result<Vec<Tag>, ApiError> {
+    ) -> Result<Vec<Tag>, AppError> {
         let offset = (page - 1) * per_page;
         
         let query = if let Some(space_id) = space_id {
@@ -144,14 +146,56 @@ impl TagService {
             .bind(("limit", per_page))
             .bind(("offset", offset))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(tags)
     }
 
-    pub async fn get_popular_tags(&self, space_id: Option<&str>, limit: i64) -> Result<Vec<Tag>, ApiError> {
+    /// 返回标签及其关联的未删除文档数量，使用单条分组查询统计，按数量降序分页
+    pub async fn get_tags_with_counts(
+        &self,
+        space_id: Option<&str>,
+        page: i64,
+        per_page: i64,
+    ) -> Result<Vec<TagWithCount>, AppError> {
+        let query = if space_id.is_some() {
+            "SELECT * FROM tag WHERE space_id = $space_id;
+             SELECT tag_id, count() AS count FROM document_tag
+             WHERE tag_id.space_id = $space_id AND document_id.is_deleted = false
+             GROUP BY tag_id;"
+        } else {
+            "SELECT * FROM tag WHERE space_id IS NULL;
+             SELECT tag_id, count() AS count FROM document_tag
+             WHERE tag_id.space_id IS NULL AND document_id.is_deleted = false
+             GROUP BY tag_id;"
+        };
+
+        let mut db_query = self.db.client.query(query);
+        if let Some(space_id) = space_id {
+            db_query = db_query.bind(("space_id", Thing::from(("space", space_id))));
+        }
+
+        let mut result = db_query
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        let tags: Vec<Tag> = result
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        let counts: Vec<TagCountRow> = result
+            .take(1)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let offset = ((page - 1) * per_page).max(0) as usize;
+        Ok(attach_document_counts(tags, counts)
+            .into_iter()
+            .skip(offset)
+            .take(per_page.max(0) as usize)
+            .collect())
+    }
+
+    pub async fn get_popular_tags(&self, space_id: Option<&str>, limit: i64) -> Result<Vec<Tag>, AppError> {
         let query = if let Some(space_id) = space_id {
             "SELECT * FROM tag WHERE space_id = $space_id AND usage_count > 0 ORDER BY usage_count DESC LIMIT $limit"
         } else {
@@ -167,14 +211,14 @@ impl TagService {
         let tags: Vec<Tag> = db_query
             .bind(("limit", limit))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(tags)
     }
 
-    pub async fn search_tags(&self, space_id: Option<&str>, query: &str, limit: i64) -> Result<Vec<Tag>, ApiError> {
+    pub async fn search_tags(&self, space_id: Option<&str>, query: &str, limit: i64) -> Result<Vec<Tag>, AppError> {
         let search_query = if let Some(space_id) = space_id {
             "SELECT * FROM tag WHERE space_id = $space_id AND (name CONTAINSTEXT $query OR description CONTAINSTEXT $query) ORDER BY usage_count DESC LIMIT $limit"
         } else {
@@ -191,9 +235,9 @@ impl TagService {
             .bind(("query", query))
             .bind(("limit", limit))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(tags)
     }
@@ -203,7 +247,7 @@ impl TagService {
         &self,
         tagger_id: &str,
         request: TagDocumentRequest,
-    ) -> Result<Vec<DocumentTag>, ApiError> {
+    ) -> Result<Vec<DocumentTag>, AppError> {
         let document_thing = Thing::from(("document", request.document_id.as_str()));
         let mut created_tags = Vec::new();
 
@@ -222,7 +266,7 @@ impl TagService {
                     .create("document_tag")
                     .content(document_tag)
                     .await
-                    .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+                    .map_err(|e| AppError::database_error(e.to_string()))?;
 
                 if let Some(created_tag) = created.into_iter().next() {
                     created_tags.push(created_tag);
@@ -236,11 +280,300 @@ impl TagService {
         Ok(created_tags)
     }
 
+    /// 批量为空间内的文档打标签：缺失的标签按名称自动创建，已存在的关联会被跳过。
+    /// 返回每个文档最终的标签集合，便于前端刷新标签展示。
+    pub async fn assign_tags_bulk(
+        &self,
+        space_id: &str,
+        document_ids: Vec<String>,
+        tag_names: Vec<String>,
+        user_id: &str,
+    ) -> Result<HashMap<String, Vec<Tag>>, AppError> {
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+        let document_ids = dedupe_preserving_order(
+            document_ids
+                .iter()
+                .map(|id| id.strip_prefix("document:").unwrap_or(id).to_string()),
+        );
+        let tag_names = normalize_tag_names(tag_names)?;
+
+        // 所有文档必须属于目标空间且未被删除
+        let documents: Vec<DocumentSpaceRow> = self.db.client
+            .query("SELECT id, space_id FROM document WHERE id INSIDE $document_ids AND is_deleted = false")
+            .bind((
+                "document_ids",
+                document_ids
+                    .iter()
+                    .map(|id| Thing::from(("document", id.as_str())))
+                    .collect::<Vec<_>>(),
+            ))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let in_space: HashSet<String> = documents
+            .iter()
+            .filter(|doc| doc.space_id.id.to_string() == space_id)
+            .map(|doc| doc.id.id.to_string())
+            .collect();
+        if let Some(missing) = document_ids.iter().find(|id| !in_space.contains(*id)) {
+            return Err(AppError::Validation(format!(
+                "Document {} does not belong to space {}",
+                missing, space_id
+            )));
+        }
+
+        // 解析标签名，缺失的在空间内创建
+        let mut existing_tags: Vec<Tag> = self.db.client
+            .query("SELECT * FROM tag WHERE space_id = $space_id AND name INSIDE $names")
+            .bind(("space_id", Thing::from(("space", space_id))))
+            .bind(("names", tag_names.clone()))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        for name in &tag_names {
+            if existing_tags.iter().any(|tag| &tag.name == name) {
+                continue;
+            }
+            let created = self.create_tag(user_id, CreateTagRequest {
+                name: name.clone(),
+                description: None,
+                color: DEFAULT_TAG_COLOR.to_string(),
+                space_id: Some(space_id.to_string()),
+            }).await?;
+            existing_tags.push(created);
+        }
+
+        let tag_ids: Vec<String> = existing_tags
+            .iter()
+            .filter_map(|tag| tag.id.as_ref().map(|thing| thing.id.to_string()))
+            .collect();
+
+        // 查询已有关联，只创建缺失的部分
+        let existing_relations: Vec<DocumentTagRow> = self.db.client
+            .query("SELECT document_id, tag_id FROM document_tag WHERE document_id INSIDE $document_ids AND tag_id INSIDE $tag_ids")
+            .bind((
+                "document_ids",
+                document_ids
+                    .iter()
+                    .map(|id| Thing::from(("document", id.as_str())))
+                    .collect::<Vec<_>>(),
+            ))
+            .bind((
+                "tag_ids",
+                tag_ids
+                    .iter()
+                    .map(|id| Thing::from(("tag", id.as_str())))
+                    .collect::<Vec<_>>(),
+            ))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let existing_pairs: HashSet<(String, String)> = existing_relations
+            .into_iter()
+            .map(|row| (row.document_id.id.to_string(), row.tag_id.id.to_string()))
+            .collect();
+        let new_pairs = plan_new_associations(&document_ids, &tag_ids, &existing_pairs);
+
+        if !new_pairs.is_empty() {
+            let mut sql = String::from("BEGIN TRANSACTION;\n");
+            let mut bindings = HashMap::new();
+            for (index, (document_id, tag_id)) in new_pairs.iter().enumerate() {
+                sql.push_str(&format!(
+                    "CREATE document_tag CONTENT {{ document_id: type::thing('document', $doc_{index}), tag_id: type::thing('tag', $tag_{index}), tagged_by: $user_id, tagged_at: time::now() }};\n",
+                    index = index
+                ));
+                bindings.insert(format!("doc_{}", index), serde_json::Value::String(document_id.clone()));
+                bindings.insert(format!("tag_{}", index), serde_json::Value::String(tag_id.clone()));
+            }
+
+            let mut usage: Vec<(&String, i64)> = Vec::new();
+            for (_, tag_id) in &new_pairs {
+                match usage.iter_mut().find(|(id, _)| *id == tag_id) {
+                    Some((_, count)) => *count += 1,
+                    None => usage.push((tag_id, 1)),
+                }
+            }
+            for (index, (tag_id, count)) in usage.iter().enumerate() {
+                sql.push_str(&format!(
+                    "UPDATE type::thing('tag', $usage_tag_{index}) SET usage_count += $usage_count_{index};\n",
+                    index = index
+                ));
+                bindings.insert(format!("usage_tag_{}", index), serde_json::Value::String((*tag_id).clone()));
+                bindings.insert(format!("usage_count_{}", index), serde_json::Value::from(*count));
+            }
+            sql.push_str("COMMIT TRANSACTION;");
+            bindings.insert("user_id".to_string(), serde_json::Value::String(user_id.to_string()));
+
+            self.db.client
+                .query(sql)
+                .bind(bindings)
+                .await
+                .map_err(|e| AppError::database_error(e.to_string()))?
+                .check()
+                .map_err(|e| AppError::database_error(e.to_string()))?;
+        }
+
+        let mut result = HashMap::new();
+        for document_id in document_ids {
+            let tags = self.get_document_tags(&document_id).await?;
+            result.insert(document_id, tags);
+        }
+
+        Ok(result)
+    }
+
+    /// 重命名空间内的标签，新名称不能与空间内其他标签冲突
+    pub async fn rename_tag(
+        &self,
+        space_id: &str,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<Tag, AppError> {
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+        let new_name = new_name.trim();
+        if new_name.is_empty() || new_name.chars().count() > 50 {
+            return Err(AppError::Validation("Tag name must be 1-50 characters".to_string()));
+        }
+
+        let tag = self.find_tag_by_name(space_id, old_name).await?
+            .ok_or_else(|| AppError::NotFound(format!("Tag '{}' not found", old_name)))?;
+        if tag.name == new_name {
+            return Ok(tag);
+        }
+
+        if self.tag_exists_in_space(&Some(space_id.to_string()), new_name).await? {
+            return Err(AppError::Conflict("Tag name already exists in this space".to_string()));
+        }
+
+        let tag_id = tag.id.as_ref()
+            .map(|thing| thing.id.to_string())
+            .ok_or_else(|| AppError::internal_server_error("Tag record has no id".to_string()))?;
+
+        let updated: Option<Tag> = self.db.client
+            .query("UPDATE type::thing('tag', $tag_id) SET name = $name, slug = $slug, updated_at = time::now() RETURN AFTER")
+            .bind(("tag_id", tag_id))
+            .bind(("name", new_name.to_string()))
+            .bind(("slug", Tag::generate_slug(new_name)))
+            .await
+            .map_err(|e| AppError::from_unique_violation(e, "tag_space_slug_idx", "Tag slug already exists in this space"))?
+            .take(0)
+            .map_err(|e| AppError::from_unique_violation(e, "tag_space_slug_idx", "Tag slug already exists in this space"))?;
+
+        updated.ok_or_else(|| AppError::internal_server_error("Failed to rename tag".to_string()))
+    }
+
+    /// 将源标签合并到目标标签：关联改指向目标，重复关联删除，源标签删除。
+    /// 目标标签不存在时会在空间内创建。
+    pub async fn merge_tags(
+        &self,
+        space_id: &str,
+        source_names: Vec<String>,
+        target_name: &str,
+        user_id: &str,
+    ) -> Result<Tag, AppError> {
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+        let target_name = target_name.trim();
+        let source_names = normalize_tag_names(source_names)?;
+        if source_names.iter().any(|name| name == target_name) {
+            return Err(AppError::Validation("Cannot merge a tag into itself".to_string()));
+        }
+
+        let mut sources = Vec::new();
+        for name in &source_names {
+            let tag = self.find_tag_by_name(space_id, name).await?
+                .ok_or_else(|| AppError::NotFound(format!("Tag '{}' not found", name)))?;
+            sources.push(tag);
+        }
+
+        let target = match self.find_tag_by_name(space_id, target_name).await? {
+            Some(tag) => tag,
+            None => self.create_tag(user_id, CreateTagRequest {
+                name: target_name.to_string(),
+                description: None,
+                color: sources[0].color.clone(),
+                space_id: Some(space_id.to_string()),
+            }).await?,
+        };
+        let target_thing = target.id.clone()
+            .ok_or_else(|| AppError::internal_server_error("Tag record has no id".to_string()))?;
+        let source_things: Vec<Thing> = sources.iter().filter_map(|tag| tag.id.clone()).collect();
+
+        let mut result = self.db.client
+            .query(
+                "SELECT id, document_id, tagged_at FROM document_tag WHERE tag_id INSIDE $source_ids ORDER BY tagged_at ASC;
+                 SELECT VALUE document_id FROM document_tag WHERE tag_id = $target_id;"
+            )
+            .bind(("source_ids", source_things.clone()))
+            .bind(("target_id", target_thing.clone()))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        let source_relations: Vec<RelationRow> = result
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+        let target_documents: Vec<Thing> = result
+            .take(1)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let plan = plan_tag_merge(
+            source_relations
+                .iter()
+                .map(|row| (row.id.id.to_string(), row.document_id.id.to_string())),
+            &target_documents.iter().map(|doc| doc.id.to_string()).collect(),
+        );
+
+        let mut sql = String::from("BEGIN TRANSACTION;\n");
+        let mut bindings = HashMap::new();
+        for (index, relation_id) in plan.repoint.iter().enumerate() {
+            sql.push_str(&format!(
+                "UPDATE type::thing('document_tag', $repoint_{index}) SET tag_id = type::thing('tag', $target_id);\n",
+                index = index
+            ));
+            bindings.insert(format!("repoint_{}", index), serde_json::Value::String(relation_id.clone()));
+        }
+        for (index, relation_id) in plan.remove.iter().enumerate() {
+            sql.push_str(&format!(
+                "DELETE type::thing('document_tag', $remove_{index});\n",
+                index = index
+            ));
+            bindings.insert(format!("remove_{}", index), serde_json::Value::String(relation_id.clone()));
+        }
+        for (index, source) in source_things.iter().enumerate() {
+            sql.push_str(&format!(
+                "DELETE type::thing('tag', $source_{index});\n",
+                index = index
+            ));
+            bindings.insert(format!("source_{}", index), serde_json::Value::String(source.id.to_string()));
+        }
+        sql.push_str("UPDATE type::thing('tag', $target_id) SET usage_count = $usage_count, updated_at = time::now();\n");
+        sql.push_str("COMMIT TRANSACTION;");
+        bindings.insert("target_id".to_string(), serde_json::Value::String(target_thing.id.to_string()));
+        bindings.insert(
+            "usage_count".to_string(),
+            serde_json::Value::from((target_documents.len() + plan.repoint.len()) as i64),
+        );
+
+        self.db.client
+            .query(sql)
+            .bind(bindings)
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .check()
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        self.get_tag(&target_thing.id.to_string()).await
+    }
+
     pub async fn untag_document(
         &self,
         document_id: &str,
         tag_id: &str,
-    ) -> Result<(), ApiError> {
+    ) -> Result<(), AppError> {
         let query = "DELETE document_tag WHERE document_id = $document_id AND tag_id = $tag_id";
         
         let _: Vec<surrealdb::sql::Value> = self.db.client
@@ -248,9 +581,9 @@ impl TagService {
             .bind(("document_id", Thing::from(("document", document_id))))
             .bind(("tag_id", Thing::from(("tag", tag_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         // 减少标签使用计数
         self.decrement_tag_usage(tag_id).await?;
@@ -258,7 +591,7 @@ impl TagService {
         Ok(())
     }
 
-    pub async fn get_document_tags(&self, document_id: &str) -> Result<Vec<Tag>, ApiError> {
+    pub async fn get_document_tags(&self, document_id: &str) -> Result<Vec<Tag>, AppError> {
         let query = "
             SELECT tag.* FROM tag, document_tag 
             WHERE document_tag.document_id = $document_id 
@@ -270,9 +603,9 @@ impl TagService {
             .query(query)
             .bind(("document_id", Thing::from(("document", document_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(tags)
     }
@@ -282,7 +615,7 @@ impl TagService {
         tag_id: &str,
         page: i64,
         per_page: i64,
-    ) -> Result<Vec<String>, ApiError> {
+    ) -> Result<Vec<String>, AppError> {
         let offset = (page - 1) * per_page;
         
         let query = "
@@ -298,9 +631,9 @@ impl TagService {
             .bind(("limit", per_page))
             .bind(("offset", offset))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let document_ids: Vec<String> = results
             .into_iter()
@@ -310,7 +643,7 @@ impl TagService {
         Ok(document_ids)
     }
 
-    pub async fn get_tag_statistics(&self, space_id: Option<&str>) -> Result<TagStatistics, ApiError> {
+    pub async fn get_tag_statistics(&self, space_id: Option<&str>) -> Result<TagStatistics, AppError> {
         let total_query = if let Some(space_id) = space_id {
             "SELECT count() as total FROM tag WHERE space_id = $space_id GROUP ALL"
         } else {
@@ -324,9 +657,9 @@ impl TagService {
 
         let total_result: Vec<surrealdb::sql::Value> = db_query
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let total_tags = total_result
             .first()
@@ -346,9 +679,9 @@ impl TagService {
 
         let used_result: Vec<surrealdb::sql::Value> = db_query
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let used_tags = used_result
             .first()
@@ -364,7 +697,20 @@ impl TagService {
     }
 
     // 私有辅助方法
-    async fn tag_exists_in_space(&self, space_id: &Option<String>, name: &str) -> Result<bool, ApiError> {
+    async fn find_tag_by_name(&self, space_id: &str, name: &str) -> Result<Option<Tag>, AppError> {
+        let tags: Vec<Tag> = self.db.client
+            .query("SELECT * FROM tag WHERE space_id = $space_id AND name = $name LIMIT 1")
+            .bind(("space_id", Thing::from(("space", space_id))))
+            .bind(("name", name))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(tags.into_iter().next())
+    }
+
+    async fn tag_exists_in_space(&self, space_id: &Option<String>, name: &str) -> Result<bool, AppError> {
         let query = if let Some(space_id) = space_id {
             "SELECT count() FROM tag WHERE space_id = $space_id AND name = $name GROUP ALL"
         } else {
@@ -379,9 +725,9 @@ impl TagService {
         let result: Vec<surrealdb::sql::Value> = db_query
             .bind(("name", name))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let count = result
             .first()
@@ -391,7 +737,7 @@ impl TagService {
         Ok(count > 0)
     }
 
-    async fn document_tag_exists(&self, document_id: &str, tag_id: &str) -> Result<bool, ApiError> {
+    async fn document_tag_exists(&self, document_id: &str, tag_id: &str) -> Result<bool, AppError> {
         let query = "SELECT count() FROM document_tag WHERE document_id = $document_id AND tag_id = $tag_id GROUP ALL";
 
         let result: Vec<surrealdb::sql::Value> = self.db.client
@@ -399,9 +745,9 @@ impl TagService {
             .bind(("document_id", Thing::from(("document", document_id))))
             .bind(("tag_id", Thing::from(("tag", tag_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let count = result
             .first()
@@ -411,39 +757,254 @@ impl TagService {
         Ok(count > 0)
     }
 
-    async fn increment_tag_usage(&self, tag_id: &str) -> Result<(), ApiError> {
+    async fn increment_tag_usage(&self, tag_id: &str) -> Result<(), AppError> {
         let query = "UPDATE tag SET usage_count += 1 WHERE id = $tag_id";
 
         let _: Vec<surrealdb::sql::Value> = self.db.client
             .query(query)
             .bind(("tag_id", Thing::from(("tag", tag_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(())
     }
 
-    async fn decrement_tag_usage(&self, tag_id: &str) -> Result<(), ApiError> {
+    async fn decrement_tag_usage(&self, tag_id: &str) -> Result<(), AppError> {
         let query = "UPDATE tag SET usage_count = math::max(usage_count - 1, 0) WHERE id = $tag_id";
 
         let _: Vec<surrealdb::sql::Value> = self.db.client
             .query(query)
             .bind(("tag_id", Thing::from(("tag", tag_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(())
     }
 }
 
+/// 带文档数量的标签，未请求数量时 document_count 不参与序列化
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TagWithCount {
+    #[serde(flatten)]
+    pub tag: Tag,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_count: Option<i64>,
+}
+
+impl From<Tag> for TagWithCount {
+    fn from(tag: Tag) -> Self {
+        Self { tag, document_count: None }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TagCountRow {
+    tag_id: Thing,
+    count: i64,
+}
+
+/// 为标签附加文档数量（没有关联的记为 0），按数量降序、名称升序排列
+fn attach_document_counts(tags: Vec<Tag>, counts: Vec<TagCountRow>) -> Vec<TagWithCount> {
+    let counts: HashMap<String, i64> = counts
+        .into_iter()
+        .map(|row| (row.tag_id.id.to_string(), row.count))
+        .collect();
+
+    let mut tags: Vec<TagWithCount> = tags
+        .into_iter()
+        .map(|tag| {
+            let count = tag.id.as_ref()
+                .and_then(|id| counts.get(&id.id.to_string()).copied())
+                .unwrap_or(0);
+            TagWithCount { tag, document_count: Some(count) }
+        })
+        .collect();
+    tags.sort_by(|a, b| {
+        b.document_count.cmp(&a.document_count).then_with(|| a.tag.name.cmp(&b.tag.name))
+    });
+    tags
+}
+
+/// 新建标签未指定颜色时使用的默认颜色，与数据库字段默认值一致
+const DEFAULT_TAG_COLOR: &str = "#3b82f6";
+
+#[derive(Debug, Deserialize)]
+struct DocumentSpaceRow {
+    id: Thing,
+    space_id: Thing,
+}
+
+#[derive(Debug, Deserialize)]
+struct DocumentTagRow {
+    document_id: Thing,
+    tag_id: Thing,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelationRow {
+    id: Thing,
+    document_id: Thing,
+}
+
+/// 标签合并计划：repoint 改指向目标标签，remove 因与目标重复而删除
+#[derive(Debug, Default, PartialEq)]
+struct TagMergePlan {
+    repoint: Vec<String>,
+    remove: Vec<String>,
+}
+
+/// 每个文档最多保留一条指向目标标签的关联，已有目标关联的文档删除全部源关联
+fn plan_tag_merge<I>(source_relations: I, target_documents: &HashSet<String>) -> TagMergePlan
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut covered = target_documents.clone();
+    let mut plan = TagMergePlan::default();
+    for (relation_id, document_id) in source_relations {
+        if covered.insert(document_id) {
+            plan.repoint.push(relation_id);
+        } else {
+            plan.remove.push(relation_id);
+        }
+    }
+    plan
+}
+
+fn dedupe_preserving_order<I>(items: I) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut seen = HashSet::new();
+    items
+        .into_iter()
+        .filter(|item| seen.insert(item.clone()))
+        .collect()
+}
+
+/// 去除首尾空白、过滤空名称并去重，保持原有顺序
+fn normalize_tag_names(names: Vec<String>) -> Result<Vec<String>, AppError> {
+    let names = dedupe_preserving_order(
+        names
+            .into_iter()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty()),
+    );
+
+    if names.is_empty() {
+        return Err(AppError::Validation("At least one tag name is required".to_string()));
+    }
+    if let Some(name) = names.iter().find(|name| name.chars().count() > 50) {
+        return Err(AppError::Validation(format!("Tag name too long: {}", name)));
+    }
+
+    Ok(names)
+}
+
+/// 计算需要新建的 (document_id, tag_id) 关联，跳过已存在的组合
+fn plan_new_associations(
+    document_ids: &[String],
+    tag_ids: &[String],
+    existing: &HashSet<(String, String)>,
+) -> Vec<(String, String)> {
+    let mut planned = HashSet::new();
+    let mut result = Vec::new();
+    for document_id in document_ids {
+        for tag_id in tag_ids {
+            let pair = (document_id.clone(), tag_id.clone());
+            if !existing.contains(&pair) && planned.insert(pair.clone()) {
+                result.push(pair);
+            }
+        }
+    }
+    result
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct TagStatistics {
     pub total_tags: i64,
     pub used_tags: i64,
     pub unused_tags: i64,
     pub most_used_tags: Vec<Tag>,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_bulk_assign_skips_existing_associations() {
+        let documents = ids(&["doc1", "doc2", "doc1"]);
+        let documents = dedupe_preserving_order(documents);
+        let tags = ids(&["rust", "api"]);
+        let existing: HashSet<(String, String)> = [
+            ("doc1".to_string(), "rust".to_string()),
+            ("doc2".to_string(), "api".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        let planned = plan_new_associations(&documents, &tags, &existing);
+
+        assert_eq!(
+            planned,
+            vec![
+                ("doc1".to_string(), "api".to_string()),
+                ("doc2".to_string(), "rust".to_string()),
+            ]
+        );
+        let unique: HashSet<_> = planned.iter().chain(existing.iter()).collect();
+        assert_eq!(unique.len(), documents.len() * tags.len());
+    }
+
+    #[test]
+    fn test_merge_plan_removes_duplicate_relations() {
+        let target_documents: HashSet<String> = ["doc1".to_string()].into_iter().collect();
+        let relations = vec![
+            ("r1".to_string(), "doc1".to_string()), // doc1 已有目标标签
+            ("r2".to_string(), "doc2".to_string()),
+            ("r3".to_string(), "doc2".to_string()), // doc2 同时带有两个源标签
+            ("r4".to_string(), "doc3".to_string()),
+        ];
+
+        let plan = plan_tag_merge(relations, &target_documents);
+
+        assert_eq!(plan.repoint, ids(&["r2", "r4"]));
+        assert_eq!(plan.remove, ids(&["r1", "r3"]));
+    }
+
+    fn tag(id: &str, name: &str) -> Tag {
+        let mut tag = Tag::new(name.to_string(), DEFAULT_TAG_COLOR.to_string(), "user1".to_string());
+        tag.id = Some(Thing::from(("tag", id)));
+        tag
+    }
+
+    #[test]
+    fn test_tag_counts_include_unused_tags() {
+        // 三个文档共享 shared 标签，unused 没有任何关联
+        let tags = vec![tag("t2", "unused"), tag("t1", "shared")];
+        let counts = vec![TagCountRow { tag_id: Thing::from(("tag", "t1")), count: 3 }];
+
+        let result = attach_document_counts(tags, counts);
+
+        assert_eq!(result[0].tag.name, "shared");
+        assert_eq!(result[0].document_count, Some(3));
+        assert_eq!(result[1].tag.name, "unused");
+        assert_eq!(result[1].document_count, Some(0));
+    }
+
+    #[test]
+    fn test_normalize_tag_names() {
+        let names = normalize_tag_names(ids(&[" rust ", "api", "rust", ""])).unwrap();
+        assert_eq!(names, ids(&["rust", "api"]));
+
+        assert!(normalize_tag_names(ids(&["  "])).is_err());
+        assert!(normalize_tag_names(vec!["x".repeat(51)]).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+use chrono::Utc;
+use surrealdb::sql::Thing;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::document::{CreateDocumentRequest, Document},
+    models::template::{CreateTemplateRequest, DocumentTemplate, TemplateContext, TemplateOverrides},
+    services::{activity::actor_display_name, auth::User, database::Database, documents::DocumentService},
+};
+
+#[derive(Clone)]
+pub struct TemplateService {
+    db: Arc<Database>,
+    document_service: Arc<DocumentService>,
+}
+
+impl TemplateService {
+    pub fn new(db: Arc<Database>, document_service: Arc<DocumentService>) -> Self {
+        Self { db, document_service }
+    }
+
+    pub async fn create_template(
+        &self,
+        space_id: &str,
+        creator_id: &str,
+        request: CreateTemplateRequest,
+    ) -> Result<DocumentTemplate, AppError> {
+        request.validate()?;
+
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+        let template = DocumentTemplate::new(
+            Thing::from(("space", space_id)),
+            request.name,
+            request.title_pattern,
+            request.content,
+            creator_id.to_string(),
+        )
+        .with_description(request.description);
+
+        let created: Vec<DocumentTemplate> = self.db.client
+            .create("document_template")
+            .content(template)
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        created
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::internal_server_error("Failed to create template".to_string()))
+    }
+
+    pub async fn list_templates(&self, space_id: &str) -> Result<Vec<DocumentTemplate>, AppError> {
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+
+        let templates: Vec<DocumentTemplate> = self.db.client
+            .query("SELECT * FROM document_template WHERE space_id = $space_id ORDER BY name ASC")
+            .bind(("space_id", Thing::from(("space", space_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        Ok(templates)
+    }
+
+    /// 获取空间内的模板，模板不属于该空间时视为不存在
+    pub async fn get_template(&self, space_id: &str, template_id: &str) -> Result<DocumentTemplate, AppError> {
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id);
+        let template_id = template_id.strip_prefix("document_template:").unwrap_or(template_id);
+
+        let template: Option<DocumentTemplate> = self.db.client
+            .select(("document_template", template_id))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        template
+            .filter(|t| t.space_id.id.to_raw() == space_id)
+            .ok_or_else(|| AppError::NotFound("Template not found".to_string()))
+    }
+
+    /// 基于模板创建文档，渲染标题和正文中的占位符
+    pub async fn apply_template(
+        &self,
+        space_id: &str,
+        template_id: &str,
+        overrides: TemplateOverrides,
+        author: &User,
+    ) -> Result<Document, AppError> {
+        let template = self.get_template(space_id, template_id).await?;
+        let context = TemplateContext {
+            date: Utc::now(),
+            author: actor_display_name(author),
+        };
+
+        let request = build_document_request(&template, overrides, &context);
+        self.document_service.create_document(space_id, &author.id, request).await
+    }
+}
+
+fn build_document_request(
+    template: &DocumentTemplate,
+    overrides: TemplateOverrides,
+    context: &TemplateContext,
+) -> CreateDocumentRequest {
+    CreateDocumentRequest {
+        title: overrides.title.unwrap_or_else(|| context.render(&template.title_pattern)),
+        slug: overrides.slug,
+        content: Some(context.render(&template.content)),
+        excerpt: None,
+        is_public: overrides.is_public,
+        parent_id: overrides.parent_id,
+        order_index: None,
+        metadata: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_applying_template_fills_todays_date() {
+        let template = DocumentTemplate::new(
+            Thing::from(("space", "s1")),
+            "Meeting note".to_string(),
+            "Meeting {{date}}".to_string(),
+            "# Meeting {{date}}\n\nHost: {{author}}".to_string(),
+            "alice".to_string(),
+        );
+        let now = Utc::now();
+        let context = TemplateContext {
+            date: now,
+            author: "alice".to_string(),
+        };
+        let overrides = TemplateOverrides {
+            slug: "weekly-sync".to_string(),
+            ..Default::default()
+        };
+
+        let request = build_document_request(&template, overrides, &context);
+        let today = now.format("%Y-%m-%d").to_string();
+
+        assert_eq!(request.title, format!("Meeting {}", today));
+        assert_eq!(request.content.as_deref(), Some(format!("# Meeting {}\n\nHost: alice", today).as_str()));
+        assert_eq!(request.slug, "weekly-sync");
+        assert!(request.validate().is_ok());
+    }
+}
@@ -95,7 +95,7 @@ impl VectorService {
     ) -> Result<VectorResponse> {
         // 验证向量维度
         if vector_data.embedding.len() != vector_data.dimension {
-            return Err(AppError::BadRequest("Vector dimension mismatch".to_string()));
+            return Err(AppError::Validation("Vector dimension mismatch".to_string()));
         }
 
         // 获取文档信息以验证文档存在并获取space_id
@@ -107,22 +107,22 @@ impl VectorService {
         let mut doc_result = self.db.client
             .query(doc_query)
             .await
-            .map_err(|e| AppError::DatabaseError(format!("Failed to fetch document: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to fetch document: {}", e)))?;
         
         let docs: Vec<serde_json::Value> = doc_result.take(0)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to parse document result: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to parse document result: {}", e)))?;
         
         if docs.is_empty() {
             return Err(AppError::NotFound("Document not found".to_string()));
         }
         
         let space_id = docs[0]["space_id"].as_str()
-            .ok_or_else(|| AppError::DatabaseError("Invalid space_id".to_string()))?
+            .ok_or_else(|| AppError::database_error("Invalid space_id".to_string()))?
             .replace("space:", "");
 
         // 将向量转换为JSON格式
         let embedding_json = serde_json::to_string(&vector_data.embedding)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to serialize embedding: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to serialize embedding: {}", e)))?;
 
         // 存储到数据库
         let metadata_json = vector_data.metadata
@@ -151,14 +151,14 @@ impl VectorService {
         let mut result = self.db.client
             .query(query)
             .await
-            .map_err(|e| AppError::DatabaseError(format!("Failed to store vector: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to store vector: {}", e)))?;
 
         let created: Vec<serde_json::Value> = result.take(0)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to parse create result: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to parse create result: {}", e)))?;
 
         let vector_id = created.get(0)
             .and_then(|v| v["id"].as_str())
-            .ok_or_else(|| AppError::DatabaseError("Failed to get vector ID".to_string()))?;
+            .ok_or_else(|| AppError::database_error("Failed to get vector ID".to_string()))?;
 
         Ok(VectorResponse {
             success: true,
@@ -174,7 +174,7 @@ impl VectorService {
     ) -> Result<VectorSearchResponse> {
         // 构建查询向量字符串
         let query_vector_str = serde_json::to_string(&request.query_vector)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to serialize query vector: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to serialize query vector: {}", e)))?;
         
         // 构建查询
         let query = if let Some(space_id) = request.space_id {
@@ -226,10 +226,10 @@ impl VectorService {
         let mut results = self.db.client
             .query(query)
             .await
-            .map_err(|e| AppError::DatabaseError(format!("Failed to search vectors: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to search vectors: {}", e)))?;
 
         let rows: Vec<serde_json::Value> = results.take(0)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to parse search results: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to parse search results: {}", e)))?;
 
         let search_results: Vec<VectorSearchResult> = rows
             .iter()
@@ -272,10 +272,10 @@ impl VectorService {
         let mut vectors = self.db.client
             .query(query)
             .await
-            .map_err(|e| AppError::DatabaseError(format!("Failed to get vectors: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to get vectors: {}", e)))?;
 
         let rows: Vec<serde_json::Value> = vectors.take(0)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to parse vector results: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to parse vector results: {}", e)))?;
 
         let vector_infos: Vec<VectorInfo> = rows
             .iter()
@@ -306,7 +306,7 @@ impl VectorService {
         self.db.client
             .query(query)
             .await
-            .map_err(|e| AppError::DatabaseError(format!("Failed to delete vector: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to delete vector: {}", e)))?;
 
         Ok(true)
     }
@@ -358,10 +358,10 @@ impl VectorService {
         let mut results = self.db.client
             .query(query)
             .await
-            .map_err(|e| AppError::DatabaseError(format!("Failed to get documents: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to get documents: {}", e)))?;
         
         let docs: Vec<serde_json::Value> = results.take(0)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to parse documents: {}", e)))?;
+            .map_err(|e| AppError::database_error(format!("Failed to parse documents: {}", e)))?;
         
         Ok(docs)
     }
@@ -3,20 +3,33 @@ use surrealdb::sql::Thing;
 use validator::Validate;
 
 use crate::{
-    error::ApiError,
+    error::AppError,
+    models::document::UpdateDocumentRequest,
     models::version::{DocumentVersion, CreateVersionRequest, VersionChangeType},
-    services::{auth::AuthService, database::Database},
+    services::{auth::AuthService, database::Database, documents::DocumentService},
+    utils::diff::{diff_text, DiffSpan},
 };
 
 #[derive(Clone)]
 pub struct VersionService {
     db: Arc<Database>,
     auth_service: Arc<AuthService>,
+    max_versions_per_document: usize,
 }
 
 impl VersionService {
     pub fn new(db: Arc<Database>, auth_service: Arc<AuthService>) -> Self {
-        Self { db, auth_service }
+        Self {
+            db,
+            auth_service,
+            max_versions_per_document: 0,
+        }
+    }
+
+    /// 设置每个文档保留的最大版本数，0 表示不限制
+    pub fn with_max_versions_per_document(mut self, max_versions: usize) -> Self {
+        self.max_versions_per_document = max_versions;
+        self
     }
 
     pub async fn create_version(
@@ -24,7 +37,7 @@ impl VersionService {
         document_id: &str,
         author_id: &str,
         request: CreateVersionRequest,
-    ) -> Result<DocumentVersion, ApiError> {
+    ) -> Result<DocumentVersion, AppError> {
         request.validate()?;
 
         // 获取当前最新版本号
@@ -49,21 +62,107 @@ impl VersionService {
             .create("document_version")
             .content(version)
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        created
+        let created_version = created
             .into_iter()
             .next()
-            .ok_or_else(|| ApiError::InternalServerError("Failed to create version".to_string()))
+            .ok_or_else(|| AppError::internal_server_error("Failed to create version".to_string()))?;
+
+        // 超出保留数量时自动清理旧版本
+        if let Err(e) = self.prune_versions(document_id).await {
+            tracing::warn!("Failed to prune versions for document {}: {}", document_id, e);
+        }
+
+        Ok(created_version)
+    }
+
+    /// 清理旧版本：保留最近的 N 个版本、当前版本以及所有固定的版本
+    pub async fn prune_versions(&self, document_id: &str) -> Result<usize, AppError> {
+        if self.max_versions_per_document == 0 {
+            return Ok(0);
+        }
+
+        let query = "
+            SELECT * FROM document_version 
+            WHERE document_id = $document_id 
+            ORDER BY version_number DESC
+        ";
+
+        let versions: Vec<DocumentVersion> = self.db.client
+            .query(query)
+            .bind(("document_id", Thing::from(("document", document_id))))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        let to_delete = select_versions_to_prune(&versions, self.max_versions_per_document);
+        if to_delete.is_empty() {
+            return Ok(0);
+        }
+
+        let _: Vec<surrealdb::sql::Value> = self.db.client
+            .query("DELETE document_version WHERE id IN $ids")
+            .bind(("ids", to_delete.clone()))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        tracing::info!("Pruned {} versions of document {}", to_delete.len(), document_id);
+        Ok(to_delete.len())
     }
 
-    pub async fn get_version(&self, version_id: &str) -> Result<DocumentVersion, ApiError> {
+    /// 固定版本，固定的版本不会被自动清理
+    pub async fn pin_version(&self, document_id: &str, version_number: i32) -> Result<DocumentVersion, AppError> {
+        self.set_version_pinned(document_id, version_number, true).await
+    }
+
+    pub async fn unpin_version(&self, document_id: &str, version_number: i32) -> Result<DocumentVersion, AppError> {
+        self.set_version_pinned(document_id, version_number, false).await
+    }
+
+    async fn set_version_pinned(
+        &self,
+        document_id: &str,
+        version_number: i32,
+        pinned: bool,
+    ) -> Result<DocumentVersion, AppError> {
+        // 确认版本存在
+        self.get_version_by_number(document_id, version_number).await?;
+
+        let query = "
+            UPDATE document_version 
+            SET pinned = $pinned 
+            WHERE document_id = $document_id 
+            AND version_number = $version_number
+            RETURN AFTER
+        ";
+
+        let updated: Vec<DocumentVersion> = self.db.client
+            .query(query)
+            .bind(("document_id", Thing::from(("document", document_id))))
+            .bind(("version_number", version_number))
+            .bind(("pinned", pinned))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        updated
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::internal_server_error("Failed to update version".to_string()))
+    }
+
+    pub async fn get_version(&self, version_id: &str) -> Result<DocumentVersion, AppError> {
         let version: Option<DocumentVersion> = self.db.client
             .select(("document_version", version_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
-        version.ok_or_else(|| ApiError::NotFound("Version not found".to_string()))
+        version.ok_or_else(|| AppError::NotFound("Version not found".to_string()))
     }
 
     pub async fn get_document_versions(
@@ -71,7 +170,7 @@ impl VersionService {
         document_id: &str,
         page: i64,
         per_page: i64,
-    ) -> Result<Vec<DocumentVersion>, ApiError> {
+    ) -> Result<Vec<DocumentVersion>, AppError> {
         let offset = (page - 1) * per_page;
         
         let query = "
@@ -87,14 +186,14 @@ impl VersionService {
             .bind(("limit", per_page))
             .bind(("offset", offset))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(versions)
     }
 
-    pub async fn get_current_version(&self, document_id: &str) -> Result<Option<DocumentVersion>, ApiError> {
+    pub async fn get_current_version(&self, document_id: &str) -> Result<Option<DocumentVersion>, AppError> {
         let query = "
             SELECT * FROM document_version 
             WHERE document_id = $document_id 
@@ -106,48 +205,61 @@ impl VersionService {
             .query(query)
             .bind(("document_id", Thing::from(("document", document_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(versions.into_iter().next())
     }
 
+    /// 将文档恢复到指定版本号的内容，并记录一个新的 Restored 版本
     pub async fn restore_version(
         &self,
         document_id: &str,
-        version_id: &str,
+        version_number: i32,
         restorer_id: &str,
-    ) -> Result<DocumentVersion, ApiError> {
-        let old_version = self.get_version(version_id).await?;
-        
-        // 验证版本属于指定文档
-        if old_version.document_id.to_string() != format!("document:{}", document_id) {
-            return Err(ApiError::BadRequest("Version does not belong to document".to_string()));
-        }
-
-        // 创建新版本（恢复操作）
-        let restore_request = CreateVersionRequest {
-            title: old_version.title.clone(),
-            content: old_version.content.clone(),
-            summary: Some(format!("Restored from version {}", old_version.version_number)),
-            change_type: VersionChangeType::Restored,
+        document_service: &DocumentService,
+    ) -> Result<DocumentVersion, AppError> {
+        let old_version = self.get_version_by_number(document_id, version_number).await?;
+
+        let update_request = UpdateDocumentRequest {
+            title: Some(old_version.title.clone()),
+            slug: None,
+            content: Some(old_version.content.clone()),
+            excerpt: None,
+            is_public: None,
+            parent_id: None,
+            order_index: None,
+            metadata: None,
+            expected_updated_at: None,
+            rewrite_backlinks: false,
         };
 
-        self.create_version(document_id, restorer_id, restore_request).await
+        // 通过 DocumentService 写回文档，同时更新搜索索引并创建新版本
+        document_service.update_document_with_change(
+            document_id,
+            restorer_id,
+            update_request,
+            format!("Restored from v{}", old_version.version_number),
+            VersionChangeType::Restored,
+        ).await?;
+
+        self.get_current_version(document_id)
+            .await?
+            .ok_or_else(|| AppError::internal_server_error("Failed to record restored version".to_string()))
     }
 
     pub async fn compare_versions(
         &self,
         version_id_1: &str,
         version_id_2: &str,
-    ) -> Result<VersionComparison, ApiError> {
+    ) -> Result<VersionComparison, AppError> {
         let version1 = self.get_version(version_id_1).await?;
         let version2 = self.get_version(version_id_2).await?;
 
         // 验证两个版本属于同一文档
         if version1.document_id != version2.document_id {
-            return Err(ApiError::BadRequest("Versions belong to different documents".to_string()));
+            return Err(AppError::Validation("Versions belong to different documents".to_string()));
         }
 
         let comparison = VersionComparison {
@@ -165,10 +277,55 @@ impl VersionService {
         Ok(comparison)
     }
 
+    /// 根据版本号获取文档的某个版本
+    pub async fn get_version_by_number(
+        &self,
+        document_id: &str,
+        version_number: i32,
+    ) -> Result<DocumentVersion, AppError> {
+        let query = "
+            SELECT * FROM document_version 
+            WHERE document_id = $document_id 
+            AND version_number = $version_number
+            LIMIT 1
+        ";
+
+        let versions: Vec<DocumentVersion> = self.db.client
+            .query(query)
+            .bind(("document_id", Thing::from(("document", document_id))))
+            .bind(("version_number", version_number))
+            .await
+            .map_err(|e| AppError::database_error(e.to_string()))?
+            .take(0)
+            .map_err(|e| AppError::database_error(e.to_string()))?;
+
+        versions.into_iter().next().ok_or_else(|| {
+            AppError::NotFound(format!("Version {} not found for this document", version_number))
+        })
+    }
+
+    /// 按版本号比较两个版本的标题和内容，返回词级差异
+    pub async fn diff_versions(
+        &self,
+        document_id: &str,
+        from_version: i32,
+        to_version: i32,
+    ) -> Result<VersionDiff, AppError> {
+        let from = self.get_version_by_number(document_id, from_version).await?;
+        let to = self.get_version_by_number(document_id, to_version).await?;
+
+        Ok(VersionDiff {
+            from_version,
+            to_version,
+            title_diff: diff_text(&from.title, &to.title),
+            content_diff: diff_text(&from.content, &to.content),
+        })
+    }
+
     pub async fn get_version_history_summary(
         &self,
         document_id: &str,
-    ) -> Result<VersionHistorySummary, ApiError> {
+    ) -> Result<VersionHistorySummary, AppError> {
         let query = "
             SELECT 
                 count() as total_versions,
@@ -185,9 +342,9 @@ impl VersionService {
             .query(query)
             .bind(("document_id", Thing::from(("document", document_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         if let Some(summary_data) = result.first() {
             // 这里需要解析SurrealDB的返回结果
@@ -212,18 +369,18 @@ impl VersionService {
         }
     }
 
-    pub async fn delete_version(&self, version_id: &str) -> Result<(), ApiError> {
+    pub async fn delete_version(&self, version_id: &str) -> Result<(), AppError> {
         let version = self.get_version(version_id).await?;
         
         // 不允许删除当前版本
         if version.is_current {
-            return Err(ApiError::BadRequest("Cannot delete current version".to_string()));
+            return Err(AppError::Validation("Cannot delete current version".to_string()));
         }
 
         let _: Option<DocumentVersion> = self.db.client
             .delete(("document_version", version_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(())
     }
@@ -232,7 +389,7 @@ impl VersionService {
         &self,
         document_id: &str,
         author_id: &str,
-    ) -> Result<Vec<DocumentVersion>, ApiError> {
+    ) -> Result<Vec<DocumentVersion>, AppError> {
         let query = "
             SELECT * FROM document_version 
             WHERE document_id = $document_id 
@@ -245,14 +402,14 @@ impl VersionService {
             .bind(("document_id", Thing::from(("document", document_id))))
             .bind(("author_id", author_id))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(versions)
     }
 
-    async fn get_latest_version_number(&self, document_id: &str) -> Result<i32, ApiError> {
+    async fn get_latest_version_number(&self, document_id: &str) -> Result<i32, AppError> {
         let query = "
             SELECT MAX(version_number) as max_version 
             FROM document_version 
@@ -264,9 +421,9 @@ impl VersionService {
             .query(query)
             .bind(("document_id", Thing::from(("document", document_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         let max_version = result
             .first()
@@ -276,7 +433,7 @@ impl VersionService {
         Ok(max_version as i32)
     }
 
-    async fn unset_current_version(&self, document_id: &str) -> Result<(), ApiError> {
+    async fn unset_current_version(&self, document_id: &str) -> Result<(), AppError> {
         let query = "
             UPDATE document_version 
             SET is_current = false 
@@ -288,9 +445,9 @@ impl VersionService {
             .query(query)
             .bind(("document_id", Thing::from(("document", document_id))))
             .await
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?
+            .map_err(|e| AppError::database_error(e.to_string()))?
             .take(0)
-            .map_err(|e| ApiError::DatabaseError(e.to_string()))?;
+            .map_err(|e| AppError::database_error(e.to_string()))?;
 
         Ok(())
     }
@@ -346,6 +503,14 @@ pub struct VersionComparison {
     pub summary: String,
 }
 
+#[derive(Debug, serde::Serialize)]
+pub struct VersionDiff {
+    pub from_version: i32,
+    pub to_version: i32,
+    pub title_diff: Vec<DiffSpan>,
+    pub content_diff: Vec<DiffSpan>,
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct ContentDiff {
     pub line_number: usize,
@@ -369,4 +534,60 @@ pub struct VersionHistorySummary {
     pub last_updated: surrealdb::sql::Datetime,
     pub authors: Vec<String>,
     pub change_types_count: std::collections::HashMap<String, i64>,
-}
\ No newline at end of file
+}
+
+/// 计算需要清理的版本ID：按版本号保留最近的 keep 个，当前版本和固定版本始终保留
+fn select_versions_to_prune(versions: &[DocumentVersion], keep: usize) -> Vec<Thing> {
+    let mut sorted: Vec<&DocumentVersion> = versions.iter().collect();
+    sorted.sort_by(|a, b| b.version_number.cmp(&a.version_number));
+
+    sorted
+        .into_iter()
+        .skip(keep)
+        .filter(|version| !version.pinned && !version.is_current)
+        .filter_map(|version| version.id.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(number: i32, pinned: bool, is_current: bool) -> DocumentVersion {
+        let mut version = DocumentVersion::new(
+            Thing::from(("document", "doc_1")),
+            number,
+            format!("v{}", number),
+            "content".to_string(),
+            "user_1".to_string(),
+            VersionChangeType::Updated,
+        );
+        version.id = Some(Thing::from(("document_version", format!("v{}", number).as_str())));
+        version.pinned = pinned;
+        version.is_current = is_current;
+        version
+    }
+
+    #[test]
+    fn test_prune_keeps_recent_and_pinned_versions() {
+        let versions = vec![
+            version(1, false, false),
+            version(2, true, false),
+            version(3, false, false),
+            version(4, false, false),
+            version(5, false, true),
+        ];
+
+        let pruned = select_versions_to_prune(&versions, 2);
+        let pruned_ids: Vec<String> = pruned.iter().map(|id| id.id.to_string()).collect();
+
+        // 保留 v5、v4（最近两个）和固定的 v2
+        assert_eq!(pruned_ids, vec!["v3".to_string(), "v1".to_string()]);
+    }
+
+    #[test]
+    fn test_prune_nothing_when_under_limit() {
+        let versions = vec![version(1, false, false), version(2, false, true)];
+        assert!(select_versions_to_prune(&versions, 5).is_empty());
+    }
+}
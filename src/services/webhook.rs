@@ -0,0 +1,670 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use surrealdb::sql::Thing;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::{AppError, Result},
+    models::{
+        document::Document,
+        webhook::{
+            CreateWebhookRequest, CreatedWebhook, Webhook, WebhookDb, WebhookDelivery, WebhookDeliveryDb,
+            WebhookDeliveryStatus, WebhookEvent, WebhookPayload,
+        },
+    },
+    services::database::Database,
+};
+
+/// 请求体的 HMAC-SHA256 签名，格式为 `sha256=<hex>`
+pub const SIGNATURE_HEADER: &str = "X-Rainbow-Signature";
+pub const EVENT_HEADER: &str = "X-Rainbow-Event";
+pub const DELIVERY_HEADER: &str = "X-Rainbow-Delivery";
+
+/// 每次事件最多投递次数（含首次）
+const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+/// 首次重试前的等待时间，之后每次翻倍
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+/// 单次请求超时时间
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// 投递记录列表最多返回条数
+const MAX_DELIVERY_LIST_LIMIT: u32 = 100;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Clone)]
+pub struct WebhookService {
+    db: Arc<Database>,
+    sender: WebhookSender,
+}
+
+impl WebhookService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self {
+            db,
+            sender: WebhookSender::new(MAX_DELIVERY_ATTEMPTS, RETRY_BASE_DELAY),
+        }
+    }
+
+    /// 是否允许投递到回环、私有网段等内部地址，只应在接收方部署在内网时开启
+    pub fn with_private_targets(mut self, allowed: bool) -> Self {
+        self.sender = self.sender.with_private_targets(allowed);
+        self
+    }
+
+    pub async fn create_webhook(
+        &self,
+        space_id: &str,
+        creator_id: &str,
+        request: CreateWebhookRequest,
+    ) -> Result<CreatedWebhook> {
+        request.validate()?;
+        resolve_target(&request.url, self.sender.allow_private_targets)
+            .await
+            .map_err(AppError::Validation)?;
+
+        let mut events: Vec<WebhookEvent> = Vec::with_capacity(request.events.len());
+        for event in request.events {
+            if !events.contains(&event) {
+                events.push(event);
+            }
+        }
+        let secret = request.secret.unwrap_or_else(generate_secret);
+
+        let query = r#"
+            CREATE webhook SET
+                space_id = $space_id,
+                url = $url,
+                secret = $secret,
+                events = $events,
+                is_active = true,
+                created_by = $created_by,
+                created_at = time::now(),
+                updated_at = time::now()
+        "#;
+
+        let created: Vec<WebhookDb> = self.db.client
+            .query(query)
+            .bind(("space_id", space_thing(space_id)))
+            .bind(("url", &request.url))
+            .bind(("secret", &secret))
+            .bind(("events", &events))
+            .bind(("created_by", creator_id))
+            .await?
+            .take(0)?;
+
+        let webhook: Webhook = created.into_iter()
+            .next()
+            .map(Webhook::from)
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Failed to create webhook")))?;
+
+        info!("Created webhook {:?} for space {}", webhook.id, space_id);
+        Ok(CreatedWebhook { webhook, secret })
+    }
+
+    pub async fn list_webhooks(&self, space_id: &str) -> Result<Vec<Webhook>> {
+        let webhooks: Vec<WebhookDb> = self.db.client
+            .query("SELECT * FROM webhook WHERE space_id = $space_id ORDER BY created_at DESC")
+            .bind(("space_id", space_thing(space_id)))
+            .await?
+            .take(0)?;
+
+        Ok(webhooks.into_iter().map(Webhook::from).collect())
+    }
+
+    pub async fn get_webhook(&self, space_id: &str, webhook_id: &str) -> Result<Webhook> {
+        let webhooks: Vec<WebhookDb> = self.db.client
+            .query("SELECT * FROM type::thing('webhook', $id) WHERE space_id = $space_id")
+            .bind(("id", raw_webhook_id(webhook_id)))
+            .bind(("space_id", space_thing(space_id)))
+            .await?
+            .take(0)?;
+
+        webhooks.into_iter()
+            .next()
+            .map(Webhook::from)
+            .ok_or_else(|| AppError::NotFound("Webhook not found".to_string()))
+    }
+
+    /// 删除 webhook 及其投递记录
+    pub async fn delete_webhook(&self, space_id: &str, webhook_id: &str) -> Result<()> {
+        let deleted: Vec<WebhookDb> = self.db.client
+            .query("DELETE type::thing('webhook', $id) WHERE space_id = $space_id RETURN BEFORE")
+            .bind(("id", raw_webhook_id(webhook_id)))
+            .bind(("space_id", space_thing(space_id)))
+            .await?
+            .take(0)?;
+
+        if deleted.is_empty() {
+            return Err(AppError::NotFound("Webhook not found".to_string()));
+        }
+
+        self.db.client
+            .query("DELETE webhook_delivery WHERE webhook_id = type::thing('webhook', $id)")
+            .bind(("id", raw_webhook_id(webhook_id)))
+            .await?
+            .check()?;
+
+        info!("Deleted webhook {} from space {}", webhook_id, space_id);
+        Ok(())
+    }
+
+    /// 最近的投递记录，按时间倒序
+    pub async fn list_deliveries(&self, space_id: &str, webhook_id: &str, limit: Option<u32>) -> Result<Vec<WebhookDelivery>> {
+        let webhook = self.get_webhook(space_id, webhook_id).await?;
+        let limit = limit.unwrap_or(20).clamp(1, MAX_DELIVERY_LIST_LIMIT);
+
+        let deliveries: Vec<WebhookDeliveryDb> = self.db.client
+            .query("SELECT * FROM webhook_delivery WHERE webhook_id = type::thing('webhook', $id)
+                ORDER BY created_at DESC LIMIT $limit")
+            .bind(("id", raw_webhook_id(webhook.id.as_deref().unwrap_or(webhook_id))))
+            .bind(("limit", limit))
+            .await?
+            .take(0)?;
+
+        Ok(deliveries.into_iter().map(WebhookDelivery::from).collect())
+    }
+
+    /// 异步推送事件给空间内订阅了该事件的 webhook，不阻塞调用方
+    pub fn dispatch(&self, space_id: &str, event: WebhookEvent, data: Value) {
+        let service = self.clone();
+        let space_id = space_id.strip_prefix("space:").unwrap_or(space_id).to_string();
+
+        tokio::spawn(async move {
+            let webhooks = match service.subscribed_webhooks(&space_id, event).await {
+                Ok(webhooks) => webhooks,
+                Err(e) => {
+                    warn!("Failed to load webhooks of space {} for {}: {}", space_id, event.as_str(), e);
+                    return;
+                }
+            };
+
+            // 各 webhook 独立投递，一个接收方的重试不影响其他接收方
+            for webhook in webhooks {
+                let service = service.clone();
+                let space_id = space_id.clone();
+                let data = data.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = service.deliver(&webhook, &space_id, event, data).await {
+                        warn!("Failed to record webhook delivery for {:?}: {}", webhook.id, e);
+                    }
+                });
+            }
+        });
+    }
+
+    async fn subscribed_webhooks(&self, space_id: &str, event: WebhookEvent) -> Result<Vec<Webhook>> {
+        let webhooks: Vec<WebhookDb> = self.db.client
+            .query("SELECT * FROM webhook WHERE space_id = $space_id AND is_active = true AND events CONTAINS $event")
+            .bind(("space_id", space_thing(space_id)))
+            .bind(("event", event))
+            .await?
+            .take(0)?;
+
+        Ok(webhooks.into_iter()
+            .map(Webhook::from)
+            .filter(|webhook| webhook.subscribes_to(event))
+            .collect())
+    }
+
+    /// 记录一次投递，发送（含重试）后写回结果
+    async fn deliver(&self, webhook: &Webhook, space_id: &str, event: WebhookEvent, data: Value) -> Result<()> {
+        let webhook_id = webhook.id.as_deref()
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Webhook ID is missing")))?;
+        let payload = WebhookPayload {
+            delivery_id: Uuid::new_v4().simple().to_string(),
+            event,
+            space_id: space_id.to_string(),
+            occurred_at: Utc::now(),
+            data,
+        };
+        let body = serde_json::to_string(&payload)?;
+
+        self.db.client
+            .query("CREATE type::thing('webhook_delivery', $id) SET
+                webhook_id = type::thing('webhook', $webhook_id),
+                event = $event,
+                payload = $payload,
+                status = 'pending',
+                attempts = 0,
+                created_at = time::now()")
+            .bind(("id", &payload.delivery_id))
+            .bind(("webhook_id", raw_webhook_id(webhook_id)))
+            .bind(("event", event))
+            .bind(("payload", &payload))
+            .await?
+            .check()?;
+
+        let outcome = self.sender.send(&webhook.url, &webhook.secret, event, &payload.delivery_id, &body).await;
+        if outcome.status() == WebhookDeliveryStatus::Failed {
+            warn!(
+                "Webhook {} gave up delivering {} after {} attempts: {}",
+                webhook_id,
+                event.as_str(),
+                outcome.attempts,
+                outcome.error.as_deref().unwrap_or_default()
+            );
+        }
+
+        let delivered_at = if outcome.status() == WebhookDeliveryStatus::Succeeded { "time::now()" } else { "NONE" };
+        self.db.client
+            .query(format!(
+                "UPDATE type::thing('webhook_delivery', $id) SET
+                    status = $status,
+                    attempts = $attempts,
+                    response_status = $response_status,
+                    error = $error,
+                    delivered_at = {}",
+                delivered_at
+            ))
+            .bind(("id", &payload.delivery_id))
+            .bind(("status", outcome.status()))
+            .bind(("attempts", outcome.attempts))
+            .bind(("response_status", outcome.response_status))
+            .bind(("error", &outcome.error))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+}
+
+/// 负责签名和发送 HTTP 请求，失败时按指数退避重试
+#[derive(Clone)]
+pub struct WebhookSender {
+    max_attempts: u32,
+    retry_base_delay: Duration,
+    allow_private_targets: bool,
+}
+
+/// 一次投递（含重试）的最终结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryOutcome {
+    pub attempts: u32,
+    pub response_status: Option<u16>,
+    /// 最后一次失败的原因，成功时为 None
+    pub error: Option<String>,
+}
+
+impl DeliveryOutcome {
+    pub fn status(&self) -> WebhookDeliveryStatus {
+        if self.error.is_none() {
+            WebhookDeliveryStatus::Succeeded
+        } else {
+            WebhookDeliveryStatus::Failed
+        }
+    }
+}
+
+impl WebhookSender {
+    pub fn new(max_attempts: u32, retry_base_delay: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            retry_base_delay,
+            allow_private_targets: false,
+        }
+    }
+
+    pub fn with_private_targets(mut self, allowed: bool) -> Self {
+        self.allow_private_targets = allowed;
+        self
+    }
+
+    /// 发送直到收到 2xx 响应或用完重试次数
+    pub async fn send(&self, url: &str, secret: &str, event: WebhookEvent, delivery_id: &str, body: &str) -> DeliveryOutcome {
+        let mut outcome = DeliveryOutcome { attempts: 0, response_status: None, error: None };
+
+        for attempt in 1..=self.max_attempts {
+            outcome.attempts = attempt;
+            match self.send_once(url, secret, event, delivery_id, body).await {
+                Ok(status) => {
+                    outcome.response_status = Some(status);
+                    outcome.error = None;
+                    return outcome;
+                }
+                Err((status, error)) => {
+                    debug!("Webhook delivery {} attempt {} failed: {}", delivery_id, attempt, error);
+                    outcome.response_status = status;
+                    outcome.error = Some(error);
+                }
+            }
+
+            if attempt < self.max_attempts {
+                tokio::time::sleep(retry_delay(self.retry_base_delay, attempt)).await;
+            }
+        }
+
+        outcome
+    }
+
+    /// 单次请求：2xx 返回状态码，否则返回状态码（如有）和错误信息
+    async fn send_once(
+        &self,
+        url: &str,
+        secret: &str,
+        event: WebhookEvent,
+        delivery_id: &str,
+        body: &str,
+    ) -> std::result::Result<u16, (Option<u16>, String)> {
+        // 每次请求都重新解析并校验地址，连接固定到校验过的地址，避免 DNS 重绑定绕过检查
+        let (host, addr) = resolve_target(url, self.allow_private_targets).await.map_err(|e| (None, e))?;
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .user_agent(concat!("Rainbow-Docs-Webhook/", env!("CARGO_PKG_VERSION")))
+            .redirect(reqwest::redirect::Policy::none())
+            .resolve(&host, addr)
+            .build()
+            .map_err(|e| (None, e.to_string()))?;
+
+        let response = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event.as_str())
+            .header(DELIVERY_HEADER, delivery_id)
+            .header(SIGNATURE_HEADER, sign_payload(secret, body.as_bytes()))
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| (None, e.to_string()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(status.as_u16())
+        } else {
+            Err((Some(status.as_u16()), format!("Receiver responded with {}", status)))
+        }
+    }
+}
+
+/// 校验 webhook 地址并解析出要连接的地址：只允许 http(s)，解析结果中不能有回环、私有网段、
+/// 链路本地（如云服务元数据 169.254.169.254）等内部地址，防止借 webhook 访问内网
+async fn resolve_target(url: &str, allow_private_targets: bool) -> std::result::Result<(String, SocketAddr), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| "Invalid webhook URL".to_string())?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Webhook URL must use http or https".to_string());
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| "Webhook URL must have a host".to_string())?
+        .to_string();
+    let port = parsed.port_or_known_default().unwrap_or(80);
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_matches(|c| c == '[' || c == ']'), port))
+        .await
+        .map_err(|e| format!("Failed to resolve webhook host {}: {}", host, e))?
+        .collect();
+    let first = *addrs
+        .first()
+        .ok_or_else(|| format!("Webhook host {} did not resolve", host))?;
+    if !allow_private_targets && addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!("Webhook host {} resolves to a private or reserved address", host));
+    }
+
+    Ok((host, first))
+}
+
+/// 是否为公网可路由的地址
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // 100.64.0.0/10 运营商级 NAT
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // fc00::/7 唯一本地地址
+                || (first & 0xfe00) == 0xfc00
+                // fe80::/10 链路本地地址
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// 计算请求体签名，接收方用同一密钥对原始请求体计算后比较
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let hex: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
+
+/// 第 attempt 次失败后的等待时间：base * 2^(attempt-1)
+fn retry_delay(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(1u32 << attempt.saturating_sub(1).min(16))
+}
+
+/// 文档事件携带的数据，不包含正文
+pub fn document_event_data(document: &Document) -> Value {
+    json!({
+        "document": {
+            "id": document.id,
+            "space_id": document.space_id,
+            "parent_id": document.parent_id,
+            "title": document.title,
+            "slug": document.slug,
+            "excerpt": document.excerpt,
+            "is_public": document.is_public,
+            "author_id": document.author_id,
+            "updated_by": document.updated_by,
+            "created_at": document.created_at,
+            "updated_at": document.updated_at,
+        }
+    })
+}
+
+fn generate_secret() -> String {
+    format!("whsec_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn space_thing(space_id: &str) -> Thing {
+    Thing::from(("space", space_id.strip_prefix("space:").unwrap_or(space_id)))
+}
+
+fn raw_webhook_id(webhook_id: &str) -> &str {
+    webhook_id.strip_prefix("webhook:").unwrap_or(webhook_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Bytes, extract::State, http::{HeaderMap, StatusCode}, routing::post, Router};
+    use std::sync::Mutex as StdMutex;
+
+    type Received = Arc<StdMutex<Vec<(HeaderMap, Bytes)>>>;
+
+    /// 启动一个模拟接收方，前 failures 次请求返回 500，之后返回 200
+    fn spawn_mock_receiver(failures: usize) -> (String, Received) {
+        let received: Received = Arc::new(StdMutex::new(Vec::new()));
+
+        async fn handler(
+            State((received, failures)): State<(Received, usize)>,
+            headers: HeaderMap,
+            body: Bytes,
+        ) -> StatusCode {
+            let mut received = received.lock().unwrap();
+            received.push((headers, body));
+            if received.len() <= failures {
+                StatusCode::INTERNAL_SERVER_ERROR
+            } else {
+                StatusCode::OK
+            }
+        }
+
+        let app = Router::new()
+            .route("/hooks", post(handler))
+            .with_state((received.clone(), failures));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+
+        (format!("http://{}/hooks", addr), received)
+    }
+
+    fn updated_document() -> Document {
+        serde_json::from_value(json!({
+            "id": "document:intro",
+            "space_id": "space:handbook",
+            "title": "Getting Started",
+            "slug": "getting-started",
+            "content": "# Getting Started",
+            "excerpt": "Getting Started",
+            "is_public": true,
+            "parent_id": null,
+            "order_index": 0,
+            "author_id": "alice",
+            "last_editor_id": "bob",
+            "view_count": 0,
+            "word_count": 2,
+            "reading_time": 1,
+            "metadata": {},
+            "updated_by": "bob",
+            "is_deleted": false,
+            "deleted_at": null,
+            "deleted_by": null,
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-02T00:00:00Z"
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_document_update_is_signed_and_received() {
+        let (url, received) = spawn_mock_receiver(0);
+        let secret = "whsec_test_secret_value";
+        let payload = WebhookPayload {
+            delivery_id: "delivery-1".to_string(),
+            event: WebhookEvent::DocumentUpdated,
+            space_id: "handbook".to_string(),
+            occurred_at: Utc::now(),
+            data: document_event_data(&updated_document()),
+        };
+        let body = serde_json::to_string(&payload).unwrap();
+
+        // 模拟接收方监听在 127.0.0.1
+        let sender = WebhookSender::new(3, Duration::from_millis(1)).with_private_targets(true);
+        let outcome = sender.send(&url, secret, payload.event, &payload.delivery_id, &body).await;
+        assert_eq!(outcome, DeliveryOutcome { attempts: 1, response_status: Some(200), error: None });
+
+        let (headers, raw_body) = received.lock().unwrap().pop().expect("request not received");
+        assert_eq!(headers[EVENT_HEADER], "document.updated");
+        assert_eq!(headers[DELIVERY_HEADER], "delivery-1");
+
+        // 接收方按文档说明用密钥独立验证签名
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(&raw_body);
+        let expected: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(signature, format!("sha256={}", expected));
+        assert_ne!(signature, sign_payload("another-secret", &raw_body));
+
+        let received_payload: WebhookPayload = serde_json::from_slice(&raw_body).unwrap();
+        assert_eq!(received_payload, payload);
+        assert_eq!(received_payload.data["document"]["id"], "document:intro");
+        assert_eq!(received_payload.data["document"]["title"], "Getting Started");
+        assert_eq!(received_payload.data["document"]["updated_by"], "bob");
+        assert!(received_payload.data["document"].get("content").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_is_retried_until_success() {
+        let (url, received) = spawn_mock_receiver(2);
+        // 模拟接收方监听在 127.0.0.1
+        let sender = WebhookSender::new(3, Duration::from_millis(1)).with_private_targets(true);
+
+        let outcome = sender.send(&url, "secret", WebhookEvent::DocumentCreated, "delivery-2", "{}").await;
+        assert_eq!(outcome.attempts, 3);
+        assert_eq!(outcome.status(), WebhookDeliveryStatus::Succeeded);
+        assert_eq!(received.lock().unwrap().len(), 3);
+
+        let (url, _received) = spawn_mock_receiver(usize::MAX);
+        let outcome = sender.send(&url, "secret", WebhookEvent::DocumentCreated, "delivery-3", "{}").await;
+        assert_eq!(outcome.attempts, 3);
+        assert_eq!(outcome.response_status, Some(500));
+        assert_eq!(outcome.status(), WebhookDeliveryStatus::Failed);
+
+        assert_eq!(retry_delay(Duration::from_secs(2), 1), Duration::from_secs(2));
+        assert_eq!(retry_delay(Duration::from_secs(2), 3), Duration::from_secs(8));
+    }
+
+    #[tokio::test]
+    async fn test_internal_targets_are_rejected() {
+        for url in [
+            "http://127.0.0.1:8080/hooks",
+            "http://localhost/hooks",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://10.0.0.5/hooks",
+            "http://192.168.1.10/hooks",
+            "http://[::1]/hooks",
+            "http://[::ffff:127.0.0.1]/hooks",
+        ] {
+            let error = resolve_target(url, false).await.unwrap_err();
+            assert!(error.contains("private or reserved address"), "{} was not rejected: {}", url, error);
+        }
+
+        assert_eq!(resolve_target("ftp://example.com/hooks", false).await.unwrap_err(), "Webhook URL must use http or https");
+        assert_eq!(resolve_target("file:///etc/passwd", false).await.unwrap_err(), "Webhook URL must use http or https");
+
+        // 公网地址允许，显式开启后内网地址也允许
+        assert_eq!(resolve_target("https://93.184.215.14/hooks", false).await.unwrap().1, "93.184.215.14:443".parse().unwrap());
+        assert_eq!(resolve_target("http://127.0.0.1:8080/hooks", true).await.unwrap().1, "127.0.0.1:8080".parse().unwrap());
+
+        // 发送时同样校验，不会发出请求
+        let sender = WebhookSender::new(1, Duration::from_millis(1));
+        let outcome = sender.send("http://127.0.0.1:9/hooks", "secret", WebhookEvent::DocumentCreated, "delivery-4", "{}").await;
+        assert_eq!(outcome.response_status, None);
+        assert!(outcome.error.unwrap().contains("private or reserved address"));
+    }
+
+    #[test]
+    fn test_public_ip_ranges() {
+        for ip in ["8.8.8.8", "93.184.215.14", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["0.0.0.0", "100.64.0.1", "172.16.0.1", "224.0.0.1", "255.255.255.255", "fc00::1", "fe80::1", "::"] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_not_followed() {
+        let (target, received) = spawn_mock_receiver(0);
+        let app = Router::new().route(
+            "/redirect",
+            post(move || async move { axum::response::Redirect::temporary(&target) }),
+        );
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+
+        let sender = WebhookSender::new(1, Duration::from_millis(1)).with_private_targets(true);
+        let outcome = sender.send(&format!("http://{}/redirect", addr), "secret", WebhookEvent::DocumentCreated, "delivery-5", "{}").await;
+
+        assert_eq!(outcome.response_status, Some(307));
+        assert_eq!(outcome.status(), WebhookDeliveryStatus::Failed);
+        assert!(received.lock().unwrap().is_empty());
+    }
+}
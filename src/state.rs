@@ -4,16 +4,23 @@ use crate::{
     config::Config,
     services::{
         auth::AuthService,
+        api_key::ApiKeyService,
         spaces::SpaceService,
         space_member::SpaceMemberService,
-        documents::DocumentService,
+        documents::{DocumentService, DocumentLimits},
+        document_permission::DocumentPermissionService,
         comments::CommentService,
         publication::PublicationService,
         search::SearchService,
         versions::VersionService,
         tags::TagService,
-        file_upload::FileUploadService,
+        templates::TemplateService,
+        file_upload::{FileUploadService, UploadPolicy},
+        stats::StatsService,
+        space_export::SpaceExportService,
+        webhook::WebhookService,
     },
+    utils::markdown::{MarkdownOptions, MarkdownProcessor},
 };
 
 #[derive(Clone)]
@@ -21,13 +28,162 @@ pub struct AppState {
     pub db: Arc<Database>,
     pub config: Config,
     pub auth_service: Arc<AuthService>,
+    pub api_key_service: Arc<ApiKeyService>,
     pub space_service: Arc<SpaceService>,
     pub space_member_service: Arc<SpaceMemberService>,
     pub file_upload_service: Arc<FileUploadService>,
     pub tag_service: Arc<TagService>,
+    pub template_service: Arc<TemplateService>,
     pub document_service: Arc<DocumentService>,
+    pub document_permission_service: Arc<DocumentPermissionService>,
     pub comment_service: Arc<CommentService>,
     pub publication_service: Arc<PublicationService>,
     pub search_service: Arc<SearchService>,
     pub version_service: Arc<VersionService>,
-}
\ No newline at end of file
+    #[cfg(feature = "pdf-export")]
+    pub pdf_export_service: Arc<crate::services::pdf_export::PdfExportService>,
+    pub stats_service: Arc<StatsService>,
+    pub space_export_service: Arc<SpaceExportService>,
+    pub webhook_service: Arc<WebhookService>,
+}
+
+impl AppState {
+    /// 按配置创建全部服务并完成相互之间的依赖注入
+    pub fn new(db: Arc<Database>, config: Config) -> Self {
+        // 创建认证服务
+        let api_key_service = Arc::new(ApiKeyService::new(db.clone()));
+        let auth_service = Arc::new(AuthService::new(config.clone()).with_api_key_service(api_key_service.clone()));
+
+        // 创建业务服务
+        let space_service = Arc::new(SpaceService::new(db.clone()));
+        let space_member_service = Arc::new(SpaceMemberService::new(db.clone(), config.clone()));
+        let document_permission_service = Arc::new(DocumentPermissionService::new(db.clone(), space_member_service.clone()));
+        let file_upload_service = Arc::new(
+            FileUploadService::new(db.clone(), auth_service.clone())
+                .with_upload_policy(UploadPolicy::from(&config.files))
+                .with_thumbnail_max_dimension(config.files.thumbnail_max_dimension)
+                .with_space_member_service(space_member_service.clone()),
+        );
+        let tag_service = Arc::new(TagService::new(db.clone(), auth_service.clone()));
+
+        let markdown_processor = Arc::new(
+            MarkdownProcessor::new(MarkdownOptions::from(&config.markdown))
+                .with_highlight_theme(&config.markdown.highlight_theme),
+        );
+        let webhook_service = Arc::new(
+            WebhookService::new(db.clone())
+                .with_private_targets(config.features.webhook_allow_private_targets),
+        );
+        let search_service = Arc::new(
+            SearchService::new(db.clone(), auth_service.clone())
+                .with_space_service(space_service.clone())
+                .with_space_member_service(space_member_service.clone())
+                .with_hybrid_vector_weight(config.features.hybrid_search_vector_weight),
+        );
+        let version_service = Arc::new(
+            VersionService::new(db.clone(), auth_service.clone())
+                .with_max_versions_per_document(config.features.max_versions_per_document as usize),
+        );
+        let document_service = Arc::new(DocumentService::new(
+            db.clone(),
+            auth_service.clone(),
+            markdown_processor.clone(),
+        ).with_search_service(search_service.clone())
+         .with_version_service(version_service.clone())
+         .with_file_upload_service(file_upload_service.clone())
+         .with_space_member_service(space_member_service.clone())
+         .with_document_permission_service(document_permission_service.clone())
+         .with_webhook_service(webhook_service.clone())
+         .with_document_limits(DocumentLimits::from(&config.features)));
+        let template_service = Arc::new(TemplateService::new(db.clone(), document_service.clone()));
+        let comment_service = Arc::new(
+            CommentService::new(db.clone(), auth_service.clone())
+                .with_max_depth(config.features.max_comment_depth as usize)
+                .with_space_member_service(space_member_service.clone()),
+        );
+        let publication_service = Arc::new(
+            PublicationService::new(db.clone())
+                .with_tree_cache_ttl(chrono::Duration::seconds(config.features.publication_cache_ttl_secs as i64))
+                .with_webhook_service(webhook_service.clone())
+                .with_access_token_secret(&config.auth.jwt_secret),
+        );
+        #[cfg(feature = "pdf-export")]
+        let pdf_export_service = Arc::new(crate::services::pdf_export::PdfExportService::new(markdown_processor.clone()));
+        let stats_service = Arc::new(StatsService::new(db.clone()));
+        let space_export_service = Arc::new(
+            SpaceExportService::new(db.clone()).with_search_service(search_service.clone()),
+        );
+
+        Self {
+            db,
+            config,
+            auth_service,
+            api_key_service,
+            space_service,
+            space_member_service,
+            file_upload_service,
+            tag_service,
+            template_service,
+            document_service,
+            document_permission_service,
+            comment_service,
+            publication_service,
+            search_service,
+            version_service,
+            #[cfg(feature = "pdf-export")]
+            pdf_export_service,
+            stats_service,
+            space_export_service,
+            webhook_service,
+        }
+    }
+}
+
+/// 路由测试使用的应用状态和请求工具，数据库需已导入 schemas/docs_schema.sql
+#[cfg(test)]
+pub mod test_support {
+    use super::*;
+    use crate::services::auth::Claims;
+    use axum::{
+        body::HttpBody,
+        http::StatusCode,
+        response::Response,
+        Extension, Router,
+    };
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    /// 连接测试数据库创建全部服务；认证使用独立模式，不依赖 Rainbow-Auth
+    pub async fn test_app_state() -> Arc<AppState> {
+        let db = crate::services::database::test_database().await;
+        let mut config = db.config.clone();
+        config.auth.integration_mode = false;
+        Arc::new(AppState::new(db, config))
+    }
+
+    /// 与 main 中一样挂载路由，并注入 User 提取器需要的 AuthService
+    pub fn test_app(prefix: &str, router: Router<Arc<AppState>>, state: &Arc<AppState>) -> Router {
+        Router::new()
+            .nest(prefix, router)
+            .with_state(state.clone())
+            .layer(Extension(state.auth_service.clone()))
+    }
+
+    /// 为指定用户签发一小时内有效的 JWT
+    pub fn bearer_token(state: &AppState, user_id: &str) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let claims = Claims { sub: user_id.to_string(), exp: now + 3600, iat: now, session_id: None };
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(state.config.auth.jwt_secret.as_ref()))
+            .expect("test token");
+        format!("Bearer {}", token)
+    }
+
+    pub async fn response_json(response: Response) -> (StatusCode, serde_json::Value) {
+        let status = response.status();
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+}
@@ -0,0 +1,137 @@
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::warn;
+
+use crate::{config::ServerConfig, services::publication::ACCESS_TOKEN_HEADER};
+
+/// 每个响应都带有的请求 ID 头，需要显式暴露给浏览器脚本
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 根据配置构建 CORS 中间件：
+/// - 配置了来源时只允许这些来源，并允许携带凭证；
+/// - 未配置来源时，仅开发模式允许任意来源（不带凭证），否则不允许跨域
+pub fn cors_layer(config: &ServerConfig) -> CorsLayer {
+    let origins: Vec<HeaderValue> = config
+        .cors_allowed_origins
+        .iter()
+        .filter_map(|origin| match HeaderValue::from_str(origin) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                warn!("Ignoring invalid CORS origin: {}", origin);
+                None
+            }
+        })
+        .collect();
+
+    if !origins.is_empty() {
+        return CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE, Method::OPTIONS])
+            .allow_headers([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                header::ACCEPT,
+                HeaderName::from_static(REQUEST_ID_HEADER),
+                // 受密码保护的发布通过该请求头携带访问令牌
+                HeaderName::from_bytes(ACCESS_TOKEN_HEADER.as_bytes()).expect("valid header name"),
+            ])
+            .expose_headers([HeaderName::from_static(REQUEST_ID_HEADER)])
+            .allow_credentials(true);
+    }
+
+    if config.dev_mode {
+        warn!("CORS_ALLOWED_ORIGINS is not set, allowing any origin because DEV_MODE is enabled");
+        return CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .expose_headers([HeaderName::from_static(REQUEST_ID_HEADER)]);
+    }
+
+    warn!("CORS_ALLOWED_ORIGINS is not set, cross-origin requests will be rejected");
+    CorsLayer::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn server_config(origins: &[&str], dev_mode: bool) -> ServerConfig {
+        ServerConfig {
+            host: "0.0.0.0".to_string(),
+            port: 3000,
+            app_url: "http://localhost:3000".to_string(),
+            cors_allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+            dev_mode,
+        }
+    }
+
+    async fn cors_header(config: &ServerConfig, origin: &str, name: header::HeaderName) -> Option<String> {
+        let app = Router::new().route("/", get(|| async { "ok" })).layer(cors_layer(config));
+        let response = app
+            .oneshot(Request::get("/").header(header::ORIGIN, origin).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        response
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    async fn allowed_origin(config: &ServerConfig, origin: &str) -> Option<String> {
+        cors_header(config, origin, header::ACCESS_CONTROL_ALLOW_ORIGIN).await
+    }
+
+    #[tokio::test]
+    async fn test_only_configured_origins_are_allowed() {
+        let config = server_config(&["https://docs.example.com"], false);
+
+        assert_eq!(
+            allowed_origin(&config, "https://docs.example.com").await.as_deref(),
+            Some("https://docs.example.com")
+        );
+        assert_eq!(allowed_origin(&config, "https://evil.example.com").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_any_origin_only_in_dev_mode() {
+        assert_eq!(allowed_origin(&server_config(&[], true), "http://localhost:5173").await.as_deref(), Some("*"));
+        assert_eq!(allowed_origin(&server_config(&[], false), "http://localhost:5173").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_exposed_to_browsers() {
+        let configured = server_config(&["https://docs.example.com"], false);
+        assert_eq!(
+            cors_header(&configured, "https://docs.example.com", header::ACCESS_CONTROL_EXPOSE_HEADERS).await.as_deref(),
+            Some("x-request-id")
+        );
+        assert_eq!(
+            cors_header(&server_config(&[], true), "http://localhost:5173", header::ACCESS_CONTROL_EXPOSE_HEADERS).await.as_deref(),
+            Some("x-request-id")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_publication_token_header_is_allowed() {
+        let config = server_config(&["https://docs.example.com"], false);
+        let app = Router::new().route("/", get(|| async { "ok" })).layer(cors_layer(&config));
+        let response = app
+            .oneshot(
+                Request::options("/")
+                    .header(header::ORIGIN, "https://docs.example.com")
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+                    .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-publication-token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let allowed = response.headers().get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap().to_str().unwrap();
+        assert!(allowed.split(',').any(|name| name.trim() == "x-publication-token"), "{}", allowed);
+    }
+}
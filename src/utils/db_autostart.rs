@@ -0,0 +1,166 @@
+use std::future::Future;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+use tokio::time::{sleep, Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::DatabaseConfig;
+
+/// 自动启动的 SurrealDB 进程ID保存位置，关闭服务时据此停止数据库
+pub const PID_FILE: &str = ".surreal_pid";
+
+const DATA_DIR: &str = "./data";
+
+/// 判断数据库地址是否指向本机，只有本机地址才允许自动启动
+pub fn is_local_address(url: &str) -> bool {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = without_scheme.split('/').next().unwrap_or("");
+
+    let host = if let Some(rest) = authority.strip_prefix('[') {
+        // IPv6 地址形如 [::1]:8000
+        rest.split(']').next().unwrap_or("")
+    } else {
+        authority.rsplit_once(':').map_or(authority, |(host, _)| host)
+    };
+
+    matches!(host, "localhost" | "0.0.0.0" | "::1") || host.starts_with("127.")
+}
+
+/// 在本机后台启动 SurrealDB，返回进程ID。
+/// 启动后数据库不一定立即可用，调用方需要配合 poll_until_ready 等待连接成功
+pub fn start_local_database(config: &DatabaseConfig) -> anyhow::Result<u32> {
+    if !is_local_address(&config.url) {
+        return Err(anyhow::anyhow!(
+            "DATABASE_URL {} is not a local address, refusing to auto-start SurrealDB",
+            config.url
+        ));
+    }
+
+    if !Path::new(DATA_DIR).exists() {
+        std::fs::create_dir_all(DATA_DIR)
+            .map_err(|e| anyhow::anyhow!("Failed to create data directory: {}", e))?;
+    }
+    let db_file = format!("{}/rainbow.db", DATA_DIR);
+    let bind = config.url.split_once("://").map_or(config.url.as_str(), |(_, rest)| rest);
+
+    info!(
+        "Executing: surreal start --auth --user {} --pass *** --bind {} file://{}",
+        config.user, bind, db_file
+    );
+
+    let child = Command::new("surreal")
+        .arg("start")
+        .arg("--auth")
+        .arg("--user").arg(&config.user)
+        .arg("--pass").arg(&config.pass)
+        .arg("--bind").arg(bind)
+        .arg(format!("file://{}", db_file))
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => anyhow::anyhow!(
+                "SurrealDB binary 'surreal' was not found in PATH. Install SurrealDB or set DATABASE_AUTOSTART=false"
+            ),
+            _ => anyhow::anyhow!("Failed to start SurrealDB: {}", e),
+        })?;
+
+    let pid = child.id();
+    std::fs::write(PID_FILE, pid.to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to save database PID: {}", e))?;
+
+    info!("SurrealDB process started (PID: {})", pid);
+    Ok(pid)
+}
+
+/// 停止由本服务自动启动的数据库。PID 文件中的进程与 pid 不一致时说明数据库已被他人接管，不做处理
+pub fn stop_local_database(pid: u32) -> anyhow::Result<()> {
+    let saved_pid = std::fs::read_to_string(PID_FILE)
+        .ok()
+        .and_then(|content| content.trim().parse::<u32>().ok());
+    if saved_pid != Some(pid) {
+        warn!("PID file does not match auto-started SurrealDB (PID: {}), leaving it running", pid);
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    let status = Command::new("kill").arg("-TERM").arg(pid.to_string()).status();
+    #[cfg(windows)]
+    let status = Command::new("taskkill").arg("/PID").arg(pid.to_string()).status();
+
+    let status = status.map_err(|e| anyhow::anyhow!("Failed to stop SurrealDB (PID: {}): {}", pid, e))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("Failed to stop SurrealDB (PID: {}): {}", pid, status));
+    }
+
+    let _ = std::fs::remove_file(PID_FILE);
+    info!("Stopped auto-started SurrealDB (PID: {})", pid);
+    Ok(())
+}
+
+/// 反复调用 attempt 直到成功或超过 timeout，每次失败后等待 poll_interval。
+/// 超时时返回最后一次的错误
+pub async fn poll_until_ready<T, E, F, Fut>(
+    timeout: Duration,
+    poll_interval: Duration,
+    mut attempt: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(e) if Instant::now() + poll_interval > deadline => return Err(e),
+            Err(_) => sleep(poll_interval).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_only_local_addresses_are_started() {
+        assert!(is_local_address("127.0.0.1:8686"));
+        assert!(is_local_address("http://localhost:8000"));
+        assert!(is_local_address("ws://[::1]:8000/rpc"));
+        assert!(is_local_address("0.0.0.0:8000"));
+
+        assert!(!is_local_address("db.internal.example.com:8000"));
+        assert!(!is_local_address("https://10.0.0.5:8000"));
+        assert!(!is_local_address("http://localhost.example.com:8000"));
+    }
+
+    #[tokio::test]
+    async fn test_polling_returns_once_ready() {
+        let attempts = AtomicU32::new(0);
+        let result: Result<u32, &str> = poll_until_ready(Duration::from_secs(2), Duration::from_millis(10), || async {
+            let n = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            if n >= 3 { Ok(n) } else { Err("not ready") }
+        })
+        .await;
+
+        assert_eq!(result, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn test_polling_gives_up_after_timeout() {
+        let started = Instant::now();
+        let attempts = AtomicU32::new(0);
+        let result: Result<(), String> = poll_until_ready(Duration::from_millis(100), Duration::from_millis(20), || async {
+            let n = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            Err(format!("attempt {} refused", n))
+        })
+        .await;
+
+        let elapsed = started.elapsed();
+        assert!(elapsed < Duration::from_secs(1));
+        assert!(attempts.load(Ordering::SeqCst) >= 2);
+        assert_eq!(result, Err(format!("attempt {} refused", attempts.load(Ordering::SeqCst))));
+    }
+}
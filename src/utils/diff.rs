@@ -0,0 +1,270 @@
+use serde::{Deserialize, Serialize};
+
+/// 差异片段类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffSpanKind {
+    Unchanged,
+    Added,
+    Removed,
+}
+
+/// 一段连续的相同类型的差异文本
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffSpan {
+    pub kind: DiffSpanKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOp {
+    Equal(usize),
+    Insert(usize),
+    Delete(usize),
+}
+
+/// 对两段文本进行词级/字符级差异比较（Myers 算法）
+///
+/// 英文等按单词切分，中日韩文字按单个字符切分，空白和标点单独成词，
+/// 这样修改一段正文时只会标记真正变化的部分。
+pub fn diff_text(old: &str, new: &str) -> Vec<DiffSpan> {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+    let ops = myers_diff(&old_tokens, &new_tokens);
+
+    let mut spans: Vec<DiffSpan> = Vec::new();
+    for op in ops {
+        let (kind, token) = match op {
+            DiffOp::Equal(i) => (DiffSpanKind::Unchanged, old_tokens[i]),
+            DiffOp::Delete(i) => (DiffSpanKind::Removed, old_tokens[i]),
+            DiffOp::Insert(j) => (DiffSpanKind::Added, new_tokens[j]),
+        };
+        match spans.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(token),
+            _ => spans.push(DiffSpan {
+                kind,
+                text: token.to_string(),
+            }),
+        }
+    }
+
+    spans
+}
+
+/// 逐项比较两个序列（如文档的标题大纲），每一项单独成为一个差异片段，不与相邻项合并
+pub fn diff_lines<S: AsRef<str>>(old: &[S], new: &[S]) -> Vec<DiffSpan> {
+    let old: Vec<&str> = old.iter().map(AsRef::as_ref).collect();
+    let new: Vec<&str> = new.iter().map(AsRef::as_ref).collect();
+
+    myers_diff(&old, &new)
+        .into_iter()
+        .map(|op| {
+            let (kind, line) = match op {
+                DiffOp::Equal(i) => (DiffSpanKind::Unchanged, old[i]),
+                DiffOp::Delete(i) => (DiffSpanKind::Removed, old[i]),
+                DiffOp::Insert(j) => (DiffSpanKind::Added, new[j]),
+            };
+            DiffSpan {
+                kind,
+                text: line.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// 相似度：未变化的字符数占前后两段文本字符总数的比例（0~1），两段都为空时为 1
+pub fn similarity(spans: &[DiffSpan]) -> f64 {
+    let count = |kind: DiffSpanKind| -> usize {
+        spans.iter().filter(|span| span.kind == kind).map(|span| span.text.chars().count()).sum()
+    };
+    let unchanged = count(DiffSpanKind::Unchanged);
+    let total = 2 * unchanged + count(DiffSpanKind::Added) + count(DiffSpanKind::Removed);
+
+    if total == 0 {
+        1.0
+    } else {
+        (2 * unchanged) as f64 / total as f64
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}'
+        | '\u{f900}'..='\u{faff}')
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum TokenClass {
+    Word,
+    Space,
+    Single,
+}
+
+fn classify(c: char) -> TokenClass {
+    if c.is_whitespace() {
+        TokenClass::Space
+    } else if c.is_alphanumeric() && !is_cjk(c) || c == '_' {
+        TokenClass::Word
+    } else {
+        TokenClass::Single
+    }
+}
+
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut current: Option<TokenClass> = None;
+
+    for (index, c) in text.char_indices() {
+        let class = classify(c);
+        match current {
+            Some(prev) if prev == class && class != TokenClass::Single => {}
+            Some(_) => {
+                tokens.push(&text[start..index]);
+                start = index;
+            }
+            None => {}
+        }
+        current = Some(class);
+    }
+
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+
+    tokens
+}
+
+fn myers_diff(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+
+    let mut v = vec![0isize; 2 * max + 3];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'outer: for d in 0..=max as isize {
+        trace.push(v.clone());
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]) {
+                v[(offset + k + 1) as usize]
+            } else {
+                v[(offset + k - 1) as usize] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                break 'outer;
+            }
+            k += 2;
+        }
+    }
+
+    // 回溯得到编辑路径
+    let mut ops = Vec::new();
+    let mut x = n;
+    let mut y = m;
+    for d in (0..trace.len() as isize).rev() {
+        let v = &trace[d as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[(offset + prev_k) as usize];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            ops.push(DiffOp::Equal((x - 1) as usize));
+            x -= 1;
+            y -= 1;
+        }
+
+        if d > 0 {
+            if x == prev_x {
+                ops.push(DiffOp::Insert((y - 1) as usize));
+            } else {
+                ops.push(DiffOp::Delete((x - 1) as usize));
+            }
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(spans: &[DiffSpan]) -> String {
+        spans
+            .iter()
+            .map(|span| match span.kind {
+                DiffSpanKind::Unchanged => span.text.clone(),
+                DiffSpanKind::Added => format!("{{+{}+}}", span.text),
+                DiffSpanKind::Removed => format!("[-{}-]", span.text),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_text_is_unchanged() {
+        let spans = diff_text("hello world", "hello world");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].kind, DiffSpanKind::Unchanged);
+    }
+
+    #[test]
+    fn test_word_level_changes() {
+        let spans = diff_text("The quick brown fox", "The slow brown fox jumps");
+        assert_eq!(render(&spans), "The [-quick-]{+slow+} brown fox{+ jumps+}");
+    }
+
+    #[test]
+    fn test_cjk_character_level_changes() {
+        let spans = diff_text("今天天气很好", "今天天气不好");
+        assert_eq!(render(&spans), "今天天气[-很-]{+不+}好");
+    }
+
+    #[test]
+    fn test_empty_inputs() {
+        assert!(diff_text("", "").is_empty());
+        assert_eq!(render(&diff_text("", "new")), "{+new+}");
+        assert_eq!(render(&diff_text("old", "")), "[-old-]");
+    }
+
+    #[test]
+    fn test_reconstructs_both_sides() {
+        let old = "# Title\n\nSome text here.\n";
+        let new = "# New Title\n\nSome other text.\n";
+        let spans = diff_text(old, new);
+
+        let rebuilt_old: String = spans
+            .iter()
+            .filter(|s| s.kind != DiffSpanKind::Added)
+            .map(|s| s.text.as_str())
+            .collect();
+        let rebuilt_new: String = spans
+            .iter()
+            .filter(|s| s.kind != DiffSpanKind::Removed)
+            .map(|s| s.text.as_str())
+            .collect();
+
+        assert_eq!(rebuilt_old, old);
+        assert_eq!(rebuilt_new, new);
+    }
+}
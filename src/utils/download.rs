@@ -0,0 +1,202 @@
+use std::io::{ErrorKind, SeekFrom};
+use std::path::Path;
+
+use axum::{
+    body::{boxed, Empty, StreamBody},
+    http::{header, StatusCode},
+    response::Response,
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use tracing::error;
+
+use crate::error::AppError;
+
+/// 闭区间 [start, end] 的字节范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// 没有 Range 头，或者是不支持的形式（如多个区间），返回完整文件
+    Full,
+    Partial(ByteRange),
+    /// 区间完全落在文件之外，返回 416
+    Unsatisfiable,
+}
+
+/// 解析 Range 请求头，支持 `bytes=0-99`、`bytes=100-` 和 `bytes=-100` 三种单区间形式
+pub fn parse_range(value: Option<&str>, file_size: u64) -> RangeRequest {
+    let Some(spec) = value.and_then(|v| v.trim().strip_prefix("bytes=")) else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeRequest::Full;
+    };
+
+    let range = match (start.trim(), end.trim()) {
+        ("", "") => return RangeRequest::Full,
+        // 最后 N 个字节
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(n) if file_size > 0 => ByteRange { start: file_size.saturating_sub(n), end: file_size - 1 },
+            Ok(_) => return RangeRequest::Unsatisfiable,
+            Err(_) => return RangeRequest::Full,
+        },
+        (start, end) => {
+            let Ok(start) = start.parse::<u64>() else {
+                return RangeRequest::Full;
+            };
+            let end = if end.is_empty() {
+                u64::MAX
+            } else {
+                match end.parse::<u64>() {
+                    Ok(end) if end >= start => end,
+                    _ => return RangeRequest::Full,
+                }
+            };
+            if start >= file_size {
+                return RangeRequest::Unsatisfiable;
+            }
+            ByteRange { start, end: end.min(file_size - 1) }
+        }
+    };
+
+    RangeRequest::Partial(range)
+}
+
+/// 以流的方式返回磁盘上的文件，不把整个文件读入内存。
+/// 支持单区间的 Range 请求，用于断点续传和音视频拖动
+pub async fn stream_file(
+    path: &Path,
+    mime_type: &str,
+    download_name: &str,
+    range: Option<&str>,
+) -> Result<Response, AppError> {
+    let mut file = tokio::fs::File::open(path).await.map_err(|e| match e.kind() {
+        ErrorKind::NotFound => AppError::not_found("File content not found".to_string()),
+        _ => {
+            error!("Failed to open file {}: {}", path.display(), e);
+            AppError::internal_server_error("Failed to read file".to_string())
+        }
+    })?;
+    let file_size = file
+        .metadata()
+        .await
+        .map_err(|e| {
+            error!("Failed to read file metadata {}: {}", path.display(), e);
+            AppError::internal_server_error("Failed to read file".to_string())
+        })?
+        .len();
+
+    let builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime_type)
+        .header(header::CONTENT_DISPOSITION, content_disposition(download_name))
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    let response = match parse_range(range, file_size) {
+        RangeRequest::Full => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, file_size)
+            .body(boxed(StreamBody::new(ReaderStream::new(file)))),
+        RangeRequest::Partial(range) => {
+            file.seek(SeekFrom::Start(range.start)).await.map_err(|e| {
+                error!("Failed to seek file {}: {}", path.display(), e);
+                AppError::internal_server_error("Failed to read file".to_string())
+            })?;
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_LENGTH, range.length())
+                .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end, file_size))
+                .body(boxed(StreamBody::new(ReaderStream::new(file.take(range.length())))))
+        }
+        RangeRequest::Unsatisfiable => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", file_size))
+            .body(boxed(Empty::new())),
+    };
+
+    response.map_err(|e| {
+        error!("Failed to build download response: {}", e);
+        AppError::internal_server_error("Failed to read file".to_string())
+    })
+}
+
+/// 同时给出 ASCII 文件名和 RFC 5987 编码的原始文件名，避免中文文件名乱码
+fn content_disposition(download_name: &str) -> String {
+    let fallback: String = download_name
+        .chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback,
+        urlencoding::encode(download_name)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::HttpBody;
+
+    async fn body_bytes(response: Response) -> Vec<u8> {
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_range_forms() {
+        let partial = |start, end| RangeRequest::Partial(ByteRange { start, end });
+
+        assert_eq!(parse_range(None, 100), RangeRequest::Full);
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), partial(0, 9));
+        assert_eq!(parse_range(Some("bytes=90-"), 100), partial(90, 99));
+        assert_eq!(parse_range(Some("bytes=90-500"), 100), partial(90, 99));
+        assert_eq!(parse_range(Some("bytes=-10"), 100), partial(90, 99));
+        assert_eq!(parse_range(Some("bytes=-500"), 100), partial(0, 99));
+        assert_eq!(parse_range(Some("bytes=100-"), 100), RangeRequest::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), RangeRequest::Full);
+        assert_eq!(parse_range(Some("bytes=9-3"), 100), RangeRequest::Full);
+        assert_eq!(parse_range(Some("items=0-9"), 100), RangeRequest::Full);
+    }
+
+    #[tokio::test]
+    async fn test_ranged_download_returns_partial_content() {
+        let path = std::env::temp_dir().join(format!("rainbow-docs-download-{}", uuid::Uuid::new_v4()));
+        tokio::fs::write(&path, b"0123456789").await.unwrap();
+
+        let response = stream_file(&path, "text/plain", "数字.txt", Some("bytes=2-5")).await.unwrap();
+        let full = stream_file(&path, "text/plain", "数字.txt", None).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 2-5/10");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "4");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"__.txt\"; filename*=UTF-8''%E6%95%B0%E5%AD%97.txt"
+        );
+        assert_eq!(body_bytes(response).await, b"2345");
+
+        assert_eq!(full.status(), StatusCode::OK);
+        assert_eq!(full.headers()[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(body_bytes(full).await, b"0123456789");
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+
+use regex::Regex;
+
+use crate::error::AppError;
+use crate::models::document::DocumentTreeNode;
+
+lazy_static::lazy_static! {
+    // 匹配指向已上传文件的链接，例如 /api/docs/files/{id}/download
+    static ref UPLOADED_FILE_REGEX: Regex =
+        Regex::new(r"\((/api/(?:docs/)?files/([A-Za-z0-9_\-:]+)/download)\)").unwrap();
+}
+
+/// 导出包中的单个文档条目
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportEntry {
+    pub document_id: String,
+    pub title: String,
+    pub path: String,
+    pub depth: usize,
+}
+
+/// 按文档树顺序生成导出路径：有子文档的节点会生成同名目录存放子文档
+pub fn plan_export_paths(tree: &[DocumentTreeNode]) -> Vec<ExportEntry> {
+    fn walk(nodes: &[DocumentTreeNode], prefix: &str, depth: usize, entries: &mut Vec<ExportEntry>) {
+        for node in nodes {
+            let path = format!("{}{}.md", prefix, node.slug);
+            entries.push(ExportEntry {
+                document_id: node.id.clone(),
+                title: node.title.clone(),
+                path,
+                depth,
+            });
+            if !node.children.is_empty() {
+                let child_prefix = format!("{}{}/", prefix, node.slug);
+                walk(&node.children, &child_prefix, depth + 1, entries);
+            }
+        }
+    }
+
+    let mut entries = Vec::new();
+    walk(tree, "", 0, &mut entries);
+    entries
+}
+
+/// 生成 index.md 目录，链接均为相对路径
+pub fn build_index(space_name: &str, entries: &[ExportEntry]) -> String {
+    let mut index = format!("# {}\n\n", space_name);
+    for entry in entries {
+        index.push_str(&format!(
+            "{}- [{}]({})\n",
+            "  ".repeat(entry.depth),
+            entry.title,
+            entry.path
+        ));
+    }
+    index
+}
+
+/// 收集内容中引用的已上传文件ID
+pub fn collect_uploaded_file_ids(content: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    for caps in UPLOADED_FILE_REGEX.captures_iter(content) {
+        let id = caps[2].to_string();
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+/// 将已上传文件的链接改写为导出包内的资源路径（相对于文档所在目录）
+pub fn rewrite_uploaded_file_links(content: &str, depth: usize, assets: &HashMap<String, String>) -> String {
+    let relative_prefix = "../".repeat(depth);
+    UPLOADED_FILE_REGEX
+        .replace_all(content, |caps: &regex::Captures| match assets.get(&caps[2]) {
+            Some(asset_path) => format!("({}{})", relative_prefix, asset_path),
+            None => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// 生成资源在导出包中的路径
+pub fn asset_path(file_id: &str, original_name: &str) -> String {
+    let safe_name: String = original_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("assets/{}-{}", file_id.replace(':', "_"), safe_name)
+}
+
+/// 将文件列表打包为 ZIP
+pub fn write_zip(files: Vec<(String, Vec<u8>)>) -> Result<Vec<u8>, AppError> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    for (path, content) in files {
+        writer
+            .start_file(path, options)
+            .map_err(|e| AppError::internal_server_error(format!("Failed to write archive: {}", e)))?;
+        writer
+            .write_all(&content)
+            .map_err(|e| AppError::internal_server_error(format!("Failed to write archive: {}", e)))?;
+    }
+
+    let cursor = writer
+        .finish()
+        .map_err(|e| AppError::internal_server_error(format!("Failed to finish archive: {}", e)))?;
+    Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, slug: &str, children: Vec<DocumentTreeNode>) -> DocumentTreeNode {
+        DocumentTreeNode {
+            id: id.to_string(),
+            title: slug.to_uppercase(),
+            slug: slug.to_string(),
+            is_public: false,
+            order_index: 0,
+            children,
+        }
+    }
+
+    #[test]
+    fn test_plan_export_paths_mirrors_tree() {
+        let tree = vec![
+            node("1", "guide", vec![node("2", "intro", vec![])]),
+            node("3", "faq", vec![]),
+        ];
+
+        let entries = plan_export_paths(&tree);
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["guide.md", "guide/intro.md", "faq.md"]);
+        assert_eq!(entries[1].depth, 1);
+
+        let index = build_index("Space", &entries);
+        assert!(index.contains("  - [INTRO](guide/intro.md)"));
+    }
+
+    #[test]
+    fn test_rewrite_uploaded_file_links() {
+        let content = "![logo](/api/docs/files/abc123/download) and [x](https://example.com)";
+        assert_eq!(collect_uploaded_file_ids(content), vec!["abc123".to_string()]);
+
+        let mut assets = HashMap::new();
+        assets.insert("abc123".to_string(), asset_path("abc123", "logo.png"));
+        let rewritten = rewrite_uploaded_file_links(content, 1, &assets);
+        assert_eq!(rewritten, "![logo](../assets/abc123-logo.png) and [x](https://example.com)");
+    }
+
+    #[test]
+    fn test_empty_archive_is_valid_zip() {
+        let archive = write_zip(vec![("index.md".to_string(), b"# Empty\n".to_vec())]).unwrap();
+        // ZIP 文件以 PK 签名开头
+        assert_eq!(&archive[..2], b"PK");
+    }
+}
@@ -0,0 +1,112 @@
+// 生成 RSS 2.0 和 Atom 1.0 订阅源
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    #[default]
+    Rss,
+    Atom,
+}
+
+impl FeedFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+        }
+    }
+}
+
+/// 订阅源本身的信息
+pub struct FeedChannel<'a> {
+    pub title: &'a str,
+    pub description: Option<&'a str>,
+    pub link: &'a str,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub struct FeedEntry {
+    pub title: String,
+    /// 文档的公开链接，同时用作条目的唯一标识
+    pub link: String,
+    pub summary: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 按给定顺序输出条目，调用方负责排序
+pub fn render(format: FeedFormat, channel: &FeedChannel, entries: &[FeedEntry]) -> String {
+    match format {
+        FeedFormat::Rss => render_rss(channel, entries),
+        FeedFormat::Atom => render_atom(channel, entries),
+    }
+}
+
+fn render_rss(channel: &FeedChannel, entries: &[FeedEntry]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">\n<channel>\n");
+    let _ = writeln!(xml, "<title>{}</title>", escape(channel.title));
+    let _ = writeln!(xml, "<link>{}</link>", escape(channel.link));
+    // RSS 要求 channel 必须有 description，没有时使用标题
+    let _ = writeln!(xml, "<description>{}</description>", escape(channel.description.unwrap_or(channel.title)));
+    let _ = writeln!(xml, "<lastBuildDate>{}</lastBuildDate>", channel.updated_at.to_rfc2822());
+
+    for entry in entries {
+        xml.push_str("<item>\n");
+        let _ = writeln!(xml, "<title>{}</title>", escape(&entry.title));
+        let _ = writeln!(xml, "<link>{}</link>", escape(&entry.link));
+        let _ = writeln!(xml, "<guid isPermaLink=\"true\">{}</guid>", escape(&entry.link));
+        if let Some(summary) = &entry.summary {
+            let _ = writeln!(xml, "<description>{}</description>", escape(summary));
+        }
+        let _ = writeln!(xml, "<pubDate>{}</pubDate>", entry.updated_at.to_rfc2822());
+        xml.push_str("</item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+fn render_atom(channel: &FeedChannel, entries: &[FeedEntry]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    let _ = writeln!(xml, "<title>{}</title>", escape(channel.title));
+    if let Some(description) = channel.description {
+        let _ = writeln!(xml, "<subtitle>{}</subtitle>", escape(description));
+    }
+    let _ = writeln!(xml, "<link href=\"{}\"/>", escape(channel.link));
+    let _ = writeln!(xml, "<id>{}</id>", escape(channel.link));
+    let _ = writeln!(xml, "<updated>{}</updated>", channel.updated_at.to_rfc3339());
+
+    for entry in entries {
+        xml.push_str("<entry>\n");
+        let _ = writeln!(xml, "<title>{}</title>", escape(&entry.title));
+        let _ = writeln!(xml, "<link href=\"{}\"/>", escape(&entry.link));
+        let _ = writeln!(xml, "<id>{}</id>", escape(&entry.link));
+        let _ = writeln!(xml, "<updated>{}</updated>", entry.updated_at.to_rfc3339());
+        if let Some(summary) = &entry.summary {
+            let _ = writeln!(xml, "<summary>{}</summary>", escape(summary));
+        }
+        xml.push_str("</entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// 转义 XML 文本和属性值中的特殊字符
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,119 @@
+use std::time::{Duration, SystemTime};
+
+use axum::{
+    headers::{CacheControl, ETag, HeaderMapExt, IfModifiedSince, IfNoneMatch, LastModified},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+use crate::error::AppError;
+
+/// 由版本号和响应内容计算强 ETag，重新发布后版本号递增，ETag 一定会变化
+pub fn content_etag(version: u32, body: &[u8]) -> ETag {
+    let mut hasher = Sha256::new();
+    hasher.update(version.to_be_bytes());
+    hasher.update(body);
+    let digest = hasher.finalize();
+    let hex: String = digest[..16].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("\"{}\"", hex).parse().expect("hex digest is a valid entity tag")
+}
+
+/// 客户端缓存是否仍然有效。按 RFC 9110，带 If-None-Match 时忽略 If-Modified-Since
+pub fn is_not_modified(headers: &HeaderMap, etag: &ETag, last_modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = headers.typed_get::<IfNoneMatch>() {
+        return !if_none_match.precondition_passes(etag);
+    }
+    match (headers.typed_get::<IfModifiedSince>(), last_modified) {
+        (Some(since), Some(modified)) => !since.is_modified(modified),
+        _ => false,
+    }
+}
+
+/// 返回带 ETag 和 Last-Modified 的 JSON 响应，条件请求命中时返回不带正文的 304。
+/// 使用 `no-cache` 让缓存每次都重新验证，重新发布后能立即看到新内容
+pub fn conditional_json<T: Serialize>(
+    request_headers: &HeaderMap,
+    version: u32,
+    last_modified: Option<DateTime<Utc>>,
+    body: &T,
+) -> Result<Response, AppError> {
+    let body = serde_json::to_vec(body).map_err(|e| {
+        error!("Failed to serialize response body: {}", e);
+        AppError::internal_server_error("Failed to serialize response".to_string())
+    })?;
+    let etag = content_etag(version, &body);
+    let last_modified = last_modified
+        .and_then(|time| u64::try_from(time.timestamp()).ok())
+        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+
+    let mut response = if is_not_modified(request_headers, &etag, last_modified) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        ([(header::CONTENT_TYPE, "application/json")], body).into_response()
+    };
+
+    let headers = response.headers_mut();
+    headers.typed_insert(etag);
+    if let Some(last_modified) = last_modified {
+        headers.typed_insert(LastModified::from(last_modified));
+    }
+    headers.typed_insert(CacheControl::new().with_public().with_no_cache());
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Path, http::Request, routing::get, Router};
+    use chrono::TimeZone;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new().route(
+            "/:version",
+            get(|Path(version): Path<u32>, headers: HeaderMap| async move {
+                let published_at = Utc.with_ymd_and_hms(2024, 3, 1, 8, 30, 0).unwrap();
+                conditional_json(&headers, version, Some(published_at), &json!({ "title": "Guide" }))
+            }),
+        )
+    }
+
+    async fn send(uri: &str, header: Option<(header::HeaderName, &str)>) -> Response {
+        let mut request = Request::get(uri);
+        if let Some((name, value)) = header {
+            request = request.header(name, value);
+        }
+        app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_second_request_with_etag_is_not_modified() {
+        let first = send("/1", None).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()[header::LAST_MODIFIED], "Fri, 01 Mar 2024 08:30:00 GMT");
+        let etag = first.headers()[header::ETAG].to_str().unwrap().to_string();
+
+        let second = send("/1", Some((header::IF_NONE_MATCH, &etag))).await;
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(second.headers()[header::ETAG], etag.as_str());
+
+        // 重新发布后版本号变化，旧的 ETag 不再命中
+        let republished = send("/2", Some((header::IF_NONE_MATCH, &etag))).await;
+        assert_eq!(republished.status(), StatusCode::OK);
+        assert_ne!(republished.headers()[header::ETAG], etag.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_if_modified_since() {
+        let unchanged = send("/1", Some((header::IF_MODIFIED_SINCE, "Fri, 01 Mar 2024 08:30:00 GMT"))).await;
+        assert_eq!(unchanged.status(), StatusCode::NOT_MODIFIED);
+
+        let stale = send("/1", Some((header::IF_MODIFIED_SINCE, "Thu, 29 Feb 2024 00:00:00 GMT"))).await;
+        assert_eq!(stale.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,214 @@
+// 按 Accept-Language 本地化错误响应，目前支持简体中文和英文
+
+use axum::{
+    http::{header, HeaderMap, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::error::{ErrorCode, ErrorDetails};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    /// 服务端的错误信息本身就是英文，未指定语言时保持原样
+    #[default]
+    En,
+    ZhCn,
+}
+
+impl Locale {
+    /// 按 q 值从高到低取第一个支持的语言，zh、zh-CN、zh-Hans 等都视为简体中文
+    pub fn from_accept_language(value: &str) -> Self {
+        let mut ranges: Vec<(&str, f32)> = value
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // 稳定排序，q 值相同时保持客户端给出的顺序
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        ranges
+            .into_iter()
+            .find_map(|(tag, _)| {
+                let primary = tag.split(['-', '_']).next().unwrap_or(tag).to_ascii_lowercase();
+                match primary.as_str() {
+                    "zh" => Some(Locale::ZhCn),
+                    "en" => Some(Locale::En),
+                    _ => None,
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(Self::from_accept_language)
+            .unwrap_or_default()
+    }
+}
+
+/// 常见错误信息的中文翻译，按英文原文精确匹配
+const ZH_CN_MESSAGES: &[(&str, &str)] = &[
+    ("Access denied to this space", "无权访问该空间"),
+    ("Access denied to private space", "无权访问私有空间"),
+    ("Access denied to this publication", "无权访问该发布"),
+    ("Document not found", "文档不存在"),
+    ("Parent document not found", "父文档不存在"),
+    ("Space not found", "空间不存在"),
+    ("Publication not found", "发布不存在"),
+    ("Publication has expired", "发布已过期"),
+    ("Shared document not found", "分享的文档不存在"),
+    ("Version not found", "版本不存在"),
+    ("Comment not found", "评论不存在"),
+    ("Parent comment not found", "父评论不存在"),
+    ("Tag not found", "标签不存在"),
+    ("Template not found", "模板不存在"),
+    ("Member not found", "成员不存在"),
+    ("Notification not found", "通知不存在"),
+    ("Webhook not found", "Webhook 不存在"),
+    ("API key not found", "API 密钥不存在"),
+    ("File not found", "文件不存在"),
+    ("Attachment not found", "附件不存在"),
+    ("Invitation not found or expired", "邀请不存在或已过期"),
+    ("Document slug already exists in this space", "该空间中已存在相同 slug 的文档"),
+    ("Tag name already exists in this space", "该空间中已存在同名标签"),
+    ("User is already a member of this space", "该用户已是空间成员"),
+    ("Invitation has been used up", "邀请的使用次数已用完"),
+    ("Cannot delete space with existing documents", "空间中还有文档，无法删除"),
+    ("Space is archived", "空间已归档"),
+    ("Invalid publication access token", "发布访问令牌无效"),
+    ("Incorrect publication password", "发布访问密码错误"),
+    ("Invalid API key", "API 密钥无效"),
+    ("API key has been revoked", "API 密钥已被吊销"),
+    ("Authorization header missing", "缺少 Authorization 请求头"),
+    ("Invalid authorization header format", "Authorization 请求头格式错误"),
+    ("Invalid token", "令牌无效"),
+    ("Database error occurred", "数据库错误"),
+    ("Internal server error", "服务器内部错误"),
+    ("Invalid JSON", "JSON 格式错误"),
+    ("External service error", "外部服务错误"),
+    ("File system error", "文件系统错误"),
+    ("Validation failed", "请求参数校验失败"),
+    ("Configuration error", "服务配置错误"),
+];
+
+/// 没有精确翻译时按错误码使用的通用中文信息
+fn zh_cn_fallback(code: ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::DatabaseError => "数据库错误",
+        ErrorCode::Unauthenticated => "身份认证失败",
+        ErrorCode::InvalidToken => "令牌无效",
+        ErrorCode::Forbidden => "没有执行该操作的权限",
+        ErrorCode::ValidationFailed => "请求参数无效",
+        ErrorCode::InvalidJson => "JSON 格式错误",
+        ErrorCode::NotFound => "请求的资源不存在",
+        ErrorCode::Conflict => "与现有数据冲突",
+        ErrorCode::StaleWrite => "数据已被他人修改，请合并后重试",
+        ErrorCode::InternalError => "服务器内部错误",
+        ErrorCode::ConfigurationError => "服务配置错误",
+        ErrorCode::ExternalServiceError => "外部服务错误",
+        ErrorCode::TooManyRequests => "请求过于频繁，请稍后再试",
+    }
+}
+
+/// 把英文错误信息翻译为指定语言
+pub fn localize(code: ErrorCode, message: &str, locale: Locale) -> String {
+    match locale {
+        Locale::En => message.to_string(),
+        Locale::ZhCn => {
+            if let Some((_, translated)) = ZH_CN_MESSAGES.iter().find(|(en, _)| *en == message) {
+                return translated.to_string();
+            }
+            // 权限检查统一使用 "Permission denied: <权限> required"
+            if let Some(permission) = message
+                .strip_prefix("Permission denied: ")
+                .and_then(|rest| rest.strip_suffix(" required"))
+            {
+                return format!("权限不足：需要 {} 权限", permission);
+            }
+            zh_cn_fallback(code).to_string()
+        }
+    }
+}
+
+/// 错误响应按请求的 Accept-Language 改写 message，错误码和状态码不变
+pub async fn localize_errors_middleware<B>(request: Request<B>, next: Next<B>) -> Response {
+    let locale = Locale::from_headers(request.headers());
+    let response = next.run(request).await;
+    if locale == Locale::En {
+        return response;
+    }
+    let Some(details) = response.extensions().get::<ErrorDetails>().cloned() else {
+        return response;
+    };
+
+    let message = localize(details.code, &details.message, locale);
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    (parts, Json(details.body(&message))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AppError;
+    use axum::{body::{Body, HttpBody}, http::StatusCode, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/doc", get(|| async { Err::<(), _>(AppError::NotFound("Document not found".to_string())) }))
+            .layer(middleware::from_fn(localize_errors_middleware))
+    }
+
+    async fn error_body(accept_language: Option<&str>) -> serde_json::Value {
+        let mut request = Request::get("/doc");
+        if let Some(value) = accept_language {
+            request = request.header(header::ACCEPT_LANGUAGE, value);
+        }
+        let response = app().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk.unwrap());
+        }
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_accept_language_negotiation() {
+        assert_eq!(Locale::from_accept_language("zh-CN,zh;q=0.9,en;q=0.8"), Locale::ZhCn);
+        assert_eq!(Locale::from_accept_language("en-US,zh-CN;q=0.5"), Locale::En);
+        assert_eq!(Locale::from_accept_language("fr-FR, zh-Hans;q=0.7, en;q=0.3"), Locale::ZhCn);
+        assert_eq!(Locale::from_accept_language("de, *;q=0.5"), Locale::En);
+        assert_eq!(Locale::from_accept_language("zh;q=0"), Locale::En);
+
+        assert_eq!(
+            localize(ErrorCode::Forbidden, "Permission denied: docs.write required", Locale::ZhCn),
+            "权限不足：需要 docs.write 权限"
+        );
+        assert_eq!(localize(ErrorCode::NotFound, "Space 'handbook' not found", Locale::ZhCn), "请求的资源不存在");
+    }
+
+    #[tokio::test]
+    async fn test_same_error_is_localized_per_request() {
+        let zh = error_body(Some("zh-CN")).await;
+        assert_eq!(zh["code"], "NOT_FOUND");
+        assert_eq!(zh["message"], "文档不存在");
+        assert_eq!(zh["error"], "文档不存在");
+
+        let en = error_body(None).await;
+        assert_eq!(en["code"], "NOT_FOUND");
+        assert_eq!(en["message"], "Document not found");
+    }
+}
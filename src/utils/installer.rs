@@ -232,6 +232,7 @@ ENABLE_PDF_EXPORT=false
 ENABLE_NOTIFICATIONS=true
 ENABLE_COMMENTS=true
 ENABLE_VERSIONING=true
+MAX_VERSIONS_PER_DOCUMENT=100
 "#,
                 config.database_url,
                 config.database_username,
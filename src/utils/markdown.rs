@@ -1,10 +1,25 @@
+use crate::config::MarkdownConfig;
 use crate::error::{AppError, Result};
 use pulldown_cmark::{Parser, Options, html};
-use syntect::html::{ClassedHTMLGenerator, ClassStyle};
+use syntect::html::highlighted_html_for_string;
 use syntect::parsing::SyntaxSet;
 use syntect::highlighting::{ThemeSet, Theme};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use serde::{Serialize, Deserialize};
+use tracing::warn;
+
+/// 默认的代码高亮主题
+pub const DEFAULT_HIGHLIGHT_THEME: &str = "base16-ocean.dark";
+
+// 语法定义和主题加载开销较大，进程内只加载一次
+lazy_static::lazy_static! {
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEME_SET: ThemeSet = ThemeSet::load_defaults();
+    // 代码高亮生成的内联样式只包含颜色和字形声明
+    static ref HIGHLIGHT_STYLE_REGEX: regex::Regex = regex::Regex::new(
+        r"^(?:(?:background-)?color:#[0-9a-fA-F]{3,8}|font-weight:bold|font-style:italic|text-decoration:underline)$"
+    ).unwrap();
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProcessedContent {
@@ -22,31 +37,147 @@ pub struct TocEntry {
     pub id: String,
 }
 
+/// 嵌套目录节点，anchor 与渲染后 HTML 中标题的 id 一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TocNode {
+    pub level: u8,
+    pub title: String,
+    pub anchor: String,
+    pub children: Vec<TocNode>,
+}
+
+/// Markdown 中的一个链接（不含图片），line 从 1 开始
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarkdownLink {
+    pub text: String,
+    pub url: String,
+    pub line: usize,
+}
+
+/// 启用的 Markdown 扩展语法，默认开启表格、脚注、删除线、任务列表和智能标点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkdownOptions {
+    pub tables: bool,
+    pub footnotes: bool,
+    pub strikethrough: bool,
+    pub tasklists: bool,
+    pub smart_punctuation: bool,
+    /// 摘要最大字符数
+    pub excerpt_length: usize,
+    pub excerpt_strategy: ExcerptStrategy,
+    /// 英文等以空格分词文字的阅读速度
+    pub words_per_minute: u32,
+    /// 中日韩文字的阅读速度（字/分钟）
+    pub cjk_chars_per_minute: u32,
+}
+
+/// 摘要取材方式，结果都会去掉 Markdown 标记并按字符数截断
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExcerptStrategy {
+    /// 第一个正文段落（跳过标题、代码块和纯图片段落）
+    FirstParagraph,
+    /// 全文纯文本的前 N 个字符，包括标题
+    FirstChars,
+    /// 去掉标题和代码块后的正文
+    #[default]
+    SkipHeadings,
+}
+
+impl std::str::FromStr for ExcerptStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "first_paragraph" => Ok(Self::FirstParagraph),
+            "first_chars" => Ok(Self::FirstChars),
+            "skip_headings" => Ok(Self::SkipHeadings),
+            other => Err(format!("Unknown excerpt strategy: {}", other)),
+        }
+    }
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        Self {
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            tasklists: true,
+            smart_punctuation: true,
+            excerpt_length: 200,
+            excerpt_strategy: ExcerptStrategy::default(),
+            words_per_minute: 200,
+            cjk_chars_per_minute: 400,
+        }
+    }
+}
+
+impl From<&MarkdownConfig> for MarkdownOptions {
+    fn from(config: &MarkdownConfig) -> Self {
+        Self {
+            tables: config.enable_tables,
+            footnotes: config.enable_footnotes,
+            strikethrough: config.enable_strikethrough,
+            tasklists: config.enable_tasklists,
+            smart_punctuation: config.enable_smart_punctuation,
+            excerpt_length: config.excerpt_length,
+            excerpt_strategy: config.excerpt_strategy.parse().unwrap_or_else(|e| {
+                warn!("{}, using skip_headings", e);
+                ExcerptStrategy::default()
+            }),
+            words_per_minute: config.words_per_minute,
+            cjk_chars_per_minute: config.cjk_chars_per_minute,
+        }
+    }
+}
+
+impl MarkdownOptions {
+    fn to_parser_options(self) -> Options {
+        let mut options = Options::empty();
+        options.set(Options::ENABLE_TABLES, self.tables);
+        options.set(Options::ENABLE_FOOTNOTES, self.footnotes);
+        options.set(Options::ENABLE_STRIKETHROUGH, self.strikethrough);
+        options.set(Options::ENABLE_TASKLISTS, self.tasklists);
+        options.set(Options::ENABLE_SMART_PUNCTUATION, self.smart_punctuation);
+        options
+    }
+}
+
 pub struct MarkdownProcessor {
-    syntax_set: SyntaxSet,
-    theme_set: ThemeSet,
-    default_theme: Theme,
+    options: MarkdownOptions,
+    theme: Theme,
 }
 
 impl MarkdownProcessor {
-    pub fn new() -> Self {
-        let syntax_set = SyntaxSet::load_defaults_newlines();
-        let theme_set = ThemeSet::load_defaults();
-        let default_theme = theme_set.themes["base16-ocean.dark"].clone();
-
+    pub fn new(options: MarkdownOptions) -> Self {
         Self {
-            syntax_set,
-            theme_set,
-            default_theme,
+            options,
+            theme: THEME_SET.themes[DEFAULT_HIGHLIGHT_THEME].clone(),
         }
     }
 
+    /// 设置代码高亮主题，未知主题名保留默认主题
+    pub fn with_highlight_theme(mut self, name: &str) -> Self {
+        match THEME_SET.themes.get(name) {
+            Some(theme) => self.theme = theme.clone(),
+            None => warn!("Unknown highlight theme '{}', using {}", name, DEFAULT_HIGHLIGHT_THEME),
+        }
+        self
+    }
+
     /// 完整处理Markdown内容
     pub async fn process(&self, markdown: &str) -> Result<ProcessedContent> {
-        let html = self.render(markdown)?;
-        let word_count = self.count_words(markdown);
-        let reading_time = self.estimate_reading_time(markdown);
-        let excerpt = self.extract_excerpt(markdown, 200);
+        self.process_with_allowed_tags(markdown, &[]).await
+    }
+
+    /// 完整处理Markdown内容，extra_tags 为空间额外允许的 HTML 标签
+    pub async fn process_with_allowed_tags(&self, markdown: &str, extra_tags: &[String]) -> Result<ProcessedContent> {
+        let html = self.render_with_allowed_tags(markdown, extra_tags)?;
+        let stats = self.reading_stats(markdown);
+        let word_count = stats.word_count();
+        let reading_time = stats.reading_time(&self.options);
+        let excerpt = self.extract_excerpt(markdown, self.options.excerpt_length);
         let toc = self.extract_toc(markdown)?;
 
         Ok(ProcessedContent {
@@ -58,158 +189,172 @@ impl MarkdownProcessor {
         })
     }
 
-    /// 统计字数
+    /// 统计字数：中日韩文字逐字计数，其他文字按单词计数，代码块也计入
     pub fn count_words(&self, markdown: &str) -> u32 {
-        let plain_text = self.strip_markdown(markdown);
-        plain_text.split_whitespace().count() as u32
+        self.reading_stats(markdown).word_count()
     }
 
-    /// 提取目录
+    /// 提取目录（平铺），id 与渲染后 HTML 中标题的 id 一致
     pub fn extract_toc(&self, markdown: &str) -> Result<Vec<TocEntry>> {
-        let mut toc = Vec::new();
-        let parser = Parser::new(markdown);
-        let mut current_heading = None;
-        let mut heading_counter = 0;
+        Ok(self.collect_headings(markdown))
+    }
 
-        for event in parser {
+    /// 生成嵌套目录：较低级别的标题作为前一个较高级别标题的子节点
+    pub fn table_of_contents(&self, markdown: &str) -> Vec<TocNode> {
+        fn insert(nodes: &mut Vec<TocNode>, node: TocNode) {
+            match nodes.last_mut() {
+                Some(last) if last.level < node.level => insert(&mut last.children, node),
+                _ => nodes.push(node),
+            }
+        }
+
+        let mut roots = Vec::new();
+        for entry in self.collect_headings(markdown) {
+            insert(&mut roots, TocNode {
+                level: entry.level,
+                title: entry.title,
+                anchor: entry.id,
+                children: Vec::new(),
+            });
+        }
+        roots
+    }
+
+    /// 按文档顺序提取所有链接及其所在行号，代码块中的内容不会被当作链接
+    pub fn extract_links(&self, markdown: &str) -> Vec<MarkdownLink> {
+        let mut links = Vec::new();
+        let mut current: Option<MarkdownLink> = None;
+
+        for (event, range) in Parser::new_ext(markdown, self.parser_options()).into_offset_iter() {
             match event {
-                pulldown_cmark::Event::Start(pulldown_cmark::Tag::Heading(level, _, _)) => {
-                    current_heading = Some((level as u8, String::new()));
+                pulldown_cmark::Event::Start(pulldown_cmark::Tag::Link(_, url, _)) => {
+                    let line = markdown[..range.start].matches('\n').count() + 1;
+                    current = Some(MarkdownLink { text: String::new(), url: url.to_string(), line });
                 }
-                pulldown_cmark::Event::Text(text) if current_heading.is_some() => {
-                    if let Some((level, ref mut title)) = current_heading {
-                        title.push_str(&text);
+                pulldown_cmark::Event::Text(text) | pulldown_cmark::Event::Code(text) => {
+                    if let Some(link) = current.as_mut() {
+                        link.text.push_str(&text);
                     }
                 }
-                pulldown_cmark::Event::End(pulldown_cmark::Tag::Heading(_, _, _)) => {
-                    if let Some((level, title)) = current_heading.take() {
-                        heading_counter += 1;
-                        let id = format!("heading-{}", heading_counter);
-                        toc.push(TocEntry { level, title, id });
+                pulldown_cmark::Event::End(pulldown_cmark::Tag::Link(..)) => {
+                    if let Some(link) = current.take() {
+                        links.push(link);
                     }
                 }
                 _ => {}
             }
         }
 
-        Ok(toc)
+        links
     }
 
-    /// 将Markdown渲染为HTML
+    /// 将Markdown渲染为经过清理的HTML
     pub fn render(&self, markdown: &str) -> Result<String> {
-        // 配置Markdown解析选项
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_STRIKETHROUGH);
-        options.insert(Options::ENABLE_TABLES);
-        options.insert(Options::ENABLE_FOOTNOTES);
-        options.insert(Options::ENABLE_TASKLISTS);
-        options.insert(Options::ENABLE_SMART_PUNCTUATION);
+        self.render_with_allowed_tags(markdown, &[])
+    }
+
+    /// 将Markdown渲染为HTML，并按默认白名单加上 extra_tags 清理危险标签和属性
+    pub fn render_with_allowed_tags(&self, markdown: &str, extra_tags: &[String]) -> Result<String> {
+        // 标题锚点与目录使用同一套解析选项生成，保证顺序一致
+        let heading_ids: Vec<String> = self.collect_headings(markdown)
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+        let mut heading_index = 0;
 
         // 解析Markdown
-        let parser = Parser::new_ext(markdown, options);
+        let parser = Parser::new_ext(markdown, self.parser_options());
 
-        // 处理代码块语法高亮
-        let parser = parser.map(|event| {
-            match &event {
-                pulldown_cmark::Event::Start(pulldown_cmark::Tag::CodeBlock(kind)) => {
-                    match kind {
-                        pulldown_cmark::CodeBlockKind::Fenced(lang) => {
-                            pulldown_cmark::Event::Start(pulldown_cmark::Tag::CodeBlock(
-                                pulldown_cmark::CodeBlockKind::Fenced(lang.clone())
-                            ))
-                        }
-                        _ => event,
-                    }
+        // 为没有显式 id 的标题补上锚点，并将围栏代码块替换为高亮后的 HTML
+        let mut events = Vec::new();
+        let mut code_block: Option<(String, String)> = None;
+        for event in parser {
+            match event {
+                pulldown_cmark::Event::Start(pulldown_cmark::Tag::Heading(level, id, classes)) => {
+                    let anchor = heading_ids.get(heading_index).map(String::as_str);
+                    heading_index += 1;
+                    events.push(pulldown_cmark::Event::Start(pulldown_cmark::Tag::Heading(
+                        level,
+                        id.or(anchor),
+                        classes,
+                    )));
+                }
+                pulldown_cmark::Event::Start(pulldown_cmark::Tag::CodeBlock(
+                    pulldown_cmark::CodeBlockKind::Fenced(lang),
+                )) => {
+                    code_block = Some((lang.to_string(), String::new()));
                 }
-                pulldown_cmark::Event::Text(_) => {
-                    // 这里可以添加自定义文本处理逻辑
-                    event
+                pulldown_cmark::Event::Text(text) => match code_block.as_mut() {
+                    Some((_, code)) => code.push_str(&text),
+                    None => events.push(pulldown_cmark::Event::Text(text)),
+                },
+                pulldown_cmark::Event::End(pulldown_cmark::Tag::CodeBlock(
+                    pulldown_cmark::CodeBlockKind::Fenced(_),
+                )) => {
+                    if let Some((lang, code)) = code_block.take() {
+                        events.push(pulldown_cmark::Event::Html(self.highlight_code(&lang, &code).into()));
+                    }
                 }
-                _ => event,
+                _ => events.push(event),
             }
-        });
+        }
 
         // 渲染为HTML
         let mut html_output = String::new();
-        html::push_html(&mut html_output, parser);
-
-        // 后处理：添加代码高亮
-        let processed_html = self.process_code_blocks(&html_output)?;
+        html::push_html(&mut html_output, events.into_iter());
 
-        Ok(processed_html)
+        // Markdown 允许内嵌原始 HTML，输出前必须清理，防止存储型 XSS
+        Ok(sanitize_html(&html_output, extra_tags))
     }
 
     /// 生成目录(TOC)
     pub fn generate_toc(&self, markdown: &str) -> Result<Vec<TocItem>> {
-        let mut toc = Vec::new();
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+        Ok(self.collect_headings(markdown)
+            .into_iter()
+            .map(|entry| TocItem {
+                level: entry.level as u32,
+                title: entry.title,
+                id: entry.id,
+            })
+            .collect())
+    }
 
-        let parser = Parser::new_ext(markdown, options);
-        let mut heading_id_counter = HashMap::new();
+    /// 去掉 Markdown 标记，返回以空格连接的纯文本
+    pub fn strip_markdown(&self, markdown: &str) -> String {
+        join_blocks(self.text_blocks(markdown).iter())
+    }
 
-        for event in parser {
-            if let pulldown_cmark::Event::Start(pulldown_cmark::Tag::Heading(level, fragment_id, _)) = event {
-                // 下一个事件应该是标题文本
-                let title = "".to_string(); // 这里需要更复杂的逻辑来提取标题文本
-                
-                let id = if let Some(id) = fragment_id {
-                    id.to_string()
-                } else {
-                    // 生成ID
-                    self.generate_heading_id(&title, &mut heading_id_counter)
-                };
-
-                toc.push(TocItem {
-                    level: level as u32,
-                    title,
-                    id,
-                });
-            }
-        }
+    /// 按配置的策略提取纯文本摘要，最多 max_length 个字符，不会截断在单词中间
+    pub fn extract_excerpt(&self, markdown: &str, max_length: usize) -> String {
+        let blocks = self.text_blocks(markdown);
+        let prose = blocks.iter().filter(|block| block.kind == TextBlockKind::Prose);
+
+        let text = match self.options.excerpt_strategy {
+            ExcerptStrategy::FirstParagraph => prose.map(|block| block.plain_text()).next().unwrap_or_default(),
+            ExcerptStrategy::SkipHeadings => join_blocks(prose),
+            ExcerptStrategy::FirstChars => join_blocks(blocks.iter().filter(|block| block.kind != TextBlockKind::Code)),
+        };
 
-        Ok(toc)
+        truncate_at_word_boundary(&text, max_length)
     }
 
-    /// 提取摘要
-    pub fn extract_excerpt(&self, markdown: &str, max_length: usize) -> String {
-        // 移除Markdown标记
-        let plain_text = self.strip_markdown(markdown);
-        
-        if plain_text.len() <= max_length {
-            plain_text
-        } else {
-            // 使用字符边界安全截断
-            let mut end = max_length;
-            
-            // 确保不会在字符中间截断
-            while !plain_text.is_char_boundary(end) && end > 0 {
-                end -= 1;
-            }
-            
-            if end == 0 {
-                return String::new();
-            }
-            
-            let truncated = &plain_text[..end];
-            
-            // 尝试在单词或中文字符边界截断
-            let chars: Vec<char> = truncated.chars().collect();
-            if let Some(pos) = chars.iter().rposition(|&c| c.is_whitespace() || c == '。' || c == '，' || c == '！' || c == '？') {
-                let safe_truncated: String = chars[..=pos].iter().collect();
-                format!("{}...", safe_truncated.trim())
+    /// 估算阅读时间（分钟，至少 1 分钟），代码块按较慢的速度计算
+    pub fn estimate_reading_time(&self, markdown: &str) -> u32 {
+        self.reading_stats(markdown).reading_time(&self.options)
+    }
+
+    fn reading_stats(&self, markdown: &str) -> ReadingStats {
+        let mut stats = ReadingStats::default();
+        for block in self.text_blocks(markdown) {
+            let (words, cjk_chars) = count_text_units(&block.text);
+            if block.kind == TextBlockKind::Code {
+                stats.code_words += words + cjk_chars;
             } else {
-                format!("{}...", truncated)
+                stats.words += words;
+                stats.cjk_chars += cjk_chars;
             }
         }
-    }
-
-    /// 估算阅读时间
-    pub fn estimate_reading_time(&self, markdown: &str) -> u32 {
-        let plain_text = self.strip_markdown(markdown);
-        let word_count = plain_text.split_whitespace().count();
-        // 假设阅读速度为每分钟200词
-        std::cmp::max(1, (word_count / 200) as u32)
+        stats
     }
 
     /// 验证Markdown语法
@@ -250,55 +395,304 @@ impl MarkdownProcessor {
 
     // 私有方法
 
-    fn process_code_blocks(&self, html: &str) -> Result<String> {
-        // 简化版代码高亮处理
-        // 实际实现需要解析HTML并替换<code>块
-        Ok(html.to_string())
+    fn parser_options(&self) -> Options {
+        self.options.to_parser_options()
     }
 
-    fn strip_markdown(&self, markdown: &str) -> String {
-        let parser = Parser::new(markdown);
+    /// 按文档顺序收集所有标题及其锚点
+    fn collect_headings(&self, markdown: &str) -> Vec<TocEntry> {
+        let mut headings = Vec::new();
+        let mut heading_id_counter = HashMap::new();
+        let mut current_heading: Option<(u8, String)> = None;
+
+        for event in Parser::new_ext(markdown, self.parser_options()) {
+            match event {
+                pulldown_cmark::Event::Start(pulldown_cmark::Tag::Heading(level, _, _)) => {
+                    current_heading = Some((level as u8, String::new()));
+                }
+                pulldown_cmark::Event::Text(text) | pulldown_cmark::Event::Code(text) => {
+                    if let Some((_, ref mut title)) = current_heading {
+                        title.push_str(&text);
+                    }
+                }
+                pulldown_cmark::Event::End(pulldown_cmark::Tag::Heading(_, _, _)) => {
+                    if let Some((level, title)) = current_heading.take() {
+                        let title = title.trim().to_string();
+                        let id = self.generate_heading_id(&title, &mut heading_id_counter);
+                        headings.push(TocEntry { level, title, id });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        headings
+    }
+
+    /// 按围栏语言高亮代码块；语言未知或高亮失败时输出转义后的纯文本代码块
+    fn highlight_code(&self, lang: &str, code: &str) -> String {
+        // 围栏信息可能带有额外标记，如 `rust,ignore`
+        let token = lang.split(|c: char| c.is_whitespace() || c == ',').next().unwrap_or("");
+        if let Some(syntax) = SYNTAX_SET.find_syntax_by_token(token).filter(|_| !token.is_empty()) {
+            match highlighted_html_for_string(code, &SYNTAX_SET, syntax, &self.theme) {
+                Ok(highlighted) => return highlighted,
+                Err(e) => warn!("Failed to highlight {} code block: {}", token, e),
+            }
+        }
+
+        let mut html_output = String::from("<pre><code");
+        if !token.is_empty() {
+            html_output.push_str(" class=\"language-");
+            let _ = pulldown_cmark::escape::escape_html(&mut html_output, token);
+            html_output.push('"');
+        }
+        html_output.push('>');
+        let _ = pulldown_cmark::escape::escape_html(&mut html_output, code);
+        html_output.push_str("</code></pre>\n");
+        html_output
+    }
+
+    /// 将文档拆成按顺序排列的纯文本块。图片替代文本、原始 HTML 和脚注标记不计入正文
+    fn text_blocks(&self, markdown: &str) -> Vec<TextBlock> {
+        use pulldown_cmark::{Event, Tag};
+
+        fn flush(blocks: &mut Vec<TextBlock>, kind: TextBlockKind, text: &mut String) {
+            if !text.trim().is_empty() {
+                blocks.push(TextBlock { kind, text: std::mem::take(text) });
+            }
+            text.clear();
+        }
+
+        let mut blocks = Vec::new();
+        let mut kind = TextBlockKind::Prose;
         let mut text = String::new();
+        let mut image_depth = 0;
 
-        for event in parser {
+        for event in Parser::new_ext(markdown, self.parser_options()) {
             match event {
-                pulldown_cmark::Event::Text(t) => text.push_str(&t),
-                pulldown_cmark::Event::SoftBreak | pulldown_cmark::Event::HardBreak => {
-                    text.push(' ');
+                Event::Start(Tag::Heading(..)) | Event::Start(Tag::CodeBlock(_)) => {
+                    flush(&mut blocks, kind, &mut text);
+                    kind = match event {
+                        Event::Start(Tag::Heading(..)) => TextBlockKind::Heading,
+                        _ => TextBlockKind::Code,
+                    };
                 }
+                Event::Start(Tag::Paragraph | Tag::Item | Tag::TableCell) => {
+                    flush(&mut blocks, kind, &mut text);
+                }
+                Event::End(Tag::Heading(..) | Tag::CodeBlock(_) | Tag::Paragraph | Tag::Item | Tag::TableCell) => {
+                    flush(&mut blocks, kind, &mut text);
+                    kind = TextBlockKind::Prose;
+                }
+                Event::Start(Tag::Image(..)) => image_depth += 1,
+                Event::End(Tag::Image(..)) => image_depth -= 1,
+                Event::Text(t) | Event::Code(t) if image_depth == 0 => text.push_str(&t),
+                Event::SoftBreak | Event::HardBreak => text.push(' '),
                 _ => {}
             }
         }
+        flush(&mut blocks, kind, &mut text);
 
-        // 清理多余的空白
-        text.split_whitespace().collect::<Vec<_>>().join(" ")
+        blocks
     }
 
+    /// 生成 GitHub 风格的标题锚点：小写，去掉标点，空格转为连字符，重复时追加 -1、-2
     fn generate_heading_id(&self, title: &str, counter: &mut HashMap<String, u32>) -> String {
-        // 生成URL友好的ID
-        let base_id = title
+        let mut base_id = title
             .to_lowercase()
             .chars()
-            .map(|c| if c.is_alphanumeric() { c } else { '-' })
-            .collect::<String>()
-            .trim_matches('-')
-            .to_string();
+            .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
+            .map(|c| if c == ' ' { '-' } else { c })
+            .collect::<String>();
+
+        if base_id.is_empty() {
+            base_id = "heading".to_string();
+        }
 
         // 处理重复ID
         let count = counter.entry(base_id.clone()).or_insert(0);
-        *count += 1;
-
-        if *count == 1 {
+        let id = if *count == 0 {
             base_id
         } else {
             format!("{}-{}", base_id, count)
-        }
+        };
+        *count += 1;
+
+        id
     }
 
     fn is_valid_language(&self, lang: &str) -> bool {
         // 检查是否是有效的编程语言标识符
-        self.syntax_set.find_syntax_by_token(lang).is_some()
+        SYNTAX_SET.find_syntax_by_token(lang).is_some()
+    }
+}
+
+/// 解析站内文档链接指向的 slug，支持 `doc-slug`、`./doc-slug`、`../guide/doc-slug.md#anchor` 等形式。
+/// 外部链接、纯锚点、接口地址以及不符合 slug 格式的路径（如图片文件）返回 None
+pub fn internal_link_slug(url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty()
+        || url.starts_with('#')
+        || url.starts_with("//")
+        || url.starts_with("/api/")
+        || url.contains("://")
+        || url.starts_with("mailto:")
+        || url.starts_with("tel:")
+    {
+        return None;
     }
+
+    let path = url.split(['#', '?']).next().unwrap_or("");
+    let last_segment = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+    let slug = last_segment.strip_suffix(".md").unwrap_or(last_segment);
+
+    let is_slug = !slug.is_empty()
+        && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    is_slug.then(|| slug.to_string())
+}
+
+/// 清理渲染后的 HTML：移除脚本、事件属性、危险协议等，保留常规排版标签。
+/// 额外保留标题锚点、脚注、任务列表复选框以及代码高亮的内联颜色样式
+pub fn sanitize_html(html: &str, extra_tags: &[String]) -> String {
+    let extra_tags: HashSet<&str> = extra_tags
+        .iter()
+        .map(|tag| tag.trim())
+        // 白名单只能放宽排版标签，不能放开可执行或可嵌入内容的标签
+        .filter(|tag| !matches!(
+            tag.to_ascii_lowercase().as_str(),
+            "script" | "style" | "iframe" | "object" | "embed" | "form" | "link" | "meta" | "base" | "svg" | "math" | ""
+        ))
+        .collect();
+
+    ammonia::Builder::default()
+        .add_tags(["input"])
+        .add_tags(extra_tags)
+        .add_tag_attributes("h1", ["id"])
+        .add_tag_attributes("h2", ["id"])
+        .add_tag_attributes("h3", ["id"])
+        .add_tag_attributes("h4", ["id"])
+        .add_tag_attributes("h5", ["id"])
+        .add_tag_attributes("h6", ["id"])
+        .add_tag_attributes("div", ["class", "id"])
+        .add_tag_attributes("sup", ["class"])
+        .add_tag_attributes("code", ["class"])
+        .add_tag_attributes("pre", ["style"])
+        .add_tag_attributes("span", ["style"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .attribute_filter(|element, attribute, value| match (element, attribute) {
+            (_, "style") => value
+                .split(';')
+                .map(str::trim)
+                .filter(|declaration| !declaration.is_empty())
+                .all(|declaration| HIGHLIGHT_STYLE_REGEX.is_match(declaration))
+                .then(|| value.into()),
+            ("input", "type") => (value == "checkbox").then(|| value.into()),
+            _ => Some(value.into()),
+        })
+        .clean(html)
+        .to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextBlockKind {
+    Heading,
+    Code,
+    Prose,
+}
+
+#[derive(Debug, Clone)]
+struct TextBlock {
+    kind: TextBlockKind,
+    text: String,
+}
+
+impl TextBlock {
+    fn plain_text(&self) -> String {
+        self.text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+fn join_blocks<'a>(blocks: impl Iterator<Item = &'a TextBlock>) -> String {
+    blocks.map(TextBlock::plain_text).collect::<Vec<_>>().join(" ")
+}
+
+/// 中日韩文字不以空格分词，任意两个字之间都可以截断或计数
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'      // 平假名、片假名
+        | '\u{3400}'..='\u{4DBF}'    // CJK 扩展 A
+        | '\u{4E00}'..='\u{9FFF}'    // CJK 统一汉字
+        | '\u{AC00}'..='\u{D7AF}'    // 韩文音节
+        | '\u{F900}'..='\u{FAFF}'    // CJK 兼容汉字
+        | '\u{20000}'..='\u{2FA1F}'  // CJK 扩展 B 及以后
+    )
+}
+
+/// 代码阅读速度相对正文的倍数
+const CODE_READING_SLOWDOWN: f64 = 2.0;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct ReadingStats {
+    words: u32,
+    cjk_chars: u32,
+    code_words: u32,
+}
+
+impl ReadingStats {
+    fn word_count(&self) -> u32 {
+        self.words + self.cjk_chars + self.code_words
+    }
+
+    fn reading_time(&self, options: &MarkdownOptions) -> u32 {
+        let words_per_minute = options.words_per_minute.max(1) as f64;
+        let cjk_per_minute = options.cjk_chars_per_minute.max(1) as f64;
+
+        let minutes = self.words as f64 / words_per_minute
+            + self.cjk_chars as f64 / cjk_per_minute
+            + self.code_words as f64 * CODE_READING_SLOWDOWN / words_per_minute;
+        (minutes.ceil() as u32).max(1)
+    }
+}
+
+/// 统计文本中的单词数和中日韩字数。中日韩文字和全角标点都会结束当前单词，
+/// 智能标点生成的右单引号（it’s）仍视为单词的一部分
+fn count_text_units(text: &str) -> (u32, u32) {
+    let mut words = 0;
+    let mut cjk_chars = 0;
+    let mut in_word = false;
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk_chars += 1;
+            in_word = false;
+        } else if c.is_whitespace() || (!c.is_ascii() && !c.is_alphanumeric() && c != '\u{2019}') {
+            in_word = false;
+        } else if !in_word && c.is_alphanumeric() {
+            words += 1;
+            in_word = true;
+        }
+    }
+
+    (words, cjk_chars)
+}
+
+/// 按字符数截断并追加省略号。只在空白、中日韩文字或中文标点处断开，
+/// 找不到合适位置（如一个超长单词）时才在字符边界硬截断
+fn truncate_at_word_boundary(text: &str, max_chars: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max_chars {
+        return text.to_string();
+    }
+
+    // 分隔字符两侧都可以断开
+    let is_separator = |c: char| c.is_whitespace() || is_cjk(c) || "。，、；：！？".contains(c);
+    let cut = (1..=max_chars)
+        .rev()
+        .find(|&i| is_separator(chars[i - 1]) || is_separator(chars[i]))
+        .unwrap_or(max_chars);
+
+    let truncated: String = chars[..cut].iter().collect();
+    format!("{}...", truncated.trim_end())
 }
 
 #[derive(Debug, Clone)]
@@ -328,17 +722,17 @@ mod tests {
 
     #[test]
     fn test_markdown_rendering() {
-        let processor = MarkdownProcessor::new();
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
         let markdown = "# Hello World\n\nThis is **bold** text.";
         let html = processor.render(markdown).unwrap();
         
-        assert!(html.contains("<h1>"));
+        assert!(html.contains("<h1 id=\"hello-world\">"));
         assert!(html.contains("<strong>"));
     }
 
     #[test]
     fn test_excerpt_extraction() {
-        let processor = MarkdownProcessor::new();
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
         let markdown = "# Title\n\nThis is a long paragraph that should be truncated at some point to create a proper excerpt.";
         let excerpt = processor.extract_excerpt(markdown, 50);
         
@@ -346,18 +740,159 @@ mod tests {
         assert!(excerpt.ends_with("..."));
     }
 
+    #[test]
+    fn test_excerpt_skips_leading_heading() {
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
+        let markdown = "# Installation Guide\n\nInstall the **CLI** with `cargo install rainbow`, then run it.";
+
+        assert_eq!(
+            processor.extract_excerpt(markdown, 200),
+            "Install the CLI with cargo install rainbow, then run it."
+        );
+        // 不会截断在单词中间
+        assert_eq!(processor.extract_excerpt(markdown, 14), "Install the...");
+    }
+
+    #[test]
+    fn test_excerpt_ignores_image_only_first_line() {
+        let processor = MarkdownProcessor::new(MarkdownOptions {
+            excerpt_strategy: ExcerptStrategy::FirstParagraph,
+            ..MarkdownOptions::default()
+        });
+        let markdown = "![architecture diagram](./arch.png)\n\n系统由网关、文档服务和搜索服务组成。\n\n第二段。";
+
+        assert_eq!(processor.extract_excerpt(markdown, 200), "系统由网关、文档服务和搜索服务组成。");
+        // 中文按字符截断，不会产生非法 UTF-8
+        assert_eq!(processor.extract_excerpt(markdown, 5), "系统由网关...");
+    }
+
     #[test]
     fn test_reading_time_estimation() {
-        let processor = MarkdownProcessor::new();
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
         let markdown = &"word ".repeat(200); // 200 words
         let time = processor.estimate_reading_time(markdown);
         
         assert_eq!(time, 1); // Should be 1 minute
     }
 
+    #[test]
+    fn test_reading_time_counts_cjk_and_code() {
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
+
+        // 1000 个汉字按每分钟 400 字计算
+        let chinese = "文档协作".repeat(250);
+        assert_eq!(processor.count_words(&chinese), 1000);
+        assert_eq!(processor.estimate_reading_time(&chinese), 3);
+
+        let english = "Rainbow docs keeps every page versioned and searchable. ".repeat(25);
+        assert_eq!(processor.count_words(&english), 200);
+        assert_eq!(processor.estimate_reading_time(&english), 1);
+
+        // 混排时英文单词和汉字分别计数
+        assert_eq!(processor.count_words("使用Rust编写, it's fast"), 7);
+
+        // 同样字数的代码比正文读得慢
+        let code = format!("```rust\n{}\n```", "let total = a + b;\n".repeat(60));
+        let prose = "let total equal a plus b. ".repeat(60);
+        assert!(processor.estimate_reading_time(&code) > processor.estimate_reading_time(&prose));
+
+        let fast = MarkdownProcessor::new(MarkdownOptions { cjk_chars_per_minute: 1000, ..MarkdownOptions::default() });
+        assert_eq!(fast.estimate_reading_time(&chinese), 1);
+    }
+
+    #[test]
+    fn test_heading_anchors_match_rendered_ids() {
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
+        let markdown = "# Getting Started\n\n## Install `cargo`\n\n## Usage\n\n### 快速 开始\n\n## Usage\n\n# FAQ!";
+
+        let toc = processor.table_of_contents(markdown);
+        assert_eq!(toc.len(), 2);
+        assert_eq!(toc[0].anchor, "getting-started");
+        let children: Vec<&str> = toc[0].children.iter().map(|c| c.anchor.as_str()).collect();
+        assert_eq!(children, vec!["install-cargo", "usage", "usage-1"]);
+        assert_eq!(toc[0].children[1].children[0].anchor, "快速-开始");
+        assert_eq!(toc[1].anchor, "faq");
+
+        let html = processor.render(markdown).unwrap();
+        for anchor in ["getting-started", "install-cargo", "usage", "usage-1", "快速-开始", "faq"] {
+            assert!(html.contains(&format!("id=\"{}\"", anchor)), "missing id {} in {}", anchor, html);
+        }
+    }
+
+    #[test]
+    fn test_extract_internal_links_with_lines() {
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
+        let markdown = "# Guide\n\nSee [setup](./setup) and [API](../reference/api.md#auth).\n\n```\n[not a link](inside-code)\n```\n\n[site](https://example.com) ![img](diagram.png)";
+
+        let links = processor.extract_links(markdown);
+        let found: Vec<(&str, usize)> = links.iter().map(|l| (l.text.as_str(), l.line)).collect();
+        assert_eq!(found, vec![("setup", 3), ("API", 3), ("site", 9)]);
+
+        let slugs: Vec<Option<String>> = links.iter().map(|l| internal_link_slug(&l.url)).collect();
+        assert_eq!(slugs, vec![Some("setup".to_string()), Some("api".to_string()), None]);
+        assert_eq!(internal_link_slug("#section"), None);
+        assert_eq!(internal_link_slug("/api/docs/files/f1/download"), None);
+    }
+
+    #[test]
+    fn test_task_list_and_footnote_rendering() {
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
+        let markdown = "- [x] done\n- [ ] todo\n\nA claim.[^src]\n\n[^src]: The source.\n";
+        let html = processor.render(markdown).unwrap();
+
+        assert!(html.contains("<li><input disabled=\"\" type=\"checkbox\" checked=\"\">\ndone</li>"));
+        assert!(html.contains("<li><input disabled=\"\" type=\"checkbox\">\ntodo</li>"));
+        assert!(html.contains("<sup class=\"footnote-reference\"><a href=\"#src\""));
+        assert!(html.contains("<div class=\"footnote-definition\" id=\"src\">"));
+
+        // 关闭扩展后按普通文本渲染
+        let plain = MarkdownProcessor::new(MarkdownOptions {
+            tasklists: false,
+            footnotes: false,
+            ..MarkdownOptions::default()
+        });
+        let html = plain.render(markdown).unwrap();
+        assert!(html.contains("<li>[x] done</li>"));
+        assert!(!html.contains("footnote"));
+    }
+
+    #[test]
+    fn test_fenced_code_highlighting() {
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
+
+        let html = processor.render("```rust\nfn main() {}\n```").unwrap();
+        assert!(html.contains("<span style="), "expected highlighted tokens in {}", html);
+        assert!(html.contains(">main</span>"));
+
+        // 未知语言退回为转义后的纯文本
+        let html = processor.render("```nosuchlang\n<script>alert(1)</script>\n```").unwrap();
+        assert!(html.contains("<pre><code class=\"language-nosuchlang\">&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_rendered_html_is_sanitized() {
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
+        let markdown = "Hello <script>alert('xss')</script><strong onclick=\"steal()\">world</strong>\n\n[click](javascript:alert(1)) <span style=\"position:fixed\">overlay</span>";
+        let html = processor.render(markdown).unwrap();
+
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("alert('xss')"));
+        assert!(html.contains("<strong>world</strong>"));
+        assert!(!html.contains("onclick"));
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("position:fixed"));
+
+        // 空间白名单可以放开排版标签，但不能放开脚本
+        let extra = vec!["mark".to_string(), "script".to_string()];
+        let html = processor.render_with_allowed_tags("<mark>note</mark><script>x()</script>", &extra).unwrap();
+        assert!(html.contains("<mark>note</mark>"));
+        assert!(!html.contains("<script"));
+    }
+
     #[test]
     fn test_strip_markdown() {
-        let processor = MarkdownProcessor::new();
+        let processor = MarkdownProcessor::new(MarkdownOptions::default());
         let markdown = "# Title\n\nThis is **bold** and *italic* text with [link](url).";
         let plain = processor.strip_markdown(markdown);
         
@@ -1,5 +1,21 @@
 pub mod auth;
+pub mod cors;
+pub mod db_autostart;
+pub mod diff;
+pub mod download;
+pub mod export;
+pub mod feed;
+pub mod http_cache;
+pub mod i18n;
 pub mod markdown;
+pub mod pagination;
+pub mod rate_limit;
+pub mod redact;
+pub mod request_id;
+pub mod shutdown;
+pub mod sitemap;
+pub mod slug;
+pub mod ttl_cache;
 
 #[cfg(feature = "installer")]
 pub mod installer;
\ No newline at end of file
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+/// 列表接口默认每页条数
+pub const DEFAULT_PAGE_LIMIT: u32 = 20;
+/// 列表接口每页最多条数
+pub const MAX_PAGE_LIMIT: u32 = 100;
+
+/// 规范化后的分页参数，page 从 1 开始，limit 不小于 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRequest {
+    pub page: u32,
+    pub limit: u32,
+}
+
+impl PageRequest {
+    /// 使用默认的每页条数和上限
+    pub fn new(page: Option<u32>, limit: Option<u32>) -> Self {
+        Self::with_limits(page, limit, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT)
+    }
+
+    /// 缺省 page 为 1、limit 为 default_limit；page 至少为 1，limit 限制在 1..=max_limit
+    pub fn with_limits(page: Option<u32>, limit: Option<u32>, default_limit: u32, max_limit: u32) -> Self {
+        Self {
+            page: page.unwrap_or(1).max(1),
+            limit: limit.unwrap_or(default_limit).clamp(1, max_limit.max(1)),
+        }
+    }
+
+    /// 查询的起始位置，页码过大时取 u32::MAX 而不是溢出
+    pub fn offset(&self) -> u32 {
+        (self.page - 1).saturating_mul(self.limit)
+    }
+
+    pub fn total_pages(&self, total: u32) -> u32 {
+        total_pages(total, self.limit)
+    }
+}
+
+/// 总页数，向上取整；没有数据时为 0
+pub fn total_pages(total: u32, limit: u32) -> u32 {
+    if limit == 0 {
+        0
+    } else {
+        total.div_ceil(limit)
+    }
+}
+
+/// 分页列表的统一结构，各列表响应由它转换得到，保证分页字段的计算方式一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: u32,
+    pub page: u32,
+    pub limit: u32,
+    pub total_pages: u32,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(items: Vec<T>, total: u32, request: PageRequest) -> Self {
+        Self {
+            items,
+            total,
+            page: request.page,
+            limit: request.limit,
+            total_pages: request.total_pages(total),
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Paginated<U> {
+        Paginated {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            page: self.page,
+            limit: self.limit,
+            total_pages: self.total_pages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_request_defaults_and_clamping() {
+        assert_eq!(PageRequest::new(None, None), PageRequest { page: 1, limit: 20 });
+        assert_eq!(PageRequest::new(Some(0), Some(0)), PageRequest { page: 1, limit: 1 });
+        assert_eq!(PageRequest::new(Some(3), Some(1000)), PageRequest { page: 3, limit: 100 });
+        assert_eq!(PageRequest::with_limits(None, None, 50, 10), PageRequest { page: 1, limit: 10 });
+
+        assert_eq!(PageRequest::new(Some(3), Some(10)).offset(), 20);
+        assert_eq!(PageRequest::new(Some(u32::MAX), Some(100)).offset(), u32::MAX);
+    }
+
+    #[test]
+    fn test_empty_result_has_no_pages() {
+        let page = Paginated::<u32>::new(Vec::new(), 0, PageRequest::new(None, None));
+        assert_eq!((page.total, page.page, page.limit, page.total_pages), (0, 1, 20, 0));
+    }
+
+    #[test]
+    fn test_last_partial_page() {
+        let all: Vec<u32> = (0..45).collect();
+        let request = PageRequest::new(Some(3), Some(20));
+        let items: Vec<u32> = all.iter().skip(request.offset() as usize).take(request.limit as usize).copied().collect();
+
+        let page = Paginated::new(items, all.len() as u32, request);
+        assert_eq!(page.items, (40..45).collect::<Vec<_>>());
+        assert_eq!(page.total_pages, 3);
+        assert_eq!(total_pages(40, 20), 2);
+        assert_eq!(total_pages(41, 20), 3);
+    }
+
+    #[test]
+    fn test_limit_larger_than_total_is_single_page() {
+        let page = Paginated::new(vec!["a", "b", "c"], 3, PageRequest::new(Some(1), Some(50)));
+        assert_eq!((page.total, page.limit, page.total_pages), (3, 50, 1));
+
+        let mapped = page.map(str::to_uppercase);
+        assert_eq!(mapped.items, vec!["A", "B", "C"]);
+        assert_eq!(mapped.total_pages, 1);
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+
+/// 滑动窗口限流器：每个 key 在 window 内最多允许 max 次操作。
+/// 只保存在内存中，调用方负责加锁和定期调用 cleanup
+#[derive(Debug)]
+pub struct SlidingWindowLimiter {
+    max: u32,
+    window: Duration,
+    hits: HashMap<String, VecDeque<DateTime<Utc>>>,
+}
+
+impl SlidingWindowLimiter {
+    /// max 为 0 表示不限制
+    pub fn new(max: u32, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            hits: HashMap::new(),
+        }
+    }
+
+    /// 尝试记录一次操作，超出限制时返回 false 且不计数
+    pub fn try_acquire(&mut self, key: &str, now: DateTime<Utc>) -> bool {
+        if self.max == 0 {
+            return true;
+        }
+
+        let window_start = now - self.window;
+        let hits = self.hits.entry(key.to_string()).or_default();
+        while hits.front().is_some_and(|at| *at <= window_start) {
+            hits.pop_front();
+        }
+
+        if hits.len() >= self.max as usize {
+            return false;
+        }
+        hits.push_back(now);
+        true
+    }
+
+    /// 清理窗口外的记录，避免长期不活跃的 key 占用内存
+    pub fn cleanup(&mut self, now: DateTime<Utc>) {
+        let window_start = now - self.window;
+        self.hits.retain(|_, hits| {
+            hits.retain(|at| *at > window_start);
+            !hits.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_rejects_over_limit_then_recovers_after_window() {
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+        let mut limiter = SlidingWindowLimiter::new(3, Duration::hours(1));
+
+        for minute in 0..3 {
+            assert!(limiter.try_acquire("space-1:alice", start + Duration::minutes(minute)));
+        }
+        assert!(!limiter.try_acquire("space-1:alice", start + Duration::minutes(30)));
+
+        // 其他空间或其他邀请者互不影响
+        assert!(limiter.try_acquire("space-2:alice", start + Duration::minutes(30)));
+        assert!(limiter.try_acquire("space-1:bob", start + Duration::minutes(30)));
+
+        // 第一次操作滑出窗口后恢复一个名额
+        assert!(limiter.try_acquire("space-1:alice", start + Duration::minutes(60)));
+        assert!(!limiter.try_acquire("space-1:alice", start + Duration::minutes(60)));
+    }
+
+    #[test]
+    fn test_zero_means_unlimited_and_cleanup_drops_idle_keys() {
+        let now = Utc::now();
+        let mut unlimited = SlidingWindowLimiter::new(0, Duration::hours(1));
+        assert!((0..100).all(|_| unlimited.try_acquire("k", now)));
+
+        let mut limiter = SlidingWindowLimiter::new(1, Duration::hours(1));
+        assert!(limiter.try_acquire("k", now));
+        limiter.cleanup(now + Duration::hours(2));
+        assert!(limiter.hits.is_empty());
+    }
+}
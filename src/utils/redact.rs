@@ -0,0 +1,73 @@
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// 日志中的用户ID：输出 `user#` 加 ID 的 SHA-256 前 8 位十六进制，
+/// 同一用户的日志仍然可以关联，但不会写出真实ID。带不带 `user:` 前缀结果相同
+pub fn user_id(id: &str) -> RedactedUserId<'_> {
+    RedactedUserId(id.strip_prefix("user:").unwrap_or(id))
+}
+
+/// 日志中的邮箱：只保留域名，如 `***@example.com`
+pub fn email(address: &str) -> RedactedEmail<'_> {
+    RedactedEmail(address)
+}
+
+pub struct RedactedUserId<'a>(&'a str);
+
+impl fmt::Display for RedactedUserId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digest = Sha256::digest(self.0.as_bytes());
+        f.write_str("user#")?;
+        for byte in &digest[..4] {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for RedactedUserId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+pub struct RedactedEmail<'a>(&'a str);
+
+impl fmt::Display for RedactedEmail<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.rsplit_once('@') {
+            Some((_, domain)) if !domain.is_empty() => write!(f, "***@{}", domain),
+            _ => f.write_str("***"),
+        }
+    }
+}
+
+impl fmt::Debug for RedactedEmail<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_ids_are_pseudonymous_but_stable() {
+        let logged = user_id("user_2f9a7c01").to_string();
+
+        assert!(logged.starts_with("user#"));
+        assert_eq!(logged.len(), "user#".len() + 8);
+        assert!(!logged.contains("2f9a7c01"));
+        assert_eq!(logged, user_id("user:user_2f9a7c01").to_string());
+        assert_ne!(logged, user_id("user_2f9a7c02").to_string());
+        assert_eq!(format!("{:?}", user_id("user_2f9a7c01")), logged);
+    }
+
+    #[test]
+    fn test_email_keeps_only_domain() {
+        assert_eq!(email("alice@example.com").to_string(), "***@example.com");
+        assert_eq!(email("not-an-email").to_string(), "***");
+        assert_eq!(email("trailing@").to_string(), "***");
+    }
+}
@@ -0,0 +1,85 @@
+use axum::{
+    http::{HeaderName, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{info_span, Instrument};
+use uuid::Uuid;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// 当前请求的关联ID，处理函数可以通过 `Extension<RequestId>` 读取
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// 为每个请求分配关联ID：沿用调用方传入的 X-Request-Id，否则生成新的 UUID。
+/// 请求在带有该ID的 tracing span 中处理，服务中的日志都会带上 request_id，
+/// 响应头中也会返回同一个ID
+pub async fn request_id_middleware<B>(mut request: Request<B>, next: Next<B>) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid_request_id(value))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let header_value = HeaderValue::from_str(&request_id).expect("request id is a valid header value");
+
+    request.headers_mut().insert(REQUEST_ID_HEADER.clone(), header_value.clone());
+    request.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    let mut response = next.run(request).instrument(span).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER.clone(), header_value);
+    response
+}
+
+/// 只接受长度有限的可见字符，避免把任意内容写进日志
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 128
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Extension, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(|Extension(id): Extension<RequestId>| async move { ([("x-seen-request-id", id.0)], "ok") }))
+            .layer(middleware::from_fn(request_id_middleware))
+    }
+
+    async fn response_request_id(request: Request<Body>) -> String {
+        let response = app().oneshot(request).await.unwrap();
+        let header = response.headers().get(&REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+        // 处理函数看到的ID与响应头一致
+        assert_eq!(response.headers()["x-seen-request-id"], header.as_str());
+        header
+    }
+
+    #[tokio::test]
+    async fn test_echoes_provided_request_id() {
+        let request = Request::get("/").header("x-request-id", "req-42").body(Body::empty()).unwrap();
+        assert_eq!(response_request_id(request).await, "req-42");
+    }
+
+    #[tokio::test]
+    async fn test_generates_request_id_when_absent_or_invalid() {
+        let generated = response_request_id(Request::get("/").body(Body::empty()).unwrap()).await;
+        assert!(Uuid::parse_str(&generated).is_ok());
+
+        let request = Request::get("/").header("x-request-id", "bad id\twith spaces").body(Body::empty()).unwrap();
+        let replaced = response_request_id(request).await;
+        assert!(Uuid::parse_str(&replaced).is_ok());
+        assert_ne!(replaced, generated);
+    }
+}
@@ -0,0 +1,63 @@
+use std::future::Future;
+
+use tracing::{info, warn};
+
+/// 等待 Ctrl-C 或 SIGTERM，用于 axum 的 with_graceful_shutdown：
+/// 信号到达后不再接受新连接，已在处理的请求会继续完成
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    first_signal(ctrl_c, terminate).await;
+    info!("Shutdown signal received, waiting for in-flight requests to finish");
+}
+
+/// 任意一个信号触发即返回
+async fn first_signal(ctrl_c: impl Future<Output = ()>, terminate: impl Future<Output = ()>) {
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+    use tokio::time::{timeout, Duration};
+
+    #[tokio::test]
+    async fn test_shutdown_resolves_on_simulated_signal() {
+        let (send_terminate, terminate) = oneshot::channel::<()>();
+        let signal = first_signal(std::future::pending(), async {
+            let _ = terminate.await;
+        });
+        tokio::pin!(signal);
+
+        // 信号到达前保持等待
+        assert!(timeout(Duration::from_millis(20), &mut signal).await.is_err());
+
+        send_terminate.send(()).unwrap();
+        assert!(timeout(Duration::from_secs(1), signal).await.is_ok());
+    }
+}
@@ -0,0 +1,28 @@
+// 生成 sitemaps.org 协议的 sitemap.xml
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt::Write;
+
+use super::feed::escape;
+
+pub struct SitemapUrl {
+    /// 页面的完整 URL
+    pub loc: String,
+    pub lastmod: DateTime<Utc>,
+}
+
+pub fn render(urls: &[SitemapUrl]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+
+    for url in urls {
+        xml.push_str("<url>\n");
+        let _ = writeln!(xml, "<loc>{}</loc>", escape(&url.loc));
+        let _ = writeln!(xml, "<lastmod>{}</lastmod>", url.lastmod.to_rfc3339_opts(SecondsFormat::Secs, true));
+        xml.push_str("</url>\n");
+    }
+
+    xml.push_str("</urlset>\n");
+    xml
+}
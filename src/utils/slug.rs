@@ -0,0 +1,71 @@
+// 根据标题生成 URL slug，用于创建文档和空间时未提供 slug 的情况
+
+/// 标题无法转写出任何字母数字时使用的 slug
+const FALLBACK_SLUG: &str = "untitled";
+
+/// 把标题转成小写、以连字符分隔的 slug，最长 max_len 个字符。
+/// 中日韩等非拉丁文字按 deunicode 转写（中文即拼音），如 "快速开始" -> "kuai-su-kai-shi"
+pub fn slug_from_title(title: &str, max_len: usize) -> String {
+    let slug = ::slug::slugify(title);
+    let slug = truncate_slug(&slug, max_len);
+
+    if slug.is_empty() {
+        truncate_slug(FALLBACK_SLUG, max_len)
+    } else {
+        slug
+    }
+}
+
+/// 在 taken 中已有同名 slug 时依次尝试 base-2、base-3……，
+/// 必要时截短 base 以保证结果不超过 max_len
+pub fn unique_slug(base: &str, taken: &[String], max_len: usize) -> String {
+    let is_taken = |candidate: &str| taken.iter().any(|slug| slug == candidate);
+
+    if !is_taken(base) {
+        return base.to_string();
+    }
+
+    (2u32..)
+        .map(|n| {
+            let suffix = format!("-{}", n);
+            let base = truncate_slug(base, max_len.saturating_sub(suffix.len()));
+            format!("{}{}", base, suffix)
+        })
+        .find(|candidate| !is_taken(candidate))
+        .expect("unbounded suffix range always yields a free slug")
+}
+
+/// slug 只含 ASCII，可以按字节截断；截断后去掉末尾的连字符
+fn truncate_slug(slug: &str, max_len: usize) -> String {
+    slug[..slug.len().min(max_len)].trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_title() {
+        assert_eq!(slug_from_title("Getting Started: Install & Run!", 100), "getting-started-install-run");
+        assert_eq!(slug_from_title("   ", 100), "untitled");
+        assert_eq!(slug_from_title("A very long title indeed", 12), "a-very-long");
+    }
+
+    #[test]
+    fn test_chinese_title() {
+        assert_eq!(slug_from_title("快速开始", 100), "kuai-su-kai-shi");
+        assert_eq!(slug_from_title("API 使用指南", 100), "api-shi-yong-zhi-nan");
+    }
+
+    #[test]
+    fn test_collision_bumps_suffix() {
+        let taken = vec!["intro".to_string(), "intro-2".to_string(), "intro-guide".to_string()];
+
+        assert_eq!(unique_slug("guide", &taken, 100), "guide");
+        assert_eq!(unique_slug("intro", &taken, 100), "intro-3");
+
+        // 加后缀后仍不超过长度上限
+        let taken = vec!["abcdef".to_string()];
+        assert_eq!(unique_slug("abcdef", &taken, 6), "abcd-2");
+    }
+}
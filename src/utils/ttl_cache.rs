@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use tokio::sync::{OnceCell, RwLock};
+
+/// 缓存的命中情况，用于调试
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry<V> {
+    value: V,
+    expires_at: DateTime<Utc>,
+}
+
+type Slot<V> = Arc<OnceCell<Entry<V>>>;
+
+/// 带过期时间的内存缓存。同一个 key 同时只有一次加载在进行，
+/// 并发请求等待这次加载的结果，避免缓存失效时一起打到数据库
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    slots: RwLock<HashMap<K, Slot<V>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> TtlCache<K, V> {
+    /// ttl 不大于 0 表示不缓存，每次都调用加载函数
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            slots: RwLock::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// 命中未过期的缓存时直接返回，否则调用 load 加载并缓存。加载失败不缓存
+    pub async fn get_or_load<F, Fut, E>(&self, key: K, load: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        if self.ttl <= Duration::zero() {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return load().await;
+        }
+
+        let slot = self.slot(&key).await;
+        let loaded = AtomicBool::new(false);
+        let entry = slot
+            .get_or_try_init(|| async {
+                loaded.store(true, Ordering::Relaxed);
+                let value = load().await?;
+                Ok(Entry { value, expires_at: Utc::now() + self.ttl })
+            })
+            .await?;
+
+        let counter = if loaded.load(Ordering::Relaxed) { &self.misses } else { &self.hits };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(entry.value.clone())
+    }
+
+    /// 删除满足条件的缓存项，正在进行的加载结果不会再被缓存
+    pub async fn invalidate_matching(&self, predicate: impl Fn(&K) -> bool) {
+        self.slots.write().await.retain(|key, _| !predicate(key));
+    }
+
+    /// 清理过期的缓存项和加载失败后留下的空槽位
+    pub async fn cleanup(&self) {
+        let now = Utc::now();
+        self.slots.write().await.retain(|_, slot| match slot.get() {
+            Some(entry) => entry.expires_at > now,
+            // 仍有请求持有的空槽位说明加载还在进行
+            None => Arc::strong_count(slot) > 1,
+        });
+    }
+
+    pub async fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.slots.read().await.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 取 key 对应的槽位，已过期的槽位换成新的空槽位
+    async fn slot(&self, key: &K) -> Slot<V> {
+        let now = Utc::now();
+        let is_fresh = |slot: &Slot<V>| match slot.get() {
+            Some(entry) => entry.expires_at > now,
+            None => true,
+        };
+
+        if let Some(slot) = self.slots.read().await.get(key) {
+            if is_fresh(slot) {
+                return slot.clone();
+            }
+        }
+
+        let mut slots = self.slots.write().await;
+        let slot = slots.entry(key.clone()).or_default();
+        if !is_fresh(slot) {
+            *slot = Slot::default();
+        }
+        slot.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    type TreeCache = TtlCache<(String, u32), Arc<Vec<String>>>;
+
+    async fn fetch(cache: &TreeCache, loads: &AtomicUsize, id: &str, version: u32) -> Arc<Vec<String>> {
+        cache
+            .get_or_load((id.to_string(), version), || async {
+                loads.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                Ok::<_, ()>(Arc::new(vec![format!("{}@v{}", id, version)]))
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_second_fetch_hits_cache_and_republish_busts_it() {
+        let cache = TreeCache::new(Duration::minutes(5));
+        let loads = AtomicUsize::new(0);
+
+        fetch(&cache, &loads, "space_publication:guide", 1).await;
+        let cached = fetch(&cache, &loads, "space_publication:guide", 1).await;
+        assert_eq!(cached[0], "space_publication:guide@v1");
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats().await, CacheStats { entries: 1, hits: 1, misses: 1 });
+
+        // 重新发布：清除该发布的缓存，版本号加一
+        cache.invalidate_matching(|(id, _)| id == "space_publication:guide").await;
+        let republished = fetch(&cache, &loads, "space_publication:guide", 2).await;
+        assert_eq!(republished[0], "space_publication:guide@v2");
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.stats().await.entries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_misses_load_once() {
+        let cache = TreeCache::new(Duration::minutes(5));
+        let loads = AtomicUsize::new(0);
+
+        let fetches = tokio::join!(
+            fetch(&cache, &loads, "space_publication:popular", 3),
+            fetch(&cache, &loads, "space_publication:popular", 3),
+            fetch(&cache, &loads, "space_publication:popular", 3),
+            fetch(&cache, &loads, "space_publication:popular", 3),
+        );
+
+        for tree in [fetches.0, fetches.1, fetches.2, fetches.3] {
+            assert_eq!(tree[0], "space_publication:popular@v3");
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats().await, CacheStats { entries: 1, hits: 3, misses: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_zero_ttl_disables_caching() {
+        let cache = TreeCache::new(Duration::zero());
+        let loads = AtomicUsize::new(0);
+
+        fetch(&cache, &loads, "space_publication:guide", 1).await;
+        fetch(&cache, &loads, "space_publication:guide", 1).await;
+
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+        cache.cleanup().await;
+        assert_eq!(cache.stats().await.entries, 0);
+    }
+}